@@ -0,0 +1,103 @@
+//! Arithmetic backend of the arithmetic-heavy opcodes.
+
+use core::convert::TryInto;
+use crate::{U256, U512};
+
+/// Implementation of `ADDMOD`, `MULMOD` and `EXP`.
+///
+/// All operations follow the EVM semantics: a zero modulus yields zero, and
+/// exponentiation wraps around on overflow.
+pub trait Arithmetic {
+	/// `(op1 + op2) % op3`, computed without intermediate overflow.
+	fn addmod(op1: U256, op2: U256, op3: U256) -> U256;
+	/// `(op1 * op2) % op3`, computed without intermediate overflow.
+	fn mulmod(op1: U256, op2: U256, op3: U256) -> U256;
+	/// `op1 ** op2`, modulo `2 ** 256`.
+	fn exp(op1: U256, op2: U256) -> U256;
+}
+
+/// Arithmetic backend built on `U256` and `U512`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NativeArithmetic;
+
+impl Arithmetic for NativeArithmetic {
+	fn addmod(op1: U256, op2: U256, op3: U256) -> U256 {
+		let op1: U512 = op1.into();
+		let op2: U512 = op2.into();
+		let op3: U512 = op3.into();
+
+		if op3 == U512::zero() {
+			U256::zero()
+		} else {
+			let v = (op1 + op2) % op3;
+			v.try_into().expect("op3 is less than U256::max_value(), thus it never overflows; qed")
+		}
+	}
+
+	fn mulmod(op1: U256, op2: U256, op3: U256) -> U256 {
+		let op1: U512 = op1.into();
+		let op2: U512 = op2.into();
+		let op3: U512 = op3.into();
+
+		if op3 == U512::zero() {
+			U256::zero()
+		} else {
+			let v = (op1 * op2) % op3;
+			v.try_into().expect("op3 is less than U256::max_value(), thus it never overflows; qed")
+		}
+	}
+
+	fn exp(op1: U256, op2: U256) -> U256 {
+		let mut op1 = op1;
+		let mut op2 = op2;
+		let mut r: U256 = 1.into();
+
+		while op2 != 0.into() {
+			if op2 & 1.into() != 0.into() {
+				r = r.overflowing_mul(op1).0;
+			}
+			op2 >>= 1;
+			op1 = op1.overflowing_mul(op1).0;
+		}
+
+		r
+	}
+}
+
+/// Arithmetic backend delegating to the `ruint` crate.
+#[cfg(feature = "ruint")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RuintArithmetic;
+
+#[cfg(feature = "ruint")]
+impl RuintArithmetic {
+	const fn to_ruint(value: U256) -> ruint::aliases::U256 {
+		ruint::aliases::U256::from_limbs(value.0)
+	}
+
+	const fn from_ruint(value: ruint::aliases::U256) -> U256 {
+		U256(*value.as_limbs())
+	}
+}
+
+#[cfg(feature = "ruint")]
+impl Arithmetic for RuintArithmetic {
+	fn addmod(op1: U256, op2: U256, op3: U256) -> U256 {
+		Self::from_ruint(Self::to_ruint(op1).add_mod(Self::to_ruint(op2), Self::to_ruint(op3)))
+	}
+
+	fn mulmod(op1: U256, op2: U256, op3: U256) -> U256 {
+		Self::from_ruint(Self::to_ruint(op1).mul_mod(Self::to_ruint(op2), Self::to_ruint(op3)))
+	}
+
+	fn exp(op1: U256, op2: U256) -> U256 {
+		Self::from_ruint(Self::to_ruint(op1).wrapping_pow(Self::to_ruint(op2)))
+	}
+}
+
+/// Arithmetic backend used by the interpreter, selected by crate features.
+#[cfg(not(feature = "ruint"))]
+pub type ActiveArithmetic = NativeArithmetic;
+/// Arithmetic backend used by the interpreter, selected by crate features.
+#[cfg(feature = "ruint")]
+pub type ActiveArithmetic = RuintArithmetic;
@@ -0,0 +1,43 @@
+use core::fmt;
+
+/// Receiver of the interpreter debug output.
+///
+/// Opcodes are reported in execution order, each with its mnemonic and its
+/// operands formatted by the interpreter, so that the output only depends on
+/// the executed code.
+pub trait DebugSink: Send + Sync {
+	/// Report an executed opcode.
+	fn op(&self, name: &'static str, operands: fmt::Arguments<'_>);
+}
+
+/// Sink writing one line per opcode, the mnemonic followed by the operands.
+#[cfg(feature = "std")]
+#[derive(Debug, Default)]
+pub struct WriterSink<W>(std::sync::Mutex<W>);
+
+#[cfg(feature = "std")]
+impl<W> WriterSink<W> {
+	/// Write the debug output to `writer`.
+	#[must_use]
+	pub const fn new(writer: W) -> Self {
+		Self(std::sync::Mutex::new(writer))
+	}
+
+	/// Get the writer back.
+	pub fn into_inner(self) -> W {
+		self.0.into_inner().unwrap_or_else(std::sync::PoisonError::into_inner)
+	}
+}
+
+#[cfg(feature = "std")]
+impl<W: std::io::Write + Send> DebugSink for WriterSink<W> {
+	fn op(&self, name: &'static str, operands: fmt::Arguments<'_>) {
+		let mut writer = self.0.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+		// Debug output is best effort, a failing writer must not affect execution.
+		let _ = if operands.as_str() == Some("") {
+			writeln!(writer, "{}", name)
+		} else {
+			writeln!(writer, "{} {}", name, operands)
+		};
+	}
+}
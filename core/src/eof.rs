@@ -0,0 +1,161 @@
+use crate::Opcode;
+
+/// Prefix of EOF containers (EIP-3540).
+pub const EOF_MAGIC: [u8; 2] = [0xef, 0x00];
+/// Supported EOF version.
+pub const EOF_VERSION: u8 = 1;
+
+const SECTION_TERMINATOR: u8 = 0x00;
+const SECTION_CODE: u8 = 0x01;
+const SECTION_DATA: u8 = 0x02;
+
+/// Reason an EOF container is invalid. Not SCALE-encodable, the positions
+/// being `usize`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EofError {
+	/// The container does not start with `EOF_MAGIC`.
+	InvalidMagic,
+	/// The container version is not `EOF_VERSION`.
+	UnsupportedVersion(u8),
+	/// The section headers are truncated.
+	IncompleteHeader,
+	/// A section header has an unknown kind.
+	UnknownSection(u8),
+	/// A section is declared with a size of zero.
+	EmptySection(u8),
+	/// A section is declared twice, or the data section precedes the code
+	/// section.
+	InvalidSectionOrder(u8),
+	/// No code section is declared.
+	MissingCodeSection,
+	/// The container size does not match the declared sections.
+	InvalidContainerSize,
+	/// The code contains an undefined opcode (EIP-3670).
+	UndefinedOpcode {
+		/// Position in the code section.
+		position: usize,
+		/// Opcode.
+		opcode: u8,
+	},
+	/// The code ends in the middle of push data (EIP-3670).
+	TruncatedPush {
+		/// Position of the push in the code section.
+		position: usize,
+	},
+}
+
+/// Parsed EOF container, borrowing its sections from the container bytes.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Eof<'a> {
+	version: u8,
+	code: &'a [u8],
+	data: &'a [u8],
+}
+
+/// Whether the code is meant to be an EOF container, that is, starts with
+/// `EOF_MAGIC`.
+#[must_use]
+pub fn is_eof(code: &[u8]) -> bool {
+	code.starts_with(&EOF_MAGIC)
+}
+
+impl<'a> Eof<'a> {
+	/// Parse the header of a container and split its sections, without
+	/// validating the code.
+	pub fn parse(container: &'a [u8]) -> Result<Self, EofError> {
+		if !is_eof(container) {
+			return Err(EofError::InvalidMagic)
+		}
+		let version = *container.get(2).ok_or(EofError::IncompleteHeader)?;
+		if version != EOF_VERSION {
+			return Err(EofError::UnsupportedVersion(version))
+		}
+
+		let mut code_size = None;
+		let mut data_size = None;
+		let mut position = 3;
+		loop {
+			let kind = *container.get(position).ok_or(EofError::IncompleteHeader)?;
+			position += 1;
+			if kind == SECTION_TERMINATOR {
+				break
+			}
+
+			let size = container.get(position..position + 2).ok_or(EofError::IncompleteHeader)?;
+			let size = usize::from(u16::from_be_bytes([size[0], size[1]]));
+			position += 2;
+			if size == 0 {
+				return Err(EofError::EmptySection(kind))
+			}
+			match kind {
+				SECTION_CODE if code_size.is_none() && data_size.is_none() => code_size = Some(size),
+				SECTION_DATA if code_size.is_some() && data_size.is_none() => data_size = Some(size),
+				SECTION_CODE | SECTION_DATA => return Err(EofError::InvalidSectionOrder(kind)),
+				_ => return Err(EofError::UnknownSection(kind)),
+			}
+		}
+
+		let code_size = code_size.ok_or(EofError::MissingCodeSection)?;
+		let data_size = data_size.unwrap_or(0);
+		if container.len() != position + code_size + data_size {
+			return Err(EofError::InvalidContainerSize)
+		}
+
+		let (code, data) = container[position..].split_at(code_size);
+		Ok(Self { version, code, data })
+	}
+
+	/// Parse a container and validate its code.
+	pub fn validate(container: &'a [u8]) -> Result<Self, EofError> {
+		let eof = Self::parse(container)?;
+		validate_code(eof.code)?;
+		Ok(eof)
+	}
+
+	/// Container version.
+	#[must_use]
+	pub const fn version(&self) -> u8 {
+		self.version
+	}
+
+	/// Code section.
+	#[must_use]
+	pub const fn code(&self) -> &'a [u8] {
+		self.code
+	}
+
+	/// Data section, empty if the container has none.
+	#[must_use]
+	pub const fn data(&self) -> &'a [u8] {
+		self.data
+	}
+}
+
+/// Check that the code only contains defined opcodes and does not end in
+/// the middle of push data (EIP-3670).
+pub fn validate_code(code: &[u8]) -> Result<(), EofError> {
+	let mut position = 0;
+	while position < code.len() {
+		let opcode = Opcode(code[position]);
+		if !is_defined(opcode) {
+			return Err(EofError::UndefinedOpcode { position, opcode: opcode.as_u8() })
+		}
+
+		let push_size = opcode.as_u8().wrapping_sub(Opcode::PUSH1.as_u8());
+		let next = position + 1 + if push_size < 32 { usize::from(push_size) + 1 } else { 0 };
+		if next > code.len() {
+			return Err(EofError::TruncatedPush { position })
+		}
+		position = next;
+	}
+	Ok(())
+}
+
+/// Whether the opcode is assigned, including the designated `INVALID`.
+const fn is_defined(opcode: Opcode) -> bool {
+	matches!(opcode.as_u8(),
+		0x00..=0x0b | 0x10..=0x1d | 0x20 | 0x30..=0x3f | 0x40..=0x47 |
+		0x50..=0x5b | 0x60..=0xa4 | 0xf0..=0xf5 | 0xfa | 0xfd..=0xff
+	)
+}
@@ -55,6 +55,59 @@ impl ExitReason {
 	pub const fn is_fatal(&self) -> bool {
 		matches!(self, Self::Fatal(_))
 	}
+
+	/// Stable numeric code of the exit reason, for transports. The high byte
+	/// is the kind of exit (`0` succeed, `1` revert, `2` error, `3` fatal,
+	/// `4` step limit), and the low byte the reason within that kind, see
+	/// `ExitError::to_code`. Fatal errors wrapping an error have the low
+	/// byte `0x80` plus the code of the error.
+	///
+	/// Invalid opcode errors have the kind `5`, or `6` wrapped in a fatal
+	/// error, and the opcode as low byte.
+	#[must_use]
+	pub const fn to_code(&self) -> u16 {
+		match self {
+			Self::Succeed(ExitSucceed::Stopped) => 0x0000,
+			Self::Succeed(ExitSucceed::Returned) => 0x0001,
+			Self::Succeed(ExitSucceed::Suicided) => 0x0002,
+			Self::Revert(ExitRevert::Reverted) => 0x0100,
+			Self::Error(ExitError::InvalidOpcode(opcode)) => 0x0500 | opcode.as_u8() as u16,
+			Self::Error(e) => 0x0200 | e.to_code() as u16,
+			Self::Fatal(ExitFatal::NotSupported) => 0x0300,
+			Self::Fatal(ExitFatal::UnhandledInterrupt) => 0x0301,
+			Self::Fatal(ExitFatal::CallErrorAsFatal(ExitError::InvalidOpcode(opcode))) =>
+				0x0600 | opcode.as_u8() as u16,
+			Self::Fatal(ExitFatal::CallErrorAsFatal(e)) => 0x0380 | e.to_code() as u16,
+			Self::StepLimitReached => 0x0400,
+		}
+	}
+
+	/// Exit reason of a code returned by `to_code`, `None` if the code is
+	/// unknown.
+	#[must_use]
+	pub const fn from_code(code: u16) -> Option<Self> {
+		let [kind, reason] = code.to_be_bytes();
+		Some(match (kind, reason) {
+			(0x00, 0x00) => Self::Succeed(ExitSucceed::Stopped),
+			(0x00, 0x01) => Self::Succeed(ExitSucceed::Returned),
+			(0x00, 0x02) => Self::Succeed(ExitSucceed::Suicided),
+			(0x01, 0x00) => Self::Revert(ExitRevert::Reverted),
+			(0x02, _) => match ExitError::from_code(reason) {
+				Some(e) => Self::Error(e),
+				None => return None,
+			},
+			(0x03, 0x00) => Self::Fatal(ExitFatal::NotSupported),
+			(0x03, 0x01) => Self::Fatal(ExitFatal::UnhandledInterrupt),
+			(0x03, 0x80..=0xff) => match ExitError::from_code(reason - 0x80) {
+				Some(e) => Self::Fatal(ExitFatal::CallErrorAsFatal(e)),
+				None => return None,
+			},
+			(0x04, 0x00) => Self::StepLimitReached,
+			(0x05, opcode) => Self::Error(ExitError::InvalidOpcode(Opcode(opcode))),
+			(0x06, opcode) => Self::Fatal(ExitFatal::CallErrorAsFatal(ExitError::InvalidOpcode(Opcode(opcode)))),
+			_ => return None,
+		})
+	}
 }
 
 /// Exit succeed reason.
@@ -108,8 +161,9 @@ pub enum ExitError {
 	DesignatedInvalid,
 	/// Call stack is too deep (runtime).
 	CallTooDeep,
-	/// Create opcode encountered collision (runtime).
-	CreateCollision,
+	/// Create opcode encountered collision (runtime), failing the given
+	/// check of the account at the created address.
+	CreateCollision(CollisionKind),
 	/// Create init code exceeds limit (runtime).
 	CreateContractLimit,
 
@@ -125,6 +179,102 @@ pub enum ExitError {
 	PCUnderflow,
 	/// Attempt to create an empty account (runtime, unused).
 	CreateEmpty,
+	/// Init or deployed code is not a valid EOF container (EIP-3540).
+	InvalidCode,
+	/// Logs of the transaction exceed `StateLimits::max_log_size`.
+	LogLimit,
+	/// Self-destructed accounts of the transaction exceed
+	/// `StateLimits::max_deleted`.
+	SelfDestructLimit,
+	/// Encountered an opcode disabled by `Config::disabled_opcodes`.
+	OpcodeDisabled,
+	/// Encountered an opcode that is not defined in the hard fork.
+	InvalidOpcode(Opcode),
+	/// `RETURN` or `REVERT` output exceeds the return data limit of the
+	/// machine, see `Machine::set_return_data_limit`.
+	ReturnDataLimit,
+	/// Legacy init code deploys code starting with `0xEF` (EIP-3541).
+	CreateContractStartingWithEF,
+}
+
+impl ExitError {
+	/// Stable numeric code of the error, below `0x80`. Codes are never
+	/// reassigned, new errors get new codes.
+	///
+	/// `CreateCollision` has a code per kind of collision, 6 for code
+	/// collisions. `InvalidOpcode` has the code 17 whatever the opcode, so
+	/// `from_code` does not decode it. Exit reason codes keep the opcode, see
+	/// `ExitReason::to_code`.
+	#[must_use]
+	pub const fn to_code(&self) -> u8 {
+		match self {
+			Self::StackUnderflow => 0,
+			Self::StackOverflow => 1,
+			Self::InvalidJump => 2,
+			Self::InvalidRange => 3,
+			Self::DesignatedInvalid => 4,
+			Self::CallTooDeep => 5,
+			Self::CreateCollision(CollisionKind::Code) => 6,
+			Self::CreateContractLimit => 7,
+			Self::OutOfOffset => 8,
+			Self::OutOfGas => 9,
+			Self::OutOfFund => 10,
+			Self::PCUnderflow => 11,
+			Self::CreateEmpty => 12,
+			Self::InvalidCode => 13,
+			Self::LogLimit => 14,
+			Self::SelfDestructLimit => 15,
+			Self::OpcodeDisabled => 16,
+			Self::InvalidOpcode(_) => 17,
+			Self::ReturnDataLimit => 18,
+			Self::CreateCollision(CollisionKind::Nonce) => 19,
+			Self::CreateCollision(CollisionKind::Storage) => 20,
+			Self::CreateContractStartingWithEF => 21,
+		}
+	}
+
+	/// Error of a code returned by `to_code`, `None` if the code is unknown.
+	#[must_use]
+	pub const fn from_code(code: u8) -> Option<Self> {
+		Some(match code {
+			0 => Self::StackUnderflow,
+			1 => Self::StackOverflow,
+			2 => Self::InvalidJump,
+			3 => Self::InvalidRange,
+			4 => Self::DesignatedInvalid,
+			5 => Self::CallTooDeep,
+			6 => Self::CreateCollision(CollisionKind::Code),
+			7 => Self::CreateContractLimit,
+			8 => Self::OutOfOffset,
+			9 => Self::OutOfGas,
+			10 => Self::OutOfFund,
+			11 => Self::PCUnderflow,
+			12 => Self::CreateEmpty,
+			13 => Self::InvalidCode,
+			14 => Self::LogLimit,
+			15 => Self::SelfDestructLimit,
+			16 => Self::OpcodeDisabled,
+			18 => Self::ReturnDataLimit,
+			19 => Self::CreateCollision(CollisionKind::Nonce),
+			20 => Self::CreateCollision(CollisionKind::Storage),
+			21 => Self::CreateContractStartingWithEF,
+			_ => return None,
+		})
+	}
+}
+
+/// Check of the account at the address of a create that failed, see
+/// `ExitError::CreateCollision`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "with-codec", derive(codec::Encode, codec::Decode))]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CollisionKind {
+	/// The account has code.
+	Code,
+	/// The account has a non-zero nonce.
+	Nonce,
+	/// The account has non-empty storage (EIP-7610).
+	Storage,
 }
 
 impl From<ExitError> for ExitReason {
@@ -140,7 +290,8 @@ impl From<ExitError> for ExitReason {
 pub enum ExitFatal {
 	/// The operation is not supported.
 	NotSupported,
-	/// The trap (interrupt) is unhandled.
+	/// The trap (interrupt) is unhandled, including a step limit reached
+	/// where execution cannot be resumed.
 	UnhandledInterrupt,
 	/// The environment explicitly set call errors as fatal error.
 	CallErrorAsFatal(ExitError),
@@ -1,6 +1,18 @@
+/// Report an opcode and its operands to the debug sink of the machine.
+#[cfg(feature = "debug-trace")]
 macro_rules! trace_op {
-//	($($arg:tt)*) => (log::trace!(target: "evm", "OpCode {}", format_args!($($arg)*)));
-        ($($arg:tt)*) => ();
+	( $machine:expr, $name:expr ) => ( trace_op!($machine, $name, "") );
+	( $machine:expr, $name:expr, $($arg:tt)* ) => (
+		if let Some(sink) = &$machine.debug_sink {
+			sink.op($name, format_args!($($arg)*));
+		}
+	);
+}
+
+/// Without a debug sink, the operands are not even evaluated.
+#[cfg(not(feature = "debug-trace"))]
+macro_rules! trace_op {
+	($($arg:tt)*) => (());
 }
 
 macro_rules! try_or_fail {
@@ -12,32 +24,24 @@ macro_rules! try_or_fail {
 	}
 }
 
-macro_rules! pop {
-	( $machine:expr, $( $x:ident ),* ) => (
-		$(
-			let $x = match $machine.stack.pop() {
-				Ok(value) => value,
-				Err(e) => return Control::Exit(e.into()),
-			};
-		)*
-	);
-}
-
+/// Pop the concrete values of words, failing with `NotSupported` for the
+/// words without one.
 macro_rules! pop_u256 {
 	( $machine:expr, $( $x:ident ),* ) => (
 		$(
-			let $x = match $machine.stack.pop_u256() {
-				Ok(value) => value,
+			let $x = match $machine.stack.pop_word().map(|word| $crate::Word::to_u256(&word)) {
+				Ok(Some(value)) => value,
+				Ok(None) => return Control::Exit($crate::ExitFatal::NotSupported.into()),
 				Err(e) => return Control::Exit(e.into()),
 			};
 		)*
 	);
 }
 
-macro_rules! push {
+macro_rules! push_u256 {
 	( $machine:expr, $( $x:expr ),* ) => (
 		$(
-			match $machine.stack.push($x) {
+			match $machine.stack.push_word($crate::Word::from_u256($x)) {
 				Ok(()) => (),
 				Err(e) => return Control::Exit(e.into()),
 			}
@@ -45,24 +49,34 @@ macro_rules! push {
 	)
 }
 
-macro_rules! push_u256 {
+/// Pop values once the stack height was validated with `Stack::check`.
+macro_rules! pop_u256_unchecked {
+	( $machine:expr, $( $x:ident ),* ) => (
+		$(
+			// SAFETY: the stack height was checked by the caller.
+			let $x = unsafe { $machine.stack.pop_u256_unchecked() };
+		)*
+	);
+}
+
+/// Push values once the stack height was validated with `Stack::check`.
+macro_rules! push_u256_unchecked {
 	( $machine:expr, $( $x:expr ),* ) => (
 		$(
-			match $machine.stack.push_u256($x) {
-				Ok(()) => (),
-				Err(e) => return Control::Exit(e.into()),
-			}
+			// SAFETY: the stack height was checked by the caller.
+			unsafe { $machine.stack.push_u256_unchecked($x) }
 		)*
 	)
 }
 
 macro_rules! op1_u256_fn {
-	( $machine:expr, $op:path ) => (
+	( $machine:expr, $op:path , $name:expr ) => (
 		{
-			pop_u256!($machine, op1);
+			try_or_fail!($machine.stack.check(1, 1));
+			pop_u256_unchecked!($machine, op1);
 			let ret = $op(op1);
-			push_u256!($machine, ret);
-			trace_op!("{} {}: {}", stringify!($op), op1, ret);
+			push_u256_unchecked!($machine, ret);
+			trace_op!($machine, $name, "{}: {}", op1, ret);
 
 			Control::Continue(1)
 		}
@@ -70,16 +84,17 @@ macro_rules! op1_u256_fn {
 }
 
 macro_rules! op2_u256_bool_ref {
-	( $machine:expr, $op:ident ) => (
+	( $machine:expr, $op:ident , $name:expr ) => (
 		{
-			pop_u256!($machine, op1, op2);
+			try_or_fail!($machine.stack.check(2, 1));
+			pop_u256_unchecked!($machine, op1, op2);
 			let ret = op1.$op(&op2);
-			push_u256!($machine, if ret {
+			push_u256_unchecked!($machine, if ret {
 				U256::one()
 			} else {
 				U256::zero()
 			});
-			trace_op!("{} {}, {}: {}", stringify!($op), op1, op2, ret);
+			trace_op!($machine, $name, "{}, {}: {}", op1, op2, ret);
 
 			Control::Continue(1)
 		}
@@ -87,12 +102,13 @@ macro_rules! op2_u256_bool_ref {
 }
 
 macro_rules! op2_u256 {
-	( $machine:expr, $op:ident ) => (
+	( $machine:expr, $op:ident , $name:expr ) => (
 		{
-			pop_u256!($machine, op1, op2);
+			try_or_fail!($machine.stack.check(2, 1));
+			pop_u256_unchecked!($machine, op1, op2);
 			let ret = op1.$op(op2);
-			push_u256!($machine, ret);
-			trace_op!("{} {}, {}: {}", stringify!($op), op1, op2, ret);
+			push_u256_unchecked!($machine, ret);
+			trace_op!($machine, $name, "{}, {}: {}", op1, op2, ret);
 
 			Control::Continue(1)
 		}
@@ -100,12 +116,13 @@ macro_rules! op2_u256 {
 }
 
 macro_rules! op2_u256_tuple {
-	( $machine:expr, $op:ident ) => (
+	( $machine:expr, $op:ident , $name:expr ) => (
 		{
-			pop_u256!($machine, op1, op2);
+			try_or_fail!($machine.stack.check(2, 1));
+			pop_u256_unchecked!($machine, op1, op2);
 			let (ret, ..) = op1.$op(op2);
-			push_u256!($machine, ret);
-			trace_op!("{} {}, {}: {}", stringify!($op), op1, op2, ret);
+			push_u256_unchecked!($machine, ret);
+			trace_op!($machine, $name, "{}, {}: {}", op1, op2, ret);
 
 			Control::Continue(1)
 		}
@@ -113,12 +130,13 @@ macro_rules! op2_u256_tuple {
 }
 
 macro_rules! op2_u256_fn {
-	( $machine:expr, $op:path ) => (
+	( $machine:expr, $op:path , $name:expr ) => (
 		{
-			pop_u256!($machine, op1, op2);
+			try_or_fail!($machine.stack.check(2, 1));
+			pop_u256_unchecked!($machine, op1, op2);
 			let ret = $op(op1, op2);
-			push_u256!($machine, ret);
-			trace_op!("{} {}, {}: {}", stringify!($op), op1, op2, ret);
+			push_u256_unchecked!($machine, ret);
+			trace_op!($machine, $name, "{}, {}: {}", op1, op2, ret);
 
 			Control::Continue(1)
 		}
@@ -126,12 +144,13 @@ macro_rules! op2_u256_fn {
 }
 
 macro_rules! op3_u256_fn {
-	( $machine:expr, $op:path ) => (
+	( $machine:expr, $op:path , $name:expr ) => (
 		{
-			pop_u256!($machine, op1, op2, op3);
+			try_or_fail!($machine.stack.check(3, 1));
+			pop_u256_unchecked!($machine, op1, op2, op3);
 			let ret = $op(op1, op2, op3);
-			push_u256!($machine, ret);
-			trace_op!("{} {}, {}, {}: {}", stringify!($op), op1, op2, op3, ret);
+			push_u256_unchecked!($machine, ret);
+			trace_op!($machine, $name, "{}, {}, {}: {}", op1, op2, op3, ret);
 
 			Control::Continue(1)
 		}
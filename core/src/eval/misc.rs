@@ -1,17 +1,17 @@
-use core::cmp::min;
+use core::cmp::{min, max};
 use super::Control;
-use crate::{Machine, ExitError, ExitSucceed, ExitFatal, ExitRevert, H256, U256};
+use crate::{Machine, ExitError, ExitSucceed, ExitFatal, ExitRevert, Word, U256};
 
-pub fn codesize(state: &mut Machine) -> Control {
+pub fn codesize<W: Word>(state: &mut Machine<W>) -> Control {
 	let size = U256::from(state.code.len());
-	trace_op!("CodeSize: {}", size);
+	trace_op!(state, "CODESIZE", "{}", size);
 	push_u256!(state, size);
 	Control::Continue(1)
 }
 
-pub fn codecopy(state: &mut Machine) -> Control {
+pub fn codecopy<W: Word>(state: &mut Machine<W>) -> Control {
 	pop_u256!(state, memory_offset, code_offset, len);
-	trace_op!("CodeCopy: {}", len);
+	trace_op!(state, "CODECOPY", "{}, {}, {}", memory_offset, code_offset, len);
 
 	let memory_offset = as_usize_or_fail!(memory_offset);
 	let code_offset = as_usize_or_fail!(code_offset);
@@ -24,9 +24,9 @@ pub fn codecopy(state: &mut Machine) -> Control {
 	}
 }
 
-pub fn calldataload(state: &mut Machine) -> Control {
+pub fn calldataload<W: Word>(state: &mut Machine<W>) -> Control {
 	pop_u256!(state, index);
-	trace_op!("CallDataLoad: {}", index);
+	trace_op!(state, "CALLDATALOAD", "{}", index);
 
 	let index = as_usize_or_fail!(index);
 	let len = if index > state.data.len() { 0 } else { min(32, state.data.len() - index) };
@@ -34,20 +34,20 @@ pub fn calldataload(state: &mut Machine) -> Control {
 	let mut load = [0_u8; 32];
 	load[0..len].copy_from_slice(&state.data[index..index + len]);
 
-	push!(state, H256::from(load));
+	push_u256!(state, U256::from_big_endian_fast(&load));
 	Control::Continue(1)
 }
 
-pub fn calldatasize(state: &mut Machine) -> Control {
+pub fn calldatasize<W: Word>(state: &mut Machine<W>) -> Control {
 	let len = U256::from(state.data.len());
-	trace_op!("CallDataSize: {}", len);
+	trace_op!(state, "CALLDATASIZE", "{}", len);
 	push_u256!(state, len);
 	Control::Continue(1)
 }
 
-pub fn calldatacopy(state: &mut Machine) -> Control {
+pub fn calldatacopy<W: Word>(state: &mut Machine<W>) -> Control {
 	pop_u256!(state, memory_offset, data_offset, len);
-	trace_op!("CallDataCopy: {}", len);
+	trace_op!(state, "CALLDATACOPY", "{}, {}, {}", memory_offset, data_offset, len);
 
 	let memory_offset = as_usize_or_fail!(memory_offset);
 	let data_offset = as_usize_or_fail!(data_offset);
@@ -65,36 +65,36 @@ pub fn calldatacopy(state: &mut Machine) -> Control {
 }
 
 pub fn pop(state: &mut Machine) -> Control {
-	pop_u256!(state, _val);
-	trace_op!("Pop  [@{}]: {}", state.stack.len(), val);
+	pop_u256!(state, val);
+	trace_op!(state, "POP", "[@{}]: {}", state.stack.len(), val);
+	// Only traced with `debug-trace`.
+	let _ = &val;
 	Control::Continue(1)
 }
 
-pub fn mload(state: &mut Machine) -> Control {
+pub fn mload<W: Word>(state: &mut Machine<W>) -> Control {
 	pop_u256!(state, index);
-	trace_op!("MLoad: {}", index);
+	trace_op!(state, "MLOAD", "{}", index);
 	let index = as_usize_or_fail!(index);
 	try_or_fail!(state.memory.resize_offset(index, 32));
-	let value = H256::from_slice(&state.memory.get(index, 32)[..]);
-	push!(state, value);
+	push_u256!(state, state.memory.load(index));
 	Control::Continue(1)
 }
 
-pub fn mstore(state: &mut Machine) -> Control {
-	pop_u256!(state, index);
-	pop!(state, value);
-	trace_op!("MStore: {}, {}", index, value);
+pub fn mstore<W: Word>(state: &mut Machine<W>) -> Control {
+	pop_u256!(state, index, value);
+	trace_op!(state, "MSTORE", "{}, {}", index, value);
 	let index = as_usize_or_fail!(index);
 	try_or_fail!(state.memory.resize_offset(index, 32));
-	match state.memory.set(index, &value[..], Some(32)) {
+	match state.memory.store(index, &value) {
 		Ok(()) => Control::Continue(1),
 		Err(e) => Control::Exit(e.into()),
 	}
 }
 
-pub fn mstore8(state: &mut Machine) -> Control {
+pub fn mstore8<W: Word>(state: &mut Machine<W>) -> Control {
 	pop_u256!(state, index, value);
-	trace_op!("MStore8: {}, {}", index, value);
+	trace_op!(state, "MSTORE8", "{}, {}", index, value);
 	let index = as_usize_or_fail!(index);
 	try_or_fail!(state.memory.resize_offset(index, 1));
 	#[allow(clippy::cast_possible_truncation)]
@@ -105,10 +105,10 @@ pub fn mstore8(state: &mut Machine) -> Control {
 	}
 }
 
-pub fn jump(state: &mut Machine) -> Control {
+pub fn jump<W: Word>(state: &mut Machine<W>) -> Control {
 	pop_u256!(state, dest);
 	let dest = as_usize_or_fail!(dest, ExitError::InvalidJump);
-	trace_op!("Jump: {}", dest);
+	trace_op!(state, "JUMP", "{}", dest);
 
 	if state.valids.is_valid(dest) {
 		Control::Jump(dest)
@@ -117,15 +117,15 @@ pub fn jump(state: &mut Machine) -> Control {
 	}
 }
 
-pub fn jumpi(state: &mut Machine) -> Control {
+pub fn jumpi<W: Word>(state: &mut Machine<W>) -> Control {
 	pop_u256!(state, dest, value);
 	let dest = as_usize_or_fail!(dest, ExitError::InvalidJump);
 
 	if value == U256::zero() {
-		trace_op!("JumpI: skipped");
+		trace_op!(state, "JUMPI", "{}: skipped", dest);
 		Control::Continue(1)
 	} else {
-		trace_op!("JumpI: {}", dest);
+		trace_op!(state, "JUMPI", "{}", dest);
 		if state.valids.is_valid(dest) {
 			Control::Jump(dest)
 		} else {
@@ -134,61 +134,86 @@ pub fn jumpi(state: &mut Machine) -> Control {
 	}
 }
 
-pub fn pc(state: &mut Machine, position: usize) -> Control {
-	trace_op!("PC");
+pub fn pc<W: Word>(state: &mut Machine<W>, position: usize) -> Control {
+	trace_op!(state, "PC", "{}", position);
 	push_u256!(state, U256::from(position));
 	Control::Continue(1)
 }
 
-pub fn msize(state: &mut Machine) -> Control {
-	trace_op!("MSize");
+pub fn msize<W: Word>(state: &mut Machine<W>) -> Control {
+	trace_op!(state, "MSIZE", "{}", state.memory.effective_len());
 	push_u256!(state, U256::from(state.memory.effective_len()));
 	Control::Continue(1)
 }
 
-pub fn push(state: &mut Machine, n: usize, position: usize) -> Control {
+pub fn mcopy<W: Word>(state: &mut Machine<W>) -> Control {
+	pop_u256!(state, dst_offset, src_offset, len);
+	trace_op!(state, "MCOPY", "{}, {}, {}", dst_offset, src_offset, len);
+
+	let dst_offset = as_usize_or_fail!(dst_offset);
+	let src_offset = as_usize_or_fail!(src_offset);
+	let len = as_usize_or_fail!(len);
+
+	if len == 0 {
+		return Control::Continue(1)
+	}
+
+	try_or_fail!(state.memory.resize_offset(max(dst_offset, src_offset), len));
+	match state.memory.copy_within(dst_offset, src_offset, len) {
+		Ok(()) => Control::Continue(1),
+		Err(e) => Control::Exit(e.into()),
+	}
+}
+
+pub fn push<W: Word>(state: &mut Machine<W>, n: usize, position: usize) -> Control {
 	let end = min(position + 1 + n, state.code.len());
 	let val = U256::from_big_endian_fast(&state.code[(position + 1)..end]);
 
 	push_u256!(state, val);
-	trace_op!("Push [@{}]: {}", state.stack.len() - 1, val);
+	trace_op!(state, "PUSH", "{} [@{}]: {}", n, state.stack.len() - 1, val);
 	Control::Continue(1 + n)
 }
 
-pub fn dup(state: &mut Machine, n: usize) -> Control {
+pub fn dup<W: Word>(state: &mut Machine<W>, n: usize) -> Control {
 	if let Err(e) = state.stack.dup(n - 1) {
 		return Control::Exit(e.into());
 	};
 
-	trace_op!("Dup{} [@{}]", n, state.stack.len());
+	trace_op!(state, "DUP", "{} [@{}]", n, state.stack.len());
 
 	Control::Continue(1)
 }
 
-pub fn swap(state: &mut Machine, n: usize) -> Control {
+pub fn swap<W: Word>(state: &mut Machine<W>, n: usize) -> Control {
 	if let Err(e) = state.stack.swap(n) {
 		return Control::Exit(e.into());
 	};
 
-	trace_op!("Swap [@0:@{}]", n);
+	trace_op!(state, "SWAP", "[@0:@{}]", n);
 	Control::Continue(1)
 }
 
-pub fn ret(state: &mut Machine) -> Control {
-	trace_op!("Return");
+pub fn ret<W: Word>(state: &mut Machine<W>) -> Control {
 	pop_u256!(state, start, len);
+	trace_op!(state, "RETURN", "{}, {}", start, len);
 	let start = as_usize_or_fail!(start);
 	let len = as_usize_or_fail!(len);
+	if len > state.return_data_limit {
+		return Control::Exit(ExitError::ReturnDataLimit.into())
+	}
 	try_or_fail!(state.memory.resize_offset(start, len));
 	state.return_range = start..(start + len);
 	Control::Exit(ExitSucceed::Returned.into())
 }
 
-pub fn revert(state: &mut Machine) -> Control {
-	trace_op!("Revert");
+pub fn revert<W: Word>(state: &mut Machine<W>) -> Control {
 	pop_u256!(state, start, len);
+	trace_op!(state, "REVERT", "{}, {}", start, len);
 	let start = as_usize_or_fail!(start);
 	let len = as_usize_or_fail!(len);
+	if len > state.return_data_limit {
+		return Control::Exit(ExitError::ReturnDataLimit.into())
+	}
 	try_or_fail!(state.memory.resize_offset(start, len));
 	state.return_range = start..(start + len);
 	Control::Exit(ExitRevert::Reverted.into())
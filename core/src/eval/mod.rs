@@ -5,7 +5,11 @@ mod bitwise;
 mod misc;
 
 use core::ops::{BitAnd, BitOr, BitXor};
-use crate::{ExitReason, ExitSucceed, ExitError, Machine, Opcode, U256};
+use alloc::vec::Vec;
+use crate::{
+	ActiveArithmetic, Arithmetic, ExitReason, ExitSucceed, ExitError, ExitFatal, Machine, Memory, Opcode,
+	Stack, Superinstruction, Word, U256,
+};
 
 #[derive(Clone, Eq, PartialEq, Debug)]
 pub enum Control {
@@ -20,103 +24,103 @@ fn eval_stop(_state: &mut Machine, _opcode: Opcode, _position: usize) -> Control
 }
 
 fn eval_add(state: &mut Machine, _opcode: Opcode, _position: usize) -> Control {
-	op2_u256_tuple!(state, overflowing_add)
+	op2_u256_tuple!(state, overflowing_add, "ADD")
 }
 
 fn eval_mul(state: &mut Machine, _opcode: Opcode, _position: usize) -> Control {
-	op2_u256_tuple!(state, overflowing_mul)
+	op2_u256_tuple!(state, overflowing_mul, "MUL")
 }
 
 fn eval_sub(state: &mut Machine, _opcode: Opcode, _position: usize) -> Control {
-	op2_u256_tuple!(state, overflowing_sub)
+	op2_u256_tuple!(state, overflowing_sub, "SUB")
 }
 
 fn eval_div(state: &mut Machine, _opcode: Opcode, _position: usize) -> Control {
-	op2_u256_fn!(state, self::arithmetic::div)
+	op2_u256_fn!(state, self::arithmetic::div, "DIV")
 }
 
 fn eval_sdiv(state: &mut Machine, _opcode: Opcode, _position: usize) -> Control {
-	op2_u256_fn!(state, self::arithmetic::sdiv)
+	op2_u256_fn!(state, self::arithmetic::sdiv, "SDIV")
 }
 
 fn eval_mod(state: &mut Machine, _opcode: Opcode, _position: usize) -> Control {
-	op2_u256_fn!(state, self::arithmetic::rem)
+	op2_u256_fn!(state, self::arithmetic::rem, "MOD")
 }
 
 fn eval_smod(state: &mut Machine, _opcode: Opcode, _position: usize) -> Control {
-	op2_u256_fn!(state, self::arithmetic::srem)
+	op2_u256_fn!(state, self::arithmetic::srem, "SMOD")
 }
 
 fn eval_addmod(state: &mut Machine, _opcode: Opcode, _position: usize) -> Control {
-	op3_u256_fn!(state, self::arithmetic::addmod)
+	op3_u256_fn!(state, ActiveArithmetic::addmod, "ADDMOD")
 }
 
 fn eval_mulmod(state: &mut Machine, _opcode: Opcode, _position: usize) -> Control {
-	op3_u256_fn!(state, self::arithmetic::mulmod)
+	op3_u256_fn!(state, ActiveArithmetic::mulmod, "MULMOD")
 }
 
 fn eval_exp(state: &mut Machine, _opcode: Opcode, _position: usize) -> Control {
-	op2_u256_fn!(state, self::arithmetic::exp)
+	op2_u256_fn!(state, ActiveArithmetic::exp, "EXP")
 }
 
 fn eval_signextend(state: &mut Machine, _opcode: Opcode, _position: usize) -> Control {
-	op2_u256_fn!(state, self::arithmetic::signextend)
+	op2_u256_fn!(state, self::arithmetic::signextend, "SIGNEXTEND")
 }
 
 fn eval_lt(state: &mut Machine, _opcode: Opcode, _position: usize) -> Control {
-	op2_u256_bool_ref!(state, lt)
+	op2_u256_bool_ref!(state, lt, "LT")
 }
 
 fn eval_gt(state: &mut Machine, _opcode: Opcode, _position: usize) -> Control {
-	op2_u256_bool_ref!(state, gt)
+	op2_u256_bool_ref!(state, gt, "GT")
 }
 
 fn eval_slt(state: &mut Machine, _opcode: Opcode, _position: usize) -> Control {
-	op2_u256_fn!(state, self::bitwise::slt)
+	op2_u256_fn!(state, self::bitwise::slt, "SLT")
 }
 
 fn eval_sgt(state: &mut Machine, _opcode: Opcode, _position: usize) -> Control {
-	op2_u256_fn!(state, self::bitwise::sgt)
+	op2_u256_fn!(state, self::bitwise::sgt, "SGT")
 }
 
 fn eval_eq(state: &mut Machine, _opcode: Opcode, _position: usize) -> Control {
-	op2_u256_bool_ref!(state, eq)
+	op2_u256_bool_ref!(state, eq, "EQ")
 }
 
 fn eval_iszero(state: &mut Machine, _opcode: Opcode, _position: usize) -> Control {
-	op1_u256_fn!(state, self::bitwise::iszero)
+	op1_u256_fn!(state, self::bitwise::iszero, "ISZERO")
 }
 
 fn eval_and(state: &mut Machine, _opcode: Opcode, _position: usize) -> Control {
-	op2_u256!(state, bitand)
+	op2_u256!(state, bitand, "AND")
 }
 
 fn eval_or(state: &mut Machine, _opcode: Opcode, _position: usize) -> Control {
-	op2_u256!(state, bitor)
+	op2_u256!(state, bitor, "OR")
 }
 
 fn eval_xor(state: &mut Machine, _opcode: Opcode, _position: usize) -> Control {
-	op2_u256!(state, bitxor)
+	op2_u256!(state, bitxor, "XOR")
 }
 
 fn eval_not(state: &mut Machine, _opcode: Opcode, _position: usize) -> Control {
-	op1_u256_fn!(state, self::bitwise::not)
+	op1_u256_fn!(state, self::bitwise::not, "NOT")
 }
 
 fn eval_byte(state: &mut Machine, _opcode: Opcode, _position: usize) -> Control {
-	op2_u256_fn!(state, self::bitwise::byte)
+	op2_u256_fn!(state, self::bitwise::byte, "BYTE")
 }
 
 fn eval_shl(state: &mut Machine, _opcode: Opcode, _position: usize) -> Control {
-	op2_u256_fn!(state, self::bitwise::shl)
+	op2_u256_fn!(state, self::bitwise::shl, "SHL")
 }
 
 fn eval_shr(state: &mut Machine, _opcode: Opcode, _position: usize) -> Control {
-	op2_u256_fn!(state, self::bitwise::shr)
+	op2_u256_fn!(state, self::bitwise::shr, "SHR")
 }
 
 fn eval_sar(state: &mut Machine, _opcode: Opcode, _position: usize) -> Control {
-	op2_u256_fn!(state, self::bitwise::sar)
+	op2_u256_fn!(state, self::bitwise::sar, "SAR")
 }
 
 fn eval_codesize(state: &mut Machine, _opcode: Opcode, _position: usize) -> Control {
@@ -171,6 +175,10 @@ fn eval_msize(state: &mut Machine, _opcode: Opcode, _position: usize) -> Control
 	self::misc::msize(state)
 }
 
+fn eval_mcopy(state: &mut Machine, _opcode: Opcode, _position: usize) -> Control {
+	self::misc::mcopy(state)
+}
+
 fn eval_jumpdest(_state: &mut Machine, _opcode: Opcode, _position: usize) -> Control {
 	Control::Continue(1)
 }
@@ -449,6 +457,11 @@ fn eval_external(_state: &mut Machine, opcode: Opcode, _position: usize) -> Cont
 
 #[allow(clippy::too_many_lines)]
 pub fn eval(state: &mut Machine, opcode: Opcode, position: usize) -> Control {
+	instruction(opcode)(state, opcode, position)
+}
+
+/// Evaluation function of an opcode.
+pub fn instruction(opcode: Opcode) -> fn(state: &mut Machine, opcode: Opcode, position: usize) -> Control {
 	static TABLE: [fn(state: &mut Machine, opcode: Opcode, position: usize) -> Control; 256] = {
 		let mut table = [eval_external as _; 256];
 
@@ -492,6 +505,7 @@ pub fn eval(state: &mut Machine, opcode: Opcode, position: usize) -> Control {
 		table[Opcode::PC.as_usize()] = eval_pc as _;
 		table[Opcode::MSIZE.as_usize()] = eval_msize as _;
 		table[Opcode::JUMPDEST.as_usize()] = eval_jumpdest as _;
+		table[Opcode::MCOPY.as_usize()] = eval_mcopy as _;
 
 		table[Opcode::PUSH1.as_usize()] = eval_push1 as _;
 		table[Opcode::PUSH2.as_usize()] = eval_push2 as _;
@@ -567,5 +581,170 @@ pub fn eval(state: &mut Machine, opcode: Opcode, position: usize) -> Control {
 		table
 	};
 
-	TABLE[opcode.as_usize()](state, opcode, position)
+	TABLE[opcode.as_usize()]
+}
+
+/// Number of operands of the opcodes evaluated by `Word::apply`, `None` for
+/// other opcodes.
+const fn arity(opcode: Opcode) -> Option<usize> {
+	match opcode {
+		Opcode::ISZERO | Opcode::NOT => Some(1),
+		Opcode::ADDMOD | Opcode::MULMOD => Some(3),
+		Opcode::ADD | Opcode::MUL | Opcode::SUB | Opcode::DIV | Opcode::SDIV | Opcode::MOD |
+		Opcode::SMOD | Opcode::EXP | Opcode::SIGNEXTEND | Opcode::LT | Opcode::GT | Opcode::SLT |
+		Opcode::SGT | Opcode::EQ | Opcode::AND | Opcode::OR | Opcode::XOR | Opcode::BYTE |
+		Opcode::SHL | Opcode::SHR | Opcode::SAR => Some(2),
+		_ => None,
+	}
+}
+
+/// Result of an arithmetic, comparison or bitwise opcode on concrete values,
+/// the top of the stack first. `None` for other opcodes, or if the number of
+/// values is not the one of the opcode.
+#[must_use]
+pub fn apply(opcode: Opcode, args: &[U256]) -> Option<U256> {
+	let flag = |value| if value { U256::one() } else { U256::zero() };
+
+	Some(match (opcode, args) {
+		(Opcode::ISZERO, &[a]) => self::bitwise::iszero(a),
+		(Opcode::NOT, &[a]) => self::bitwise::not(a),
+		(Opcode::ADD, &[a, b]) => a.overflowing_add(b).0,
+		(Opcode::MUL, &[a, b]) => a.overflowing_mul(b).0,
+		(Opcode::SUB, &[a, b]) => a.overflowing_sub(b).0,
+		(Opcode::DIV, &[a, b]) => self::arithmetic::div(a, b),
+		(Opcode::SDIV, &[a, b]) => self::arithmetic::sdiv(a, b),
+		(Opcode::MOD, &[a, b]) => self::arithmetic::rem(a, b),
+		(Opcode::SMOD, &[a, b]) => self::arithmetic::srem(a, b),
+		(Opcode::EXP, &[a, b]) => ActiveArithmetic::exp(a, b),
+		(Opcode::SIGNEXTEND, &[a, b]) => self::arithmetic::signextend(a, b),
+		(Opcode::LT, &[a, b]) => flag(a < b),
+		(Opcode::GT, &[a, b]) => flag(a > b),
+		(Opcode::SLT, &[a, b]) => self::bitwise::slt(a, b),
+		(Opcode::SGT, &[a, b]) => self::bitwise::sgt(a, b),
+		(Opcode::EQ, &[a, b]) => flag(a == b),
+		(Opcode::AND, &[a, b]) => a & b,
+		(Opcode::OR, &[a, b]) => a | b,
+		(Opcode::XOR, &[a, b]) => a ^ b,
+		(Opcode::BYTE, &[a, b]) => self::bitwise::byte(a, b),
+		(Opcode::SHL, &[a, b]) => self::bitwise::shl(a, b),
+		(Opcode::SHR, &[a, b]) => self::bitwise::shr(a, b),
+		(Opcode::SAR, &[a, b]) => self::bitwise::sar(a, b),
+		(Opcode::ADDMOD, &[a, b, c]) => ActiveArithmetic::addmod(a, b, c),
+		(Opcode::MULMOD, &[a, b, c]) => ActiveArithmetic::mulmod(a, b, c),
+		_ => return None,
+	})
+}
+
+/// Evaluate `opcode` on a machine of any word. The arithmetic goes through
+/// `Word::apply`, the other opcodes need the concrete values of their
+/// operands, failing with `NotSupported` without them.
+pub fn eval_word<W: Word>(state: &mut Machine<W>, opcode: Opcode, position: usize) -> Control {
+	if let Some(arity) = arity(opcode) {
+		try_or_fail!(state.stack.check(arity, 1));
+		let args = (0..arity).filter_map(|_| state.stack.pop_word().ok()).collect::<Vec<_>>();
+		return match W::apply(opcode, &args) {
+			Some(ret) => {
+				try_or_fail!(state.stack.push_word(ret));
+				Control::Continue(1)
+			},
+			None => Control::Exit(ExitFatal::NotSupported.into()),
+		}
+	}
+
+	match opcode {
+		Opcode::STOP => Control::Exit(ExitSucceed::Stopped.into()),
+		Opcode::CODESIZE => self::misc::codesize(state),
+		Opcode::CODECOPY => self::misc::codecopy(state),
+		Opcode::CALLDATALOAD => self::misc::calldataload(state),
+		Opcode::CALLDATASIZE => self::misc::calldatasize(state),
+		Opcode::CALLDATACOPY => self::misc::calldatacopy(state),
+		Opcode::POP => {
+			try_or_fail!(state.stack.pop_word());
+			Control::Continue(1)
+		},
+		Opcode::MLOAD => self::misc::mload(state),
+		Opcode::MSTORE => self::misc::mstore(state),
+		Opcode::MSTORE8 => self::misc::mstore8(state),
+		Opcode::JUMP => self::misc::jump(state),
+		Opcode::JUMPI => self::misc::jumpi(state),
+		Opcode::PC => self::misc::pc(state, position),
+		Opcode::MSIZE => self::misc::msize(state),
+		Opcode::JUMPDEST => Control::Continue(1),
+		Opcode::MCOPY => self::misc::mcopy(state),
+		Opcode(0x60..=0x7f) => self::misc::push(state, usize::from(opcode.0 - 0x5f), position),
+		Opcode(0x80..=0x8f) => self::misc::dup(state, usize::from(opcode.0 - 0x7f)),
+		Opcode(0x90..=0x9f) => self::misc::swap(state, usize::from(opcode.0 - 0x8f)),
+		Opcode::RETURN => self::misc::ret(state),
+		Opcode::REVERT => self::misc::revert(state),
+		Opcode::INVALID => Control::Exit(ExitError::DesignatedInvalid.into()),
+		_ => Control::Trap(opcode),
+	}
+}
+
+/// Evaluate a superinstruction, validating each of its opcodes in turn as
+/// if they were dispatched one by one. Returns the number of opcodes that
+/// completed, along with the control of the sequence.
+pub fn eval_fused<F>(
+	state: &mut Machine,
+	instruction: Superinstruction,
+	position: usize,
+	pre_validate: &mut F,
+) -> (u64, Control) where
+	F: FnMut(Opcode, usize, &Stack, &Memory) -> Result<(), ExitError>,
+{
+	macro_rules! validate {
+		( $steps:expr, $offset:expr ) => {
+			let offset = $offset;
+			if let Err(e) = pre_validate(Opcode(state.code[position + offset]), position + offset, &state.stack, &state.memory) {
+				return ($steps, Control::Exit(e.into()))
+			}
+		}
+	}
+
+	macro_rules! check {
+		( $steps:expr, $e:expr ) => {
+			if let Err(e) = $e {
+				return ($steps, Control::Exit(e.into()))
+			}
+		}
+	}
+
+	/// Control of the sequence from the control of its last opcode.
+	const fn last(steps: u64, offset: usize, control: Control) -> (u64, Control) {
+		match control {
+			Control::Continue(p) => (steps + 1, Control::Continue(offset + p)),
+			Control::Jump(p) => (steps + 1, Control::Jump(p)),
+			control => (steps, control),
+		}
+	}
+
+	match instruction {
+		Superinstruction::PushJump { value, jump } => {
+			validate!(0, 0);
+			check!(0, state.stack.push_u256(value));
+			validate!(1, jump);
+			last(1, jump, self::misc::jump(state))
+		},
+		Superinstruction::PushJumpi { value, jumpi } => {
+			validate!(0, 0);
+			check!(0, state.stack.push_u256(value));
+			validate!(1, jumpi);
+			last(1, jumpi, self::misc::jumpi(state))
+		},
+		Superinstruction::PushPushAdd { a, b, push, add } => {
+			validate!(0, 0);
+			check!(0, state.stack.push_u256(a));
+			validate!(1, push);
+			check!(1, state.stack.push_u256(b));
+			validate!(2, add);
+			last(2, add, eval_add(state, Opcode::ADD, position + add))
+		},
+		Superinstruction::DupSwap { dup, swap } => {
+			validate!(0, 0);
+			check!(0, state.stack.dup(dup - 1));
+			validate!(1, 1);
+			check!(1, state.stack.swap(swap));
+			(2, Control::Continue(2))
+		},
+	}
 }
@@ -0,0 +1,44 @@
+//! Version of the serialized machine state.
+
+use core::fmt;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// Version of the serde encoding of `Machine` and `Stack`, bumped whenever
+/// the encoding changes. Values are encoded independently of the host: the
+/// `U256` values in big-endian order, and the memory as plain bytes.
+pub const SERDE_FORMAT_VERSION: u8 = 1;
+
+/// Version tag of a serialized value, checked against
+/// `SERDE_FORMAT_VERSION` when deserializing.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct FormatVersion;
+
+impl Serialize for FormatVersion {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.serialize_u8(SERDE_FORMAT_VERSION)
+	}
+}
+
+impl<'de> Deserialize<'de> for FormatVersion {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		struct Visitor;
+
+		impl<'de> de::Visitor<'de> for Visitor {
+			type Value = FormatVersion;
+
+			fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+				write!(formatter, "format version {SERDE_FORMAT_VERSION}")
+			}
+
+			fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+				if v == u64::from(SERDE_FORMAT_VERSION) {
+					Ok(FormatVersion)
+				} else {
+					Err(E::invalid_value(de::Unexpected::Unsigned(v), &self))
+				}
+			}
+		}
+
+		deserializer.deserialize_u8(Visitor)
+	}
+}
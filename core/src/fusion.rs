@@ -0,0 +1,163 @@
+use alloc::vec::Vec;
+use crate::{Opcode, U256};
+
+/// Common opcode sequence executed with a single dispatch.
+///
+/// Offsets are relative to the position of the first opcode of the sequence.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Superinstruction {
+	/// `PUSHn value; JUMP`.
+	PushJump {
+		/// Pushed value.
+		value: U256,
+		/// Offset of `JUMP`.
+		jump: usize,
+	},
+	/// `PUSHn value; JUMPI`.
+	PushJumpi {
+		/// Pushed value.
+		value: U256,
+		/// Offset of `JUMPI`.
+		jumpi: usize,
+	},
+	/// `PUSHn a; PUSHm b; ADD`.
+	PushPushAdd {
+		/// First pushed value.
+		a: U256,
+		/// Second pushed value.
+		b: U256,
+		/// Offset of the second push.
+		push: usize,
+		/// Offset of `ADD`.
+		add: usize,
+	},
+	/// `DUPn; SWAPm`.
+	DupSwap {
+		/// Index of the duplicated value, from `1` for `DUP1`.
+		dup: usize,
+		/// Index of the swapped value, from `1` for `SWAP1`.
+		swap: usize,
+	},
+}
+
+impl Superinstruction {
+	/// Number of opcodes in the sequence.
+	#[must_use]
+	pub const fn steps(&self) -> u64 {
+		match self {
+			Self::PushJump { .. } | Self::PushJumpi { .. } | Self::DupSwap { .. } => 2,
+			Self::PushPushAdd { .. } => 3,
+		}
+	}
+}
+
+/// Side table of superinstructions of a code, indexed by the position of
+/// their first opcode.
+///
+/// Computing it is a single pass over the code, so it is worth keeping
+/// alongside the code of hot contracts.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Fusion {
+	/// Index plus one into `instructions` for each position, zero for none.
+	slots: Vec<u32>,
+	instructions: Vec<Superinstruction>,
+}
+
+/// Size of the push data of the opcode at `position`, if it is a push
+/// whose data is entirely within the code.
+fn push_len(code: &[u8], position: usize) -> Option<usize> {
+	let len = Opcode(*code.get(position)?).push_size()?;
+	if position + len < code.len() {
+		Some(len)
+	} else {
+		None
+	}
+}
+
+fn push_value(code: &[u8], position: usize, len: usize) -> U256 {
+	U256::from_big_endian_fast(&code[(position + 1)..=(position + len)])
+}
+
+impl Fusion {
+	/// Recognize superinstructions in the code.
+	#[must_use]
+	pub fn analyze(code: &[u8]) -> Self {
+		let mut fusion = Self {
+			slots: alloc::vec![0; code.len()],
+			instructions: Vec::new(),
+		};
+
+		let mut position = 0;
+		while position < code.len() {
+			if let Some(instruction) = Self::recognize(code, position) {
+				fusion.instructions.push(instruction);
+				#[allow(clippy::cast_possible_truncation)]
+				let slot = fusion.instructions.len() as u32;
+				fusion.slots[position] = slot;
+			}
+
+			position += 1 + Opcode(code[position]).push_size().unwrap_or(0);
+		}
+
+		fusion
+	}
+
+	fn recognize(code: &[u8], position: usize) -> Option<Superinstruction> {
+		let opcode = Opcode(code[position]);
+
+		if let Some(len) = push_len(code, position) {
+			let value = push_value(code, position, len);
+			let next = position + 1 + len;
+			match Opcode(*code.get(next)?) {
+				Opcode::JUMP => return Some(Superinstruction::PushJump { value, jump: next - position }),
+				Opcode::JUMPI => return Some(Superinstruction::PushJumpi { value, jumpi: next - position }),
+				_ => (),
+			}
+
+			let second = push_len(code, next)?;
+			let add = next + 1 + second;
+			if code.get(add) == Some(&Opcode::ADD.as_u8()) {
+				return Some(Superinstruction::PushPushAdd {
+					a: value,
+					b: push_value(code, next, second),
+					push: next - position,
+					add: add - position,
+				})
+			}
+			return None
+		}
+
+		if (Opcode::DUP1.as_u8()..=Opcode::DUP16.as_u8()).contains(&opcode.as_u8()) {
+			let next = *code.get(position + 1)?;
+			if (Opcode::SWAP1.as_u8()..=Opcode::SWAP16.as_u8()).contains(&next) {
+				return Some(Superinstruction::DupSwap {
+					dup: usize::from(opcode.as_u8() - Opcode::DUP1.as_u8()) + 1,
+					swap: usize::from(next - Opcode::SWAP1.as_u8()) + 1,
+				})
+			}
+		}
+
+		None
+	}
+
+	/// Superinstruction starting at the given position, if any.
+	#[must_use]
+	pub fn get(&self, position: usize) -> Option<Superinstruction> {
+		match self.slots.get(position) {
+			Some(0) | None => None,
+			Some(slot) => Some(self.instructions[*slot as usize - 1]),
+		}
+	}
+
+	/// Number of recognized superinstructions.
+	#[must_use]
+	pub fn len(&self) -> usize {
+		self.instructions.len()
+	}
+
+	/// Whether no superinstruction was recognized.
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.instructions.is_empty()
+	}
+}
@@ -0,0 +1,77 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::cmp::min;
+use core::fmt;
+use crate::eval::{instruction, Control};
+use crate::{Machine, Opcode, U256};
+
+type Compiled = Box<dyn Fn(&mut Machine) -> Control + Send + Sync>;
+
+/// Code translated ahead of time into one closure per opcode.
+///
+/// Push data is decoded and the evaluation function of every opcode is
+/// resolved once, so that running the code skips both. Gas is still
+/// validated opcode by opcode, so the semantics are those of the
+/// interpreter.
+#[derive(Default)]
+pub struct CompiledCode {
+	/// Translated opcode for each position, `None` inside push data.
+	ops: Vec<Option<Compiled>>,
+}
+
+impl fmt::Debug for CompiledCode {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("CompiledCode")
+			.field("len", &self.ops.len())
+			.finish()
+	}
+}
+
+impl CompiledCode {
+	/// Translate the code.
+	#[must_use]
+	pub fn compile(code: &[u8]) -> Self {
+		let mut ops = Vec::with_capacity(code.len());
+		let mut position = 0;
+		while position < code.len() {
+			let opcode = Opcode(code[position]);
+			let (run, len): (Compiled, usize) =
+				if (Opcode::PUSH1.as_u8()..=Opcode::PUSH32.as_u8()).contains(&opcode.as_u8()) {
+					let n = usize::from(opcode.as_u8() - Opcode::PUSH1.as_u8()) + 1;
+					let end = min(position + 1 + n, code.len());
+					let value = U256::from_big_endian_fast(&code[(position + 1)..end]);
+					(Box::new(move |machine: &mut Machine| match machine.stack_mut().push_u256(value) {
+						Ok(()) => Control::Continue(1 + n),
+						Err(e) => Control::Exit(e.into()),
+					}), 1 + n)
+				} else {
+					let eval = instruction(opcode);
+					(Box::new(move |machine: &mut Machine| eval(machine, opcode, position)), 1)
+				};
+
+			ops.push(Some(run));
+			ops.extend((1..len).map(|_| None));
+			position += len;
+		}
+		ops.truncate(code.len());
+
+		Self { ops }
+	}
+
+	/// Code size the translation was made from.
+	#[must_use]
+	pub fn len(&self) -> usize {
+		self.ops.len()
+	}
+
+	/// Whether the translated code is empty.
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.ops.is_empty()
+	}
+
+	/// Translated opcode at the given position, if one starts there.
+	pub(crate) fn get(&self, position: usize) -> Option<&Compiled> {
+		self.ops.get(position).and_then(Option::as_ref)
+	}
+}
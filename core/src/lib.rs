@@ -14,30 +14,75 @@
 extern crate core;
 extern crate alloc;
 
+mod arithmetic;
 mod memory;
 mod stack;
 mod valids;
 mod opcode;
 mod error;
 mod eval;
+mod fusion;
+mod eof;
+#[cfg(feature = "debug-trace")]
+mod debug;
+#[cfg(feature = "jit")]
+mod jit;
+#[cfg(feature = "taint")]
+mod taint;
 mod utils;
+mod word;
 mod primitive_types;
+#[cfg(feature = "with-serde")]
+mod format;
+#[cfg(feature = "with-serde")]
+pub mod serde_hex;
 
+pub use crate::arithmetic::{Arithmetic, NativeArithmetic, ActiveArithmetic};
+#[cfg(feature = "ruint")]
+pub use crate::arithmetic::RuintArithmetic;
 pub use crate::memory::Memory;
-pub use crate::stack::Stack;
+pub use crate::stack::{Stack, MAX_STACK_LIMIT};
 pub use crate::valids::Valids;
-pub use crate::opcode::Opcode;
-pub use crate::error::{Trap, Capture, ExitReason, ExitSucceed, ExitError, ExitRevert, ExitFatal};
+pub use crate::fusion::{Fusion, Superinstruction};
+pub use crate::eof::{Eof, EofError, EOF_MAGIC, EOF_VERSION, is_eof, validate_code};
+#[cfg(feature = "debug-trace")]
+pub use crate::debug::DebugSink;
+#[cfg(all(feature = "debug-trace", feature = "std"))]
+pub use crate::debug::WriterSink;
+#[cfg(feature = "jit")]
+pub use crate::jit::CompiledCode;
+#[cfg(feature = "taint")]
+pub use crate::taint::{Taint, TaintFlow, TaintSink};
+#[cfg(all(feature = "taint", feature = "std"))]
+pub use crate::taint::TaintLog;
+pub use crate::opcode::{Instruction, Opcode, OpcodeSet};
+pub use crate::error::{Trap, Capture, ExitReason, ExitSucceed, ExitError, ExitRevert, ExitFatal, CollisionKind};
 pub use crate::primitive_types::{H160, H256, U256, U512};
+#[cfg(feature = "with-serde")]
+pub use crate::format::SERDE_FORMAT_VERSION;
+pub use crate::utils::{Sign, I256};
+pub use crate::word::Word;
 
 use core::ops::Range;
+use alloc::sync::Arc;
 use alloc::vec::Vec;
-use crate::eval::{eval, Control};
+use crate::eval::{eval, eval_fused, eval_word, Control};
 
 /// Core execution layer for EVM.
+///
+/// The stack holds words of type `W`, see `Word`. The memory holds bytes,
+/// words being stored with their concrete value.
 #[cfg_attr(feature = "with-codec", derive(codec::Encode, codec::Decode))]
 #[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
-pub struct Machine {
+#[cfg_attr(feature = "with-serde", serde(bound(
+	serialize = "Stack<W>: serde::Serialize",
+	deserialize = "Stack<W>: serde::Deserialize<'de>",
+)))]
+pub struct Machine<W: Word = U256> {
+	/// Version of the serialized machine, see `SERDE_FORMAT_VERSION`.
+	#[cfg(feature = "with-serde")]
+	#[cfg_attr(feature = "with-codec", codec(skip))]
+	version: crate::format::FormatVersion,
 	/// Program data.
 	#[cfg_attr(feature = "with-serde", serde(with = "serde_bytes"))]
 	data: Vec<u8>,
@@ -45,64 +90,125 @@ pub struct Machine {
 	#[cfg_attr(feature = "with-serde", serde(with = "serde_bytes"))]
 	code: Vec<u8>,
 	/// Program counter.
-	position: Result<usize, ExitReason>,
+	position: usize,
+	/// Exit reason, once the machine has exited.
+	status: Option<ExitReason>,
 	/// Return value.
 	return_range: Range<usize>,
-	/// Code validity maps.
-	valids: Valids,
+	/// Maximum length of the `RETURN` and `REVERT` output.
+	return_data_limit: usize,
+	/// Code validity maps, shared by the machines running the same code.
+	valids: Arc<Valids>,
 	/// Memory.
 	memory: Memory,
 	/// Stack.
-	stack: Stack,
+	stack: Stack<W>,
+	/// Superinstructions of the code, if fused dispatch is enabled.
+	#[cfg_attr(feature = "with-codec", codec(skip))]
+	#[cfg_attr(feature = "with-serde", serde(skip))]
+	fusion: Option<Fusion>,
+	/// Translated code, if compiled execution is enabled.
+	#[cfg(feature = "jit")]
+	#[cfg_attr(feature = "with-codec", codec(skip))]
+	#[cfg_attr(feature = "with-serde", serde(skip))]
+	compiled: Option<alloc::sync::Arc<CompiledCode>>,
+	/// Receiver of the debug output, if any.
+	#[cfg(feature = "debug-trace")]
+	#[cfg_attr(feature = "with-codec", codec(skip))]
+	#[cfg_attr(feature = "with-serde", serde(skip))]
+	debug_sink: Option<alloc::sync::Arc<dyn DebugSink>>,
+	/// Shadow state of taint tracking, if enabled.
+	#[cfg(feature = "taint")]
+	#[cfg_attr(feature = "with-codec", codec(skip))]
+	#[cfg_attr(feature = "with-serde", serde(skip))]
+	taint: Option<Taint>,
 }
 
-impl Machine {
+impl<W: Word> Machine<W> {
 	/// Reference of machine stack.
 	#[must_use]
-	pub const fn stack(&self) -> &Stack { &self.stack }
+	pub const fn stack(&self) -> &Stack<W> { &self.stack }
 	/// Mutable reference of machine stack.
-	pub fn stack_mut(&mut self) -> &mut Stack { &mut self.stack }
+	pub fn stack_mut(&mut self) -> &mut Stack<W> { &mut self.stack }
 	/// Reference of machine memory.
 	#[must_use]
 	pub const fn memory(&self) -> &Memory { &self.memory }
 	/// Mutable reference of machine memory.
 	pub fn memory_mut(&mut self) -> &mut Memory { &mut self.memory }
+	/// Program counter, or the exit reason if the machine has exited.
+	pub const fn position(&self) -> Result<usize, ExitReason> {
+		match self.status {
+			Some(reason) => Err(reason),
+			None => Ok(self.position),
+		}
+	}
 
-	/// Create a new machine with given code and data.
+	/// Create a new machine with given code and data, running on stack
+	/// words of type `W`.
 	#[must_use]
-	pub fn new(
+	pub fn new_with_word(
 		code: Vec<u8>,
-		valids: Vec<u8>,
+		valids: Arc<Valids>,
 		data: Vec<u8>,
 		stack_limit: usize,
 		memory_limit: usize
 	) -> Self {
-		let valids = Valids::new(valids);
-
 		Self {
+			#[cfg(feature = "with-serde")]
+			version: crate::format::FormatVersion,
 			data,
 			code,
-			position: Ok(0),
+			position: 0,
+			status: None,
 			return_range: 0..0,
+			return_data_limit: usize::MAX,
 			valids,
 			memory: Memory::new(memory_limit),
 			stack: Stack::new(stack_limit),
+			fusion: None,
+			#[cfg(feature = "jit")]
+			compiled: None,
+			#[cfg(feature = "debug-trace")]
+			debug_sink: None,
+			#[cfg(feature = "taint")]
+			taint: None,
 		}
 	}
 
+	/// Fail `RETURN` and `REVERT` with `ReturnDataLimit` when their output
+	/// is longer than `limit` bytes. Unlimited by default.
+	pub fn set_return_data_limit(&mut self, limit: usize) {
+		self.return_data_limit = limit;
+	}
+
+	/// Maximum length of the `RETURN` and `REVERT` output.
+	#[must_use]
+	pub const fn return_data_limit(&self) -> usize {
+		self.return_data_limit
+	}
+
 	/// Explicit exit of the machine. Further step will return error.
 	pub fn exit(&mut self, reason: ExitReason) {
-		self.position = Err(reason);
+		self.status = Some(reason);
 	}
 
 	/// Inspect the machine's next opcode and current stack.
 	#[must_use]
-	pub fn inspect(&self) -> Option<(Opcode, &Stack)> {
-		let position = match self.position {
-			Ok(position) => position,
-			Err(_) => return None,
-		};
-		self.code.get(position).map(|v| (Opcode(*v), &self.stack))
+	pub fn inspect(&self) -> Option<(Opcode, &Stack<W>)> {
+		if self.status.is_some() {
+			return None
+		}
+		self.code.get(self.position).map(|v| (Opcode(*v), &self.stack))
+	}
+
+	/// Inspect the machine's next instruction, with the immediate bytes of a
+	/// push, and current stack.
+	#[must_use]
+	pub fn inspect_detailed(&self) -> Option<(Instruction<'_>, &Stack<W>)> {
+		if self.status.is_some() {
+			return None
+		}
+		Instruction::decode(&self.code, self.position).map(|instruction| (instruction, &self.stack))
 	}
 
 	/// Copy and get the return value of the machine, if any.
@@ -114,79 +220,199 @@ impl Machine {
 		)
 	}
 
+	/// Exit at the given position, as returned by `run`.
+	fn halt(&mut self, step: u64, position: usize, reason: ExitReason) -> (u64, Capture<ExitReason, Trap>) {
+		self.position = position;
+		self.status = Some(reason);
+		(step, Capture::Exit(reason))
+	}
+
 	/// Loop stepping the machine, until it stops.
-	pub fn run<F>(&mut self, max_steps: u64, mut pre_validate: F) -> (u64, Capture<ExitReason, Trap>)
-		where F: FnMut(Opcode, &Stack) -> Result<(), ExitError>
+	pub fn run<F>(&mut self, max_steps: u64, pre_validate: F) -> (u64, Capture<ExitReason, Trap>)
+		where F: FnMut(Opcode, usize, &Stack<W>, &Memory) -> Result<(), ExitError>
 	{
-		for step in 0..max_steps {
-			let position = match self.position {
-				Ok(position) => position,
-				Err(reason) => return (step, Capture::Exit(reason))
-			};
+		W::run(self, max_steps, pre_validate)
+	}
+
+	/// Step the machine, executing one opcode. It then returns.
+	pub fn step(&mut self) -> Result<(), Capture<ExitReason, Trap>> {
+		self.position().map_err(Capture::Exit)?;
+
+		match self.run(1, |_, _, _, _| Ok(())) {
+			(1, Capture::Exit(ExitReason::StepLimitReached)) => Ok(()),
+			(_, capture) => Err(capture),
+		}
+	}
 
+	/// Interpreter loop of any word, see `Word::run`.
+	pub(crate) fn interpret<F>(&mut self, max_steps: u64, mut pre_validate: F) -> (u64, Capture<ExitReason, Trap>)
+		where F: FnMut(Opcode, usize, &Stack<W>, &Memory) -> Result<(), ExitError>
+	{
+		if let Some(reason) = self.status {
+			return (0, Capture::Exit(reason))
+		}
+
+		let mut position = self.position;
+		let mut step = 0;
+		while step < max_steps {
 			let opcode = match self.code.get(position) {
 				Some(opcode) => Opcode(*opcode),
-				None => {
-					self.position = Err(ExitReason::Succeed(ExitSucceed::Stopped));
-					return (step, Capture::Exit(ExitReason::Succeed(ExitSucceed::Stopped)));
-				}
+				None => return self.halt(step, position, ExitSucceed::Stopped.into()),
 			};
 
-			if let Err(error) = pre_validate(opcode, &self.stack()) {
-				let reason = ExitReason::from(error);
-				self.exit(reason);
-				return (step, Capture::Exit(reason));
+			if let Err(error) = pre_validate(opcode, position, &self.stack, &self.memory) {
+				return self.halt(step, position, error.into());
 			}
 
-			match eval(self, opcode, position) {
-				Control::Continue(p) => {
-					self.position = Ok(position + p);
-				},
-				Control::Exit(reason) => {
-					self.exit(reason);
-					return (step, Capture::Exit(reason))
-				},
-				Control::Jump(p) => {
-					self.position = Ok(p);
-				},
+			match eval_word(self, opcode, position) {
+				Control::Continue(p) => position += p,
+				Control::Jump(p) => position = p,
+				Control::Exit(reason) => return self.halt(step, position, reason),
 				Control::Trap(opcode) => {
-					self.position = Ok(position + 1);
+					self.position = position + 1;
 					return (step, Capture::Trap(opcode));
 				},
 			}
+			step += 1;
 		}
 
+		self.position = position;
 		(max_steps, Capture::Exit(ExitReason::StepLimitReached))
 	}
+}
 
-	/// Step the machine, executing one opcode. It then returns.
-	pub fn step(&mut self) -> Result<(), Capture<ExitReason, Trap>> {
-		let position = *self.position.as_ref().map_err(|reason| Capture::Exit(reason.clone()))?;
-
-		let opcode = if let Some(opcode) = self.code.get(position).map(|v| Opcode(*v)) {
-			opcode
-		} else {
-			self.position = Err(ExitSucceed::Stopped.into());
-			return Err(Capture::Exit(ExitSucceed::Stopped.into()))
-		};
-
-		match eval(self, opcode, position) {
-			Control::Continue(p) => {
-				self.position = Ok(position + p);
-				Ok(())
-			},
-			Control::Exit(e) => {
-				self.position = Err(e.clone());
-				Err(Capture::Exit(e))
-			},
-			Control::Jump(p) => {
-				self.position = Ok(p);
-				Ok(())
-			},
-			Control::Trap(opcode) => {
-				self.position = Ok(position + 1);
-				Err(Capture::Trap(opcode))
-			},
+impl Machine {
+	/// Create a new machine with given code and data.
+	#[must_use]
+	pub fn new(
+		code: Vec<u8>,
+		valids: Arc<Valids>,
+		data: Vec<u8>,
+		stack_limit: usize,
+		memory_limit: usize
+	) -> Self {
+		Self::new_with_word(code, valids, data, stack_limit, memory_limit)
+	}
+
+	/// Enable fused dispatch in `run`, recognizing superinstructions in the
+	/// code.
+	pub fn fuse(&mut self) {
+		self.set_fusion(Fusion::analyze(&self.code));
+	}
+
+	/// Enable fused dispatch in `run` with superinstructions previously
+	/// recognized in the same code.
+	pub fn set_fusion(&mut self, fusion: Fusion) {
+		self.fusion = Some(fusion);
+	}
+
+	/// Run the code through its translation, which must have been compiled
+	/// from the same code. Superinstructions still take precedence.
+	#[cfg(feature = "jit")]
+	pub fn set_compiled(&mut self, compiled: alloc::sync::Arc<CompiledCode>) {
+		self.compiled = Some(compiled);
+	}
+
+	/// Report executed opcodes and their operands to `sink`. Superinstructions
+	/// and the pushes of compiled code are not reported.
+	#[cfg(feature = "debug-trace")]
+	pub fn set_debug_sink(&mut self, sink: alloc::sync::Arc<dyn DebugSink>) {
+		self.debug_sink = Some(sink);
+	}
+
+	/// Track the values derived from the call data through the stack and
+	/// memory, reporting the storage writes and calls they reach to `sink`.
+	///
+	/// Superinstructions and compiled code are not run while tracking.
+	#[cfg(feature = "taint")]
+	pub fn set_taint_sink(&mut self, sink: alloc::sync::Arc<dyn TaintSink>) {
+		self.taint = Some(Taint::new(sink));
+	}
+
+	/// Shadow state of taint tracking, if enabled.
+	#[cfg(feature = "taint")]
+	#[must_use]
+	pub const fn taint(&self) -> Option<&Taint> {
+		self.taint.as_ref()
+	}
+
+	/// Evaluate `opcode`, updating the taint shadow state if enabled.
+	fn eval(&mut self, opcode: Opcode, position: usize) -> Control {
+		#[cfg(feature = "taint")]
+		if let Some(mut taint) = self.taint.take() {
+			let transfer = taint.transfer(opcode, position, &self.stack);
+			let control = eval(self, opcode, position);
+			if !matches!(control, Control::Exit(_)) {
+				taint.apply(transfer, &self.memory);
+			}
+			self.taint = Some(taint);
+			return control
+		}
+
+		eval(self, opcode, position)
+	}
+
+	/// Interpreter loop of `U256` words, see `Word::run`.
+	pub(crate) fn interpret_u256<F>(&mut self, max_steps: u64, mut pre_validate: F) -> (u64, Capture<ExitReason, Trap>)
+		where F: FnMut(Opcode, usize, &Stack, &Memory) -> Result<(), ExitError>
+	{
+		if let Some(reason) = self.status {
+			return (0, Capture::Exit(reason))
 		}
+
+		#[cfg(feature = "jit")]
+		let compiled = self.compiled.clone();
+		#[cfg(all(feature = "jit", feature = "taint"))]
+		let compiled = compiled.filter(|_| self.taint.is_none());
+
+		// The program counter lives in a local until the loop is left.
+		let mut position = self.position;
+		let mut step = 0;
+		while step < max_steps {
+			let opcode = match self.code.get(position) {
+				Some(opcode) => Opcode(*opcode),
+				None => return self.halt(step, position, ExitSucceed::Stopped.into()),
+			};
+
+			let fused = self.fusion.as_ref()
+				.and_then(|fusion| fusion.get(position))
+				.filter(|instruction| instruction.steps() <= max_steps - step);
+			#[cfg(feature = "taint")]
+			let fused = fused.filter(|_| self.taint.is_none());
+			let control = if let Some(instruction) = fused {
+				let (steps, control) = eval_fused(self, instruction, position, &mut pre_validate);
+				step += steps;
+				control
+			} else {
+				if let Err(error) = pre_validate(opcode, position, &self.stack, &self.memory) {
+					return self.halt(step, position, error.into());
+				}
+
+				#[cfg(feature = "jit")]
+				let control = match compiled.as_ref().and_then(|compiled| compiled.get(position)) {
+					Some(run) => run(self),
+					None => self.eval(opcode, position),
+				};
+				#[cfg(not(feature = "jit"))]
+				let control = self.eval(opcode, position);
+				if let Control::Continue(_) | Control::Jump(_) = control {
+					step += 1;
+				}
+				control
+			};
+
+			match control {
+				Control::Continue(p) => position += p,
+				Control::Jump(p) => position = p,
+				Control::Exit(reason) => return self.halt(step, position, reason),
+				Control::Trap(opcode) => {
+					self.position = position + 1;
+					return (step, Capture::Trap(opcode));
+				},
+			}
+		}
+
+		self.position = position;
+		(max_steps, Capture::Exit(ExitReason::StepLimitReached))
 	}
 }
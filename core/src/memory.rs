@@ -1,25 +1,65 @@
 use core::cmp::{min, max};
+#[cfg(feature = "with-serde")]
+use core::convert::TryFrom;
 use alloc::{vec,vec::Vec};
-use crate::{ExitError, ExitFatal};
+use crate::{ExitError, ExitFatal, U256, Word};
+
+/// Granularity of the zero-filled backing buffer growth.
+const PAGE_SIZE: usize = 4096;
 
 /// A sequencial memory. It uses Rust's `Vec` for internal
 /// representation.
+///
+/// The backing buffer grows in page-sized zero-filled chunks, so that
+/// successive writes just past the written region don't reallocate. The
+/// length of the memory is the high-water mark of the written region.
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "with-codec", derive(codec::Encode, codec::Decode))]
-#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize), serde(try_from = "MemoryRepr"))]
 pub struct Memory {
 	#[cfg_attr(feature = "with-serde", serde(with = "serde_bytes"))]
 	data: Vec<u8>,
+	high_water: usize,
+	effective_len: usize,
+	limit: usize,
+}
+
+/// Serialized form of the memory.
+#[cfg(feature = "with-serde")]
+#[derive(serde::Deserialize)]
+struct MemoryRepr {
+	#[serde(with = "serde_bytes")]
+	data: Vec<u8>,
+	high_water: usize,
 	effective_len: usize,
 	limit: usize,
 }
 
+/// The written region of a deserialized memory must lie in its buffer.
+#[cfg(feature = "with-serde")]
+impl TryFrom<MemoryRepr> for Memory {
+	type Error = &'static str;
+
+	fn try_from(repr: MemoryRepr) -> Result<Self, Self::Error> {
+		if repr.high_water > repr.data.len() {
+			return Err("memory length above its buffer")
+		}
+		Ok(Self {
+			data: repr.data,
+			high_water: repr.high_water,
+			effective_len: repr.effective_len,
+			limit: repr.limit,
+		})
+	}
+}
+
 impl Memory {
 	/// Create a new memory with the given limit.
 	#[must_use]
 	pub const fn new(limit: usize) -> Self {
 		Self {
 			data: Vec::new(),
+			high_water: 0_usize,
 			effective_len: 0_usize,
 			limit,
 		}
@@ -33,8 +73,14 @@ impl Memory {
 
 	/// Get the length of the current memory range.
 	#[must_use]
-	pub fn len(&self) -> usize {
-		self.data.len()
+	pub const fn len(&self) -> usize {
+		self.high_water
+	}
+
+	/// Written region of the memory, `len()` bytes long.
+	#[must_use]
+	pub fn data(&self) -> &[u8] {
+		&self.data[..self.high_water]
 	}
 
 	/// Get the effective length.
@@ -45,7 +91,7 @@ impl Memory {
 
 	/// Return true if current effective memory range is zero.
 	#[must_use]
-	pub fn is_empty(&self) -> bool {
+	pub const fn is_empty(&self) -> bool {
 		self.len() == 0
 	}
 
@@ -90,11 +136,11 @@ impl Memory {
 	pub fn get(&self, offset: usize, size: usize) -> Vec<u8> {
 		let mut ret = vec![0; size];
 
-		if offset >= self.data.len() {
+		if offset >= self.high_water {
 			return ret;
 		}
 		let end = match offset.checked_add(size) {
-			Some(end) => min(end, self.data.len()),
+			Some(end) => min(end, self.high_water),
 			None => return ret
 		};
 
@@ -118,9 +164,7 @@ impl Memory {
 			return Err(ExitFatal::NotSupported)
 		}
 
-		if self.data.len() < offset + target_size {
-			self.data.resize(offset + target_size, 0);
-		}
+		self.grow(offset + target_size);
 
 		let data = &mut self.data[offset..(offset + target_size)];
 		let value_size = min(value.len(), target_size);
@@ -131,6 +175,57 @@ impl Memory {
 		Ok(())
 	}
 
+	/// Load the 32-byte big-endian word at the given offset. The offset is
+	/// considered trusted, as in `get`.
+	#[must_use]
+	pub fn load<W: Word>(&self, offset: usize) -> W {
+		W::from_u256(U256::from_big_endian_fast(&self.get(offset, 32)))
+	}
+
+	/// Store a word as 32 big-endian bytes at the given offset. The offset is
+	/// considered untrusted. Words without a concrete value cannot be stored.
+	pub fn store<W: Word>(&mut self, offset: usize, value: &W) -> Result<(), ExitFatal> {
+		let value = value.to_u256().ok_or(ExitFatal::NotSupported)?;
+		let mut bytes = [0_u8; 32];
+		value.into_big_endian_fast(&mut bytes);
+		self.set(offset, &bytes, Some(32))
+	}
+
+	/// Copy `len` bytes from `src_offset` to `dst_offset` inside the memory.
+	/// Overlapping regions are handled as if the source was first copied to
+	/// a temporary buffer. The offsets are considered untrusted.
+	pub fn copy_within(
+		&mut self,
+		dst_offset: usize,
+		src_offset: usize,
+		len: usize,
+	) -> Result<(), ExitFatal> {
+		if len == 0 {
+			return Ok(())
+		}
+
+		let end = max(dst_offset, src_offset).checked_add(len);
+		if end.map_or(true, |end| end > self.limit) {
+			return Err(ExitFatal::NotSupported)
+		}
+
+		self.grow(max(dst_offset, src_offset) + len);
+		self.data.copy_within(src_offset..(src_offset + len), dst_offset);
+
+		Ok(())
+	}
+
+	/// Make the backing buffer cover `end`, growing it to the next page
+	/// boundary within the limit, and raise the high-water mark.
+	fn grow(&mut self, end: usize) {
+		if self.data.len() < end {
+			let page_end = end.checked_add(PAGE_SIZE - 1)
+				.map_or(end, |end| end - end % PAGE_SIZE);
+			self.data.resize(max(end, min(page_end, self.limit)), 0);
+		}
+		self.high_water = max(self.high_water, end);
+	}
+
 	/// Copy `data` into the memory, of given `len`.
 	pub fn copy_large(
 		&mut self,
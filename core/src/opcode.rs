@@ -1,6 +1,8 @@
 #![allow(clippy::use_self)]
 /// Opcode enum. One-to-one corresponding to an `u8` value.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "with-codec", derive(codec::Encode, codec::Decode))]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Opcode(pub u8);
 
 // Core opcodes.
@@ -89,6 +91,8 @@ impl Opcode {
 	pub const MSIZE: Opcode = Opcode(0x59);
 	/// `JUMPDEST`
 	pub const JUMPDEST: Opcode = Opcode(0x5b);
+	/// `MCOPY`
+	pub const MCOPY: Opcode = Opcode(0x5e);
 
 	/// `PUSHn`
 	pub const PUSH1: Opcode = Opcode(0x60);
@@ -252,4 +256,83 @@ impl Opcode {
 	pub const fn as_usize(self) -> usize {
 		self.0 as usize
 	}
+
+	/// Number of immediate bytes of a `PUSHn`, `None` for other opcodes.
+	#[must_use]
+	pub const fn push_size(self) -> Option<usize> {
+		if self.0 >= Opcode::PUSH1.0 && self.0 <= Opcode::PUSH32.0 {
+			Some((self.0 - Opcode::PUSH1.0) as usize + 1)
+		} else {
+			None
+		}
+	}
+}
+
+/// Instruction decoded from code, as shown by `Machine::inspect_detailed`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Instruction<'a> {
+	/// Opcode of the instruction.
+	pub opcode: Opcode,
+	/// Immediate bytes of a `PUSHn`, shorter than `n` if the code ends
+	/// before them, and empty for other opcodes.
+	pub immediate: &'a [u8],
+	/// Position of the following instruction, if this one does not jump.
+	pub next: usize,
+}
+
+impl<'a> Instruction<'a> {
+	/// Decode the instruction at `position`, `None` past the end of the code.
+	#[must_use]
+	pub fn decode(code: &'a [u8], position: usize) -> Option<Self> {
+		let opcode = Opcode(*code.get(position)?);
+		let size = opcode.push_size().unwrap_or(0);
+		let start = position + 1;
+		let immediate = code.get(start..code.len().min(start + size)).unwrap_or(&[]);
+		Some(Self { opcode, immediate, next: start + size })
+	}
+
+	/// Value pushed by a `PUSHn`, read from the immediate bytes there are, as
+	/// the interpreter does.
+	#[must_use]
+	pub fn push_value(&self) -> Option<crate::U256> {
+		self.opcode.push_size().map(|_| crate::U256::from_big_endian(self.immediate))
+	}
+}
+
+/// Set of opcodes, for instance the opcodes a chain disables.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct OpcodeSet([u64; 4]);
+
+impl OpcodeSet {
+	/// Empty set.
+	#[must_use]
+	pub const fn new() -> Self {
+		Self([0; 4])
+	}
+
+	/// Set with `opcode` added.
+	#[must_use]
+	pub const fn with(mut self, opcode: Opcode) -> Self {
+		self.0[opcode.as_usize() / 64] |= 1 << (opcode.as_usize() % 64);
+		self
+	}
+
+	/// Set with `opcode` removed.
+	#[must_use]
+	pub const fn without(mut self, opcode: Opcode) -> Self {
+		self.0[opcode.as_usize() / 64] &= !(1 << (opcode.as_usize() % 64));
+		self
+	}
+
+	/// Whether the set contains `opcode`.
+	#[must_use]
+	pub const fn contains(&self, opcode: Opcode) -> bool {
+		self.0[opcode.as_usize() / 64] & (1 << (opcode.as_usize() % 64)) != 0
+	}
+
+	/// Whether the set is empty.
+	#[must_use]
+	pub const fn is_empty(&self) -> bool {
+		self.0[0] == 0 && self.0[1] == 0 && self.0[2] == 0 && self.0[3] == 0
+	}
 }
@@ -28,6 +28,7 @@ construct_uint! {
 
 impl_rlp::impl_uint_rlp!(U256, 4);
 impl_rlp::impl_fixed_hash_rlp!(H160, 20);
+impl_rlp::impl_fixed_hash_rlp!(H256, 32);
 
 
 impl From<U256> for U512 {
@@ -99,7 +100,7 @@ macro_rules! impl_fixed_hash_serde {
 			where
 				S: serde::Serializer,
 			{
-				serializer.serialize_bytes(self.as_bytes())
+				$crate::serde_hex::serialize(self.as_bytes(), serializer)
 			}
 		}
 
@@ -119,13 +120,31 @@ macro_rules! impl_fixed_hash_serde {
 					fn visit_bytes<E: serde::de::Error>(self, v: &[u8]) -> Result<Self::Value, E>
 					{
 						let mut data = $name::default();
+						if v.len() != data.as_bytes().len() {
+							return Err(E::invalid_length(v.len(), &self))
+						}
 						data.as_bytes_mut().copy_from_slice(v);
 
 						Ok(data)
 					}
+
+					fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E>
+					{
+						let bytes = $crate::serde_hex::decode(v).map_err(E::custom)?;
+						self.visit_bytes(&bytes)
+					}
+
+					fn visit_seq<A: serde::de::SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error>
+					{
+						self.visit_bytes(&$crate::serde_hex::bytes_from_seq(seq)?)
+					}
 				}
 
-				deserializer.deserialize_bytes(Visitor)
+				if $crate::serde_hex::enabled(deserializer.is_human_readable()) {
+					deserializer.deserialize_str(Visitor)
+				} else {
+					deserializer.deserialize_bytes(Visitor)
+				}
 			}
 		}
 	};
@@ -138,16 +157,26 @@ impl_fixed_hash_serde!(H160);
 impl_fixed_hash_serde!(H256);
 
 
+/// Serialized as 32 bytes in big-endian order, whatever the endianness of
+/// the host, or as a `0x`-prefixed hex quantity in human-readable formats
+/// with the `serde-hex` feature.
+#[cfg(feature = "with-serde")]
 impl serde::Serialize for U256 {
 	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
 	where
 		S: serde::Serializer,
 	{
-		let data: [u8; 32] = unsafe { core::mem::transmute_copy(self) };
+		if crate::serde_hex::enabled(serializer.is_human_readable()) {
+			return serializer.serialize_str(&alloc::format!("{self:#x}"))
+		}
+
+		let mut data = [0_u8; 32];
+		self.into_big_endian_fast(&mut data);
 		serializer.serialize_bytes(&data)
 	}
 }
 
+#[cfg(feature = "with-serde")]
 impl<'de> serde::Deserialize<'de> for U256 {
 	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
 	where
@@ -158,43 +187,76 @@ impl<'de> serde::Deserialize<'de> for U256 {
 			type Value = U256;
 
 			fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
-				formatter.write_str("U256")
+				formatter.write_str("32 big-endian bytes or a hex quantity")
 			}
 
 			fn visit_bytes<E: serde::de::Error>(self, v: &[u8]) -> Result<Self::Value, E>
 			{
-				let mut data = [0_u8; 32];
-				data.copy_from_slice(v);
+				if v.len() != 32 {
+					return Err(E::invalid_length(v.len(), &self))
+				}
 
-				let value: U256 = unsafe { core::mem::transmute(data) };
-				Ok(value)
+				Ok(U256::from_big_endian_fast(v))
+			}
+
+			fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E>
+			{
+				let digits = v.strip_prefix("0x").unwrap_or(v);
+				if digits.is_empty() {
+					return Err(E::invalid_value(serde::de::Unexpected::Str(v), &self))
+				}
+
+				U256::from_str_radix(digits, 16).map_err(|_| E::invalid_value(serde::de::Unexpected::Str(v), &self))
+			}
+
+			fn visit_seq<A: serde::de::SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error>
+			{
+				self.visit_bytes(&crate::serde_hex::bytes_from_seq(seq)?)
 			}
 		}
 
-		deserializer.deserialize_bytes(Visitor)
+		if crate::serde_hex::enabled(deserializer.is_human_readable()) {
+			deserializer.deserialize_str(Visitor)
+		} else {
+			deserializer.deserialize_bytes(Visitor)
+		}
 	}
 }
 
 
 impl U256 {
+	/// Write the value in big-endian order to the first 32 bytes of
+	/// `buffer`.
+	///
+	/// # Panics
+	///
+	/// Panics if `buffer` is shorter than 32 bytes.
 	pub fn into_big_endian_fast(self, buffer: &mut [u8]) {
-		let data: [u8; 32] = unsafe { core::mem::transmute(self) };
-		
-		let buffer = &mut buffer[0..32];
-		buffer.copy_from_slice(&data[..]);
-		buffer.reverse();
+		let U256(ref limbs) = self;
+		for (chunk, limb) in buffer[0..32].chunks_exact_mut(8).zip(limbs.iter().rev()) {
+			chunk.copy_from_slice(&limb.to_be_bytes());
+		}
 	}
 
+	/// Read a value from at most 32 bytes in big-endian order.
+	///
+	/// # Panics
+	///
+	/// Panics if `buffer` is longer than 32 bytes.
 	#[must_use]
 	pub fn from_big_endian_fast(buffer: &[u8]) -> U256 {
 		assert!(32 >= buffer.len());
 
 		let mut data = [0_u8; 32];
-
 		data[32 - buffer.len()..32].copy_from_slice(buffer);
-		data.reverse();
 
-		unsafe { core::mem::transmute(data) }
+		let mut limbs = [0_u64; 4];
+		for (limb, chunk) in limbs.iter_mut().rev().zip(data.chunks_exact(8)) {
+			let mut bytes = [0_u8; 8];
+			bytes.copy_from_slice(chunk);
+			*limb = u64::from_be_bytes(bytes);
+		}
+		U256(limbs)
 	}
 }
 
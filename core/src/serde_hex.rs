@@ -0,0 +1,95 @@
+//! Serde representation of byte strings as `0x`-prefixed hex strings.
+//!
+//! With the `serde-hex` feature, human-readable formats such as JSON get the
+//! hex strings, for use with `#[serde(with = "evm_core::serde_hex")]`.
+//! Binary formats, and all formats without the feature, keep the compact
+//! `serde_bytes` encoding.
+
+use alloc::{fmt, string::String, vec::Vec};
+use serde::{Deserializer, Serializer};
+
+/// Whether hex strings are used with the given format.
+#[must_use]
+pub const fn enabled(human_readable: bool) -> bool {
+	cfg!(feature = "serde-hex") && human_readable
+}
+
+/// Encode `bytes` in lowercase hex, prefixed with `0x`.
+#[must_use]
+pub fn encode(bytes: &[u8]) -> String {
+	const DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+	let mut hex = String::with_capacity(2 + bytes.len() * 2);
+	hex.push_str("0x");
+	for byte in bytes {
+		hex.push(DIGITS[usize::from(byte >> 4)] as char);
+		hex.push(DIGITS[usize::from(byte & 0xf)] as char);
+	}
+	hex
+}
+
+/// Decode a hex string, with or without the `0x` prefix. An odd number of
+/// digits is read with a leading zero.
+pub fn decode(hex: &str) -> Result<Vec<u8>, &'static str> {
+	let hex = hex.strip_prefix("0x").unwrap_or(hex).as_bytes();
+
+	let digit = |c: u8| match c {
+		b'0'..=b'9' => Ok(c - b'0'),
+		b'a'..=b'f' => Ok(c - b'a' + 10),
+		b'A'..=b'F' => Ok(c - b'A' + 10),
+		_ => Err("invalid hex digit"),
+	};
+
+	let (head, tail) = hex.split_at(hex.len() % 2);
+	let mut bytes = Vec::with_capacity(hex.len() / 2 + 1);
+	if let [c] = head {
+		bytes.push(digit(*c)?);
+	}
+	for pair in tail.chunks_exact(2) {
+		bytes.push(digit(pair[0])? << 4 | digit(pair[1])?);
+	}
+	Ok(bytes)
+}
+
+/// Serialize `bytes` as a hex string in human-readable formats with the
+/// `serde-hex` feature, as bytes otherwise.
+pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+	if enabled(serializer.is_human_readable()) {
+		serializer.serialize_str(&encode(bytes))
+	} else {
+		serializer.serialize_bytes(bytes)
+	}
+}
+
+/// Deserialize bytes serialized with `serialize`.
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+	if enabled(deserializer.is_human_readable()) {
+		deserializer.deserialize_str(HexVisitor)
+	} else {
+		serde_bytes::deserialize(deserializer)
+	}
+}
+
+/// Collect the bytes of a sequence, the form of byte strings in formats
+/// without a byte string type, such as JSON.
+pub(crate) fn bytes_from_seq<'de, A: serde::de::SeqAccess<'de>>(mut seq: A) -> Result<Vec<u8>, A::Error> {
+	let mut bytes = Vec::new();
+	while let Some(byte) = seq.next_element()? {
+		bytes.push(byte);
+	}
+	Ok(bytes)
+}
+
+struct HexVisitor;
+
+impl serde::de::Visitor<'_> for HexVisitor {
+	type Value = Vec<u8>;
+
+	fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+		formatter.write_str("a 0x-prefixed hex string")
+	}
+
+	fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+		decode(v).map_err(E::custom)
+	}
+}
@@ -1,19 +1,25 @@
+use alloc::{boxed::Box, vec};
+#[cfg(feature = "with-serde")]
 use alloc::vec::Vec;
-use crate::{ExitError, H256, U256};
+#[cfg(feature = "with-serde")]
+use core::convert::TryFrom;
+use crate::{ExitError, Word, H256, U256};
 
 #[cfg(feature = "with-serde")]
 mod serde_vec_u256 {
 	use serde::{Serializer, Deserializer, de};
-	use alloc::{fmt, vec::Vec};
+	use alloc::{fmt, vec, vec::Vec};
 	use crate::U256;
 
+	/// Serialize the values as consecutive 32-byte big-endian words.
 	pub fn serialize<S: Serializer>(data: &[U256], serializer: S) -> Result<S::Ok, S::Error>
 	{
-		let (prefix, bytes, sufix) = unsafe { data.align_to::<u8>() };
-		assert_eq!(prefix.len(), 0);
-		assert_eq!(sufix.len(), 0);
-		
-		serializer.serialize_bytes(bytes)
+		let mut bytes = vec![0_u8; data.len() * 32];
+		for (chunk, value) in bytes.chunks_exact_mut(32).zip(data) {
+			value.into_big_endian_fast(chunk);
+		}
+
+		serializer.serialize_bytes(&bytes)
 	}
 
 	pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<U256>, D::Error> {
@@ -23,24 +29,20 @@ mod serde_vec_u256 {
 			type Value = Vec<U256>;
 
 			fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-				formatter.write_str("Vec<U256>")
+				formatter.write_str("32-byte big-endian words")
 			}
 
 			fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E>
 			{
 				if v.len() % 32 != 0 {
-					return Err(E::custom("unexpected slice len"));
+					return Err(E::invalid_length(v.len(), &self));
 				}
 
-				let mut data: Vec<U256> = Vec::with_capacity( (v.len() / 32) + 32 );
-				unsafe {
-					let ptr = data.as_mut_ptr().cast::<u8>();
-					ptr.copy_from_nonoverlapping(v.as_ptr(), v.len());
-
-					data.set_len(v.len() / 32);
-				}
+				Ok(v.chunks_exact(32).map(U256::from_big_endian_fast).collect())
+			}
 
-				Ok(data)
+			fn visit_seq<A: de::SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+				self.visit_bytes(&crate::serde_hex::bytes_from_seq(seq)?)
 			}
 		}
 
@@ -48,22 +50,86 @@ mod serde_vec_u256 {
 	}
 }
 
+/// Stack limit of the EVM, the largest limit of a deserialized stack.
+pub const MAX_STACK_LIMIT: usize = 1024;
+
 /// EVM stack.
+///
+/// Values live in a buffer allocated once with the capacity of the stack
+/// limit, so that pushing never reallocates. Besides the checked accessors,
+/// the interpreter validates the stack height of an opcode once with
+/// `check`, then uses the unchecked accessors.
+///
+/// The unchecked accessors, used by the fast path of the interpreter, are
+/// for stacks of `U256`.
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "with-codec", derive(codec::Encode, codec::Decode))]
-#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
-pub struct Stack {
-	#[cfg_attr(feature = "with-serde", serde(with="serde_vec_u256"))]
+pub struct Stack<W: Word = U256> {
+	data: Box<[W]>,
+	len: usize,
+	limit: usize,
+}
+
+/// Serialized form of the stack, holding the values in use only.
+#[cfg(feature = "with-serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct StackRepr {
+	version: crate::format::FormatVersion,
+	#[serde(with="serde_vec_u256")]
 	data: Vec<U256>,
 	limit: usize,
 }
 
-impl Stack {
+#[cfg(feature = "with-serde")]
+impl From<&Stack> for StackRepr {
+	fn from(stack: &Stack) -> Self {
+		Self { version: crate::format::FormatVersion, data: stack.data().to_vec(), limit: stack.limit }
+	}
+}
+
+#[cfg(feature = "with-serde")]
+impl serde::Serialize for Stack {
+	fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		StackRepr::from(self).serialize(serializer)
+	}
+}
+
+#[cfg(feature = "with-serde")]
+impl<'de> serde::Deserialize<'de> for Stack {
+	fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		use serde::de::Error;
+
+		StackRepr::deserialize(deserializer).and_then(|repr| Self::try_from(repr).map_err(D::Error::custom))
+	}
+}
+
+/// Stacks with a limit up to `MAX_STACK_LIMIT` only are deserialized, so
+/// that a serialized stack cannot request an arbitrary allocation.
+#[cfg(feature = "with-serde")]
+impl TryFrom<StackRepr> for Stack {
+	type Error = &'static str;
+
+	fn try_from(repr: StackRepr) -> Result<Self, Self::Error> {
+		if repr.limit > MAX_STACK_LIMIT {
+			return Err("stack limit above MAX_STACK_LIMIT")
+		}
+		if repr.data.len() > repr.limit {
+			return Err("more stack values than the stack limit")
+		}
+		let mut stack = Self::new(repr.limit);
+		stack.data[..repr.data.len()].copy_from_slice(&repr.data);
+		stack.len = repr.data.len();
+		Ok(stack)
+	}
+}
+
+impl<W: Word> Stack<W> {
 	/// Create a new stack with given limit.
 	#[must_use]
-	pub const fn new(limit: usize) -> Self {
+	pub fn new(limit: usize) -> Self {
 		Self {
-			data: Vec::new(),
+			data: vec![W::from_u256(U256::zero()); limit].into_boxed_slice(),
+			len: 0,
 			limit,
 		}
 	}
@@ -77,54 +143,51 @@ impl Stack {
 	/// Stack length.
 	#[must_use]
 	#[allow(clippy::len_without_is_empty)]
-	pub fn len(&self) -> usize {
-		self.data.len()
+	pub const fn len(&self) -> usize {
+		self.len
 	}
 
-	/// Pop a value from the stack. If the stack is already empty, returns the
-	/// `StackUnderflow` error.
-	pub fn pop(&mut self) -> Result<H256, ExitError> {
-		self.data.pop()
-			.map(|d| {
-				let mut value = H256::default();
-				d.into_big_endian_fast(&mut value[..]);
-				value
-			})
-			.ok_or(ExitError::StackUnderflow)
+	/// Values in the stack, the top of the stack being the last one.
+	#[must_use]
+	pub fn data(&self) -> &[W] {
+		&self.data[..self.len]
 	}
 
-	/// Push a new value into the stack. If it will exceed the stack limit,
-	/// returns `StackOverflow` error and leaves the stack unchanged.
-	pub fn push(&mut self, value: H256) -> Result<(), ExitError> {
-		if self.data.len() + 1 > self.limit {
+	/// Check that `pop` values can be popped from the stack, and `push`
+	/// values pushed after that.
+	pub const fn check(&self, pop: usize, push: usize) -> Result<(), ExitError> {
+		if self.len < pop {
+			return Err(ExitError::StackUnderflow)
+		}
+		if self.len - pop + push > self.limit {
 			return Err(ExitError::StackOverflow)
 		}
-		self.data.push(U256::from_big_endian_fast(&value[..]));
 		Ok(())
 	}
 
-	/// Pop a value from the stack. If the stack is already empty, returns the
+	/// Pop a word from the stack. If the stack is already empty, returns the
 	/// `StackUnderflow` error.
-	pub fn pop_u256(&mut self) -> Result<U256, ExitError> {
-		self.data.pop().ok_or(ExitError::StackUnderflow)
+	pub fn pop_word(&mut self) -> Result<W, ExitError> {
+		self.check(1, 0)?;
+		self.len -= 1;
+		Ok(self.data[self.len].clone())
 	}
 
-	/// Push a new value into the stack. If it will exceed the stack limit,
+	/// Push a word into the stack. If it will exceed the stack limit,
 	/// returns `StackOverflow` error and leaves the stack unchanged.
-	pub fn push_u256(&mut self, value: U256) -> Result<(), ExitError> {
-		if self.data.len() + 1 > self.limit {
-			return Err(ExitError::StackOverflow)
-		}
-		self.data.push(value);
+	pub fn push_word(&mut self, value: W) -> Result<(), ExitError> {
+		self.check(0, 1)?;
+		self.data[self.len] = value;
+		self.len += 1;
 		Ok(())
 	}
 
 	/// Peek a value at given index for the stack, where the top of
 	/// the stack is at index `0`. If the index is too large,
 	/// `StackError::Underflow` is returned.
-	pub fn peek(&self, no_from_top: usize) -> Result<U256, ExitError> {
-		if self.data.len() > no_from_top {
-			Ok(self.data[self.data.len() - no_from_top - 1])
+	pub fn peek(&self, no_from_top: usize) -> Result<W, ExitError> {
+		if self.len > no_from_top {
+			Ok(self.data[self.len - no_from_top - 1].clone())
 		} else {
 			Err(ExitError::StackUnderflow)
 		}
@@ -133,10 +196,9 @@ impl Stack {
 	/// Set a value at given index for the stack, where the top of the
 	/// stack is at index `0`. If the index is too large,
 	/// `StackError::Underflow` is returned.
-	pub fn set(&mut self, no_from_top: usize, val: U256) -> Result<(), ExitError> {
-		if self.data.len() > no_from_top {
-			let len = self.data.len();
-			self.data[len - no_from_top - 1] = val;
+	pub fn set(&mut self, no_from_top: usize, val: W) -> Result<(), ExitError> {
+		if self.len > no_from_top {
+			self.data[self.len - no_from_top - 1] = val;
 			Ok(())
 		} else {
 			Err(ExitError::StackUnderflow)
@@ -145,26 +207,74 @@ impl Stack {
 
 	/// Dupplicate a value at given index
 	pub fn dup(&mut self, no_from_top: usize) -> Result<(), ExitError> {
-		if self.data.len() <= no_from_top {
+		if self.len <= no_from_top {
 			return Err(ExitError::StackUnderflow);
 		}
 
-		let index = self.data.len() - no_from_top - 1;
-		self.push_u256(self.data[index])
+		self.push_word(self.data[self.len - no_from_top - 1].clone())
 	}
 
 	/// Swap a value at given index with the top value
 	pub fn swap(&mut self, no_from_top: usize) -> Result<(), ExitError> {
-		if self.data.len() <= no_from_top {
+		if self.len <= no_from_top {
 			return Err(ExitError::StackUnderflow);
 		}
 
-		let len = self.data.len();
-		let a = len - no_from_top - 1;
-		let b = len - 1;
-
-		self.data.swap(a, b);
+		self.data.swap(self.len - no_from_top - 1, self.len - 1);
 
 		Ok(())
 	}
 }
+
+impl Stack {
+	/// Pop a value from the stack. If the stack is already empty, returns the
+	/// `StackUnderflow` error.
+	pub fn pop(&mut self) -> Result<H256, ExitError> {
+		self.pop_u256()
+			.map(|d| {
+				let mut value = H256::default();
+				d.into_big_endian_fast(&mut value[..]);
+				value
+			})
+	}
+
+	/// Push a new value into the stack. If it will exceed the stack limit,
+	/// returns `StackOverflow` error and leaves the stack unchanged.
+	pub fn push(&mut self, value: H256) -> Result<(), ExitError> {
+		self.push_u256(U256::from_big_endian_fast(&value[..]))
+	}
+
+	/// Pop a value from the stack. If the stack is already empty, returns the
+	/// `StackUnderflow` error.
+	pub fn pop_u256(&mut self) -> Result<U256, ExitError> {
+		self.pop_word()
+	}
+
+	/// Push a new value into the stack. If it will exceed the stack limit,
+	/// returns `StackOverflow` error and leaves the stack unchanged.
+	pub fn push_u256(&mut self, value: U256) -> Result<(), ExitError> {
+		self.push_word(value)
+	}
+
+	/// Pop a value from the stack without checking its height.
+	///
+	/// # Safety
+	///
+	/// The stack must not be empty, see `check`.
+	pub unsafe fn pop_u256_unchecked(&mut self) -> U256 {
+		debug_assert!(self.len > 0);
+		self.len -= 1;
+		*self.data.get_unchecked(self.len)
+	}
+
+	/// Push a new value into the stack without checking its height.
+	///
+	/// # Safety
+	///
+	/// The stack must be below its limit, see `check`.
+	pub unsafe fn push_u256_unchecked(&mut self, value: U256) {
+		debug_assert!(self.len < self.limit);
+		*self.data.get_unchecked_mut(self.len) = value;
+		self.len += 1;
+	}
+}
@@ -0,0 +1,255 @@
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use crate::{Memory, Opcode, Stack, U256};
+
+/// Storage write or call whose arguments derive from the call data, as
+/// found by taint tracking.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TaintFlow {
+	/// Position of the opcode in the code.
+	pub position: usize,
+	/// `SSTORE`, or the opcode of the call or creation.
+	pub opcode: Opcode,
+	/// Whether each stack argument is tainted, the top of the stack first.
+	pub arguments: Vec<bool>,
+	/// Whether the memory input of a call or creation is tainted, that is
+	/// the call input or the init code.
+	pub input: bool,
+}
+
+impl TaintFlow {
+	/// Whether the stack argument at `index`, the top of the stack being at
+	/// `0`, is tainted.
+	#[must_use]
+	pub fn is_argument_tainted(&self, index: usize) -> bool {
+		self.arguments.get(index).copied().unwrap_or(false)
+	}
+}
+
+/// Receiver of the flows found by taint tracking.
+pub trait TaintSink: Send + Sync {
+	/// Report a storage write or call with tainted arguments, when its opcode
+	/// is reached.
+	fn flow(&self, flow: TaintFlow);
+}
+
+/// Sink keeping the flows in order.
+#[cfg(feature = "std")]
+#[derive(Debug, Default)]
+pub struct TaintLog(std::sync::Mutex<Vec<TaintFlow>>);
+
+#[cfg(feature = "std")]
+impl TaintLog {
+	/// Flows reported so far.
+	#[must_use]
+	pub fn flows(&self) -> Vec<TaintFlow> {
+		self.0.lock().unwrap_or_else(std::sync::PoisonError::into_inner).clone()
+	}
+}
+
+#[cfg(feature = "std")]
+impl TaintSink for TaintLog {
+	fn flow(&self, flow: TaintFlow) {
+		self.0.lock().unwrap_or_else(std::sync::PoisonError::into_inner).push(flow);
+	}
+}
+
+/// Change of the stack taint by an opcode.
+enum StackEffect {
+	/// Pop values, then push one of the given taint if any.
+	Replace(usize, Option<bool>),
+	/// `DUPn`, with the index from the top of the stack.
+	Dup(usize),
+	/// `SWAPn`, with the index from the top of the stack.
+	Swap(usize),
+}
+
+/// Taint change of an opcode, computed before it runs and applied once it
+/// ran.
+pub(crate) struct Transfer {
+	stack: StackEffect,
+	/// Memory range written, and whether it is tainted.
+	memory: Option<(usize, usize, bool)>,
+	flow: Option<TaintFlow>,
+}
+
+/// Shadow state of a machine, tagging the stack values and memory bytes
+/// derived from the call data.
+///
+/// Taint flows from the operands to the result of computations, and through
+/// memory. Values read from the state or the environment, including the
+/// results of calls, are clean whatever their key. Control flow does not
+/// taint.
+pub struct Taint {
+	/// Taint of the stack values, the top of the stack being the last one.
+	stack: Vec<bool>,
+	/// Taint of the memory bytes, the bytes past the end being clean.
+	memory: Vec<bool>,
+	sink: Arc<dyn TaintSink>,
+}
+
+impl Taint {
+	pub(crate) fn new(sink: Arc<dyn TaintSink>) -> Self {
+		Self { stack: Vec::new(), memory: Vec::new(), sink }
+	}
+
+	/// Taint of the stack values, the top of the stack being the last one.
+	#[must_use]
+	pub fn stack(&self) -> &[bool] {
+		&self.stack
+	}
+
+	/// Whether any byte of the memory range is tainted.
+	#[must_use]
+	pub fn is_memory_tainted(&self, offset: usize, len: usize) -> bool {
+		let end = offset.saturating_add(len).min(self.memory.len());
+		offset < end && self.memory[offset..end].contains(&true)
+	}
+
+	fn peek(&self, no_from_top: usize) -> bool {
+		self.stack.len().checked_sub(no_from_top + 1).map_or(false, |index| self.stack[index])
+	}
+
+	fn any(&self, count: usize) -> bool {
+		(0..count).any(|index| self.peek(index))
+	}
+
+	/// Memory range given by the stack arguments at `offset` and `len`,
+	/// `None` if empty or out of the addressable range.
+	fn range(stack: &Stack, offset: usize, len: usize) -> Option<(usize, usize)> {
+		let start = stack.peek(offset).ok()?;
+		let len = stack.peek(len).ok()?;
+		if len.is_zero() || start > U256::from(usize::MAX) || len > U256::from(usize::MAX) {
+			return None
+		}
+		Some((start.as_usize(), len.as_usize()))
+	}
+
+	fn range_tainted(&self, stack: &Stack, offset: usize) -> bool {
+		Self::range(stack, offset, offset + 1).map_or(false, |(start, len)| self.is_memory_tainted(start, len))
+	}
+
+	fn flow(&self, stack: &Stack, opcode: Opcode, position: usize, arguments: usize, input: Option<usize>) -> Option<TaintFlow> {
+		let flow = TaintFlow {
+			position,
+			opcode,
+			arguments: (0..arguments).map(|index| self.peek(index)).collect(),
+			input: input.map_or(false, |offset| self.range_tainted(stack, offset)),
+		};
+		(flow.input || flow.arguments.contains(&true)).then_some(flow)
+	}
+
+	/// Compute the taint change of `opcode`, about to run at `position`.
+	pub(crate) fn transfer(&mut self, opcode: Opcode, position: usize, stack: &Stack) -> Transfer {
+		// Opcodes unknown to the table may have changed the stack, their
+		// values are considered clean.
+		self.stack.resize(stack.len(), false);
+
+		let copy = |offset, len, tainted| Self::range(stack, offset, len).map(|(start, len)| (start, len, tainted));
+		let (effect, memory, flow) = match opcode.as_u8() {
+			// Computations.
+			0x01..=0x07 | 0x0a | 0x0b | 0x10..=0x14 | 0x16..=0x18 | 0x1a..=0x1d =>
+				(StackEffect::Replace(2, Some(self.any(2))), None, None),
+			0x08 | 0x09 => (StackEffect::Replace(3, Some(self.any(3))), None, None),
+			0x15 | 0x19 => (StackEffect::Replace(1, Some(self.any(1))), None, None),
+			// SHA3
+			0x20 => (StackEffect::Replace(2, Some(self.any(2) || self.range_tainted(stack, 0))), None, None),
+			// CALLDATALOAD, CALLDATASIZE
+			0x35 => (StackEffect::Replace(1, Some(true)), None, None),
+			0x36 => (StackEffect::Replace(0, Some(true)), None, None),
+			// CALLDATACOPY
+			0x37 => (StackEffect::Replace(3, None), copy(0, 2, true), None),
+			// CODECOPY, RETURNDATACOPY
+			0x39 | 0x3e => (StackEffect::Replace(3, None), copy(0, 2, false), None),
+			// EXTCODECOPY
+			0x3c => (StackEffect::Replace(4, None), copy(1, 3, false), None),
+			// Environment.
+			0x30 | 0x32..=0x34 | 0x38 | 0x3a | 0x3d | 0x41..=0x48 | 0x58..=0x5a =>
+				(StackEffect::Replace(0, Some(false)), None, None),
+			0x31 | 0x3b | 0x3f | 0x40 | 0x54 => (StackEffect::Replace(1, Some(false)), None, None),
+			// POP, JUMP, JUMPI
+			0x50 | 0x56 => (StackEffect::Replace(1, None), None, None),
+			0x57 => (StackEffect::Replace(2, None), None, None),
+			// MLOAD
+			0x51 => {
+				let tainted = stack.peek(0).ok()
+					.filter(|offset| *offset <= U256::from(usize::MAX))
+					.map_or(false, |offset| self.is_memory_tainted(offset.as_usize(), 32));
+				(StackEffect::Replace(1, Some(tainted)), None, None)
+			},
+			// MSTORE, MSTORE8
+			0x52 | 0x53 => {
+				let len = if opcode == Opcode::MSTORE { 32 } else { 1 };
+				let memory = stack.peek(0).ok()
+					.filter(|offset| *offset <= U256::from(usize::MAX))
+					.map(|offset| (offset.as_usize(), len, self.peek(1)));
+				(StackEffect::Replace(2, None), memory, None)
+			},
+			// SSTORE
+			0x55 => (StackEffect::Replace(2, None), None, self.flow(stack, opcode, position, 2, None)),
+			0x5b => (StackEffect::Replace(0, None), None, None),
+			// MCOPY
+			0x5e => (StackEffect::Replace(3, None), copy(0, 2, self.range_tainted(stack, 1)), None),
+			0x60..=0x7f => (StackEffect::Replace(0, Some(false)), None, None),
+			0x80..=0x8f => (StackEffect::Dup(usize::from(opcode.as_u8() - 0x80)), None, None),
+			0x90..=0x9f => (StackEffect::Swap(usize::from(opcode.as_u8() - 0x90 + 1)), None, None),
+			// LOGn
+			0xa0..=0xa4 => (StackEffect::Replace(usize::from(opcode.as_u8() - 0xa0 + 2), None), None, None),
+			// CREATE, CREATE2
+			0xf0 => (StackEffect::Replace(3, Some(false)), None, self.flow(stack, opcode, position, 3, Some(1))),
+			0xf5 => (StackEffect::Replace(4, Some(false)), None, self.flow(stack, opcode, position, 4, Some(1))),
+			// CALL, CALLCODE
+			0xf1 | 0xf2 => (StackEffect::Replace(7, Some(false)), copy(5, 6, false), self.flow(stack, opcode, position, 7, Some(3))),
+			// DELEGATECALL, STATICCALL
+			0xf4 | 0xfa => (StackEffect::Replace(6, Some(false)), copy(4, 5, false), self.flow(stack, opcode, position, 6, Some(2))),
+			// SUICIDE
+			0xff => (StackEffect::Replace(1, None), None, None),
+			_ => (StackEffect::Replace(0, None), None, None),
+		};
+		Transfer { stack: effect, memory, flow }
+	}
+
+	/// Apply the taint change of an opcode that ran, or trapped to be run
+	/// by the runtime.
+	pub(crate) fn apply(&mut self, transfer: Transfer, memory: &Memory) {
+		match transfer.stack {
+			StackEffect::Replace(pops, push) => {
+				self.stack.truncate(self.stack.len().saturating_sub(pops));
+				self.stack.extend(push);
+			},
+			StackEffect::Dup(index) => {
+				let tainted = self.peek(index);
+				self.stack.push(tainted);
+			},
+			StackEffect::Swap(index) => {
+				let len = self.stack.len();
+				if len > index {
+					self.stack.swap(len - index - 1, len - 1);
+				}
+			},
+		}
+
+		if let Some((start, len, tainted)) = transfer.memory {
+			let end = start.saturating_add(len);
+			if tainted {
+				// The opcode ran, so the range is within the memory.
+				if end <= memory.len() {
+					if self.memory.len() < end {
+						self.memory.resize(end, false);
+					}
+					self.memory[start..end].fill(true);
+				}
+			} else {
+				// The bytes past the end are already clean.
+				let end = end.min(self.memory.len());
+				if start < end {
+					self.memory[start..end].fill(false);
+				}
+			}
+		}
+
+		if let Some(flow) = transfer.flow {
+			self.sink.flow(flow);
+		}
+	}
+}
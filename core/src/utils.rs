@@ -4,17 +4,23 @@ use core::ops::{Rem, Div};
 use core::cmp::Ordering;
 use crate::U256;
 
+/// Sign of an `I256`.
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 #[allow(clippy::pub_enum_variant_names)]
 pub enum Sign {
+	/// Positive.
 	Plus,
+	/// Negative.
 	Minus,
+	/// Zero.
 	NoSign,
 }
 
 const SIGN_BIT_MASK: U256 = U256([0xffff_ffff_ffff_ffff, 0xffff_ffff_ffff_ffff,
 								  0xffff_ffff_ffff_ffff, 0x7fff_ffff_ffff_ffff]);
 
+/// Signed 256-bit integer, as a sign and a magnitude, converted from and to
+/// the two's complement `U256` of the EVM. Zero has no sign.
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub struct I256(pub Sign, pub U256);
 
@@ -23,6 +29,71 @@ impl I256 {
 	pub const fn zero() -> I256 { I256(Sign::NoSign, U256::zero()) }
 	/// Minimum value of I256.
 	pub fn min_value() -> I256 { I256(Sign::Minus, (U256::max_value() & SIGN_BIT_MASK) + U256::from(1_u64)) }
+	/// Maximum value of I256.
+	pub const fn max_value() -> I256 { I256(Sign::Plus, SIGN_BIT_MASK) }
+
+	/// Value of the given sign and magnitude, `None` if out of range.
+	fn from_parts(negative: bool, magnitude: U256) -> Option<I256> {
+		if magnitude == U256::zero() {
+			Some(I256::zero())
+		} else if negative {
+			(magnitude <= I256::min_value().1).then(|| I256(Sign::Minus, magnitude))
+		} else {
+			(magnitude <= SIGN_BIT_MASK).then(|| I256(Sign::Plus, magnitude))
+		}
+	}
+
+	/// Whether the value is negative.
+	pub fn is_negative(&self) -> bool {
+		self.0 == Sign::Minus
+	}
+
+	/// `-self`, `None` for the minimum value.
+	pub fn checked_neg(self) -> Option<I256> {
+		I256::from_parts(!self.is_negative(), self.1)
+	}
+
+	/// Sum of `self` and a value of the given sign and magnitude.
+	fn checked_add_parts(self, negative: bool, magnitude: U256) -> Option<I256> {
+		if self.is_negative() == negative {
+			I256::from_parts(negative, self.1.checked_add(magnitude)?)
+		} else if self.1 >= magnitude {
+			I256::from_parts(self.is_negative(), self.1 - magnitude)
+		} else {
+			I256::from_parts(negative, magnitude - self.1)
+		}
+	}
+
+	/// `self + other`, `None` on overflow.
+	pub fn checked_add(self, other: I256) -> Option<I256> {
+		self.checked_add_parts(other.is_negative(), other.1)
+	}
+
+	/// `self - other`, `None` on overflow.
+	pub fn checked_sub(self, other: I256) -> Option<I256> {
+		self.checked_add_parts(!other.is_negative(), other.1)
+	}
+
+	/// `self * other`, `None` on overflow.
+	pub fn checked_mul(self, other: I256) -> Option<I256> {
+		I256::from_parts(self.is_negative() != other.is_negative(), self.1.checked_mul(other.1)?)
+	}
+
+	/// `self / other` rounded towards zero, `None` for a zero divisor or the
+	/// minimum value divided by `-1`. `SDIV` instead yields zero and the
+	/// minimum value in these cases.
+	pub fn checked_div(self, other: I256) -> Option<I256> {
+		if other == I256::zero() || (self == I256::min_value() && other == I256(Sign::Minus, U256::one())) {
+			return None
+		}
+		Some(self / other)
+	}
+
+	/// Remainder of `self / other`, of the sign of `self`, `None` for a zero
+	/// divisor.
+	pub fn checked_rem(self, other: I256) -> Option<I256> {
+		(other != I256::zero()).then(|| self % other)
+	}
 }
 
 impl Ord for I256 {
@@ -86,7 +157,9 @@ impl Div for I256 {
 			return I256::min_value();
 		}
 
-		let d = (self.1 / other.1) & SIGN_BIT_MASK;
+		// At most the magnitude of the minimum value, reached by dividing it
+		// by one.
+		let d = self.1 / other.1;
 
 		if d == U256::zero() {
 			return I256::zero();
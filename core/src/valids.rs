@@ -1,7 +1,7 @@
 use alloc::{vec, vec::Vec};
 
 /// Mapping of valid jump destination from code.
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
 #[cfg_attr(feature = "with-codec", derive(codec::Encode, codec::Decode))]
 #[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Valids{
@@ -9,6 +9,12 @@ pub struct Valids{
 	data: Vec<u8>
 }
 
+impl From<Vec<u8>> for Valids {
+	fn from(valids: Vec<u8>) -> Self {
+		Self::new(valids)
+	}
+}
+
 impl Valids {
 	/// Create a new valid mapping from given code bytes.
 	#[must_use]
@@ -33,6 +39,7 @@ impl Valids {
 		(byte & bit_test) == bit_test
 	}
 
+	/// Compute the valid mapping of the given code, in the format of `new`.
 	#[must_use]
 	pub fn compute(code: &[u8]) -> Vec<u8> {
 		let valids_bytes_len = (code.len() / 8) + 1;
@@ -0,0 +1,54 @@
+//! Representation of the stack values.
+
+use crate::{Capture, ExitError, ExitReason, Machine, Memory, Opcode, Stack, Trap, U256};
+
+/// Value held by a stack slot.
+///
+/// Symbolic or concolic engines can run `Machine` on their own
+/// representation, keeping the control flow and the stack checks of the
+/// interpreter. The arithmetic goes through `apply`, while jump destinations,
+/// memory offsets and stored values need concrete values.
+pub trait Word: Clone {
+	/// Word of a concrete value.
+	fn from_u256(value: U256) -> Self;
+	/// Concrete value of the word, `None` if it is not known.
+	fn to_u256(&self) -> Option<U256>;
+
+	/// Result of an arithmetic, comparison or bitwise opcode on `args`, the
+	/// top of the stack first. `None` fails the opcode with `NotSupported`.
+	///
+	/// The default evaluates the opcode on the concrete values of `args`.
+	fn apply(opcode: Opcode, args: &[Self]) -> Option<Self> {
+		let mut values = [U256::zero(); 3];
+		for (value, arg) in values.iter_mut().zip(args) {
+			*value = arg.to_u256()?;
+		}
+		crate::eval::apply(opcode, values.get(..args.len())?).map(Self::from_u256)
+	}
+
+	/// Run `machine`, see `Machine::run`.
+	///
+	/// The default interprets one opcode at a time. `U256` also runs
+	/// superinstructions, compiled code and taint tracking.
+	fn run<F>(machine: &mut Machine<Self>, max_steps: u64, pre_validate: F) -> (u64, Capture<ExitReason, Trap>)
+		where F: FnMut(Opcode, usize, &Stack<Self>, &Memory) -> Result<(), ExitError>
+	{
+		machine.interpret(max_steps, pre_validate)
+	}
+}
+
+impl Word for U256 {
+	fn from_u256(value: U256) -> Self {
+		value
+	}
+
+	fn to_u256(&self) -> Option<U256> {
+		Some(*self)
+	}
+
+	fn run<F>(machine: &mut Machine, max_steps: u64, pre_validate: F) -> (u64, Capture<ExitReason, Trap>)
+		where F: FnMut(Opcode, usize, &Stack, &Memory) -> Result<(), ExitError>
+	{
+		machine.interpret_u256(max_steps, pre_validate)
+	}
+}
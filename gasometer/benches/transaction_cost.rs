@@ -0,0 +1,25 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use evm_gasometer::{call_transaction_cost, create_transaction_cost};
+
+/// Calldata of the given size with roughly one zero byte out of three.
+fn calldata(size: usize) -> Vec<u8> {
+	(0..size).map(|i| if i % 3 == 0 { 0 } else { (i % 251) as u8 + 1 }).collect()
+}
+
+fn transaction_cost(c: &mut Criterion) {
+	let mut group = c.benchmark_group("transaction_cost");
+	for size in [1024, 32 * 1024, 128 * 1024] {
+		let data = calldata(size);
+		group.throughput(Throughput::Bytes(size as u64));
+		group.bench_with_input(BenchmarkId::new("call", size), &data, |b, data| {
+			b.iter(|| call_transaction_cost(black_box(data)));
+		});
+		group.bench_with_input(BenchmarkId::new("create", size), &data, |b, data| {
+			b.iter(|| create_transaction_cost(black_box(data)));
+		});
+	}
+	group.finish();
+}
+
+criterion_group!(benches, transaction_cost);
+criterion_main!(benches);
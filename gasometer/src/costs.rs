@@ -1,9 +1,9 @@
 use crate::consts::*;
 use evm_core::{ExitError, H256, U256};
-use evm_runtime::CONFIG;
+use evm_runtime::Spec;
 
-pub fn call_extra_check(gas: U256, after_gas: u64) -> Result<(), ExitError> {
-	if CONFIG.err_on_call_with_more_gas && U256::from(after_gas) < gas {
+pub fn call_extra_check<S: Spec>(gas: U256, after_gas: u64) -> Result<(), ExitError> {
+	if S::CONFIG.err_on_call_with_more_gas && U256::from(after_gas) < gas {
 		Err(ExitError::OutOfGas)
 	} else {
 		Ok(())
@@ -18,28 +18,28 @@ pub fn suicide_refund(already_removed: bool) -> i64 {
 	}
 }
 
-pub fn sstore_refund(original: H256, current: H256, new: H256) -> i64 {
-	if CONFIG.sstore_gas_metering {
+pub fn sstore_refund<S: Spec>(original: H256, current: H256, new: H256) -> i64 {
+	if S::CONFIG.sstore_gas_metering {
 		if current == new {
 			0
 		} else {
 			if original == current && new == H256::default() {
-				CONFIG.refund_sstore_clears
+				S::CONFIG.refund_sstore_clears
 			} else {
 				let mut refund = 0;
 				if original != H256::default() {
 					if current == H256::default() {
-						refund -= CONFIG.refund_sstore_clears;
+						refund -= S::CONFIG.refund_sstore_clears;
 					} else if new == H256::default() {
-						refund += CONFIG.refund_sstore_clears;
+						refund += S::CONFIG.refund_sstore_clears;
 					}
 				}
 
 				if original == new {
 					if original == H256::default() {
-						refund += (CONFIG.gas_sstore_set - CONFIG.gas_sload) as i64;
+						refund += (S::CONFIG.gas_sstore_set - S::CONFIG.gas_sload) as i64;
 					} else {
-						refund += (CONFIG.gas_sstore_reset - CONFIG.gas_sload) as i64;
+						refund += (S::CONFIG.gas_sstore_reset - S::CONFIG.gas_sload) as i64;
 					}
 				}
 
@@ -48,7 +48,7 @@ pub fn sstore_refund(original: H256, current: H256, new: H256) -> i64 {
 		}
 	} else {
 		if current != H256::default() && new == H256::default() {
-			CONFIG.refund_sstore_clears
+			S::CONFIG.refund_sstore_clears
 		} else {
 			0
 		}
@@ -71,13 +71,13 @@ pub fn create2_cost(len: U256) -> Result<u64, ExitError> {
 	Ok(gas.as_u64())
 }
 
-pub fn exp_cost(power: U256) -> Result<u64, ExitError> {
+pub fn exp_cost<S: Spec>(power: U256) -> Result<u64, ExitError> {
 	if power == U256::zero() {
 		Ok(G_EXP)
 	} else {
 		let gas = U256::from(G_EXP)
 			.checked_add(
-				U256::from(CONFIG.gas_expbyte)
+				U256::from(S::CONFIG.gas_expbyte)
 					.checked_mul(U256::from(crate::utils::log2floor(power) / 8 + 1))
 					.ok_or(ExitError::OutOfGas)?
 			)
@@ -112,11 +112,30 @@ pub fn verylowcopy_cost(len: U256) -> Result<u64, ExitError> {
 	Ok(gas.as_u64())
 }
 
-pub fn extcodecopy_cost(len: U256) -> Result<u64, ExitError> {
+/// Cost of accessing an account, `regular` without EIP-2929.
+pub fn account_access_cost<S: Spec>(is_cold: bool, regular: u64) -> u64 {
+	if !S::CONFIG.increase_state_access_gas {
+		regular
+	} else if is_cold {
+		S::CONFIG.gas_account_access_cold
+	} else {
+		S::CONFIG.gas_storage_read_warm
+	}
+}
+
+pub fn sload_cost<S: Spec>(is_cold: bool) -> u64 {
+	if S::CONFIG.increase_state_access_gas && is_cold {
+		S::CONFIG.gas_sload_cold
+	} else {
+		S::CONFIG.gas_sload
+	}
+}
+
+pub fn extcodecopy_cost<S: Spec>(len: U256, is_cold: bool) -> Result<u64, ExitError> {
 	let wordd = len / U256::from(32);
 	let wordr = len % U256::from(32);
 
-	let gas = U256::from(CONFIG.gas_ext_code).checked_add(
+	let gas = U256::from(account_access_cost::<S>(is_cold, S::CONFIG.gas_ext_code)).checked_add(
 		U256::from(G_COPY).checked_mul(
 			if wordr == U256::zero() {
 				wordd
@@ -168,38 +187,40 @@ pub fn sha3_cost(len: U256) -> Result<u64, ExitError> {
 	Ok(gas.as_u64())
 }
 
-pub fn sstore_cost(original: H256, current: H256, new: H256, gas: u64) -> Result<u64, ExitError> {
-	if CONFIG.sstore_gas_metering {
-		if CONFIG.sstore_revert_under_stipend {
-			if gas < CONFIG.call_stipend {
+pub fn sstore_cost<S: Spec>(original: H256, current: H256, new: H256, gas: u64, is_cold: bool) -> Result<u64, ExitError> {
+	// EIP-2929 charges a cold slot once more, on top of the warm cost.
+	let cold = if S::CONFIG.increase_state_access_gas && is_cold { S::CONFIG.gas_sload_cold } else { 0 };
+	if S::CONFIG.sstore_gas_metering {
+		if S::CONFIG.sstore_revert_under_stipend {
+			if gas < S::CONFIG.call_stipend {
 				return Err(ExitError::OutOfGas)
 			}
 		}
 
-		Ok(if new == current {
-			CONFIG.gas_sload
+		Ok(cold + if new == current {
+			S::CONFIG.gas_sload
 		} else {
 			if original == current {
 				if original == H256::zero() {
-					CONFIG.gas_sstore_set
+					S::CONFIG.gas_sstore_set
 				} else {
-					CONFIG.gas_sstore_reset
+					S::CONFIG.gas_sstore_reset
 				}
 			} else {
-				CONFIG.gas_sload
+				S::CONFIG.gas_sload
 			}
 		})
 	} else {
-		Ok(if current == H256::zero() && new != H256::zero() {
-			CONFIG.gas_sstore_set
+		Ok(cold + if current == H256::zero() && new != H256::zero() {
+			S::CONFIG.gas_sstore_set
 		} else {
-			CONFIG.gas_sstore_reset
+			S::CONFIG.gas_sstore_reset
 		})
 	}
 }
 
-pub fn suicide_cost(value: U256, target_exists: bool) -> u64 {
-	let eip161 = !CONFIG.empty_considered_exists;
+pub fn suicide_cost<S: Spec>(value: U256, target_exists: bool, is_cold: bool) -> u64 {
+	let eip161 = !S::CONFIG.empty_considered_exists;
 	let should_charge_topup = if eip161 {
 		value != U256::zero() && !target_exists
 	} else {
@@ -207,24 +228,27 @@ pub fn suicide_cost(value: U256, target_exists: bool) -> u64 {
 	};
 
 	let suicide_gas_topup = if should_charge_topup {
-		CONFIG.gas_suicide_new_account
+		S::CONFIG.gas_suicide_new_account
 	} else {
 		0
 	};
 
-	CONFIG.gas_suicide + suicide_gas_topup
+	let cold = if S::CONFIG.increase_state_access_gas && is_cold { S::CONFIG.gas_account_access_cold } else { 0 };
+
+	S::CONFIG.gas_suicide + suicide_gas_topup + cold
 }
 
-pub fn call_cost(
+pub fn call_cost<S: Spec>(
 	value: U256,
 	is_call_or_callcode: bool,
 	is_call_or_staticcall: bool,
 	new_account: bool,
+	is_cold: bool,
 ) -> u64 {
 	let transfers_value = value != U256::default();
-	CONFIG.gas_call +
+	account_access_cost::<S>(is_cold, S::CONFIG.gas_call) +
 		xfer_cost(is_call_or_callcode, transfers_value) +
-		new_cost(is_call_or_staticcall, new_account, transfers_value)
+		new_cost::<S>(is_call_or_staticcall, new_account, transfers_value)
 }
 
 fn xfer_cost(
@@ -238,12 +262,12 @@ fn xfer_cost(
 	}
 }
 
-fn new_cost(
+fn new_cost<S: Spec>(
 	is_call_or_staticcall: bool,
 	new_account: bool,
 	transfers_value: bool,
 ) -> u64 {
-	let eip161 = !CONFIG.empty_considered_exists;
+	let eip161 = !S::CONFIG.empty_considered_exists;
 	if is_call_or_staticcall {
 		if eip161 {
 			if transfers_value && new_account {
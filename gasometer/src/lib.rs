@@ -17,9 +17,12 @@ mod memory;
 mod utils;
 
 use evm_core::{ExitError, Opcode, Stack, H160, H256, U256};
-use evm_runtime::{CONFIG, Handler};
+use core::marker::PhantomData;
+use evm_runtime::{Handler, Istanbul, Spec};
 use serde::{Serialize, Deserialize};
 
+pub use crate::memory::memory_expansion_cost;
+
 macro_rules! try_or_fail {
 	( $inner:expr, $e:expr ) => (
 		match $e {
@@ -32,15 +35,20 @@ macro_rules! try_or_fail {
 	)
 }
 
-/// EVM gasometer.
+/// EVM gasometer, for the hard fork `S`.
 #[derive(Clone)]
 #[derive(Serialize, Deserialize)]
-pub struct Gasometer {
+#[serde(bound = "")]
+pub struct Gasometer<S = Istanbul> {
 	gas_limit: u64,
-	inner: Result<Inner, ExitError>
+	inner: Result<Inner, ExitError>,
+	#[serde(default)]
+	breakdown: Option<GasBreakdown>,
+	#[serde(skip)]
+	spec: PhantomData<S>,
 }
 
-impl Gasometer {
+impl<S: Spec> Gasometer<S> {
 	/// Create a new gasometer with given gas limit.
 	pub fn new(gas_limit: u64) -> Self {
 		Self {
@@ -50,6 +58,29 @@ impl Gasometer {
 				used_gas: 0,
 				refunded_gas: 0,
 			}),
+			breakdown: None,
+			spec: PhantomData,
+		}
+	}
+
+	/// Accumulate the recorded gas by category, see `breakdown`.
+	#[must_use]
+	pub fn with_breakdown(mut self) -> Self {
+		self.breakdown = Some(GasBreakdown::default());
+		self
+	}
+
+	/// Recorded gas by category, if enabled with `with_breakdown`.
+	///
+	/// A failed gasometer keeps the breakdown of the gas recorded before the
+	/// failure, which then no longer adds up to the used gas.
+	pub const fn breakdown(&self) -> Option<&GasBreakdown> {
+		self.breakdown.as_ref()
+	}
+
+	fn categorize(&mut self, category: GasCategory, cost: u64) {
+		if let Some(breakdown) = &mut self.breakdown {
+			breakdown.add(category, cost);
 		}
 	}
 
@@ -88,15 +119,26 @@ impl Gasometer {
 		}
 	}
 
+	/// Refund cap, that is the maximum refund applicable to the total used
+	/// gas.
+	pub fn refund_cap(&self) -> u64 {
+		self.total_used_gas() / S::CONFIG.max_refund_quotient
+	}
+
+	/// Effective refund, that is the refunded gas limited by the refund cap.
+	pub fn effective_refund(&self) -> u64 {
+		let rg = self.refunded_gas();
+		if rg <= 0 {
+			0
+		} else {
+			core::cmp::min(self.refund_cap(), rg as u64)
+		}
+	}
+
 	/// Used gas.
 	pub fn used_gas(&self) -> u64 {
 		match self.inner.as_ref() {
-			Ok(inner) => {
-				let mg = memory::memory_gas(inner.memory_cost).expect("Checked via record");
-				let tug = inner.used_gas + mg;
-				let rg = inner.refunded_gas;
-				tug - core::cmp::min(tug / 2, rg as u64)
-			},
+			Ok(_) => self.total_used_gas() - self.effective_refund(),
 			Err(_) => 0,
 		}
 	}
@@ -107,10 +149,19 @@ impl Gasometer {
 		ExitError::OutOfGas
 	}
 
-	/// Record an explicit cost.
+	/// Record an explicit cost, as computation.
 	pub fn record_cost(
 		&mut self,
 		cost: u64
+	) -> Result<(), ExitError> {
+		self.record_cost_in(cost, GasCategory::Compute)
+	}
+
+	/// Record an explicit cost in the given category of the breakdown.
+	pub fn record_cost_in(
+		&mut self,
+		cost: u64,
+		category: GasCategory,
 	) -> Result<(), ExitError> {
 		let all_gas_cost = self.total_used_gas() + cost;
 		if self.gas_limit < all_gas_cost {
@@ -119,6 +170,7 @@ impl Gasometer {
 		}
 
 		self.inner_mut()?.used_gas += cost;
+		self.categorize(category, cost);
 		Ok(())
 	}
 
@@ -128,6 +180,9 @@ impl Gasometer {
 		refund: i64,
 	) -> Result<(), ExitError> {
 		self.inner_mut()?.refunded_gas += refund;
+		if let Some(breakdown) = &mut self.breakdown {
+			breakdown.refund += refund;
+		}
 		Ok(())
 	}
 
@@ -137,7 +192,7 @@ impl Gasometer {
 		len: usize
 	) -> Result<(), ExitError> {
 		let cost = len as u64 * consts::G_CODEDEPOSIT;
-		self.record_cost(cost)
+		self.record_cost_in(cost, GasCategory::CodeDeposit)
 	}
 
 	/// Record opcode gas cost.
@@ -147,14 +202,15 @@ impl Gasometer {
 		memory: Option<MemoryCost>,
 	) -> Result<(), ExitError> {
 		let gas = self.gas();
+		let category = GasCategory::of(&cost);
 
 		let memory_cost = match memory {
 			Some(memory) => try_or_fail!(self.inner, self.inner_mut()?.memory_cost(memory)),
 			None => self.inner_mut()?.memory_cost,
 		};
 		let memory_gas = try_or_fail!(self.inner, memory::memory_gas(memory_cost));
-		let gas_cost = try_or_fail!(self.inner, self.inner_mut()?.gas_cost(cost.clone(), gas));
-		let gas_refund = self.inner_mut()?.gas_refund(cost.clone());
+		let gas_cost = try_or_fail!(self.inner, self.inner_mut()?.gas_cost::<S>(cost.clone(), gas));
+		let gas_refund = self.inner_mut()?.gas_refund::<S>(cost.clone());
 		let used_gas = self.inner_mut()?.used_gas;
 
 		let all_gas_cost = memory_gas + used_gas + gas_cost;
@@ -164,41 +220,69 @@ impl Gasometer {
 		}
 
 		let after_gas = self.gas_limit - all_gas_cost;
-		try_or_fail!(self.inner, self.inner_mut()?.extra_check(cost, after_gas));
+		try_or_fail!(self.inner, self.inner_mut()?.extra_check::<S>(cost, after_gas));
 
+		let previous_memory_gas = memory::memory_gas(self.inner_mut()?.memory_cost)?;
 		self.inner_mut()?.used_gas += gas_cost;
 		self.inner_mut()?.memory_cost = memory_cost;
 		self.inner_mut()?.refunded_gas += gas_refund;
 
+		self.categorize(category, gas_cost);
+		self.categorize(GasCategory::Memory, memory_gas - previous_memory_gas);
+		if let Some(breakdown) = &mut self.breakdown {
+			breakdown.refund += gas_refund;
+		}
+
 		Ok(())
 	}
 
-	/// Record opcode stipend.
+	/// Record opcode stipend, or the gas returned by a subframe.
 	pub fn record_stipend(
 		&mut self,
 		stipend: u64,
 	) -> Result<(), ExitError> {
 		self.inner_mut()?.used_gas -= stipend;
+		if let Some(breakdown) = &mut self.breakdown {
+			breakdown.calls = breakdown.calls.saturating_sub(stipend);
+		}
 		Ok(())
 	}
 
+	/// Move the gas used by a subframe out of `calls`, into the categories
+	/// of its breakdown. Its refund is left out, as it is recorded with
+	/// `record_refund` when the subframe succeeds.
+	pub fn merge_breakdown(&mut self, breakdown: Option<&GasBreakdown>) {
+		if let (Some(parent), Some(child)) = (&mut self.breakdown, breakdown) {
+			parent.calls = parent.calls.saturating_sub(child.total());
+			parent.intrinsic += child.intrinsic;
+			parent.compute += child.compute;
+			parent.memory += child.memory;
+			parent.storage += child.storage;
+			parent.calls += child.calls;
+			parent.code_deposit += child.code_deposit;
+		}
+	}
+
+	/// Save the gas used, memory cost and refund so far, for instance to
+	/// charge gas tentatively and roll it back with `revert_to` on failure.
+	pub fn snapshot(&self) -> GasSnapshot {
+		GasSnapshot(self.inner.clone(), self.breakdown.clone())
+	}
+
+	/// Restore a snapshot taken from this gasometer, discarding the gas
+	/// recorded since. A gasometer failed since the snapshot is restored as
+	/// well.
+	pub fn revert_to(&mut self, snapshot: GasSnapshot) {
+		self.inner = snapshot.0;
+		self.breakdown = snapshot.1;
+	}
+
 	/// Record transaction cost.
 	pub fn record_transaction(
 		&mut self,
 		cost: TransactionCost,
 	) -> Result<(), ExitError> {
-		let gas_cost = match cost {
-			TransactionCost::Call { zero_data_len, non_zero_data_len } => {
-				CONFIG.gas_transaction_call +
-					zero_data_len as u64 * CONFIG.gas_transaction_zero_data +
-					non_zero_data_len as u64  * CONFIG.gas_transaction_non_zero_data
-			},
-			TransactionCost::Create { zero_data_len, non_zero_data_len } => {
-				CONFIG.gas_transaction_create +
-					zero_data_len as u64 * CONFIG.gas_transaction_zero_data +
-					non_zero_data_len as u64 * CONFIG.gas_transaction_non_zero_data
-			},
-		};
+		let gas_cost = intrinsic_gas::<S>(&cost);
 
 		if self.gas() < gas_cost {
 			self.inner = Err(ExitError::OutOfGas);
@@ -206,15 +290,140 @@ impl Gasometer {
 		}
 
 		self.inner_mut()?.used_gas += gas_cost;
+		self.categorize(GasCategory::Intrinsic, gas_cost);
 		Ok(())
 	}
 }
 
+/// State of a gasometer at some point, see `Gasometer::snapshot`.
+#[derive(Clone, Debug)]
+pub struct GasSnapshot(Result<Inner, ExitError>, Option<GasBreakdown>);
+
+/// Category of recorded gas, see `GasBreakdown`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum GasCategory {
+	/// Intrinsic gas of the transaction, including its access list.
+	Intrinsic,
+	/// Opcodes other than storage accesses and calls, and precompiles.
+	Compute,
+	/// Memory expansion.
+	Memory,
+	/// `SLOAD` and `SSTORE`.
+	Storage,
+	/// Calls, creations and self-destructs, with the gas forwarded to their
+	/// frames.
+	Calls,
+	/// Code deposit of creations.
+	CodeDeposit,
+}
+
+impl GasCategory {
+	/// Category of an opcode cost.
+	pub const fn of(cost: &GasCost) -> Self {
+		match cost {
+			GasCost::SStore { .. } | GasCost::SLoad { .. } => Self::Storage,
+			GasCost::Call { .. } | GasCost::CallCode { .. } | GasCost::DelegateCall { .. } |
+			GasCost::StaticCall { .. } | GasCost::Suicide { .. } | GasCost::Create |
+			GasCost::Create2 { .. } => Self::Calls,
+			_ => Self::Compute,
+		}
+	}
+}
+
+/// Gas recorded by a gasometer, by category.
+///
+/// Gas forwarded to a subframe counts as `calls` until the subframe is
+/// merged with `Gasometer::merge_breakdown`, which moves the gas it used to
+/// its own categories. The categories add up to the used gas before refund.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct GasBreakdown {
+	/// Intrinsic gas of the transaction, including its access list.
+	pub intrinsic: u64,
+	/// Opcodes other than storage accesses and calls, and precompiles.
+	pub compute: u64,
+	/// Memory expansion.
+	pub memory: u64,
+	/// `SLOAD` and `SSTORE`.
+	pub storage: u64,
+	/// Calls, creations and self-destructs, with the gas forwarded to their
+	/// frames and not used by them.
+	pub calls: u64,
+	/// Code deposit of creations.
+	pub code_deposit: u64,
+	/// Refund of storage clears and self-destructs, before the refund cap.
+	pub refund: i64,
+}
+
+impl GasBreakdown {
+	/// Gas of a category.
+	pub const fn get(&self, category: GasCategory) -> u64 {
+		match category {
+			GasCategory::Intrinsic => self.intrinsic,
+			GasCategory::Compute => self.compute,
+			GasCategory::Memory => self.memory,
+			GasCategory::Storage => self.storage,
+			GasCategory::Calls => self.calls,
+			GasCategory::CodeDeposit => self.code_deposit,
+		}
+	}
+
+	/// Gas of all categories, refund excluded.
+	pub const fn total(&self) -> u64 {
+		self.intrinsic + self.compute + self.memory + self.storage + self.calls + self.code_deposit
+	}
+
+	fn add(&mut self, category: GasCategory, cost: u64) {
+		let gas = match category {
+			GasCategory::Intrinsic => &mut self.intrinsic,
+			GasCategory::Compute => &mut self.compute,
+			GasCategory::Memory => &mut self.memory,
+			GasCategory::Storage => &mut self.storage,
+			GasCategory::Calls => &mut self.calls,
+			GasCategory::CodeDeposit => &mut self.code_deposit,
+		};
+		*gas += cost;
+	}
+}
+
+/// Gas charged for a transaction before its execution starts.
+#[must_use]
+pub const fn intrinsic_gas<S: Spec>(cost: &TransactionCost) -> u64 {
+	match *cost {
+		TransactionCost::Call { zero_data_len, non_zero_data_len } => {
+			S::CONFIG.gas_transaction_call +
+				zero_data_len as u64 * S::CONFIG.gas_transaction_zero_data +
+				non_zero_data_len as u64  * S::CONFIG.gas_transaction_non_zero_data
+		},
+		TransactionCost::Create { zero_data_len, non_zero_data_len } => {
+			S::CONFIG.gas_transaction_create +
+				zero_data_len as u64 * S::CONFIG.gas_transaction_zero_data +
+				non_zero_data_len as u64 * S::CONFIG.gas_transaction_non_zero_data
+		},
+	}
+}
+
+/// Count zero bytes of transaction data, a `u64` word at a time.
+fn zero_bytes(data: &[u8]) -> usize {
+	const LOW_BITS: u64 = 0x7f7f_7f7f_7f7f_7f7f;
+
+	let chunks = data.chunks_exact(8);
+	let tail = chunks.remainder().iter().filter(|v| **v == 0).count();
+	chunks.fold(tail, |count, chunk| {
+		let mut word = [0_u8; 8];
+		word.copy_from_slice(chunk);
+		let word = u64::from_ne_bytes(word);
+		// High bit of each byte is set iff the byte is zero.
+		let zero = !(((word & LOW_BITS) + LOW_BITS) | word | LOW_BITS);
+		count + zero.count_ones() as usize
+	})
+}
+
 /// Calculate the call transaction cost.
 pub fn call_transaction_cost(
 	data: &[u8]
 ) -> TransactionCost {
-	let zero_data_len = data.iter().filter(|v| **v == 0).count();
+	let zero_data_len = zero_bytes(data);
 	let non_zero_data_len = data.len() - zero_data_len;
 
 	TransactionCost::Call { zero_data_len, non_zero_data_len }
@@ -224,7 +433,7 @@ pub fn call_transaction_cost(
 pub fn create_transaction_cost(
 	data: &[u8]
 ) -> TransactionCost {
-	let zero_data_len = data.iter().filter(|v| **v == 0).count();
+	let zero_data_len = zero_bytes(data);
 	let non_zero_data_len = data.len() - zero_data_len;
 
 	TransactionCost::Create { zero_data_len, non_zero_data_len }
@@ -354,53 +563,92 @@ pub fn static_opcode_cost(
 	TABLE[opcode.as_usize()]
 }
 
-/// Calculate the opcode cost.
-pub fn dynamic_opcode_cost<H: Handler>(
+/// State entry accessed by an opcode, warm for the rest of the transaction
+/// with EIP-2929.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StorageTarget {
+	/// No account nor storage slot is accessed.
+	None,
+	/// The account at the address is accessed.
+	Address(H160),
+	/// The storage slot of the account is accessed.
+	Slot(H160, U256),
+}
+
+/// State entry accessed by `opcode` when run by the account at `address`.
+pub fn storage_target(address: H160, opcode: Opcode, stack: &Stack) -> StorageTarget {
+	let peek = |n| stack.peek(n).ok();
+	let target = match opcode {
+		Opcode::EXTCODESIZE | Opcode::EXTCODECOPY | Opcode::EXTCODEHASH | Opcode::BALANCE |
+		Opcode::SUICIDE => peek(0).map(|target| StorageTarget::Address(target.into())),
+		Opcode::CALL | Opcode::CALLCODE | Opcode::DELEGATECALL | Opcode::STATICCALL =>
+			peek(1).map(|target| StorageTarget::Address(target.into())),
+		Opcode::SLOAD | Opcode::SSTORE => peek(0).map(|index| StorageTarget::Slot(address, index)),
+		_ => None,
+	};
+	target.unwrap_or(StorageTarget::None)
+}
+
+/// Calculate the opcode cost for the hard fork `S`.
+pub fn dynamic_opcode_cost<S: Spec, H: Handler>(
 	address: H160,
 	opcode: Opcode,
 	stack: &Stack,
 	is_static: bool,
 	handler: &H
 ) -> Result<(GasCost, Option<MemoryCost>), ExitError> {
+	let is_cold = |address: H160, index: Option<U256>| {
+		S::CONFIG.increase_state_access_gas && handler.is_cold(address, index)
+	};
+
 	let gas_cost = match opcode {
 		Opcode::RETURN => GasCost::Zero,
 
 		Opcode::MLOAD | Opcode::MSTORE | Opcode::MSTORE8 => GasCost::VeryLow,
 
-		Opcode::REVERT if CONFIG.has_revert => GasCost::Zero,
-		Opcode::REVERT => GasCost::Invalid,
+		Opcode::REVERT if S::CONFIG.has_revert => GasCost::Zero,
+		Opcode::REVERT => GasCost::Invalid(opcode),
 
-		Opcode::CHAINID if CONFIG.has_chain_id => GasCost::Base,
-		Opcode::CHAINID => GasCost::Invalid,
+		Opcode::CHAINID if S::CONFIG.has_chain_id => GasCost::Base,
+		Opcode::CHAINID => GasCost::Invalid(opcode),
 
-		Opcode::SHL | Opcode::SHR | Opcode::SAR if CONFIG.has_bitwise_shifting =>
+		Opcode::SHL | Opcode::SHR | Opcode::SAR if S::CONFIG.has_bitwise_shifting =>
 			GasCost::VeryLow,
-		Opcode::SHL | Opcode::SHR | Opcode::SAR => GasCost::Invalid,
+		Opcode::SHL | Opcode::SHR | Opcode::SAR => GasCost::Invalid(opcode),
 
-		Opcode::SELFBALANCE if CONFIG.has_self_balance => GasCost::Low,
-		Opcode::SELFBALANCE => GasCost::Invalid,
+		Opcode::SELFBALANCE if S::CONFIG.has_self_balance => GasCost::Low,
+		Opcode::SELFBALANCE => GasCost::Invalid(opcode),
 
-		Opcode::EXTCODESIZE => GasCost::ExtCodeSize,
-		Opcode::BALANCE => GasCost::Balance,
+		Opcode::EXTCODESIZE => GasCost::ExtCodeSize {
+			target_is_cold: is_cold(stack.peek(0)?.into(), None),
+		},
+		Opcode::BALANCE => GasCost::Balance {
+			target_is_cold: is_cold(stack.peek(0)?.into(), None),
+		},
 		Opcode::BLOCKHASH => GasCost::BlockHash,
 
-		Opcode::EXTCODEHASH if CONFIG.has_ext_code_hash => GasCost::ExtCodeHash,
-		Opcode::EXTCODEHASH => GasCost::Invalid,
+		Opcode::EXTCODEHASH if S::CONFIG.has_ext_code_hash => GasCost::ExtCodeHash {
+			target_is_cold: is_cold(stack.peek(0)?.into(), None),
+		},
+		Opcode::EXTCODEHASH => GasCost::Invalid(opcode),
 
 		Opcode::CALLCODE => GasCost::CallCode {
 			value: stack.peek(2)?,
 			gas: stack.peek(0)?,
 			target_exists: handler.exists(stack.peek(1)?.into()),
+			target_is_cold: is_cold(stack.peek(1)?.into(), None),
 		},
 		Opcode::STATICCALL => GasCost::StaticCall {
 			gas: stack.peek(0)?,
 			target_exists: handler.exists(stack.peek(1)?.into()),
+			target_is_cold: is_cold(stack.peek(1)?.into(), None),
 		},
 		Opcode::SHA3 => GasCost::Sha3 {
 			len: stack.peek(1)?,
 		},
 		Opcode::EXTCODECOPY => GasCost::ExtCodeCopy {
 			len: stack.peek(3)?,
+			target_is_cold: is_cold(stack.peek(0)?.into(), None),
 		},
 		Opcode::CALLDATACOPY | Opcode::CODECOPY => GasCost::VeryLowCopy {
 			len: stack.peek(2)?,
@@ -408,19 +656,27 @@ pub fn dynamic_opcode_cost<H: Handler>(
 		Opcode::EXP => GasCost::Exp {
 			power: stack.peek(1)?,
 		},
-		Opcode::SLOAD => GasCost::SLoad,
+		Opcode::SLOAD => GasCost::SLoad {
+			target_is_cold: is_cold(address, Some(stack.peek(0)?)),
+		},
 
-		Opcode::DELEGATECALL if CONFIG.has_delegate_call => GasCost::DelegateCall {
+		Opcode::DELEGATECALL if S::CONFIG.has_delegate_call => GasCost::DelegateCall {
 			gas: stack.peek(0)?,
 			target_exists: handler.exists(stack.peek(1)?.into()),
+			target_is_cold: is_cold(stack.peek(1)?.into(), None),
+		},
+		Opcode::DELEGATECALL => GasCost::Invalid(opcode),
+
+		Opcode::RETURNDATASIZE if S::CONFIG.has_return_data => GasCost::Base,
+		Opcode::RETURNDATACOPY if S::CONFIG.has_return_data => GasCost::VeryLowCopy {
+			len: stack.peek(2)?,
 		},
-		Opcode::DELEGATECALL => GasCost::Invalid,
+		Opcode::RETURNDATASIZE | Opcode::RETURNDATACOPY => GasCost::Invalid(opcode),
 
-		Opcode::RETURNDATASIZE if CONFIG.has_return_data => GasCost::Base,
-		Opcode::RETURNDATACOPY if CONFIG.has_return_data => GasCost::VeryLowCopy {
+		Opcode::MCOPY if S::CONFIG.has_mcopy => GasCost::VeryLowCopy {
 			len: stack.peek(2)?,
 		},
-		Opcode::RETURNDATASIZE | Opcode::RETURNDATACOPY => GasCost::Invalid,
+		Opcode::MCOPY => GasCost::Invalid(opcode),
 
 		Opcode::SSTORE if !is_static => {
 			let index = stack.peek(0)?;
@@ -430,6 +686,7 @@ pub fn dynamic_opcode_cost<H: Handler>(
 				original: handler.original_storage(address, index).into(),
 				current: handler.storage(address, index).into(),
 				new: value.into(),
+				target_is_cold: is_cold(address, Some(index)),
 			}
 		},
 		Opcode::LOG0 if !is_static => GasCost::Log {
@@ -453,13 +710,14 @@ pub fn dynamic_opcode_cost<H: Handler>(
 			len: stack.peek(1)?,
 		},
 		Opcode::CREATE if !is_static => GasCost::Create,
-		Opcode::CREATE2 if !is_static && CONFIG.has_create2 => GasCost::Create2 {
+		Opcode::CREATE2 if !is_static && S::CONFIG.has_create2 => GasCost::Create2 {
 			len: stack.peek(2)?,
 		},
 		Opcode::SUICIDE if !is_static => GasCost::Suicide {
 			value: handler.balance(address),
 			target_exists: handler.exists(stack.peek(0)?.into()),
 			already_removed: handler.deleted(address),
+			target_is_cold: is_cold(stack.peek(0)?.into(), None),
 		},
 		Opcode::CALL
 			if !is_static ||
@@ -468,9 +726,10 @@ pub fn dynamic_opcode_cost<H: Handler>(
 				value: stack.peek(2)?,
 				gas: stack.peek(0)?,
 				target_exists: handler.exists(stack.peek(1)?.into()),
+				target_is_cold: is_cold(stack.peek(1)?.into(), None),
 			},
 
-		_ => GasCost::Invalid,
+		_ => GasCost::Invalid(opcode),
 	};
 
 	let memory_cost = match opcode {
@@ -502,6 +761,14 @@ pub fn dynamic_opcode_cost<H: Handler>(
 			len: U256::from(1),
 		}),
 
+		Opcode::MCOPY => Some(MemoryCost {
+			offset: stack.peek(0)?,
+			len: stack.peek(2)?,
+		}.join(MemoryCost {
+			offset: stack.peek(1)?,
+			len: stack.peek(2)?,
+		})),
+
 		Opcode::CREATE | Opcode::CREATE2 => Some(MemoryCost {
 			offset: stack.peek(1)?,
 			len: stack.peek(2)?,
@@ -530,7 +797,7 @@ pub fn dynamic_opcode_cost<H: Handler>(
 	Ok((gas_cost, memory_cost))
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 #[derive(Serialize, Deserialize)]
 struct Inner {
 	memory_cost: u64,
@@ -543,95 +810,77 @@ impl Inner {
 		&self,
 		memory: MemoryCost,
 	) -> Result<u64, ExitError> {
-		let from = memory.offset;
-		let len = memory.len;
-
-		if len.is_zero() {
-			return Ok(self.memory_cost)
-		}
-
-		let end = from.checked_add(len).ok_or(ExitError::OutOfGas)?;
-
-		if end > U256::from(usize::max_value()) {
-			return Err(ExitError::OutOfGas)
-		}
-		let end = end.as_usize();
-
-		let rem = end % 32;
-		let new = if rem == 0 {
-			end / 32
-		} else {
-			end / 32 + 1
-		};
-
-		Ok(core::cmp::max(self.memory_cost, new as u64))
+		Ok(core::cmp::max(self.memory_cost, memory.words()?))
 	}
 
-	fn extra_check(
+	fn extra_check<S: Spec>(
 		&self,
 		cost: GasCost,
 		after_gas: u64,
 	) -> Result<(), ExitError> {
 		match cost {
-			GasCost::Call { gas, .. } => costs::call_extra_check(gas, after_gas),
-			GasCost::CallCode { gas, .. } => costs::call_extra_check(gas, after_gas),
-			GasCost::DelegateCall { gas, .. } => costs::call_extra_check(gas, after_gas),
-			GasCost::StaticCall { gas, .. } => costs::call_extra_check(gas, after_gas),
+			GasCost::Call { gas, .. } => costs::call_extra_check::<S>(gas, after_gas),
+			GasCost::CallCode { gas, .. } => costs::call_extra_check::<S>(gas, after_gas),
+			GasCost::DelegateCall { gas, .. } => costs::call_extra_check::<S>(gas, after_gas),
+			GasCost::StaticCall { gas, .. } => costs::call_extra_check::<S>(gas, after_gas),
 			_ => Ok(()),
 		}
 	}
 
 	/// Returns the gas cost numerical value.
-	fn gas_cost(
+	fn gas_cost<S: Spec>(
 		&self,
 		cost: GasCost,
 		gas: u64,
 	) -> Result<u64, ExitError> {
 		Ok(match cost {
-			GasCost::Call { value, target_exists, .. } =>
-				costs::call_cost(value, true, true, !target_exists),
-			GasCost::CallCode { value, target_exists, .. } =>
-				costs::call_cost(value, true, false, !target_exists),
-			GasCost::DelegateCall { target_exists, .. } =>
-				costs::call_cost(U256::zero(), false, false, !target_exists),
-			GasCost::StaticCall { target_exists, .. } =>
-				costs::call_cost(U256::zero(), false, true, !target_exists),
-			GasCost::Suicide { value, target_exists, .. } =>
-				costs::suicide_cost(value, target_exists),
-			GasCost::SStore { .. } if CONFIG.estimate => CONFIG.gas_sstore_set,
-			GasCost::SStore { original, current, new } =>
-				costs::sstore_cost(original, current, new, gas)?,
+			GasCost::Call { value, target_exists, target_is_cold, .. } =>
+				costs::call_cost::<S>(value, true, true, !target_exists, target_is_cold),
+			GasCost::CallCode { value, target_exists, target_is_cold, .. } =>
+				costs::call_cost::<S>(value, true, false, !target_exists, target_is_cold),
+			GasCost::DelegateCall { target_exists, target_is_cold, .. } =>
+				costs::call_cost::<S>(U256::zero(), false, false, !target_exists, target_is_cold),
+			GasCost::StaticCall { target_exists, target_is_cold, .. } =>
+				costs::call_cost::<S>(U256::zero(), false, true, !target_exists, target_is_cold),
+			GasCost::Suicide { value, target_exists, target_is_cold, .. } =>
+				costs::suicide_cost::<S>(value, target_exists, target_is_cold),
+			GasCost::SStore { .. } if S::CONFIG.estimate => S::CONFIG.gas_sstore_set,
+			GasCost::SStore { original, current, new, target_is_cold } =>
+				costs::sstore_cost::<S>(original, current, new, gas, target_is_cold)?,
 
 			GasCost::Sha3 { len } => costs::sha3_cost(len)?,
 			GasCost::Log { n, len } => costs::log_cost(n, len)?,
-			GasCost::ExtCodeCopy { len } => costs::extcodecopy_cost(len)?,
+			GasCost::ExtCodeCopy { len, target_is_cold } => costs::extcodecopy_cost::<S>(len, target_is_cold)?,
 			GasCost::VeryLowCopy { len } => costs::verylowcopy_cost(len)?,
-			GasCost::Exp { power } => costs::exp_cost(power)?,
+			GasCost::Exp { power } => costs::exp_cost::<S>(power)?,
 			GasCost::Create => consts::G_CREATE,
 			GasCost::Create2 { len } => costs::create2_cost(len)?,
-			GasCost::SLoad => CONFIG.gas_sload,
+			GasCost::SLoad { target_is_cold } => costs::sload_cost::<S>(target_is_cold),
 
 			GasCost::Zero => consts::G_ZERO,
 			GasCost::Base => consts::G_BASE,
 			GasCost::VeryLow => consts::G_VERYLOW,
 			GasCost::Low => consts::G_LOW,
-			GasCost::Invalid => return Err(ExitError::OutOfGas),
+			GasCost::Invalid(opcode) => return Err(ExitError::InvalidOpcode(opcode)),
 
-			GasCost::ExtCodeSize => CONFIG.gas_ext_code,
-			GasCost::Balance => CONFIG.gas_balance,
+			GasCost::ExtCodeSize { target_is_cold } =>
+				costs::account_access_cost::<S>(target_is_cold, S::CONFIG.gas_ext_code),
+			GasCost::Balance { target_is_cold } =>
+				costs::account_access_cost::<S>(target_is_cold, S::CONFIG.gas_balance),
 			GasCost::BlockHash => consts::G_BLOCKHASH,
-			GasCost::ExtCodeHash => CONFIG.gas_ext_code_hash,
+			GasCost::ExtCodeHash { target_is_cold } =>
+				costs::account_access_cost::<S>(target_is_cold, S::CONFIG.gas_ext_code_hash),
 		})
 	}
 
-	fn gas_refund(
+	fn gas_refund<S: Spec>(
 		&self,
 		cost: GasCost
 	) -> i64 {
 		match cost {
-			_ if CONFIG.estimate => 0,
-			GasCost::SStore { original, current, new } =>
-				costs::sstore_refund(original, current, new),
+			_ if S::CONFIG.estimate => 0,
+			GasCost::SStore { original, current, new, .. } =>
+				costs::sstore_refund::<S>(original, current, new),
 			GasCost::Suicide { already_removed, .. } =>
 				costs::suicide_refund(already_removed),
 			_ => 0,
@@ -650,17 +899,26 @@ pub enum GasCost {
 	VeryLow,
 	/// Low gas cost.
 	Low,
-	/// Fail the gasometer.
-	Invalid,
+	/// Fail the gasometer, the opcode not being defined in the hard fork.
+	Invalid(Opcode),
 
 	/// Gas cost for `EXTCODESIZE`.
-	ExtCodeSize,
+	ExtCodeSize {
+		/// Whether the target is cold.
+		target_is_cold: bool
+	},
 	/// Gas cost for `BALANCE`.
-	Balance,
+	Balance {
+		/// Whether the target is cold.
+		target_is_cold: bool
+	},
 	/// Gas cost for `BLOCKHASH`.
 	BlockHash,
 	/// Gas cost for `EXTBLOCKHASH`.
-	ExtCodeHash,
+	ExtCodeHash {
+		/// Whether the target is cold.
+		target_is_cold: bool
+	},
 
 	/// Gas cost for `CALL`.
 	Call {
@@ -669,7 +927,9 @@ pub enum GasCost {
 		/// Call gas.
 		gas: U256,
 		/// Whether the target exists.
-		target_exists: bool
+		target_exists: bool,
+		/// Whether the target is cold.
+		target_is_cold: bool
 	},
 	/// Gas cost for `CALLCODE.
 	CallCode {
@@ -678,21 +938,27 @@ pub enum GasCost {
 		/// Call gas.
 		gas: U256,
 		/// Whether the target exists.
-		target_exists: bool
+		target_exists: bool,
+		/// Whether the target is cold.
+		target_is_cold: bool
 	},
 	/// Gas cost for `DELEGATECALL`.
 	DelegateCall {
 		/// Call gas.
 		gas: U256,
 		/// Whether the target exists.
-		target_exists: bool
+		target_exists: bool,
+		/// Whether the target is cold.
+		target_is_cold: bool
 	},
 	/// Gas cost for `STATICCALL`.
 	StaticCall {
 		/// Call gas.
 		gas: U256,
 		/// Whether the target exists.
-		target_exists: bool
+		target_exists: bool,
+		/// Whether the target is cold.
+		target_is_cold: bool
 	},
 	/// Gas cost for `SUICIDE`.
 	Suicide {
@@ -701,7 +967,9 @@ pub enum GasCost {
 		/// Whether the target exists.
 		target_exists: bool,
 		/// Whether the target has already been removed.
-		already_removed: bool
+		already_removed: bool,
+		/// Whether the target is cold.
+		target_is_cold: bool
 	},
 	/// Gas cost for `SSTORE`.
 	SStore {
@@ -710,7 +978,9 @@ pub enum GasCost {
 		/// Current value.
 		current: H256,
 		/// New value.
-		new: H256
+		new: H256,
+		/// Whether the storage slot is cold.
+		target_is_cold: bool
 	},
 	/// Gas cost for `SHA3`.
 	Sha3 {
@@ -727,7 +997,9 @@ pub enum GasCost {
 	/// Gas cost for `EXTCODECOPY`.
 	ExtCodeCopy {
 		/// Length.
-		len: U256
+		len: U256,
+		/// Whether the target is cold.
+		target_is_cold: bool
 	},
 	/// Gas cost for some copy opcodes that is documented as `VERYLOW`.
 	VeryLowCopy {
@@ -747,7 +1019,10 @@ pub enum GasCost {
 		len: U256
 	},
 	/// Gas cost for `SLOAD`.
-	SLoad,
+	SLoad {
+		/// Whether the storage slot is cold.
+		target_is_cold: bool
+	},
 }
 
 /// Memory cost.
@@ -779,6 +1054,30 @@ pub enum TransactionCost {
 }
 
 impl MemoryCost {
+	/// Number of 32-byte words the memory needs to cover the range, zero if
+	/// it is empty.
+	pub fn words(&self) -> Result<u64, ExitError> {
+		if self.len.is_zero() {
+			return Ok(0)
+		}
+
+		let end = self.offset.checked_add(self.len).ok_or(ExitError::OutOfGas)?;
+
+		if end > U256::from(usize::max_value()) {
+			return Err(ExitError::OutOfGas)
+		}
+		let end = end.as_usize();
+
+		let rem = end % 32;
+		let words = if rem == 0 {
+			end / 32
+		} else {
+			end / 32 + 1
+		};
+
+		Ok(words as u64)
+	}
+
 	/// Join two memory cost together.
 	pub fn join(self, other: MemoryCost) -> MemoryCost {
 		if self.len.is_zero() {
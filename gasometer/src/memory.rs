@@ -1,5 +1,6 @@
-use evm_core::ExitError;
+use evm_core::{ExitError, U256};
 use crate::consts::*;
+use crate::MemoryCost;
 
 pub fn memory_gas(a: u64) -> Result<u64, ExitError> {
 	G_MEMORY
@@ -8,3 +9,14 @@ pub fn memory_gas(a: u64) -> Result<u64, ExitError> {
 			a.checked_mul(a).ok_or(ExitError::OutOfGas)? / 512
 		).ok_or(ExitError::OutOfGas)
 }
+
+/// Gas charged for expanding the memory from `current_words` words to cover
+/// `len` bytes at `offset`, as the gasometer charges it. Zero if the range is
+/// empty or already covered.
+pub fn memory_expansion_cost(current_words: u64, offset: U256, len: U256) -> Result<u64, ExitError> {
+	let new_words = MemoryCost { offset, len }.words()?;
+	if new_words <= current_words {
+		return Ok(0)
+	}
+	Ok(memory_gas(new_words)? - memory_gas(current_words)?)
+}
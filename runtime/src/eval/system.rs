@@ -102,11 +102,16 @@ pub fn extcodecopy<H: Handler>(runtime: &mut Runtime, handler: &H) -> Control<H>
 	let len = as_usize_or_fail!(len);
 
 	try_or_fail!(runtime.machine.memory_mut().resize_offset(memory_offset, len));
+	let code = if len == 0 {
+		Vec::new()
+	} else {
+		handler.code_range(address.into(), code_offset, len)
+	};
 	match runtime.machine.memory_mut().copy_large(
 		memory_offset,
-		code_offset,
+		0,
 		len,
-		&handler.code(address.into())
+		&code
 	) {
 		Ok(()) => (),
 		Err(e) => return Control::Exit(e.into()),
@@ -267,8 +272,8 @@ pub fn create<H: Handler>(
 	};
 
 	match handler.create(runtime.context.address, scheme, value, code, None) {
-		Capture::Exit((reason, address, _return_data)) => {
-			save_created_address(runtime, reason, address, handler)
+		Capture::Exit((reason, address, return_data)) => {
+			save_created_address(runtime, reason, address, return_data, handler)
 		},
 		Capture::Trap(interrupt) => {
 			// The created contract's address will be push by the method save_created_address()
@@ -365,14 +370,17 @@ pub fn call<'config, H: Handler>(
 }
 
 /// save created contract address into parent runtime
+///
+/// Per EIP-211 the return data buffer holds the revert data of a reverted
+/// create, and is empty otherwise.
 pub fn save_created_address<'config, H: Handler>(
 	runtime: &mut Runtime,
 	reason : ExitReason,
 	address: Option<H160>,
-	// return_data : Vec<u8>,
+	return_data : Vec<u8>,
 	_handler: & H
 ) -> Control<H> {
-	// runtime.return_data_buffer = return_data;
+	runtime.return_data_buffer = if reason.is_revert() { return_data } else { Vec::new() };
 	let create_address: H256 = address.map(|a| a.into()).unwrap_or_default();
 
 	match reason {
@@ -392,12 +400,18 @@ pub fn save_created_address<'config, H: Handler>(
 			push!(runtime, H256::default());
 			Control::Exit(e.into())
 		},
-		ExitReason::StepLimitReached => { unreachable!() }
+		ExitReason::StepLimitReached => {
+			push!(runtime, H256::default());
+			Control::Exit(ExitFatal::UnhandledInterrupt.into())
+		},
 	}
 
 }
 
 /// save return_value into parent runtime
+///
+/// Per EIP-211 the return data buffer holds the output of a succeeded or
+/// reverted call, and is empty if the call failed.
 pub fn save_return_value<'config, H: Handler>(
 	runtime: &mut Runtime,
 	reason : ExitReason,
@@ -412,7 +426,11 @@ pub fn save_return_value<'config, H: Handler>(
 	try_or_fail!(runtime.machine.memory_mut().resize_offset(out_offset, out_len));
 
         {  // this block uses the given alignment to match the original code.
-			runtime.return_data_buffer = return_data;
+			runtime.return_data_buffer = if reason.is_succeed() || reason.is_revert() {
+				return_data
+			} else {
+				Vec::new()
+			};
 			let target_len = min(out_len, runtime.return_data_buffer.len());
 
 			match reason {
@@ -455,7 +473,10 @@ pub fn save_return_value<'config, H: Handler>(
 
 					Control::Exit(e.into())
 				},
-				ExitReason::StepLimitReached => { unreachable!() }
+				ExitReason::StepLimitReached => {
+					push!(runtime, H256::default());
+					Control::Exit(ExitFatal::UnhandledInterrupt.into())
+				},
 			}
         }
 }
@@ -1,7 +1,7 @@
 use alloc::vec::Vec;
-use crate::{Capture, Stack, ExitError, Opcode,
+use crate::{Capture, Memory, Stack, ExitError, Opcode,
 			CreateScheme, Context, Machine, ExitReason,
-			H160, H256, U256};
+			H160, H256, U256, Keccak};
 
 /// Transfer from source to target, with given value.
 #[derive(Clone, Copy, Debug)]
@@ -17,7 +17,7 @@ pub struct Transfer {
 }
 
 /// EVM context handler.
-pub trait Handler {
+pub trait Handler: Keccak {
 	/// Type of `CREATE` interrupt.
 	type CreateInterrupt;
 	/// Feedback value for `CREATE` interrupt.
@@ -27,9 +27,6 @@ pub trait Handler {
 	/// Feedback value of `CALL` interrupt.
 	type CallFeedback;
 
-	/// Get keccak hash from data.
-	fn keccak256_h256(&self, data: &[u8]) -> H256;
-
 	/// Get balance of address.
 	fn balance(&self, address: H160) -> U256;
 	/// Get code size of address.
@@ -38,8 +35,22 @@ pub trait Handler {
 	fn code_hash(&self, address: H160) -> H256;
 	/// Get code of address.
 	fn code(&self, address: H160) -> Vec<u8>;
+	/// Get `len` bytes of code of address at `offset`, truncated to the end
+	/// of the code. Defaults to slicing the full code.
+	fn code_range(&self, address: H160, offset: usize, len: usize) -> Vec<u8> {
+		let code = self.code(address);
+		match offset.checked_add(len) {
+			Some(end) if offset <= code.len() => code[offset..core::cmp::min(end, code.len())].to_vec(),
+			_ => Vec::new(),
+		}
+	}
 	/// Get valids of address.
 	fn valids(&self, address: H160) -> Vec<u8>;
+	/// Get code and valids of address together, as needed to run the code.
+	/// Defaults to fetching them separately.
+	fn code_and_valids(&self, address: H160) -> (Vec<u8>, Vec<u8>) {
+		(self.code(address), self.valids(address))
+	}
 	/// Get storage value of address at index.
 	fn storage(&self, address: H160, index: U256) -> U256;
 	/// Get original storage value of address at index.
@@ -68,6 +79,11 @@ pub trait Handler {
 
 	/// Check whether an address exists.
 	fn exists(&self, address: H160) -> bool;
+	/// Check whether the account at `address`, or its storage at `index`,
+	/// is cold, that is not accessed yet in the transaction (EIP-2929).
+	fn is_cold(&self, _address: H160, _index: Option<U256>) -> bool {
+		false
+	}
 	/// Check whether an address has already been deleted.
 	fn deleted(&self, address: H160) -> bool;
 
@@ -111,12 +127,22 @@ pub trait Handler {
 		Ok(())
 	}
 
-	/// Pre-validation step for the runtime.
+	/// Get the return data of the last call or create operation.
+	fn return_data(&self) -> &[u8] {
+		&[]
+	}
+	/// Set the return data of the last call or create operation.
+	fn set_return_data(&mut self, _data: Vec<u8>) {}
+
+	/// Pre-validation step for the runtime, before the opcode at the given
+	/// position is executed.
 	fn pre_validate(
 		&mut self,
 		context: &Context,
 		opcode: Opcode,
-		stack: &Stack
+		position: usize,
+		stack: &Stack,
+		memory: &Memory,
 	) -> Result<(), ExitError>;
 	/// Handle other unknown external opcodes.
 	fn other(
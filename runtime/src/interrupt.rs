@@ -1,4 +1,6 @@
-use crate::{Runtime, Handler, ExitFatal};
+use alloc::vec::Vec;
+use crate::{Runtime, Handler, ExitFatal, ExitReason, H160};
+use crate::eval::{save_created_address, save_return_value, Control};
 
 /// Interrupt resolution.
 pub enum Resolve<'a, H: Handler> {
@@ -9,37 +11,127 @@ pub enum Resolve<'a, H: Handler> {
 }
 
 /// Create interrupt resolution.
+///
+/// Dropping it without resolving fails the runtime with
+/// `UnhandledInterrupt`.
 pub struct ResolveCreate<'a> {
-	runtime: &'a mut Runtime,
+	runtime: Option<&'a mut Runtime>,
 }
 
 impl<'a> ResolveCreate<'a> {
 	pub(crate) fn new(runtime: &'a mut Runtime) -> Self {
-		Self { runtime }
+		Self { runtime: Some(runtime) }
+	}
+
+	/// Resolution of a `CREATE` left unresolved with `suspend`.
+	pub fn suspended(runtime: &'a mut Runtime) -> Self {
+		Self::new(runtime)
+	}
+
+	/// Leave the trapped `CREATE` unresolved, keeping the runtime as it is,
+	/// for instance to persist it while the create frame runs. It is then
+	/// resolved through `ResolveCreate::suspended`.
+	pub fn suspend(mut self) {
+		self.runtime = None;
+	}
+
+	/// Resolve the trapped `CREATE` with the outcome of the create frame.
+	///
+	/// The feedback is first given to `Handler::create_feedback`, for
+	/// instance to refund the gas left by the frame. The created address, or
+	/// zero on failure, is then pushed and the return data set as the opcode
+	/// would, and the runtime can be run further. An error is the exit
+	/// reason of the runtime, which is then stopped.
+	pub fn resolve<H: Handler>(
+		mut self,
+		handler: &mut H,
+		reason: ExitReason,
+		address: Option<H160>,
+		return_data: Vec<u8>,
+		feedback: H::CreateFeedback,
+	) -> Result<(), ExitReason> {
+		let runtime = self.runtime.take().expect("taken only when resolved");
+		if let Err(e) = handler.create_feedback(feedback) {
+			return Err(exit(runtime, e.into()))
+		}
+		match save_created_address(runtime, reason, address, return_data, handler) {
+			Control::Continue => Ok(()),
+			Control::Exit(e) => Err(exit(runtime, e)),
+			Control::CallInterrupt(_) | Control::CreateInterrupt(_) => Err(exit(runtime, ExitFatal::UnhandledInterrupt.into())),
+		}
 	}
 }
 
 impl<'a> Drop for ResolveCreate<'a> {
 	fn drop(&mut self) {
-		self.runtime.status = Err(ExitFatal::UnhandledInterrupt.into());
-		self.runtime.machine.exit(ExitFatal::UnhandledInterrupt.into());
+		if let Some(runtime) = self.runtime.take() {
+			exit(runtime, ExitFatal::UnhandledInterrupt.into());
+		}
 	}
 }
 
 /// Call interrupt resolution.
+///
+/// Dropping it without resolving fails the runtime with
+/// `UnhandledInterrupt`.
 pub struct ResolveCall<'a> {
-	runtime: &'a mut Runtime,
+	runtime: Option<&'a mut Runtime>,
 }
 
 impl<'a> ResolveCall<'a> {
 	pub(crate) fn new(runtime: &'a mut Runtime) -> Self {
-		Self { runtime }
+		Self { runtime: Some(runtime) }
+	}
+
+	/// Resolution of a `CALL` left unresolved with `suspend`.
+	pub fn suspended(runtime: &'a mut Runtime) -> Self {
+		Self::new(runtime)
+	}
+
+	/// Leave the trapped `CALL` unresolved, keeping the runtime as it is,
+	/// for instance to persist it while the call frame runs. It is then
+	/// resolved through `ResolveCall::suspended`.
+	pub fn suspend(mut self) {
+		self.runtime = None;
+	}
+
+	/// Resolve the trapped `CALL` with the outcome of the call frame.
+	///
+	/// The feedback is first given to `Handler::call_feedback`, for instance
+	/// to refund the gas left by the frame. The success flag is then pushed,
+	/// the output copied to the memory range given to the opcode and kept as
+	/// return data, and the runtime can be run further. An error is the exit
+	/// reason of the runtime, which is then stopped.
+	pub fn resolve<H: Handler>(
+		mut self,
+		handler: &mut H,
+		reason: ExitReason,
+		return_data: Vec<u8>,
+		feedback: H::CallFeedback,
+	) -> Result<(), ExitReason> {
+		let runtime = self.runtime.take().expect("taken only when resolved");
+		if let Err(e) = handler.call_feedback(feedback) {
+			return Err(exit(runtime, e.into()))
+		}
+		match save_return_value(runtime, reason, return_data, handler) {
+			Control::Continue => Ok(()),
+			Control::Exit(e) => Err(exit(runtime, e)),
+			Control::CallInterrupt(_) | Control::CreateInterrupt(_) => Err(exit(runtime, ExitFatal::UnhandledInterrupt.into())),
+		}
 	}
 }
 
 impl<'a> Drop for ResolveCall<'a> {
 	fn drop(&mut self) {
-		self.runtime.status = Err(ExitFatal::UnhandledInterrupt.into());
-		self.runtime.machine.exit(ExitFatal::UnhandledInterrupt.into());
+		if let Some(runtime) = self.runtime.take() {
+			exit(runtime, ExitFatal::UnhandledInterrupt.into());
+		}
 	}
 }
+
+/// Stop the runtime with `reason`, returning it.
+fn exit(runtime: &mut Runtime, reason: ExitReason) -> ExitReason {
+	runtime.status = Err(reason);
+	runtime.machine.exit(reason);
+	reason
+}
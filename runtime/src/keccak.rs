@@ -0,0 +1,16 @@
+use alloc::vec::Vec;
+use crate::H256;
+
+/// Keccak-256 provider.
+///
+/// Supplied by the backend and the handler, so that embedders with a native
+/// implementation (such as a syscall) never link a software one.
+pub trait Keccak {
+	/// Get keccak hash from slice.
+	fn keccak256_h256(&self, data: &[u8]) -> H256;
+
+	/// Get keccak hash from the concatenation of an array of slices.
+	fn keccak256_h256_v(&self, data: &[&[u8]]) -> H256 {
+		self.keccak256_h256(&data.iter().flat_map(|s| s.iter().copied()).collect::<Vec<u8>>())
+	}
+}
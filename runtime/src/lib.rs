@@ -17,20 +17,25 @@ mod eval;
 mod context;
 mod interrupt;
 mod handler;
+mod keccak;
+mod spec;
 
 pub use evm_core::*;
 
 pub use crate::context::{CreateScheme, CallScheme, Context};
 pub use crate::interrupt::{Resolve, ResolveCall, ResolveCreate};
 pub use crate::handler::{Transfer, Handler};
+pub use crate::keccak::Keccak;
+pub use crate::spec::{Berlin, Frontier, Istanbul, Spec};
 pub use crate::eval::{save_return_value, save_created_address, Control};
 
+use alloc::sync::Arc;
 use alloc::vec::Vec;
 
 macro_rules! step {
 	( $self:expr, $handler:expr, $return:tt $($err:path)?; $($ok:path)? ) => ({
-		if let Some((opcode, stack)) = $self.machine.inspect() {
-			match $handler.pre_validate(&$self.context, opcode, stack) {
+		if let (Some((opcode, stack)), Ok(position)) = ($self.machine.inspect(), $self.machine.position()) {
+			match $handler.pre_validate(&$self.context, opcode, position, stack, $self.machine.memory()) {
 				Ok(()) => (),
 				Err(e) => {
 					$self.machine.exit(e.clone().into());
@@ -93,21 +98,58 @@ pub struct Runtime {
 }
 
 impl Runtime {
-	/// Create a new runtime with given code and data.
+	/// Create a new runtime with given code and data, and the limits of
+	/// `CONFIG`.
 	pub fn new(
 		code: Vec<u8>,
-		valids: Vec<u8>,
+		valids: Arc<Valids>,
 		data: Vec<u8>,
 		context: Context,
+	) -> Self {
+		Self::new_with_config(code, valids, data, context, &CONFIG)
+	}
+
+	/// Create a new runtime with given code and data, and the limits of the
+	/// given configuration.
+	pub fn new_with_config(
+		code: Vec<u8>,
+		valids: Arc<Valids>,
+		data: Vec<u8>,
+		context: Context,
+		config: &Config,
+	) -> Self {
+		Self::new_with_limits(code, valids, data, context, Limits::from_config(config))
+	}
+
+	/// Create a new runtime with given code, data and limits.
+	pub fn new_with_limits(
+		code: Vec<u8>,
+		valids: Arc<Valids>,
+		data: Vec<u8>,
+		context: Context,
+		limits: Limits,
 	) -> Self {
 		Self {
-			machine: Machine::new(code, valids, data, CONFIG.stack_limit, CONFIG.memory_limit),
+			machine: {
+				let mut machine = Machine::new(code, valids, data, limits.stack_limit, limits.memory_limit);
+				machine.set_return_data_limit(limits.return_data_limit);
+				machine
+			},
 			status: Ok(()),
 			return_data_buffer: Vec::new(),
 			context,
 		}
 	}
 
+	/// Stack, memory and return data limits of the runtime.
+	pub const fn limits(&self) -> Limits {
+		Limits {
+			stack_limit: self.machine.stack().limit(),
+			memory_limit: self.machine.memory().limit(),
+			return_data_limit: self.machine.return_data_limit(),
+		}
+	}
+
 	/// Get return data
 	pub fn return_data(&self) -> &Vec<u8> {
 		&self.return_data_buffer
@@ -123,6 +165,30 @@ impl Runtime {
 		&self.machine
 	}
 
+	/// Enable fused dispatch of common opcode sequences, see `Machine::fuse`.
+	pub fn fuse(&mut self) {
+		self.machine.fuse();
+	}
+
+	/// Run the code through its translation, see `Machine::set_compiled`.
+	#[cfg(feature = "jit")]
+	pub fn set_compiled(&mut self, compiled: alloc::sync::Arc<CompiledCode>) {
+		self.machine.set_compiled(compiled);
+	}
+
+	/// Report executed opcodes to `sink`, see `Machine::set_debug_sink`.
+	#[cfg(feature = "debug-trace")]
+	pub fn set_debug_sink(&mut self, sink: alloc::sync::Arc<dyn DebugSink>) {
+		self.machine.set_debug_sink(sink);
+	}
+
+	/// Track the values derived from the call data, see
+	/// `Machine::set_taint_sink`.
+	#[cfg(feature = "taint")]
+	pub fn set_taint_sink(&mut self, sink: alloc::sync::Arc<dyn TaintSink>) {
+		self.machine.set_taint_sink(sink);
+	}
+
 	/// Step the runtime.
 	pub fn step<'a, H: Handler>(
 		&'a mut self,
@@ -146,7 +212,9 @@ impl Runtime {
 		while steps < max_steps {
 			let (steps_executed, capture) = {
 				let context = &self.context;
-				let pre_validate = |opcode, stack: &Stack| { handler.pre_validate(context, opcode, stack) };
+				let pre_validate = |opcode, position, stack: &Stack, memory: &Memory| {
+					handler.pre_validate(context, opcode, position, stack, memory)
+				};
 				self.machine.run(max_steps - steps, pre_validate)
 			};
 			steps += steps_executed;
@@ -184,6 +252,28 @@ impl Runtime {
 	}
 }
 
+/// Stack, memory and return data limits of a runtime.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Limits {
+	/// Maximum number of stack items.
+	pub stack_limit: usize,
+	/// Maximum memory size in bytes.
+	pub memory_limit: usize,
+	/// Maximum length in bytes of the output of `RETURN` and `REVERT`.
+	pub return_data_limit: usize,
+}
+
+impl Limits {
+	/// Limits of the given configuration.
+	pub const fn from_config(config: &Config) -> Self {
+		Self {
+			stack_limit: config.stack_limit,
+			memory_limit: config.memory_limit,
+			return_data_limit: config.return_data_limit,
+		}
+	}
+}
+
 /// Runtime configuration.
 #[derive(Clone, Debug)]
 pub struct Config {
@@ -197,6 +287,8 @@ pub struct Config {
 	pub gas_sstore_reset: u64,
 	/// Gas paid for sstore refund.
 	pub refund_sstore_clears: i64,
+	/// Maximum refund is the used gas divided by this quotient.
+	pub max_refund_quotient: u64,
 	/// Gas paid for BALANCE opcode.
 	pub gas_balance: u64,
 	/// Gas paid for SLOAD opcode.
@@ -207,6 +299,12 @@ pub struct Config {
 	pub gas_suicide_new_account: u64,
 	/// Gas paid for CALL opcode.
 	pub gas_call: u64,
+	/// Gas paid for accessing a cold account, with EIP-2929.
+	pub gas_account_access_cold: u64,
+	/// Gas paid for SLOAD of a cold storage slot, with EIP-2929.
+	pub gas_sload_cold: u64,
+	/// Gas paid for accessing a warm account or storage slot, with EIP-2929.
+	pub gas_storage_read_warm: u64,
 	/// Gas paid for EXP opcode for every byte.
 	pub gas_expbyte: u64,
 	/// Gas paid for a contract creation transaction.
@@ -227,17 +325,37 @@ pub struct Config {
 	pub err_on_call_with_more_gas: bool,
 	/// Take l64 for callcreate after gas.
 	pub call_l64_after_gas: bool,
-	/// Whether empty account is considered exists.
+	/// Whether empty account is considered exists. Before EIP-161, an
+	/// account exists once it is in the state, afterwards only if it has a
+	/// nonce, a balance or code.
 	pub empty_considered_exists: bool,
+	/// Whether the addresses served by the precompile registry of the
+	/// executor are considered existing, whatever their state, so that
+	/// calls transferring value to them are not charged for a new account.
+	pub precompiles_exist: bool,
+	/// Whether touched empty accounts are deleted at the end of the
+	/// transaction (EIP-161).
+	pub clear_touched_empty: bool,
 	/// Whether create transactions and create opcode increases nonce by one.
 	pub create_increase_nonce: bool,
+	/// Whether the caller nonce is still increased when create transactions
+	/// and create opcode hit an address collision.
+	pub create_collision_increase_nonce: bool,
 	/// Stack limit.
 	pub stack_limit: usize,
 	/// Memory limit.
 	pub memory_limit: usize,
+	/// Limit of the output of `RETURN` and `REVERT`, failing with
+	/// `ReturnDataLimit` above it.
+	pub return_data_limit: usize,
 	/// Call limit.
 	pub call_stack_limit: usize,
-	/// Create contract limit.
+	/// Whether calls served by a precompile or a call interceptor count as
+	/// call frames towards the call limit.
+	pub hook_call_depth: bool,
+	/// Maximum size of deployed code, failing the create with
+	/// `CreateContractLimit` above it (EIP-170). Chains raising the limit
+	/// set it in the configuration of their `Spec`.
 	pub create_contract_limit: Option<usize>,
 	/// Call stipend.
 	pub call_stipend: u64,
@@ -257,6 +375,33 @@ pub struct Config {
 	pub has_self_balance: bool,
 	/// Has ext code hash.
 	pub has_ext_code_hash: bool,
+	/// Has `MCOPY` (EIP-5656).
+	pub has_mcopy: bool,
+	/// Whether transactions sent from an account with code are rejected
+	/// (EIP-3607).
+	pub reject_sender_with_code: bool,
+	/// Whether deploying code starting with `0xEF` fails the create with
+	/// `CreateContractStartingWithEF` (EIP-3541). Implied by `has_eof`.
+	pub reject_ef_code: bool,
+	/// Whether EOF containers are validated on creation and run by code
+	/// section (EIP-3540, EIP-3541, EIP-3670).
+	pub has_eof: bool,
+	/// Whether accesses to accounts and storage slots cost more the first
+	/// time in a transaction (EIP-2929).
+	pub increase_state_access_gas: bool,
+	/// Whether transactions with an access list, of type 1, are accepted
+	/// (EIP-2930).
+	pub has_access_list_transactions: bool,
+	/// Whether transactions with a base fee and a priority fee, of type 2,
+	/// are accepted (EIP-1559).
+	pub has_dynamic_fee_transactions: bool,
+	/// Number of Ethereum precompiles of the hard fork, at the addresses
+	/// from 1 up. They are warm from the start of every transaction with
+	/// EIP-2929, however the executor serves them.
+	pub ethereum_precompiles: u8,
+	/// Opcodes disabled on the chain, failing with `OpcodeDisabled` instead
+	/// of running, for instance `CALLCODE` or `SELFDESTRUCT`.
+	pub disabled_opcodes: OpcodeSet,
 	/// Whether the gasometer is running in estimate mode.
 	pub estimate: bool,
 }
@@ -274,9 +419,13 @@ impl Config {
 			gas_sstore_set: 20000,
 			gas_sstore_reset: 5000,
 			refund_sstore_clears: 15000,
+			max_refund_quotient: 2,
 			gas_suicide: 0,
 			gas_suicide_new_account: 0,
 			gas_call: 40,
+			gas_account_access_cold: 0,
+			gas_sload_cold: 0,
+			gas_storage_read_warm: 0,
 			gas_expbyte: 10,
 			gas_transaction_create: 21000,
 			gas_transaction_call: 21000,
@@ -286,11 +435,16 @@ impl Config {
 			sstore_revert_under_stipend: false,
 			err_on_call_with_more_gas: true,
 			empty_considered_exists: true,
+			precompiles_exist: true,
+			clear_touched_empty: false,
 			create_increase_nonce: false,
+			create_collision_increase_nonce: true,
 			call_l64_after_gas: false,
 			stack_limit: 1024,
 			memory_limit: usize::max_value(),
+			return_data_limit: usize::max_value(),
 			call_stack_limit: 1024,
+			hook_call_depth: true,
 			create_contract_limit: None,
 			call_stipend: 2300,
 			has_delegate_call: false,
@@ -301,6 +455,15 @@ impl Config {
 			has_chain_id: false,
 			has_self_balance: false,
 			has_ext_code_hash: false,
+			has_mcopy: false,
+			reject_sender_with_code: false,
+			reject_ef_code: false,
+			has_eof: false,
+			increase_state_access_gas: false,
+			has_access_list_transactions: false,
+			has_dynamic_fee_transactions: false,
+			ethereum_precompiles: 4,
+			disabled_opcodes: OpcodeSet::new(),
 			estimate: false,
 		}
 	}
@@ -315,9 +478,13 @@ impl Config {
 			gas_sstore_set: 20000,
 			gas_sstore_reset: 5000,
 			refund_sstore_clears: 15000,
+			max_refund_quotient: 2,
 			gas_suicide: 5000,
 			gas_suicide_new_account: 25000,
 			gas_call: 700,
+			gas_account_access_cold: 0,
+			gas_sload_cold: 0,
+			gas_storage_read_warm: 0,
 			gas_expbyte: 50,
 			gas_transaction_create: 53000,
 			gas_transaction_call: 21000,
@@ -327,11 +494,16 @@ impl Config {
 			sstore_revert_under_stipend: true,
 			err_on_call_with_more_gas: false,
 			empty_considered_exists: false,
+			precompiles_exist: true,
+			clear_touched_empty: true,
 			create_increase_nonce: true,
+			create_collision_increase_nonce: true,
 			call_l64_after_gas: true,
 			stack_limit: 1024,
 			memory_limit: usize::max_value(),
+			return_data_limit: usize::max_value(),
 			call_stack_limit: 1024,
+			hook_call_depth: true,
 			create_contract_limit: Some(0x6000),
 			call_stipend: 2300,
 			has_delegate_call: true,
@@ -342,10 +514,39 @@ impl Config {
 			has_chain_id: true,
 			has_self_balance: true,
 			has_ext_code_hash: true,
+			has_mcopy: false,
+			reject_sender_with_code: false,
+			reject_ef_code: false,
+			has_eof: false,
+			increase_state_access_gas: false,
+			has_access_list_transactions: false,
+			has_dynamic_fee_transactions: false,
+			ethereum_precompiles: 9,
+			disabled_opcodes: OpcodeSet::new(),
 			estimate: false,
 		}
 	}
 
+	/// Berlin hard fork configuration: Istanbul with the cold and warm
+	/// access costs of EIP-2929 and the access list transactions of
+	/// EIP-2930.
+	pub const fn berlin() -> Config {
+		Config {
+			gas_ext_code: 100,
+			gas_ext_code_hash: 100,
+			gas_balance: 100,
+			gas_sload: 100,
+			gas_sstore_reset: 2900,
+			gas_call: 100,
+			gas_account_access_cold: 2600,
+			gas_sload_cold: 2100,
+			gas_storage_read_warm: 100,
+			increase_state_access_gas: true,
+			has_access_list_transactions: true,
+			..Self::istanbul()
+		}
+	}
+
 	/// Reference to default configuration
 	pub fn default() -> &'static Config {
 		&CONFIG
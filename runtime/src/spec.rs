@@ -0,0 +1,35 @@
+use crate::Config;
+
+/// Hard fork specification, resolving the configuration at compile time.
+///
+/// Code generic over a specification reads `S::CONFIG`, whose fields are
+/// constants, so several hard forks can be compiled into the same binary
+/// without branching on a runtime configuration.
+pub trait Spec: Clone + Copy + Default {
+	/// Configuration of the hard fork.
+	const CONFIG: Config;
+}
+
+/// Frontier hard fork.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Frontier;
+
+impl Spec for Frontier {
+	const CONFIG: Config = Config::frontier();
+}
+
+/// Istanbul hard fork, matching `CONFIG`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Istanbul;
+
+impl Spec for Istanbul {
+	const CONFIG: Config = Config::istanbul();
+}
+
+/// Berlin hard fork.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Berlin;
+
+impl Spec for Berlin {
+	const CONFIG: Config = Config::berlin();
+}
@@ -0,0 +1,92 @@
+//! # Access list extraction
+//!
+//! `AccessListTracer` records the accounts and storage keys accessed by the
+//! opcodes of a simulated transaction, and turns them into an EIP-2930
+//! access list for the same transaction.
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use crate::{Opcode, H160, H256, U256};
+use crate::tracing::{Event, EventListener};
+
+/// EIP-2930 access list, the storage keys of each address sorted.
+pub type AccessList = Vec<(H160, Vec<H256>)>;
+
+/// Event listener building the access list of a transaction.
+///
+/// Accounts accessed by `BALANCE`, `EXTCODE*`, `SELFDESTRUCT` and calls are
+/// recorded, except the sender, the recipient and the precompiles, which
+/// are warm anyway. Storage keys accessed by `SLOAD` and `SSTORE` are
+/// recorded for every account, including the recipient. Accesses of frames
+/// that revert are kept, as the transaction performs them all the same.
+///
+/// The listener reads the stack of `Step` events, which must not be disabled
+/// by the trace configuration.
+#[derive(Debug, Default)]
+pub struct AccessListTracer {
+	excluded: BTreeSet<H160>,
+	accessed: RefCell<BTreeMap<H160, BTreeSet<H256>>>,
+}
+
+impl AccessListTracer {
+	/// Tracer for a transaction from `sender` to `recipient`, `None` for a
+	/// create, on a chain with the given precompiles.
+	pub fn new<I: IntoIterator<Item=H160>>(sender: H160, recipient: Option<H160>, precompiles: I) -> Self {
+		let mut excluded: BTreeSet<H160> = precompiles.into_iter().collect();
+		excluded.insert(sender);
+		excluded.extend(recipient);
+		Self { excluded, accessed: RefCell::new(BTreeMap::new()) }
+	}
+
+	/// Access list of the accesses recorded so far, sorted by address.
+	#[must_use]
+	pub fn access_list(&self) -> AccessList {
+		self.accessed.borrow().iter()
+			.map(|(address, keys)| (*address, keys.iter().copied().collect()))
+			.collect()
+	}
+
+	fn access_address(&self, address: H160) {
+		if !self.excluded.contains(&address) {
+			self.accessed.borrow_mut().entry(address).or_default();
+		}
+	}
+
+	fn access_storage(&self, address: H160, index: U256) {
+		self.accessed.borrow_mut().entry(address).or_default().insert(H256::from(index));
+	}
+}
+
+/// Address held by a stack value.
+fn address(value: U256) -> H160 {
+	H256::from(value).into()
+}
+
+impl EventListener for AccessListTracer {
+	fn event(&self, event: Event<'_>) {
+		let Event::Step { context, opcode, stack: Some(stack), .. } = event else {
+			return
+		};
+		match opcode {
+			Opcode::SLOAD | Opcode::SSTORE => {
+				if let Some(index) = stack.peek(0) {
+					self.access_storage(context.address, index);
+				}
+			},
+			Opcode::BALANCE | Opcode::EXTCODESIZE | Opcode::EXTCODECOPY | Opcode::EXTCODEHASH
+				| Opcode::SUICIDE =>
+			{
+				if let Some(value) = stack.peek(0) {
+					self.access_address(address(value));
+				}
+			},
+			Opcode::CALL | Opcode::CALLCODE | Opcode::DELEGATECALL | Opcode::STATICCALL => {
+				if let Some(value) = stack.peek(1) {
+					self.access_address(address(value));
+				}
+			},
+			_ => (),
+		}
+	}
+}
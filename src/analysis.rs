@@ -0,0 +1,217 @@
+//! # Ahead-of-time code analysis
+//!
+//! Jump destinations, basic blocks and their static gas and stack height,
+//! computed once per code hash so that the executor can charge the gas of a
+//! whole basic block at once.
+
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use crate::{Opcode, Valids, H256};
+use crate::gasometer;
+
+/// Straight-line run of opcodes with a static gas cost.
+///
+/// A block starts at a `JUMPDEST` or after an opcode that is not part of any
+/// block, and ends after `JUMP`, `JUMPI` or `STOP`. Opcodes with a dynamic
+/// cost, undefined opcodes and `GAS`, which observes the gas left, are never
+/// part of a block.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct BasicBlock {
+	/// Position of the first opcode.
+	pub start: usize,
+	/// Position right after the last opcode and its push data.
+	pub end: usize,
+	/// Sum of the static gas cost of the opcodes.
+	pub gas: u64,
+	/// Number of stack items the block needs on entry not to underflow.
+	pub stack_min: usize,
+	/// Maximum number of stack items the block adds over its entry height.
+	pub stack_growth: usize,
+}
+
+/// Analysis of a code, see the module documentation.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CodeAnalysis {
+	valids: Arc<Valids>,
+	/// Index plus one into `blocks` for each position, zero for none.
+	slots: Vec<u32>,
+	blocks: Vec<BasicBlock>,
+}
+
+/// Size of the push data of the opcode, zero if it is not a push.
+fn push_size(opcode: Opcode) -> usize {
+	if (Opcode::PUSH1.as_u8()..=Opcode::PUSH32.as_u8()).contains(&opcode.as_u8()) {
+		usize::from(opcode.as_u8() - Opcode::PUSH1.as_u8()) + 1
+	} else {
+		0
+	}
+}
+
+/// Number of items popped and pushed by an opcode, `None` if the opcode is
+/// undefined.
+fn stack_io(opcode: Opcode) -> Option<(usize, usize)> {
+	let op = opcode.as_u8();
+	if (Opcode::DUP1.as_u8()..=Opcode::DUP16.as_u8()).contains(&op) {
+		let n = usize::from(op - Opcode::DUP1.as_u8()) + 1;
+		return Some((n, n + 1))
+	}
+	if (Opcode::SWAP1.as_u8()..=Opcode::SWAP16.as_u8()).contains(&op) {
+		let n = usize::from(op - Opcode::SWAP1.as_u8()) + 2;
+		return Some((n, n))
+	}
+	if push_size(opcode) > 0 {
+		return Some((0, 1))
+	}
+
+	let io = match opcode {
+		Opcode::STOP | Opcode::JUMPDEST | Opcode::INVALID => (0, 0),
+		Opcode::ADDRESS | Opcode::ORIGIN | Opcode::CALLER | Opcode::CALLVALUE |
+		Opcode::CALLDATASIZE | Opcode::CODESIZE | Opcode::GASPRICE | Opcode::RETURNDATASIZE |
+		Opcode::COINBASE | Opcode::TIMESTAMP | Opcode::NUMBER | Opcode::DIFFICULTY |
+		Opcode::GASLIMIT | Opcode::CHAINID | Opcode::SELFBALANCE | Opcode::PC |
+		Opcode::MSIZE | Opcode::GAS => (0, 1),
+		Opcode::POP | Opcode::JUMP | Opcode::SUICIDE => (1, 0),
+		Opcode::NOT | Opcode::ISZERO | Opcode::CALLDATALOAD | Opcode::BALANCE |
+		Opcode::EXTCODESIZE | Opcode::EXTCODEHASH | Opcode::BLOCKHASH | Opcode::MLOAD |
+		Opcode::SLOAD => (1, 1),
+		Opcode::JUMPI | Opcode::MSTORE | Opcode::MSTORE8 | Opcode::SSTORE |
+		Opcode::RETURN | Opcode::REVERT | Opcode::LOG0 => (2, 0),
+		Opcode::ADD | Opcode::SUB | Opcode::MUL | Opcode::DIV | Opcode::SDIV |
+		Opcode::MOD | Opcode::SMOD | Opcode::EXP | Opcode::SIGNEXTEND | Opcode::LT |
+		Opcode::GT | Opcode::SLT | Opcode::SGT | Opcode::EQ | Opcode::AND | Opcode::OR |
+		Opcode::XOR | Opcode::BYTE | Opcode::SHL | Opcode::SHR | Opcode::SAR |
+		Opcode::SHA3 => (2, 1),
+		Opcode::CALLDATACOPY | Opcode::CODECOPY | Opcode::RETURNDATACOPY | Opcode::MCOPY |
+		Opcode::LOG1 => (3, 0),
+		Opcode::ADDMOD | Opcode::MULMOD | Opcode::CREATE => (3, 1),
+		Opcode::EXTCODECOPY | Opcode::LOG2 => (4, 0),
+		Opcode::CREATE2 => (4, 1),
+		Opcode::LOG3 => (5, 0),
+		Opcode::LOG4 => (6, 0),
+		Opcode::DELEGATECALL | Opcode::STATICCALL => (6, 1),
+		Opcode::CALL | Opcode::CALLCODE => (7, 1),
+		_ => return None,
+	};
+	Some(io)
+}
+
+impl CodeAnalysis {
+	/// Analyze the code.
+	#[must_use]
+	pub fn analyze(code: &[u8]) -> Self {
+		let mut analysis = Self {
+			valids: Arc::new(Valids::compute(code).into()),
+			slots: alloc::vec![0; code.len()],
+			blocks: Vec::new(),
+		};
+
+		let mut current: Option<BasicBlock> = None;
+		let mut height = 0_isize;
+		let mut position = 0;
+		while position < code.len() {
+			let opcode = Opcode(code[position]);
+			let next = position + 1 + push_size(opcode);
+
+			// Opcodes without a static cost, and undefined ones, end the block.
+			let cost = gasometer::static_opcode_cost(opcode).filter(|_| opcode != Opcode::GAS);
+			let step = cost.zip(stack_io(opcode));
+			if opcode == Opcode::JUMPDEST || step.is_none() {
+				analysis.close(current.take());
+			}
+
+			if let Some((cost, (pop, push))) = step {
+				let block = current.get_or_insert_with(|| {
+					height = 0;
+					BasicBlock { start: position, end: next, gas: 0, stack_min: 0, stack_growth: 0 }
+				});
+
+				#[allow(clippy::cast_possible_wrap)]
+				let (pop, push) = (pop as isize, push as isize);
+				#[allow(clippy::cast_sign_loss)]
+				{
+					block.stack_min = block.stack_min.max((pop - height).max(0) as usize);
+					height += push - pop;
+					block.stack_growth = block.stack_growth.max(height.max(0) as usize);
+				}
+				block.gas += cost;
+				block.end = next;
+
+				if matches!(opcode, Opcode::JUMP | Opcode::JUMPI | Opcode::STOP) {
+					analysis.close(current.take());
+				}
+			}
+
+			position = next;
+		}
+		analysis.close(current);
+
+		analysis
+	}
+
+	fn close(&mut self, block: Option<BasicBlock>) {
+		if let Some(block) = block {
+			self.blocks.push(block);
+			#[allow(clippy::cast_possible_truncation)]
+			let slot = self.blocks.len() as u32;
+			self.slots[block.start] = slot;
+		}
+	}
+
+	/// Valid jump destinations, shared with the runtimes running the code.
+	#[must_use]
+	pub const fn valids(&self) -> &Arc<Valids> {
+		&self.valids
+	}
+
+	/// Basic block starting at the given position, if any.
+	#[must_use]
+	pub fn block(&self, position: usize) -> Option<&BasicBlock> {
+		match self.slots.get(position) {
+			Some(0) | None => None,
+			Some(slot) => Some(&self.blocks[*slot as usize - 1]),
+		}
+	}
+
+	/// All basic blocks, ordered by position.
+	#[must_use]
+	pub fn blocks(&self) -> &[BasicBlock] {
+		&self.blocks
+	}
+}
+
+/// Code analyses shared by an executor and its substates, keyed by code
+/// hash. Clones share the same cache, so it can be kept across transactions.
+#[derive(Clone, Debug, Default)]
+pub struct AnalysisCache(Rc<RefCell<BTreeMap<H256, Rc<CodeAnalysis>>>>);
+
+impl AnalysisCache {
+	/// Create an empty cache.
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Analysis of the code with the given hash, analyzing it on first use.
+	#[must_use]
+	pub fn get_or_analyze(&self, hash: H256, code: &[u8]) -> Rc<CodeAnalysis> {
+		self.0.borrow_mut()
+			.entry(hash)
+			.or_insert_with(|| Rc::new(CodeAnalysis::analyze(code)))
+			.clone()
+	}
+
+	/// Number of analyzed codes.
+	#[must_use]
+	pub fn len(&self) -> usize {
+		self.0.borrow().len()
+	}
+
+	/// Whether no code was analyzed yet.
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.0.borrow().is_empty()
+	}
+}
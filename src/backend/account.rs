@@ -0,0 +1,96 @@
+use crate::{Keccak, H256, U256};
+use super::Basic;
+
+/// Hash of empty code, `keccak256("")`.
+pub const EMPTY_CODE_HASH: H256 = H256([
+	0xc5, 0xd2, 0x46, 0x01, 0x86, 0xf7, 0x23, 0x3c,
+	0x92, 0x7e, 0x7d, 0xb2, 0xdc, 0xc7, 0x03, 0xc0,
+	0xe5, 0x00, 0xb6, 0x53, 0xca, 0x82, 0x27, 0x3b,
+	0x7b, 0xfa, 0xd8, 0x04, 0x5d, 0x85, 0xa4, 0x70,
+]);
+
+/// Root of an empty storage trie, `keccak256(rlp(""))`.
+pub const EMPTY_STORAGE_ROOT: H256 = H256([
+	0x56, 0xe8, 0x1f, 0x17, 0x1b, 0xcc, 0x55, 0xa6,
+	0xff, 0x83, 0x45, 0xe6, 0x92, 0xc0, 0xf8, 0x6e,
+	0x5b, 0x48, 0xe0, 0x1b, 0x99, 0x6c, 0xad, 0xc0,
+	0x01, 0x62, 0x2f, 0xb5, 0xe3, 0x63, 0xb4, 0x21,
+]);
+
+/// Account as stored in the Ethereum state trie, RLP encoded as the list of
+/// the nonce, the balance, the storage root and the code hash.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "with-codec", derive(codec::Encode, codec::Decode))]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Account {
+	/// Account nonce.
+	pub nonce: U256,
+	/// Account balance.
+	pub balance: U256,
+	/// Root of the storage trie of the account.
+	pub storage_root: H256,
+	/// Hash of the account code.
+	pub code_hash: H256,
+}
+
+impl Default for Account {
+	fn default() -> Self {
+		Self {
+			nonce: U256::zero(),
+			balance: U256::zero(),
+			storage_root: EMPTY_STORAGE_ROOT,
+			code_hash: EMPTY_CODE_HASH,
+		}
+	}
+}
+
+impl Account {
+	/// Account with the given basic information and code, hashed with
+	/// `keccak`, and the root of its storage trie.
+	pub fn new<K: Keccak + ?Sized>(keccak: &K, basic: &Basic, code: &[u8], storage_root: H256) -> Self {
+		Self {
+			nonce: basic.nonce,
+			balance: basic.balance,
+			storage_root,
+			code_hash: keccak.keccak256_h256(code),
+		}
+	}
+
+	/// Basic information of the account.
+	#[must_use]
+	pub const fn basic(&self) -> Basic {
+		Basic { balance: self.balance, nonce: self.nonce }
+	}
+
+	/// Whether the account is empty as defined by EIP-161: no code, zero
+	/// nonce and zero balance.
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.nonce.is_zero() && self.balance.is_zero() && self.code_hash == EMPTY_CODE_HASH
+	}
+}
+
+impl rlp::Encodable for Account {
+	fn rlp_append(&self, s: &mut rlp::RlpStream) {
+		s.begin_list(4);
+		s.append(&self.nonce);
+		s.append(&self.balance);
+		s.append(&self.storage_root);
+		s.append(&self.code_hash);
+	}
+}
+
+impl rlp::Decodable for Account {
+	fn decode(rlp: &rlp::Rlp) -> Result<Self, rlp::DecoderError> {
+		if rlp.item_count()? != 4 {
+			return Err(rlp::DecoderError::RlpIncorrectListLen)
+		}
+
+		Ok(Self {
+			nonce: rlp.val_at(0)?,
+			balance: rlp.val_at(1)?,
+			storage_root: rlp.val_at(2)?,
+			code_hash: rlp.val_at(3)?,
+		})
+	}
+}
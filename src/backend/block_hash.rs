@@ -0,0 +1,105 @@
+use alloc::vec::Vec;
+use crate::{H256, U256};
+
+/// Number of past blocks whose hash is returned by `BLOCKHASH`.
+pub const BLOCK_HASH_WINDOW: usize = 256;
+
+/// Whether `BLOCKHASH` returns the hash of block `number` while executing
+/// block `current`, that is for the `BLOCK_HASH_WINDOW` blocks before it.
+#[must_use]
+pub fn in_block_hash_window(current: U256, number: U256) -> bool {
+	number < current && current - number <= U256::from(BLOCK_HASH_WINDOW)
+}
+
+/// Source of the hashes of past blocks, for backends.
+pub trait BlockHashProvider {
+	/// Hash of block `number`, queried while executing block `current`.
+	/// Backends only query blocks in the `BLOCKHASH` window, see
+	/// `in_block_hash_window`.
+	fn block_hash(&self, current: U256, number: U256) -> H256;
+}
+
+/// Hashes of the previous blocks, most recent first, as in
+/// `MemoryVicinity::block_hashes`. Missing blocks have a zero hash.
+impl BlockHashProvider for [H256] {
+	fn block_hash(&self, current: U256, number: U256) -> H256 {
+		let index = current - number - U256::one();
+		if index >= U256::from(self.len()) {
+			return H256::default()
+		}
+		self[index.as_usize()]
+	}
+}
+
+impl BlockHashProvider for Vec<H256> {
+	fn block_hash(&self, current: U256, number: U256) -> H256 {
+		self.as_slice().block_hash(current, number)
+	}
+}
+
+/// Provider computing the hash of a block number with a function, for
+/// deterministic tests.
+#[derive(Clone, Copy, Debug)]
+pub struct BlockHashFn<F>(pub F);
+
+impl<F: Fn(U256) -> H256> BlockHashProvider for BlockHashFn<F> {
+	fn block_hash(&self, _current: U256, number: U256) -> H256 {
+		(self.0)(number)
+	}
+}
+
+/// Ring buffer keeping the hashes of the last `BLOCK_HASH_WINDOW` blocks
+/// pushed, older ones being overwritten.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct BlockHashRing {
+	slots: Vec<Option<(U256, H256)>>,
+}
+
+impl BlockHashRing {
+	/// Empty ring.
+	#[must_use]
+	pub const fn new() -> Self {
+		Self { slots: Vec::new() }
+	}
+
+	/// Record the hash of block `number`, replacing the block
+	/// `BLOCK_HASH_WINDOW` blocks before it.
+	pub fn push(&mut self, number: U256, hash: H256) {
+		if self.slots.is_empty() {
+			self.slots.resize(BLOCK_HASH_WINDOW, None);
+		}
+		self.slots[slot(number)] = Some((number, hash));
+	}
+
+	/// Hash of block `number`, if it is still in the ring.
+	#[must_use]
+	pub fn get(&self, number: U256) -> Option<H256> {
+		match self.slots.get(slot(number)) {
+			Some(Some((recorded, hash))) if *recorded == number => Some(*hash),
+			_ => None,
+		}
+	}
+
+	/// Number of hashes in the ring.
+	#[must_use]
+	pub fn len(&self) -> usize {
+		self.slots.iter().filter(|slot| slot.is_some()).count()
+	}
+
+	/// Whether the ring is empty.
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+}
+
+/// Slot of block `number` in a ring.
+fn slot(number: U256) -> usize {
+	(number % U256::from(BLOCK_HASH_WINDOW)).as_usize()
+}
+
+impl BlockHashProvider for BlockHashRing {
+	fn block_hash(&self, _current: U256, number: U256) -> H256 {
+		self.get(number).unwrap_or_default()
+	}
+}
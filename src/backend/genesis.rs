@@ -0,0 +1,165 @@
+//! Loading and saving the state of a memory backend in the `alloc` format of
+//! geth genesis files:
+//!
+//! ```json
+//! {
+//!   "alloc": {
+//!     "0x1111111111111111111111111111111111111111": {
+//!       "balance": "0xde0b6b3a7640000",
+//!       "nonce": "0x1",
+//!       "code": "0x6000",
+//!       "storage": { "0x00": "0x01" }
+//!     }
+//!   }
+//! }
+//! ```
+//!
+//! Other fields of the genesis file are ignored. Addresses and storage
+//! entries are hex, with or without a `0x` prefix, and balances and nonces
+//! are hex with a `0x` prefix or decimal.
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+use std::string::{String, ToString};
+use std::vec::Vec;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use crate::{H160, H256, U256};
+use super::{MemoryAccount, MemoryBackend, MemoryVicinity};
+
+/// Error loading a genesis file, see `MemoryBackend::from_genesis_json`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum GenesisError {
+	/// The file is not valid JSON, or not a genesis object.
+	Json(String),
+	/// An address, quantity or hex string is malformed.
+	InvalidValue(String),
+}
+
+impl fmt::Display for GenesisError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Json(e) => write!(f, "invalid genesis: {e}"),
+			Self::InvalidValue(value) => write!(f, "invalid genesis value: {value}"),
+		}
+	}
+}
+
+impl std::error::Error for GenesisError {}
+
+#[derive(Deserialize)]
+struct Genesis {
+	#[serde(default)]
+	alloc: BTreeMap<String, Account>,
+}
+
+#[derive(Deserialize)]
+struct Account {
+	#[serde(default)]
+	balance: Option<String>,
+	#[serde(default)]
+	nonce: Option<String>,
+	#[serde(default)]
+	code: Option<String>,
+	#[serde(default)]
+	storage: BTreeMap<String, String>,
+}
+
+impl<'vicinity> MemoryBackend<'vicinity> {
+	/// Create a memory backend with the state allocated by a geth genesis
+	/// file.
+	pub fn from_genesis_json(vicinity: &'vicinity MemoryVicinity, json: &str) -> Result<Self, GenesisError> {
+		let genesis: Genesis = serde_json::from_str(json)
+			.map_err(|e| GenesisError::Json(e.to_string()))?;
+
+		let mut state = BTreeMap::new();
+		for (address, account) in genesis.alloc {
+			let mut storage = BTreeMap::new();
+			for (index, value) in account.storage {
+				storage.insert(word(&index)?, word(&value)?);
+			}
+			state.insert(address_of(&address)?, MemoryAccount {
+				balance: account.balance.as_deref().map_or_else(|| Ok(U256::zero()), quantity)?,
+				nonce: account.nonce.as_deref().map_or_else(|| Ok(U256::zero()), quantity)?,
+				code: account.code.as_deref().map_or_else(|| Ok(Vec::new()), bytes)?,
+				storage,
+			});
+		}
+		Ok(Self::new(vicinity, state))
+	}
+
+	/// Genesis file allocating the state of the backend, loadable by
+	/// `from_genesis_json`. Zero nonces, empty code and empty storage are
+	/// left out, and zero storage values are skipped.
+	#[must_use]
+	pub fn to_genesis_json(&self) -> String {
+		let mut alloc = Map::new();
+		for (address, account) in self.state() {
+			let mut entry = Map::new();
+			entry.insert("balance".into(), format!("{:#x}", account.balance).into());
+			if !account.nonce.is_zero() {
+				entry.insert("nonce".into(), format!("{:#x}", account.nonce).into());
+			}
+			if !account.code.is_empty() {
+				entry.insert("code".into(), hex(&account.code).into());
+			}
+			let storage = account.storage.iter()
+				.filter(|(_, value)| !value.is_zero())
+				.map(|(index, value)| (format!("{:?}", H256::from(*index)), Value::from(format!("{:?}", H256::from(*value)))))
+				.collect::<Map<_, _>>();
+			if !storage.is_empty() {
+				entry.insert("storage".into(), Value::Object(storage));
+			}
+			alloc.insert(format!("{address:?}"), Value::Object(entry));
+		}
+		let mut genesis = Map::new();
+		genesis.insert("alloc".into(), Value::Object(alloc));
+		Value::Object(genesis).to_string()
+	}
+}
+
+fn hex(data: &[u8]) -> String {
+	let mut s = String::with_capacity(2 + data.len() * 2);
+	s.push_str("0x");
+	for byte in data {
+		let _ = write!(s, "{byte:02x}");
+	}
+	s
+}
+
+fn bytes(s: &str) -> Result<Vec<u8>, GenesisError> {
+	let digits = s.strip_prefix("0x").unwrap_or(s);
+	digits.as_bytes().chunks(2)
+		.map(|pair| {
+			core::str::from_utf8(pair).ok()
+				.filter(|pair| pair.len() == 2)
+				.and_then(|pair| u8::from_str_radix(pair, 16).ok())
+				.ok_or_else(|| GenesisError::InvalidValue(s.into()))
+		})
+		.collect()
+}
+
+fn address_of(s: &str) -> Result<H160, GenesisError> {
+	let data = bytes(s)?;
+	if data.len() != 20 {
+		return Err(GenesisError::InvalidValue(s.into()));
+	}
+	Ok(H160::from_slice(&data))
+}
+
+/// Storage index or value, hex of at most 32 bytes.
+fn word(s: &str) -> Result<U256, GenesisError> {
+	let digits = s.strip_prefix("0x").unwrap_or(s);
+	if digits.len() > 64 {
+		return Err(GenesisError::InvalidValue(s.into()));
+	}
+	U256::from_str_radix(digits, 16).map_err(|_| GenesisError::InvalidValue(s.into()))
+}
+
+fn quantity(s: &str) -> Result<U256, GenesisError> {
+	let value = s.strip_prefix("0x").map_or_else(
+		|| U256::from_dec_str(s).ok(),
+		|digits| U256::from_str_radix(digits, 16).ok(),
+	);
+	value.ok_or_else(|| GenesisError::InvalidValue(s.into()))
+}
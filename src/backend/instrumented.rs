@@ -0,0 +1,214 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use evm_runtime::CreateScheme;
+use super::{Apply, ApplyBackend, Backend, Basic, CallInterceptor, Log, LogSink};
+use crate::{Keccak, H160, H256, U256};
+
+/// Listener notified about the state read from a backend, see
+/// `InstrumentedBackend`.
+pub trait ReadListener {
+	/// Existence, balance, nonce or storage emptiness of `address` is read.
+	fn on_account_read(&self, _address: H160) {}
+	/// Code, code size or code hash of `address` is read.
+	fn on_code_read(&self, _address: H160) {}
+	/// Storage of `address` at `index` is read.
+	fn on_storage_read(&self, _address: H160, _index: U256) {}
+}
+
+impl<L: ReadListener + ?Sized> ReadListener for &L {
+	fn on_account_read(&self, address: H160) { (**self).on_account_read(address) }
+	fn on_code_read(&self, address: H160) { (**self).on_code_read(address) }
+	fn on_storage_read(&self, address: H160, index: U256) { (**self).on_storage_read(address, index) }
+}
+
+/// Read listener recording the state entries read, for instance to build
+/// the witness of a stateless client or to prewarm a cache.
+#[derive(Debug, Default)]
+pub struct StateReads {
+	accounts: RefCell<BTreeSet<H160>>,
+	code: RefCell<BTreeSet<H160>>,
+	storage: RefCell<BTreeMap<H160, BTreeSet<U256>>>,
+	reads: RefCell<usize>,
+}
+
+impl StateReads {
+	/// Recorder without any read yet.
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Accounts whose existence, balance or nonce was read.
+	#[must_use]
+	pub fn accounts(&self) -> BTreeSet<H160> {
+		self.accounts.borrow().clone()
+	}
+
+	/// Accounts whose code was read.
+	#[must_use]
+	pub fn code(&self) -> BTreeSet<H160> {
+		self.code.borrow().clone()
+	}
+
+	/// Storage keys read, per account.
+	#[must_use]
+	pub fn storage(&self) -> BTreeMap<H160, BTreeSet<U256>> {
+		self.storage.borrow().clone()
+	}
+
+	/// Number of reads, repeated reads of the same entry included.
+	#[must_use]
+	pub fn reads(&self) -> usize {
+		*self.reads.borrow()
+	}
+}
+
+impl ReadListener for StateReads {
+	fn on_account_read(&self, address: H160) {
+		*self.reads.borrow_mut() += 1;
+		self.accounts.borrow_mut().insert(address);
+	}
+
+	fn on_code_read(&self, address: H160) {
+		*self.reads.borrow_mut() += 1;
+		self.code.borrow_mut().insert(address);
+	}
+
+	fn on_storage_read(&self, address: H160, index: U256) {
+		*self.reads.borrow_mut() += 1;
+		self.storage.borrow_mut().entry(address).or_default().insert(index);
+	}
+}
+
+/// Backend notifying a listener about every account, code and storage read,
+/// and serving them from the inner backend.
+///
+/// The executor memoizes the account information it reads within a
+/// transaction, so repeated reads of the same account are only seen once.
+#[derive(Debug)]
+pub struct InstrumentedBackend<B, L> {
+	inner: B,
+	listener: L,
+}
+
+impl<B, L> InstrumentedBackend<B, L> {
+	/// Wrap a backend, notifying `listener` about its reads.
+	#[must_use]
+	pub const fn new(inner: B, listener: L) -> Self {
+		Self { inner, listener }
+	}
+
+	/// Get the inner backend.
+	#[must_use]
+	pub const fn inner(&self) -> &B {
+		&self.inner
+	}
+
+	/// Get the listener.
+	#[must_use]
+	pub const fn listener(&self) -> &L {
+		&self.listener
+	}
+
+	/// Deconstruct into the inner backend and the listener.
+	#[must_use]
+	pub fn into_parts(self) -> (B, L) {
+		(self.inner, self.listener)
+	}
+}
+
+impl<B: Keccak, L> Keccak for InstrumentedBackend<B, L> {
+	fn keccak256_h256(&self, data: &[u8]) -> H256 { self.inner.keccak256_h256(data) }
+	fn keccak256_h256_v(&self, data: &[&[u8]]) -> H256 { self.inner.keccak256_h256_v(data) }
+}
+
+impl<B: Backend, L: ReadListener> Backend for InstrumentedBackend<B, L> {
+	fn gas_price(&self) -> U256 { self.inner.gas_price() }
+	fn origin(&self) -> H160 { self.inner.origin() }
+	fn block_hash(&self, number: U256) -> H256 { self.inner.block_hash(number) }
+	fn block_number(&self) -> U256 { self.inner.block_number() }
+	fn block_coinbase(&self) -> H160 { self.inner.block_coinbase() }
+	fn block_timestamp(&self) -> U256 { self.inner.block_timestamp() }
+	fn block_difficulty(&self) -> U256 { self.inner.block_difficulty() }
+	fn block_gas_limit(&self) -> U256 { self.inner.block_gas_limit() }
+	fn chain_id(&self) -> U256 { self.inner.chain_id() }
+
+	fn exists(&self, address: H160) -> bool {
+		self.listener.on_account_read(address);
+		self.inner.exists(address)
+	}
+
+	fn basic(&self, address: H160) -> Basic {
+		self.listener.on_account_read(address);
+		self.inner.basic(address)
+	}
+
+	fn code_hash(&self, address: H160) -> H256 {
+		self.listener.on_code_read(address);
+		self.inner.code_hash(address)
+	}
+
+	fn code_size(&self, address: H160) -> usize {
+		self.listener.on_code_read(address);
+		self.inner.code_size(address)
+	}
+
+	fn code(&self, address: H160) -> Vec<u8> {
+		self.listener.on_code_read(address);
+		self.inner.code(address)
+	}
+
+	fn code_range(&self, address: H160, offset: usize, len: usize) -> Vec<u8> {
+		self.listener.on_code_read(address);
+		self.inner.code_range(address, offset, len)
+	}
+
+	fn valids(&self, address: H160) -> Vec<u8> {
+		self.listener.on_code_read(address);
+		self.inner.valids(address)
+	}
+
+	fn code_and_valids(&self, address: H160) -> (Vec<u8>, Vec<u8>) {
+		self.listener.on_code_read(address);
+		self.inner.code_and_valids(address)
+	}
+
+	fn storage(&self, address: H160, index: U256) -> U256 {
+		self.listener.on_storage_read(address, index);
+		self.inner.storage(address, index)
+	}
+
+	fn storage_multi(&self, address: H160, indices: &[U256]) -> Vec<U256> {
+		for index in indices {
+			self.listener.on_storage_read(address, *index);
+		}
+		self.inner.storage_multi(address, indices)
+	}
+
+	fn create(&self, scheme: &CreateScheme, address: &H160) { self.inner.create(scheme, address) }
+
+	fn has_storage(&self, address: H160) -> bool {
+		self.listener.on_account_read(address);
+		self.inner.has_storage(address)
+	}
+
+	fn call_interceptor(&self) -> Option<&dyn CallInterceptor> { self.inner.call_interceptor() }
+
+	fn log_sink(&self) -> Option<&dyn LogSink> { self.inner.log_sink() }
+}
+
+impl<B: ApplyBackend, L> ApplyBackend for InstrumentedBackend<B, L> {
+	fn apply<A, I, T>(
+		&mut self,
+		values: A,
+		logs: T,
+		delete_empty: bool,
+	) where
+		A: IntoIterator<Item=Apply<I>>,
+		I: IntoIterator<Item=(U256, U256)>,
+		T: IntoIterator<Item=Log>,
+	{
+		self.inner.apply(values, logs, delete_empty);
+	}
+}
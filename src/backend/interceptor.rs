@@ -0,0 +1,74 @@
+use alloc::vec::Vec;
+use crate::{Context, ExitReason, Transfer, H160};
+use crate::executor::FrameKind;
+
+/// Call handed to a `CallInterceptor`, once the value is transferred and
+/// before the code of the callee runs.
+#[derive(Clone, Debug)]
+pub struct CallRequest {
+	/// Address whose code is called.
+	pub code_address: H160,
+	/// Value transfer of the call, already applied.
+	pub transfer: Option<Transfer>,
+	/// Call input.
+	pub input: Vec<u8>,
+	/// Context the code would run in.
+	pub context: Context,
+	/// Gas available to the call.
+	pub gas_limit: u64,
+	/// Whether the call must not modify the state.
+	pub is_static: bool,
+	/// Whether the call is the top-level frame of a transaction or comes
+	/// from an opcode.
+	pub kind: FrameKind,
+	/// Call depth the interceptor runs at. An interceptor executing nested
+	/// calls should start its executor at that depth, see
+	/// `StackExecutor::with_depth`, so that recursion is bounded by the call
+	/// limit.
+	pub depth: usize,
+}
+
+/// Result of an intercepted call.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CallResponse {
+	/// Exit reason of the call.
+	pub reason: ExitReason,
+	/// Return or revert data.
+	pub output: Vec<u8>,
+	/// Gas used by the call, charged to the calling frame. A call using more
+	/// than its gas limit fails with `OutOfGas`.
+	pub gas_used: u64,
+	/// Gas refunded by the call, kept if it succeeds. Refunds are capped at
+	/// the end of the transaction as for code run by the executor.
+	pub gas_refunded: i64,
+}
+
+impl CallResponse {
+	/// Response with the given exit reason, output and gas used, and no
+	/// refund.
+	#[must_use]
+	pub const fn new(reason: ExitReason, output: Vec<u8>, gas_used: u64) -> Self {
+		Self { reason, output, gas_used, gas_refunded: 0 }
+	}
+
+	/// Set the gas refund of the call.
+	#[must_use]
+	pub const fn with_refund(mut self, gas_refunded: i64) -> Self {
+		self.gas_refunded = gas_refunded;
+		self
+	}
+}
+
+/// Handler of calls in place of the code of the callee, for instance to
+/// bridge calls to another executor or to native code, see
+/// `Backend::call_interceptor`.
+///
+/// The call runs in a frame of the executor: its gas is reconciled with the
+/// caller as for other frames. A successful call keeps the state of the
+/// frame, the value transfer, and its refund; a reverted call gives back its
+/// unused gas; a failed call consumes all of its gas. State changed by the
+/// interceptor itself is outside of the executor and is not reverted.
+pub trait CallInterceptor {
+	/// Handle `call`, or return `None` to run the code of the callee.
+	fn intercept(&self, call: &CallRequest) -> Option<CallResponse>;
+}
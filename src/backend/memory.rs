@@ -1,23 +1,28 @@
 use alloc::vec::Vec;
-use alloc::collections::BTreeMap;
-use core::convert::Infallible;
+use alloc::collections::{BTreeMap, BTreeSet};
+use core::fmt;
 use sha3::{Digest, Keccak256};
-use super::{Basic, Backend, ApplyBackend, Apply, Log};
+use super::{
+	in_block_hash_window, Basic, Backend, BlockHashProvider, ApplyBackend, Apply, AppliedSummary, ApplyError,
+	Log,
+};
 use evm_runtime::CreateScheme;
-use crate::{Capture, Transfer, ExitReason, Valids, H160, H256, U256};
+use crate::{Keccak, Valids, H160, H256, U256};
 
 /// Vivinity value of a memory backend.
 #[derive(Clone, Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "with-codec", derive(codec::Encode, codec::Decode))]
 #[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MemoryVicinity {
-	/// Gas price.
+	/// Gas price, unless given by the transaction context of the executor,
+	/// see `StackExecutor::with_tx_context`.
 	pub gas_price: U256,
-	/// Origin.
+	/// Origin, unless given by the transaction context of the executor.
 	pub origin: H160,
 	/// Chain ID.
 	pub chain_id: U256,
-	/// Environmental block hashes.
+	/// Hashes of the previous blocks, most recent first, unless the backend
+	/// has a block hash provider, see `MemoryBackend::with_block_hashes`.
 	pub block_hashes: Vec<H256>,
 	/// Environmental block number.
 	pub block_number: U256,
@@ -43,47 +48,184 @@ pub struct MemoryAccount {
 	/// Full account storage.
 	pub storage: BTreeMap<U256, U256>,
 	/// Account code.
+	#[cfg_attr(feature = "serde-hex", serde(with = "crate::serde_hex"))]
 	pub code: Vec<u8>,
 }
 
 /// Memory backend, storing all state values in a `BTreeMap` in memory.
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 pub struct MemoryBackend<'vicinity> {
 	vicinity: &'vicinity MemoryVicinity,
+	block_hashes: Option<&'vicinity dyn BlockHashProvider>,
 	state: BTreeMap<H160, MemoryAccount>,
 	logs: Vec<Log>,
 }
 
+/// Outcome of committing an apply.
+enum Committed {
+	/// The account is modified or created.
+	Kept,
+	/// The account is removed from the state.
+	Removed,
+	/// Deleted account that was not in the state.
+	Unchanged,
+}
+
+impl fmt::Debug for MemoryBackend<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("MemoryBackend")
+			.field("vicinity", &self.vicinity)
+			.field("state", &self.state)
+			.field("logs", &self.logs)
+			.finish_non_exhaustive()
+	}
+}
+
 impl<'vicinity> MemoryBackend<'vicinity> {
 	/// Create a new memory backend.
 	#[must_use]
 	pub fn new(vicinity: &'vicinity MemoryVicinity, state: BTreeMap<H160, MemoryAccount>) -> Self {
 		Self {
 			vicinity,
+			block_hashes: None,
 			state,
 			logs: Vec::new(),
 		}
 	}
 
+	/// Serve `BLOCKHASH` from `provider` instead of the block hashes of the
+	/// vicinity.
+	#[must_use]
+	pub fn with_block_hashes(mut self, provider: &'vicinity dyn BlockHashProvider) -> Self {
+		self.block_hashes = Some(provider);
+		self
+	}
+
 	/// Get the underlying `BTreeMap` storing the state.
 	#[must_use]
 	pub const fn state(&self) -> &BTreeMap<H160, MemoryAccount> {
 		&self.state
 	}
+
+	/// Apply given values and logs as a whole: all the applies are staged
+	/// and validated before the first one is committed, so a rejected batch
+	/// leaves the state and logs unchanged.
+	pub fn apply_result<A, I, L>(
+		&mut self,
+		values: A,
+		logs: L,
+		delete_empty: bool,
+	) -> Result<AppliedSummary, ApplyError> where
+		A: IntoIterator<Item=Apply<I>>,
+		I: IntoIterator<Item=(U256, U256)>,
+		L: IntoIterator<Item=Log>,
+	{
+		let mut addresses = BTreeSet::new();
+		let mut staged = Vec::new();
+		for apply in values {
+			let apply = match apply {
+				Apply::Modify { address, basic, code_and_valids, storage, reset_storage } => Apply::Modify {
+					address, basic, code_and_valids, reset_storage,
+					storage: storage.into_iter().collect::<Vec<_>>(),
+				},
+				Apply::Delete { address } => Apply::Delete { address },
+			};
+			let (Apply::Modify { address, .. } | Apply::Delete { address }) = &apply;
+			let address = *address;
+			if !addresses.insert(address) {
+				return Err(ApplyError::DuplicateAccount(address))
+			}
+			staged.push(apply);
+		}
+		let logs = logs.into_iter().collect::<Vec<_>>();
+
+		let mut summary = AppliedSummary::default();
+		for apply in staged {
+			match self.commit(apply, delete_empty) {
+				Committed::Kept => summary.modified += 1,
+				Committed::Removed => summary.deleted += 1,
+				Committed::Unchanged => (),
+			}
+		}
+		self.logs.extend(logs);
+		Ok(summary)
+	}
+
+	/// Commit an apply, returning how the account changed.
+	fn commit<I: IntoIterator<Item=(U256, U256)>>(&mut self, apply: Apply<I>, delete_empty: bool) -> Committed {
+		match apply {
+			Apply::Modify {
+				address, basic, code_and_valids, storage, reset_storage,
+			} => {
+				let is_empty = {
+					let account = self.state.entry(address).or_default();
+					account.balance = basic.balance;
+					account.nonce = basic.nonce;
+					if let Some((code, _valids)) = code_and_valids {
+						account.code = code;
+					}
+
+					if reset_storage {
+						account.storage = BTreeMap::new();
+					}
+
+					let zeros = account.storage.iter()
+						.filter(|(_, v)| v == &&U256::zero())
+						.map(|(k, _)| *k)
+						.collect::<Vec<U256>>();
+
+					for zero in zeros {
+						account.storage.remove(&zero);
+					}
+
+					for (index, value) in storage {
+						if value == U256::zero() {
+							account.storage.remove(&index);
+						} else {
+							account.storage.insert(index, value);
+						}
+					}
+
+					account.balance == U256::zero() &&
+						account.nonce == U256::zero() &&
+						account.code.is_empty()
+				};
+
+				if is_empty && delete_empty {
+					self.state.remove(&address);
+					return Committed::Removed
+				}
+				Committed::Kept
+			},
+			Apply::Delete {
+				address,
+			} => {
+				if self.state.remove(&address).is_some() {
+					Committed::Removed
+				} else {
+					Committed::Unchanged
+				}
+			},
+		}
+	}
+
+	/// Deconstruct the backend, returning the underlying `BTreeMap` storing
+	/// the state.
+	#[must_use]
+	pub fn into_state(self) -> BTreeMap<H160, MemoryAccount> {
+		self.state
+	}
 }
 
 impl<'vicinity> Backend for MemoryBackend<'vicinity> {
 	fn gas_price(&self) -> U256 { self.vicinity.gas_price }
 	fn origin(&self) -> H160 { self.vicinity.origin }
 	fn block_hash(&self, number: U256) -> H256 {
-		if number >= self.vicinity.block_number ||
-			self.vicinity.block_number - number - U256::one() >= U256::from(self.vicinity.block_hashes.len())
-		{
-			H256::default()
-		} else {
-			let index = (self.vicinity.block_number - number - U256::one()).as_usize();
-			self.vicinity.block_hashes[index]
+		let current = self.vicinity.block_number;
+		if !in_block_hash_window(current, number) {
+			return H256::default()
 		}
+		self.block_hashes.unwrap_or(&self.vicinity.block_hashes).block_hash(current, number)
 	}
 	fn block_number(&self) -> U256 { self.vicinity.block_number }
 	fn block_coinbase(&self) -> H160 { self.vicinity.block_coinbase }
@@ -119,30 +261,35 @@ impl<'vicinity> Backend for MemoryBackend<'vicinity> {
 		self.state.get(&address).map(|v| v.code.clone()).unwrap_or_default()
 	}
 
+	fn code_range(&self, address: H160, offset: usize, len: usize) -> Vec<u8> {
+		self.state.get(&address).and_then(|v| {
+			let end = offset.checked_add(len)?;
+			v.code.get(offset..core::cmp::min(end, v.code.len()))
+		}).map(<[u8]>::to_vec).unwrap_or_default()
+	}
+
 	fn valids(&self, address: H160) -> Vec<u8> {
 		self.state.get(&address).map(|v| Valids::compute(&v.code)).unwrap_or_default()
 	}
 
+	fn code_and_valids(&self, address: H160) -> (Vec<u8>, Vec<u8>) {
+		self.state.get(&address).map(|v| (v.code.clone(), Valids::compute(&v.code))).unwrap_or_default()
+	}
+
 	fn storage(&self, address: H160, index: U256) -> U256 {
 		self.state.get(&address)
 			.map_or(U256::zero(), |v|
 				v.storage.get(&index).cloned().unwrap_or_else(U256::zero))
 	}
 
-	fn create(&self, _scheme: &CreateScheme, _address: &H160) {}
-
-	fn call_inner(&self,
-		_code_address: H160,
-		_transfer: Option<Transfer>,
-		_input: Vec<u8>,
-		_target_gas: Option<u64>,
-		_is_static: bool,
-		_take_l64: bool,
-		_take_stipend: bool,
-	) -> Option<Capture<(ExitReason, Vec<u8>), Infallible>> {
-		None
+	fn has_storage(&self, address: H160) -> bool {
+		self.state.get(&address).is_some_and(|v| v.storage.values().any(|value| !value.is_zero()))
 	}
 
+	fn create(&self, _scheme: &CreateScheme, _address: &H160) {}
+}
+
+impl Keccak for MemoryBackend<'_> {
 	fn keccak256_h256(&self, data: &[u8]) -> H256 {
 		H256::from_slice(Keccak256::digest(data).as_slice())
 	}
@@ -168,54 +315,7 @@ impl<'vicinity> ApplyBackend for MemoryBackend<'vicinity> {
 		L: IntoIterator<Item=Log>,
 	{
 		for apply in values {
-			match apply {
-				Apply::Modify {
-					address, basic, code_and_valids, storage, reset_storage,
-				} => {
-					let is_empty = {
-						let account = self.state.entry(address).or_insert_with(Default::default);
-						account.balance = basic.balance;
-						account.nonce = basic.nonce;
-						if let Some((code, _valids)) = code_and_valids {
-							account.code = code;
-						}
-
-						if reset_storage {
-							account.storage = BTreeMap::new();
-						}
-
-						let zeros = account.storage.iter()
-							.filter(|(_, v)| v == &&U256::zero())
-							.map(|(k, _)| *k)
-							.collect::<Vec<U256>>();
-
-						for zero in zeros {
-							account.storage.remove(&zero);
-						}
-
-						for (index, value) in storage {
-							if value == U256::zero() {
-								account.storage.remove(&index);
-							} else {
-								account.storage.insert(index, value);
-							}
-						}
-
-						account.balance == U256::zero() &&
-							account.nonce == U256::zero() &&
-							account.code.is_empty()
-					};
-
-					if is_empty && delete_empty {
-						self.state.remove(&address);
-					}
-				},
-				Apply::Delete {
-					address,
-				} => {
-					self.state.remove(&address);
-				},
-			}
+			self.commit(apply, delete_empty);
 		}
 
 		for log in logs {
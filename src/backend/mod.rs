@@ -3,14 +3,39 @@
 //! Backends store state information of the VM, and exposes it to runtime.
 
 extern crate alloc;
+mod account;
+mod block_hash;
+#[cfg(feature = "genesis")]
+mod genesis;
+mod instrumented;
+mod interceptor;
+#[cfg(feature = "software-keccak")]
 mod memory;
+#[cfg(feature = "std")]
+mod paged;
+mod squash;
+#[cfg(feature = "software-keccak")]
+mod witness;
 
+#[cfg(feature = "software-keccak")]
 pub use self::memory::{MemoryBackend, MemoryVicinity, MemoryAccount};
+#[cfg(feature = "genesis")]
+pub use self::genesis::GenesisError;
+#[cfg(feature = "software-keccak")]
+pub use self::witness::{Witness, WitnessBackend};
+#[cfg(feature = "std")]
+pub use self::paged::{PagedCode, PagedCodeBackend, CODE_PAGE_SIZE, DEFAULT_PAGE_LIMIT};
+pub use self::account::{Account, EMPTY_CODE_HASH, EMPTY_STORAGE_ROOT};
+pub use self::block_hash::{
+	in_block_hash_window, BlockHashFn, BlockHashProvider, BlockHashRing, BLOCK_HASH_WINDOW,
+};
+pub use self::instrumented::{InstrumentedBackend, ReadListener, StateReads};
+pub use self::interceptor::{CallInterceptor, CallRequest, CallResponse};
+pub use self::squash::ApplySet;
 
 use alloc::vec::Vec;
-use core::convert::Infallible;
 use evm_runtime::CreateScheme;
-use crate::{Capture, Transfer, ExitReason, H160, H256, U256};
+use crate::{Keccak, H160, H256, U256};
 
 /// Basic account information.
 #[derive(Clone, Eq, PartialEq, Debug, Default)]
@@ -36,13 +61,51 @@ pub struct Log {
 	/// topics
 	pub topics: Vec<H256>,
 	/// data
-	#[cfg_attr(feature = "with-serde", serde(with = "serde_bytes"))]
+	#[cfg_attr(feature = "with-serde", serde(with = "crate::serde_hex"))]
 	pub data: Bytes,
 }
 //pub use ethereum::Log;
 
+/// Encoded as the list of the address, the topics and the data, as in
+/// Ethereum receipts.
+impl rlp::Encodable for Log {
+	fn rlp_append(&self, s: &mut rlp::RlpStream) {
+		s.begin_list(3);
+		s.append(&self.address);
+		s.append_list(&self.topics);
+		s.append(&self.data);
+	}
+}
+
+impl rlp::Decodable for Log {
+	fn decode(rlp: &rlp::Rlp) -> Result<Self, rlp::DecoderError> {
+		if rlp.item_count()? != 3 {
+			return Err(rlp::DecoderError::RlpIncorrectListLen)
+		}
+
+		Ok(Self {
+			address: rlp.val_at(0)?,
+			topics: rlp.list_at(1)?,
+			data: rlp.val_at(2)?,
+		})
+	}
+}
+
+/// Log emitted during execution, with whether its frame or one of the
+/// frames above it reverted, see `StackExecutor::with_reverted_logs`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "with-codec", derive(codec::Encode, codec::Decode))]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TracedLog {
+	/// Log.
+	pub log: Log,
+	/// Whether the log was discarded by a revert or an error.
+	pub reverted: bool,
+}
+
 /// Apply state operation.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Apply<I> {
 	/// Modify or create at address.
 	Modify {
@@ -51,6 +114,7 @@ pub enum Apply<I> {
 		/// Basic information of the address.
 		basic: Basic,
 		/// Code. `None` means leaving it unchanged.
+		#[cfg_attr(feature = "serde-hex", serde(with = "serde_code_and_valids"))]
 		code_and_valids: Option<(Vec<u8>, Vec<u8>)>,
 		/// Storage iterator.
 		storage: I,
@@ -65,8 +129,76 @@ pub enum Apply<I> {
 	},
 }
 
+/// Code and valids of `Apply::Modify` as hex strings.
+#[cfg(feature = "serde-hex")]
+mod serde_code_and_valids {
+	use alloc::vec::Vec;
+	use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+	type CodeAndValids = Option<(Vec<u8>, Vec<u8>)>;
+
+	#[derive(Serialize)]
+	struct Borrowed<'a>(
+		#[serde(with = "crate::serde_hex")] &'a [u8],
+		#[serde(with = "crate::serde_hex")] &'a [u8],
+	);
+
+	#[derive(Deserialize)]
+	struct Owned(
+		#[serde(with = "crate::serde_hex")] Vec<u8>,
+		#[serde(with = "crate::serde_hex")] Vec<u8>,
+	);
+
+	#[allow(clippy::ref_option)]
+	pub fn serialize<S: Serializer>(value: &CodeAndValids, serializer: S) -> Result<S::Ok, S::Error> {
+		value.as_ref().map(|(code, valids)| Borrowed(code, valids)).serialize(serializer)
+	}
+
+	pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<CodeAndValids, D::Error> {
+		Ok(Option::<Owned>::deserialize(deserializer)?.map(|Owned(code, valids)| (code, valids)))
+	}
+}
+
+/// Error rejecting a batch of applies before any of them is committed, see
+/// `MemoryBackend::apply_result`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "with-codec", derive(codec::Encode, codec::Decode))]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ApplyError {
+	/// The account is changed by more than one apply of the batch.
+	DuplicateAccount(H160),
+}
+
+/// Accounts changed by a committed batch of applies.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "with-codec", derive(codec::Encode, codec::Decode))]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AppliedSummary {
+	/// Accounts modified or created, and kept in the state.
+	pub modified: usize,
+	/// Accounts removed from the state, either deleted or left empty. A
+	/// deleted account missing from the state is not counted.
+	pub deleted: usize,
+}
+
+/// Receiver of logs as they are emitted during execution.
+///
+/// Logs are reported as soon as the `LOG` opcode runs, before the frame
+/// emitting them returns, so the stream is speculative. When a frame reverts
+/// or fails, `revert` is called with the position of its first log, and the
+/// logs reported from that position on are not part of the transaction
+/// anymore. Their positions are reused by the logs emitted next.
+pub trait LogSink {
+	/// Notify a new log, together with its position in the transaction.
+	fn log(&self, log: &Log, position: usize);
+
+	/// Notify that the logs reported from `position` on were dropped, their
+	/// frame having reverted or failed.
+	fn revert(&self, position: usize);
+}
+
 /// EVM backend.
-pub trait Backend {
+pub trait Backend: Keccak {
 	/// Gas price.
 	fn gas_price(&self) -> U256;
 	/// Origin.
@@ -96,31 +228,52 @@ pub trait Backend {
 	fn code_size(&self, address: H160) -> usize;
 	/// Get account code.
 	fn code(&self, address: H160) -> Vec<u8>;
+	/// Get `len` bytes of account code at `offset`, truncated to the end of
+	/// the code. Backends storing large code should override the default,
+	/// which reads the full code.
+	fn code_range(&self, address: H160, offset: usize, len: usize) -> Vec<u8> {
+		let code = self.code(address);
+		match offset.checked_add(len) {
+			Some(end) if offset <= code.len() => code[offset..core::cmp::min(end, code.len())].to_vec(),
+			_ => Vec::new(),
+		}
+	}
 	/// Get account code valids.
 	fn valids(&self, address: H160) -> Vec<u8>;
+	/// Get account code and valids together, as needed to run the code.
+	/// Backends with costly lookups should override the default, which
+	/// queries them separately.
+	fn code_and_valids(&self, address: H160) -> (Vec<u8>, Vec<u8>) {
+		(self.code(address), self.valids(address))
+	}
 	/// Get storage value of address at index.
 	fn storage(&self, address: H160, index: U256) -> U256;
+	/// Get storage values of address at the given indices, in order.
+	/// Backends with costly lookups, such as those fetching state over RPC,
+	/// should override the default, which queries the slots one by one.
+	fn storage_multi(&self, address: H160, indices: &[U256]) -> Vec<U256> {
+		indices.iter().map(|index| self.storage(address, *index)).collect()
+	}
+	/// Whether the account has a non-zero storage slot, for the storage
+	/// collision check of creates (EIP-7610). Backends that cannot tell
+	/// return `false`, the default.
+	fn has_storage(&self, _address: H160) -> bool {
+		false
+	}
 
 	/// Notification about create new address
 	fn create(&self, scheme: &CreateScheme, address: &H160);
 
-	/// Hook on Solidity's call
-	#[allow(clippy::too_many_arguments)]
-	fn call_inner(&self,
-		code_address: H160,
-		transfer: Option<Transfer>,
-		input: Vec<u8>,
-		target_gas: Option<u64>,
-		is_static: bool,
-		take_l64: bool,
-		take_stipend: bool,
-	) -> Option<Capture<(ExitReason, Vec<u8>), Infallible>>;
-
-	/// Get keccak hash from slice
-	fn keccak256_h256(&self, data: &[u8]) -> H256;
-
-	/// Get keccak hash from array of slices
-	fn keccak256_h256_v(&self, data: &[&[u8]]) -> H256;
+	/// Handler of calls in place of the code of the callee, see
+	/// `CallInterceptor`.
+	fn call_interceptor(&self) -> Option<&dyn CallInterceptor> {
+		None
+	}
+
+	/// Sink notified about logs as they are emitted.
+	fn log_sink(&self) -> Option<&dyn LogSink> {
+		None
+	}
 }
 
 /// EVM backend that can apply changes.
@@ -0,0 +1,273 @@
+use alloc::collections::BTreeMap;
+use alloc::collections::btree_map::Entry;
+use alloc::vec::Vec;
+use core::cell::{Cell, RefCell};
+use core::convert::TryFrom;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+use evm_runtime::CreateScheme;
+use super::{Backend, Basic, CallInterceptor, LogSink};
+use crate::{Keccak, Valids, H160, H256, U256};
+
+/// Size of a page of code read from disk.
+pub const CODE_PAGE_SIZE: usize = 4096;
+
+/// Number of pages a `PagedCode` keeps in memory, unless set with
+/// `PagedCode::with_page_limit`.
+pub const DEFAULT_PAGE_LIMIT: usize = 64;
+
+/// Contract code stored in a file, paged in on demand.
+///
+/// Opening it only reads the file metadata, so the size and hash of large
+/// contracts are known without loading them. The file stays open. Partial
+/// reads load and keep the pages they touch, the least recently used ones
+/// being dropped past the page limit, while a full load for execution is
+/// not kept around.
+#[derive(Debug)]
+pub struct PagedCode {
+	file: RefCell<File>,
+	len: usize,
+	hash: H256,
+	page_limit: usize,
+	/// Pages by index, with the tick of their last use.
+	pages: RefCell<BTreeMap<usize, (u64, Vec<u8>)>>,
+	tick: Cell<u64>,
+}
+
+impl PagedCode {
+	/// Open the code stored at `path`, whose keccak hash is `hash`.
+	pub fn open<P: AsRef<Path>>(path: P, hash: H256) -> io::Result<Self> {
+		let file = File::open(path)?;
+		let len = usize::try_from(file.metadata()?.len())
+			.map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "code file too large"))?;
+		Ok(Self {
+			file: RefCell::new(file),
+			len,
+			hash,
+			page_limit: DEFAULT_PAGE_LIMIT,
+			pages: RefCell::new(BTreeMap::new()),
+			tick: Cell::new(0),
+		})
+	}
+
+	/// Keep at most `page_limit` pages in memory, at least one.
+	#[must_use]
+	pub fn with_page_limit(mut self, page_limit: usize) -> Self {
+		self.page_limit = page_limit.max(1);
+		self
+	}
+
+	/// Code size in bytes.
+	#[must_use]
+	pub const fn len(&self) -> usize {
+		self.len
+	}
+
+	/// Whether the code is empty.
+	#[must_use]
+	pub const fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	/// Keccak hash of the code.
+	#[must_use]
+	pub const fn hash(&self) -> H256 {
+		self.hash
+	}
+
+	/// Number of pages kept in memory.
+	#[must_use]
+	pub fn resident_pages(&self) -> usize {
+		self.pages.borrow().len()
+	}
+
+	/// Read `len` bytes at `offset`, truncated to the end of the code,
+	/// paging in only the touched pages.
+	pub fn read(&self, offset: usize, len: usize) -> io::Result<Vec<u8>> {
+		let end = offset.saturating_add(len).min(self.len);
+		let mut out = Vec::with_capacity(end.saturating_sub(offset));
+		let mut position = offset;
+		while position < end {
+			let index = position / CODE_PAGE_SIZE;
+			let page_start = index * CODE_PAGE_SIZE;
+			let tick = self.tick.get() + 1;
+			self.tick.set(tick);
+
+			let mut pages = self.pages.borrow_mut();
+			if !pages.contains_key(&index) && pages.len() >= self.page_limit {
+				let oldest = pages.iter().min_by_key(|(_, (used, _))| *used).map(|(index, _)| *index);
+				if let Some(oldest) = oldest {
+					pages.remove(&oldest);
+				}
+			}
+			let (used, page) = match pages.entry(index) {
+				Entry::Occupied(entry) => entry.into_mut(),
+				Entry::Vacant(entry) => {
+					let page_len = CODE_PAGE_SIZE.min(self.len - page_start);
+					entry.insert((tick, self.read_exact(page_start, page_len)?))
+				},
+			};
+			*used = tick;
+
+			let page_end = (page_start + page.len()).min(end);
+			out.extend_from_slice(&page[(position - page_start)..(page_end - page_start)]);
+			position = page_end;
+		}
+		Ok(out)
+	}
+
+	/// Load the whole code, without keeping it in the page cache.
+	pub fn load(&self) -> io::Result<Vec<u8>> {
+		self.read_exact(0, self.len)
+	}
+
+	fn read_exact(&self, offset: usize, len: usize) -> io::Result<Vec<u8>> {
+		let mut file = self.file.borrow_mut();
+		file.seek(SeekFrom::Start(offset as u64))?;
+		let mut buffer = alloc::vec![0; len];
+		file.read_exact(&mut buffer)?;
+		Ok(buffer)
+	}
+}
+
+/// Backend serving the code of some accounts from `PagedCode`, and
+/// everything else from the inner backend.
+///
+/// The jump destinations of paged code are computed once per code hash.
+/// Code that can no longer be read is served as empty, the error being kept
+/// for `take_error`: check it before using the results of an execution, as
+/// they were computed with a wrong code.
+#[derive(Debug)]
+pub struct PagedCodeBackend<B> {
+	inner: B,
+	code: BTreeMap<H160, PagedCode>,
+	valids: RefCell<BTreeMap<H256, Vec<u8>>>,
+	error: RefCell<Option<io::Error>>,
+}
+
+impl<B> PagedCodeBackend<B> {
+	/// Wrap a backend, without any paged code yet.
+	#[must_use]
+	pub const fn new(inner: B) -> Self {
+		Self { inner, code: BTreeMap::new(), valids: RefCell::new(BTreeMap::new()), error: RefCell::new(None) }
+	}
+
+	/// Serve the code of `address` from `code`.
+	pub fn insert(&mut self, address: H160, code: PagedCode) {
+		self.code.insert(address, code);
+		let code = &self.code;
+		self.valids.get_mut().retain(|hash, _| code.values().any(|code| code.hash == *hash));
+	}
+
+	/// Paged code of `address`, if any.
+	#[must_use]
+	pub fn paged(&self, address: H160) -> Option<&PagedCode> {
+		self.code.get(&address)
+	}
+
+	/// Take the first error reading paged code since the last call, if any.
+	pub fn take_error(&self) -> Option<io::Error> {
+		self.error.borrow_mut().take()
+	}
+
+	/// Get the inner backend.
+	#[must_use]
+	pub const fn inner(&self) -> &B {
+		&self.inner
+	}
+
+	/// Deconstruct into the inner backend.
+	#[must_use]
+	pub fn into_inner(self) -> B {
+		self.inner
+	}
+
+	/// Keep `error` for `take_error`, returning the empty code served instead.
+	fn fail(&self, error: io::Error) -> Vec<u8> {
+		self.error.borrow_mut().get_or_insert(error);
+		Vec::new()
+	}
+
+	fn load(&self, address: H160) -> Option<Vec<u8>> {
+		self.code.get(&address).map(|code| code.load().unwrap_or_else(|e| self.fail(e)))
+	}
+
+	/// Code and jump destinations of a paged account, the latter from the
+	/// cache when already computed.
+	fn load_with_valids(&self, address: H160) -> Option<(Vec<u8>, Vec<u8>)> {
+		let paged = self.code.get(&address)?;
+		let code = match paged.load() {
+			Ok(code) => code,
+			Err(e) => {
+				let code = self.fail(e);
+				let valids = Valids::compute(&code);
+				return Some((code, valids))
+			},
+		};
+		let valids = self.valids.borrow_mut()
+			.entry(paged.hash)
+			.or_insert_with(|| Valids::compute(&code))
+			.clone();
+		Some((code, valids))
+	}
+}
+
+impl<B: Keccak> Keccak for PagedCodeBackend<B> {
+	fn keccak256_h256(&self, data: &[u8]) -> H256 { self.inner.keccak256_h256(data) }
+	fn keccak256_h256_v(&self, data: &[&[u8]]) -> H256 { self.inner.keccak256_h256_v(data) }
+}
+
+impl<B: Backend> Backend for PagedCodeBackend<B> {
+	fn gas_price(&self) -> U256 { self.inner.gas_price() }
+	fn origin(&self) -> H160 { self.inner.origin() }
+	fn block_hash(&self, number: U256) -> H256 { self.inner.block_hash(number) }
+	fn block_number(&self) -> U256 { self.inner.block_number() }
+	fn block_coinbase(&self) -> H160 { self.inner.block_coinbase() }
+	fn block_timestamp(&self) -> U256 { self.inner.block_timestamp() }
+	fn block_difficulty(&self) -> U256 { self.inner.block_difficulty() }
+	fn block_gas_limit(&self) -> U256 { self.inner.block_gas_limit() }
+	fn chain_id(&self) -> U256 { self.inner.chain_id() }
+	fn exists(&self, address: H160) -> bool { self.inner.exists(address) }
+	fn basic(&self, address: H160) -> Basic { self.inner.basic(address) }
+
+	fn code_hash(&self, address: H160) -> H256 {
+		self.code.get(&address).map_or_else(|| self.inner.code_hash(address), PagedCode::hash)
+	}
+
+	fn code_size(&self, address: H160) -> usize {
+		self.code.get(&address).map_or_else(|| self.inner.code_size(address), PagedCode::len)
+	}
+
+	fn code(&self, address: H160) -> Vec<u8> {
+		self.load(address).unwrap_or_else(|| self.inner.code(address))
+	}
+
+	fn code_range(&self, address: H160, offset: usize, len: usize) -> Vec<u8> {
+		self.code.get(&address).map_or_else(|| self.inner.code_range(address, offset, len), |code| {
+			code.read(offset, len).unwrap_or_else(|e| self.fail(e))
+		})
+	}
+
+	fn valids(&self, address: H160) -> Vec<u8> {
+		let cached = self.code.get(&address).and_then(|code| self.valids.borrow().get(&code.hash).cloned());
+		cached.or_else(|| self.load_with_valids(address).map(|(_, valids)| valids))
+			.unwrap_or_else(|| self.inner.valids(address))
+	}
+
+	fn code_and_valids(&self, address: H160) -> (Vec<u8>, Vec<u8>) {
+		self.load_with_valids(address).unwrap_or_else(|| self.inner.code_and_valids(address))
+	}
+
+	fn storage(&self, address: H160, index: U256) -> U256 { self.inner.storage(address, index) }
+	fn storage_multi(&self, address: H160, indices: &[U256]) -> Vec<U256> {
+		self.inner.storage_multi(address, indices)
+	}
+	fn create(&self, scheme: &CreateScheme, address: &H160) { self.inner.create(scheme, address) }
+
+	fn has_storage(&self, address: H160) -> bool { self.inner.has_storage(address) }
+
+	fn call_interceptor(&self) -> Option<&dyn CallInterceptor> { self.inner.call_interceptor() }
+
+	fn log_sink(&self) -> Option<&dyn LogSink> { self.inner.log_sink() }
+}
@@ -0,0 +1,92 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use crate::{H160, U256};
+use super::Apply;
+
+/// Block-level set of state operations, squashing the operations of
+/// successive transactions into at most one operation per account.
+///
+/// Applying the squashed set has the same effect as applying each
+/// transaction's set in order, with fewer backend writes.
+#[derive(Clone, Debug, Default)]
+pub struct ApplySet {
+	accounts: BTreeMap<H160, Apply<BTreeMap<U256, U256>>>,
+}
+
+impl ApplySet {
+	/// Create an empty set.
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Number of accounts touched.
+	#[must_use]
+	pub fn len(&self) -> usize {
+		self.accounts.len()
+	}
+
+	/// Whether no account was touched.
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.accounts.is_empty()
+	}
+
+	/// Squash the operations of the next transaction on top of the set.
+	pub fn push<A, I>(&mut self, applies: A) where
+		A: IntoIterator<Item=Apply<I>>,
+		I: IntoIterator<Item=(U256, U256)>,
+	{
+		for apply in applies {
+			match apply {
+				Apply::Modify { address, basic, code_and_valids, storage, reset_storage } => {
+					let squashed = match self.accounts.remove(&address) {
+						None => Apply::Modify {
+							address,
+							basic,
+							code_and_valids,
+							storage: storage.into_iter().collect(),
+							reset_storage,
+						},
+						// A deleted account is recreated empty.
+						Some(Apply::Delete { .. }) => Apply::Modify {
+							address,
+							basic,
+							code_and_valids: code_and_valids.or_else(|| Some((Vec::new(), Vec::new()))),
+							storage: storage.into_iter().collect(),
+							reset_storage: true,
+						},
+						Some(Apply::Modify {
+							code_and_valids: previous_code,
+							storage: mut previous_storage,
+							reset_storage: previous_reset,
+							..
+						}) => {
+							if reset_storage {
+								previous_storage.clear();
+							}
+							previous_storage.extend(storage);
+							Apply::Modify {
+								address,
+								basic,
+								code_and_valids: code_and_valids.or(previous_code),
+								storage: previous_storage,
+								reset_storage: reset_storage || previous_reset,
+							}
+						},
+					};
+					self.accounts.insert(address, squashed);
+				},
+				Apply::Delete { address } => {
+					self.accounts.insert(address, Apply::Delete { address });
+				},
+			}
+		}
+	}
+
+	/// Squashed operations, ordered by address.
+	#[must_use]
+	pub fn into_applies(self) -> Vec<Apply<BTreeMap<U256, U256>>> {
+		self.accounts.into_values().collect()
+	}
+}
@@ -0,0 +1,213 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use evm_runtime::CreateScheme;
+use super::{in_block_hash_window, Backend, Basic, CallInterceptor, LogSink, MemoryAccount, MemoryBackend, MemoryVicinity};
+use crate::{Keccak, Valids, H160, H256, U256};
+
+/// State accessed by an execution, see `WitnessBackend::witness`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "with-codec", derive(codec::Encode, codec::Decode))]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Witness {
+	/// Environment of the block, with the block hashes read.
+	pub vicinity: MemoryVicinity,
+	/// Existing accounts read, with their code if it was read, and the
+	/// storage slots read.
+	pub state: BTreeMap<H160, MemoryAccount>,
+}
+
+impl Witness {
+	/// Memory backend holding the witness, on which the recorded execution
+	/// can be replayed.
+	#[must_use]
+	pub fn backend(&self) -> MemoryBackend<'_> {
+		MemoryBackend::new(&self.vicinity, self.state.clone())
+	}
+}
+
+/// Account read by the execution.
+#[derive(Clone, Debug)]
+struct WitnessAccount {
+	exists: bool,
+	basic: Basic,
+	code: Option<Vec<u8>>,
+	storage: BTreeMap<U256, U256>,
+}
+
+/// Backend recording the values of every account, code, storage slot and
+/// block hash read from the inner backend, so that an execution can be
+/// replayed from its witness only.
+///
+/// The first read of an account records its existence, balance and nonce,
+/// and the first read of its code, size or hash records the full code.
+/// Block hashes older than the `BLOCKHASH` window are not recorded.
+///
+/// Reads are served from the record once made, so the inner backend must not
+/// change while executions are recorded: the witness is the state before
+/// them.
+#[derive(Debug)]
+pub struct WitnessBackend<B> {
+	inner: B,
+	accounts: RefCell<BTreeMap<H160, WitnessAccount>>,
+	block_hashes: RefCell<BTreeMap<U256, H256>>,
+}
+
+impl<B: Backend> WitnessBackend<B> {
+	/// Wrap a backend, without any read recorded yet.
+	#[must_use]
+	pub const fn new(inner: B) -> Self {
+		Self { inner, accounts: RefCell::new(BTreeMap::new()), block_hashes: RefCell::new(BTreeMap::new()) }
+	}
+
+	/// Get the inner backend.
+	#[must_use]
+	pub const fn inner(&self) -> &B {
+		&self.inner
+	}
+
+	/// Deconstruct into the inner backend.
+	#[must_use]
+	pub fn into_inner(self) -> B {
+		self.inner
+	}
+
+	/// Witness of the reads recorded so far.
+	#[must_use]
+	pub fn witness(&self) -> Witness {
+		let block_number = self.inner.block_number();
+		let mut block_hashes = Vec::new();
+		for (number, hash) in self.block_hashes.borrow().iter() {
+			let index = (block_number - number - U256::one()).as_usize();
+			if block_hashes.len() <= index {
+				block_hashes.resize(index + 1, H256::default());
+			}
+			block_hashes[index] = *hash;
+		}
+
+		let vicinity = MemoryVicinity {
+			gas_price: self.inner.gas_price(),
+			origin: self.inner.origin(),
+			chain_id: self.inner.chain_id(),
+			block_hashes,
+			block_number,
+			block_coinbase: self.inner.block_coinbase(),
+			block_timestamp: self.inner.block_timestamp(),
+			block_difficulty: self.inner.block_difficulty(),
+			block_gas_limit: self.inner.block_gas_limit(),
+		};
+		let state = self.accounts.borrow().iter()
+			.filter(|(_, account)| account.exists)
+			.map(|(address, account)| (*address, MemoryAccount {
+				nonce: account.basic.nonce,
+				balance: account.basic.balance,
+				storage: account.storage.clone(),
+				code: account.code.clone().unwrap_or_default(),
+			}))
+			.collect();
+		Witness { vicinity, state }
+	}
+
+	/// Run `f` on the record of `address`, reading its account information
+	/// first if needed.
+	fn account<T>(&self, address: H160, f: impl FnOnce(&mut WitnessAccount) -> T) -> T {
+		let mut accounts = self.accounts.borrow_mut();
+		let account = accounts.entry(address).or_insert_with(|| WitnessAccount {
+			exists: self.inner.exists(address),
+			basic: self.inner.basic(address),
+			code: None,
+			storage: BTreeMap::new(),
+		});
+		f(account)
+	}
+
+	fn recorded_code<T>(&self, address: H160, f: impl FnOnce(&[u8]) -> T) -> T {
+		self.account(address, |account| {
+			f(account.code.get_or_insert_with(|| self.inner.code(address)))
+		})
+	}
+}
+
+impl<B: Keccak> Keccak for WitnessBackend<B> {
+	fn keccak256_h256(&self, data: &[u8]) -> H256 { self.inner.keccak256_h256(data) }
+	fn keccak256_h256_v(&self, data: &[&[u8]]) -> H256 { self.inner.keccak256_h256_v(data) }
+}
+
+impl<B: Backend> Backend for WitnessBackend<B> {
+	fn gas_price(&self) -> U256 { self.inner.gas_price() }
+	fn origin(&self) -> H160 { self.inner.origin() }
+
+	fn block_hash(&self, number: U256) -> H256 {
+		let hash = self.inner.block_hash(number);
+		let block_number = self.inner.block_number();
+		if in_block_hash_window(block_number, number) {
+			self.block_hashes.borrow_mut().insert(number, hash);
+		}
+		hash
+	}
+
+	fn block_number(&self) -> U256 { self.inner.block_number() }
+	fn block_coinbase(&self) -> H160 { self.inner.block_coinbase() }
+	fn block_timestamp(&self) -> U256 { self.inner.block_timestamp() }
+	fn block_difficulty(&self) -> U256 { self.inner.block_difficulty() }
+	fn block_gas_limit(&self) -> U256 { self.inner.block_gas_limit() }
+	fn chain_id(&self) -> U256 { self.inner.chain_id() }
+
+	fn exists(&self, address: H160) -> bool {
+		self.account(address, |account| account.exists)
+	}
+
+	fn basic(&self, address: H160) -> Basic {
+		self.account(address, |account| account.basic.clone())
+	}
+
+	fn code_hash(&self, address: H160) -> H256 {
+		self.recorded_code(address, |_| ());
+		self.inner.code_hash(address)
+	}
+
+	fn code_size(&self, address: H160) -> usize {
+		self.recorded_code(address, <[u8]>::len)
+	}
+
+	fn code(&self, address: H160) -> Vec<u8> {
+		self.recorded_code(address, <[u8]>::to_vec)
+	}
+
+	fn code_range(&self, address: H160, offset: usize, len: usize) -> Vec<u8> {
+		self.recorded_code(address, |code| {
+			offset.checked_add(len)
+				.and_then(|end| code.get(offset..core::cmp::min(end, code.len())))
+				.map(<[u8]>::to_vec)
+				.unwrap_or_default()
+		})
+	}
+
+	fn valids(&self, address: H160) -> Vec<u8> {
+		self.recorded_code(address, Valids::compute)
+	}
+
+	fn code_and_valids(&self, address: H160) -> (Vec<u8>, Vec<u8>) {
+		self.recorded_code(address, |code| (code.to_vec(), Valids::compute(code)))
+	}
+
+	fn storage(&self, address: H160, index: U256) -> U256 {
+		let value = self.inner.storage(address, index);
+		self.account(address, |account| account.storage.insert(index, value));
+		value
+	}
+
+	fn storage_multi(&self, address: H160, indices: &[U256]) -> Vec<U256> {
+		let values = self.inner.storage_multi(address, indices);
+		self.account(address, |account| account.storage.extend(indices.iter().copied().zip(values.iter().copied())));
+		values
+	}
+
+	fn create(&self, scheme: &CreateScheme, address: &H160) { self.inner.create(scheme, address) }
+
+	fn has_storage(&self, address: H160) -> bool { self.inner.has_storage(address) }
+
+	fn call_interceptor(&self) -> Option<&dyn CallInterceptor> { self.inner.call_interceptor() }
+
+	fn log_sink(&self) -> Option<&dyn LogSink> { self.inner.log_sink() }
+}
@@ -0,0 +1,120 @@
+//! # Block gas accounting
+//!
+//! Validation of transaction gas limits against the block gas limit, and
+//! running accounting of the gas used by the transactions of a block.
+
+use crate::U256;
+use crate::backend::Backend;
+
+/// Error validating the gas limit of a transaction.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "with-codec", derive(codec::Encode, codec::Decode))]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GasLimitError {
+	/// Transaction gas limit is above the configured per-transaction maximum.
+	TransactionGasLimitExceeded {
+		/// Gas limit of the transaction.
+		gas_limit: u64,
+		/// Maximum gas limit of a single transaction.
+		max_gas_limit: u64,
+	},
+	/// Transaction gas limit is above the gas left in the block.
+	BlockGasLimitExceeded {
+		/// Gas limit of the transaction.
+		gas_limit: u64,
+		/// Gas already used in the block.
+		gas_used: u64,
+		/// Block gas limit.
+		block_gas_limit: u64,
+	},
+}
+
+/// Running gas accounting of a block.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "with-codec", derive(codec::Encode, codec::Decode))]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BlockGas {
+	limit: u64,
+	max_transaction_gas_limit: Option<u64>,
+	gas_used: u64,
+}
+
+impl BlockGas {
+	/// Create an empty block with the given gas limit. Limits above `u64::MAX`
+	/// are capped.
+	#[must_use]
+	pub fn new(block_gas_limit: U256) -> Self {
+		Self {
+			limit: if block_gas_limit > U256::from(u64::MAX) {
+				u64::MAX
+			} else {
+				block_gas_limit.as_u64()
+			},
+			max_transaction_gas_limit: None,
+			gas_used: 0,
+		}
+	}
+
+	/// Create an empty block with the gas limit of the backend.
+	#[must_use]
+	pub fn from_backend<B: Backend>(backend: &B) -> Self {
+		Self::new(backend.block_gas_limit())
+	}
+
+	/// Limit the gas limit of a single transaction, below the block gas
+	/// limit.
+	#[must_use]
+	pub const fn with_max_transaction_gas_limit(mut self, max_gas_limit: u64) -> Self {
+		self.max_transaction_gas_limit = Some(max_gas_limit);
+		self
+	}
+
+	/// Block gas limit.
+	#[must_use]
+	pub const fn limit(&self) -> u64 {
+		self.limit
+	}
+
+	/// Gas used by the transactions recorded so far.
+	#[must_use]
+	pub const fn gas_used(&self) -> u64 {
+		self.gas_used
+	}
+
+	/// Gas left in the block.
+	#[must_use]
+	pub const fn gas_left(&self) -> u64 {
+		self.limit.saturating_sub(self.gas_used)
+	}
+
+	/// Check that a transaction with the given gas limit fits in the block.
+	pub const fn validate(&self, gas_limit: u64) -> Result<(), GasLimitError> {
+		if let Some(max_gas_limit) = self.max_transaction_gas_limit {
+			if gas_limit > max_gas_limit {
+				return Err(GasLimitError::TransactionGasLimitExceeded { gas_limit, max_gas_limit })
+			}
+		}
+		if gas_limit > self.gas_left() {
+			return Err(GasLimitError::BlockGasLimitExceeded {
+				gas_limit,
+				gas_used: self.gas_used,
+				block_gas_limit: self.limit,
+			})
+		}
+		Ok(())
+	}
+
+	/// Record the gas used by an included transaction.
+	pub const fn record(&mut self, used_gas: u64) {
+		self.gas_used = self.gas_used.saturating_add(used_gas);
+	}
+
+	/// Start a new block with the given gas limit, keeping the
+	/// per-transaction maximum.
+	pub fn reset(&mut self, block_gas_limit: U256) {
+		*self = Self {
+			max_transaction_gas_limit: self.max_transaction_gas_limit,
+			..Self::new(block_gas_limit)
+		};
+	}
+}
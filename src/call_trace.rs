@@ -0,0 +1,335 @@
+//! # Call traces
+//!
+//! An event listener recording the call and create frames of transactions as
+//! a tree, with encoders to the JSON formats of the geth `callTracer` and of
+//! the parity `trace_transaction` RPC method.
+//!
+//! The gas used by the transaction frame leaves out the intrinsic gas and
+//! the refund. The code deployed by create frames is not part of the events,
+//! and is left out of the parity create results.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::fmt::Write;
+use crate::{CreateScheme, ExitError, ExitReason, H160, U256};
+use crate::tracing::{Event, EventListener};
+
+/// Opcode entering a frame.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CallKind {
+	/// `CALL`, also used for transaction frames.
+	Call,
+	/// `CALLCODE`.
+	CallCode,
+	/// `DELEGATECALL`.
+	DelegateCall,
+	/// `STATICCALL`.
+	StaticCall,
+	/// `CREATE`, also used for create transactions.
+	Create,
+	/// `CREATE2`.
+	Create2,
+}
+
+impl CallKind {
+	/// Name of the opcode.
+	#[must_use]
+	pub const fn name(self) -> &'static str {
+		match self {
+			Self::Call => "CALL",
+			Self::CallCode => "CALLCODE",
+			Self::DelegateCall => "DELEGATECALL",
+			Self::StaticCall => "STATICCALL",
+			Self::Create => "CREATE",
+			Self::Create2 => "CREATE2",
+		}
+	}
+
+	/// Whether the frame creates a contract.
+	#[must_use]
+	pub const fn is_create(self) -> bool {
+		matches!(self, Self::Create | Self::Create2)
+	}
+}
+
+/// Frame of a call trace.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CallFrame {
+	/// Opcode entering the frame.
+	pub kind: CallKind,
+	/// Caller, or the delegating contract for `DELEGATECALL`.
+	pub from: H160,
+	/// Called address, or created contract.
+	pub to: H160,
+	/// Value transferred, `None` for `DELEGATECALL` and `STATICCALL`.
+	pub value: Option<U256>,
+	/// Gas limit of the frame, stipend included.
+	pub gas: u64,
+	/// Stipend added to the gas forwarded by the caller.
+	pub stipend: u64,
+	/// Gas used by the frame.
+	pub gas_used: u64,
+	/// Unused gas given back to the caller, zero if the frame failed.
+	pub gas_returned: u64,
+	/// Call input, or init code.
+	pub input: Vec<u8>,
+	/// Return value, or revert data.
+	pub output: Vec<u8>,
+	/// Exit reason, `None` if the frame did not exit.
+	pub reason: Option<ExitReason>,
+	/// Frames entered by this frame, in order.
+	pub calls: Vec<Self>,
+}
+
+impl CallFrame {
+	/// Trace in the geth `callTracer` format.
+	#[must_use]
+	pub fn geth_json(&self) -> String {
+		let mut json = String::new();
+		self.write_geth(&mut json);
+		json
+	}
+
+	/// Traces of the frame and its children, depth first, in the parity
+	/// `trace_transaction` format.
+	#[must_use]
+	pub fn parity_json(&self) -> String {
+		let mut traces = Vec::new();
+		self.write_parity(&mut Vec::new(), &mut traces);
+		format!("[{}]", traces.join(","))
+	}
+
+	fn write_geth(&self, json: &mut String) {
+		let _ = write!(
+			json,
+			"{{\"type\":\"{}\",\"from\":\"{:?}\",\"to\":\"{:?}\"",
+			self.kind.name(), self.from, self.to,
+		);
+		if let Some(value) = self.value {
+			let _ = write!(json, ",\"value\":\"{value:#x}\"");
+		}
+		let _ = write!(
+			json,
+			",\"gas\":\"{:#x}\",\"gasUsed\":\"{:#x}\",\"input\":\"{}\"",
+			self.gas, self.gas_used, hex(&self.input),
+		);
+		if !self.output.is_empty() {
+			let _ = write!(json, ",\"output\":\"{}\"", hex(&self.output));
+		}
+		if let Some(error) = self.reason.and_then(geth_error) {
+			let _ = write!(json, ",\"error\":\"{error}\"");
+		}
+		if !self.calls.is_empty() {
+			json.push_str(",\"calls\":[");
+			for (i, call) in self.calls.iter().enumerate() {
+				if i > 0 {
+					json.push(',');
+				}
+				call.write_geth(json);
+			}
+			json.push(']');
+		}
+		json.push('}');
+	}
+
+	fn write_parity(&self, address: &mut Vec<usize>, traces: &mut Vec<String>) {
+		let mut json = String::from("{\"action\":{");
+		if self.kind.is_create() {
+			let _ = write!(
+				json,
+				"\"from\":\"{:?}\",\"gas\":\"{:#x}\",\"init\":\"{}\",\"value\":\"{:#x}\"}}",
+				self.from, self.gas, hex(&self.input), self.value.unwrap_or_default(),
+			);
+		} else {
+			let _ = write!(
+				json,
+				"\"callType\":\"{}\",\"from\":\"{:?}\",\"gas\":\"{:#x}\",\"input\":\"{}\",\"to\":\"{:?}\",\"value\":\"{:#x}\"}}",
+				self.kind.name().to_ascii_lowercase(), self.from, self.gas, hex(&self.input), self.to,
+				self.value.unwrap_or_default(),
+			);
+		}
+		match self.reason.and_then(parity_error) {
+			Some(error) => {
+				let _ = write!(json, ",\"error\":\"{error}\"");
+			},
+			None if self.kind.is_create() => {
+				let _ = write!(json, ",\"result\":{{\"address\":\"{:?}\",\"gasUsed\":\"{:#x}\"}}", self.to, self.gas_used);
+			},
+			None => {
+				let _ = write!(
+					json,
+					",\"result\":{{\"gasUsed\":\"{:#x}\",\"output\":\"{}\"}}",
+					self.gas_used, hex(&self.output),
+				);
+			},
+		}
+		let trace_address = address.iter().map(|i| format!("{i}")).collect::<Vec<_>>().join(",");
+		let _ = write!(
+			json,
+			",\"subtraces\":{},\"traceAddress\":[{}],\"type\":\"{}\"}}",
+			self.calls.len(), trace_address, if self.kind.is_create() { "create" } else { "call" },
+		);
+		traces.push(json);
+
+		for (i, call) in self.calls.iter().enumerate() {
+			address.push(i);
+			call.write_parity(address, traces);
+			address.pop();
+		}
+	}
+}
+
+fn hex(data: &[u8]) -> String {
+	let mut s = String::with_capacity(2 + data.len() * 2);
+	s.push_str("0x");
+	for byte in data {
+		let _ = write!(s, "{byte:02x}");
+	}
+	s
+}
+
+/// Error message of geth for a frame exiting with `reason`.
+fn geth_error(reason: ExitReason) -> Option<String> {
+	Some(match reason {
+		ExitReason::Succeed(_) => return None,
+		ExitReason::Revert(_) => "execution reverted".into(),
+		ExitReason::Error(e) => match e {
+			ExitError::OutOfGas => "out of gas".into(),
+			ExitError::StackUnderflow => "stack underflow".into(),
+			ExitError::StackOverflow => "stack limit reached".into(),
+			ExitError::InvalidJump => "invalid jump destination".into(),
+			ExitError::InvalidRange => "return data out of bounds".into(),
+			ExitError::DesignatedInvalid => "invalid opcode: INVALID".into(),
+			ExitError::InvalidOpcode(opcode) => format!("invalid opcode: opcode {:#04x} not defined", opcode.as_u8()),
+			ExitError::CallTooDeep => "max call depth exceeded".into(),
+			ExitError::CreateCollision(_) => "contract address collision".into(),
+			ExitError::CreateContractLimit => "max code size exceeded".into(),
+			ExitError::OutOfFund => "insufficient balance for transfer".into(),
+			ExitError::CreateContractStartingWithEF => "invalid code: must not begin with 0xef".into(),
+			ExitError::InvalidCode => "invalid code".into(),
+			e => format!("{e:?}"),
+		},
+		ExitReason::Fatal(e) => format!("fatal: {e:?}"),
+		ExitReason::StepLimitReached => "step limit reached".into(),
+	})
+}
+
+/// Error message of parity for a frame exiting with `reason`.
+fn parity_error(reason: ExitReason) -> Option<String> {
+	Some(match reason {
+		ExitReason::Succeed(_) => return None,
+		ExitReason::Revert(_) => "Reverted".into(),
+		ExitReason::Error(e) => match e {
+			ExitError::OutOfGas => "Out of gas".into(),
+			ExitError::StackUnderflow => "Stack underflow".into(),
+			ExitError::StackOverflow => "Out of stack".into(),
+			ExitError::InvalidJump => "Bad jump destination".into(),
+			ExitError::DesignatedInvalid | ExitError::InvalidOpcode(_) => "Bad instruction".into(),
+			ExitError::InvalidRange | ExitError::OutOfOffset => "Out of bounds".into(),
+			e => format!("{e:?}"),
+		},
+		ExitReason::Fatal(e) => format!("Internal error: {e:?}"),
+		ExitReason::StepLimitReached => "Step limit reached".into(),
+	})
+}
+
+#[derive(Debug, Default)]
+struct State {
+	/// Frames being executed, innermost last.
+	open: Vec<CallFrame>,
+	traces: Vec<CallFrame>,
+}
+
+/// Event listener recording a call trace per transaction.
+#[derive(Debug, Default)]
+pub struct CallTracer {
+	state: RefCell<State>,
+}
+
+impl CallTracer {
+	/// Create a new tracer.
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Traces of the transactions, in execution order.
+	#[must_use]
+	pub fn traces(&self) -> Vec<CallFrame> {
+		self.state.borrow().traces.clone()
+	}
+
+	fn enter(&self, frame: CallFrame) {
+		self.state.borrow_mut().open.push(frame);
+	}
+}
+
+impl EventListener for CallTracer {
+	fn event(&self, event: Event<'_>) {
+		match event {
+			Event::Call { code_address, transfer, input, target_gas, is_static, context } => {
+				let kind = match transfer {
+					Some(transfer) if transfer.target != code_address => CallKind::CallCode,
+					None if context.address != code_address => CallKind::DelegateCall,
+					None if is_static => CallKind::StaticCall,
+					_ => CallKind::Call,
+				};
+				self.enter(CallFrame {
+					kind,
+					from: if kind == CallKind::DelegateCall { context.address } else { context.caller },
+					to: code_address,
+					value: match kind {
+						CallKind::DelegateCall | CallKind::StaticCall => None,
+						_ => Some(transfer.as_ref().map_or_else(U256::zero, |transfer| transfer.value)),
+					},
+					gas: target_gas.unwrap_or_default(),
+					stipend: 0,
+					gas_used: 0,
+					gas_returned: 0,
+					input: input.to_vec(),
+					output: Vec::new(),
+					reason: None,
+					calls: Vec::new(),
+				});
+			},
+			Event::Create { caller, address, scheme, value, init_code, target_gas, .. } => {
+				self.enter(CallFrame {
+					kind: match scheme {
+						CreateScheme::Create2 { .. } => CallKind::Create2,
+						CreateScheme::Legacy { .. } | CreateScheme::Fixed(_) => CallKind::Create,
+					},
+					from: caller,
+					to: address,
+					value: Some(value),
+					gas: target_gas.unwrap_or_default(),
+					stipend: 0,
+					gas_used: 0,
+					gas_returned: 0,
+					input: init_code.to_vec(),
+					output: Vec::new(),
+					reason: None,
+					calls: Vec::new(),
+				});
+			},
+			Event::Exit { reason, return_value, frame_gas, .. } => {
+				let mut state = self.state.borrow_mut();
+				let Some(mut frame) = state.open.pop() else { return };
+				if let Some(usage) = frame_gas {
+					frame.gas = usage.gas_limit();
+					frame.stipend = usage.stipend;
+					frame.gas_used = usage.used;
+					frame.gas_returned = usage.returned;
+				}
+				frame.output = return_value.to_vec();
+				frame.reason = Some(*reason);
+				match state.open.last_mut() {
+					Some(parent) => parent.calls.push(frame),
+					None => state.traces.push(frame),
+				}
+			},
+			Event::FrameGas { .. } | Event::Step { .. } | Event::SetStorage { .. } => (),
+		}
+	}
+}
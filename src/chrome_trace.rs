@@ -0,0 +1,178 @@
+//! # Chrome trace export
+//!
+//! An event listener recording call frames and expensive opcodes as Chrome
+//! trace events, to be loaded in `chrome://tracing` or Perfetto.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::format;
+use core::cell::RefCell;
+#[cfg(feature = "std")]
+use core::convert::TryFrom;
+use crate::{ExitReason, Opcode};
+use crate::tracing::{Event, EventListener};
+
+/// Name and category of opcodes worth their own trace event.
+const fn expensive(opcode: Opcode) -> Option<(&'static str, &'static str)> {
+	Some(match opcode {
+		Opcode::SLOAD => ("SLOAD", "storage"),
+		Opcode::SSTORE => ("SSTORE", "storage"),
+		Opcode::SHA3 => ("SHA3", "hash"),
+		Opcode::BALANCE => ("BALANCE", "account"),
+		Opcode::EXTCODESIZE => ("EXTCODESIZE", "account"),
+		Opcode::EXTCODECOPY => ("EXTCODECOPY", "account"),
+		Opcode::EXTCODEHASH => ("EXTCODEHASH", "account"),
+		Opcode::LOG0 => ("LOG0", "log"),
+		Opcode::LOG1 => ("LOG1", "log"),
+		Opcode::LOG2 => ("LOG2", "log"),
+		Opcode::LOG3 => ("LOG3", "log"),
+		Opcode::LOG4 => ("LOG4", "log"),
+		_ => return None,
+	})
+}
+
+#[derive(Debug, Default)]
+struct State {
+	events: Vec<String>,
+	/// Expensive opcode being executed: name, category, start and gas cost.
+	pending: Option<(&'static str, &'static str, u64, u64)>,
+}
+
+/// Event listener recording a Chrome trace.
+///
+/// Timestamps are read from `clock`, in microseconds, so that they can share
+/// a time base with spans of the host application.
+pub struct ChromeTracer<F> {
+	clock: F,
+	pid: u64,
+	tid: u64,
+	state: RefCell<State>,
+}
+
+#[cfg(feature = "std")]
+impl ChromeTracer<fn() -> u64> {
+	/// Create a new tracer using wall-clock time since the Unix epoch.
+	#[must_use]
+	pub fn new() -> Self {
+		Self::with_clock(|| {
+			std::time::SystemTime::now()
+				.duration_since(std::time::UNIX_EPOCH)
+				.map_or(0, |d| u64::try_from(d.as_micros()).unwrap_or(u64::MAX))
+		})
+	}
+}
+
+#[cfg(feature = "std")]
+impl Default for ChromeTracer<fn() -> u64> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<F: Fn() -> u64> ChromeTracer<F> {
+	/// Create a new tracer reading timestamps, in microseconds, from `clock`.
+	pub fn with_clock(clock: F) -> Self {
+		Self {
+			clock,
+			pid: 1,
+			tid: 1,
+			state: RefCell::new(State::default()),
+		}
+	}
+
+	/// Set the process and thread identifiers of the recorded events.
+	#[must_use]
+	pub const fn with_ids(mut self, pid: u64, tid: u64) -> Self {
+		self.pid = pid;
+		self.tid = tid;
+		self
+	}
+
+	/// Recorded events, as JSON objects.
+	#[must_use]
+	pub fn events(&self) -> Vec<String> {
+		self.state.borrow().events.clone()
+	}
+
+	/// Recorded trace, in the Chrome JSON object format.
+	#[must_use]
+	pub fn json(&self) -> String {
+		format!("{{\"traceEvents\":[{}]}}", self.state.borrow().events.join(","))
+	}
+
+	fn push(&self, state: &mut State, phase: char, name: &str, category: &str, ts: u64, extra: &str) {
+		state.events.push(format!(
+			"{{\"name\":\"{}\",\"cat\":\"{}\",\"ph\":\"{}\",\"ts\":{},\"pid\":{},\"tid\":{}{}}}",
+			name, category, phase, ts, self.pid, self.tid, extra,
+		));
+	}
+
+	/// Close the pending expensive opcode, if any.
+	fn flush(&self, state: &mut State, now: u64) {
+		if let Some((name, category, start, gas)) = state.pending.take() {
+			let extra = format!(",\"dur\":{},\"args\":{{\"gas\":{}}}", now.saturating_sub(start), gas);
+			self.push(state, 'X', name, category, start, &extra);
+		}
+	}
+}
+
+const fn reason_name(reason: ExitReason) -> &'static str {
+	match reason {
+		ExitReason::Succeed(_) => "succeed",
+		ExitReason::Error(_) => "error",
+		ExitReason::Revert(_) => "revert",
+		ExitReason::Fatal(_) => "fatal",
+		ExitReason::StepLimitReached => "step_limit_reached",
+	}
+}
+
+impl<F: Fn() -> u64> EventListener for ChromeTracer<F> {
+	fn event(&self, event: Event<'_>) {
+		let now = (self.clock)();
+		let mut state = self.state.borrow_mut();
+		let state = &mut *state;
+		self.flush(state, now);
+
+		match event {
+			Event::Call { code_address, context, target_gas, is_static, .. } => {
+				let extra = format!(
+					",\"args\":{{\"address\":\"{:?}\",\"code_address\":\"{:?}\",\"caller\":\"{:?}\",\"gas\":{},\"static\":{}}}",
+					context.address, code_address, context.caller,
+					target_gas.map_or_else(|| String::from("null"), |gas| format!("{gas}")),
+					is_static,
+				);
+				self.push(state, 'B', &format!("call {code_address:?}"), "call", now, &extra);
+			},
+			Event::Create { caller, address, target_gas, .. } => {
+				let extra = format!(
+					",\"args\":{{\"address\":\"{:?}\",\"caller\":\"{:?}\",\"gas\":{}}}",
+					address, caller,
+					target_gas.map_or_else(|| String::from("null"), |gas| format!("{gas}")),
+				);
+				self.push(state, 'B', &format!("create {address:?}"), "create", now, &extra);
+			},
+			Event::FrameGas { gas, l64_reduction, target_gas, stipend, gas_limit, .. } => {
+				let extra = format!(
+					",\"s\":\"t\",\"args\":{{\"gas\":{},\"l64_reduction\":{},\"target_gas\":{},\"stipend\":{},\"gas_limit\":{}}}",
+					gas, l64_reduction,
+					target_gas.map_or_else(|| String::from("null"), |gas| format!("{gas}")),
+					stipend, gas_limit,
+				);
+				self.push(state, 'i', "gas", "gas", now, &extra);
+			},
+			Event::Exit { reason, return_value, .. } => {
+				let extra = format!(
+					",\"args\":{{\"reason\":\"{}\",\"return_size\":{}}}",
+					reason_name(*reason), return_value.len(),
+				);
+				self.push(state, 'E', "", "", now, &extra);
+			},
+			Event::Step { opcode, gas_cost, .. } => {
+				if let Some((name, category)) = expensive(opcode) {
+					state.pending = Some((name, category, now, gas_cost));
+				}
+			},
+			Event::SetStorage { .. } => (),
+		}
+	}
+}
@@ -0,0 +1,154 @@
+//! # Bytecode coverage
+//!
+//! An event listener counting the executions of each program counter per
+//! contract, for testing frameworks to report the coverage of deployed and
+//! init code, as JSON or in an LCOV-like format.
+
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::fmt::Write;
+use crate::H160;
+use crate::profiler::instruction_positions;
+use crate::tracing::{Event, EventListener};
+
+/// Coverage of the deployed or init code of a contract.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CodeCoverage {
+	/// Address of the code.
+	pub address: H160,
+	/// Whether the code is the init code of a create.
+	pub create: bool,
+	/// Number of executions of each program counter. Instructions of code
+	/// registered with the collector are listed even if never executed.
+	pub hits: BTreeMap<usize, u64>,
+}
+
+impl CodeCoverage {
+	/// Number of instructions executed at least once.
+	#[must_use]
+	pub fn covered(&self) -> usize {
+		self.hits.values().filter(|count| **count > 0).count()
+	}
+
+	fn name(&self) -> String {
+		if self.create {
+			format!("{:?}:init", self.address)
+		} else {
+			format!("{:?}", self.address)
+		}
+	}
+}
+
+#[derive(Debug, Default)]
+struct State {
+	/// Code run by each entered frame.
+	frames: Vec<(H160, bool)>,
+	hits: BTreeMap<(H160, bool), BTreeMap<usize, u64>>,
+}
+
+/// Event listener collecting the bytecode coverage of executed contracts.
+///
+/// Only executed code is known from the events. Registering the code of a
+/// contract with `with_code` or `with_init_code` lists its instructions that
+/// never ran too.
+#[derive(Debug, Default)]
+pub struct CoverageCollector {
+	state: RefCell<State>,
+}
+
+impl CoverageCollector {
+	/// Create a new empty collector.
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Report the instructions of the deployed code at `address`.
+	#[must_use]
+	pub fn with_code(self, address: H160, code: &[u8]) -> Self {
+		self.register(address, false, code);
+		self
+	}
+
+	/// Report the instructions of the init code creating `address`.
+	#[must_use]
+	pub fn with_init_code(self, address: H160, code: &[u8]) -> Self {
+		self.register(address, true, code);
+		self
+	}
+
+	fn register(&self, address: H160, create: bool, code: &[u8]) {
+		let mut state = self.state.borrow_mut();
+		let hits = state.hits.entry((address, create)).or_default();
+		for position in instruction_positions(code) {
+			hits.entry(position).or_default();
+		}
+	}
+
+	/// Coverage of each code, by address with deployed code first.
+	#[must_use]
+	pub fn coverage(&self) -> Vec<CodeCoverage> {
+		self.state.borrow().hits.iter()
+			.map(|(&(address, create), hits)| CodeCoverage { address, create, hits: hits.clone() })
+			.collect()
+	}
+
+	/// Coverage as a JSON array of objects with the code `address`, whether
+	/// it is `init` code, the number of `instructions` and of `covered`
+	/// ones, and the `hits` of each program counter.
+	#[must_use]
+	pub fn json(&self) -> String {
+		let codes = self.coverage().iter()
+			.map(|code| {
+				let hits = code.hits.iter()
+					.map(|(position, count)| format!("\"{position}\":{count}"))
+					.collect::<Vec<_>>();
+				format!(
+					"{{\"address\":\"{:?}\",\"init\":{},\"instructions\":{},\"covered\":{},\"hits\":{{{}}}}}",
+					code.address, code.create, code.hits.len(), code.covered(), hits.join(","),
+				)
+			})
+			.collect::<Vec<_>>();
+		format!("[{}]", codes.join(","))
+	}
+
+	/// Coverage in the LCOV tracefile format, a record per code named after
+	/// its address, with `:init` appended for init code. As LCOV lines start
+	/// at one, the line of an instruction is its program counter plus one.
+	#[must_use]
+	pub fn lcov(&self) -> String {
+		let mut lcov = String::new();
+		for code in self.coverage() {
+			let _ = writeln!(lcov, "SF:{}", code.name());
+			for (position, count) in &code.hits {
+				let _ = writeln!(lcov, "DA:{},{}", position + 1, count);
+			}
+			let _ = writeln!(lcov, "LF:{}", code.hits.len());
+			let _ = writeln!(lcov, "LH:{}", code.covered());
+			lcov.push_str("end_of_record\n");
+		}
+		lcov
+	}
+}
+
+impl EventListener for CoverageCollector {
+	fn event(&self, event: Event<'_>) {
+		let mut state = self.state.borrow_mut();
+		match event {
+			Event::Call { code_address, .. } => state.frames.push((code_address, false)),
+			Event::Create { address, .. } => state.frames.push((address, true)),
+			Event::Exit { .. } => {
+				state.frames.pop();
+			},
+			Event::Step { context, position, .. } => {
+				let code = state.frames.last().copied().unwrap_or((context.address, false));
+				*state.hits.entry(code).or_default().entry(position).or_default() += 1;
+			},
+			Event::FrameGas { .. } | Event::SetStorage { .. } => (),
+		}
+	}
+}
@@ -0,0 +1,127 @@
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use crate::backend::{Backend, Basic};
+use crate::{H160, H256, U256};
+
+/// Account information read from the backend for one address.
+#[derive(Clone, Debug, Default)]
+struct CachedAccount {
+	basic: Option<Basic>,
+	code_size: Option<usize>,
+	code_hash: Option<H256>,
+	exists: Option<bool>,
+	/// Storage slots prefetched with `StackExecutor::prefetch_storage`.
+	storage: BTreeMap<U256, U256>,
+}
+
+/// Account metadata read through from the backend, each field queried once
+/// per address.
+///
+/// The cache holds the backend view only. Writes land in the executor
+/// state, which takes precedence and is journaled by substates, so entries
+/// stay valid for the whole transaction and are shared by all frames.
+/// Writing the executor changes back to the backend, with
+/// `StackExecutor::commit_to` or `StackExecutor::deconstruct`, invalidates
+/// the entries of the written accounts. Clones share the same entries, so
+/// that executors running in turn on the same backend can share a cache.
+#[derive(Clone, Debug, Default)]
+pub struct AccountCache(Rc<RefCell<BTreeMap<H160, CachedAccount>>>);
+
+impl AccountCache {
+	/// Create an empty cache.
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Memoized lookup of a field of the account at `address`.
+	fn get<T: Clone>(
+		&self,
+		address: H160,
+		field: fn(&mut CachedAccount) -> &mut Option<T>,
+		query: impl FnOnce() -> T,
+	) -> T {
+		if let Some(value) = self.0.borrow_mut().get_mut(&address).and_then(|account| field(account).clone()) {
+			return value
+		}
+		let value = query();
+		*field(self.0.borrow_mut().entry(address).or_default()) = Some(value.clone());
+		value
+	}
+
+	/// Nonce and balance of the account in the backend.
+	pub fn basic<B: Backend + ?Sized>(&self, backend: &B, address: H160) -> Basic {
+		self.get(address, |account| &mut account.basic, || backend.basic(address))
+	}
+
+	/// Code size of the account in the backend.
+	pub fn code_size<B: Backend + ?Sized>(&self, backend: &B, address: H160) -> usize {
+		self.get(address, |account| &mut account.code_size, || backend.code_size(address))
+	}
+
+	/// Code hash of the account in the backend.
+	pub fn code_hash<B: Backend + ?Sized>(&self, backend: &B, address: H160) -> H256 {
+		self.get(address, |account| &mut account.code_hash, || backend.code_hash(address))
+	}
+
+	/// Whether the account exists in the backend.
+	pub fn exists<B: Backend + ?Sized>(&self, backend: &B, address: H160) -> bool {
+		self.get(address, |account| &mut account.exists, || backend.exists(address))
+	}
+
+	/// Storage slot of the account in the backend, read from the backend
+	/// unless prefetched. Slots read one by one are not kept, the executor
+	/// state keeps them.
+	pub fn storage<B: Backend + ?Sized>(&self, backend: &B, address: H160, index: U256) -> U256 {
+		let prefetched = self.0.borrow().get(&address)
+			.and_then(|account| account.storage.get(&index).copied());
+		prefetched.unwrap_or_else(|| backend.storage(address, index))
+	}
+
+	/// Whether the storage slot was prefetched.
+	#[must_use]
+	pub fn is_prefetched(&self, address: H160, index: U256) -> bool {
+		self.0.borrow().get(&address).is_some_and(|account| account.storage.contains_key(&index))
+	}
+
+	/// Read the storage slots at `indices`, which must not be prefetched yet,
+	/// in one `Backend::storage_multi` query.
+	pub fn prefetch<B: Backend + ?Sized>(&self, backend: &B, address: H160, indices: Vec<U256>) {
+		if indices.is_empty() {
+			return
+		}
+		let values = backend.storage_multi(address, &indices);
+		self.0.borrow_mut().entry(address).or_default().storage.extend(indices.into_iter().zip(values));
+	}
+
+	/// Forget what was read for the account at `address`, once the backend
+	/// changed it.
+	pub fn invalidate(&self, address: H160) {
+		self.0.borrow_mut().remove(&address);
+	}
+
+	/// Forget everything read.
+	pub fn clear(&self) {
+		self.0.borrow_mut().clear();
+	}
+
+	/// Whether anything was read for the account at `address`.
+	#[must_use]
+	pub fn contains(&self, address: H160) -> bool {
+		self.0.borrow().contains_key(&address)
+	}
+
+	/// Number of accounts read.
+	#[must_use]
+	pub fn len(&self) -> usize {
+		self.0.borrow().len()
+	}
+
+	/// Whether no account was read yet.
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.0.borrow().is_empty()
+	}
+}
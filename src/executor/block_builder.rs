@@ -0,0 +1,136 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use crate::{Istanbul, Spec, U256};
+use crate::backend::{Apply, ApplyBackend, ApplySet, Backend};
+use crate::block::{BlockGas, GasLimitError};
+use super::TransactOutcome;
+
+/// Transaction considered for inclusion in a block, see `BlockBuilder`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Candidate<T> {
+	/// Gas limit, checked against the gas left in the block before the
+	/// transaction is simulated.
+	pub gas_limit: u64,
+	/// Transaction, as understood by the simulate closure.
+	pub transaction: T,
+}
+
+/// Execution of a candidate, returned by the simulate closure of
+/// `BlockBuilder::build`.
+///
+/// It is typically built from `StackExecutor::transact_call_with_outcome`
+/// and `StackExecutor::deconstruct` on the rolling state of the block.
+#[derive(Clone, Debug)]
+pub struct Simulation {
+	/// Result of the transaction.
+	pub outcome: TransactOutcome,
+	/// State changes of the transaction.
+	pub applies: Vec<Apply<BTreeMap<U256, U256>>>,
+}
+
+/// Included transaction.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BlockReceipt<T> {
+	/// Position of the transaction among the candidates.
+	pub index: usize,
+	/// Included transaction.
+	pub transaction: T,
+	/// Result of the transaction.
+	pub outcome: TransactOutcome,
+	/// Gas used by the block up to and including this transaction.
+	pub cumulative_gas_used: u64,
+}
+
+/// Block packed by `BlockBuilder::build`.
+#[derive(Debug)]
+pub struct BuiltBlock<B, T> {
+	/// Backend with the changes of the included transactions applied.
+	pub backend: B,
+	/// Included transactions, in execution order.
+	pub receipts: Vec<BlockReceipt<T>>,
+	/// Candidates left out, with their position among the candidates.
+	pub skipped: Vec<(usize, Candidate<T>, GasLimitError)>,
+	/// Gas used by the included transactions.
+	pub gas_used: u64,
+	/// State changes of the included transactions, squashed by account.
+	pub state_diff: Vec<Apply<BTreeMap<U256, U256>>>,
+}
+
+/// Packs transactions into a block under its gas limit, for the hard fork
+/// `S`.
+///
+/// Candidates are simulated in order against a rolling state, each seeing
+/// the changes of the transactions included before it. A candidate whose gas
+/// limit exceeds the gas left in the block is skipped without being
+/// simulated, and later candidates are still considered.
+pub struct BlockBuilder<B, S = Istanbul> {
+	backend: B,
+	block_gas: BlockGas,
+	spec: PhantomData<S>,
+}
+
+impl<B: Backend + ApplyBackend> BlockBuilder<B> {
+	/// Create a builder for the Istanbul hard fork, with the block gas limit
+	/// of the backend.
+	pub fn new(backend: B) -> Self {
+		Self::new_with_spec(backend, Istanbul)
+	}
+}
+
+impl<B: Backend + ApplyBackend, S: Spec> BlockBuilder<B, S> {
+	/// Create a builder for the hard fork `S`, with the block gas limit of
+	/// the backend.
+	pub fn new_with_spec(backend: B, _spec: S) -> Self {
+		Self {
+			block_gas: BlockGas::from_backend(&backend),
+			backend,
+			spec: PhantomData,
+		}
+	}
+
+	/// Pack under the given block gas accounting instead, for instance to
+	/// cap the gas limit of a single transaction.
+	#[must_use]
+	pub const fn with_block_gas(mut self, block_gas: BlockGas) -> Self {
+		self.block_gas = block_gas;
+		self
+	}
+
+	/// Simulate the candidates in order with `simulate`, given the rolling
+	/// state, and include those fitting in the block.
+	pub fn build<T, I, F>(mut self, candidates: I, mut simulate: F) -> BuiltBlock<B, T> where
+		I: IntoIterator<Item=Candidate<T>>,
+		F: FnMut(&B, &Candidate<T>) -> Simulation,
+	{
+		let mut receipts = Vec::new();
+		let mut skipped = Vec::new();
+		let mut state_diff = ApplySet::new();
+
+		for (index, candidate) in candidates.into_iter().enumerate() {
+			if let Err(e) = self.block_gas.validate(candidate.gas_limit) {
+				skipped.push((index, candidate, e));
+				continue
+			}
+
+			let Simulation { outcome, applies } = simulate(&self.backend, &candidate);
+			self.block_gas.record(outcome.gas_used);
+			state_diff.push(applies.clone());
+			self.backend.apply(applies, outcome.logs.clone(), S::CONFIG.clear_touched_empty);
+			receipts.push(BlockReceipt {
+				index,
+				transaction: candidate.transaction,
+				outcome,
+				cumulative_gas_used: self.block_gas.gas_used(),
+			});
+		}
+
+		BuiltBlock {
+			backend: self.backend,
+			receipts,
+			skipped,
+			gas_used: self.block_gas.gas_used(),
+			state_diff: state_diff.into_applies(),
+		}
+	}
+}
@@ -0,0 +1,267 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use evm_runtime::{Istanbul, Spec};
+use crate::{Limits, Valids, H160, U256};
+use crate::backend::Backend;
+use crate::tracing::{EventListener, TraceConfig};
+use super::{no_precompile, Fees, PrecompileFn, Precompiles, StackExecutor, StateLimits, TxContext};
+
+/// Replacement of parts of an account for the executor, leaving the backend
+/// untouched, as `eth_call` state overrides do. Unset fields keep the
+/// backend value.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct StateOverride {
+	/// Balance.
+	pub balance: Option<U256>,
+	/// Nonce.
+	pub nonce: Option<U256>,
+	/// Code.
+	pub code: Option<Vec<u8>>,
+	/// Whole storage, slots not present being zero.
+	pub storage: Option<BTreeMap<U256, U256>>,
+	/// Storage slots to replace, the other slots keeping the backend value.
+	pub storage_diff: BTreeMap<U256, U256>,
+}
+
+/// Reason `StackExecutorBuilder::build` rejects its options.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BuildError {
+	/// No gas limit was given.
+	MissingGasLimit,
+	/// Starting depth is beyond the call stack limit of the hard fork.
+	DepthTooLarge {
+		/// Starting depth.
+		depth: usize,
+		/// Call stack limit.
+		limit: usize,
+	},
+	/// Stack or memory limit is zero.
+	InvalidLimits,
+	/// Override of the account sets both the whole storage and a storage
+	/// diff.
+	ConflictingStorage(H160),
+}
+
+/// Builder of a `StackExecutor`, checking that its options fit together.
+///
+/// Options not set keep the defaults of `StackExecutor::new_with_spec`,
+/// except the gas limit, which is required.
+pub struct StackExecutorBuilder<'backend, B, S = Istanbul> {
+	backend: &'backend B,
+	gas_limit: Option<u64>,
+	precompile: PrecompileFn,
+	precompiles: Option<&'backend Precompiles>,
+	listener: Option<&'backend dyn EventListener>,
+	trace_config: TraceConfig,
+	is_static: bool,
+	depth: Option<usize>,
+	limits: Option<Limits>,
+	fees: Option<Fees>,
+	tx_context: Option<TxContext>,
+	state_limits: StateLimits,
+	overrides: BTreeMap<H160, StateOverride>,
+	spec: PhantomData<S>,
+}
+
+impl<'backend, B: 'backend + Backend> StackExecutorBuilder<'backend, B> {
+	/// Builder of an executor on `backend`, for the Istanbul hard fork and
+	/// without precompiles.
+	pub fn new(backend: &'backend B) -> Self {
+		Self {
+			backend,
+			gas_limit: None,
+			precompile: no_precompile,
+			precompiles: None,
+			listener: None,
+			trace_config: TraceConfig::all(),
+			is_static: false,
+			depth: None,
+			limits: None,
+			fees: None,
+			tx_context: None,
+			state_limits: StateLimits::default(),
+			overrides: BTreeMap::new(),
+			spec: PhantomData,
+		}
+	}
+}
+
+impl<'backend, B: 'backend + Backend, S: Spec> StackExecutorBuilder<'backend, B, S> {
+	/// Run the executor for the hard fork `T` instead.
+	pub fn with_spec<T: Spec>(self, _spec: T) -> StackExecutorBuilder<'backend, B, T> {
+		StackExecutorBuilder {
+			backend: self.backend,
+			gas_limit: self.gas_limit,
+			precompile: self.precompile,
+			precompiles: self.precompiles,
+			listener: self.listener,
+			trace_config: self.trace_config,
+			is_static: self.is_static,
+			depth: self.depth,
+			limits: self.limits,
+			fees: self.fees,
+			tx_context: self.tx_context,
+			state_limits: self.state_limits,
+			overrides: self.overrides,
+			spec: PhantomData,
+		}
+	}
+
+	/// Gas limit of the executor.
+	#[must_use]
+	pub const fn with_gas_limit(mut self, gas_limit: u64) -> Self {
+		self.gas_limit = Some(gas_limit);
+		self
+	}
+
+	/// Serve calls to precompiled contracts, see
+	/// `StackExecutor::new_with_precompile`.
+	#[must_use]
+	pub fn with_precompile(mut self, precompile: PrecompileFn) -> Self {
+		self.precompile = precompile;
+		self
+	}
+
+	/// Serve calls to precompiled contracts with a registry, see
+	/// `StackExecutor::with_precompiles`.
+	#[must_use]
+	pub const fn with_precompiles(mut self, precompiles: &'backend Precompiles) -> Self {
+		self.precompiles = Some(precompiles);
+		self
+	}
+
+	/// Attach an event listener, see `StackExecutor::with_listener`.
+	#[must_use]
+	pub fn with_listener(mut self, listener: &'backend dyn EventListener) -> Self {
+		self.listener = Some(listener);
+		self
+	}
+
+	/// Only notify the listener about the given categories of events, see
+	/// `StackExecutor::with_trace_config`.
+	#[must_use]
+	pub const fn with_trace_config(mut self, config: TraceConfig) -> Self {
+		self.trace_config = config;
+		self
+	}
+
+	/// Run every frame as static, rejecting state changes.
+	#[must_use]
+	pub const fn with_static(mut self) -> Self {
+		self.is_static = true;
+		self
+	}
+
+	/// Run the executor as if nested at the given call depth, see
+	/// `StackExecutor::with_depth`.
+	#[must_use]
+	pub const fn with_depth(mut self, depth: usize) -> Self {
+		self.depth = Some(depth);
+		self
+	}
+
+	/// Run frames with the given limits, see `StackExecutor::with_limits`.
+	#[must_use]
+	pub const fn with_limits(mut self, limits: Limits) -> Self {
+		self.limits = Some(limits);
+		self
+	}
+
+	/// Charge gas fees, see `StackExecutor::with_fees`.
+	#[must_use]
+	pub const fn with_fees(mut self, fees: Fees) -> Self {
+		self.fees = Some(fees);
+		self
+	}
+
+	/// Read `ORIGIN` and `GASPRICE` from the given transaction data, see
+	/// `StackExecutor::with_tx_context`.
+	#[must_use]
+	pub const fn with_tx_context(mut self, context: TxContext) -> Self {
+		self.tx_context = Some(context);
+		self
+	}
+
+	/// Cap the logs and the self-destruct set, see
+	/// `StackExecutor::with_state_limits`.
+	#[must_use]
+	pub const fn with_state_limits(mut self, limits: StateLimits) -> Self {
+		self.state_limits = limits;
+		self
+	}
+
+	/// Replace parts of the account at `address`. A later override of the
+	/// same address replaces the earlier one.
+	#[must_use]
+	pub fn with_state_override(mut self, address: H160, state_override: StateOverride) -> Self {
+		self.overrides.insert(address, state_override);
+		self
+	}
+
+	/// Check the options and create the executor. Overridden accounts are
+	/// part of its state, and are thus returned by
+	/// `StackExecutor::deconstruct`.
+	pub fn build(self) -> Result<StackExecutor<'backend, B, S>, BuildError> {
+		let gas_limit = self.gas_limit.ok_or(BuildError::MissingGasLimit)?;
+		if let Some(depth) = self.depth {
+			let limit = S::CONFIG.call_stack_limit;
+			if depth > limit {
+				return Err(BuildError::DepthTooLarge { depth, limit })
+			}
+		}
+		if self.limits.is_some_and(|limits| limits.stack_limit == 0 || limits.memory_limit == 0) {
+			return Err(BuildError::InvalidLimits)
+		}
+		if let Some((address, _)) = self.overrides.iter()
+			.find(|(_, state_override)| state_override.storage.is_some() && !state_override.storage_diff.is_empty())
+		{
+			return Err(BuildError::ConflictingStorage(*address))
+		}
+
+		let mut executor = StackExecutor::new_with_spec(self.backend, gas_limit, self.precompile, S::default())
+			.with_trace_config(self.trace_config)
+			.with_state_limits(self.state_limits);
+		if let Some(precompiles) = self.precompiles {
+			executor = executor.with_precompiles(precompiles);
+		}
+		if let Some(listener) = self.listener {
+			executor = executor.with_listener(listener);
+		}
+		if self.is_static {
+			executor = executor.with_static();
+		}
+		if let Some(depth) = self.depth {
+			executor = executor.with_depth(depth);
+		}
+		if let Some(limits) = self.limits {
+			executor = executor.with_limits(limits);
+		}
+		if let Some(fees) = self.fees {
+			executor = executor.with_fees(fees);
+		}
+		if let Some(context) = self.tx_context {
+			executor = executor.with_tx_context(context);
+		}
+
+		for (address, state_override) in self.overrides {
+			let account = executor.account_mut(address);
+			if let Some(balance) = state_override.balance {
+				account.basic.balance = balance;
+			}
+			if let Some(nonce) = state_override.nonce {
+				account.basic.nonce = nonce;
+			}
+			if let Some(code) = state_override.code {
+				account.valids = Some(Valids::compute(&code));
+				account.code = Some(code);
+			}
+			if let Some(storage) = state_override.storage {
+				account.storage = storage;
+				account.reset_storage = true;
+			}
+			account.storage.extend(state_override.storage_diff);
+		}
+		Ok(executor)
+	}
+}
@@ -0,0 +1,50 @@
+use crate::H160;
+
+/// Checks of the account at the address of a create, failing the create
+/// with `CreateCollision` if one of them finds the account in use.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "with-codec", derive(codec::Encode, codec::Decode))]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CollisionChecks {
+	/// Collide on an account with code.
+	pub code: bool,
+	/// Collide on an account with a non-zero nonce.
+	pub nonce: bool,
+	/// Collide on an account with non-empty storage, as reported by
+	/// `Backend::has_storage`.
+	pub storage: bool,
+}
+
+impl CollisionChecks {
+	/// Code and nonce checks (EIP-684).
+	pub const EIP684: Self = Self { code: true, nonce: true, storage: false };
+	/// Code, nonce and storage checks (EIP-7610).
+	pub const EIP7610: Self = Self { code: true, nonce: true, storage: true };
+	/// No check, the create overwrites the account.
+	pub const NONE: Self = Self { code: false, nonce: false, storage: false };
+}
+
+impl Default for CollisionChecks {
+	fn default() -> Self {
+		Self::EIP684
+	}
+}
+
+/// Policy deciding which collision checks a create runs.
+///
+/// The default method follows EIP-684. Chains can relax the checks for
+/// system deployments, for instance to redeploy a predeployed contract from
+/// a system address.
+pub trait CollisionPolicy {
+	/// Checks run on the account at `address` created by `caller`.
+	fn checks(&self, _caller: H160, _address: H160) -> CollisionChecks {
+		CollisionChecks::EIP684
+	}
+}
+
+/// The same checks for all creates.
+impl CollisionPolicy for CollisionChecks {
+	fn checks(&self, _caller: H160, _address: H160) -> CollisionChecks {
+		*self
+	}
+}
@@ -0,0 +1,46 @@
+use crate::{Config, U256};
+
+/// Kind of frame gas is forwarded to.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FrameKind {
+	/// Top-level frame of a transaction or a system call, given the whole
+	/// gas limit.
+	Transaction,
+	/// Frame of a `CALL`-like opcode.
+	Call,
+	/// Frame of a `CREATE`-like opcode.
+	Create,
+}
+
+/// Policy deciding how much gas the executor forwards to a child frame.
+///
+/// The default methods follow Ethereum, as configured by
+/// `call_l64_after_gas` and `call_stipend`.
+pub trait GasForwardingPolicy {
+	/// Gas available for forwarding to a frame of `kind`, out of `gas` left
+	/// in the parent. The frame receives the minimum of this and the gas it
+	/// requested.
+	fn available_gas(&self, config: &Config, kind: FrameKind, gas: u64) -> u64 {
+		if kind != FrameKind::Transaction && config.call_l64_after_gas {
+			gas - gas / 64
+		} else {
+			gas
+		}
+	}
+
+	/// Gas given to a frame of `kind` transferring `value`, on top of the
+	/// forwarded gas and without being charged to the parent.
+	fn stipend(&self, config: &Config, kind: FrameKind, value: U256) -> u64 {
+		if kind == FrameKind::Call && value != U256::zero() {
+			config.call_stipend
+		} else {
+			0
+		}
+	}
+}
+
+/// Ethereum gas forwarding, used when no policy is set.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EthereumGasForwarding;
+
+impl GasForwardingPolicy for EthereumGasForwarding {}
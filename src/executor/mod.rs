@@ -3,6 +3,26 @@
 //! Executors are structs that hook gasometer and the EVM core together. It
 //! also handles the call stacks in EVM.
 
+mod account_cache;
+mod block_builder;
+mod builder;
+mod collision;
+mod forwarding;
+mod nonce;
+mod precompiles;
 mod stack;
+mod validation;
 
-pub use self::stack::{StackAccount, StackExecutor};
+pub use self::account_cache::AccountCache;
+pub use self::block_builder::{BlockBuilder, BlockReceipt, BuiltBlock, Candidate, Simulation};
+pub use self::builder::{BuildError, StackExecutorBuilder, StateOverride};
+pub use self::collision::{CollisionChecks, CollisionPolicy};
+pub use self::forwarding::{EthereumGasForwarding, FrameKind, GasForwardingPolicy};
+pub use self::nonce::NoncePolicy;
+pub use self::precompiles::{Activation, Precompiles};
+pub(crate) use self::stack::no_precompile;
+pub use self::stack::{
+	apply_from_executor, ExecutionState, ExecutionStats, ExecutorStats, Fees, FrameInterrupt, Pending, PrecompileFn, PrecompileOutput, PrecompileResult, Refund, StackAccount, StackExecutor, StateLimits,
+	TransactOutcome, TransactionValidationError, TxContext, SYSTEM_ADDRESS, SYSTEM_CALL_GAS, VIEW_CALL_GAS,
+};
+pub use self::validation::{PhaseCall, ValidatedOutcome, VALIDATION_BANNED_OPCODES};
@@ -0,0 +1,22 @@
+use crate::H160;
+
+/// Policy deciding when the executor increases the nonce of an account.
+///
+/// The default methods follow Ethereum. The nonce of a created contract is
+/// still governed by `create_increase_nonce`, and the address of a legacy
+/// create is still derived from the creator nonce, so a policy keeping the
+/// creator nonce makes repeated creates collide.
+pub trait NoncePolicy {
+	/// Whether a call transaction increases the nonce of its sender.
+	fn increase_on_call(&self, _caller: H160) -> bool {
+		true
+	}
+
+	/// Whether a create, either a transaction or an opcode, increases the
+	/// nonce of its creator. With `collision`, the create fails on an
+	/// existing account, and the nonce is only increased if
+	/// `create_collision_increase_nonce` is also set.
+	fn increase_on_create(&self, _caller: H160, _collision: bool) -> bool {
+		true
+	}
+}
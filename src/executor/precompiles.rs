@@ -0,0 +1,156 @@
+use alloc::vec::Vec;
+use core::ops::RangeInclusive;
+use crate::{Config, H160};
+use super::{PrecompileFn, PrecompileResult};
+
+/// Predicate on the hard fork configuration deciding whether a precompile is
+/// active, for instance `|config| config.has_chain_id` for a precompile
+/// introduced in Istanbul.
+pub type Activation = fn(&Config) -> bool;
+
+/// Precompile registered for a range of addresses.
+#[derive(Clone, Copy, Debug)]
+struct Entry {
+	start: H160,
+	end: H160,
+	activation: Option<Activation>,
+	precompile: PrecompileFn,
+}
+
+impl Entry {
+	fn serves(&self, config: &Config, address: H160) -> bool {
+		self.start <= address && address <= self.end &&
+			self.activation.is_none_or(|activation| activation(config))
+	}
+
+	fn is_exact(&self) -> bool {
+		self.start == self.end
+	}
+}
+
+/// Registry of the precompiles of a chain, see
+/// `StackExecutor::with_precompiles`.
+///
+/// Precompiles are registered at a single address or for a range of
+/// addresses, and are called with the code address, so one function can
+/// serve a whole range. Exact addresses take precedence over ranges, and
+/// later registrations over earlier ones.
+///
+/// Chains hosting precompiles outside of the Ethereum addresses can also
+/// reserve ranges of addresses for them, whether a precompile is registered
+/// there or not yet. Active precompile and reserved addresses are considered
+/// existing by the executor, so calls transferring value to them are not
+/// charged for a new account, and they are warm with EIP-2929. Calls to a
+/// reserved address that no precompile serves run its code, if any, as for
+/// any other account.
+#[derive(Clone, Debug, Default)]
+pub struct Precompiles {
+	entries: Vec<Entry>,
+	reserved: Vec<(H160, H160, Option<Activation>)>,
+}
+
+impl Precompiles {
+	/// Registry without any precompile.
+	#[must_use]
+	pub const fn new() -> Self {
+		Self { entries: Vec::new(), reserved: Vec::new() }
+	}
+
+	/// Register a precompile at `address`, active in all hard forks.
+	#[must_use]
+	pub fn with_precompile(self, address: H160, precompile: PrecompileFn) -> Self {
+		self.with_entry(address..=address, None, precompile)
+	}
+
+	/// Register a precompile at `address`, only active in the hard forks
+	/// where `activation` holds.
+	#[must_use]
+	pub fn with_precompile_if(self, address: H160, activation: Activation, precompile: PrecompileFn) -> Self {
+		self.with_entry(address..=address, Some(activation), precompile)
+	}
+
+	/// Register a precompile for all the addresses of `range`, active in all
+	/// hard forks.
+	#[must_use]
+	pub fn with_range(self, range: RangeInclusive<H160>, precompile: PrecompileFn) -> Self {
+		self.with_entry(range, None, precompile)
+	}
+
+	/// Register a precompile for all the addresses of `range`, only active in
+	/// the hard forks where `activation` holds.
+	#[must_use]
+	pub fn with_range_if(self, range: RangeInclusive<H160>, activation: Activation, precompile: PrecompileFn) -> Self {
+		self.with_entry(range, Some(activation), precompile)
+	}
+
+	/// Reserve all the addresses of `range` for precompiles, in all hard
+	/// forks.
+	#[must_use]
+	pub fn with_reserved(mut self, range: RangeInclusive<H160>) -> Self {
+		let (start, end) = range.into_inner();
+		self.reserved.push((start, end, None));
+		self
+	}
+
+	/// Reserve all the addresses of `range` for precompiles, only in the hard
+	/// forks where `activation` holds.
+	#[must_use]
+	pub fn with_reserved_if(mut self, range: RangeInclusive<H160>, activation: Activation) -> Self {
+		let (start, end) = range.into_inner();
+		self.reserved.push((start, end, Some(activation)));
+		self
+	}
+
+	fn with_entry(mut self, range: RangeInclusive<H160>, activation: Option<Activation>, precompile: PrecompileFn) -> Self {
+		let (start, end) = range.into_inner();
+		self.entries.push(Entry { start, end, activation, precompile });
+		self
+	}
+
+	/// Precompile serving `address` in the hard fork `config`.
+	#[must_use]
+	pub fn get(&self, config: &Config, address: H160) -> Option<PrecompileFn> {
+		let mut matching = self.entries.iter().rev().filter(|entry| entry.serves(config, address));
+		let first = matching.next()?;
+		if first.is_exact() {
+			return Some(first.precompile)
+		}
+		Some(matching.find(|entry| entry.is_exact()).unwrap_or(first).precompile)
+	}
+
+	/// Whether a precompile serves `address` in the hard fork `config`.
+	#[must_use]
+	pub fn is_precompile(&self, config: &Config, address: H160) -> bool {
+		self.entries.iter().any(|entry| entry.serves(config, address))
+	}
+
+	/// Whether `address` is reserved for precompiles in the hard fork
+	/// `config`, either served by a precompile or in a reserved range.
+	#[must_use]
+	pub fn is_reserved(&self, config: &Config, address: H160) -> bool {
+		self.is_precompile(config, address) || self.reserved.iter().any(|(start, end, activation)| {
+			*start <= address && address <= *end && activation.is_none_or(|activation| activation(config))
+		})
+	}
+
+	/// Addresses registered exactly and active in the hard fork `config`,
+	/// in increasing order, for instance to build an `AccessListTracer`.
+	/// Ranges are not enumerated.
+	#[must_use]
+	pub fn addresses(&self, config: &Config) -> Vec<H160> {
+		let mut addresses: Vec<H160> = self.entries.iter()
+			.filter(|entry| entry.is_exact() && entry.serves(config, entry.start))
+			.map(|entry| entry.start)
+			.collect();
+		addresses.sort_unstable();
+		addresses.dedup();
+		addresses
+	}
+
+	/// Call the precompile serving `address` in the hard fork `config`,
+	/// `None` if there is none.
+	#[must_use]
+	pub fn call(&self, config: &Config, address: H160, input: &[u8], target_gas: Option<u64>) -> PrecompileResult {
+		self.get(config, address).and_then(|precompile| precompile(address, input, target_gas))
+	}
+}
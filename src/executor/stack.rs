@@ -1,20 +1,63 @@
 #![allow(clippy::let_underscore_drop)]
 
 use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use alloc::sync::Arc;
 use alloc::vec::Vec;
 use core::convert::Infallible;
-use evm_runtime::CONFIG;
+use evm_runtime::{Istanbul, Spec};
 
+#[cfg(feature = "debug-trace")]
+use crate::DebugSink;
+#[cfg(feature = "taint")]
+use crate::TaintSink;
 use crate::{
-	Capture, Context, CreateScheme, ExitError, ExitReason, ExitSucceed, H160,
-	H256, Handler, Opcode, Runtime, Stack, Transfer, Valids, U256,
+	Capture, CollisionKind, Context, CreateScheme, Eof, ExitError, ExitFatal, ExitReason, ExitSucceed, H160,
+	H256, Handler, Keccak, Limits, Memory, Opcode, OpcodeSet, Resolve, ResolveCall, ResolveCreate, Runtime, Stack,
+	Transfer, Valids, U256, is_eof,
 };
-use crate::backend::{Apply, Backend, Basic, Log};
-use crate::gasometer::{self, Gasometer};
+use crate::analysis::{AnalysisCache, CodeAnalysis};
+#[cfg(feature = "jit")]
+use crate::jit::JitCache;
+use crate::backend::{Apply, ApplyBackend, Backend, Basic, CallRequest, CallResponse, Log, TracedLog};
+use crate::access_list::AccessList;
+use crate::gasometer::{self, GasBreakdown, GasCategory, Gasometer, StorageTarget, TransactionCost};
+use crate::transaction::{SenderRecovery, Transaction, TransactionAction, TransactionError, TransactionType};
+use crate::tracing::{Event, EventListener, FrameGasUsage, MemorySnapshot, StackSnapshot, TraceConfig};
+use super::{AccountCache, CollisionChecks, CollisionPolicy, EthereumGasForwarding, FrameKind, GasForwardingPolicy, NoncePolicy, PhaseCall, Precompiles, ValidatedOutcome};
+
+
+/// Maps serialized as sequences of entries, as formats such as JSON only take
+/// string keys.
+#[cfg(feature = "with-serde")]
+mod serde_entries {
+	use alloc::collections::BTreeMap;
+	use alloc::vec::Vec;
+	use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+	pub fn serialize<K, V, S>(map: &BTreeMap<K, V>, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		K: Serialize,
+		V: Serialize,
+		S: Serializer,
+	{
+		serializer.collect_seq(map)
+	}
 
+	pub fn deserialize<'de, K, V, D>(deserializer: D) -> Result<BTreeMap<K, V>, D::Error>
+	where
+		K: Deserialize<'de> + Ord,
+		V: Deserialize<'de>,
+		D: Deserializer<'de>,
+	{
+		Vec::<(K, V)>::deserialize(deserializer).map(|entries| entries.into_iter().collect())
+	}
+}
 
 /// Account definition for the stack-based executor.
 #[derive(Default, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct StackAccount {
 	/// Basic account information, including nonce and balance.
 	pub basic: Basic,
@@ -23,30 +66,389 @@ pub struct StackAccount {
 	/// Valids. `None` means the code is currently unknown.
 	pub valids: Option<Vec<u8>>,
 	/// Storage. Not inserted values mean it is currently known, but not empty.
+	#[cfg_attr(feature = "with-serde", serde(with = "serde_entries"))]
 	pub storage: BTreeMap<U256, U256>,
 	/// Whether the storage in the database should be reset before storage
 	/// values are applied.
 	pub reset_storage: bool,
 }
 
-type PrecompileOutput = (ExitSucceed, Vec<u8>, u64);
-type PrecompileResult = Option<Result<PrecompileOutput, ExitError>>;
-type PrecompileFn = fn(H160, &[u8], Option<u64>) -> PrecompileResult;
+/// Refund of an executed transaction.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "with-codec", derive(codec::Encode, codec::Decode))]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Refund {
+	/// Raw accumulated refund.
+	pub refunded_gas: i64,
+	/// Maximum refund applicable to the used gas.
+	pub refund_cap: u64,
+	/// Refund actually applied, that is the raw refund limited by the cap.
+	pub effective_refund: u64,
+}
+
+/// Gas fees charged by the `transact_*` functions, see
+/// `StackExecutor::with_fees`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "with-codec", derive(codec::Encode, codec::Decode))]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Fees {
+	/// Price paid by the sender per unit of gas.
+	pub gas_price: U256,
+	/// Part of the gas price that is burnt. The rest of the price, the
+	/// priority fee, is paid to the block coinbase.
+	pub base_fee: U256,
+}
+
+/// Result of a transaction, see `StackExecutor::transact_call_with_outcome`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TransactOutcome {
+	/// Exit reason.
+	pub exit: ExitReason,
+	/// Return value of a call, revert data of a failed create.
+	pub output: Vec<u8>,
+	/// Gas used by the transaction, refund deducted.
+	pub gas_used: u64,
+	/// Refund applied to the gas used.
+	pub gas_refunded: u64,
+	/// Logs emitted by the transaction.
+	pub logs: Vec<Log>,
+}
+
+/// Data of the transaction being executed, read by `ORIGIN` and `GASPRICE`,
+/// see `StackExecutor::with_tx_context`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "with-codec", derive(codec::Encode, codec::Decode))]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TxContext {
+	/// Sender of the transaction.
+	pub origin: H160,
+	/// Gas price of the transaction.
+	pub gas_price: U256,
+}
+
+/// Caps on the state accumulated by a transaction, for hosts running
+/// untrusted code, see `StackExecutor::with_state_limits`. Frames exceeding a
+/// cap fail with `LogLimit` or `SelfDestructLimit`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "with-codec", derive(codec::Encode, codec::Decode))]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StateLimits {
+	/// Maximum total size of the logs, topics and data, in bytes.
+	pub max_log_size: Option<usize>,
+	/// Maximum number of accounts in the self-destruct set.
+	pub max_deleted: Option<usize>,
+}
+
+/// Size of the state accumulated by the executor, see
+/// `StackExecutor::stats`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "with-codec", derive(codec::Encode, codec::Decode))]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExecutorStats {
+	/// Number of logs.
+	pub logs: usize,
+	/// Total size of the logs, topics and data, in bytes.
+	pub log_size: usize,
+	/// Number of accounts in the self-destruct set.
+	pub deleted: usize,
+	/// Number of cached accounts.
+	pub accounts: usize,
+	/// Number of cached storage slots.
+	pub storage_slots: usize,
+	/// Estimate of the heap memory used by the logs, the self-destruct set
+	/// and the cached accounts, in bytes.
+	pub memory: usize,
+}
+
+/// Peaks reached by the frames of the current transaction, see
+/// `StackExecutor::execution_stats`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "with-codec", derive(codec::Encode, codec::Decode))]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExecutionStats {
+	/// Largest memory of a frame, in bytes, as charged for gas.
+	pub peak_memory: usize,
+	/// Deepest frame run by the interpreter, the transaction frame having
+	/// depth 1. Precompiles and intercepted calls do not count.
+	pub max_depth: usize,
+}
+
+/// Size of a log counted against `StateLimits::max_log_size`.
+const fn log_size(topics: &[H256], data: &[u8]) -> usize {
+	topics.len() * 32 + data.len()
+}
+
+/// Reason a transaction cannot be included, see
+/// `StackExecutor::validate_transaction`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "with-codec", derive(codec::Encode, codec::Decode))]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TransactionValidationError {
+	/// Sender account does not exist.
+	UnknownSender,
+	/// Transaction nonce is not the nonce of the sender.
+	InvalidNonce {
+		/// Nonce of the sender.
+		expected: U256,
+		/// Nonce of the transaction.
+		actual: U256,
+	},
+	/// Sender balance does not cover the value and the maximum fee.
+	InsufficientBalance {
+		/// Balance of the sender.
+		balance: U256,
+		/// Value plus gas limit times gas price, `None` if it overflows.
+		cost: Option<U256>,
+	},
+	/// Gas limit does not cover the intrinsic gas of the transaction.
+	IntrinsicGasTooLow {
+		/// Intrinsic gas of the transaction.
+		intrinsic_gas: u64,
+		/// Gas limit of the transaction.
+		gas_limit: u64,
+	},
+	/// Sender account has code (EIP-3607).
+	SenderHasCode,
+}
+
+/// Sender of system calls, see `StackExecutor::system_call`.
+pub const SYSTEM_ADDRESS: H160 = H160([
+	0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+	0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe,
+]);
+
+/// Gas available to a system call, which is not charged.
+pub const SYSTEM_CALL_GAS: u64 = 30_000_000;
+
+/// Gas available to a view call. Large enough not to run out in practice,
+/// while leaving room for the gasometer arithmetic.
+pub const VIEW_CALL_GAS: u64 = u64::MAX / 2;
+
+/// Address of the RIPEMD-160 precompile. Its touch survives the revert of
+/// the touching frame, as mainnet consensus kept it after the Parity and
+/// Geth divergence of block 2675119.
+const RIPEMD: H160 = H160([
+	0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 3,
+]);
+
+/// Replacement code of an account, see `StackExecutor::with_code_override`.
+#[derive(Clone, Debug)]
+struct CodeOverride {
+	code: Vec<u8>,
+	hash: H256,
+	valids: Vec<u8>,
+}
+
+/// Accounts and storage slots accessed in a transaction, warm with EIP-2929.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+struct AccessedSet {
+	addresses: BTreeSet<H160>,
+	storage: BTreeSet<(H160, U256)>,
+}
+
+/// State of a suspended execution, see `StackExecutor::execution_state`.
+///
+/// An execution whose `Runtime::run` stopped with `StepLimitReached` is fully
+/// described by the runtime and this state: gas, cached accounts, logs,
+/// self-destructs and warm entries. Both can be serialized with `with-serde`,
+/// so that the execution resumes in another process, by an executor built
+/// with `StackExecutor::with_execution_state` over the same backend state.
+/// Configuration set by the `with_*` builders is not part of the state, and
+/// must be set again on the resuming executor.
+#[derive(Clone)]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "with-serde", serde(bound = ""))]
+pub struct ExecutionState<S = Istanbul> {
+	gasometer: Gasometer<S>,
+	#[cfg_attr(feature = "with-serde", serde(with = "serde_entries"))]
+	state: BTreeMap<H160, StackAccount>,
+	deleted: BTreeSet<H160>,
+	touched: BTreeSet<H160>,
+	logs: Vec<Log>,
+	traced_logs: Option<Vec<TracedLog>>,
+	#[cfg_attr(feature = "with-serde", serde(with = "serde_bytes"))]
+	return_data_buffer: Vec<u8>,
+	is_static: bool,
+	depth: Option<usize>,
+	log_size: usize,
+	prepaid: Option<(usize, usize)>,
+	frame_gas: Option<(u64, u64, u64)>,
+	accessed: AccessedSet,
+	prewarmed: AccessedSet,
+	dirty: BTreeSet<H160>,
+	destroyed: BTreeSet<H160>,
+	#[cfg_attr(feature = "with-serde", serde(with = "serde_entries"))]
+	originals: BTreeMap<(H160, U256), U256>,
+	execution_stats: ExecutionStats,
+}
+
+/// Execution stopped by its step limit, see
+/// `StackExecutor::execute_with_step_limit`.
+///
+/// It holds the runtime and the state of the execution, along with those of
+/// the call and create frames it was running, and is resumed with
+/// `StackExecutor::resume`.
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "with-serde", serde(bound = ""))]
+pub struct Pending<S = Istanbul> {
+	runtime: Runtime,
+	state: ExecutionState<S>,
+	/// Child frames, innermost last.
+	frames: Vec<PendingFrame<S>>,
+	steps: u64,
+}
+
+/// Child frame of a pending execution.
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "with-serde", serde(bound = ""))]
+struct PendingFrame<S> {
+	runtime: Runtime,
+	state: ExecutionState<S>,
+	exit: FrameExit,
+}
+
+/// How the parent of a frame takes its outcome once it exits.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+enum FrameExit {
+	Call,
+	/// Deploy the returned code at `address`, as an EOF container if `eof`.
+	Create { address: H160, eof: bool },
+}
+
+/// Call or create frame about to run, trapped out of the interpreter.
+///
+/// Step-limited executions, see `StackExecutor::execute_with_step_limit`,
+/// run child frames one after the other instead of recursively, so that the
+/// step limit covers them and they can be suspended.
+pub struct FrameInterrupt<'backend, B, S = Istanbul> {
+	executor: StackExecutor<'backend, B, S>,
+	runtime: Runtime,
+	exit: FrameExit,
+}
+
+/// Outcome of a call or create, or the frame it entered.
+type Entered<'backend, B, S, T> = Capture<T, Box<FrameInterrupt<'backend, B, S>>>;
+
+impl<B: Backend, S: Spec> FrameInterrupt<'_, B, S> {
+	fn suspend(self) -> PendingFrame<S> {
+		PendingFrame {
+			state: self.executor.execution_state(),
+			runtime: self.runtime,
+			exit: self.exit,
+		}
+	}
+}
+
+impl<S> Pending<S> {
+	/// Runtime of the execution.
+	#[must_use]
+	pub const fn runtime(&self) -> &Runtime {
+		&self.runtime
+	}
+
+	/// Number of call and create frames suspended below the runtime.
+	#[must_use]
+	pub const fn depth(&self) -> usize {
+		self.frames.len()
+	}
+
+	/// Steps executed so far, over all slices.
+	#[must_use]
+	pub const fn steps(&self) -> u64 {
+		self.steps
+	}
+}
+
+/// Exit status, output and used gas of a precompile.
+pub type PrecompileOutput = (ExitSucceed, Vec<u8>, u64);
+/// Result of a precompile, `None` if there is no precompile at the address.
+pub type PrecompileResult = Option<Result<PrecompileOutput, ExitError>>;
+/// Precompiles, called with the code address, input and target gas.
+pub type PrecompileFn = fn(H160, &[u8], Option<u64>) -> PrecompileResult;
 
 /// Stack-based executor.
 #[derive(Clone)]
-pub struct StackExecutor<'backend, B> {
+pub struct StackExecutor<'backend, B, S = Istanbul> {
 	backend: &'backend B,
-	gasometer: Gasometer,
+	gasometer: Gasometer<S>,
 	state: BTreeMap<H160, StackAccount>,
 	deleted: BTreeSet<H160>,
+	touched: BTreeSet<H160>,
 	logs: Vec<Log>,
+	/// All logs emitted, reverted ones included, if retained.
+	traced_logs: Option<Vec<TracedLog>>,
+	return_data_buffer: Vec<u8>,
 	precompile: PrecompileFn,
+	precompiles: Option<&'backend Precompiles>,
+	listener: Option<&'backend dyn EventListener>,
+	trace_config: TraceConfig,
+	nonce_policy: Option<&'backend dyn NoncePolicy>,
+	collision_policy: Option<&'backend dyn CollisionPolicy>,
+	gas_forwarding: Option<&'backend dyn GasForwardingPolicy>,
+	/// Opcodes failing with `OpcodeDisabled` in this frame and its
+	/// children, on top of those disabled by the hard fork.
+	banned_opcodes: OpcodeSet,
 	is_static: bool,
 	depth: Option<usize>,
+	fused_dispatch: bool,
+	/// Whether child frames are trapped out of the interpreter to be run by
+	/// `run_slice`, instead of run recursively.
+	trap_frames: bool,
+	fees: Option<Fees>,
+	tx_context: Option<TxContext>,
+	limits: Limits,
+	state_limits: StateLimits,
+	log_size: usize,
+	analyses: Option<AnalysisCache>,
+	/// Replacement code per address, kept across transactions.
+	code_overrides: Rc<BTreeMap<H160, CodeOverride>>,
+	/// Analysis of the code being executed, when gas is charged per block.
+	analysis: Option<Rc<CodeAnalysis>>,
+	/// Next position and end of the basic block whose gas is already charged.
+	prepaid: Option<(usize, usize)>,
+	/// Gas left before forwarding, gas forwarded and stipend of the child
+	/// frame being run, reported by its `Exit` event.
+	frame_gas: Option<(u64, u64, u64)>,
+	#[cfg(feature = "jit")]
+	jit: Option<JitCache>,
+	#[cfg(feature = "debug-trace")]
+	debug_sink: Option<Arc<dyn DebugSink>>,
+	#[cfg(feature = "taint")]
+	taint_sink: Option<Arc<dyn TaintSink>>,
+	account_cache: AccountCache,
+	accessed: AccessedSet,
+	/// Entries warm from the start of the next transaction.
+	prewarmed: AccessedSet,
+	/// Accounts changed since the last `commit_to`.
+	dirty: BTreeSet<H160>,
+	/// Accounts deleted by previous transactions of the executor, see
+	/// `reset_for_next_transaction`.
+	destroyed: BTreeSet<H160>,
+	/// Storage values at the start of the transaction, recorded on the
+	/// first write of a slot.
+	originals: BTreeMap<(H160, U256), U256>,
+	execution_stats: ExecutionStats,
+}
+
+/// Apply the state of `executor` and its logs to `backend`.
+///
+/// This is `deconstruct` followed by `ApplyBackend::apply`, without
+/// collecting the applies first: the accounts of the executor, storage
+/// included, are moved into the backend one at a time. Empty accounts are
+/// deleted if the hard fork clears touched empty accounts.
+pub fn apply_from_executor<A, B, S>(backend: &mut A, executor: StackExecutor<'_, B, S>)
+where
+	A: ApplyBackend,
+	B: Backend,
+	S: Spec,
+{
+	let (applies, logs) = executor.into_applies();
+	backend.apply(applies, logs, S::CONFIG.clear_touched_empty);
 }
 
-const fn no_precompile(
+pub const fn no_precompile(
 	_address: H160,
 	_input: &[u8],
 	_target_gas: Option<u64>
@@ -68,43 +470,551 @@ impl<'backend, B: 'backend + Backend> StackExecutor<'backend, B> {
 		backend: &'backend B,
 		gas_limit: u64,
 		precompile: PrecompileFn,
+	) -> Self {
+		Self::new_with_spec(backend, gas_limit, precompile, Istanbul)
+	}
+}
+
+impl<'backend, B: 'backend + Backend, S: Spec> StackExecutor<'backend, B, S> {
+	/// Create a new stack-based executor with given precompiles, for the
+	/// hard fork `S`.
+	pub fn new_with_spec(
+		backend: &'backend B,
+		gas_limit: u64,
+		precompile: PrecompileFn,
+		_spec: S,
 	) -> Self {
 		Self {
 			backend,
 			gasometer: Gasometer::new(gas_limit),
 			state: BTreeMap::new(),
 			deleted: BTreeSet::new(),
+			touched: BTreeSet::new(),
 			logs: Vec::new(),
+			traced_logs: None,
+			return_data_buffer: Vec::new(),
 			precompile,
+			precompiles: None,
+			listener: None,
+			trace_config: TraceConfig::all(),
+			nonce_policy: None,
+			collision_policy: None,
+			gas_forwarding: None,
+			banned_opcodes: OpcodeSet::new(),
 			is_static: false,
 			depth: None,
+			fused_dispatch: false,
+			trap_frames: false,
+			fees: None,
+			tx_context: None,
+			limits: Limits::from_config(&S::CONFIG),
+			state_limits: StateLimits::default(),
+			log_size: 0,
+			analyses: None,
+			code_overrides: Rc::default(),
+			analysis: None,
+			prepaid: None,
+			frame_gas: None,
+			#[cfg(feature = "jit")]
+			jit: None,
+			#[cfg(feature = "debug-trace")]
+			debug_sink: None,
+			#[cfg(feature = "taint")]
+			taint_sink: None,
+			account_cache: AccountCache::default(),
+			accessed: AccessedSet::default(),
+			prewarmed: AccessedSet::default(),
+			dirty: BTreeSet::new(),
+			destroyed: BTreeSet::new(),
+			originals: BTreeMap::new(),
+			execution_stats: ExecutionStats::default(),
+		}
+	}
+
+	/// Run `code` instead of the code of `address`, as seen by calls and by
+	/// the code queries, without changing the state. Unlike a state
+	/// override, the substitution is never committed and survives across
+	/// transactions, for instance to run instrumented builds of contracts
+	/// or debugging shims.
+	#[must_use]
+	pub fn with_code_override(mut self, address: H160, code: Vec<u8>) -> Self {
+		let hash = self.backend.keccak256_h256(&code);
+		let valids = Valids::compute(&code);
+		Rc::make_mut(&mut self.code_overrides).insert(address, CodeOverride { code, hash, valids });
+		self
+	}
+
+	/// Replacement code of `address`, if any.
+	#[must_use]
+	pub fn code_override(&self, address: H160) -> Option<&[u8]> {
+		self.code_overrides.get(&address).map(|code| code.code.as_slice())
+	}
+
+	/// Serve calls to precompiled contracts with the given registry, which
+	/// takes precedence over the precompile function of the executor.
+	#[must_use]
+	pub const fn with_precompiles(mut self, precompiles: &'backend Precompiles) -> Self {
+		self.precompiles = Some(precompiles);
+		self
+	}
+
+	/// Attach an event listener, notified about calls, creates and steps of
+	/// the executor.
+	#[must_use]
+	pub fn with_listener(mut self, listener: &'backend dyn EventListener) -> Self {
+		self.listener = Some(listener);
+		self
+	}
+
+	/// Only notify the listener about the given categories of events.
+	#[must_use]
+	pub const fn with_trace_config(mut self, config: TraceConfig) -> Self {
+		self.trace_config = config;
+		self
+	}
+
+	/// Decide when nonces are increased with the given policy, instead of
+	/// always increasing them as Ethereum does.
+	#[must_use]
+	pub fn with_nonce_policy(mut self, policy: &'backend dyn NoncePolicy) -> Self {
+		self.nonce_policy = Some(policy);
+		self
+	}
+
+	/// Decide which collision checks creates run with the given policy,
+	/// instead of the EIP-684 checks.
+	#[must_use]
+	pub fn with_collision_policy(mut self, policy: &'backend dyn CollisionPolicy) -> Self {
+		self.collision_policy = Some(policy);
+		self
+	}
+
+	/// Decide how much gas child frames receive with the given policy,
+	/// instead of the Ethereum rules.
+	#[must_use]
+	pub fn with_gas_forwarding(mut self, policy: &'backend dyn GasForwardingPolicy) -> Self {
+		self.gas_forwarding = Some(policy);
+		self
+	}
+
+	/// Execute common opcode sequences as superinstructions, see
+	/// `Machine::fuse`.
+	#[must_use]
+	pub const fn with_fused_dispatch(mut self) -> Self {
+		self.fused_dispatch = true;
+		self
+	}
+
+	/// Charge gas fees in the `transact_*` functions: the sender pays the gas
+	/// limit upfront and is refunded the unused gas, the coinbase receives
+	/// the priority fee of the used gas, and the base fee is burnt.
+	/// Transactions whose sender cannot pay the gas limit exit with
+	/// `OutOfFund` without being executed.
+	#[must_use]
+	pub const fn with_fees(mut self, fees: Fees) -> Self {
+		self.fees = Some(fees);
+		self
+	}
+
+	/// Read `ORIGIN` and `GASPRICE` from the given transaction data instead
+	/// of the backend, so that transactions of different senders can run on
+	/// the same backend.
+	#[must_use]
+	pub const fn with_tx_context(mut self, context: TxContext) -> Self {
+		self.tx_context = Some(context);
+		self
+	}
+
+	/// Replace the transaction data, before running the next transaction
+	/// with the same executor, see `with_tx_context`.
+	pub const fn set_tx_context(&mut self, context: TxContext) {
+		self.tx_context = Some(context);
+	}
+
+	/// Run frames with the given stack, memory and return data limits
+	/// instead of those of the hard fork.
+	#[must_use]
+	pub const fn with_limits(mut self, limits: Limits) -> Self {
+		self.limits = limits;
+		self
+	}
+
+	/// Limits frames run with.
+	#[must_use]
+	pub const fn limits(&self) -> Limits {
+		self.limits
+	}
+
+	/// Cap the logs and the self-destruct set of the transaction.
+	#[must_use]
+	pub const fn with_state_limits(mut self, limits: StateLimits) -> Self {
+		self.state_limits = limits;
+		self
+	}
+
+	/// Read the accounts of the backend through the given cache, for
+	/// instance one shared with the executors of the previous transactions
+	/// of a block, committed with `commit_to`.
+	#[must_use]
+	pub fn with_account_cache(mut self, cache: AccountCache) -> Self {
+		self.account_cache = cache;
+		self
+	}
+
+	/// Charge the static gas of each basic block at once on entry, using the
+	/// given cache of code analyses. Gas used and exit reasons are unchanged,
+	/// but the `gas_cost` of steps is the cost of the whole block for its
+	/// first opcode and zero for the following ones. The frames running the
+	/// same code also share its jump destinations instead of computing them.
+	#[must_use]
+	pub fn with_analysis_cache(mut self, cache: AnalysisCache) -> Self {
+		self.analyses = Some(cache);
+		self
+	}
+
+	/// Count calls per code hash in the given cache, and run code through
+	/// its compiled translation once it is hot. Init code is always
+	/// interpreted.
+	#[cfg(feature = "jit")]
+	#[must_use]
+	pub fn with_jit(mut self, cache: JitCache) -> Self {
+		self.jit = Some(cache);
+		self
+	}
+
+	/// Report the opcodes executed in every frame to `sink`, see
+	/// `Machine::set_debug_sink`.
+	#[cfg(feature = "debug-trace")]
+	#[must_use]
+	pub fn with_debug_sink(mut self, sink: Arc<dyn DebugSink>) -> Self {
+		self.debug_sink = Some(sink);
+		self
+	}
+
+	/// Track the values derived from the call data of every frame, reporting
+	/// the storage writes and calls they reach to `sink`, see
+	/// `Machine::set_taint_sink`. Each frame only tracks its own call data.
+	#[cfg(feature = "taint")]
+	#[must_use]
+	pub fn with_taint_sink(mut self, sink: Arc<dyn TaintSink>) -> Self {
+		self.taint_sink = Some(sink);
+		self
+	}
+
+	/// Accumulate the gas of the transactions by category, across frames,
+	/// see `gas_breakdown`.
+	#[must_use]
+	pub fn with_gas_breakdown(mut self) -> Self {
+		self.gasometer = self.gasometer.with_breakdown();
+		self
+	}
+
+	/// Retain the logs of frames that revert or fail along with the others,
+	/// for debuggers, see `traced_logs`. The logs applied to the state are
+	/// unchanged.
+	#[must_use]
+	pub fn with_reverted_logs(mut self) -> Self {
+		self.traced_logs = Some(Vec::new());
+		self
+	}
+
+	/// Run every frame as static, see `StackExecutorBuilder::with_static`.
+	pub(crate) const fn with_static(mut self) -> Self {
+		self.is_static = true;
+		self
+	}
+
+	/// Run the executor as if nested in a frame at the given call depth, so
+	/// that its frames count towards the call limit. This is meant for
+	/// executors spawned by a call interceptor, see `CallInterceptor`.
+	#[must_use]
+	pub const fn with_depth(mut self, depth: usize) -> Self {
+		self.depth = Some(depth);
+		self
+	}
+
+	/// Call depth of the frame being executed, `None` for the transaction
+	/// level executor.
+	#[must_use]
+	pub const fn depth(&self) -> Option<usize> {
+		self.depth
+	}
+
+	/// Gas to charge for an opcode with a static cost. With code analysis,
+	/// the first opcode of a basic block pays for the whole block, unless the
+	/// block would run out of gas or stack, so that errors happen at the same
+	/// opcode as without it.
+	fn block_cost(&mut self, opcode: Opcode, position: usize, stack: &Stack, cost: u64) -> u64 {
+		let push_size = opcode.as_u8().wrapping_sub(Opcode::PUSH1.as_u8());
+		let next = position + 1 + if push_size < 32 { usize::from(push_size) + 1 } else { 0 };
+
+		if let Some((expected, end)) = self.prepaid.take() {
+			if expected == position {
+				if next < end {
+					self.prepaid = Some((next, end));
+				}
+				return 0
+			}
+		}
+
+		let Some(block) = self.analysis.as_ref().and_then(|analysis| analysis.block(position).copied()) else {
+			return cost
+		};
+		if next >= block.end
+			|| stack.len() < block.stack_min
+			|| stack.len() + block.stack_growth > self.limits.stack_limit
+			|| block.gas > self.gasometer.gas()
+		{
+			return cost
+		}
+
+		self.prepaid = Some((next, block.end));
+		block.gas
+	}
+
+	/// Nonce and balance of the account at `address`, from the state, else
+	/// from the backend through the account cache.
+	fn account_basic(&self, address: H160) -> Basic {
+		self.state.get(&address).map_or_else(|| self.account_cache.basic(self.backend, address), |account| account.basic.clone())
+	}
+
+	/// Code size of the account at `address`, from the state, else from the
+	/// backend through the account cache.
+	fn account_code_size(&self, address: H160) -> usize {
+		self.code_overrides.get(&address).map(|code| &code.code)
+			.or_else(|| self.state.get(&address).and_then(|account| account.code.as_ref()))
+			.map_or_else(|| self.account_cache.code_size(self.backend, address), Vec::len)
+	}
+
+	/// Read the storage of `address` at `indices` from the backend in one
+	/// `Backend::storage_multi` query, so that later reads are served from
+	/// memory. Slots already prefetched, or resolved by the state of the
+	/// executor, are skipped. Transactions prefetch the storage keys warm
+	/// from their start, such as those of their access list.
+	pub fn prefetch_storage(&self, address: H160, indices: &[U256]) {
+		let account = self.state.get(&address);
+		if account.is_some_and(|account| account.reset_storage) {
+			return
+		}
+		let indices = indices.iter()
+			.filter(|index| !account.is_some_and(|account| account.storage.contains_key(index)))
+			.filter(|index| !self.account_cache.is_prefetched(address, **index))
+			.copied()
+			.collect::<BTreeSet<_>>();
+		self.account_cache.prefetch(self.backend, address, indices.into_iter().collect());
+	}
+
+	/// Whether the registry reserves `address` for precompiles, served by a
+	/// precompile or not.
+	fn is_precompile(&self, address: H160) -> bool {
+		self.precompiles.is_some_and(|precompiles| precompiles.is_reserved(&S::CONFIG, address))
+	}
+
+	/// Whether `address` is one of the Ethereum precompiles of the hard
+	/// fork, see `Config::ethereum_precompiles`.
+	fn is_ethereum_precompile(address: H160) -> bool {
+		let (prefix, last) = address.as_bytes().split_at(19);
+		prefix.iter().all(|byte| *byte == 0) && (1..=S::CONFIG.ethereum_precompiles).contains(&last[0])
+	}
+
+	/// Whether the account at `address` has no nonce, balance nor code, as
+	/// defined by EIP-161. The code size is only read if the rest is empty.
+	fn is_empty_account(&self, address: H160) -> bool {
+		let basic = self.account_basic(address);
+		basic.nonce == U256::zero() && basic.balance == U256::zero() && self.account_code_size(address) == 0
+	}
+
+	fn trace(&self, event: Event<'_>) {
+		if let Some(listener) = self.listener {
+			if self.trace_config.enabled(&event) {
+				listener.event(event);
+			}
 		}
 	}
 
 	/// Create a substate executor from the current executor.
 	#[must_use]
-	pub fn substate(&self, gas_limit: u64, is_static: bool) -> StackExecutor<'backend, B> {
+	pub fn substate(&self, gas_limit: u64, is_static: bool) -> Self {
 		Self {
 			backend: self.backend,
-			gasometer: Gasometer::new(gas_limit),
+			gasometer: self.new_gasometer(gas_limit),
 			state: self.state.clone(),
 			deleted: self.deleted.clone(),
+			touched: self.touched.clone(),
 			logs: self.logs.clone(),
+			traced_logs: self.traced_logs.clone(),
+			return_data_buffer: Vec::new(),
 			precompile: self.precompile,
+			precompiles: self.precompiles,
+			listener: self.listener,
+			trace_config: self.trace_config,
+			nonce_policy: self.nonce_policy,
+			collision_policy: self.collision_policy,
+			gas_forwarding: self.gas_forwarding,
+			banned_opcodes: self.banned_opcodes,
 			is_static: is_static || self.is_static,
 			depth: match self.depth {
 				None => Some(0),
 				Some(n) => Some(n + 1),
 			},
+			fused_dispatch: self.fused_dispatch,
+			trap_frames: self.trap_frames,
+			fees: self.fees,
+			tx_context: self.tx_context,
+			limits: self.limits,
+			state_limits: self.state_limits,
+			log_size: self.log_size,
+			analyses: self.analyses.clone(),
+			code_overrides: self.code_overrides.clone(),
+			analysis: None,
+			prepaid: None,
+			frame_gas: None,
+			#[cfg(feature = "jit")]
+			jit: self.jit.clone(),
+			#[cfg(feature = "debug-trace")]
+			debug_sink: self.debug_sink.clone(),
+			#[cfg(feature = "taint")]
+			taint_sink: self.taint_sink.clone(),
+			account_cache: self.account_cache.clone(),
+			accessed: self.accessed.clone(),
+			prewarmed: AccessedSet::default(),
+			dirty: self.dirty.clone(),
+			destroyed: self.destroyed.clone(),
+			originals: self.originals.clone(),
+			execution_stats: self.execution_stats,
 		}
 	}
 
 	/// Execute the runtime until it returns.
 	pub fn execute(&mut self, runtime: &mut Runtime) -> ExitReason {
-		match runtime.run(u64::max_value(), self).1 {
-			Capture::Exit(s) => s,
-			Capture::Trap(_) => unreachable!("Trap is Infallible"),
+		loop {
+			match runtime.run(u64::MAX, self).1 {
+				Capture::Exit(s) => return s,
+				Capture::Trap(Resolve::Call(frame, resolve)) => {
+					let (reason, _, output) = self.run_frame(*frame);
+					let output = self.record_return_data(reason, output);
+					let _ = resolve.resolve(self, reason, output, ());
+				},
+				Capture::Trap(Resolve::Create(frame, resolve)) => {
+					let (reason, address, output) = self.run_frame(*frame);
+					let output = self.record_return_data(reason, output);
+					let _ = resolve.resolve(self, reason, address, output, ());
+				},
+			}
+		}
+	}
+
+	/// Execute the runtime for at most `max_steps` steps, those of the
+	/// frames it calls or creates included. An execution exiting in time
+	/// gives its exit reason and return value, otherwise it is suspended as
+	/// `Pending`, to be continued by `resume` with this or another executor,
+	/// for instance on the next compute budget.
+	pub fn execute_with_step_limit(
+		&mut self,
+		runtime: Runtime,
+		max_steps: u64,
+	) -> Capture<(ExitReason, Vec<u8>), Pending<S>> {
+		self.run_slice(runtime, Vec::new(), max_steps, 0)
+	}
+
+	/// Continue a pending execution for at most `max_steps` more steps, see
+	/// `execute_with_step_limit`. The state of the execution replaces the one
+	/// of this executor, which can then be deconstructed once it exits.
+	pub fn resume(
+		&mut self,
+		pending: Pending<S>,
+		max_steps: u64,
+	) -> Capture<(ExitReason, Vec<u8>), Pending<S>> {
+		self.set_execution_state(pending.state);
+		let mut frames: Vec<FrameInterrupt<'backend, B, S>> = Vec::with_capacity(pending.frames.len());
+		for frame in pending.frames {
+			let parent = frames.last().map_or(&*self, |parent| &parent.executor);
+			let executor = parent.substate(0, false).with_execution_state(frame.state);
+			frames.push(FrameInterrupt { executor, runtime: frame.runtime, exit: frame.exit });
 		}
+		self.run_slice(pending.runtime, frames, max_steps, pending.steps)
+	}
+
+	/// Run the innermost of `runtime` and `frames` until the step limit is
+	/// reached or `runtime` exits. Frames entered are pushed onto `frames`,
+	/// and popped and merged into their parent as they exit.
+	fn run_slice(
+		&mut self,
+		mut runtime: Runtime,
+		mut frames: Vec<FrameInterrupt<'backend, B, S>>,
+		max_steps: u64,
+		steps: u64,
+	) -> Capture<(ExitReason, Vec<u8>), Pending<S>> {
+		self.trap_frames = true;
+		let mut executed = 0;
+		let reason = loop {
+			let (count, capture) = match frames.last_mut() {
+				Some(frame) => Self::run_until_frame(&mut frame.executor, &mut frame.runtime, max_steps - executed),
+				None => Self::run_until_frame(self, &mut runtime, max_steps - executed),
+			};
+			executed += count;
+
+			match capture {
+				Capture::Trap(frame) => frames.push(*frame),
+				Capture::Exit(ExitReason::StepLimitReached) => break None,
+				Capture::Exit(reason) => {
+					let Some(frame) = frames.pop() else { break Some(reason) };
+					match frames.last_mut() {
+						Some(parent) => parent.executor.resolve_frame(&mut parent.runtime, frame, reason),
+						None => self.resolve_frame(&mut runtime, frame, reason),
+					}
+				},
+			}
+		};
+		self.trap_frames = false;
+
+		match reason {
+			Some(reason) => {
+				let output = runtime.machine().return_value();
+				Capture::Exit((reason, output))
+			},
+			None => Capture::Trap(Pending {
+				state: self.execution_state(),
+				frames: frames.into_iter().map(FrameInterrupt::suspend).collect(),
+				runtime,
+				steps: steps + executed,
+			}),
+		}
+	}
+
+	/// Run `runtime` for at most `max_steps` steps, until it exits or enters
+	/// a child frame.
+	fn run_until_frame(
+		executor: &mut Self,
+		runtime: &mut Runtime,
+		max_steps: u64,
+	) -> (u64, Entered<'backend, B, S, ExitReason>) {
+		let (count, capture) = runtime.run(max_steps, executor);
+		let capture = match capture {
+			Capture::Exit(reason) => Capture::Exit(reason),
+			Capture::Trap(Resolve::Call(frame, resolve)) => {
+				resolve.suspend();
+				Capture::Trap(frame)
+			},
+			Capture::Trap(Resolve::Create(frame, resolve)) => {
+				resolve.suspend();
+				Capture::Trap(frame)
+			},
+		};
+		(count, capture)
+	}
+
+	/// Merge a child frame that exited with `reason` and resolve the call or
+	/// create of `runtime` that entered it. A failed resolution stops
+	/// `runtime`, which then exits when run.
+	fn resolve_frame(&mut self, runtime: &mut Runtime, frame: FrameInterrupt<'backend, B, S>, reason: ExitReason) {
+		let exit = frame.exit;
+		let (reason, address, output) = self.exit_frame_with(frame, reason);
+		let output = self.record_return_data(reason, output);
+		let _ = match exit {
+			FrameExit::Call => ResolveCall::suspended(runtime).resolve(self, reason, output, ()),
+			FrameExit::Create { .. } => ResolveCreate::suspended(runtime).resolve(self, reason, address, output, ()),
+		};
 	}
 
 	/// Get remaining gas.
@@ -116,34 +1026,155 @@ impl<'backend, B: 'backend + Backend> StackExecutor<'backend, B> {
 	/// Merge a substate executor that succeeded.
 	pub fn merge_succeed<OB>(
 		&mut self,
-		mut substate: StackExecutor<OB>
+		mut substate: StackExecutor<OB, S>
 	) -> Result<(), ExitError> {
-		self.logs.append(&mut substate.logs);
+		self.logs = substate.logs;
+		self.traced_logs = substate.traced_logs;
+		self.log_size = substate.log_size;
 		self.deleted.append(&mut substate.deleted);
+		self.touched = substate.touched;
 		self.state = substate.state;
+		self.accessed = substate.accessed;
+		self.dirty = substate.dirty;
+		self.destroyed = substate.destroyed;
+		self.originals = substate.originals;
+		self.execution_stats = substate.execution_stats;
 
 		self.gasometer.record_stipend(substate.gasometer.gas())?;
+		self.gasometer.merge_breakdown(substate.gasometer.breakdown());
 		self.gasometer.record_refund(substate.gasometer.refunded_gas())?;
 		Ok(())
 	}
 
 	/// Merge a substate executor that reverted.
+	#[allow(clippy::needless_pass_by_value)]
 	pub fn merge_revert<OB>(
 		&mut self,
-		mut substate: StackExecutor<OB>
+		mut substate: StackExecutor<OB, S>
 	) -> Result<(), ExitError> {
-		self.logs.append(&mut substate.logs);
-
+		self.merge_ripemd_touch(&substate);
+		self.revert_logs(&substate);
+		self.merge_reverted_logs(&mut substate);
 		self.gasometer.record_stipend(substate.gasometer.gas())?;
+		self.gasometer.merge_breakdown(substate.gasometer.breakdown());
+		self.execution_stats = substate.execution_stats;
 		Ok(())
 	}
 
 	/// Merge a substate executor that failed.
+	#[allow(clippy::needless_pass_by_value)]
 	pub fn merge_fail<OB>(
 		&mut self,
-		mut substate: StackExecutor<OB>
+		mut substate: StackExecutor<OB, S>
 	) -> Result<(), ExitError> {
-		self.logs.append(&mut substate.logs);
+		self.merge_ripemd_touch(&substate);
+		self.revert_logs(&substate);
+		self.merge_reverted_logs(&mut substate);
+		self.gasometer.merge_breakdown(substate.gasometer.breakdown());
+		self.execution_stats = substate.execution_stats;
+		Ok(())
+	}
+
+	/// Charge the gas of an intercepted call to its frame and merge the
+	/// frame according to the exit reason, see `CallInterceptor`.
+	fn merge_intercepted<OB>(
+		&mut self,
+		mut substate: StackExecutor<OB, S>,
+		response: CallResponse,
+	) -> (ExitReason, Vec<u8>) {
+		let CallResponse { reason, output, gas_used, gas_refunded } = response;
+		if let Err(e) = substate.gasometer.record_cost(gas_used) {
+			let _ = self.merge_fail(substate);
+			return (e.into(), Vec::new())
+		}
+
+		match reason {
+			ExitReason::Succeed(_) => {
+				let _ = substate.gasometer.record_refund(gas_refunded);
+				let _ = self.merge_succeed(substate);
+			},
+			ExitReason::Revert(_) => {
+				let _ = self.merge_revert(substate);
+			},
+			ExitReason::Error(_) => {
+				let _ = self.merge_fail(substate);
+			},
+			ExitReason::Fatal(_) => (),
+			ExitReason::StepLimitReached => {
+				// Interceptors run outside of the step loop, so a step limit
+				// cannot be resumed.
+				return (ExitFatal::UnhandledInterrupt.into(), Vec::new())
+			},
+		}
+		(reason, output)
+	}
+
+	/// Notify the log sink that the logs of a substate that reverted or
+	/// failed were dropped, if it emitted any.
+	fn revert_logs<OB>(&self, substate: &StackExecutor<OB, S>) {
+		if substate.logs.len() > self.logs.len() {
+			if let Some(sink) = self.backend.log_sink() {
+				sink.revert(self.logs.len());
+			}
+		}
+	}
+
+	/// Keep the retained logs of a substate that reverted or failed, marking
+	/// those it emitted as reverted.
+	fn merge_reverted_logs<OB>(&mut self, substate: &mut StackExecutor<OB, S>) {
+		let (Some(logs), Some(substate_logs)) = (&mut self.traced_logs, &mut substate.traced_logs) else {
+			return
+		};
+		for mut log in substate_logs.drain(logs.len()..) {
+			log.reverted = true;
+			logs.push(log);
+		}
+	}
+
+	fn merge_ripemd_touch<OB>(&mut self, substate: &StackExecutor<OB, S>) {
+		if substate.touched.contains(&RIPEMD) {
+			self.touched.insert(RIPEMD);
+		}
+	}
+
+	/// Check that a transaction can be executed against the current state:
+	/// the sender exists, has the transaction nonce and a balance covering
+	/// `value` plus `gas_limit` times `gas_price`, the gas limit covers the
+	/// intrinsic gas of `cost`, and with `reject_sender_with_code` the sender
+	/// has no code.
+	pub fn validate_transaction(
+		&self,
+		caller: H160,
+		value: U256,
+		gas_limit: u64,
+		gas_price: U256,
+		nonce: U256,
+		cost: &TransactionCost,
+	) -> Result<(), TransactionValidationError> {
+		if !self.exists(caller) {
+			return Err(TransactionValidationError::UnknownSender)
+		}
+
+		let expected = self.nonce(caller);
+		if expected != nonce {
+			return Err(TransactionValidationError::InvalidNonce { expected, actual: nonce })
+		}
+
+		let balance = self.balance(caller);
+		let total = gas_price.checked_mul(U256::from(gas_limit))
+			.and_then(|fee| fee.checked_add(value));
+		if total.is_none_or(|total| total > balance) {
+			return Err(TransactionValidationError::InsufficientBalance { balance, cost: total })
+		}
+
+		let intrinsic_gas = gasometer::intrinsic_gas::<S>(cost);
+		if intrinsic_gas > gas_limit {
+			return Err(TransactionValidationError::IntrinsicGasTooLow { intrinsic_gas, gas_limit })
+		}
+
+		if S::CONFIG.reject_sender_with_code && self.code_size(caller) != U256::zero() {
+			return Err(TransactionValidationError::SenderHasCode)
+		}
 
 		Ok(())
 	}
@@ -161,18 +1192,27 @@ impl<'backend, B: 'backend + Backend> StackExecutor<'backend, B> {
 			Ok(()) => (),
 			Err(e) => return e.into(),
 		}
+		if let Err(e) = self.buy_gas(caller, gas_limit) {
+			return e.into()
+		}
+		self.start_access_tracking(caller, None);
 
-		match self.create_inner(
+		let reason = match self.create_inner(
 			caller,
 			CreateScheme::Legacy { caller },
 			value,
 			init_code,
 			Some(gas_limit),
-			false,
+			FrameKind::Transaction,
 		) {
-			Capture::Exit((s, _, _)) => s,
-			Capture::Trap(_) => unreachable!(),
-		}
+			Capture::Exit((s, _, v)) => {
+				self.record_return_data(s, v);
+				s
+			},
+			Capture::Trap(interrupt) => match interrupt {},
+		};
+		self.settle_fees(caller, gas_limit);
+		reason
 	}
 
 	/// Execute a `CREATE2` transaction.
@@ -189,18 +1229,115 @@ impl<'backend, B: 'backend + Backend> StackExecutor<'backend, B> {
 			Ok(()) => (),
 			Err(e) => return e.into(),
 		}
-		let code_hash = self.backend.keccak256_h256(&init_code); //H256::from_slice(Keccak256::digest(&init_code).as_slice());
+		if let Err(e) = self.buy_gas(caller, gas_limit) {
+			return e.into()
+		}
+		self.start_access_tracking(caller, None);
+		let code_hash = self.backend.keccak256_h256(&init_code);
 
-		match self.create_inner(
+		let reason = match self.create_inner(
 			caller,
 			CreateScheme::Create2 { caller, code_hash, salt },
 			value,
 			init_code,
 			Some(gas_limit),
-			false,
+			FrameKind::Transaction,
+		) {
+			Capture::Exit((s, _, v)) => {
+				self.record_return_data(s, v);
+				s
+			},
+			Capture::Trap(interrupt) => match interrupt {},
+		};
+		self.settle_fees(caller, gas_limit);
+		reason
+	}
+
+	/// Execute a `CREATE` transaction deploying at the given address, as
+	/// done for genesis or system contracts. The creator nonce is increased
+	/// as for other creates, and the create fails with `CreateCollision` if
+	/// the address already has code or a nonce.
+	pub fn transact_create_fixed(
+		&mut self,
+		caller: H160,
+		address: H160,
+		value: U256,
+		init_code: Vec<u8>,
+		gas_limit: u64,
+	) -> ExitReason {
+		let transaction_cost = gasometer::create_transaction_cost(&init_code);
+		match self.gasometer.record_transaction(transaction_cost) {
+			Ok(()) => (),
+			Err(e) => return e.into(),
+		}
+		if let Err(e) = self.buy_gas(caller, gas_limit) {
+			return e.into()
+		}
+		self.start_access_tracking(caller, None);
+
+		let reason = match self.create_inner(
+			caller,
+			CreateScheme::Fixed(address),
+			value,
+			init_code,
+			Some(gas_limit),
+			FrameKind::Transaction,
 		) {
-			Capture::Exit((s, _, _)) => s,
-			Capture::Trap(_) => unreachable!(),
+			Capture::Exit((s, _, v)) => {
+				self.record_return_data(s, v);
+				s
+			},
+			Capture::Trap(interrupt) => match interrupt {},
+		};
+		self.settle_fees(caller, gas_limit);
+		reason
+	}
+
+	/// Execute a `CREATE` transaction, returning its gas and logs along with
+	/// the exit reason. Gas and logs are those of this transaction only, if
+	/// the executor already ran others, except that transactions share the
+	/// refund cap, so that part of an earlier refund may be applied to a
+	/// later transaction.
+	pub fn transact_create_with_outcome(
+		&mut self,
+		caller: H160,
+		value: U256,
+		init_code: Vec<u8>,
+		gas_limit: u64,
+	) -> TransactOutcome {
+		let before = self.outcome_start();
+		let exit = self.transact_create(caller, value, init_code, gas_limit);
+		let output = if exit.is_succeed() { Vec::new() } else { self.return_data_buffer.clone() };
+		self.outcome(before, exit, output)
+	}
+
+	/// Execute a `CALL` transaction, returning its gas and logs along with
+	/// the exit reason and return value, see `transact_create_with_outcome`.
+	pub fn transact_call_with_outcome(
+		&mut self,
+		caller: H160,
+		address: H160,
+		value: U256,
+		data: Vec<u8>,
+		gas_limit: u64,
+	) -> TransactOutcome {
+		let before = self.outcome_start();
+		let (exit, output) = self.transact_call(caller, address, value, data, gas_limit);
+		self.outcome(before, exit, output)
+	}
+
+	/// Used gas, refund and number of logs before a transaction.
+	fn outcome_start(&self) -> (u64, u64, usize) {
+		(self.used_gas(), self.gasometer.effective_refund(), self.logs.len())
+	}
+
+	fn outcome(&self, (used_gas, refund, logs): (u64, u64, usize), exit: ExitReason, output: Vec<u8>) -> TransactOutcome {
+		TransactOutcome {
+			exit,
+			output,
+			gas_used: self.used_gas() - used_gas,
+			gas_refunded: self.gasometer.effective_refund().saturating_sub(refund),
+			logs: self.logs[logs..].to_vec(),
 		}
 	}
 
@@ -218,8 +1355,14 @@ impl<'backend, B: 'backend + Backend> StackExecutor<'backend, B> {
 			Ok(()) => (),
 			Err(e) => return (e.into(), Vec::new()),
 		}
+		if let Err(e) = self.buy_gas(caller, gas_limit) {
+			return (e.into(), Vec::new())
+		}
+		self.start_access_tracking(caller, Some(address));
 
-		self.account_mut(caller).basic.nonce += U256::one();
+		if self.nonce_policy.is_none_or(|policy| policy.increase_on_call(caller)) {
+			self.inc_nonce(caller);
+		}
 
 		let context = Context {
 			caller,
@@ -227,25 +1370,386 @@ impl<'backend, B: 'backend + Backend> StackExecutor<'backend, B> {
 			apparent_value: value,
 		};
 
-		match self.call_inner(address, Some(Transfer {
-			source: caller,
-			target: address,
-			value
-		}), data, Some(gas_limit), false, false, false, context) {
-			Capture::Exit((s, v)) => (s, v),
-			Capture::Trap(_) => unreachable!(),
+		let result = match self.call_inner(address, Some(Transfer {
+			source: caller,
+			target: address,
+			value
+		}), data, Some(gas_limit), false, FrameKind::Transaction, context) {
+			Capture::Exit((s, v)) => (s, self.record_return_data(s, v)),
+			Capture::Trap(interrupt) => match interrupt {},
+		};
+		self.settle_fees(caller, gas_limit);
+		result
+	}
+
+	/// Decode a signed transaction envelope, legacy, EIP-2930 or EIP-1559,
+	/// recover its sender with `recovery` and execute it, see
+	/// `transact_transaction`.
+	pub fn transact_raw<R: SenderRecovery + ?Sized>(
+		&mut self,
+		raw: &[u8],
+		recovery: &R,
+	) -> Result<TransactOutcome, TransactionError> {
+		let transaction = Transaction::decode(raw)?;
+		let sender = transaction.recover_sender(self.backend, recovery)?;
+		self.transact_transaction(&transaction, sender)
+	}
+
+	/// Decode a signed transaction envelope and execute it from the sender
+	/// recovered from its signature, hashing with the backend, see
+	/// `transact_raw`.
+	#[cfg(feature = "secp256k1")]
+	pub fn transact_signed(&mut self, raw: &[u8]) -> Result<TransactOutcome, TransactionError> {
+		let backend = self.backend;
+		self.transact_raw(raw, &crate::transaction::Secp256k1(backend))
+	}
+
+	/// Execute a decoded transaction from `sender`. The transaction is
+	/// checked against the transaction types of the hard fork and the
+	/// chain ID of the backend, and validated as by
+	/// `validate_transaction`, with the intrinsic gas of its access list,
+	/// and is not executed if invalid. Its access list is warmed, and with
+	/// `with_fees` the sender pays the effective gas price of the
+	/// transaction at the configured base fee.
+	pub fn transact_transaction(
+		&mut self,
+		transaction: &Transaction,
+		sender: H160,
+	) -> Result<TransactOutcome, TransactionError> {
+		let activated = match transaction.transaction_type {
+			TransactionType::Legacy => true,
+			TransactionType::AccessList => S::CONFIG.has_access_list_transactions,
+			TransactionType::DynamicFee => S::CONFIG.has_dynamic_fee_transactions,
+		};
+		if !activated {
+			return Err(TransactionError::UnsupportedType(transaction.transaction_type.as_u8()))
+		}
+		if let Some(actual) = transaction.chain_id {
+			let expected = self.chain_id();
+			if expected != U256::from(actual) {
+				return Err(TransactionError::ChainIdMismatch { expected, actual })
+			}
+		}
+		if transaction.gas_limit > U256::from(u64::MAX) {
+			return Err(TransactionError::GasLimitOverflow)
+		}
+		let gas_limit = transaction.gas_limit.as_u64();
+
+		let base_fee = self.fees.map(|fees| fees.base_fee).unwrap_or_default();
+		let gas_price = transaction.effective_gas_price(base_fee)?;
+
+		self.validate_transaction(
+			sender,
+			transaction.value,
+			gas_limit,
+			transaction.gas_price,
+			transaction.nonce,
+			&transaction.transaction_cost(),
+		)?;
+		let intrinsic_gas = transaction.intrinsic_gas::<S>();
+		if intrinsic_gas > gas_limit {
+			return Err(TransactionValidationError::IntrinsicGasTooLow { intrinsic_gas, gas_limit }.into())
+		}
+
+		let fees = self.fees;
+		self.fees = fees.map(|fees| Fees { gas_price, ..fees });
+		self.set_tx_context(TxContext { origin: sender, gas_price });
+		self.prewarm_access_list(&transaction.access_list);
+
+		let before = self.outcome_start();
+		let access_list_gas = intrinsic_gas - gasometer::intrinsic_gas::<S>(&transaction.transaction_cost());
+		let outcome = if let Err(e) = self.gasometer.record_cost_in(access_list_gas, GasCategory::Intrinsic) {
+			self.outcome(before, e.into(), Vec::new())
+		} else {
+			match transaction.action {
+				TransactionAction::Call(address) => {
+					let (exit, output) =
+						self.transact_call(sender, address, transaction.value, transaction.input.clone(), gas_limit);
+					self.outcome(before, exit, output)
+				},
+				TransactionAction::Create => {
+					let exit = self.transact_create(sender, transaction.value, transaction.input.clone(), gas_limit);
+					let output = if exit.is_succeed() { Vec::new() } else { self.return_data_buffer.clone() };
+					self.outcome(before, exit, output)
+				},
+			}
+		};
+		self.fees = fees;
+		Ok(outcome)
+	}
+
+	/// Call `target` from `SYSTEM_ADDRESS`, as the protocol does at the start
+	/// of a block (EIP-4788). The call has no value, runs with
+	/// `SYSTEM_CALL_GAS` and charges neither intrinsic nor execution gas, and
+	/// leaves the system account untouched, so that only the state changes
+	/// of the called code remain.
+	pub fn system_call(&mut self, target: H160, data: Vec<u8>) -> (ExitReason, Vec<u8>) {
+		let gasometer = core::mem::replace(&mut self.gasometer, Gasometer::new(SYSTEM_CALL_GAS));
+		self.start_access_tracking(SYSTEM_ADDRESS, Some(target));
+		let context = Context {
+			caller: SYSTEM_ADDRESS,
+			address: target,
+			apparent_value: U256::zero(),
+		};
+
+		let result = match self.call_inner(target, None, data, None, false, FrameKind::Transaction, context) {
+			Capture::Exit((s, v)) => (s, self.record_return_data(s, v)),
+			Capture::Trap(interrupt) => match interrupt {},
+		};
+		self.gasometer = gasometer;
+		result
+	}
+
+	/// Run a validation call followed by an execution call, both from
+	/// `caller`, as an ERC-4337 entry point does for a user operation.
+	///
+	/// The validation runs with `banned_opcodes` failing with
+	/// `OpcodeDisabled`, in its frame and the frames it enters, such as
+	/// `VALIDATION_BANNED_OPCODES`. The execution only runs if the validation
+	/// succeeds. Each phase runs as a frame capped by its own gas limit, and
+	/// shares the accesses warmed by the other. No intrinsic gas is charged
+	/// and the nonce of `caller` is left unchanged, as both phases are calls
+	/// of the entry point. Unless both phases succeed, the executor is
+	/// brought back to its state before the call, gas and logs included.
+	pub fn transact_validated(
+		&mut self,
+		caller: H160,
+		validation: PhaseCall,
+		execution: PhaseCall,
+		banned_opcodes: OpcodeSet,
+	) -> ValidatedOutcome {
+		let snapshot = self.execution_state();
+		self.start_access_tracking(caller, Some(validation.address));
+		self.accessed.addresses.insert(execution.address);
+
+		let banned = core::mem::replace(&mut self.banned_opcodes, banned_opcodes);
+		let validation = self.transact_phase(caller, validation);
+		self.banned_opcodes = banned;
+
+		let execution = validation.exit.is_succeed().then(|| self.transact_phase(caller, execution));
+		let outcome = ValidatedOutcome { validation, execution };
+		if !outcome.is_succeed() {
+			self.set_execution_state(snapshot);
+		}
+		outcome
+	}
+
+	/// Run a phase of `transact_validated`.
+	fn transact_phase(&mut self, caller: H160, call: PhaseCall) -> TransactOutcome {
+		let before = self.outcome_start();
+		let context = Context {
+			caller,
+			address: call.address,
+			apparent_value: call.value,
+		};
+		let transfer = Transfer {
+			source: caller,
+			target: call.address,
+			value: call.value,
+		};
+		let (exit, output) = match self.call_inner(
+			call.address, Some(transfer), call.data, Some(call.gas_limit), false, FrameKind::Transaction, context,
+		) {
+			Capture::Exit((s, v)) => (s, self.record_return_data(s, v)),
+			Capture::Trap(interrupt) => match interrupt {},
+		};
+		self.outcome(before, exit, output)
+	}
+
+	/// Call `address` read-only, as `eth_call` does. The call runs static
+	/// from the zero address with `VIEW_CALL_GAS`, on a substate of the executor
+	/// so that neither state nor nonces change. Returns the exit reason, the
+	/// return value and the gas used including the intrinsic gas, an estimate
+	/// of the gas limit a transaction would need.
+	///
+	/// Code that loops forever exhausts the gas only after a very long time,
+	/// callers serving untrusted requests should bound the execution with a
+	/// transaction of limited gas instead.
+	#[must_use]
+	pub fn view_call(&self, address: H160, data: Vec<u8>) -> (ExitReason, Vec<u8>, u64) {
+		let mut executor = self.substate(VIEW_CALL_GAS, true);
+		executor.depth = self.depth;
+		executor.start_access_tracking(H160::default(), Some(address));
+		let transaction_cost = gasometer::call_transaction_cost(&data);
+		if let Err(e) = executor.gasometer.record_transaction(transaction_cost) {
+			return (e.into(), Vec::new(), 0)
+		}
+
+		let context = Context {
+			caller: H160::default(),
+			address,
+			apparent_value: U256::zero(),
+		};
+		let (reason, out) = match executor.call_inner(address, None, data, None, true, FrameKind::Transaction, context) {
+			Capture::Exit((s, v)) => (s, executor.record_return_data(s, v)),
+			Capture::Trap(interrupt) => match interrupt {},
+		};
+		(reason, out, executor.gasometer.total_used_gas())
+	}
+
+	/// Warm `address` from the start of the next transaction, as if listed
+	/// in its EIP-2930 access list.
+	pub fn prewarm_address(&mut self, address: H160) {
+		self.prewarmed.addresses.insert(address);
+	}
+
+	/// Warm the storage of `address` at `index` from the start of the next
+	/// transaction.
+	pub fn prewarm_storage(&mut self, address: H160, index: U256) {
+		self.prewarmed.storage.insert((address, index));
+	}
+
+	/// Warm the addresses and storage keys of an EIP-2930 access list from
+	/// the start of the next transaction.
+	pub fn prewarm_access_list(&mut self, access_list: &AccessList) {
+		for (address, keys) in access_list {
+			self.prewarm_address(*address);
+			for key in keys {
+				self.prewarm_storage(*address, U256::from_big_endian(key.as_bytes()));
+			}
+		}
+	}
+
+	/// Start tracking the accesses of a transaction from `caller` to
+	/// `target`, with the sender, the recipient and the prewarmed entries
+	/// warm. Precompiles of the registry are always warm, and created
+	/// addresses are warmed by the create. The execution statistics start
+	/// over as well.
+	fn start_access_tracking(&mut self, caller: H160, target: Option<H160>) {
+		self.execution_stats = ExecutionStats::default();
+		self.accessed = core::mem::take(&mut self.prewarmed);
+		self.accessed.addresses.insert(caller);
+		self.accessed.addresses.extend(target);
+
+		let mut keys = BTreeMap::<H160, Vec<U256>>::new();
+		for (address, index) in &self.accessed.storage {
+			keys.entry(*address).or_default().push(*index);
+		}
+		for (address, indices) in keys {
+			self.prefetch_storage(address, &indices);
+		}
+	}
+
+	/// Withdraw the maximum fee of a transaction from its sender.
+	fn buy_gas(&mut self, caller: H160, gas_limit: u64) -> Result<(), ExitError> {
+		let Some(fees) = self.fees else {
+			return Ok(())
+		};
+		let cost = fees.gas_price.checked_mul(U256::from(gas_limit)).ok_or(ExitError::OutOfFund)?;
+		self.withdraw(caller, cost)
+	}
+
+	/// Refund the unused gas of a transaction to its sender, and pay the
+	/// priority fee of the used gas to the coinbase.
+	fn settle_fees(&mut self, caller: H160, gas_limit: u64) {
+		let Some(fees) = self.fees else {
+			return
+		};
+		let used_gas = core::cmp::min(self.used_gas(), gas_limit);
+		self.deposit(caller, fees.gas_price * U256::from(gas_limit - used_gas));
+
+		let priority_fee = fees.gas_price.saturating_sub(fees.base_fee) * U256::from(used_gas);
+		if !priority_fee.is_zero() {
+			self.deposit(self.backend.block_coinbase(), priority_fee);
+		}
+	}
+
+	/// Logs emitted so far, including those of frames that reverted or
+	/// failed, in emission order. Empty unless enabled by
+	/// `with_reverted_logs`.
+	#[must_use]
+	pub fn traced_logs(&self) -> &[TracedLog] {
+		self.traced_logs.as_deref().unwrap_or_default()
+	}
+
+	/// State of the execution, to resume it elsewhere after the runtime
+	/// stopped with `StepLimitReached`, see `ExecutionState`.
+	#[must_use]
+	pub fn execution_state(&self) -> ExecutionState<S> {
+		ExecutionState {
+			gasometer: self.gasometer.clone(),
+			state: self.state.clone(),
+			deleted: self.deleted.clone(),
+			touched: self.touched.clone(),
+			logs: self.logs.clone(),
+			traced_logs: self.traced_logs.clone(),
+			return_data_buffer: self.return_data_buffer.clone(),
+			is_static: self.is_static,
+			depth: self.depth,
+			log_size: self.log_size,
+			prepaid: self.prepaid,
+			frame_gas: self.frame_gas,
+			accessed: self.accessed.clone(),
+			prewarmed: self.prewarmed.clone(),
+			dirty: self.dirty.clone(),
+			destroyed: self.destroyed.clone(),
+			originals: self.originals.clone(),
+			execution_stats: self.execution_stats,
+		}
+	}
+
+	/// Resume the execution suspended with the given state. The runtime it
+	/// was taken with can then be run further with this executor as handler.
+	#[must_use]
+	pub fn with_execution_state(mut self, state: ExecutionState<S>) -> Self {
+		self.set_execution_state(state);
+		self
+	}
+
+	/// Replace the state of the execution, see `with_execution_state`.
+	pub fn set_execution_state(&mut self, state: ExecutionState<S>) {
+		self.gasometer = state.gasometer;
+		self.state = state.state;
+		self.deleted = state.deleted;
+		self.touched = state.touched;
+		self.logs = state.logs;
+		self.traced_logs = state.traced_logs;
+		self.return_data_buffer = state.return_data_buffer;
+		self.is_static = state.is_static;
+		self.depth = state.depth;
+		self.log_size = state.log_size;
+		self.prepaid = state.prepaid;
+		self.frame_gas = state.frame_gas;
+		self.accessed = state.accessed;
+		self.prewarmed = state.prewarmed;
+		self.dirty = state.dirty;
+		self.destroyed = state.destroyed;
+		self.originals = state.originals;
+		self.execution_stats = state.execution_stats;
+	}
+
+	/// Gas of the current transaction by category, if enabled with
+	/// `with_gas_breakdown`.
+	#[must_use]
+	pub const fn gas_breakdown(&self) -> Option<&GasBreakdown> {
+		self.gasometer.breakdown()
+	}
+
+	/// Gasometer of a new frame or transaction, keeping the breakdown
+	/// enabled.
+	fn new_gasometer(&self, gas_limit: u64) -> Gasometer<S> {
+		let gasometer = Gasometer::new(gas_limit);
+		if self.gasometer.breakdown().is_some() {
+			gasometer.with_breakdown()
+		} else {
+			gasometer
 		}
 	}
 
 	/// Get used gas for the current executor.
 	#[must_use]
-	#[allow(clippy::cast_sign_loss)]
 	pub fn used_gas(&self) -> u64 {
-		let rg = self.gasometer.refunded_gas();
-		assert!(rg >= 0);
-		let tug = self.gasometer.total_used_gas();
-		tug - core::cmp::min(tug / 2, rg as u64)
-        // 0
+		self.gasometer.total_used_gas() - self.gasometer.effective_refund()
+	}
+
+	/// Get refund information for the current executor.
+	#[must_use]
+	pub fn refund(&self) -> Refund {
+		Refund {
+			refunded_gas: self.gasometer.refunded_gas(),
+			refund_cap: self.gasometer.refund_cap(),
+			effective_refund: self.gasometer.effective_refund(),
+		}
 	}
 
 	/// Get fee needed for the current executor, given the price.
@@ -255,41 +1759,195 @@ impl<'backend, B: 'backend + Backend> StackExecutor<'backend, B> {
 		U256::from(used_gas) * price
 	}
 
+	/// Accounts touched so far in the transaction, by value transfers
+	/// including zero-value ones, see `touch`.
+	#[must_use]
+	pub const fn touched(&self) -> &BTreeSet<H160> {
+		&self.touched
+	}
+
+	/// Touched accounts that are empty, to be deleted at the end of the
+	/// transaction if `clear_touched_empty` is enabled.
+	#[must_use]
+	pub fn touched_empty(&self) -> BTreeSet<H160> {
+		if !S::CONFIG.clear_touched_empty {
+			return BTreeSet::new()
+		}
+
+		self.touched.iter()
+			.filter(|address| {
+				self.balance(**address) == U256::zero() &&
+					self.nonce(**address) == U256::zero() &&
+					self.code_size(**address) == U256::zero()
+			})
+			.copied()
+			.collect()
+	}
+
+	/// Peak memory and call depth of the frames of the current transaction,
+	/// or of the last one.
+	#[must_use]
+	pub const fn execution_stats(&self) -> ExecutionStats {
+		self.execution_stats
+	}
+
+	/// Cache of the accounts read from the backend.
+	#[must_use]
+	pub const fn account_cache(&self) -> &AccountCache {
+		&self.account_cache
+	}
+
+	/// Record a frame about to run in the interpreter, at the depth of this
+	/// executor.
+	fn enter_frame(&mut self) {
+		let depth = self.depth.map_or(0, |depth| depth + 1);
+		self.execution_stats.max_depth = core::cmp::max(self.execution_stats.max_depth, depth);
+	}
+
+	/// Record the memory of a frame that ran. Memory never shrinks, so its
+	/// final size is its peak.
+	fn exit_frame(&mut self, runtime: &Runtime) {
+		let memory = runtime.machine().memory().effective_len();
+		self.execution_stats.peak_memory = core::cmp::max(self.execution_stats.peak_memory, memory);
+	}
+
+	/// Size of the state accumulated so far.
+	#[must_use]
+	pub fn stats(&self) -> ExecutorStats {
+		let storage_slots = self.state.values().map(|account| account.storage.len()).sum();
+		let code_size: usize = self.state.values()
+			.map(|account| account.code.as_ref().map_or(0, Vec::len) + account.valids.as_ref().map_or(0, Vec::len))
+			.sum();
+		let memory = self.logs.len() * core::mem::size_of::<Log>() + self.log_size
+			+ self.deleted.len() * core::mem::size_of::<H160>()
+			+ self.state.len() * (core::mem::size_of::<H160>() + core::mem::size_of::<StackAccount>())
+			+ storage_slots * 2 * core::mem::size_of::<U256>()
+			+ code_size;
+		ExecutorStats {
+			logs: self.logs.len(),
+			log_size: self.log_size,
+			deleted: self.deleted.len(),
+			accounts: self.state.len(),
+			storage_slots,
+			memory,
+		}
+	}
+
 	/// Deconstruct the executor, return state to be applied.
 	#[must_use]
 	pub fn deconstruct(
 		self
 	) -> (Vec::<Apply<BTreeMap<U256, U256>>>, Vec<Log>)
 	{
-		let mut applies = Vec::<Apply<BTreeMap<U256, U256>>>::new();
+		let (applies, logs) = self.into_applies();
+		(applies.collect(), logs)
+	}
 
-		for (address, account) in self.state {
-			if self.deleted.contains(&address) {
-				continue
-			}
+	/// Deconstruct the executor into the state to be applied, produced
+	/// lazily from the accounts it owns, and the logs.
+	fn into_applies(
+		self
+	) -> (impl Iterator<Item=Apply<BTreeMap<U256, U256>>>, Vec<Log>) {
+		let mut deleted = self.touched_empty();
+		deleted.extend(&self.deleted);
+		deleted.extend(&self.destroyed);
 
-			applies.push(Apply::Modify {
+		for address in self.state.keys().chain(&deleted) {
+			self.account_cache.invalidate(*address);
+		}
+
+		let skipped = deleted.clone();
+		let modified = self.state.into_iter()
+			.filter(move |(address, _)| !skipped.contains(address))
+			.map(|(address, account)| Apply::Modify {
 				address,
 				basic: account.basic,
 				code_and_valids: account.code.zip(account.valids),
 				storage: account.storage,
 				reset_storage: account.reset_storage,
 			});
-		}
+		let deleted = deleted.into_iter().map(|address| Apply::Delete { address });
 
-		for address in self.deleted {
+		(modified.chain(deleted), self.logs)
+	}
+
+	/// Apply the changes made since the last commit, and the logs, to
+	/// `backend`, keeping the executor usable. As the executor only reads
+	/// its own backend, `backend` is a separate one, such as the write side
+	/// of a database, while the cached accounts of the executor keep serving
+	/// the next transactions, see `reset_for_next_transaction`.
+	pub fn commit_to<A: ApplyBackend>(&mut self, backend: &mut A) {
+		let mut deleted = self.touched_empty();
+		deleted.extend(&self.deleted);
+		deleted.extend(self.dirty.intersection(&self.destroyed));
+
+		let mut applies = Vec::<Apply<BTreeMap<U256, U256>>>::new();
+		for address in core::mem::take(&mut self.dirty) {
+			if deleted.contains(&address) {
+				continue
+			}
+			let Some(account) = self.state.get(&address) else { continue };
+			applies.push(Apply::Modify {
+				address,
+				basic: account.basic.clone(),
+				code_and_valids: account.code.clone().zip(account.valids.clone()),
+				storage: account.storage.clone(),
+				reset_storage: account.reset_storage,
+			});
+		}
+		for address in deleted {
 			applies.push(Apply::Delete { address });
 		}
+		for apply in &applies {
+			let (Apply::Modify { address, .. } | Apply::Delete { address }) = apply;
+			self.account_cache.invalidate(*address);
+		}
 
-		let logs = self.logs;
+		backend.apply(applies, core::mem::take(&mut self.logs), S::CONFIG.clear_touched_empty);
+	}
+
+	/// Prepare the executor for the next transaction, with the given gas
+	/// limit. The accounts written so far stay cached, deleted ones as
+	/// empty accounts, along with backend reads and code analyses, while
+	/// the gasometer, logs, touched, deleted and warm sets are cleared.
+	/// Changes not committed yet with `commit_to` are kept for the next
+	/// commit, except the logs which are dropped.
+	pub fn reset_for_next_transaction(&mut self, gas_limit: u64) {
+		let mut deleted = self.touched_empty();
+		deleted.append(&mut self.deleted);
+		for address in deleted {
+			self.state.insert(address, StackAccount {
+				basic: Basic::default(),
+				code: Some(Vec::new()),
+				valids: Some(Vec::new()),
+				storage: BTreeMap::new(),
+				reset_storage: true,
+			});
+			self.dirty.insert(address);
+			self.destroyed.insert(address);
+		}
 
-		(applies, logs)
+		self.gasometer = self.new_gasometer(gas_limit);
+		self.touched.clear();
+		self.logs.clear();
+		if let Some(traced_logs) = &mut self.traced_logs {
+			traced_logs.clear();
+		}
+		self.log_size = 0;
+		self.return_data_buffer.clear();
+		self.accessed = AccessedSet::default();
+		self.originals.clear();
+		self.analysis = None;
+		self.prepaid = None;
 	}
 
 	/// Get mutable account reference.
 	pub fn account_mut(&mut self, address: H160) -> &mut StackAccount {
-		self.state.entry(address).or_insert(StackAccount {
-			basic: self.backend.basic(address),
+		self.dirty.insert(address);
+		self.destroyed.remove(&address);
+		let (backend, cache) = (self.backend, &self.account_cache);
+		self.state.entry(address).or_insert_with(|| StackAccount {
+			basic: cache.basic(backend, address),
 			code: None,
 			valids: None,
 			storage: BTreeMap::new(),
@@ -300,7 +1958,46 @@ impl<'backend, B: 'backend + Backend> StackExecutor<'backend, B> {
 	/// Get account nonce.
 	#[must_use]
 	pub fn nonce(&self, address: H160) -> U256 {
-		self.state.get(&address).map_or(self.backend.basic(address).nonce, |v| v.basic.nonce)
+		self.account_basic(address).nonce
+	}
+
+	/// Increase account nonce by one.
+	pub fn inc_nonce(&mut self, address: H160) {
+		self.account_mut(address).basic.nonce += U256::one();
+	}
+
+	/// Check of the collision policy failed by a create from `caller` at
+	/// `address`, `None` if the create does not collide with an existing
+	/// account.
+	#[must_use]
+	pub fn create_collision(&self, caller: H160, address: H160) -> Option<CollisionKind> {
+		let checks = self.collision_policy.map_or(CollisionChecks::EIP684, |policy| policy.checks(caller, address));
+		if checks.code && self.code_size(address) != U256::zero() {
+			Some(CollisionKind::Code)
+		} else if checks.nonce && self.nonce(address) > U256::zero() {
+			Some(CollisionKind::Nonce)
+		} else if checks.storage && self.has_storage(address) {
+			Some(CollisionKind::Storage)
+		} else {
+			None
+		}
+	}
+
+	/// Whether the account has a non-zero storage slot, in the state of the
+	/// executor or in the backend.
+	fn has_storage(&self, address: H160) -> bool {
+		match self.state.get(&address) {
+			Some(account) if account.storage.values().any(|value| !value.is_zero()) => true,
+			Some(account) if account.reset_storage => false,
+			_ => self.backend.has_storage(address),
+		}
+	}
+
+	/// Mark the account at address as touched, so that it is deleted at the
+	/// end of the transaction if it is empty (EIP-161).
+	pub fn touch(&mut self, address: H160) {
+		self.account_mut(address);
+		self.touched.insert(address);
 	}
 
 	/// Withdraw balance from address.
@@ -324,10 +2021,37 @@ impl<'backend, B: 'backend + Backend> StackExecutor<'backend, B> {
 	pub fn transfer(&mut self, transfer: &Transfer) -> Result<(), ExitError> {
 		self.withdraw(transfer.source, transfer.value)?;
 		self.deposit(transfer.target, transfer.value);
+		self.touch(transfer.source);
+		self.touch(transfer.target);
 
 		Ok(())
 	}
 
+	/// Record the return data of a finished call or create, regardless of
+	/// whether it was served by the interpreter, a precompile or a call
+	/// interceptor, and notify the listener about the frame exit. Only succeeded and
+	/// reverted executions carry return data.
+	fn record_return_data(&mut self, reason: ExitReason, data: Vec<u8>) -> Vec<u8> {
+		let data = if reason.is_succeed() || reason.is_revert() {
+			data
+		} else {
+			Vec::new()
+		};
+		let gas = self.gasometer.gas();
+		let frame_gas = self.frame_gas.take().map(|(before, forwarded, stipend)| {
+			let returned = (gas + forwarded).saturating_sub(before);
+			FrameGasUsage { forwarded, stipend, used: (forwarded + stipend).saturating_sub(returned), returned }
+		});
+		self.trace(Event::Exit {
+			reason: &reason,
+			return_value: &data,
+			gas,
+			frame_gas,
+		});
+		self.return_data_buffer.clone_from(&data);
+		data
+	}
+
 	/// Get the create address from given scheme.
 	#[must_use]
 	pub fn create_address(&self, scheme: CreateScheme) -> H160 {
@@ -349,8 +2073,6 @@ impl<'backend, B: 'backend + Backend> StackExecutor<'backend, B> {
 		}
 	}
 
-	
-	#[allow(clippy::too_many_lines)]
 	fn create_inner(
 		&mut self,
 		caller: H160,
@@ -358,8 +2080,26 @@ impl<'backend, B: 'backend + Backend> StackExecutor<'backend, B> {
 		value: U256,
 		init_code: Vec<u8>,
 		target_gas: Option<u64>,
-		take_l64: bool,
+		kind: FrameKind,
 	) -> Capture<(ExitReason, Option<H160>, Vec<u8>), Infallible> {
+		match self.enter_create(caller, scheme, value, init_code, target_gas, kind) {
+			Capture::Exit(exit) => Capture::Exit(exit),
+			Capture::Trap(frame) => Capture::Exit(self.run_frame(*frame)),
+		}
+	}
+
+	/// Run a create up to its init code, giving the frame to run it in
+	/// unless the create ends before.
+	#[allow(clippy::too_many_lines)]
+	fn enter_create(
+		&mut self,
+		caller: H160,
+		scheme: CreateScheme,
+		value: U256,
+		init_code: Vec<u8>,
+		target_gas: Option<u64>,
+		kind: FrameKind,
+	) -> Entered<'backend, B, S, (ExitReason, Option<H160>, Vec<u8>)> {
 		macro_rules! try_or_fail {
 			( $e:expr ) => {
 				match $e {
@@ -369,12 +2109,25 @@ impl<'backend, B: 'backend + Backend> StackExecutor<'backend, B> {
 			}
 		}
 
-		const fn l64(gas: u64) -> u64 {
-			gas - gas / 64
-		}
+		// The init code is hashed once with the backend hasher, `CREATE2`
+		// schemes already carrying the hash.
+		let code_hash = match scheme {
+			CreateScheme::Create2 { code_hash, .. } => code_hash,
+			CreateScheme::Legacy { .. } | CreateScheme::Fixed(_) => self.backend.keccak256_h256(&init_code),
+		};
+		let address = self.create_address(scheme);
+		self.trace(Event::Create {
+			caller,
+			address,
+			scheme,
+			value,
+			init_code: &init_code,
+			code_hash,
+			target_gas,
+		});
 
 		if let Some(depth) = self.depth {
-			if depth + 1 > CONFIG.call_stack_limit {
+			if depth + 1 > S::CONFIG.call_stack_limit {
 				return Capture::Exit((ExitError::CallTooDeep.into(), None, Vec::new()))
 			}
 		}
@@ -383,43 +2136,64 @@ impl<'backend, B: 'backend + Backend> StackExecutor<'backend, B> {
 			return Capture::Exit((ExitError::OutOfFund.into(), None, Vec::new()))
 		}
 
-		let mut after_gas = self.gasometer.gas(); // 0;
-		if take_l64 && CONFIG.call_l64_after_gas {
-			after_gas = l64(after_gas);
-		}
+		// The created address is warm only once the create passed the depth
+		// and balance checks (EIP-2929).
+		self.accessed.addresses.insert(address);
+
+		let gas = self.gasometer.gas();
+		let after_gas = self.gas_forwarding().available_gas(&S::CONFIG, kind, gas);
+		let requested_gas = target_gas;
 		let target_gas = target_gas.unwrap_or(after_gas);
 
 		let gas_limit = core::cmp::min(after_gas, target_gas);
-		try_or_fail!(self.gasometer.record_cost(gas_limit));
+		try_or_fail!(self.gasometer.record_cost_in(gas_limit, GasCategory::Calls));
+		self.frame_gas = Some((gas, gas_limit, 0));
+		self.trace(Event::FrameGas {
+			gas,
+			l64_reduction: gas - after_gas,
+			after_gas,
+			target_gas: requested_gas,
+			stipend: 0,
+			gas_limit,
+		});
 
-		let address = self.create_address(scheme);
                 self.backend.create(&scheme, &address);
-		self.account_mut(caller).basic.nonce += U256::one();
-
-		let mut substate = self.substate(gas_limit, false);
-		{
-			if let Some(code) = substate.account_mut(address).code.as_ref() {
-				if !code.is_empty() {
-					let _ = self.merge_fail(substate);
-					return Capture::Exit((ExitError::CreateCollision.into(), None, Vec::new()))
-				}
-			} else  {
-				let code = substate.backend.code(address);
-				substate.account_mut(address).code = Some(code.clone());
 
-				if !code.is_empty() {
-					let _ = self.merge_fail(substate);
-					return Capture::Exit((ExitError::CreateCollision.into(), None, Vec::new()))
-				}
+		// The caller nonce is increased once the create passed the depth and
+		// balance checks, and is kept regardless of whether the init code
+		// later succeeds, reverts or runs out of gas.
+		let collision = self.create_collision(caller, address);
+		let increase_nonce = self.nonce_policy
+			.is_none_or(|policy| policy.increase_on_create(caller, collision.is_some()));
+		if let Some(kind) = collision {
+			if increase_nonce && S::CONFIG.create_collision_increase_nonce {
+				self.inc_nonce(caller);
 			}
+			return Capture::Exit((ExitError::CreateCollision(kind).into(), None, Vec::new()))
+		}
+		if increase_nonce {
+			self.inc_nonce(caller);
+		}
 
-			if substate.account_mut(address).basic.nonce > U256::zero() {
-				let _ = self.merge_fail(substate);
-				return Capture::Exit((ExitError::CreateCollision.into(), None, Vec::new()))
+		// EOF init code runs by code section, and is rejected before running
+		// if invalid, consuming the gas given to the create.
+		let eof = S::CONFIG.has_eof && is_eof(&init_code);
+		let init_code = if eof {
+			match Eof::validate(&init_code) {
+				Ok(container) => container.code().to_vec(),
+				Err(_) => return Capture::Exit((ExitError::InvalidCode.into(), None, Vec::new())),
 			}
+		} else {
+			init_code
+		};
 
-			substate.account_mut(address).reset_storage = true;
-			substate.account_mut(address).storage = BTreeMap::new();
+		let mut substate = self.substate(gas_limit, false);
+		{
+			let code = substate.code(address);
+			let account = substate.account_mut(address);
+			account.code = Some(code);
+			account.reset_storage = true;
+			account.storage = BTreeMap::new();
 		}
 
 		let context = Context {
@@ -440,67 +2214,149 @@ impl<'backend, B: 'backend + Backend> StackExecutor<'backend, B> {
 			},
 		}
 
-		if CONFIG.create_increase_nonce {
-			substate.account_mut(address).basic.nonce += U256::one();
+		// The created account nonce only survives if the init code succeeds,
+		// as it lives in the substate.
+		if S::CONFIG.create_increase_nonce {
+			substate.inc_nonce(address);
 		}
 
-		let valids = Valids::compute(&init_code);
-		let mut runtime = Runtime::new(
+		let valids = if let Some(analyses) = &self.analyses {
+			let analysis = analyses.get_or_analyze(code_hash, &init_code);
+			let valids = analysis.valids().clone();
+			substate.analysis = Some(analysis);
+			valids
+		} else {
+			Arc::new(Valids::compute(&init_code).into())
+		};
+		let mut runtime = Runtime::new_with_limits(
 			init_code,
 			valids,
 			Vec::new(),
 			context,
+			self.limits,
 		);
+		if self.fused_dispatch {
+			runtime.fuse();
+		}
+		#[cfg(feature = "debug-trace")]
+		if let Some(sink) = &self.debug_sink {
+			runtime.set_debug_sink(sink.clone());
+		}
+		#[cfg(feature = "taint")]
+		if let Some(sink) = &self.taint_sink {
+			runtime.set_taint_sink(sink.clone());
+		}
 
-		let reason = substate.execute(&mut runtime);
-		//log::debug!(target: "evm", "Create execution using address {}: {:?}", address, reason);
+		substate.enter_frame();
+		Capture::Trap(Box::new(FrameInterrupt {
+			executor: substate,
+			runtime,
+			exit: FrameExit::Create { address, eof },
+		}))
+	}
 
-		match reason {
-			ExitReason::Succeed(s) => {
+	/// Run a frame given by `enter_call` or `enter_create` until it exits.
+	fn run_frame(&mut self, mut frame: FrameInterrupt<'backend, B, S>) -> (ExitReason, Option<H160>, Vec<u8>) {
+		let reason = frame.executor.execute(&mut frame.runtime);
+		self.exit_frame_with(frame, reason)
+	}
+
+	/// Merge a child frame that exited with `reason`, giving the outcome of
+	/// the call or create that entered it.
+	fn exit_frame_with(
+		&mut self,
+		frame: FrameInterrupt<'backend, B, S>,
+		reason: ExitReason,
+	) -> (ExitReason, Option<H160>, Vec<u8>) {
+		let FrameInterrupt { executor: mut substate, runtime, exit } = frame;
+		substate.exit_frame(&runtime);
+
+		match (reason, exit) {
+			(ExitReason::Succeed(s), FrameExit::Call) => {
+				let _ = self.merge_succeed(substate);
+				(ExitReason::Succeed(s), None, runtime.machine().return_value())
+			},
+			(ExitReason::Succeed(s), FrameExit::Create { address, eof }) => {
 				let out = runtime.machine().return_value();
 
-				if let Some(limit) = CONFIG.create_contract_limit {
-					if out.len() > limit {
-						substate.gasometer.fail();
-						let _ = self.merge_fail(substate);
-						return Capture::Exit((ExitError::CreateContractLimit.into(), None, Vec::new()))
-					}
+				if let Err(e) = Self::check_deployed_code(eof, &out) {
+					substate.gasometer.fail();
+					let _ = self.merge_fail(substate);
+					return (e.into(), None, Vec::new())
 				}
 
 				match substate.gasometer.record_deposit(out.len()) {
 					Ok(()) => {
 						let e = self.merge_succeed(substate);
-						let entry: &mut _ = self.state.entry(address).or_insert_with(Default::default);
+						self.dirty.insert(address);
+						let entry: &mut _ = self.state.entry(address).or_default();
 						entry.valids = Some(Valids::compute(&out));
 						entry.code = Some(out);
-						try_or_fail!(e);
-						Capture::Exit((ExitReason::Succeed(s), Some(address), Vec::new()))
+						match e {
+							Ok(()) => (ExitReason::Succeed(s), Some(address), Vec::new()),
+							Err(e) => (e.into(), None, Vec::new()),
+						}
 					},
 					Err(e) => {
 						let _ = self.merge_fail(substate);
-						Capture::Exit((ExitReason::Error(e), None, Vec::new()))
+						(ExitReason::Error(e), None, Vec::new())
 					},
 				}
 			},
-			ExitReason::Error(e) => {
-				substate.gasometer.fail();
+			(ExitReason::Error(e), exit) => {
+				if matches!(exit, FrameExit::Create { .. }) {
+					substate.gasometer.fail();
+				}
 				let _ = self.merge_fail(substate);
-				Capture::Exit((ExitReason::Error(e), None, Vec::new()))
+				(ExitReason::Error(e), None, Vec::new())
 			},
-			ExitReason::Revert(e) => {
+			(ExitReason::Revert(e), _) => {
 				let _ = self.merge_revert(substate);
-				Capture::Exit((ExitReason::Revert(e), None, runtime.machine().return_value()))
+				(ExitReason::Revert(e), None, runtime.machine().return_value())
+			},
+			(ExitReason::Fatal(e), _) => {
+				self.revert_logs(&substate);
+				self.gasometer.fail();
+				(ExitReason::Fatal(e), None, Vec::new())
 			},
-			ExitReason::Fatal(e) => {
+			(ExitReason::StepLimitReached, _) => {
+				self.revert_logs(&substate);
 				self.gasometer.fail();
-				Capture::Exit((ExitReason::Fatal(e), None, Vec::new()))
+				(ExitFatal::UnhandledInterrupt.into(), None, Vec::new())
 			},
-			ExitReason::StepLimitReached => { unreachable!() }
 		}
 	}
 
+	fn gas_forwarding(&self) -> &dyn GasForwardingPolicy {
+		self.gas_forwarding.unwrap_or(&EthereumGasForwarding)
+	}
+
+	/// Check code returned by init code before deploying it: the code must
+	/// fit the size limit, EOF init code must deploy a valid container, and
+	/// legacy init code must not deploy code starting with `0xEF` once
+	/// EIP-3541 applies.
+	fn check_deployed_code(eof: bool, code: &[u8]) -> Result<(), ExitError> {
+		if S::CONFIG.create_contract_limit.is_some_and(|limit| code.len() > limit) {
+			return Err(ExitError::CreateContractLimit)
+		}
+		if eof {
+			Eof::validate(code).map_err(|_| ExitError::InvalidCode)?;
+		} else if (S::CONFIG.reject_ef_code || S::CONFIG.has_eof) && code.first() == Some(&0xef) {
+			return Err(ExitError::CreateContractStartingWithEF)
+		}
+		Ok(())
+	}
+
+	/// Code section to run for `code`, if it is an EOF container and EOF is
+	/// enabled.
+	fn eof_code_section(code: &[u8]) -> Option<Vec<u8>> {
+		if !S::CONFIG.has_eof {
+			return None
+		}
+		Eof::parse(code).ok().map(|container| container.code().to_vec())
+	}
+
 	#[allow(clippy::too_many_arguments)]
-	#[allow(clippy::too_many_lines)]
 	fn call_inner(
 		&mut self,
 		code_address: H160,
@@ -508,10 +2364,32 @@ impl<'backend, B: 'backend + Backend> StackExecutor<'backend, B> {
 		input: Vec<u8>,
 		target_gas: Option<u64>,
 		is_static: bool,
-		take_l64: bool,
-		take_stipend: bool,
+		kind: FrameKind,
 		context: Context,
 	) -> Capture<(ExitReason, Vec<u8>), Infallible> {
+		match self.enter_call(code_address, transfer, input, target_gas, is_static, kind, context) {
+			Capture::Exit(exit) => Capture::Exit(exit),
+			Capture::Trap(frame) => {
+				let (reason, _, output) = self.run_frame(*frame);
+				Capture::Exit((reason, output))
+			},
+		}
+	}
+
+	/// Run a call up to its code, giving the frame to run it in unless the
+	/// call is served without one or ends before.
+	#[allow(clippy::too_many_arguments)]
+	#[allow(clippy::too_many_lines)]
+	fn enter_call(
+		&mut self,
+		code_address: H160,
+		transfer: Option<Transfer>,
+		input: Vec<u8>,
+		target_gas: Option<u64>,
+		is_static: bool,
+		kind: FrameKind,
+		context: Context,
+	) -> Entered<'backend, B, S, (ExitReason, Vec<u8>)> {
 		macro_rules! try_or_fail {
 			( $e:expr ) => {
 				match $e {
@@ -521,37 +2399,53 @@ impl<'backend, B: 'backend + Backend> StackExecutor<'backend, B> {
 			}
 		}
 
-		const fn l64(gas: u64) -> u64 {
-			gas - gas / 64
-		}
+		self.trace(Event::Call {
+			code_address,
+			transfer: &transfer,
+			input: &input,
+			target_gas,
+			is_static,
+			context: &context,
+		});
 
-		let mut after_gas = self.gasometer.gas(); // 0;
-		if take_l64 && CONFIG.call_l64_after_gas {
-			after_gas = l64(after_gas);
-		}
+		let gas = self.gasometer.gas();
+		let after_gas = self.gas_forwarding().available_gas(&S::CONFIG, kind, gas);
 
+		let requested_gas = target_gas;
 		let target_gas = target_gas.unwrap_or(after_gas);
 		let mut gas_limit = core::cmp::min(target_gas, after_gas);
 
-		try_or_fail!(self.gasometer.record_cost(gas_limit));
+		try_or_fail!(self.gasometer.record_cost_in(gas_limit, GasCategory::Calls));
+
+		let value = transfer.as_ref().map_or_else(U256::zero, |transfer| transfer.value);
+		let stipend = self.gas_forwarding().stipend(&S::CONFIG, kind, value);
+		self.frame_gas = Some((gas, gas_limit, stipend));
+		gas_limit = gas_limit.saturating_add(stipend);
+		self.trace(Event::FrameGas {
+			gas,
+			l64_reduction: gas - after_gas,
+			after_gas,
+			target_gas: requested_gas,
+			stipend,
+			gas_limit,
+		});
 
-		if let Some(transfer) = transfer.as_ref() {
-			if take_stipend && transfer.value != U256::zero() {
-				gas_limit = gas_limit.saturating_add(CONFIG.call_stipend);
-			}
+		let (mut code, mut valids) = self.code_and_valids(code_address);
+		if let Some(section) = Self::eof_code_section(&code) {
+			valids = Valids::compute(&section);
+			code = section;
 		}
 
-		let code = self.code(code_address);
-		let valids = self.valids(code_address);
-
 		let mut substate = self.substate(gas_limit, is_static);
 		substate.account_mut(context.address);
 
-		if let Some(depth) = self.depth {
-			if depth + 1 > CONFIG.call_stack_limit {
-				let _ = self.merge_revert(substate);
-				return Capture::Exit((ExitError::CallTooDeep.into(), Vec::new()))
-			}
+		// Calls served by a precompile or an interceptor are only subject to the
+		// call limit if they count as frames, otherwise the check is delayed
+		// until the interpreter is about to run.
+		let too_deep = self.depth.is_some_and(|depth| depth + 1 > S::CONFIG.call_stack_limit);
+		if too_deep && S::CONFIG.hook_call_depth {
+			let _ = self.merge_revert(substate);
+			return Capture::Exit((ExitError::CallTooDeep.into(), Vec::new()))
 		}
 
 		if let Some(transfer) = transfer {
@@ -564,7 +2458,11 @@ impl<'backend, B: 'backend + Backend> StackExecutor<'backend, B> {
 			}
 		}
 
-		if let Some(ret) = (substate.precompile)(code_address, &input, Some(gas_limit)) {
+		let precompile_result = match substate.precompiles {
+			Some(precompiles) => precompiles.call(&S::CONFIG, code_address, &input, Some(gas_limit)),
+			None => (substate.precompile)(code_address, &input, Some(gas_limit)),
+		};
+		if let Some(ret) = precompile_result {
 			return match ret {
 				Ok((s, out, cost)) => {
 					let _ = substate.gasometer.record_cost(cost);
@@ -578,124 +2476,169 @@ impl<'backend, B: 'backend + Backend> StackExecutor<'backend, B> {
 			}
 		}
 
-		let hook_res = self.backend.call_inner(code_address, transfer, input.clone(), Some(target_gas), is_static, take_l64, take_stipend);
-		if let Some(hook_res) = hook_res {
-			match &hook_res {
-				Capture::Exit((reason, _return_data)) => {
-					match reason {
-						ExitReason::Succeed(_) => {
-							let _ = self.merge_succeed(substate);
-						},
-						ExitReason::Revert(_) => {
-							let _ = self.merge_revert(substate);
-						},
-						ExitReason::Error(_) => {
-							let _ = self.merge_fail(substate);
-						},
-						ExitReason::Fatal(_) => {
-						},
-						ExitReason::StepLimitReached => { unreachable!() }
-					}
-				},
-				Capture::Trap(_interrupt) => {
-				},
+		let hook_depth = if S::CONFIG.hook_call_depth {
+			substate.depth.unwrap_or_default()
+		} else {
+			self.depth.unwrap_or_default()
+		};
+		let mut input = input;
+		if let Some(interceptor) = self.backend.call_interceptor() {
+			let request = CallRequest {
+				code_address,
+				transfer,
+				input,
+				context: context.clone(),
+				gas_limit,
+				is_static,
+				kind,
+				depth: hook_depth,
+			};
+			match interceptor.intercept(&request) {
+				Some(response) => return Capture::Exit(self.merge_intercepted(substate, response)),
+				None => input = request.input,
 			}
-			return hook_res;
 		}
 
-		let mut runtime = Runtime::new(
+		if too_deep {
+			let _ = self.merge_revert(substate);
+			return Capture::Exit((ExitError::CallTooDeep.into(), Vec::new()))
+		}
+
+		// Analyzed code shares its valids with every frame running it.
+		let valids = if let Some(analyses) = self.analyses.as_ref().filter(|_| !code.is_empty()) {
+			let analysis = analyses.get_or_analyze(self.code_hash(code_address), &code);
+			let valids = analysis.valids().clone();
+			substate.analysis = Some(analysis);
+			valids
+		} else {
+			Arc::new(valids.into())
+		};
+
+		#[cfg(feature = "jit")]
+		let compiled = self.jit.as_ref()
+			.filter(|_| !code.is_empty())
+			.and_then(|jit| jit.record(self.code_hash(code_address), &code));
+
+		let mut runtime = Runtime::new_with_limits(
 			code,
 			valids,
 			input,
 			context,
+			self.limits,
 		);
-
-		let reason = substate.execute(&mut runtime);
-		//log::debug!(target: "evm", "Call execution using address {}: {:?}", code_address, reason);
-
-		match reason {
-			ExitReason::Succeed(s) => {
-				let _ = self.merge_succeed(substate);
-				Capture::Exit((ExitReason::Succeed(s), runtime.machine().return_value()))
-			},
-			ExitReason::Error(e) => {
-				let _ = self.merge_fail(substate);
-				Capture::Exit((ExitReason::Error(e), Vec::new()))
-			},
-			ExitReason::Revert(e) => {
-				let _ = self.merge_revert(substate);
-				Capture::Exit((ExitReason::Revert(e), runtime.machine().return_value()))
-			},
-			ExitReason::Fatal(e) => {
-				self.gasometer.fail();
-				Capture::Exit((ExitReason::Fatal(e), Vec::new()))
-			},
-			ExitReason::StepLimitReached => { unreachable!() }
+		if self.fused_dispatch {
+			runtime.fuse();
+		}
+		#[cfg(feature = "debug-trace")]
+		if let Some(sink) = &self.debug_sink {
+			runtime.set_debug_sink(sink.clone());
+		}
+		#[cfg(feature = "taint")]
+		if let Some(sink) = &self.taint_sink {
+			runtime.set_taint_sink(sink.clone());
 		}
+		#[cfg(feature = "jit")]
+		if let Some(compiled) = compiled {
+			runtime.set_compiled(compiled);
+		}
+
+		substate.enter_frame();
+		Capture::Trap(Box::new(FrameInterrupt {
+			executor: substate,
+			runtime,
+			exit: FrameExit::Call,
+		}))
 	}
 }
 
-impl<'backend, B: Backend> Handler for StackExecutor<'backend, B> {
-	type CreateInterrupt = Infallible;
-	type CreateFeedback = Infallible;
-	type CallInterrupt = Infallible;
-	type CallFeedback = Infallible;
-
+impl<B: Backend, S: Spec> Keccak for StackExecutor<'_, B, S> {
 	fn keccak256_h256(&self, data: &[u8]) -> H256 {
 		self.backend.keccak256_h256(data)
 	}
 
+	fn keccak256_h256_v(&self, data: &[&[u8]]) -> H256 {
+		self.backend.keccak256_h256_v(data)
+	}
+}
+
+impl<'backend, B: Backend, S: Spec> Handler for StackExecutor<'backend, B, S> {
+	type CreateInterrupt = Box<FrameInterrupt<'backend, B, S>>;
+	type CreateFeedback = ();
+	type CallInterrupt = Box<FrameInterrupt<'backend, B, S>>;
+	type CallFeedback = ();
+
 	fn balance(&self, address: H160) -> U256 {
-		self.state.get(&address).map_or(self.backend.basic(address).balance, |v| v.basic.balance)
+		self.account_basic(address).balance
 	}
 
 	fn code_size(&self, address: H160) -> U256 {
-		U256::from(
-			self.state.get(&address).and_then(|v| v.code.as_ref().map(Vec::len))
-				.unwrap_or_else(|| self.backend.code_size(address))
-		)
+		U256::from(self.account_code_size(address))
 	}
 
 	fn code_hash(&self, address: H160) -> H256 {
-		if !self.exists(address) {
-			return H256::default()
+		if let Some(code) = self.code_overrides.get(&address) {
+			return code.hash
 		}
 
-		let (balance, nonce, code_size) = self.state.get(&address).map_or_else(|| {
-			let basic = self.backend.basic(address);
-			(basic.balance, basic.nonce, U256::from(self.backend.code_size(address)))
-		}, |account| 
-			(
-				account.basic.balance, account.basic.nonce,
-				account.code.as_ref().map_or(self.code_size(address), |c| U256::from(c.len()))
-			)
-		);
-
-		if balance == U256::zero() && nonce == U256::zero() && code_size == U256::zero() {
+		// Without `empty_considered_exists`, the accounts that exist are not
+		// empty, precompiles aside, so emptiness is only checked once.
+		if !self.exists(address) ||
+			((S::CONFIG.empty_considered_exists || self.is_precompile(address)) && self.is_empty_account(address))
+		{
 			return H256::default()
 		}
 
-		let value = self.state.get(&address).and_then(|v| {
-			v.code.as_ref().map(|c| {
-				//H256::from_slice(Keccak256::digest(&c).as_slice())
-				self.backend.keccak256_h256(c)
-			})
-		}).unwrap_or_else(|| self.backend.code_hash(address));
-		value
+		self.state.get(&address).and_then(|v| v.code.as_ref())
+			.map_or_else(|| self.account_cache.code_hash(self.backend, address), |code| self.backend.keccak256_h256(code))
 	}
 
 	fn code(&self, address: H160) -> Vec<u8> {
+		if let Some(code) = self.code_overrides.get(&address) {
+			return code.code.clone()
+		}
+
 		self.state.get(&address).and_then(|v| {
 			v.code.clone()
 		}).unwrap_or_else(|| self.backend.code(address))
 	}
 
+	fn code_range(&self, address: H160, offset: usize, len: usize) -> Vec<u8> {
+		self.code_overrides.get(&address).map(|code| &code.code)
+			.or_else(|| self.state.get(&address).and_then(|v| v.code.as_ref()))
+			.map_or_else(
+				|| self.backend.code_range(address, offset, len),
+				|code| offset.checked_add(len)
+					.and_then(|end| code.get(offset..core::cmp::min(end, code.len())))
+					.map(<[u8]>::to_vec)
+					.unwrap_or_default(),
+			)
+	}
+
 	fn valids(&self, address: H160) -> Vec<u8> {
+		if let Some(code) = self.code_overrides.get(&address) {
+			return code.valids.clone()
+		}
+
 		self.state.get(&address).and_then(|v| {
 			v.valids.clone()
 		}).unwrap_or_else(|| self.backend.valids(address))
 	}
 
+	fn code_and_valids(&self, address: H160) -> (Vec<u8>, Vec<u8>) {
+		if let Some(code) = self.code_overrides.get(&address) {
+			return (code.code.clone(), code.valids.clone())
+		}
+
+		let account = self.state.get(&address);
+		match (account.and_then(|v| v.code.clone()), account.and_then(|v| v.valids.clone())) {
+			(None, None) => self.backend.code_and_valids(address),
+			(code, valids) => (
+				code.unwrap_or_else(|| self.backend.code(address)),
+				valids.unwrap_or_else(|| self.backend.valids(address)),
+			),
+		}
+	}
+
 	fn storage(&self, address: H160, index: U256) -> U256 {
 		self.state.get(&address)
 			.and_then(|v| {
@@ -708,46 +2651,46 @@ impl<'backend, B: Backend> Handler for StackExecutor<'backend, B> {
 				}
 
 			})
-			.unwrap_or_else(|| self.backend.storage(address, index))
+			.unwrap_or_else(|| self.account_cache.storage(self.backend, address, index))
 	}
 
 	fn original_storage(&self, address: H160, index: U256) -> U256 {
-		if let Some(account) = self.state.get(&address) {
-			if account.reset_storage {
-				return U256::zero()
-			}
-		}
-		self.backend.storage(address, index)
+		// A slot not written yet by the transaction has its original value.
+		self.originals.get(&(address, index)).copied().unwrap_or_else(|| self.storage(address, index))
+	}
+
+	fn is_cold(&self, address: H160, index: Option<U256>) -> bool {
+		index.map_or_else(
+			|| {
+				!self.accessed.addresses.contains(&address)
+					&& !self.is_precompile(address)
+					&& !Self::is_ethereum_precompile(address)
+			},
+			|index| !self.accessed.storage.contains(&(address, index)),
+		)
 	}
 
-	#[allow(clippy::option_if_let_else)]
-	#[allow(clippy::map_unwrap_or)]
 	fn exists(&self, address: H160) -> bool {
-		if CONFIG.empty_considered_exists {
-			self.state.get(&address).is_some() || self.backend.exists(address)
-		} else if let Some(account) = self.state.get(&address) {
-			account.basic.nonce != U256::zero() ||
-				account.basic.balance != U256::zero() ||
-				account.code.as_ref().map(|c| !c.is_empty()).unwrap_or(false) ||
-				!self.backend.code(address).is_empty()
+		if (S::CONFIG.precompiles_exist && self.is_precompile(address)) || self.code_overrides.contains_key(&address) {
+			return true
+		}
+
+		if S::CONFIG.empty_considered_exists {
+			!self.destroyed.contains(&address)
+				&& (self.state.contains_key(&address) || self.account_cache.exists(self.backend, address))
 		} else {
-			self.state.get(&address).map_or_else(||
-					self.backend.basic(address).nonce != U256::zero() ||
-					self.backend.basic(address).balance != U256::zero() ||
-					!self.backend.code(address).is_empty(), 
-				|account| 
-					account.basic.nonce != U256::zero() ||
-					account.basic.balance != U256::zero() ||
-					account.code.as_ref().map_or(false, |c| !c.is_empty()) ||
-					!self.backend.code(address).is_empty()
-			)
+			!self.is_empty_account(address)
 		}
 	}
 
 	fn gas_left(&self) -> U256 { U256::from(self.gasometer.gas()) } // { U256::one() }
 
-	fn gas_price(&self) -> U256 { self.backend.gas_price() }
-	fn origin(&self) -> H160 { self.backend.origin() }
+	fn gas_price(&self) -> U256 {
+		self.tx_context.map_or_else(|| self.backend.gas_price(), |context| context.gas_price)
+	}
+	fn origin(&self) -> H160 {
+		self.tx_context.map_or_else(|| self.backend.origin(), |context| context.origin)
+	}
 	fn block_hash(&self, number: U256) -> H256 { self.backend.block_hash(number) }
 	fn block_number(&self) -> U256 { self.backend.block_number() }
 	fn block_coinbase(&self) -> H160 { self.backend.block_coinbase() }
@@ -759,20 +2702,45 @@ impl<'backend, B: Backend> Handler for StackExecutor<'backend, B> {
 	fn deleted(&self, address: H160) -> bool { self.deleted.contains(&address) }
 
 	fn set_storage(&mut self, address: H160, index: U256, value: U256) -> Result<(), ExitError> {
+		if !self.originals.contains_key(&(address, index)) {
+			let original = self.storage(address, index);
+			self.originals.insert((address, index), original);
+		}
 		self.account_mut(address).storage.insert(index, value);
+		self.trace(Event::SetStorage { address, index, value });
 
 		Ok(())
 	}
 
 	fn log(&mut self, address: H160, topics: Vec<H256>, data: Vec<u8>) -> Result<(), ExitError> {
-		self.logs.push(Log {
+		let size = self.log_size + log_size(&topics, &data);
+		if self.state_limits.max_log_size.is_some_and(|max| size > max) {
+			return Err(ExitError::LogLimit)
+		}
+		self.log_size = size;
+
+		let log = Log {
 			address, topics, data
-		});
+		};
+
+		if let Some(sink) = self.backend.log_sink() {
+			sink.log(&log, self.logs.len());
+		}
+		if let Some(traced_logs) = &mut self.traced_logs {
+			traced_logs.push(TracedLog { log: log.clone(), reverted: false });
+		}
+		self.logs.push(log);
 
 		Ok(())
 	}
 
 	fn mark_delete(&mut self, address: H160, target: H160) -> Result<(), ExitError> {
+		if !self.deleted.contains(&address)
+			&& self.state_limits.max_deleted.is_some_and(|max| self.deleted.len() >= max)
+		{
+			return Err(ExitError::SelfDestructLimit)
+		}
+
 		let balance = self.balance(address);
 
 		self.transfer(&Transfer {
@@ -795,7 +2763,12 @@ impl<'backend, B: Backend> Handler for StackExecutor<'backend, B> {
 		init_code: Vec<u8>,
 		target_gas: Option<u64>,
 	) -> Capture<(ExitReason, Option<H160>, Vec<u8>), Self::CreateInterrupt> {
-		self.create_inner(caller, scheme, value, init_code, target_gas, true)
+		let (s, address, v) = match self.enter_create(caller, scheme, value, init_code, target_gas, FrameKind::Create) {
+			Capture::Exit(exit) => exit,
+			Capture::Trap(frame) if self.trap_frames => return Capture::Trap(frame),
+			Capture::Trap(frame) => self.run_frame(*frame),
+		};
+		Capture::Exit((s, address, self.record_return_data(s, v)))
 	}
 
 	fn call(
@@ -807,20 +2780,48 @@ impl<'backend, B: Backend> Handler for StackExecutor<'backend, B> {
 		is_static: bool,
 		context: Context,
 	) -> Capture<(ExitReason, Vec<u8>), Self::CallInterrupt> {
-		self.call_inner(code_address, transfer, input, target_gas, is_static, true, true, context)
+		let (s, v) = match self.enter_call(code_address, transfer, input, target_gas, is_static, FrameKind::Call, context) {
+			Capture::Exit(exit) => exit,
+			Capture::Trap(frame) if self.trap_frames => return Capture::Trap(frame),
+			Capture::Trap(frame) => {
+				let (s, _, v) = self.run_frame(*frame);
+				(s, v)
+			},
+		};
+		Capture::Exit((s, self.record_return_data(s, v)))
+	}
+
+	/// Return data of the last finished call or create, empty if it failed.
+	/// The runtimes keep their own buffer for `RETURNDATASIZE` and
+	/// `RETURNDATACOPY`, filled with the same data.
+	fn return_data(&self) -> &[u8] {
+		&self.return_data_buffer
+	}
+
+	fn set_return_data(&mut self, data: Vec<u8>) {
+		self.return_data_buffer = data;
 	}
 
 	fn pre_validate(
 		&mut self,
 		context: &Context,
 		opcode: Opcode,
+		position: usize,
 		stack: &Stack,
+		memory: &Memory,
 	) -> Result<(), ExitError> {
+		if S::CONFIG.disabled_opcodes.contains(opcode) || self.banned_opcodes.contains(opcode) {
+			return Err(ExitError::OpcodeDisabled)
+		}
+
+		let gas_before = self.gasometer.gas();
+
 		if let Some(cost) = gasometer::static_opcode_cost(opcode) {
+			let cost = self.block_cost(opcode, position, stack, cost);
 			self.gasometer.record_cost(cost)?;
 		} else {
 			let is_static = self.is_static;
-			let (gas_cost, memory_cost) = gasometer::dynamic_opcode_cost(
+			let (gas_cost, memory_cost) = gasometer::dynamic_opcode_cost::<S, _>(
 				context.address,
 				opcode,
 				stack,
@@ -828,8 +2829,27 @@ impl<'backend, B: Backend> Handler for StackExecutor<'backend, B> {
 				self,
 			)?;
 			self.gasometer.record_dynamic_cost(gas_cost, memory_cost)?;
+
+			if S::CONFIG.increase_state_access_gas {
+				match gasometer::storage_target(context.address, opcode, stack) {
+					StorageTarget::Address(address) => { self.accessed.addresses.insert(address); },
+					StorageTarget::Slot(address, index) => { self.accessed.storage.insert((address, index)); },
+					StorageTarget::None => (),
+				}
+			}
 		}
 
+		let gas_left = self.gasometer.gas();
+		self.trace(Event::Step {
+			context,
+			opcode,
+			position,
+			stack: self.trace_config.stack.then(|| StackSnapshot::new(stack)),
+			memory: self.trace_config.memory.then(|| MemorySnapshot::new(memory)),
+			gas_cost: gas_before - gas_left,
+			gas_left,
+		});
+
 		Ok(())
 	}
 }
@@ -0,0 +1,54 @@
+use alloc::vec::Vec;
+use crate::{Opcode, OpcodeSet, H160, U256};
+use super::TransactOutcome;
+
+/// Opcodes reading the block or account environment, banned from the
+/// validation frame of user operations by ERC-7562.
+///
+/// The ban keeps the outcome of the validation the same between simulation
+/// and inclusion. `GAS` is left out, as it is allowed right before a call,
+/// and `CREATE2` is left to the caller.
+pub const VALIDATION_BANNED_OPCODES: OpcodeSet = OpcodeSet::new()
+	.with(Opcode::BALANCE)
+	.with(Opcode::SELFBALANCE)
+	.with(Opcode::ORIGIN)
+	.with(Opcode::GASPRICE)
+	.with(Opcode::BLOCKHASH)
+	.with(Opcode::COINBASE)
+	.with(Opcode::TIMESTAMP)
+	.with(Opcode::NUMBER)
+	.with(Opcode::DIFFICULTY)
+	.with(Opcode::GASLIMIT)
+	.with(Opcode::CREATE)
+	.with(Opcode::SUICIDE);
+
+/// Call run as a phase of `StackExecutor::transact_validated`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PhaseCall {
+	/// Called address.
+	pub address: H160,
+	/// Value transferred to the called address.
+	pub value: U256,
+	/// Call input.
+	pub data: Vec<u8>,
+	/// Gas available to the phase, capping its frame.
+	pub gas_limit: u64,
+}
+
+/// Outcome of `StackExecutor::transact_validated`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ValidatedOutcome {
+	/// Outcome of the validation phase.
+	pub validation: TransactOutcome,
+	/// Outcome of the execution phase, `None` if the validation failed.
+	pub execution: Option<TransactOutcome>,
+}
+
+impl ValidatedOutcome {
+	/// Whether both phases succeeded, so that their state was kept.
+	#[must_use]
+	pub fn is_succeed(&self) -> bool {
+		self.validation.exit.is_succeed()
+			&& self.execution.as_ref().is_some_and(|execution| execution.exit.is_succeed())
+	}
+}
@@ -0,0 +1,303 @@
+//! # High-level EVM
+//!
+//! `Evm` owns a backend together with the executor options, and runs
+//! transactions against it, committing their changes. The executor and
+//! backend traits stay available for integrators needing finer control.
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use crate::{CreateScheme, ExitReason, Istanbul, Limits, Spec, H160, U256};
+use crate::analysis::AnalysisCache;
+use crate::backend::{Apply, ApplyBackend, Backend, Log};
+use crate::block::{BlockGas, GasLimitError};
+use crate::executor::{Fees, GasForwardingPolicy, NoncePolicy, PrecompileFn, StackExecutor, StateLimits, TxContext};
+use crate::summary::{CallTreeCollector, ExecutionSummary};
+use crate::tracing::{Event, EventListener};
+
+pub use crate::transaction::TransactionAction;
+
+/// Call or create transaction executed by `Evm::execute_block`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Call {
+	/// Sender.
+	pub caller: H160,
+	/// Call or create.
+	pub action: TransactionAction,
+	/// Transferred value.
+	pub value: U256,
+	/// Call input or init code.
+	pub data: Vec<u8>,
+	/// Gas limit.
+	pub gas_limit: u64,
+}
+
+/// State of the backend at some point, see `Evm::snapshot`.
+#[derive(Clone, Debug)]
+pub struct Snapshot<B>(B);
+
+/// Summary, state changes and logs of an executed transaction.
+type Outcome = (ExecutionSummary, Vec<Apply<BTreeMap<U256, U256>>>, Vec<Log>);
+
+/// Notifies two listeners.
+struct Tee<'a>(&'a dyn EventListener, &'a dyn EventListener);
+
+impl EventListener for Tee<'_> {
+	fn event(&self, event: Event<'_>) {
+		self.0.event(event);
+		self.1.event(event);
+	}
+}
+
+/// EVM running transactions against an owned backend, for the hard fork `S`.
+pub struct Evm<B, S = Istanbul> {
+	backend: B,
+	precompile: Option<PrecompileFn>,
+	analyses: AnalysisCache,
+	fees: Option<Fees>,
+	limits: Option<Limits>,
+	state_limits: Option<StateLimits>,
+	nonce_policy: Option<Box<dyn NoncePolicy>>,
+	gas_forwarding: Option<Box<dyn GasForwardingPolicy>>,
+	spec: PhantomData<S>,
+}
+
+impl<B: Backend + ApplyBackend> Evm<B> {
+	/// Create an EVM for the Istanbul hard fork.
+	pub fn new(backend: B) -> Self {
+		Self::new_with_spec(backend, Istanbul)
+	}
+}
+
+impl<B: Backend + ApplyBackend, S: Spec> Evm<B, S> {
+	/// Create an EVM for the hard fork `S`, without precompiles nor fees.
+	pub fn new_with_spec(backend: B, _spec: S) -> Self {
+		Self {
+			backend,
+			precompile: None,
+			analyses: AnalysisCache::new(),
+			fees: None,
+			limits: None,
+			state_limits: None,
+			nonce_policy: None,
+			gas_forwarding: None,
+			spec: PhantomData,
+		}
+	}
+
+	/// Serve calls to precompiled contracts, see
+	/// `StackExecutor::new_with_precompile`.
+	#[must_use]
+	pub fn with_precompile(mut self, precompile: PrecompileFn) -> Self {
+		self.precompile = Some(precompile);
+		self
+	}
+
+	/// Charge gas fees, see `StackExecutor::with_fees`.
+	#[must_use]
+	pub const fn with_fees(mut self, fees: Fees) -> Self {
+		self.fees = Some(fees);
+		self
+	}
+
+	/// Run frames with the given limits, see `StackExecutor::with_limits`.
+	#[must_use]
+	pub const fn with_limits(mut self, limits: Limits) -> Self {
+		self.limits = Some(limits);
+		self
+	}
+
+	/// Cap the logs and the self-destruct set of each transaction, see
+	/// `StackExecutor::with_state_limits`.
+	#[must_use]
+	pub const fn with_state_limits(mut self, limits: StateLimits) -> Self {
+		self.state_limits = Some(limits);
+		self
+	}
+
+	/// Decide when nonces are increased, see
+	/// `StackExecutor::with_nonce_policy`.
+	#[must_use]
+	pub fn with_nonce_policy(mut self, policy: Box<dyn NoncePolicy>) -> Self {
+		self.nonce_policy = Some(policy);
+		self
+	}
+
+	/// Decide how much gas child frames receive, see
+	/// `StackExecutor::with_gas_forwarding`.
+	#[must_use]
+	pub fn with_gas_forwarding(mut self, policy: Box<dyn GasForwardingPolicy>) -> Self {
+		self.gas_forwarding = Some(policy);
+		self
+	}
+
+	/// Get the backend.
+	pub const fn backend(&self) -> &B {
+		&self.backend
+	}
+
+	/// Get the backend mutably, for instance to move to the next block.
+	pub const fn backend_mut(&mut self) -> &mut B {
+		&mut self.backend
+	}
+
+	/// Deconstruct into the backend.
+	pub fn into_backend(self) -> B {
+		self.backend
+	}
+
+	/// Code analyses shared by the transactions, keyed by code hash.
+	pub const fn analyses(&self) -> &AnalysisCache {
+		&self.analyses
+	}
+
+	/// Create an executor with the options of the EVM, for running a
+	/// transaction by hand. Its changes are not committed.
+	pub fn executor(&self, gas_limit: u64) -> StackExecutor<'_, B, S> {
+		let mut executor = StackExecutor::new_with_spec(
+			&self.backend,
+			gas_limit,
+			self.precompile.unwrap_or(crate::executor::no_precompile),
+			S::default(),
+		).with_analysis_cache(self.analyses.clone());
+		if let Some(fees) = self.fees {
+			executor = executor.with_fees(fees);
+		}
+		if let Some(limits) = self.limits {
+			executor = executor.with_limits(limits);
+		}
+		if let Some(limits) = self.state_limits {
+			executor = executor.with_state_limits(limits);
+		}
+		if let Some(policy) = &self.nonce_policy {
+			executor = executor.with_nonce_policy(policy.as_ref());
+		}
+		if let Some(policy) = &self.gas_forwarding {
+			executor = executor.with_gas_forwarding(policy.as_ref());
+		}
+		executor
+	}
+
+	/// Address of the contract created by the next `CREATE` transaction of
+	/// `caller`.
+	pub fn create_address(&self, caller: H160) -> H160 {
+		self.executor(0).create_address(CreateScheme::Legacy { caller })
+	}
+
+	/// Execute a `CALL` transaction and commit its changes. Transactions run
+	/// with `caller` as origin, and the gas price of the fees if set, of the
+	/// backend otherwise.
+	pub fn call(
+		&mut self,
+		caller: H160,
+		address: H160,
+		value: U256,
+		data: Vec<u8>,
+		gas_limit: u64,
+	) -> ExecutionSummary {
+		self.commit(&Call { caller, action: TransactionAction::Call(address), value, data, gas_limit })
+	}
+
+	/// Execute a `CREATE` transaction and commit its changes.
+	pub fn create(
+		&mut self,
+		caller: H160,
+		value: U256,
+		init_code: Vec<u8>,
+		gas_limit: u64,
+	) -> ExecutionSummary {
+		self.commit(&Call { caller, action: TransactionAction::Create, value, data: init_code, gas_limit })
+	}
+
+	/// Execute a `CALL` transaction notifying `listener`, without committing
+	/// its changes.
+	pub fn trace_call(
+		&self,
+		caller: H160,
+		address: H160,
+		value: U256,
+		data: Vec<u8>,
+		gas_limit: u64,
+		listener: &dyn EventListener,
+	) -> ExecutionSummary {
+		let transaction = Call { caller, action: TransactionAction::Call(address), value, data, gas_limit };
+		self.transact(&transaction, Some(listener)).0
+	}
+
+	/// Execute the transactions of a block in order, committing the changes
+	/// of each. Transactions whose gas limit does not fit in the block gas
+	/// limit of the backend are skipped.
+	pub fn execute_block<I>(&mut self, transactions: I) -> Vec<Result<ExecutionSummary, GasLimitError>> where
+		I: IntoIterator<Item=Call>,
+	{
+		let mut block_gas = BlockGas::from_backend(&self.backend);
+		transactions.into_iter()
+			.map(|transaction| {
+				block_gas.validate(transaction.gas_limit)?;
+				let summary = self.commit(&transaction);
+				block_gas.record(summary.used_gas);
+				Ok(summary)
+			})
+			.collect()
+	}
+
+	/// Save the current state of the backend.
+	pub fn snapshot(&self) -> Snapshot<B> where B: Clone {
+		Snapshot(self.backend.clone())
+	}
+
+	/// Restore a previously saved state of the backend, discarding the
+	/// changes committed since.
+	pub fn revert_to(&mut self, snapshot: Snapshot<B>) {
+		self.backend = snapshot.0;
+	}
+
+	fn commit(&mut self, transaction: &Call) -> ExecutionSummary {
+		let (summary, applies, logs) = self.transact(transaction, None);
+		self.backend.apply(applies, logs, S::CONFIG.clear_touched_empty);
+		summary
+	}
+
+	fn transact(
+		&self,
+		transaction: &Call,
+		listener: Option<&dyn EventListener>,
+	) -> Outcome {
+		let collector = CallTreeCollector::new();
+		let tee;
+		let listener: &dyn EventListener = match listener {
+			Some(listener) => {
+				tee = Tee(listener, &collector);
+				&tee
+			},
+			None => &collector,
+		};
+
+		let gas_limit = transaction.gas_limit;
+		let context = TxContext {
+			origin: transaction.caller,
+			gas_price: self.fees.map_or_else(|| self.backend.gas_price(), |fees| fees.gas_price),
+		};
+		let mut executor = self.executor(gas_limit).with_listener(listener).with_tx_context(context);
+		let (reason, return_value): (ExitReason, Vec<u8>) = match transaction.action {
+			TransactionAction::Call(address) => executor.transact_call(
+				transaction.caller, address, transaction.value, transaction.data.clone(), gas_limit,
+			),
+			TransactionAction::Create => {
+				let reason = executor.transact_create(
+					transaction.caller, transaction.value, transaction.data.clone(), gas_limit,
+				);
+				(reason, Vec::new())
+			},
+		};
+		let used_gas = executor.used_gas();
+		let refund = executor.refund();
+		let (applies, logs) = executor.deconstruct();
+
+		let summary = ExecutionSummary::new(reason, return_value, gas_limit, used_gas, refund)
+			.with_logs(logs.clone())
+			.with_call_tree(&collector);
+		(summary, applies, logs)
+	}
+}
@@ -0,0 +1,308 @@
+//! # Gas regression harness
+//!
+//! Runs bytecode fixtures and renders the gas they use, in total and per
+//! opcode, as text kept in golden files. Comparing the rendering with the
+//! golden one flags any change of gas semantics, for instance when updating
+//! the crate on a chain whose gas costs must not move.
+//!
+//! A golden file has a block per fixture, separated by blank lines:
+//!
+//! ```text
+//! fixture add
+//! reason Succeed(Stopped)
+//! total 21009
+//! 0x00 1 0
+//! 0x01 1 3
+//! 0x60 2 6
+//! ```
+//!
+//! Opcode lines give the opcode, its executions and the gas charged for
+//! them, nested frames included.
+
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::fmt::{self, Write};
+use core::marker::PhantomData;
+use crate::{ExitReason, Istanbul, Spec, H160, U256};
+use crate::backend::{MemoryAccount, MemoryBackend, MemoryVicinity};
+use crate::executor::{no_precompile, StackExecutor};
+use crate::tracing::{Event, EventListener};
+
+/// Gas limit of a fixture, unless set with `Fixture::with_gas_limit`.
+pub const DEFAULT_FIXTURE_GAS_LIMIT: u64 = 1_000_000;
+
+/// Bytecode fixture, called as the code of a contract.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Fixture {
+	/// Name of the fixture in the golden file, on a single line.
+	pub name: String,
+	/// Code of the called contract.
+	pub code: Vec<u8>,
+	/// Call input.
+	pub input: Vec<u8>,
+	/// Gas limit of the call.
+	pub gas_limit: u64,
+}
+
+impl Fixture {
+	/// Fixture calling `code` with no input.
+	pub fn new(name: impl Into<String>, code: Vec<u8>) -> Self {
+		Self {
+			name: name.into(),
+			code,
+			input: Vec::new(),
+			gas_limit: DEFAULT_FIXTURE_GAS_LIMIT,
+		}
+	}
+
+	/// Call the code with the given input.
+	#[must_use]
+	pub fn with_input(mut self, input: Vec<u8>) -> Self {
+		self.input = input;
+		self
+	}
+
+	/// Call the code with the given gas limit.
+	#[must_use]
+	pub const fn with_gas_limit(mut self, gas_limit: u64) -> Self {
+		self.gas_limit = gas_limit;
+		self
+	}
+}
+
+/// Gas used by a fixture.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GasUsage {
+	/// Name of the fixture.
+	pub name: String,
+	/// Exit reason of the call.
+	pub reason: ExitReason,
+	/// Gas used by the call, intrinsic gas included and refund deducted.
+	pub total: u64,
+	/// Executions and gas charged, by opcode.
+	pub opcodes: BTreeMap<u8, (u64, u64)>,
+}
+
+impl fmt::Display for GasUsage {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		writeln!(f, "fixture {}", self.name)?;
+		writeln!(f, "reason {:?}", self.reason)?;
+		writeln!(f, "total {}", self.total)?;
+		for (opcode, (count, gas)) in &self.opcodes {
+			writeln!(f, "{opcode:#04x} {count} {gas}")?;
+		}
+		Ok(())
+	}
+}
+
+/// Fixture whose rendering differs from the golden one.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GasMismatch {
+	/// Name of the fixture.
+	pub name: String,
+	/// Golden rendering, `None` for a new fixture.
+	pub expected: Option<String>,
+	/// Current rendering, `None` for a fixture no longer run.
+	pub actual: Option<String>,
+}
+
+/// Fixtures whose gas usage changed, see `GasHarness::check`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GasRegression(pub Vec<GasMismatch>);
+
+impl fmt::Display for GasRegression {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		writeln!(f, "gas usage changed for {} fixture(s)", self.0.len())?;
+		for mismatch in &self.0 {
+			writeln!(f, "--- {}", mismatch.name)?;
+			writeln!(f, "expected:\n{}", mismatch.expected.as_deref().unwrap_or("(none)\n"))?;
+			writeln!(f, "actual:\n{}", mismatch.actual.as_deref().unwrap_or("(none)\n"))?;
+		}
+		Ok(())
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for GasRegression {}
+
+/// Failure of `GasHarness::check_file`.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub enum GoldenFileError {
+	/// The golden file could not be read or written.
+	Io(std::io::Error),
+	/// The gas usage differs from the golden file.
+	Regression(GasRegression),
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for GoldenFileError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Io(e) => write!(f, "golden file: {e}"),
+			Self::Regression(regression) => regression.fmt(f),
+		}
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for GoldenFileError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Self::Io(e) => Some(e),
+			Self::Regression(regression) => Some(regression),
+		}
+	}
+}
+
+/// Listener counting the executions and gas of each opcode.
+#[derive(Default)]
+struct OpcodeCounter(RefCell<BTreeMap<u8, (u64, u64)>>);
+
+impl EventListener for OpcodeCounter {
+	fn event(&self, event: Event<'_>) {
+		if let Event::Step { opcode, gas_cost, .. } = event {
+			let mut opcodes = self.0.borrow_mut();
+			let (count, gas) = opcodes.entry(opcode.as_u8()).or_default();
+			*count += 1;
+			*gas += gas_cost;
+		}
+	}
+}
+
+/// Harness running fixtures for the hard fork `S`.
+#[derive(Clone, Debug, Default)]
+pub struct GasHarness<S = Istanbul> {
+	fixtures: Vec<Fixture>,
+	spec: PhantomData<S>,
+}
+
+impl GasHarness {
+	/// Create a harness for the Istanbul hard fork.
+	#[must_use]
+	pub const fn new() -> Self {
+		Self::new_with_spec(Istanbul)
+	}
+}
+
+impl<S: Spec> GasHarness<S> {
+	/// Create a harness for the hard fork `S`.
+	#[must_use]
+	pub const fn new_with_spec(_spec: S) -> Self {
+		Self { fixtures: Vec::new(), spec: PhantomData }
+	}
+
+	/// Add a fixture.
+	#[must_use]
+	pub fn with_fixture(mut self, fixture: Fixture) -> Self {
+		self.fixtures.push(fixture);
+		self
+	}
+
+	/// Run the fixtures, each on a fresh state with the code deployed at a
+	/// fixed address.
+	#[must_use]
+	pub fn run(&self) -> Vec<GasUsage> {
+		self.fixtures.iter().map(Self::run_fixture).collect()
+	}
+
+	fn run_fixture(fixture: &Fixture) -> GasUsage {
+		let caller = H160::repeat_byte(0x10);
+		let contract = H160::repeat_byte(0x20);
+		let vicinity = MemoryVicinity {
+			gas_price: U256::zero(),
+			origin: caller,
+			chain_id: U256::one(),
+			block_hashes: Vec::new(),
+			block_number: U256::zero(),
+			block_coinbase: H160::default(),
+			block_timestamp: U256::zero(),
+			block_difficulty: U256::zero(),
+			block_gas_limit: U256::from(u64::MAX),
+		};
+		let mut state = BTreeMap::new();
+		state.insert(contract, MemoryAccount {
+			nonce: U256::one(),
+			code: fixture.code.clone(),
+			..MemoryAccount::default()
+		});
+		let backend = MemoryBackend::new(&vicinity, state);
+
+		let counter = OpcodeCounter::default();
+		let mut executor = StackExecutor::new_with_spec(&backend, fixture.gas_limit, no_precompile, S::default())
+			.with_listener(&counter);
+		let (reason, _) = executor.transact_call(caller, contract, U256::zero(), fixture.input.clone(), fixture.gas_limit);
+		let total = executor.used_gas();
+		drop(executor);
+
+		GasUsage {
+			name: fixture.name.clone(),
+			reason,
+			total,
+			opcodes: counter.0.into_inner(),
+		}
+	}
+
+	/// Golden rendering of the fixtures.
+	#[must_use]
+	pub fn render(&self) -> String {
+		let blocks = self.run().iter().map(|usage| format!("{usage}")).collect::<Vec<_>>();
+		blocks.join("\n")
+	}
+
+	/// Compare the fixtures with a golden rendering.
+	pub fn check(&self, golden: &str) -> Result<(), GasRegression> {
+		let mut expected = BTreeMap::new();
+		let mut block = String::new();
+		for line in golden.lines().filter(|line| !line.trim().is_empty()) {
+			if line.starts_with("fixture ") && !block.is_empty() {
+				insert_block(&mut expected, core::mem::take(&mut block));
+			}
+			let _ = writeln!(block, "{}", line.trim_end());
+		}
+		if !block.is_empty() {
+			insert_block(&mut expected, block);
+		}
+
+		let mut mismatches = Vec::new();
+		for usage in self.run() {
+			let actual = format!("{usage}");
+			let golden = expected.remove(&usage.name);
+			if golden.as_ref() != Some(&actual) {
+				mismatches.push(GasMismatch { name: usage.name, expected: golden, actual: Some(actual) });
+			}
+		}
+		for (name, golden) in expected {
+			mismatches.push(GasMismatch { name, expected: Some(golden), actual: None });
+		}
+
+		if mismatches.is_empty() {
+			Ok(())
+		} else {
+			Err(GasRegression(mismatches))
+		}
+	}
+
+	/// Compare the fixtures with a golden file. With the `UPDATE_GOLDEN`
+	/// environment variable set, the file is written instead, created if it
+	/// does not exist, to record the current gas usage.
+	#[cfg(feature = "std")]
+	pub fn check_file(&self, path: impl AsRef<std::path::Path>) -> Result<(), GoldenFileError> {
+		let path = path.as_ref();
+		let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+		match std::fs::read_to_string(path) {
+			Ok(golden) if !update => self.check(&golden).map_err(GoldenFileError::Regression),
+			Err(e) if !update || e.kind() != std::io::ErrorKind::NotFound => Err(GoldenFileError::Io(e)),
+			_ => std::fs::write(path, self.render()).map_err(GoldenFileError::Io),
+		}
+	}
+}
+
+/// Record a golden block under the name on its first line.
+fn insert_block(blocks: &mut BTreeMap<String, String>, block: String) {
+	let name = block.lines().next().and_then(|line| line.strip_prefix("fixture ")).unwrap_or_default().into();
+	blocks.insert(name, block);
+}
@@ -0,0 +1,82 @@
+//! # Compiled execution of hot code
+//!
+//! Counts executions per code hash, and translates code into closures once
+//! it has run often enough, see `CompiledCode`. Cold code keeps running in
+//! the interpreter.
+
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use alloc::sync::Arc;
+use core::cell::RefCell;
+use crate::{CompiledCode, H256};
+
+/// Default number of executions after which code is compiled.
+pub const DEFAULT_THRESHOLD: u64 = 16;
+
+#[derive(Debug)]
+enum Entry {
+	Cold(u64),
+	Hot(Arc<CompiledCode>),
+}
+
+#[derive(Debug)]
+struct Inner {
+	threshold: u64,
+	entries: BTreeMap<H256, Entry>,
+}
+
+/// Execution counts and compiled code, keyed by code hash. Clones share the
+/// same cache, so it can be kept across transactions.
+#[derive(Clone, Debug)]
+pub struct JitCache(Rc<RefCell<Inner>>);
+
+impl Default for JitCache {
+	fn default() -> Self {
+		Self::new(DEFAULT_THRESHOLD)
+	}
+}
+
+impl JitCache {
+	/// Create an empty cache, compiling code on its `threshold`th execution.
+	#[must_use]
+	pub fn new(threshold: u64) -> Self {
+		Self(Rc::new(RefCell::new(Inner { threshold, entries: BTreeMap::new() })))
+	}
+
+	/// Record an execution of the code with the given hash, returning its
+	/// translation once it is hot.
+	#[must_use]
+	pub fn record(&self, hash: H256, code: &[u8]) -> Option<Arc<CompiledCode>> {
+		let mut inner = self.0.borrow_mut();
+		let threshold = inner.threshold;
+		let entry = inner.entries.entry(hash).or_insert(Entry::Cold(0));
+		if let Entry::Cold(count) = entry {
+			*count += 1;
+			if *count < threshold {
+				return None
+			}
+			*entry = Entry::Hot(Arc::new(CompiledCode::compile(code)));
+		}
+
+		match entry {
+			Entry::Hot(compiled) => Some(compiled.clone()),
+			Entry::Cold(_) => None,
+		}
+	}
+
+	/// Whether the code with the given hash has been compiled.
+	#[must_use]
+	pub fn is_hot(&self, hash: H256) -> bool {
+		matches!(self.0.borrow().entries.get(&hash), Some(Entry::Hot(_)))
+	}
+
+	/// Number of executions recorded for the code with the given hash, while
+	/// it is still cold.
+	#[must_use]
+	pub fn count(&self, hash: H256) -> Option<u64> {
+		match self.0.borrow().entries.get(&hash) {
+			Some(Entry::Cold(count)) => Some(*count),
+			_ => None,
+		}
+	}
+}
@@ -16,6 +16,29 @@ extern crate alloc;
 pub use evm_core::*;
 pub use evm_runtime::*;
 pub use evm_gasometer as gasometer;
+pub use crate::facade::Evm;
 
+pub mod access_list;
+pub mod analysis;
 pub mod executor;
 pub mod backend;
+pub mod block;
+pub mod call_trace;
+pub mod chrome_trace;
+pub mod coverage;
+pub mod facade;
+#[cfg(feature = "software-keccak")]
+pub mod gas_regression;
+#[cfg(feature = "jit")]
+pub mod jit;
+pub mod metrics;
+pub mod profiler;
+pub mod receipt;
+pub mod reentrancy;
+pub mod summary;
+#[cfg(feature = "software-keccak")]
+pub mod testing;
+pub mod tracing;
+pub mod transaction;
+#[cfg(feature = "wasm")]
+pub mod wasm;
@@ -0,0 +1,198 @@
+//! # Execution metrics
+//!
+//! An event listener aggregating opcode and contract level statistics, to
+//! profile where the gas of a transaction is spent.
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use crate::{Opcode, H160};
+use crate::tracing::{Event, EventListener};
+
+/// Statistics of a single opcode.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OpcodeMetrics {
+	/// Number of times the opcode was executed.
+	pub count: u64,
+	/// Cumulative gas spent on the opcode.
+	pub gas: u64,
+}
+
+/// Statistics of a single contract.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ContractMetrics {
+	/// Number of opcodes executed in the contract context.
+	pub steps: u64,
+	/// Cumulative gas spent on opcodes in the contract context.
+	pub gas: u64,
+	/// Number of times the contract was called or created.
+	pub calls: u64,
+}
+
+/// Maps keyed by opcodes or addresses. Human-readable formats, such as JSON,
+/// only have string keys, so the keys are `0x`-prefixed hex strings there.
+#[cfg(feature = "with-serde")]
+mod serde_keys {
+	use alloc::collections::BTreeMap;
+	use alloc::string::String;
+	use alloc::vec::Vec;
+	use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+	use crate::H160;
+
+	/// Key with a fixed-size byte representation.
+	pub trait Key: Ord + Sized {
+		fn to_bytes(&self) -> Vec<u8>;
+		fn from_bytes(bytes: &[u8]) -> Option<Self>;
+	}
+
+	impl Key for u8 {
+		fn to_bytes(&self) -> Vec<u8> {
+			alloc::vec![*self]
+		}
+
+		fn from_bytes(bytes: &[u8]) -> Option<Self> {
+			match bytes {
+				[byte] => Some(*byte),
+				_ => None,
+			}
+		}
+	}
+
+	impl Key for H160 {
+		fn to_bytes(&self) -> Vec<u8> {
+			self.as_bytes().to_vec()
+		}
+
+		fn from_bytes(bytes: &[u8]) -> Option<Self> {
+			(bytes.len() == 20).then(|| Self::from_slice(bytes))
+		}
+	}
+
+	pub fn serialize<K, V, S>(map: &BTreeMap<K, V>, serializer: S) -> Result<S::Ok, S::Error> where
+		K: Key + Serialize,
+		V: Serialize,
+		S: Serializer,
+	{
+		if serializer.is_human_readable() {
+			serializer.collect_map(map.iter().map(|(key, value)| (crate::serde_hex::encode(&key.to_bytes()), value)))
+		} else {
+			map.serialize(serializer)
+		}
+	}
+
+	pub fn deserialize<'de, K, V, D>(deserializer: D) -> Result<BTreeMap<K, V>, D::Error> where
+		K: Key + Deserialize<'de>,
+		V: Deserialize<'de>,
+		D: Deserializer<'de>,
+	{
+		if !deserializer.is_human_readable() {
+			return BTreeMap::deserialize(deserializer)
+		}
+
+		BTreeMap::<String, V>::deserialize(deserializer)?
+			.into_iter()
+			.map(|(key, value)| {
+				crate::serde_hex::decode(&key).ok()
+					.and_then(|bytes| K::from_bytes(&bytes))
+					.map(|key| (key, value))
+					.ok_or_else(|| de::Error::invalid_value(de::Unexpected::Str(&key), &"a hex key"))
+			})
+			.collect()
+	}
+}
+
+/// Aggregated execution metrics.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MetricsReport {
+	/// Statistics per opcode byte.
+	#[cfg_attr(feature = "with-serde", serde(with = "serde_keys"))]
+	pub opcodes: BTreeMap<u8, OpcodeMetrics>,
+	/// Statistics per contract address.
+	#[cfg_attr(feature = "with-serde", serde(with = "serde_keys"))]
+	pub contracts: BTreeMap<H160, ContractMetrics>,
+	/// Total number of executed opcodes.
+	pub steps: u64,
+	/// Total gas spent on opcodes.
+	pub gas: u64,
+}
+
+impl MetricsReport {
+	/// Opcodes sorted by descending cumulative gas, limited to `n` entries.
+	#[must_use]
+	pub fn hot_opcodes(&self, n: usize) -> Vec<(Opcode, OpcodeMetrics)> {
+		let mut opcodes = self.opcodes.iter()
+			.map(|(opcode, metrics)| (Opcode(*opcode), *metrics))
+			.collect::<Vec<_>>();
+		opcodes.sort_by_key(|(_, metrics)| core::cmp::Reverse(metrics.gas));
+		opcodes.truncate(n);
+		opcodes
+	}
+
+	/// Contracts sorted by descending cumulative gas, limited to `n` entries.
+	#[must_use]
+	pub fn hot_contracts(&self, n: usize) -> Vec<(H160, ContractMetrics)> {
+		let mut contracts = self.contracts.iter()
+			.map(|(address, metrics)| (*address, *metrics))
+			.collect::<Vec<_>>();
+		contracts.sort_by_key(|(_, metrics)| core::cmp::Reverse(metrics.gas));
+		contracts.truncate(n);
+		contracts
+	}
+}
+
+/// Event listener collecting a `MetricsReport`.
+#[derive(Debug, Default)]
+pub struct MetricsCollector {
+	report: RefCell<MetricsReport>,
+}
+
+impl MetricsCollector {
+	/// Create a new empty collector.
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Get a copy of the metrics collected so far.
+	#[must_use]
+	pub fn report(&self) -> MetricsReport {
+		self.report.borrow().clone()
+	}
+
+	/// Deconstruct the collector, returning the collected metrics.
+	#[must_use]
+	pub fn into_report(self) -> MetricsReport {
+		self.report.into_inner()
+	}
+}
+
+impl EventListener for MetricsCollector {
+	fn event(&self, event: Event<'_>) {
+		let mut report = self.report.borrow_mut();
+
+		match event {
+			Event::Call { context, .. } => {
+				report.contracts.entry(context.address).or_default().calls += 1;
+			},
+			Event::Create { address, .. } => {
+				report.contracts.entry(address).or_default().calls += 1;
+			},
+			Event::Step { context, opcode, gas_cost, .. } => {
+				report.steps += 1;
+				report.gas += gas_cost;
+
+				let opcode = report.opcodes.entry(opcode.as_u8()).or_default();
+				opcode.count += 1;
+				opcode.gas += gas_cost;
+
+				let contract = report.contracts.entry(context.address).or_default();
+				contract.steps += 1;
+				contract.gas += gas_cost;
+			},
+			Event::FrameGas { .. } | Event::Exit { .. } | Event::SetStorage { .. } => (),
+		}
+	}
+}
@@ -0,0 +1,214 @@
+//! # Gas profiler
+//!
+//! An event listener attributing gas to program counters, optionally mapped
+//! back to Solidity source lines, and rendered as folded stacks for
+//! flamegraph tools.
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::format;
+use core::cell::RefCell;
+use core::convert::TryFrom;
+use core::fmt::Write;
+use crate::H160;
+use crate::tracing::{Event, EventListener};
+
+/// Location of an opcode in deployed or init code.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProgramCounter {
+	/// Address of the code.
+	pub address: H160,
+	/// Whether the code is the init code of a create.
+	pub create: bool,
+	/// Offset of the opcode in the code.
+	pub position: usize,
+}
+
+/// Statistics of a single program counter.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PositionMetrics {
+	/// Number of times the opcode was executed.
+	pub count: u64,
+	/// Cumulative gas spent on the opcode.
+	pub gas: u64,
+}
+
+/// Mapping from program counters to source lines, built from a Solidity
+/// compressed source map.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SourceMap {
+	lines: BTreeMap<usize, String>,
+}
+
+impl SourceMap {
+	/// Parse a Solidity source map (`s:l:f:j;...`) for the given code.
+	///
+	/// `sources` lists the name and text of each source file, in the order of
+	/// the compiler file indexes. Instructions referring to no file or to an
+	/// unknown one are left unmapped.
+	#[must_use]
+	pub fn parse(source_map: &str, code: &[u8], sources: &[(&str, &str)]) -> Self {
+		let positions = instruction_positions(code);
+		let mut lines = BTreeMap::new();
+		let (mut start, mut file) = (0_usize, -1_i64);
+
+		for (entry, position) in source_map.split(';').zip(positions) {
+			let mut fields = entry.split(':');
+			if let Some(value) = fields.next().and_then(|s| s.parse().ok()) {
+				start = value;
+			}
+			let _length = fields.next();
+			if let Some(value) = fields.next().and_then(|s| s.parse().ok()) {
+				file = value;
+			}
+
+			let source = usize::try_from(file).ok().and_then(|file| sources.get(file));
+			if let Some((name, text)) = source {
+				let line = text.bytes().take(start).filter(|b| *b == b'\n').count() + 1;
+				lines.insert(position, format!("{name}:{line}"));
+			}
+		}
+
+		Self { lines }
+	}
+
+	/// Source line of the opcode at the given program counter.
+	#[must_use]
+	pub fn line(&self, position: usize) -> Option<&str> {
+		self.lines.get(&position).map(String::as_str)
+	}
+}
+
+/// Program counter of each instruction, skipping `PUSHn` immediates.
+pub(crate) fn instruction_positions(code: &[u8]) -> Vec<usize> {
+	let mut positions = Vec::new();
+	let mut position = 0;
+	while position < code.len() {
+		positions.push(position);
+		let opcode = code[position];
+		position += match opcode {
+			0x60..=0x7f => (opcode - 0x5e) as usize,
+			_ => 1,
+		};
+	}
+	positions
+}
+
+#[derive(Debug, Default)]
+struct State {
+	frames: Vec<ProgramCounter>,
+	positions: BTreeMap<ProgramCounter, PositionMetrics>,
+	stacks: BTreeMap<Vec<ProgramCounter>, u64>,
+}
+
+/// Event listener attributing gas to program counters and call stacks.
+#[derive(Debug, Default)]
+pub struct GasProfiler {
+	state: RefCell<State>,
+	source_maps: BTreeMap<(H160, bool), SourceMap>,
+}
+
+impl GasProfiler {
+	/// Create a new empty profiler.
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Map the deployed code at `address` to source lines.
+	#[must_use]
+	pub fn with_source_map(mut self, address: H160, source_map: SourceMap) -> Self {
+		self.source_maps.insert((address, false), source_map);
+		self
+	}
+
+	/// Map the init code creating `address` to source lines.
+	#[must_use]
+	pub fn with_init_source_map(mut self, address: H160, source_map: SourceMap) -> Self {
+		self.source_maps.insert((address, true), source_map);
+		self
+	}
+
+	/// Statistics per program counter.
+	#[must_use]
+	pub fn positions(&self) -> BTreeMap<ProgramCounter, PositionMetrics> {
+		self.state.borrow().positions.clone()
+	}
+
+	/// Program counters sorted by descending cumulative gas, limited to `n`
+	/// entries.
+	#[must_use]
+	pub fn hot_positions(&self, n: usize) -> Vec<(ProgramCounter, PositionMetrics)> {
+		let mut positions = self.state.borrow().positions.iter()
+			.map(|(pc, metrics)| (*pc, *metrics))
+			.collect::<Vec<_>>();
+		positions.sort_by_key(|(_, metrics)| core::cmp::Reverse(metrics.gas));
+		positions.truncate(n);
+		positions
+	}
+
+	/// Label of a program counter, its source line if a source map is known.
+	#[must_use]
+	pub fn label(&self, pc: &ProgramCounter) -> String {
+		let line = self.source_maps.get(&(pc.address, pc.create))
+			.and_then(|source_map| source_map.line(pc.position));
+		match (line, pc.create) {
+			(Some(line), _) => String::from(line),
+			(None, false) => format!("{:?}:{}", pc.address, pc.position),
+			(None, true) => format!("{:?}(create):{}", pc.address, pc.position),
+		}
+	}
+
+	/// Gas per call stack in the folded format, one `frame;...;frame gas`
+	/// line per stack, where outer frames are labeled by their call site.
+	#[must_use]
+	pub fn folded(&self) -> String {
+		let mut stacks = BTreeMap::<String, u64>::new();
+		for (stack, gas) in &self.state.borrow().stacks {
+			let labels = stack.iter().map(|pc| self.label(pc)).collect::<Vec<_>>();
+			*stacks.entry(labels.join(";")).or_default() += gas;
+		}
+
+		let mut folded = String::new();
+		for (stack, gas) in stacks {
+			let _ = writeln!(folded, "{stack} {gas}");
+		}
+		folded
+	}
+}
+
+impl EventListener for GasProfiler {
+	fn event(&self, event: Event<'_>) {
+		let mut state = self.state.borrow_mut();
+		match event {
+			Event::Call { code_address, .. } => {
+				state.frames.push(ProgramCounter { address: code_address, create: false, position: 0 });
+			},
+			Event::Create { address, .. } => {
+				state.frames.push(ProgramCounter { address, create: true, position: 0 });
+			},
+			Event::Exit { .. } => {
+				state.frames.pop();
+			},
+			Event::FrameGas { .. } | Event::SetStorage { .. } => (),
+			Event::Step { context, position, gas_cost, .. } => {
+				let state = &mut *state;
+				if state.frames.is_empty() {
+					state.frames.push(ProgramCounter { address: context.address, create: false, position });
+				}
+				let last = state.frames.len() - 1;
+				state.frames[last].position = position;
+				let pc = state.frames[last];
+
+				let metrics = state.positions.entry(pc).or_default();
+				metrics.count += 1;
+				metrics.gas += gas_cost;
+
+				*state.stacks.entry(state.frames.clone()).or_default() += gas_cost;
+			},
+		}
+	}
+}
@@ -0,0 +1,246 @@
+//! # Receipts
+//!
+//! Ethereum transaction receipts and their logs bloom, with their RLP
+//! encoding, for clients computing the receipts root of a block or storing
+//! receipts in Ethereum data structures. Hashing goes through a `Keccak`
+//! implementation, such as the backend of the executor.
+
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::fmt;
+use crate::{ExitReason, Keccak, H256};
+use crate::backend::Log;
+
+/// Size in bytes of a logs bloom.
+pub const BLOOM_SIZE: usize = 256;
+
+/// 2048-bit bloom filter of the addresses and topics of logs.
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub struct Bloom(pub [u8; BLOOM_SIZE]);
+
+impl Default for Bloom {
+	fn default() -> Self {
+		Self([0; BLOOM_SIZE])
+	}
+}
+
+impl fmt::Debug for Bloom {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str("Bloom(0x")?;
+		for byte in &self.0[..] {
+			write!(f, "{byte:02x}")?;
+		}
+		f.write_str(")")
+	}
+}
+
+impl Bloom {
+	/// Bloom of the given logs.
+	pub fn from_logs<'a, K, I>(keccak: &K, logs: I) -> Self
+	where
+		K: Keccak + ?Sized,
+		I: IntoIterator<Item = &'a Log>,
+	{
+		let mut bloom = Self::default();
+		for log in logs {
+			bloom.accrue_log(keccak, log);
+		}
+		bloom
+	}
+
+	/// Positions of the three bits of `input`.
+	fn bits<K: Keccak + ?Sized>(keccak: &K, input: &[u8]) -> [usize; 3] {
+		let hash = keccak.keccak256_h256(input);
+		let mut bits = [0; 3];
+		for (bit, pair) in bits.iter_mut().zip(hash.as_bytes().chunks_exact(2)) {
+			*bit = (usize::from(pair[0]) << 8 | usize::from(pair[1])) & (BLOOM_SIZE * 8 - 1);
+		}
+		bits
+	}
+
+	/// Add `input` to the bloom.
+	pub fn accrue<K: Keccak + ?Sized>(&mut self, keccak: &K, input: &[u8]) {
+		for bit in Self::bits(keccak, input) {
+			self.0[BLOOM_SIZE - 1 - bit / 8] |= 1 << (bit % 8);
+		}
+	}
+
+	/// Add the address and the topics of `log` to the bloom.
+	pub fn accrue_log<K: Keccak + ?Sized>(&mut self, keccak: &K, log: &Log) {
+		self.accrue(keccak, log.address.as_bytes());
+		for topic in &log.topics {
+			self.accrue(keccak, topic.as_bytes());
+		}
+	}
+
+	/// Add the entries of another bloom, as for the bloom of a block.
+	pub fn accrue_bloom(&mut self, other: &Self) {
+		for (byte, other) in self.0.iter_mut().zip(&other.0[..]) {
+			*byte |= other;
+		}
+	}
+
+	/// Whether `input` may have been added to the bloom.
+	pub fn contains_input<K: Keccak + ?Sized>(&self, keccak: &K, input: &[u8]) -> bool {
+		Self::bits(keccak, input).iter().all(|bit| self.0[BLOOM_SIZE - 1 - bit / 8] & (1 << (bit % 8)) != 0)
+	}
+}
+
+impl rlp::Encodable for Bloom {
+	fn rlp_append(&self, s: &mut rlp::RlpStream) {
+		s.encoder().encode_value(&self.0);
+	}
+}
+
+impl rlp::Decodable for Bloom {
+	fn decode(rlp: &rlp::Rlp) -> Result<Self, rlp::DecoderError> {
+		rlp.decoder().decode_value(|bytes| match bytes.len().cmp(&BLOOM_SIZE) {
+			Ordering::Less => Err(rlp::DecoderError::RlpIsTooShort),
+			Ordering::Greater => Err(rlp::DecoderError::RlpIsTooBig),
+			Ordering::Equal => {
+				let mut bloom = Self::default();
+				bloom.0.copy_from_slice(bytes);
+				Ok(bloom)
+			},
+		})
+	}
+}
+
+#[cfg(feature = "with-serde")]
+impl serde::Serialize for Bloom {
+	fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		crate::serde_hex::serialize(&self.0, serializer)
+	}
+}
+
+#[cfg(feature = "with-serde")]
+impl<'de> serde::Deserialize<'de> for Bloom {
+	fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let bytes = crate::serde_hex::deserialize(deserializer)?;
+		if bytes.len() != BLOOM_SIZE {
+			return Err(serde::de::Error::invalid_length(bytes.len(), &"256 bytes"))
+		}
+		let mut bloom = Self::default();
+		bloom.0.copy_from_slice(&bytes);
+		Ok(bloom)
+	}
+}
+
+/// Outcome of the transaction in a receipt.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ReceiptOutcome {
+	/// State root after the transaction, before Byzantium.
+	StateRoot(H256),
+	/// Whether the transaction succeeded, from Byzantium on (EIP-658).
+	Status(bool),
+}
+
+impl ReceiptOutcome {
+	/// Status of a transaction exiting with `reason`.
+	#[must_use]
+	pub const fn status(reason: &ExitReason) -> Self {
+		Self::Status(reason.is_succeed())
+	}
+}
+
+/// Ethereum transaction receipt.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Receipt {
+	/// Outcome of the transaction.
+	pub outcome: ReceiptOutcome,
+	/// Gas used in the block up to and including the transaction.
+	pub cumulative_gas_used: u64,
+	/// Bloom of the logs.
+	pub bloom: Bloom,
+	/// Logs emitted by the transaction.
+	pub logs: Vec<Log>,
+}
+
+impl Receipt {
+	/// Receipt of a transaction emitting `logs`, with their bloom computed
+	/// with `keccak`.
+	pub fn new<K: Keccak + ?Sized>(
+		keccak: &K,
+		outcome: ReceiptOutcome,
+		cumulative_gas_used: u64,
+		logs: Vec<Log>,
+	) -> Self {
+		Self {
+			outcome,
+			cumulative_gas_used,
+			bloom: Bloom::from_logs(keccak, &logs),
+			logs,
+		}
+	}
+
+	/// Encoding of the receipt for a transaction of the given EIP-2718
+	/// type: the RLP encoding for legacy transactions, of type 0, prefixed
+	/// with the type otherwise. This is the value stored in the receipts
+	/// trie.
+	#[must_use]
+	pub fn encode(&self, transaction_type: u8) -> Vec<u8> {
+		let rlp = rlp::encode(self);
+		if transaction_type == 0 {
+			return rlp.to_vec()
+		}
+
+		let mut encoded = Vec::with_capacity(1 + rlp.len());
+		encoded.push(transaction_type);
+		encoded.extend_from_slice(&rlp);
+		encoded
+	}
+
+	/// Decode a receipt encoded with `encode`, returning its transaction
+	/// type.
+	pub fn decode(bytes: &[u8]) -> Result<(u8, Self), rlp::DecoderError> {
+		match bytes.first() {
+			Some(&transaction_type) if transaction_type < 0x80 => {
+				Ok((transaction_type, rlp::decode(&bytes[1..])?))
+			},
+			_ => Ok((0, rlp::decode(bytes)?)),
+		}
+	}
+}
+
+/// Encoded as the list of the outcome, the cumulative gas used, the bloom
+/// and the logs.
+impl rlp::Encodable for Receipt {
+	fn rlp_append(&self, s: &mut rlp::RlpStream) {
+		s.begin_list(4);
+		match self.outcome {
+			ReceiptOutcome::StateRoot(root) => s.append(&root),
+			ReceiptOutcome::Status(status) => s.append(&u8::from(status)),
+		};
+		s.append(&self.cumulative_gas_used);
+		s.append(&self.bloom);
+		s.append_list(&self.logs);
+	}
+}
+
+impl rlp::Decodable for Receipt {
+	fn decode(rlp: &rlp::Rlp) -> Result<Self, rlp::DecoderError> {
+		if rlp.item_count()? != 4 {
+			return Err(rlp::DecoderError::RlpIncorrectListLen)
+		}
+
+		let outcome = rlp.at(0)?;
+		let outcome = if outcome.size() == 32 {
+			ReceiptOutcome::StateRoot(outcome.as_val()?)
+		} else {
+			match outcome.as_val::<u8>()? {
+				0 => ReceiptOutcome::Status(false),
+				1 => ReceiptOutcome::Status(true),
+				_ => return Err(rlp::DecoderError::Custom("invalid receipt status")),
+			}
+		};
+
+		Ok(Self {
+			outcome,
+			cumulative_gas_used: rlp.val_at(1)?,
+			bloom: rlp.val_at(2)?,
+			logs: rlp.list_at(3)?,
+		})
+	}
+}
@@ -0,0 +1,173 @@
+//! # Reentrancy detection
+//!
+//! An event listener flagging reentrancy patterns from call and storage
+//! events, for security tooling: storage written by a contract after it
+//! called another one in the same frame, the checks-effects-interactions
+//! violation behind most reentrancy bugs, and contracts entered again while
+//! one of their frames is still running.
+//!
+//! Findings are patterns, not exploits. Frames that reverted are reported
+//! too, as the pattern is in the code whatever the outcome of the run.
+
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::fmt;
+use crate::{H160, U256};
+use crate::tracing::{Event, EventListener};
+
+/// Reentrancy pattern found in a transaction.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Finding {
+	/// A frame wrote its storage after calling another contract, which
+	/// could have called back before the write.
+	WriteAfterCall {
+		/// Contract whose storage is written.
+		address: H160,
+		/// Written storage index.
+		index: U256,
+		/// Last contract called by the frame before the write.
+		callee: H160,
+		/// Depth of the frame, the transaction frame having depth 1.
+		depth: usize,
+	},
+	/// A contract was entered while one of its frames was still running,
+	/// called back by another contract.
+	Reentry {
+		/// Contract entered again.
+		address: H160,
+		/// Contract calling back.
+		caller: H160,
+		/// Depth of the new frame.
+		depth: usize,
+	},
+}
+
+impl fmt::Display for Finding {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::WriteAfterCall { address, index, callee, depth } => write!(
+				f, "{address:?} writes slot {index:#x} after calling {callee:?} (depth {depth})",
+			),
+			Self::Reentry { address, caller, depth } => write!(
+				f, "{address:?} reentered from {caller:?} (depth {depth})",
+			),
+		}
+	}
+}
+
+/// Findings of a `ReentrancyDetector`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReentrancyReport {
+	/// Findings, in detection order.
+	pub findings: Vec<Finding>,
+}
+
+impl ReentrancyReport {
+	/// Whether nothing was found.
+	#[must_use]
+	pub const fn is_clean(&self) -> bool {
+		self.findings.is_empty()
+	}
+
+	/// Contracts with a finding, sorted.
+	#[must_use]
+	pub fn addresses(&self) -> BTreeSet<H160> {
+		self.findings.iter()
+			.map(|finding| match finding {
+				Finding::WriteAfterCall { address, .. } | Finding::Reentry { address, .. } => *address,
+			})
+			.collect()
+	}
+}
+
+impl fmt::Display for ReentrancyReport {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		writeln!(f, "## Reentrancy report")?;
+		writeln!(f)?;
+		if self.is_clean() {
+			return writeln!(f, "No finding.")
+		}
+		for finding in &self.findings {
+			writeln!(f, "- {finding}")?;
+		}
+		Ok(())
+	}
+}
+
+#[derive(Debug)]
+struct Frame {
+	/// Contract whose storage the frame runs with.
+	address: H160,
+	/// Last other contract called or created by the frame.
+	callee: Option<H160>,
+	/// Storage indices already reported for the frame.
+	reported: BTreeSet<U256>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+	frames: Vec<Frame>,
+	findings: Vec<Finding>,
+}
+
+impl State {
+	fn enter(&mut self, address: H160) {
+		if let Some(parent) = self.frames.last_mut() {
+			if parent.address != address {
+				parent.callee = Some(address);
+				let caller = parent.address;
+				if self.frames.iter().any(|frame| frame.address == address) {
+					self.findings.push(Finding::Reentry { address, caller, depth: self.frames.len() + 1 });
+				}
+			}
+		}
+		self.frames.push(Frame { address, callee: None, reported: BTreeSet::new() });
+	}
+}
+
+/// Event listener detecting reentrancy patterns, see the module
+/// documentation. It needs the call and storage events of `TraceConfig`.
+#[derive(Debug, Default)]
+pub struct ReentrancyDetector {
+	state: RefCell<State>,
+}
+
+impl ReentrancyDetector {
+	/// Create a new detector.
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Findings so far, across the transactions observed.
+	#[must_use]
+	pub fn report(&self) -> ReentrancyReport {
+		ReentrancyReport { findings: self.state.borrow().findings.clone() }
+	}
+}
+
+impl EventListener for ReentrancyDetector {
+	fn event(&self, event: Event<'_>) {
+		let mut state = self.state.borrow_mut();
+		match event {
+			// Delegated frames run with the storage of their caller.
+			Event::Call { context, .. } => state.enter(context.address),
+			Event::Create { address, .. } => state.enter(address),
+			Event::Exit { .. } => {
+				state.frames.pop();
+			},
+			Event::SetStorage { address, index, .. } => {
+				let depth = state.frames.len();
+				let Some(frame) = state.frames.last_mut() else { return };
+				let Some(callee) = frame.callee else { return };
+				if frame.address == address && frame.reported.insert(index) {
+					state.findings.push(Finding::WriteAfterCall { address, index, callee, depth });
+				}
+			},
+			Event::FrameGas { .. } | Event::Step { .. } => (),
+		}
+	}
+}
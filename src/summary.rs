@@ -0,0 +1,285 @@
+//! # Execution summary
+//!
+//! A human-readable rendering of an executed transaction, for command line
+//! tools and bug reports. The summary is built from the transaction result
+//! and from executor events, and is displayed as markdown.
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::convert::TryFrom;
+use core::fmt;
+use crate::{ExitReason, H160, U256};
+use crate::backend::Log;
+use crate::executor::Refund;
+use crate::tracing::{Event, EventListener};
+
+/// Number of return value bytes shown before it is truncated.
+const RETURN_VALUE_DISPLAY_LIMIT: usize = 64;
+
+#[derive(Debug, Default)]
+struct Frame {
+	/// Address of the created contract, for a create frame.
+	create: Option<H160>,
+	/// Contracts created by the frame and its succeeded children.
+	created: Vec<H160>,
+}
+
+#[derive(Debug, Default)]
+struct CallTree {
+	frames: Vec<Frame>,
+	created: Vec<H160>,
+	frame_count: u64,
+	max_depth: usize,
+}
+
+/// Event listener recording the call tree shape and the contracts created by
+/// a transaction.
+#[derive(Debug, Default)]
+pub struct CallTreeCollector {
+	tree: RefCell<CallTree>,
+}
+
+impl CallTreeCollector {
+	/// Create a new empty collector.
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Contracts created by frames that succeeded, along with all their
+	/// parents, in creation order.
+	#[must_use]
+	pub fn created(&self) -> Vec<H160> {
+		self.tree.borrow().created.clone()
+	}
+
+	/// Number of call and create frames entered.
+	#[must_use]
+	pub fn frame_count(&self) -> u64 {
+		self.tree.borrow().frame_count
+	}
+
+	/// Maximum nesting of frames, the transaction frame having depth 1.
+	#[must_use]
+	pub fn max_depth(&self) -> usize {
+		self.tree.borrow().max_depth
+	}
+
+	fn enter(&self, create: Option<H160>) {
+		let mut tree = self.tree.borrow_mut();
+		tree.frames.push(Frame { create, created: Vec::new() });
+		tree.frame_count += 1;
+		tree.max_depth = core::cmp::max(tree.max_depth, tree.frames.len());
+	}
+}
+
+impl EventListener for CallTreeCollector {
+	fn event(&self, event: Event<'_>) {
+		match event {
+			Event::Call { .. } => self.enter(None),
+			Event::Create { address, .. } => self.enter(Some(address)),
+			Event::Exit { reason, .. } => {
+				let mut tree = self.tree.borrow_mut();
+				let Some(frame) = tree.frames.pop() else { return };
+				if !reason.is_succeed() {
+					return
+				}
+
+				let mut created = frame.created;
+				if let Some(address) = frame.create {
+					created.insert(0, address);
+				}
+				match tree.frames.last_mut() {
+					Some(parent) => parent.created.append(&mut created),
+					None => tree.created.append(&mut created),
+				}
+			},
+			Event::FrameGas { .. } | Event::Step { .. } | Event::SetStorage { .. } => (),
+		}
+	}
+}
+
+/// Summary of an executed transaction.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ExecutionSummary {
+	/// Exit reason of the transaction.
+	pub reason: ExitReason,
+	/// Return value of the transaction.
+	pub return_value: Vec<u8>,
+	/// Gas limit of the transaction.
+	pub gas_limit: u64,
+	/// Gas used by the transaction, after refund.
+	pub used_gas: u64,
+	/// Refund of the transaction.
+	pub refund: Refund,
+	/// Contracts created by the transaction.
+	pub created: Vec<H160>,
+	/// Logs emitted by the transaction.
+	pub logs: Vec<Log>,
+	/// Number of call and create frames, if the call tree was traced.
+	pub frame_count: Option<u64>,
+	/// Maximum depth of the call tree, if it was traced.
+	pub max_depth: Option<usize>,
+}
+
+impl ExecutionSummary {
+	/// Create a summary of a transaction, without logs nor call tree.
+	#[must_use]
+	pub const fn new(
+		reason: ExitReason,
+		return_value: Vec<u8>,
+		gas_limit: u64,
+		used_gas: u64,
+		refund: Refund,
+	) -> Self {
+		Self {
+			reason,
+			return_value,
+			gas_limit,
+			used_gas,
+			refund,
+			created: Vec::new(),
+			logs: Vec::new(),
+			frame_count: None,
+			max_depth: None,
+		}
+	}
+
+	/// Set the logs emitted by the transaction.
+	#[must_use]
+	pub fn with_logs(mut self, logs: Vec<Log>) -> Self {
+		self.logs = logs;
+		self
+	}
+
+	/// Set the created contracts and call tree shape recorded by a collector.
+	#[must_use]
+	pub fn with_call_tree(mut self, collector: &CallTreeCollector) -> Self {
+		self.created = collector.created();
+		self.frame_count = Some(collector.frame_count());
+		self.max_depth = Some(collector.max_depth());
+		self
+	}
+
+	/// Gas used by the transaction, before refund.
+	#[must_use]
+	pub const fn used_gas_before_refund(&self) -> u64 {
+		self.used_gas + self.refund.effective_refund
+	}
+
+	/// Reason of a top-level revert, decoded from a Solidity `Error(string)`
+	/// or `Panic(uint256)` return value.
+	#[must_use]
+	pub fn revert_reason(&self) -> Option<String> {
+		if self.reason.is_revert() {
+			decode_revert_reason(&self.return_value)
+		} else {
+			None
+		}
+	}
+}
+
+#[cfg(feature = "software-keccak")]
+impl From<&crate::testing::TestReceipt> for ExecutionSummary {
+	fn from(receipt: &crate::testing::TestReceipt) -> Self {
+		Self {
+			created: receipt.contract_address.into_iter().collect(),
+			..Self::new(
+				receipt.reason,
+				receipt.return_value.clone(),
+				receipt.gas_limit,
+				receipt.used_gas,
+				receipt.refund,
+			).with_logs(receipt.logs.clone())
+		}
+	}
+}
+
+fn decode_word(data: &[u8], offset: usize) -> Option<usize> {
+	let word = data.get(offset..offset.checked_add(32)?)?;
+	usize::try_from(U256::from_big_endian(word)).ok()
+}
+
+/// Decode a Solidity `Error(string)` or `Panic(uint256)` revert value.
+#[must_use]
+pub fn decode_revert_reason(data: &[u8]) -> Option<String> {
+	let (selector, body) = (data.get(..4)?, &data[4..]);
+	match selector {
+		[0x08, 0xc3, 0x79, 0xa0] => {
+			let offset = decode_word(body, 0)?;
+			let len = decode_word(body, offset)?;
+			let start = offset.checked_add(32)?;
+			let bytes = body.get(start..start.checked_add(len)?)?;
+			Some(String::from_utf8_lossy(bytes).into_owned())
+		},
+		[0x4e, 0x48, 0x7b, 0x71] => {
+			let code = U256::from_big_endian(body.get(..32)?);
+			Some(alloc::format!("Panic(0x{code:x})"))
+		},
+		_ => None,
+	}
+}
+
+fn write_hex(f: &mut fmt::Formatter<'_>, data: &[u8]) -> fmt::Result {
+	write!(f, "0x")?;
+	for byte in data.iter().take(RETURN_VALUE_DISPLAY_LIMIT) {
+		write!(f, "{byte:02x}")?;
+	}
+	if data.len() > RETURN_VALUE_DISPLAY_LIMIT {
+		write!(f, "...")?;
+	}
+	Ok(())
+}
+
+impl fmt::Display for ExecutionSummary {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		writeln!(f, "## Execution summary")?;
+		writeln!(f)?;
+		writeln!(f, "- Status: {:?}", self.reason)?;
+		write!(f, "- Return value: ")?;
+		write_hex(f, &self.return_value)?;
+		writeln!(f, " ({} bytes)", self.return_value.len())?;
+		if let Some(reason) = self.revert_reason() {
+			writeln!(f, "- Revert reason: {reason:?}")?;
+		}
+		if let (Some(frame_count), Some(max_depth)) = (self.frame_count, self.max_depth) {
+			writeln!(f, "- Call tree: {frame_count} frames, max depth {max_depth}")?;
+		}
+
+		writeln!(f)?;
+		writeln!(f, "### Gas")?;
+		writeln!(f)?;
+		writeln!(f, "- Limit: {}", self.gas_limit)?;
+		writeln!(f, "- Used before refund: {}", self.used_gas_before_refund())?;
+		writeln!(
+			f, "- Refund: {} (raw {}, cap {})",
+			self.refund.effective_refund, self.refund.refunded_gas, self.refund.refund_cap,
+		)?;
+		writeln!(f, "- Used: {}", self.used_gas)?;
+
+		if !self.created.is_empty() {
+			writeln!(f)?;
+			writeln!(f, "### Created contracts")?;
+			writeln!(f)?;
+			for address in &self.created {
+				writeln!(f, "- {address:?}")?;
+			}
+		}
+
+		writeln!(f)?;
+		writeln!(f, "### Logs")?;
+		writeln!(f)?;
+		writeln!(f, "- Total: {}", self.logs.len())?;
+		let mut per_address = BTreeMap::<H160, usize>::new();
+		for log in &self.logs {
+			*per_address.entry(log.address).or_default() += 1;
+		}
+		for (address, count) in per_address {
+			writeln!(f, "- {address:?}: {count}")?;
+		}
+
+		Ok(())
+	}
+}
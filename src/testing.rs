@@ -0,0 +1,240 @@
+//! # Testing utilities
+//!
+//! A minimal in-process chain built on top of `MemoryBackend` and
+//! `StackExecutor`, for integration tests.
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use sha3::{Digest, Keccak256};
+use crate::{CreateScheme, ExitReason, H160, H256, U256};
+use crate::block::{BlockGas, GasLimitError};
+use crate::backend::{ApplyBackend, Log, MemoryAccount, MemoryBackend, MemoryVicinity, BLOCK_HASH_WINDOW};
+use crate::executor::{no_precompile, Refund, StackExecutor};
+use crate::{Istanbul, Spec};
+
+/// Receipt of a transaction executed on a `TestChain`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TestReceipt {
+	/// Number of the block including the transaction.
+	pub block_number: U256,
+	/// Index of the transaction in its block.
+	pub transaction_index: usize,
+	/// Exit reason of the transaction.
+	pub reason: ExitReason,
+	/// Return value of the transaction.
+	pub return_value: Vec<u8>,
+	/// Created contract address, for a succeeded create transaction.
+	pub contract_address: Option<H160>,
+	/// Gas limit of the transaction.
+	pub gas_limit: u64,
+	/// Gas used by the transaction, after refund.
+	pub used_gas: u64,
+	/// Gas used in the block up to and including the transaction.
+	pub cumulative_gas_used: u64,
+	/// Refund of the transaction.
+	pub refund: Refund,
+	/// Logs emitted by the transaction.
+	pub logs: Vec<Log>,
+}
+
+/// In-memory chain, producing blocks with a fixed block time and executing
+/// transactions with the rules of the hard fork `S`.
+#[derive(Clone, Debug)]
+pub struct TestChain<S = Istanbul> {
+	vicinity: MemoryVicinity,
+	state: BTreeMap<H160, MemoryAccount>,
+	receipts: Vec<TestReceipt>,
+	block_time: U256,
+	block_gas: BlockGas,
+	spec: PhantomData<S>,
+}
+
+impl TestChain {
+	/// Create a new Istanbul chain starting at the given vicinity and state.
+	#[must_use]
+	pub fn new(
+		vicinity: MemoryVicinity,
+		state: BTreeMap<H160, MemoryAccount>,
+		block_time: U256,
+	) -> Self {
+		Self::new_with_spec(vicinity, state, block_time, Istanbul)
+	}
+}
+
+impl<S: Spec> TestChain<S> {
+	/// Create a new chain of the given hard fork, starting at the given
+	/// vicinity and state.
+	#[must_use]
+	pub fn new_with_spec(
+		vicinity: MemoryVicinity,
+		state: BTreeMap<H160, MemoryAccount>,
+		block_time: U256,
+		_spec: S,
+	) -> Self {
+		let block_gas = BlockGas::new(vicinity.block_gas_limit);
+		Self {
+			vicinity,
+			state,
+			receipts: Vec::new(),
+			block_time,
+			block_gas,
+			spec: PhantomData,
+		}
+	}
+
+	/// Limit the gas limit of a single transaction.
+	#[must_use]
+	pub const fn with_max_transaction_gas_limit(mut self, max_gas_limit: u64) -> Self {
+		self.block_gas = self.block_gas.with_max_transaction_gas_limit(max_gas_limit);
+		self
+	}
+
+	/// Vicinity of the block currently being built.
+	#[must_use]
+	pub const fn vicinity(&self) -> &MemoryVicinity {
+		&self.vicinity
+	}
+
+	/// Current state.
+	#[must_use]
+	pub const fn state(&self) -> &BTreeMap<H160, MemoryAccount> {
+		&self.state
+	}
+
+	/// Number of the block currently being built.
+	#[must_use]
+	pub const fn block_number(&self) -> U256 {
+		self.vicinity.block_number
+	}
+
+	/// Gas accounting of the block currently being built.
+	#[must_use]
+	pub const fn block_gas(&self) -> &BlockGas {
+		&self.block_gas
+	}
+
+	/// Seal the current block and start building the next one.
+	pub fn mine(&mut self) -> H256 {
+		let mut number = [0_u8; 32];
+		self.vicinity.block_number.to_big_endian(&mut number);
+		let hash = H256::from_slice(Keccak256::digest(&number).as_slice());
+
+		self.vicinity.block_hashes.insert(0, hash);
+		self.vicinity.block_hashes.truncate(BLOCK_HASH_WINDOW);
+		self.vicinity.block_number += U256::one();
+		self.vicinity.block_timestamp += self.block_time;
+		self.block_gas.reset(self.vicinity.block_gas_limit);
+
+		hash
+	}
+
+	/// Execute a `CALL` transaction in the current block, if its gas limit
+	/// fits in the block.
+	pub fn transact_call(
+		&mut self,
+		caller: H160,
+		address: H160,
+		value: U256,
+		data: Vec<u8>,
+		gas_limit: u64,
+	) -> Result<&TestReceipt, GasLimitError> {
+		self.transact(gas_limit, |executor| {
+			let (reason, return_value) = executor.transact_call(
+				caller, address, value, data, gas_limit,
+			);
+			(reason, return_value, None)
+		})
+	}
+
+	/// Execute a `CREATE` transaction in the current block, if its gas limit
+	/// fits in the block.
+	pub fn transact_create(
+		&mut self,
+		caller: H160,
+		value: U256,
+		init_code: Vec<u8>,
+		gas_limit: u64,
+	) -> Result<&TestReceipt, GasLimitError> {
+		self.transact(gas_limit, |executor| {
+			let address = executor.create_address(CreateScheme::Legacy { caller });
+			let reason = executor.transact_create(caller, value, init_code, gas_limit);
+			let address = if reason.is_succeed() { Some(address) } else { None };
+			(reason, Vec::new(), address)
+		})
+	}
+
+	fn transact<F>(&mut self, gas_limit: u64, f: F) -> Result<&TestReceipt, GasLimitError> where
+		F: FnOnce(&mut StackExecutor<MemoryBackend, S>) -> (ExitReason, Vec<u8>, Option<H160>),
+	{
+		self.block_gas.validate(gas_limit)?;
+
+		let state = core::mem::take(&mut self.state);
+		let mut backend = MemoryBackend::new(&self.vicinity, state);
+
+		let mut executor = StackExecutor::new_with_spec(&backend, gas_limit, no_precompile, S::default());
+		let (reason, return_value, contract_address) = f(&mut executor);
+		let used_gas = executor.used_gas();
+		let refund = executor.refund();
+		let (applies, logs) = executor.deconstruct();
+
+		backend.apply(applies, logs.clone(), S::CONFIG.clear_touched_empty);
+		self.state = backend.into_state();
+		self.block_gas.record(used_gas);
+
+		let transaction_index = self.receipts.iter()
+			.filter(|r| r.block_number == self.vicinity.block_number)
+			.count();
+		self.receipts.push(TestReceipt {
+			block_number: self.vicinity.block_number,
+			transaction_index,
+			reason,
+			return_value,
+			contract_address,
+			gas_limit,
+			used_gas,
+			cumulative_gas_used: self.block_gas.gas_used(),
+			refund,
+			logs,
+		});
+
+		Ok(&self.receipts[self.receipts.len() - 1])
+	}
+
+	/// All receipts, in execution order.
+	#[must_use]
+	pub fn receipts(&self) -> &[TestReceipt] {
+		&self.receipts
+	}
+
+	/// Receipts of transactions included in the given block.
+	pub fn block_receipts(&self, number: U256) -> impl Iterator<Item=&TestReceipt> {
+		self.receipts.iter().filter(move |r| r.block_number == number)
+	}
+
+	/// Get account balance.
+	#[must_use]
+	pub fn balance(&self, address: H160) -> U256 {
+		self.state.get(&address).map_or_else(U256::zero, |a| a.balance)
+	}
+
+	/// Get account nonce.
+	#[must_use]
+	pub fn nonce(&self, address: H160) -> U256 {
+		self.state.get(&address).map_or_else(U256::zero, |a| a.nonce)
+	}
+
+	/// Get account code.
+	#[must_use]
+	pub fn code(&self, address: H160) -> &[u8] {
+		self.state.get(&address).map_or(&[][..], |a| &a.code[..])
+	}
+
+	/// Get storage value of address at index.
+	#[must_use]
+	pub fn storage(&self, address: H160, index: U256) -> U256 {
+		self.state.get(&address)
+			.and_then(|a| a.storage.get(&index).copied())
+			.unwrap_or_default()
+	}
+}
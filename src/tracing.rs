@@ -0,0 +1,278 @@
+//! # EVM tracing
+//!
+//! Events emitted by the executor while it runs, for debuggers, profilers and
+//! metrics collectors.
+
+use alloc::vec::Vec;
+use crate::{Context, CreateScheme, ExitReason, Memory, Opcode, Stack, Transfer, H160, H256, U256};
+
+/// Listener of executor events.
+///
+/// The listener is shared by all call frames of an executor, so
+/// implementations needing to mutate their own state should rely on interior
+/// mutability.
+pub trait EventListener {
+	/// Handle an event.
+	fn event(&self, event: Event<'_>);
+}
+
+/// Categories of events emitted by the executor, see
+/// `StackExecutor::with_trace_config`. Disabled categories cost nothing to
+/// the execution.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct TraceConfig {
+	/// Emit `Step` events.
+	pub steps: bool,
+	/// Include the memory in `Step` events.
+	pub memory: bool,
+	/// Include the stack in `Step` events.
+	pub stack: bool,
+	/// Emit `SetStorage` events.
+	pub storage: bool,
+	/// Emit `FrameGas` events.
+	pub gas: bool,
+	/// Emit `Call`, `Create` and `Exit` events.
+	pub calls: bool,
+}
+
+impl TraceConfig {
+	/// Emit every event, with memory and stack.
+	#[must_use]
+	pub const fn all() -> Self {
+		Self { steps: true, memory: true, stack: true, storage: true, gas: true, calls: true }
+	}
+
+	/// Emit call, create and exit events only.
+	#[must_use]
+	pub const fn calls() -> Self {
+		Self { steps: false, memory: false, stack: false, storage: false, gas: false, calls: true }
+	}
+
+	/// Whether `event` belongs to an enabled category.
+	#[must_use]
+	pub const fn enabled(&self, event: &Event<'_>) -> bool {
+		match event {
+			Event::Call { .. } | Event::Create { .. } | Event::Exit { .. } => self.calls,
+			Event::FrameGas { .. } => self.gas,
+			Event::Step { .. } => self.steps,
+			Event::SetStorage { .. } => self.storage,
+		}
+	}
+}
+
+impl Default for TraceConfig {
+	fn default() -> Self {
+		Self::all()
+	}
+}
+
+/// Stack of a `Step` event, borrowed from the running frame so that
+/// listeners only copy the values they need.
+#[derive(Clone, Copy, Debug)]
+pub struct StackSnapshot<'a>(&'a Stack);
+
+impl<'a> StackSnapshot<'a> {
+	/// Snapshot of `stack`.
+	#[must_use]
+	pub const fn new(stack: &'a Stack) -> Self {
+		Self(stack)
+	}
+
+	/// Number of values in the stack.
+	#[must_use]
+	pub const fn len(&self) -> usize {
+		self.0.len()
+	}
+
+	/// Whether the stack is empty.
+	#[must_use]
+	pub const fn is_empty(&self) -> bool {
+		self.0.len() == 0
+	}
+
+	/// Value at the given index from the top, the top being at `0`.
+	#[must_use]
+	pub fn peek(&self, no_from_top: usize) -> Option<U256> {
+		self.0.peek(no_from_top).ok()
+	}
+
+	/// The `n` top values, or all of them if the stack is shorter, the top
+	/// being the last one.
+	#[must_use]
+	pub fn top(&self, n: usize) -> &'a [U256] {
+		let data = self.0.data();
+		&data[data.len().saturating_sub(n)..]
+	}
+
+	/// Copy all the values, the top being the last one.
+	#[must_use]
+	pub fn to_vec(&self) -> Vec<U256> {
+		self.0.data().to_vec()
+	}
+}
+
+/// Memory of a `Step` event, borrowed from the running frame so that
+/// listeners only copy the ranges they need.
+#[derive(Clone, Copy, Debug)]
+pub struct MemorySnapshot<'a>(&'a Memory);
+
+impl<'a> MemorySnapshot<'a> {
+	/// Snapshot of `memory`.
+	#[must_use]
+	pub const fn new(memory: &'a Memory) -> Self {
+		Self(memory)
+	}
+
+	/// Length of the written region.
+	#[must_use]
+	pub const fn len(&self) -> usize {
+		self.0.len()
+	}
+
+	/// Whether nothing has been written.
+	#[must_use]
+	pub const fn is_empty(&self) -> bool {
+		self.0.is_empty()
+	}
+
+	/// Bytes of the given range, `None` if it exceeds the written region.
+	#[must_use]
+	pub fn slice(&self, offset: usize, size: usize) -> Option<&'a [u8]> {
+		self.0.data().get(offset..offset.checked_add(size)?)
+	}
+
+	/// Copy the given range, padded with zeros past the written region, as
+	/// the EVM reads it. Only the written part is copied from the memory,
+	/// but the returned buffer is `size` bytes long.
+	#[must_use]
+	pub fn read(&self, offset: usize, size: usize) -> Vec<u8> {
+		self.0.get(offset, size)
+	}
+
+	/// Copy the whole written region.
+	#[must_use]
+	pub fn to_vec(&self) -> Vec<u8> {
+		self.0.data().to_vec()
+	}
+}
+
+/// Gas accounting of an exited call or create frame.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct FrameGasUsage {
+	/// Gas forwarded by the calling frame, after the gas forwarding policy.
+	pub forwarded: u64,
+	/// Stipend added on top of the forwarded gas.
+	pub stipend: u64,
+	/// Gas used by the frame, out of `forwarded + stipend`.
+	pub used: u64,
+	/// Unused gas given back to the calling frame, zero if the frame failed.
+	pub returned: u64,
+}
+
+impl FrameGasUsage {
+	/// Gas limit of the frame, including the stipend.
+	#[must_use]
+	pub const fn gas_limit(&self) -> u64 {
+		self.forwarded.saturating_add(self.stipend)
+	}
+}
+
+/// Executor event.
+#[derive(Clone, Copy, Debug)]
+pub enum Event<'a> {
+	/// A call frame is entered.
+	Call {
+		/// Address of the code being called.
+		code_address: H160,
+		/// Value transfer of the call.
+		transfer: &'a Option<Transfer>,
+		/// Call input.
+		input: &'a [u8],
+		/// Target gas.
+		target_gas: Option<u64>,
+		/// Whether the call is static.
+		is_static: bool,
+		/// Call context.
+		context: &'a Context,
+	},
+	/// A create frame is entered.
+	Create {
+		/// Caller of the create.
+		caller: H160,
+		/// Address of the created contract, resolved from the scheme with the
+		/// backend hasher.
+		address: H160,
+		/// Create scheme.
+		scheme: CreateScheme,
+		/// Value endowed to the created contract.
+		value: U256,
+		/// Init code.
+		init_code: &'a [u8],
+		/// Hash of the init code, computed by the backend hasher.
+		code_hash: H256,
+		/// Target gas.
+		target_gas: Option<u64>,
+	},
+	/// The gas forwarded to the last entered call or create frame has been
+	/// computed. Not emitted for frames rejected before, such as those
+	/// exceeding the call depth limit.
+	FrameGas {
+		/// Gas left in the parent frame before forwarding.
+		gas: u64,
+		/// Gas withheld from the parent by the gas forwarding policy, the all
+		/// but one 64th rule in Ethereum.
+		l64_reduction: u64,
+		/// Gas available for forwarding, `gas - l64_reduction`.
+		after_gas: u64,
+		/// Gas requested by the caller, `None` for all available gas.
+		target_gas: Option<u64>,
+		/// Stipend added on top of the forwarded gas for calls transferring
+		/// value.
+		stipend: u64,
+		/// Gas limit of the child frame, including the stipend.
+		gas_limit: u64,
+	},
+	/// The last entered call or create frame exited.
+	Exit {
+		/// Exit reason.
+		reason: &'a ExitReason,
+		/// Return value.
+		return_value: &'a [u8],
+		/// Gas left in the calling frame once the unused gas of the exited
+		/// frame is given back. The gas used by the frame is the `gas` of its
+		/// `FrameGas` event less this, plus the stipend.
+		gas: u64,
+		/// Gas accounting of the frame, `None` for frames rejected before
+		/// their gas was forwarded.
+		frame_gas: Option<FrameGasUsage>,
+	},
+	/// An opcode is about to be executed, and its gas cost has been recorded.
+	Step {
+		/// Context of the running frame.
+		context: &'a Context,
+		/// Opcode to be executed.
+		opcode: Opcode,
+		/// Program counter of the opcode.
+		position: usize,
+		/// Stack before the opcode is executed, `None` if disabled by
+		/// `TraceConfig::stack`.
+		stack: Option<StackSnapshot<'a>>,
+		/// Memory before the opcode is executed, `None` if disabled by
+		/// `TraceConfig::memory`.
+		memory: Option<MemorySnapshot<'a>>,
+		/// Gas cost of the opcode, including memory expansion.
+		gas_cost: u64,
+		/// Gas left after the cost is recorded.
+		gas_left: u64,
+	},
+	/// A storage slot has been written by `SSTORE`.
+	SetStorage {
+		/// Address of the account.
+		address: H160,
+		/// Storage index.
+		index: U256,
+		/// Written value.
+		value: U256,
+	},
+}
@@ -0,0 +1,441 @@
+//! # Transactions
+//!
+//! Decoding of signed Ethereum transaction envelopes, legacy ones and the
+//! EIP-2718 typed ones of EIP-2930 and EIP-1559, to run them with
+//! `StackExecutor::transact_raw`.
+//!
+//! Senders are recovered from signatures through the `SenderRecovery` trait,
+//! from the signing hash computed here. The `secp256k1` feature provides an
+//! implementation, `Secp256k1`, on top of the `k256` crate.
+
+use alloc::vec::Vec;
+use crate::{Keccak, Spec, H160, H256, U256};
+use crate::access_list::AccessList;
+use crate::executor::TransactionValidationError;
+use crate::gasometer::{self, TransactionCost};
+
+/// Intrinsic gas of each address of an access list (EIP-2930).
+pub const ACCESS_LIST_ADDRESS_GAS: u64 = 2400;
+/// Intrinsic gas of each storage key of an access list (EIP-2930).
+pub const ACCESS_LIST_STORAGE_KEY_GAS: u64 = 1900;
+
+/// EIP-2718 type of a transaction.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TransactionType {
+	/// Legacy transaction, before EIP-2718.
+	Legacy,
+	/// Transaction with an access list (EIP-2930), of type 1.
+	AccessList,
+	/// Transaction with a base fee and a priority fee (EIP-1559), of type 2.
+	DynamicFee,
+}
+
+impl TransactionType {
+	/// Type byte of the envelope, 0 for legacy transactions.
+	#[must_use]
+	pub const fn as_u8(self) -> u8 {
+		match self {
+			Self::Legacy => 0,
+			Self::AccessList => 1,
+			Self::DynamicFee => 2,
+		}
+	}
+}
+
+/// Recipient of a transaction.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TransactionAction {
+	/// Call of the given address.
+	Call(H160),
+	/// Creation of a contract.
+	Create,
+}
+
+/// Signature of a transaction.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Signature {
+	/// Parity of the y coordinate of the curve point.
+	pub odd_y_parity: bool,
+	/// `r` value.
+	pub r: U256,
+	/// `s` value.
+	pub s: U256,
+}
+
+/// Recovery of the sender of a transaction from its signature.
+pub trait SenderRecovery {
+	/// Address whose key signed `hash` with `signature`, `None` if the
+	/// signature is invalid.
+	fn recover(&self, hash: H256, signature: &Signature) -> Option<H160>;
+}
+
+impl<F: Fn(H256, &Signature) -> Option<H160>> SenderRecovery for F {
+	fn recover(&self, hash: H256, signature: &Signature) -> Option<H160> {
+		self(hash, signature)
+	}
+}
+
+/// Recovery of senders on the secp256k1 curve, hashing public keys with the
+/// wrapped `Keccak`. Signatures with an `s` value above half the curve order
+/// are rejected, as they are since Homestead (EIP-2).
+#[cfg(feature = "secp256k1")]
+#[derive(Clone, Copy, Debug)]
+pub struct Secp256k1<'k, K: ?Sized>(pub &'k K);
+
+#[cfg(feature = "secp256k1")]
+impl<K: Keccak + ?Sized> SenderRecovery for Secp256k1<'_, K> {
+	fn recover(&self, hash: H256, signature: &Signature) -> Option<H160> {
+		use k256::ecdsa::{RecoveryId, Signature as EcdsaSignature, VerifyingKey};
+
+		let mut r = [0_u8; 32];
+		let mut s = [0_u8; 32];
+		signature.r.to_big_endian(&mut r);
+		signature.s.to_big_endian(&mut s);
+		let ecdsa = EcdsaSignature::from_scalars(r, s).ok()?;
+		if ecdsa.normalize_s().is_some() {
+			return None
+		}
+		let recovery_id = RecoveryId::new(signature.odd_y_parity, false);
+		let key = VerifyingKey::recover_from_prehash(hash.as_bytes(), &ecdsa, recovery_id).ok()?;
+		let public = key.to_encoded_point(false);
+		Some(H160::from(self.0.keccak256_h256(&public.as_bytes()[1..])))
+	}
+}
+
+/// Recover the sender of `transaction` from its signature, see
+/// `Secp256k1`.
+#[cfg(feature = "secp256k1")]
+pub fn recover_sender<K: Keccak + ?Sized>(keccak: &K, transaction: &Transaction) -> Result<H160, TransactionError> {
+	transaction.recover_sender(keccak, &Secp256k1(keccak))
+}
+
+/// Reason a raw transaction cannot be executed, see
+/// `StackExecutor::transact_raw`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TransactionError {
+	/// Malformed envelope.
+	Decode(rlp::DecoderError),
+	/// EIP-2718 transaction type unknown, or not activated by the hard fork
+	/// of the executor.
+	UnsupportedType(u8),
+	/// Signature with an invalid `v` value, or from which no sender is
+	/// recovered.
+	InvalidSignature,
+	/// Transaction signed for another chain.
+	ChainIdMismatch {
+		/// Chain ID of the backend.
+		expected: U256,
+		/// Chain ID of the transaction.
+		actual: u64,
+	},
+	/// Chain ID too large for the `v` value of a legacy signature, twice the
+	/// chain ID plus 35 or 36 (EIP-155), to fit in a `u64`.
+	ChainIdOverflow,
+	/// Gas limit above `u64::MAX`.
+	GasLimitOverflow,
+	/// Maximum fee per gas below the base fee of the executor.
+	FeeCapTooLow {
+		/// Maximum fee per gas, or gas price.
+		max_fee_per_gas: U256,
+		/// Base fee.
+		base_fee: U256,
+	},
+	/// Maximum priority fee per gas above the maximum fee per gas.
+	PriorityFeeAboveFeeCap,
+	/// Transaction invalid against the current state.
+	Invalid(TransactionValidationError),
+}
+
+impl From<rlp::DecoderError> for TransactionError {
+	fn from(e: rlp::DecoderError) -> Self {
+		Self::Decode(e)
+	}
+}
+
+impl From<TransactionValidationError> for TransactionError {
+	fn from(e: TransactionValidationError) -> Self {
+		Self::Invalid(e)
+	}
+}
+
+/// Signed transaction.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Transaction {
+	/// Type of the transaction.
+	pub transaction_type: TransactionType,
+	/// Chain ID, `None` for legacy transactions predating EIP-155.
+	pub chain_id: Option<u64>,
+	/// Nonce of the sender.
+	pub nonce: U256,
+	/// Gas price, or maximum fee per gas for EIP-1559 transactions.
+	pub gas_price: U256,
+	/// Maximum priority fee per gas of EIP-1559 transactions.
+	pub max_priority_fee_per_gas: Option<U256>,
+	/// Gas limit.
+	pub gas_limit: U256,
+	/// Recipient.
+	pub action: TransactionAction,
+	/// Value transferred.
+	pub value: U256,
+	/// Call input, or init code.
+	pub input: Vec<u8>,
+	/// Access list, empty for legacy transactions.
+	pub access_list: AccessList,
+	/// Signature.
+	pub signature: Signature,
+}
+
+impl Transaction {
+	/// Decode a transaction envelope: the RLP list of a legacy transaction,
+	/// or the type byte followed by the RLP list of a typed one.
+	pub fn decode(raw: &[u8]) -> Result<Self, TransactionError> {
+		match raw.first() {
+			Some(&0x01) => Self::decode_typed(TransactionType::AccessList, &whole_rlp(&raw[1..])?),
+			Some(&0x02) => Self::decode_typed(TransactionType::DynamicFee, &whole_rlp(&raw[1..])?),
+			Some(&transaction_type) if transaction_type < 0x80 => {
+				Err(TransactionError::UnsupportedType(transaction_type))
+			},
+			_ => Self::decode_legacy(&whole_rlp(raw)?),
+		}
+	}
+
+	fn decode_legacy(rlp: &rlp::Rlp) -> Result<Self, TransactionError> {
+		if rlp.item_count()? != 9 {
+			return Err(rlp::DecoderError::RlpIncorrectListLen.into())
+		}
+
+		let v: u64 = rlp.val_at(6)?;
+		let (chain_id, odd_y_parity) = match v {
+			27 | 28 => (None, v == 28),
+			35.. => (Some((v - 35) / 2), (v - 35) % 2 == 1),
+			_ => return Err(TransactionError::InvalidSignature),
+		};
+
+		Ok(Self {
+			transaction_type: TransactionType::Legacy,
+			chain_id,
+			nonce: rlp.val_at(0)?,
+			gas_price: rlp.val_at(1)?,
+			max_priority_fee_per_gas: None,
+			gas_limit: rlp.val_at(2)?,
+			action: decode_action(&rlp.at(3)?)?,
+			value: rlp.val_at(4)?,
+			input: rlp.val_at(5)?,
+			access_list: AccessList::new(),
+			signature: Signature { odd_y_parity, r: rlp.val_at(7)?, s: rlp.val_at(8)? },
+		})
+	}
+
+	fn decode_typed(transaction_type: TransactionType, rlp: &rlp::Rlp) -> Result<Self, TransactionError> {
+		let dynamic_fee = transaction_type == TransactionType::DynamicFee;
+		let fields = if dynamic_fee { 12 } else { 11 };
+		if rlp.item_count()? != fields {
+			return Err(rlp::DecoderError::RlpIncorrectListLen.into())
+		}
+		// Fields after the fees are shifted by one in EIP-1559 transactions.
+		let shift = usize::from(dynamic_fee);
+
+		let odd_y_parity = match rlp.val_at::<u8>(8 + shift)? {
+			0 => false,
+			1 => true,
+			_ => return Err(TransactionError::InvalidSignature),
+		};
+		let (max_priority_fee_per_gas, gas_price) = if dynamic_fee {
+			(Some(rlp.val_at(2)?), rlp.val_at(3)?)
+		} else {
+			(None, rlp.val_at(2)?)
+		};
+
+		Ok(Self {
+			transaction_type,
+			chain_id: Some(rlp.val_at(0)?),
+			nonce: rlp.val_at(1)?,
+			gas_price,
+			max_priority_fee_per_gas,
+			gas_limit: rlp.val_at(3 + shift)?,
+			action: decode_action(&rlp.at(4 + shift)?)?,
+			value: rlp.val_at(5 + shift)?,
+			input: rlp.val_at(6 + shift)?,
+			access_list: decode_access_list(&rlp.at(7 + shift)?)?,
+			signature: Signature { odd_y_parity, r: rlp.val_at(9 + shift)?, s: rlp.val_at(10 + shift)? },
+		})
+	}
+
+	/// `v` value of the signature: the y parity for typed transactions, the
+	/// y parity plus 27, or plus twice the chain ID and 35 with EIP-155, for
+	/// legacy ones.
+	fn v(&self) -> Result<u64, TransactionError> {
+		let parity = u64::from(self.signature.odd_y_parity);
+		match (self.transaction_type, self.chain_id) {
+			(TransactionType::Legacy, None) => Ok(27 + parity),
+			(TransactionType::Legacy, Some(chain_id)) => chain_id.checked_mul(2)
+				.and_then(|v| v.checked_add(35 + parity))
+				.ok_or(TransactionError::ChainIdOverflow),
+			(TransactionType::AccessList | TransactionType::DynamicFee, _) => Ok(parity),
+		}
+	}
+
+	/// Append the fields of the transaction to `s`, with the signature if
+	/// its `v` value is given, or with the chain ID in place of the
+	/// signature of an EIP-155 legacy transaction otherwise.
+	fn rlp_append_fields(&self, s: &mut rlp::RlpStream, v: Option<u64>) {
+		let signed = v.is_some();
+		match self.transaction_type {
+			TransactionType::Legacy => {
+				let eip155 = !signed && self.chain_id.is_some();
+				s.begin_list(if signed || eip155 { 9 } else { 6 });
+				s.append(&self.nonce);
+				s.append(&self.gas_price);
+				s.append(&self.gas_limit);
+				append_action(s, self.action);
+				s.append(&self.value);
+				s.append(&self.input);
+				if let Some(v) = v {
+					s.append(&v);
+				} else if let Some(chain_id) = self.chain_id {
+					s.append(&chain_id);
+					s.append(&0_u8);
+					s.append(&0_u8);
+				}
+			},
+			TransactionType::AccessList | TransactionType::DynamicFee => {
+				let dynamic_fee = self.transaction_type == TransactionType::DynamicFee;
+				s.begin_list(8 + usize::from(dynamic_fee) + if signed { 3 } else { 0 });
+				s.append(&self.chain_id.unwrap_or_default());
+				s.append(&self.nonce);
+				if dynamic_fee {
+					s.append(&self.max_priority_fee_per_gas.unwrap_or_default());
+				}
+				s.append(&self.gas_price);
+				s.append(&self.gas_limit);
+				append_action(s, self.action);
+				s.append(&self.value);
+				s.append(&self.input);
+				s.begin_list(self.access_list.len());
+				for (address, keys) in &self.access_list {
+					s.begin_list(2);
+					s.append(address);
+					s.append_list(keys);
+				}
+				if let Some(v) = v {
+					s.append(&v);
+				}
+			},
+		}
+		if signed {
+			s.append(&self.signature.r);
+			s.append(&self.signature.s);
+		}
+	}
+
+	fn envelope(&self, v: Option<u64>) -> Vec<u8> {
+		let mut s = rlp::RlpStream::new();
+		self.rlp_append_fields(&mut s, v);
+		let rlp = s.out();
+
+		let mut envelope = Vec::with_capacity(1 + rlp.len());
+		if self.transaction_type != TransactionType::Legacy {
+			envelope.push(self.transaction_type.as_u8());
+		}
+		envelope.extend_from_slice(&rlp);
+		envelope
+	}
+
+	/// Encode the transaction envelope, as decoded by `decode`.
+	pub fn encode(&self) -> Result<Vec<u8>, TransactionError> {
+		Ok(self.envelope(Some(self.v()?)))
+	}
+
+	/// Hash of the transaction.
+	pub fn hash<K: Keccak + ?Sized>(&self, keccak: &K) -> Result<H256, TransactionError> {
+		Ok(keccak.keccak256_h256(&self.encode()?))
+	}
+
+	/// Hash signed by the sender.
+	pub fn signing_hash<K: Keccak + ?Sized>(&self, keccak: &K) -> H256 {
+		keccak.keccak256_h256(&self.envelope(None))
+	}
+
+	/// Recover the sender of the transaction.
+	pub fn recover_sender<K, R>(&self, keccak: &K, recovery: &R) -> Result<H160, TransactionError>
+	where
+		K: Keccak + ?Sized,
+		R: SenderRecovery + ?Sized,
+	{
+		recovery.recover(self.signing_hash(keccak), &self.signature).ok_or(TransactionError::InvalidSignature)
+	}
+
+	/// Intrinsic gas cost of the data of the transaction.
+	#[must_use]
+	pub fn transaction_cost(&self) -> TransactionCost {
+		match self.action {
+			TransactionAction::Call(_) => gasometer::call_transaction_cost(&self.input),
+			TransactionAction::Create => gasometer::create_transaction_cost(&self.input),
+		}
+	}
+
+	/// Intrinsic gas of the transaction for the hard fork `S`, access list
+	/// included.
+	#[must_use]
+	pub fn intrinsic_gas<S: Spec>(&self) -> u64 {
+		let keys = self.access_list.iter().map(|(_, keys)| keys.len() as u64).sum::<u64>();
+		gasometer::intrinsic_gas::<S>(&self.transaction_cost())
+			.saturating_add(self.access_list.len() as u64 * ACCESS_LIST_ADDRESS_GAS)
+			.saturating_add(keys * ACCESS_LIST_STORAGE_KEY_GAS)
+	}
+
+	/// Gas price paid with the given base fee: the gas price of legacy and
+	/// EIP-2930 transactions, the base fee plus the priority fee, capped by
+	/// the maximum fee, for EIP-1559 ones.
+	pub fn effective_gas_price(&self, base_fee: U256) -> Result<U256, TransactionError> {
+		if self.gas_price < base_fee {
+			return Err(TransactionError::FeeCapTooLow { max_fee_per_gas: self.gas_price, base_fee })
+		}
+
+		match self.max_priority_fee_per_gas {
+			None => Ok(self.gas_price),
+			Some(priority_fee) if priority_fee > self.gas_price => Err(TransactionError::PriorityFeeAboveFeeCap),
+			Some(priority_fee) => Ok(core::cmp::min(self.gas_price, base_fee.saturating_add(priority_fee))),
+		}
+	}
+}
+
+/// RLP item spanning the whole of `bytes`, so that non-canonical encodings
+/// with trailing bytes are rejected.
+fn whole_rlp(bytes: &[u8]) -> Result<rlp::Rlp<'_>, rlp::DecoderError> {
+	let info = rlp::Rlp::new(bytes).payload_info()?;
+	if info.header_len + info.value_len != bytes.len() {
+		return Err(rlp::DecoderError::RlpIsTooBig)
+	}
+	Ok(rlp::Rlp::new(bytes))
+}
+
+fn decode_action(rlp: &rlp::Rlp) -> Result<TransactionAction, rlp::DecoderError> {
+	if rlp.is_empty() {
+		Ok(TransactionAction::Create)
+	} else {
+		Ok(TransactionAction::Call(rlp.as_val()?))
+	}
+}
+
+fn append_action(s: &mut rlp::RlpStream, action: TransactionAction) {
+	match action {
+		TransactionAction::Call(address) => s.append(&address),
+		TransactionAction::Create => s.append_empty_data(),
+	};
+}
+
+fn decode_access_list(rlp: &rlp::Rlp) -> Result<AccessList, rlp::DecoderError> {
+	rlp.iter()
+		.map(|item| {
+			if item.item_count()? != 2 {
+				return Err(rlp::DecoderError::RlpIncorrectListLen)
+			}
+			Ok((item.val_at(0)?, item.list_at(1)?))
+		})
+		.collect()
+}
@@ -0,0 +1,207 @@
+//! # WebAssembly bindings
+//!
+//! A minimal JavaScript-facing API running a single transaction against a
+//! JSON state map, for browser based education and debugging tools.
+//!
+//! The transaction is a JSON object:
+//!
+//! ```json
+//! {
+//!   "state": { "0x11..11": { "balance": "0x0", "nonce": "0x0", "code": "0x", "storage": {} } },
+//!   "caller": "0x11..11",
+//!   "to": "0x22..22",
+//!   "value": "0x0",
+//!   "data": "0x",
+//!   "gasLimit": 1000000
+//! }
+//! ```
+//!
+//! A missing `to` runs `data` as init code of a create. Quantities are hex
+//! with a `0x` prefix or decimal.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::string::{String, ToString};
+use std::vec::Vec;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use wasm_bindgen::prelude::*;
+use crate::{CreateScheme, H160, H256, U256};
+use crate::backend::{Apply, Log, MemoryAccount, MemoryBackend, MemoryVicinity};
+use crate::executor::StackExecutor;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Account {
+	#[serde(default)]
+	balance: Option<String>,
+	#[serde(default)]
+	nonce: Option<String>,
+	#[serde(default)]
+	code: Option<String>,
+	#[serde(default)]
+	storage: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Transaction {
+	#[serde(default)]
+	state: BTreeMap<String, Account>,
+	caller: String,
+	#[serde(default)]
+	to: Option<String>,
+	#[serde(default)]
+	value: Option<String>,
+	#[serde(default)]
+	data: Option<String>,
+	gas_limit: u64,
+}
+
+/// Run a JSON transaction, returning the JSON outcome: exit reason, return
+/// value, used gas, logs and state diff.
+#[wasm_bindgen]
+pub fn run(transaction: &str) -> Result<String, JsValue> {
+	execute(transaction)
+		.map(|outcome| outcome.to_string())
+		.map_err(|e| JsValue::from_str(&e))
+}
+
+/// Run a JSON transaction, see `run`.
+pub fn execute(transaction: &str) -> Result<Value, String> {
+	let transaction: Transaction = serde_json::from_str(transaction)
+		.map_err(|e| e.to_string())?;
+
+	let mut state = BTreeMap::new();
+	for (address, account) in transaction.state {
+		let mut storage = BTreeMap::new();
+		for (index, value) in account.storage {
+			storage.insert(quantity(&index)?, quantity(&value)?);
+		}
+		state.insert(address_of(&address)?, MemoryAccount {
+			balance: optional_quantity(account.balance.as_deref())?,
+			nonce: optional_quantity(account.nonce.as_deref())?,
+			code: optional_bytes(account.code.as_deref())?,
+			storage,
+		});
+	}
+
+	let caller = address_of(&transaction.caller)?;
+	let to = transaction.to.as_deref().map(address_of).transpose()?;
+	let value = optional_quantity(transaction.value.as_deref())?;
+	let data = optional_bytes(transaction.data.as_deref())?;
+	let gas_limit = transaction.gas_limit;
+
+	let vicinity = MemoryVicinity {
+		gas_price: U256::zero(),
+		origin: caller,
+		chain_id: U256::one(),
+		block_hashes: Vec::new(),
+		block_number: U256::zero(),
+		block_coinbase: H160::default(),
+		block_timestamp: U256::zero(),
+		block_difficulty: U256::zero(),
+		block_gas_limit: U256::from(gas_limit),
+	};
+	let backend = MemoryBackend::new(&vicinity, state);
+	let mut executor = StackExecutor::new(&backend, gas_limit);
+
+	let (reason, return_value, contract_address) = if let Some(to) = to {
+		let (reason, return_value) = executor.transact_call(caller, to, value, data, gas_limit);
+		(reason, return_value, None)
+	} else {
+		let address = executor.create_address(CreateScheme::Legacy { caller });
+		let reason = executor.transact_create(caller, value, data, gas_limit);
+		let address = if reason.is_succeed() { Some(address) } else { None };
+		(reason, Vec::new(), address)
+	};
+	let used_gas = executor.used_gas();
+	let (applies, logs) = executor.deconstruct();
+
+	Ok(json!({
+		"reason": format!("{reason:?}"),
+		"success": reason.is_succeed(),
+		"returnValue": hex(&return_value),
+		"contractAddress": contract_address.map(|address| format!("{address:?}")),
+		"usedGas": used_gas,
+		"logs": logs.iter().map(log_json).collect::<Vec<_>>(),
+		"stateDiff": state_diff(applies),
+	}))
+}
+
+fn log_json(log: &Log) -> Value {
+	json!({
+		"address": format!("{:?}", log.address),
+		"topics": log.topics.iter().map(|topic: &H256| format!("{topic:?}")).collect::<Vec<_>>(),
+		"data": hex(&log.data),
+	})
+}
+
+fn state_diff(applies: Vec<Apply<BTreeMap<U256, U256>>>) -> Value {
+	let mut diff = Map::new();
+	for apply in applies {
+		match apply {
+			Apply::Modify { address, basic, code_and_valids, storage, reset_storage } => {
+				let storage = storage.into_iter()
+					.map(|(index, value)| (format!("{index:#x}"), Value::from(format!("{value:#x}"))))
+					.collect::<Map<_, _>>();
+				diff.insert(format!("{address:?}"), json!({
+					"balance": format!("{:#x}", basic.balance),
+					"nonce": format!("{:#x}", basic.nonce),
+					"code": code_and_valids.map(|(code, _)| hex(&code)),
+					"storage": storage,
+					"resetStorage": reset_storage,
+				}));
+			},
+			Apply::Delete { address } => {
+				diff.insert(format!("{address:?}"), Value::Null);
+			},
+		}
+	}
+	Value::Object(diff)
+}
+
+fn hex(data: &[u8]) -> String {
+	let mut s = String::with_capacity(2 + data.len() * 2);
+	s.push_str("0x");
+	for byte in data {
+		let _ = write!(s, "{byte:02x}");
+	}
+	s
+}
+
+fn bytes(s: &str) -> Result<Vec<u8>, String> {
+	let digits = s.strip_prefix("0x").unwrap_or(s);
+	digits.as_bytes().chunks(2)
+		.map(|pair| {
+			core::str::from_utf8(pair).ok()
+				.filter(|pair| pair.len() == 2)
+				.and_then(|pair| u8::from_str_radix(pair, 16).ok())
+				.ok_or_else(|| format!("invalid hex: {s}"))
+		})
+		.collect()
+}
+
+fn optional_bytes(s: Option<&str>) -> Result<Vec<u8>, String> {
+	s.map_or_else(|| Ok(Vec::new()), bytes)
+}
+
+fn address_of(s: &str) -> Result<H160, String> {
+	let data = bytes(s)?;
+	if data.len() != 20 {
+		return Err(format!("invalid address: {s}"));
+	}
+	Ok(H160::from_slice(&data))
+}
+
+fn quantity(s: &str) -> Result<U256, String> {
+	let value = s.strip_prefix("0x").map_or_else(
+		|| U256::from_dec_str(s).ok(),
+		|digits| U256::from_str_radix(digits, 16).ok(),
+	);
+	value.ok_or_else(|| format!("invalid quantity: {s}"))
+}
+
+fn optional_quantity(s: Option<&str>) -> Result<U256, String> {
+	s.map_or_else(|| Ok(U256::zero()), quantity)
+}
@@ -0,0 +1,87 @@
+mod common;
+
+use std::collections::BTreeMap;
+use evm::{H160, H256, U256};
+use evm::access_list::AccessListTracer;
+use evm::backend::{MemoryAccount, MemoryBackend, MemoryVicinity};
+use evm::executor::StackExecutor;
+use evm::tracing::TraceConfig;
+use common::{account, callee, caller, contract, vicinity};
+
+fn other() -> H160 {
+	H160::repeat_byte(0x44)
+}
+
+fn precompile() -> H160 {
+	let mut address = [0; 20];
+	address[19] = 1;
+	H160(address)
+}
+
+fn key(index: u64) -> H256 {
+	H256::from(U256::from(index))
+}
+
+/// Reads slot 1, writes slot 2, queries `other()`, the sender and the
+/// precompile, then calls `callee()`.
+fn code() -> Vec<u8> {
+	let mut code = vec![0x60, 0x01, 0x54, 0x50, 0x60, 0x09, 0x60, 0x02, 0x55, 0x73];
+	code.extend_from_slice(other().as_bytes());
+	code.extend_from_slice(&[0x31, 0x50, 0x73]);
+	code.extend_from_slice(caller().as_bytes());
+	code.extend_from_slice(&[0x3b, 0x50, 0x60, 0x01, 0x31, 0x50]);
+	code.extend_from_slice(&[0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x73]);
+	code.extend_from_slice(callee().as_bytes());
+	code.extend_from_slice(&[0x5a, 0xf1, 0x50, 0x00]);
+	code
+}
+
+/// Reads slot 5, then reverts.
+const CALLEE_CODE: [u8; 8] = [0x60, 0x05, 0x54, 0x50, 0x60, 0x00, 0x80, 0xfd];
+
+fn backend(vicinity: &MemoryVicinity) -> MemoryBackend<'_> {
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount::default());
+	state.insert(contract(), account(code()));
+	state.insert(callee(), account(CALLEE_CODE.to_vec()));
+	MemoryBackend::new(vicinity, state)
+}
+
+#[test]
+fn records_accesses() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let tracer = AccessListTracer::new(caller(), Some(contract()), vec![precompile()]);
+	let mut executor = StackExecutor::new(&backend, 1_000_000).with_listener(&tracer);
+	let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 1_000_000);
+	assert!(reason.is_succeed());
+
+	// The recipient only appears for its storage keys, and the reverted call
+	// is kept.
+	assert_eq!(tracer.access_list(), vec![
+		(contract(), vec![key(1), key(2)]),
+		(callee(), vec![key(5)]),
+		(other(), Vec::new()),
+	]);
+}
+
+#[test]
+fn deduplicates_across_transactions() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let tracer = AccessListTracer::new(caller(), None, Vec::new());
+	let mut executor = StackExecutor::new(&backend, 1_000_000)
+		.with_listener(&tracer)
+		.with_trace_config(TraceConfig { memory: false, ..TraceConfig::all() });
+	for _ in 0..2 {
+		let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 1_000_000);
+		assert!(reason.is_succeed());
+	}
+
+	assert_eq!(tracer.access_list(), vec![
+		(precompile(), Vec::new()),
+		(contract(), vec![key(1), key(2)]),
+		(callee(), vec![key(5)]),
+		(other(), Vec::new()),
+	]);
+}
@@ -0,0 +1,198 @@
+mod common;
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use evm::{Berlin, CreateScheme, ExitError, ExitSucceed, Opcode, H160, H256, U256};
+use evm::backend::{MemoryAccount, MemoryBackend, MemoryVicinity};
+use evm::executor::{Precompiles, StackExecutor};
+use evm::tracing::{Event, EventListener};
+use common::{account, caller, contract, vicinity};
+
+fn child() -> H160 {
+	H160::repeat_byte(0x33)
+}
+
+fn other() -> H160 {
+	H160::repeat_byte(0x44)
+}
+
+fn precompile() -> H160 {
+	H160::from(U256::from(1))
+}
+
+/// Gas cost of every `BALANCE` and `SLOAD`, in execution order.
+#[derive(Default)]
+struct Recorder(RefCell<Vec<u64>>);
+
+impl EventListener for Recorder {
+	fn event(&self, event: Event<'_>) {
+		if let Event::Step { opcode: Opcode::BALANCE | Opcode::SLOAD, gas_cost, .. } = event {
+			self.0.borrow_mut().push(gas_cost);
+		}
+	}
+}
+
+type PrecompileResult = Option<Result<(ExitSucceed, Vec<u8>, u64), ExitError>>;
+
+fn identity(_address: H160, input: &[u8], _target_gas: Option<u64>) -> PrecompileResult {
+	Some(Ok((ExitSucceed::Returned, input.to_vec(), 0)))
+}
+
+/// Identity at the precompile address only.
+fn at_precompile(address: H160, input: &[u8], target_gas: Option<u64>) -> PrecompileResult {
+	if address == precompile() { identity(address, input, target_gas) } else { None }
+}
+
+fn none(_address: H160, _input: &[u8], _target_gas: Option<u64>) -> PrecompileResult {
+	None
+}
+
+/// `BALANCE` of `address`, discarding it.
+fn balance(address: H160) -> Vec<u8> {
+	let mut code = vec![0x73];
+	code.extend_from_slice(address.as_bytes());
+	code.extend_from_slice(&[0x31, 0x50]);
+	code
+}
+
+/// `SLOAD` of slot 0, discarding it.
+const SLOAD: [u8; 4] = [0x60, 0x00, 0x54, 0x50];
+
+fn code() -> Vec<u8> {
+	let mut code = Vec::new();
+	code.extend(balance(other()));
+	code.extend(balance(other()));
+	code.extend(balance(caller()));
+	code.extend(balance(precompile()));
+	code.extend_from_slice(&SLOAD);
+	code.extend_from_slice(&SLOAD);
+	code
+}
+
+/// Reads the balance of `other`, then reverts.
+fn child_code() -> Vec<u8> {
+	let mut code = balance(other());
+	code.extend_from_slice(&[0x60, 0x00, 0x60, 0x00, 0xfd]);
+	code
+}
+
+/// Calls the child, then reads the balance of `other`.
+fn parent_code() -> Vec<u8> {
+	let mut code = vec![0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x73];
+	code.extend_from_slice(child().as_bytes());
+	code.extend_from_slice(&[0x5a, 0xf1, 0x50]);
+	code.extend(balance(other()));
+	code
+}
+
+fn backend(vicinity: &MemoryVicinity, code: Vec<u8>) -> MemoryBackend<'_> {
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount { nonce: U256::one(), ..Default::default() });
+	state.insert(contract(), account(code));
+	state.insert(child(), account(child_code()));
+	MemoryBackend::new(vicinity, state)
+}
+
+fn transact(executor: &mut StackExecutor<'_, MemoryBackend<'_>, Berlin>) {
+	let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 1_000_000);
+	assert!(reason.is_succeed());
+}
+
+#[test]
+fn cold_then_warm() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity, code());
+	let precompiles = Precompiles::new().with_precompile(precompile(), identity);
+	let recorder = Recorder::default();
+	let mut executor = StackExecutor::new_with_spec(&backend, 10_000_000, none, Berlin)
+		.with_precompiles(&precompiles)
+		.with_listener(&recorder);
+
+	transact(&mut executor);
+	assert_eq!(recorder.0.take(), vec![2600, 100, 100, 100, 2100, 100]);
+
+	// Each transaction starts cold again.
+	transact(&mut executor);
+	assert_eq!(recorder.0.take(), vec![2600, 100, 100, 100, 2100, 100]);
+}
+
+#[test]
+fn precompiles_outside_registry_are_warm() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity, code());
+	let recorder = Recorder::default();
+	let mut executor = StackExecutor::new_with_spec(&backend, 10_000_000, at_precompile, Berlin)
+		.with_listener(&recorder);
+
+	// The precompiles of the hard fork start warm (EIP-2929), whether the
+	// executor serves them or not.
+	transact(&mut executor);
+	assert_eq!(recorder.0.take(), vec![2600, 100, 100, 100, 2100, 100]);
+}
+
+#[test]
+fn prewarm() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity, code());
+	let recorder = Recorder::default();
+	let mut executor = StackExecutor::new_with_spec(&backend, 10_000_000, none, Berlin)
+		.with_listener(&recorder);
+
+	executor.prewarm_access_list(&vec![(contract(), vec![H256::zero()])]);
+	executor.prewarm_address(other());
+	transact(&mut executor);
+	assert_eq!(recorder.0.take(), vec![100, 100, 100, 100, 100, 100]);
+
+	// Prewarmed entries only apply to the next transaction.
+	transact(&mut executor);
+	assert_eq!(recorder.0.take(), vec![2600, 100, 100, 100, 2100, 100]);
+
+	executor.prewarm_storage(contract(), U256::zero());
+	transact(&mut executor);
+	assert_eq!(recorder.0.take(), vec![2600, 100, 100, 100, 100, 100]);
+}
+
+#[test]
+fn reverted_accesses_are_cold() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity, parent_code());
+	let recorder = Recorder::default();
+	let mut executor = StackExecutor::new_with_spec(&backend, 10_000_000, none, Berlin)
+		.with_listener(&recorder);
+
+	transact(&mut executor);
+	assert_eq!(recorder.0.take(), vec![2600, 2600]);
+}
+
+/// `CREATE` of an empty contract with the given value, then `BALANCE` of the
+/// created address.
+fn create_then_balance(value: u8) -> Vec<u8> {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity, Vec::new());
+	let executor = StackExecutor::new_with_spec(&backend, 10_000_000, none, Berlin);
+	let created = executor.create_address(CreateScheme::Legacy { caller: contract() });
+
+	let mut code = vec![0x60, 0x00, 0x60, 0x00, 0x60, value, 0xf0, 0x50];
+	code.extend(balance(created));
+	code
+}
+
+#[test]
+fn failed_create_is_cold() {
+	let vicinity = vicinity();
+	let recorder = Recorder::default();
+
+	let succeeding = backend(&vicinity, create_then_balance(0));
+	let mut executor = StackExecutor::new_with_spec(&succeeding, 10_000_000, none, Berlin)
+		.with_listener(&recorder);
+	transact(&mut executor);
+	assert_eq!(recorder.0.take(), vec![100]);
+
+	// The contract has no balance, the create fails with `OutOfFund` before
+	// the address is accessed.
+	let failing = backend(&vicinity, create_then_balance(1));
+	let mut executor = StackExecutor::new_with_spec(&failing, 10_000_000, none, Berlin)
+		.with_listener(&recorder);
+	transact(&mut executor);
+	assert_eq!(recorder.0.take(), vec![2600]);
+}
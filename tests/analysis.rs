@@ -0,0 +1,100 @@
+mod common;
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use evm::{Valids, H256, U256};
+use evm::analysis::{AnalysisCache, BasicBlock, CodeAnalysis};
+use evm::backend::MemoryBackend;
+use evm::executor::StackExecutor;
+use common::{account, caller, contract, vicinity};
+
+/// Count down from 5, storing the counter in memory on every iteration.
+const LOOP: [u8; 22] = [
+	0x60, 0x05, // PUSH1 5
+	0x5b, // JUMPDEST
+	0x60, 0x01, 0x90, 0x03, // PUSH1 1 SWAP1 SUB
+	0x80, 0x60, 0x00, 0x52, // DUP1 PUSH1 0 MSTORE
+	0x80, 0x60, 0x02, 0x57, // DUP1 PUSH1 2 JUMPI
+	0x50, 0x5a, 0x50, 0x00, // POP GAS POP STOP
+	0x01, 0x00, 0x00, // ADD STOP STOP
+];
+
+#[test]
+fn blocks() {
+	let analysis = CodeAnalysis::analyze(&LOOP);
+	assert_eq!(analysis.blocks(), &[
+		BasicBlock { start: 0, end: 2, gas: 3, stack_min: 0, stack_growth: 1 },
+		BasicBlock { start: 2, end: 10, gas: 16, stack_min: 1, stack_growth: 2 },
+		BasicBlock { start: 11, end: 15, gas: 16, stack_min: 1, stack_growth: 2 },
+		BasicBlock { start: 15, end: 16, gas: 2, stack_min: 1, stack_growth: 0 },
+		BasicBlock { start: 17, end: 19, gas: 2, stack_min: 1, stack_growth: 0 },
+		BasicBlock { start: 19, end: 21, gas: 3, stack_min: 2, stack_growth: 0 },
+		BasicBlock { start: 21, end: 22, gas: 0, stack_min: 0, stack_growth: 0 },
+	][..]);
+	assert_eq!(analysis.block(2).map(|block| block.gas), Some(16));
+	assert_eq!(analysis.block(3), None);
+	assert_eq!(analysis.block(16), None);
+}
+
+#[test]
+fn undefined_opcode_ends_block() {
+	// PUSH1 1, undefined 0x0c, PUSH1 2 POP
+	let analysis = CodeAnalysis::analyze(&[0x60, 0x01, 0x0c, 0x60, 0x02, 0x50]);
+	assert_eq!(analysis.blocks(), &[
+		BasicBlock { start: 0, end: 2, gas: 3, stack_min: 0, stack_growth: 1 },
+		BasicBlock { start: 3, end: 6, gas: 5, stack_min: 0, stack_growth: 1 },
+	][..]);
+	assert_eq!(analysis.block(2), None);
+}
+
+#[test]
+fn shared_valids() {
+	let cache = AnalysisCache::new();
+	let analysis = cache.get_or_analyze(H256::repeat_byte(1), &LOOP);
+	assert_eq!(**analysis.valids(), Valids::new(Valids::compute(&LOOP)));
+
+	// Runtimes clone the `Arc`, not the table.
+	let again = cache.get_or_analyze(H256::repeat_byte(1), &LOOP);
+	assert!(Arc::ptr_eq(analysis.valids(), again.valids()));
+}
+
+#[test]
+fn same_gas_as_per_opcode() {
+	let vicinity = vicinity();
+	let mut state = BTreeMap::new();
+	state.insert(contract(), account(LOOP.to_vec()));
+	let backend = MemoryBackend::new(&vicinity, state);
+
+	// Every gas limit running out at a different opcode of the loop.
+	for gas_limit in 21_000..21_250 {
+		let run = |cache: Option<AnalysisCache>| {
+			let mut executor = StackExecutor::new(&backend, gas_limit);
+			if let Some(cache) = cache {
+				executor = executor.with_analysis_cache(cache);
+			}
+			let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), gas_limit);
+			(reason, executor.used_gas())
+		};
+		assert_eq!(run(Some(AnalysisCache::new())), run(None), "gas limit {}", gas_limit);
+	}
+}
+
+#[test]
+fn analyzed_once_per_code_hash() {
+	let vicinity = vicinity();
+	let mut state = BTreeMap::new();
+	state.insert(contract(), account(LOOP.to_vec()));
+	let backend = MemoryBackend::new(&vicinity, state);
+
+	let cache = AnalysisCache::new();
+	for _ in 0..2 {
+		let mut executor = StackExecutor::new(&backend, 100_000).with_analysis_cache(cache.clone());
+		let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 100_000);
+		assert!(reason.is_succeed());
+	}
+	assert_eq!(cache.len(), 1);
+
+	let analysis = cache.get_or_analyze(H256::default(), &[]);
+	assert!(analysis.blocks().is_empty());
+	assert_eq!(cache.len(), 2);
+}
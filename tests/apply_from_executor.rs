@@ -0,0 +1,56 @@
+mod common;
+
+use std::collections::BTreeMap;
+use evm::{ExitReason, ExitSucceed, H160, U256};
+use evm::backend::{ApplyBackend, MemoryAccount, MemoryBackend};
+use evm::executor::{apply_from_executor, StackExecutor};
+use common::{account, caller, contract, vicinity};
+
+fn destructible() -> H160 {
+	H160::repeat_byte(0x33)
+}
+
+fn state() -> BTreeMap<H160, MemoryAccount> {
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount { balance: U256::from(1_000_000), ..Default::default() });
+	// PUSH1 7 PUSH1 0 SSTORE PUSH1 0 PUSH1 0 LOG0
+	state.insert(contract(), account(vec![0x60, 0x07, 0x60, 0x00, 0x55, 0x60, 0x00, 0x60, 0x00, 0xa0]));
+	// CALLER SELFDESTRUCT
+	state.insert(destructible(), MemoryAccount {
+		nonce: U256::one(),
+		balance: U256::from(10),
+		code: vec![0x33, 0xff],
+		..Default::default()
+	});
+	state
+}
+
+fn execute(executor: &mut StackExecutor<'_, MemoryBackend<'_>>) {
+	let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 100_000);
+	assert_eq!(reason, ExitReason::Succeed(ExitSucceed::Stopped));
+	let (reason, _) = executor.transact_call(caller(), destructible(), U256::zero(), Vec::new(), 100_000);
+	assert_eq!(reason, ExitReason::Succeed(ExitSucceed::Suicided));
+}
+
+#[test]
+fn streamed_apply_matches_deconstruct() {
+	let vicinity = vicinity();
+	let backend = MemoryBackend::new(&vicinity, state());
+
+	let mut streamed = MemoryBackend::new(&vicinity, state());
+	let mut executor = StackExecutor::new(&backend, 1_000_000);
+	execute(&mut executor);
+	apply_from_executor(&mut streamed, executor);
+
+	let mut collected = MemoryBackend::new(&vicinity, state());
+	let mut executor = StackExecutor::new(&backend, 1_000_000);
+	execute(&mut executor);
+	let (applies, logs) = executor.deconstruct();
+	collected.apply(applies, logs, true);
+
+	let state = streamed.into_state();
+	assert_eq!(state, collected.into_state());
+	assert!(!state.contains_key(&destructible()));
+	assert_eq!(state[&contract()].storage[&U256::zero()], U256::from(7));
+	assert_eq!(state[&caller()].balance, U256::from(1_000_010));
+}
@@ -0,0 +1,135 @@
+mod common;
+
+use std::collections::BTreeMap;
+use evm::{H160, U256};
+use evm::backend::{
+	Apply, ApplyBackend, AppliedSummary, ApplyError, ApplySet, Basic, Log, MemoryAccount, MemoryBackend, MemoryVicinity,
+};
+
+type Applies = Vec<Apply<Vec<(U256, U256)>>>;
+
+fn vicinity() -> MemoryVicinity {
+	MemoryVicinity {
+		block_gas_limit: U256::zero(),
+		..common::vicinity()
+	}
+}
+
+fn modify(
+	address: H160,
+	nonce: u64,
+	code: Option<Vec<u8>>,
+	storage: Vec<(u64, u64)>,
+	reset_storage: bool,
+) -> Apply<Vec<(U256, U256)>> {
+	Apply::Modify {
+		address,
+		basic: Basic { balance: U256::one(), nonce: U256::from(nonce) },
+		code_and_valids: code.map(|code| (code, Vec::new())),
+		storage: storage.into_iter().map(|(k, v)| (U256::from(k), U256::from(v))).collect(),
+		reset_storage,
+	}
+}
+
+fn initial_state() -> BTreeMap<H160, MemoryAccount> {
+	let mut storage = BTreeMap::new();
+	storage.insert(U256::from(1), U256::from(1));
+	storage.insert(U256::from(2), U256::from(2));
+	let mut state = BTreeMap::new();
+	state.insert(H160::repeat_byte(0x11), MemoryAccount {
+		nonce: U256::one(),
+		balance: U256::one(),
+		storage: storage.clone(),
+		code: vec![0x00],
+	});
+	state.insert(H160::repeat_byte(0x22), MemoryAccount {
+		nonce: U256::one(),
+		balance: U256::one(),
+		storage,
+		code: vec![0x00],
+	});
+	state
+}
+
+fn assert_squash_equivalent(transactions: Vec<Applies>) {
+	let vicinity = vicinity();
+
+	let mut sequential = MemoryBackend::new(&vicinity, initial_state());
+	for applies in transactions.clone() {
+		sequential.apply(applies, Vec::<Log>::new(), false);
+	}
+
+	let mut set = ApplySet::new();
+	for applies in transactions {
+		set.push(applies);
+	}
+	let mut squashed = MemoryBackend::new(&vicinity, initial_state());
+	squashed.apply(set.into_applies(), Vec::<Log>::new(), false);
+
+	assert_eq!(sequential.state(), squashed.state());
+}
+
+#[test]
+fn squash_storage_updates() {
+	let a = H160::repeat_byte(0x11);
+	assert_squash_equivalent(vec![
+		vec![modify(a, 2, None, vec![(1, 10), (3, 3)], false)],
+		vec![modify(a, 3, None, vec![(3, 0), (4, 4)], false)],
+	]);
+}
+
+#[test]
+fn squash_storage_reset() {
+	let a = H160::repeat_byte(0x11);
+	assert_squash_equivalent(vec![
+		vec![modify(a, 2, None, vec![(3, 3)], false)],
+		vec![modify(a, 3, Some(vec![0x01]), vec![(4, 4)], true)],
+		vec![modify(a, 4, None, vec![(5, 5)], false)],
+	]);
+}
+
+#[test]
+fn squash_delete_then_recreate() {
+	let a = H160::repeat_byte(0x11);
+	let b = H160::repeat_byte(0x22);
+	assert_squash_equivalent(vec![
+		vec![modify(a, 2, None, vec![(3, 3)], false), Apply::Delete { address: b }],
+		vec![Apply::Delete { address: a }, modify(b, 1, None, vec![(7, 7)], false)],
+		vec![modify(a, 1, None, vec![(8, 8)], false)],
+	]);
+
+	let mut set = ApplySet::new();
+	set.push(vec![Apply::<Vec<(U256, U256)>>::Delete { address: a }]);
+	set.push(vec![modify(a, 1, None, Vec::new(), false)]);
+	assert_eq!(set.len(), 1);
+}
+
+#[test]
+fn apply_result_is_atomic() {
+	let vicinity = vicinity();
+	let (a, b, c) = (H160::repeat_byte(0x11), H160::repeat_byte(0x22), H160::repeat_byte(0x33));
+	let log = Log { address: a, topics: Vec::new(), data: Vec::new() };
+	let mut backend = MemoryBackend::new(&vicinity, initial_state());
+
+	let applies: Applies = vec![modify(c, 1, None, vec![(1, 1)], false), Apply::Delete { address: c }];
+	assert_eq!(backend.apply_result(applies, vec![log.clone()], false), Err(ApplyError::DuplicateAccount(c)));
+	assert_eq!(backend.state(), &initial_state());
+
+	let empty = Apply::Modify {
+		address: H160::repeat_byte(0x44),
+		basic: Basic::default(),
+		code_and_valids: None,
+		storage: Vec::new(),
+		reset_storage: false,
+	};
+	let applies: Applies = vec![
+		modify(a, 2, None, vec![(1, 10)], false),
+		Apply::Delete { address: b },
+		Apply::Delete { address: H160::repeat_byte(0x55) },
+		empty,
+		modify(c, 1, None, Vec::new(), false),
+	];
+	let summary = backend.apply_result(applies, vec![log], true).unwrap();
+	assert_eq!(summary, AppliedSummary { modified: 2, deleted: 2 });
+	assert_eq!(backend.state().keys().copied().collect::<Vec<_>>(), vec![a, c]);
+}
@@ -0,0 +1,136 @@
+use evm::{ActiveArithmetic, Arithmetic, NativeArithmetic, Opcode, Word, U256};
+
+/// `i256::MIN`, the sign bit alone.
+fn min() -> U256 {
+	U256::one() << 255
+}
+
+/// Two's complement of `value`.
+fn neg(value: u64) -> U256 {
+	(!U256::from(value)).overflowing_add(U256::one()).0
+}
+
+fn edges() -> Vec<U256> {
+	vec![
+		U256::zero(),
+		U256::one(),
+		U256::from(2),
+		U256::from(31),
+		U256::from(32),
+		U256::from(256),
+		U256::one() << 128,
+		min() - U256::one(),
+		min(),
+		neg(2),
+		U256::MAX,
+	]
+}
+
+fn apply(opcode: Opcode, args: &[U256]) -> U256 {
+	U256::apply(opcode, args).unwrap()
+}
+
+#[test]
+fn division_by_zero() {
+	for value in edges() {
+		for opcode in [Opcode::DIV, Opcode::SDIV, Opcode::MOD, Opcode::SMOD] {
+			assert_eq!(apply(opcode, &[value, U256::zero()]), U256::zero(), "{:?} {}", opcode, value);
+		}
+		assert_eq!(NativeArithmetic::addmod(value, U256::MAX, U256::zero()), U256::zero());
+		assert_eq!(NativeArithmetic::mulmod(value, U256::MAX, U256::zero()), U256::zero());
+		assert_eq!(ActiveArithmetic::addmod(value, U256::MAX, U256::zero()), U256::zero());
+		assert_eq!(ActiveArithmetic::mulmod(value, U256::MAX, U256::zero()), U256::zero());
+	}
+}
+
+#[test]
+fn signed_division_at_min() {
+	assert_eq!(apply(Opcode::SDIV, &[min(), U256::MAX]), min());
+	assert_eq!(apply(Opcode::SMOD, &[min(), U256::MAX]), U256::zero());
+	assert_eq!(apply(Opcode::SDIV, &[min(), U256::one()]), min());
+	assert_eq!(apply(Opcode::SDIV, &[min(), neg(2)]), U256::one() << 254);
+	// 2 ** 255 % 3 is 2, the result takes the sign of the dividend.
+	assert_eq!(apply(Opcode::SMOD, &[min(), U256::from(3)]), neg(2));
+	assert_eq!(apply(Opcode::SMOD, &[min(), neg(3)]), neg(2));
+	assert_eq!(apply(Opcode::SDIV, &[neg(7), U256::from(2)]), neg(3));
+	assert_eq!(apply(Opcode::SMOD, &[neg(7), U256::from(2)]), neg(1));
+}
+
+#[test]
+fn modular_arithmetic_without_overflow() {
+	fn check<A: Arithmetic>() {
+		// 2 * MAX % (MAX - 1) is 2.
+		assert_eq!(A::addmod(U256::MAX, U256::MAX, U256::MAX - U256::one()), U256::from(2));
+		assert_eq!(A::addmod(U256::MAX, U256::one(), U256::MAX), U256::one());
+		assert_eq!(A::addmod(U256::MAX, U256::MAX, U256::MAX), U256::zero());
+		// MAX is 1 modulo MAX - 1.
+		assert_eq!(A::mulmod(U256::MAX, U256::MAX, U256::MAX - U256::one()), U256::one());
+		assert_eq!(A::mulmod(U256::MAX, U256::MAX, U256::MAX), U256::zero());
+		// 2 ** 256 % 3 is 1.
+		assert_eq!(A::mulmod(min(), U256::from(2), U256::from(3)), U256::one());
+		assert_eq!(A::mulmod(U256::from(5), U256::from(7), U256::one()), U256::zero());
+	}
+
+	check::<NativeArithmetic>();
+	check::<ActiveArithmetic>();
+}
+
+#[test]
+fn exponentiation_wraps_around() {
+	fn check<A: Arithmetic>() {
+		assert_eq!(A::exp(U256::zero(), U256::zero()), U256::one());
+		assert_eq!(A::exp(U256::zero(), U256::MAX), U256::zero());
+		assert_eq!(A::exp(U256::from(2), U256::from(255)), min());
+		assert_eq!(A::exp(U256::from(2), U256::from(256)), U256::zero());
+		assert_eq!(A::exp(U256::MAX, U256::from(2)), U256::one());
+		assert_eq!(A::exp(U256::MAX, U256::from(3)), U256::MAX);
+		assert_eq!(A::exp(U256::from(3), U256::from(5)), U256::from(243));
+		assert_eq!(A::exp(U256::MAX, U256::MAX), U256::MAX);
+	}
+
+	check::<NativeArithmetic>();
+	check::<ActiveArithmetic>();
+}
+
+#[test]
+fn signextend() {
+	assert_eq!(apply(Opcode::SIGNEXTEND, &[U256::zero(), U256::from(0xff)]), U256::MAX);
+	assert_eq!(apply(Opcode::SIGNEXTEND, &[U256::zero(), U256::from(0x7f)]), U256::from(0x7f));
+	assert_eq!(apply(Opcode::SIGNEXTEND, &[U256::zero(), U256::from(0x1ff)]), U256::MAX);
+	assert_eq!(apply(Opcode::SIGNEXTEND, &[U256::one(), U256::from(0x80ff)]), (U256::MAX << 16) | U256::from(0x80ff));
+	assert_eq!(apply(Opcode::SIGNEXTEND, &[U256::from(30), min()]), U256::zero());
+	for value in edges() {
+		assert_eq!(apply(Opcode::SIGNEXTEND, &[U256::from(31), value]), value);
+		assert_eq!(apply(Opcode::SIGNEXTEND, &[U256::MAX, value]), value);
+	}
+}
+
+/// Both backends agree on every combination of edge values, and on values
+/// from a deterministic xorshift generator.
+#[cfg(feature = "ruint")]
+#[test]
+fn ruint_matches_native() {
+	use evm::RuintArithmetic;
+
+	let mut values = edges();
+	let mut state = 0x2545_f491_4f6c_dd1d_u64;
+	let mut next = || {
+		state ^= state << 13;
+		state ^= state >> 7;
+		state ^= state << 17;
+		state
+	};
+	for _ in 0..20 {
+		values.push(U256([next(), next(), next(), next()]));
+	}
+
+	for &a in &values {
+		for &b in &values {
+			assert_eq!(RuintArithmetic::exp(a, b), NativeArithmetic::exp(a, b), "exp {} {}", a, b);
+			for &c in &values {
+				assert_eq!(RuintArithmetic::addmod(a, b, c), NativeArithmetic::addmod(a, b, c), "addmod {} {} {}", a, b, c);
+				assert_eq!(RuintArithmetic::mulmod(a, b, c), NativeArithmetic::mulmod(a, b, c), "mulmod {} {} {}", a, b, c);
+			}
+		}
+	}
+}
@@ -0,0 +1,208 @@
+mod common;
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use evm::{CreateScheme, Keccak, H160, H256, U256};
+use evm::backend::{Backend, Basic, MemoryAccount, MemoryBackend};
+use evm::executor::{AccountCache, StackExecutor};
+use common::{caller, contract, vicinity};
+
+fn other() -> H160 {
+	H160::repeat_byte(0x33)
+}
+
+fn empty() -> H160 {
+	H160::repeat_byte(0x44)
+}
+
+/// Counts the account queries made to the inner backend.
+struct CountingBackend<'vicinity> {
+	inner: MemoryBackend<'vicinity>,
+	queries: RefCell<BTreeMap<(&'static str, H160), usize>>,
+}
+
+impl CountingBackend<'_> {
+	fn count(&self, query: &'static str, address: H160) {
+		*self.queries.borrow_mut().entry((query, address)).or_insert(0) += 1;
+	}
+
+	fn queries(&self, query: &'static str, address: H160) -> usize {
+		self.queries.borrow().get(&(query, address)).copied().unwrap_or(0)
+	}
+}
+
+impl Keccak for CountingBackend<'_> {
+	fn keccak256_h256(&self, data: &[u8]) -> H256 { self.inner.keccak256_h256(data) }
+}
+
+impl Backend for CountingBackend<'_> {
+	fn gas_price(&self) -> U256 { self.inner.gas_price() }
+	fn origin(&self) -> H160 { self.inner.origin() }
+	fn block_hash(&self, number: U256) -> H256 { self.inner.block_hash(number) }
+	fn block_number(&self) -> U256 { self.inner.block_number() }
+	fn block_coinbase(&self) -> H160 { self.inner.block_coinbase() }
+	fn block_timestamp(&self) -> U256 { self.inner.block_timestamp() }
+	fn block_difficulty(&self) -> U256 { self.inner.block_difficulty() }
+	fn block_gas_limit(&self) -> U256 { self.inner.block_gas_limit() }
+	fn chain_id(&self) -> U256 { self.inner.chain_id() }
+
+	fn exists(&self, address: H160) -> bool {
+		self.count("exists", address);
+		self.inner.exists(address)
+	}
+
+	fn basic(&self, address: H160) -> Basic {
+		self.count("basic", address);
+		self.inner.basic(address)
+	}
+
+	fn code_hash(&self, address: H160) -> H256 {
+		self.count("code_hash", address);
+		self.inner.code_hash(address)
+	}
+
+	fn code_size(&self, address: H160) -> usize {
+		self.count("code_size", address);
+		self.inner.code_size(address)
+	}
+
+	fn code(&self, address: H160) -> Vec<u8> {
+		self.count("code", address);
+		self.inner.code(address)
+	}
+
+	fn valids(&self, address: H160) -> Vec<u8> { self.inner.valids(address) }
+	fn storage(&self, address: H160, index: U256) -> U256 { self.inner.storage(address, index) }
+	fn create(&self, scheme: &CreateScheme, address: &H160) { self.inner.create(scheme, address) }
+}
+
+/// `opcode` applied to `other()` `times` times, popping the result.
+fn query(opcode: u8, times: usize) -> Vec<u8> {
+	query_of(other(), opcode, times)
+}
+
+/// `opcode` applied to `address` `times` times, popping the result.
+fn query_of(address: H160, opcode: u8, times: usize) -> Vec<u8> {
+	let mut code = Vec::new();
+	for _ in 0..times {
+		code.push(0x73);
+		code.extend_from_slice(address.as_bytes());
+		code.extend_from_slice(&[opcode, 0x50]);
+	}
+	code
+}
+
+/// Queries `other()` with `BALANCE`, `EXTCODESIZE` and `EXTCODEHASH`, then
+/// transfers it 1 wei and queries it again.
+fn code() -> Vec<u8> {
+	let mut code = query(0x31, 3);
+	code.extend(query(0x3b, 2));
+	code.extend(query(0x3f, 1));
+	code.extend_from_slice(&[0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x01, 0x73]);
+	code.extend_from_slice(other().as_bytes());
+	code.extend_from_slice(&[0x61, 0x27, 0x10, 0xf1, 0x50]);
+	code.extend(query(0x31, 1));
+	code.extend(query(0x3f, 1));
+	code.extend_from_slice(&[0x73]);
+	code.extend_from_slice(other().as_bytes());
+	code.extend_from_slice(&[0x31, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3]);
+	code
+}
+
+#[test]
+fn account_queries_are_memoized() {
+	let vicinity = vicinity();
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount::default());
+	state.insert(contract(), MemoryAccount {
+		nonce: U256::one(),
+		balance: U256::from(10),
+		code: code(),
+		..Default::default()
+	});
+	state.insert(other(), MemoryAccount {
+		balance: U256::from(5),
+		code: vec![0x00],
+		..Default::default()
+	});
+	let backend = CountingBackend {
+		inner: MemoryBackend::new(&vicinity, state),
+		queries: RefCell::new(BTreeMap::new()),
+	};
+
+	let mut executor = StackExecutor::new(&backend, 1_000_000);
+	let (reason, out) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 1_000_000);
+	assert!(reason.is_succeed());
+	// The transfer is visible to queries made after it.
+	assert_eq!(U256::from_big_endian(&out), U256::from(6));
+
+	assert_eq!(backend.queries("basic", other()), 1);
+	assert_eq!(backend.queries("code_size", other()), 1);
+	// Loaded once, to run it.
+	assert_eq!(backend.queries("code", other()), 1);
+	assert_eq!(backend.queries("code_hash", other()), 1);
+	assert_eq!(backend.queries("exists", other()), 0);
+}
+
+#[test]
+fn code_hash_checks_emptiness_once() {
+	let vicinity = vicinity();
+	let mut code = query_of(empty(), 0x3f, 2);
+	code.extend(query_of(other(), 0x3f, 2));
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount::default());
+	state.insert(contract(), MemoryAccount { code, ..Default::default() });
+	state.insert(empty(), MemoryAccount::default());
+	state.insert(other(), MemoryAccount { code: vec![0x00], ..Default::default() });
+	let backend = CountingBackend {
+		inner: MemoryBackend::new(&vicinity, state),
+		queries: RefCell::new(BTreeMap::new()),
+	};
+
+	let mut executor = StackExecutor::new(&backend, 1_000_000);
+	let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 1_000_000);
+	assert!(reason.is_succeed());
+
+	// The hash of an empty account is zero, without asking the backend.
+	assert_eq!(backend.queries("basic", empty()), 1);
+	assert_eq!(backend.queries("code_size", empty()), 1);
+	assert_eq!(backend.queries("code_hash", empty()), 0);
+	assert_eq!(backend.queries("basic", other()), 1);
+	assert_eq!(backend.queries("code_size", other()), 1);
+	assert_eq!(backend.queries("code_hash", other()), 1);
+	assert_eq!(backend.queries("code", other()), 0);
+}
+
+#[test]
+fn shared_account_cache() {
+	let vicinity = vicinity();
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount { balance: U256::from(10), ..Default::default() });
+	state.insert(contract(), MemoryAccount { code: query(0x31, 1), ..Default::default() });
+	state.insert(other(), MemoryAccount { balance: U256::from(5), ..Default::default() });
+	let backend = CountingBackend {
+		inner: MemoryBackend::new(&vicinity, state),
+		queries: RefCell::new(BTreeMap::new()),
+	};
+
+	let cache = AccountCache::new();
+	for _ in 0..2 {
+		let mut executor = StackExecutor::new(&backend, 1_000_000).with_account_cache(cache.clone());
+		let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 1_000_000);
+		assert!(reason.is_succeed());
+	}
+	// Read once for both transactions.
+	assert_eq!(backend.queries("basic", other()), 1);
+	assert!(cache.contains(other()));
+
+	// Writing the changes of a transaction invalidates the written accounts.
+	let mut executor = StackExecutor::new(&backend, 1_000_000).with_account_cache(cache.clone());
+	let (reason, _) = executor.transact_call(caller(), other(), U256::one(), Vec::new(), 1_000_000);
+	assert!(reason.is_succeed());
+	let mut target = MemoryBackend::new(&vicinity, BTreeMap::new());
+	executor.commit_to(&mut target);
+	assert!(!cache.contains(caller()));
+	assert!(!cache.contains(other()));
+	assert!(cache.contains(contract()));
+	assert_eq!(executor.account_cache().len(), cache.len());
+}
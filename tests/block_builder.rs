@@ -0,0 +1,78 @@
+mod common;
+
+use std::collections::BTreeMap;
+use evm::{H160, U256};
+use evm::backend::{Apply, MemoryAccount, MemoryBackend, MemoryVicinity};
+use evm::block::GasLimitError;
+use evm::executor::{BlockBuilder, Candidate, Simulation, StackExecutor};
+use common::{account, caller};
+
+fn vicinity() -> MemoryVicinity {
+	MemoryVicinity {
+		block_gas_limit: U256::from(100_000),
+		..common::vicinity()
+	}
+}
+
+fn counter() -> H160 {
+	H160::repeat_byte(0x22)
+}
+
+/// Increments the word at slot 0.
+const COUNTER: [u8; 10] = [0x60, 0x00, 0x54, 0x60, 0x01, 0x01, 0x60, 0x00, 0x55, 0x00];
+
+fn backend(vicinity: &MemoryVicinity) -> MemoryBackend<'_> {
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount::default());
+	state.insert(counter(), account(COUNTER.to_vec()));
+	MemoryBackend::new(vicinity, state)
+}
+
+/// Calls the counter, the transaction being its name.
+fn simulate(backend: &MemoryBackend<'_>, candidate: &Candidate<&'static str>) -> Simulation {
+	let mut executor = StackExecutor::new(backend, candidate.gas_limit);
+	let outcome = executor.transact_call_with_outcome(caller(), counter(), U256::zero(), Vec::new(), candidate.gas_limit);
+	let (applies, _) = executor.deconstruct();
+	Simulation { outcome, applies }
+}
+
+#[test]
+fn pack_under_block_gas_limit() {
+	let vicinity = vicinity();
+	let candidates = vec![
+		Candidate { gas_limit: 60_000, transaction: "first" },
+		Candidate { gas_limit: 60_000, transaction: "too large" },
+		Candidate { gas_limit: 30_000, transaction: "second" },
+	];
+	let block = BlockBuilder::new(backend(&vicinity)).build(candidates, simulate);
+
+	let receipts: Vec<_> = block.receipts.iter()
+		.map(|receipt| (receipt.index, receipt.transaction, receipt.outcome.exit.is_succeed()))
+		.collect();
+	assert_eq!(receipts, vec![(0, "first", true), (2, "second", true)]);
+
+	// Setting the counter costs more than increasing it.
+	let (first, second) = (block.receipts[0].outcome.gas_used, block.receipts[1].outcome.gas_used);
+	assert!(first > second);
+	assert_eq!(block.receipts[0].cumulative_gas_used, first);
+	assert_eq!(block.receipts[1].cumulative_gas_used, first + second);
+	assert_eq!(block.gas_used, first + second);
+
+	assert_eq!(block.skipped.len(), 1);
+	let (index, candidate, error) = &block.skipped[0];
+	assert_eq!((*index, candidate.transaction), (1, "too large"));
+	assert_eq!(*error, GasLimitError::BlockGasLimitExceeded {
+		gas_limit: 60_000,
+		gas_used: first,
+		block_gas_limit: 100_000,
+	});
+
+	// The second transaction sees the counter set by the first.
+	let two = U256::from(2);
+	assert_eq!(block.backend.state()[&counter()].storage[&U256::zero()], two);
+	let storage = block.state_diff.iter().find_map(|apply| match apply {
+		Apply::Modify { address, storage, .. } if *address == counter() => Some(storage.clone()),
+		_ => None,
+	});
+	assert_eq!(storage.unwrap()[&U256::zero()], two);
+}
@@ -0,0 +1,69 @@
+mod common;
+
+use std::collections::BTreeMap;
+use evm::{H160, U256};
+use evm::backend::{MemoryAccount, MemoryVicinity};
+use evm::block::GasLimitError;
+use evm::testing::TestChain;
+use common::caller;
+
+fn chain(block_gas_limit: u64) -> TestChain {
+	let vicinity = MemoryVicinity {
+		gas_price: U256::zero(),
+		origin: H160::default(),
+		chain_id: U256::one(),
+		block_hashes: Vec::new(),
+		block_number: U256::zero(),
+		block_coinbase: H160::default(),
+		block_timestamp: U256::zero(),
+		block_difficulty: U256::zero(),
+		block_gas_limit: U256::from(block_gas_limit),
+	};
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount {
+		balance: U256::from(1_000_000),
+		..Default::default()
+	});
+	TestChain::new(vicinity, state, U256::from(15))
+}
+
+#[test]
+fn transaction_gas_limit_exceeded() {
+	let mut chain = chain(100_000).with_max_transaction_gas_limit(30_000);
+
+	let error = chain.transact_call(caller(), H160::default(), U256::zero(), Vec::new(), 30_001)
+		.unwrap_err();
+	assert_eq!(error, GasLimitError::TransactionGasLimitExceeded {
+		gas_limit: 30_001,
+		max_gas_limit: 30_000,
+	});
+	assert!(chain.receipts().is_empty());
+}
+
+#[test]
+fn block_gas_accounting() {
+	let mut chain = chain(50_000);
+
+	let receipt = chain.transact_call(caller(), H160::default(), U256::zero(), Vec::new(), 30_000)
+		.unwrap();
+	assert_eq!(receipt.used_gas, 21_000);
+	assert_eq!(receipt.cumulative_gas_used, 21_000);
+
+	let error = chain.transact_call(caller(), H160::default(), U256::zero(), Vec::new(), 30_000)
+		.unwrap_err();
+	assert_eq!(error, GasLimitError::BlockGasLimitExceeded {
+		gas_limit: 30_000,
+		gas_used: 21_000,
+		block_gas_limit: 50_000,
+	});
+
+	let receipt = chain.transact_call(caller(), H160::default(), U256::zero(), Vec::new(), 29_000)
+		.unwrap();
+	assert_eq!(receipt.cumulative_gas_used, 42_000);
+
+	chain.mine();
+	assert_eq!(chain.block_gas().gas_used(), 0);
+	let receipt = chain.transact_call(caller(), H160::default(), U256::zero(), Vec::new(), 30_000)
+		.unwrap();
+	assert_eq!(receipt.cumulative_gas_used, 21_000);
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use std::collections::BTreeMap;
+use evm::{H160, H256, U256};
+use evm::backend::{
+	Backend, BlockHashFn, BlockHashProvider, BlockHashRing, MemoryAccount, MemoryBackend, MemoryVicinity,
+	BLOCK_HASH_WINDOW,
+};
+use evm::executor::StackExecutor;
+use common::{account, caller, contract};
+
+fn vicinity(block_hashes: Vec<H256>) -> MemoryVicinity {
+	MemoryVicinity {
+		block_hashes,
+		block_number: U256::from(1000),
+		..common::vicinity()
+	}
+}
+
+/// Hash of block `number` in these tests.
+fn hash(number: U256) -> H256 {
+	H256::from(number + U256::from(0xabcd))
+}
+
+/// Returns `BLOCKHASH` of the number given as the first word of the input.
+const CODE: [u8; 10] = [0x60, 0x00, 0x35, 0x40, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60];
+
+fn blockhash(backend: &MemoryBackend<'_>, number: u64) -> H256 {
+	let mut executor = StackExecutor::new(backend, 1_000_000);
+	let mut input = [0_u8; 32];
+	U256::from(number).to_big_endian(&mut input);
+	let (reason, out) = executor.transact_call(caller(), contract(), U256::zero(), input.to_vec(), 100_000);
+	assert!(reason.is_succeed());
+	H256::from_slice(&out)
+}
+
+fn state() -> BTreeMap<H160, MemoryAccount> {
+	let mut code = CODE.to_vec();
+	code.extend_from_slice(&[0x00, 0xf3]);
+
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount::default());
+	state.insert(contract(), account(code));
+	state
+}
+
+#[test]
+fn vicinity_hashes_within_window() {
+	let hashes: Vec<H256> = (1..=300).map(|back| hash(U256::from(1000 - back))).collect();
+	let vicinity = vicinity(hashes);
+	let backend = MemoryBackend::new(&vicinity, state());
+
+	assert_eq!(backend.block_hash(U256::from(999)), hash(U256::from(999)));
+	assert_eq!(backend.block_hash(U256::from(744)), hash(U256::from(744)));
+	// Older than the window, even though the vicinity has it.
+	assert_eq!(backend.block_hash(U256::from(743)), H256::default());
+	assert_eq!(backend.block_hash(U256::from(1000)), H256::default());
+	assert_eq!(blockhash(&backend, 998), hash(U256::from(998)));
+}
+
+#[test]
+fn injected_hash_function() {
+	let vicinity = vicinity(Vec::new());
+	let provider = BlockHashFn(hash);
+	let backend = MemoryBackend::new(&vicinity, state()).with_block_hashes(&provider);
+
+	assert_eq!(blockhash(&backend, 900), hash(U256::from(900)));
+	assert_eq!(blockhash(&backend, 700), H256::default());
+	assert_eq!(blockhash(&backend, 1001), H256::default());
+}
+
+#[test]
+fn ring_keeps_last_blocks() {
+	let mut ring = BlockHashRing::new();
+	assert!(ring.is_empty());
+	for number in 0..1000 {
+		ring.push(U256::from(number), hash(U256::from(number)));
+	}
+	assert_eq!(ring.len(), BLOCK_HASH_WINDOW);
+	assert_eq!(ring.get(U256::from(999)), Some(hash(U256::from(999))));
+	assert_eq!(ring.get(U256::from(744)), Some(hash(U256::from(744))));
+	assert_eq!(ring.get(U256::from(743)), None);
+	assert_eq!(ring.block_hash(U256::from(1000), U256::from(743)), H256::default());
+
+	let vicinity = vicinity(Vec::new());
+	let backend = MemoryBackend::new(&vicinity, state()).with_block_hashes(&ring);
+	assert_eq!(blockhash(&backend, 800), hash(U256::from(800)));
+}
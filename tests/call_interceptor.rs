@@ -0,0 +1,234 @@
+mod common;
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use evm::{CreateScheme, ExitError, ExitReason, ExitRevert, ExitSucceed, Handler, Keccak, H160, H256, U256};
+use evm::backend::{
+	Backend, Basic, CallInterceptor, CallRequest, CallResponse, MemoryAccount, MemoryBackend, MemoryVicinity,
+};
+use evm::call_trace::CallTracer;
+use evm::executor::{FrameKind, StackExecutor};
+use common::{account, caller, contract, vicinity};
+
+/// Served by the interceptor.
+fn bridged() -> H160 {
+	H160::repeat_byte(0x33)
+}
+
+/// Backend intercepting calls to `bridged()` with a fixed response.
+struct BridgeBackend<'vicinity> {
+	inner: MemoryBackend<'vicinity>,
+	response: CallResponse,
+	requests: RefCell<Vec<CallRequest>>,
+}
+
+impl Keccak for BridgeBackend<'_> {
+	fn keccak256_h256(&self, data: &[u8]) -> H256 { self.inner.keccak256_h256(data) }
+}
+
+impl Backend for BridgeBackend<'_> {
+	fn gas_price(&self) -> U256 { self.inner.gas_price() }
+	fn origin(&self) -> H160 { self.inner.origin() }
+	fn block_hash(&self, number: U256) -> H256 { self.inner.block_hash(number) }
+	fn block_number(&self) -> U256 { self.inner.block_number() }
+	fn block_coinbase(&self) -> H160 { self.inner.block_coinbase() }
+	fn block_timestamp(&self) -> U256 { self.inner.block_timestamp() }
+	fn block_difficulty(&self) -> U256 { self.inner.block_difficulty() }
+	fn block_gas_limit(&self) -> U256 { self.inner.block_gas_limit() }
+	fn chain_id(&self) -> U256 { self.inner.chain_id() }
+	fn exists(&self, address: H160) -> bool { self.inner.exists(address) }
+	fn basic(&self, address: H160) -> Basic { self.inner.basic(address) }
+	fn code_hash(&self, address: H160) -> H256 { self.inner.code_hash(address) }
+	fn code_size(&self, address: H160) -> usize { self.inner.code_size(address) }
+	fn code(&self, address: H160) -> Vec<u8> { self.inner.code(address) }
+	fn valids(&self, address: H160) -> Vec<u8> { self.inner.valids(address) }
+	fn storage(&self, address: H160, index: U256) -> U256 { self.inner.storage(address, index) }
+	fn create(&self, scheme: &CreateScheme, address: &H160) { self.inner.create(scheme, address) }
+
+	fn call_interceptor(&self) -> Option<&dyn CallInterceptor> {
+		Some(self)
+	}
+}
+
+impl CallInterceptor for BridgeBackend<'_> {
+	fn intercept(&self, call: &CallRequest) -> Option<CallResponse> {
+		self.requests.borrow_mut().push(call.clone());
+		if call.code_address == bridged() {
+			Some(self.response.clone())
+		} else {
+			None
+		}
+	}
+}
+
+fn backend(vicinity: &MemoryVicinity, response: CallResponse) -> BridgeBackend<'_> {
+	// PUSH1 0 CALLDATALOAD PUSH1 0 SSTORE
+	backend_with_code(vicinity, response, vec![0x60, 0x00, 0x35, 0x60, 0x00, 0x55])
+}
+
+fn backend_with_code(vicinity: &MemoryVicinity, response: CallResponse, code: Vec<u8>) -> BridgeBackend<'_> {
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount {
+		balance: U256::from(1000),
+		..Default::default()
+	});
+	state.insert(contract(), account(code));
+	BridgeBackend {
+		inner: MemoryBackend::new(vicinity, state),
+		response,
+		requests: RefCell::new(Vec::new()),
+	}
+}
+
+#[test]
+fn succeeded_call() {
+	let vicinity = vicinity();
+	let response = CallResponse::new(ExitReason::Succeed(ExitSucceed::Returned), vec![0xaa], 5_000);
+	let backend = backend(&vicinity, response);
+
+	let mut executor = StackExecutor::new(&backend, 100_000);
+	let (reason, output) = executor.transact_call(caller(), bridged(), U256::from(7), vec![0x01], 100_000);
+	assert_eq!(reason, ExitReason::Succeed(ExitSucceed::Returned));
+	assert_eq!(output, vec![0xaa]);
+	assert_eq!(executor.used_gas(), 21_016 + 5_000);
+	assert_eq!(executor.balance(bridged()), U256::from(7));
+
+	let requests = backend.requests.borrow();
+	assert_eq!(requests.len(), 1);
+	let request = &requests[0];
+	assert_eq!(request.input, vec![0x01]);
+	assert_eq!(request.gas_limit, 100_000 - 21_016);
+	assert_eq!(request.context.caller, caller());
+	assert_eq!(request.context.address, bridged());
+	assert_eq!(request.transfer.map(|transfer| transfer.value), Some(U256::from(7)));
+	assert_eq!(request.kind, FrameKind::Transaction);
+	assert!(!request.is_static);
+}
+
+#[test]
+fn refund() {
+	let vicinity = vicinity();
+	let response = CallResponse::new(ExitReason::Succeed(ExitSucceed::Stopped), Vec::new(), 10_000)
+		.with_refund(5_000);
+	let succeeded = backend(&vicinity, response.clone());
+
+	let mut executor = StackExecutor::new(&succeeded, 100_000);
+	let outcome = executor.transact_call_with_outcome(caller(), bridged(), U256::zero(), Vec::new(), 100_000);
+	assert_eq!(outcome.gas_refunded, 5_000);
+	assert_eq!(outcome.gas_used, 21_000 + 10_000 - 5_000);
+
+	let reverted = CallResponse { reason: ExitReason::Revert(ExitRevert::Reverted), ..response };
+	let reverted = backend(&vicinity, reverted);
+	let mut executor = StackExecutor::new(&reverted, 100_000);
+	let outcome = executor.transact_call_with_outcome(caller(), bridged(), U256::zero(), Vec::new(), 100_000);
+	assert_eq!(outcome.gas_refunded, 0);
+	assert_eq!(outcome.gas_used, 21_000 + 10_000);
+}
+
+#[test]
+fn reverted_call() {
+	let vicinity = vicinity();
+	let response = CallResponse::new(ExitReason::Revert(ExitRevert::Reverted), vec![0xbb], 5_000);
+	let backend = backend(&vicinity, response);
+
+	let mut executor = StackExecutor::new(&backend, 100_000);
+	let (reason, output) = executor.transact_call(caller(), bridged(), U256::from(7), Vec::new(), 100_000);
+	assert_eq!(reason, ExitReason::Revert(ExitRevert::Reverted));
+	assert_eq!(output, vec![0xbb]);
+	assert_eq!(executor.used_gas(), 21_000 + 5_000);
+	assert_eq!(executor.balance(bridged()), U256::zero());
+	assert_eq!(executor.balance(caller()), U256::from(1000));
+}
+
+#[test]
+fn failed_call() {
+	let vicinity = vicinity();
+	let response = CallResponse::new(ExitReason::Error(ExitError::InvalidRange), Vec::new(), 5_000);
+	let backend = backend(&vicinity, response);
+
+	let mut executor = StackExecutor::new(&backend, 100_000);
+	let (reason, _) = executor.transact_call(caller(), bridged(), U256::from(7), Vec::new(), 100_000);
+	assert_eq!(reason, ExitReason::Error(ExitError::InvalidRange));
+	assert_eq!(executor.used_gas(), 100_000);
+	assert_eq!(executor.balance(bridged()), U256::zero());
+}
+
+#[test]
+fn call_out_of_gas() {
+	let vicinity = vicinity();
+	let response = CallResponse::new(ExitReason::Succeed(ExitSucceed::Stopped), Vec::new(), 80_000);
+	let backend = backend(&vicinity, response);
+
+	let mut executor = StackExecutor::new(&backend, 100_000);
+	let (reason, _) = executor.transact_call(caller(), bridged(), U256::from(7), Vec::new(), 100_000);
+	assert_eq!(reason, ExitReason::Error(ExitError::OutOfGas));
+	assert_eq!(executor.used_gas(), 100_000);
+	assert_eq!(executor.balance(bridged()), U256::zero());
+}
+
+#[test]
+fn declined_call() {
+	let vicinity = vicinity();
+	let response = CallResponse::new(ExitReason::Succeed(ExitSucceed::Stopped), Vec::new(), 0);
+	let backend = backend(&vicinity, response);
+
+	let mut input = vec![0; 32];
+	input[31] = 7;
+	let mut executor = StackExecutor::new(&backend, 100_000);
+	let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), input.clone(), 100_000);
+	assert_eq!(reason, ExitReason::Succeed(ExitSucceed::Stopped));
+	assert_eq!(executor.storage(contract(), U256::zero()), U256::from(7));
+	assert_eq!(backend.requests.borrow()[0].input, input);
+}
+
+#[test]
+fn static_call_from_code() {
+	// PUSH1 0 PUSH1 0 PUSH1 0 PUSH1 0 PUSH20 bridged() PUSH2 10000 STATICCALL
+	let mut code = vec![0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x73];
+	code.extend_from_slice(bridged().as_bytes());
+	code.extend_from_slice(&[0x61, 0x27, 0x10, 0xfa]);
+
+	let vicinity = vicinity();
+	let response = CallResponse::new(ExitReason::Succeed(ExitSucceed::Stopped), Vec::new(), 1_000);
+	let backend = backend_with_code(&vicinity, response, code);
+
+	let mut executor = StackExecutor::new(&backend, 100_000);
+	let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 100_000);
+	assert_eq!(reason, ExitReason::Succeed(ExitSucceed::Stopped));
+
+	let requests = backend.requests.borrow();
+	assert_eq!(requests.len(), 2);
+	let request = &requests[1];
+	assert_eq!(request.kind, FrameKind::Call);
+	assert!(request.is_static);
+	assert_eq!(request.gas_limit, 10_000);
+	assert_eq!(request.context.caller, contract());
+	assert_eq!(request.depth, requests[0].depth + 1);
+	// Six pushes, the Istanbul `STATICCALL` and the intercepted gas.
+	assert_eq!(executor.used_gas(), 21_000 + 6 * 3 + 700 + 1_000);
+}
+
+#[test]
+fn traced_gas() {
+	// PUSH1 0 PUSH1 0 PUSH1 0 PUSH1 0 PUSH20 bridged() PUSH2 10000 STATICCALL
+	let mut code = vec![0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x73];
+	code.extend_from_slice(bridged().as_bytes());
+	code.extend_from_slice(&[0x61, 0x27, 0x10, 0xfa]);
+
+	let vicinity = vicinity();
+	let response = CallResponse::new(ExitReason::Revert(ExitRevert::Reverted), Vec::new(), 1_234);
+	let backend = backend_with_code(&vicinity, response, code);
+
+	let tracer = CallTracer::new();
+	let mut executor = StackExecutor::new(&backend, 100_000).with_listener(&tracer);
+	let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 100_000);
+	assert_eq!(reason, ExitReason::Succeed(ExitSucceed::Stopped));
+
+	let traces = tracer.traces();
+	let frame = &traces[0];
+	assert_eq!(frame.calls.len(), 1);
+	assert_eq!(frame.calls[0].gas, 10_000);
+	assert_eq!(frame.calls[0].gas_used, 1_234);
+	assert_eq!(frame.gas_used, 6 * 3 + 700 + 1_234);
+	assert_eq!(executor.used_gas(), 21_000 + frame.gas_used);
+}
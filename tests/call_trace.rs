@@ -0,0 +1,189 @@
+mod common;
+
+use std::collections::BTreeMap;
+use evm::{ExitReason, ExitRevert, ExitSucceed, H160, H256, U256};
+use evm::backend::{MemoryAccount, MemoryBackend, MemoryVicinity};
+use evm::call_trace::{CallKind, CallTracer};
+use evm::executor::StackExecutor;
+use evm::tracing::TraceConfig;
+use common::{account, callee, caller, contract, vicinity};
+
+fn reverter() -> H160 {
+	H160::repeat_byte(0x44)
+}
+
+/// Call `target` with the given opcode, all gas and 32 bytes of output, then
+/// pop the success flag.
+fn call(opcode: u8, target: H160) -> Vec<u8> {
+	let mut code = vec![0x60, 0x20, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x73];
+	code.extend_from_slice(target.as_bytes());
+	code.extend_from_slice(&[0x5a, opcode, 0x50]);
+	code
+}
+
+fn invalid() -> H160 {
+	H160::repeat_byte(0x55)
+}
+
+/// `CALL` to `target` transferring `value`, with 1000 gas and no input nor
+/// output, then pop the success flag.
+fn call_with_value(target: H160, value: u8) -> Vec<u8> {
+	let mut code = vec![0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, value, 0x73];
+	code.extend_from_slice(target.as_bytes());
+	code.extend_from_slice(&[0x61, 0x03, 0xe8, 0xf1, 0x50]);
+	code
+}
+
+fn backend(vicinity: &MemoryVicinity) -> MemoryBackend<'_> {
+	let mut code = call(0xfa, callee());
+	code.extend(call(0xf4, reverter()));
+	code.push(0x00);
+
+	let mut state = BTreeMap::new();
+	state.insert(contract(), account(code));
+	// PUSH1 1 PUSH1 0 MSTORE PUSH1 32 PUSH1 0 RETURN
+	let returns = vec![0x60, 0x01, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3];
+	state.insert(callee(), account(returns));
+	// PUSH1 0 PUSH1 0 REVERT
+	let reverts = vec![0x60, 0x00, 0x60, 0x00, 0xfd];
+	state.insert(reverter(), account(reverts));
+	MemoryBackend::new(vicinity, state)
+}
+
+#[test]
+fn call_tree() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let tracer = CallTracer::new();
+	let mut executor = StackExecutor::new(&backend, 100_000).with_listener(&tracer);
+	let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), vec![0xab], 100_000);
+	assert!(reason.is_succeed());
+
+	let traces = tracer.traces();
+	assert_eq!(traces.len(), 1);
+	let root = &traces[0];
+	assert_eq!((root.kind, root.from, root.to), (CallKind::Call, caller(), contract()));
+	assert_eq!(root.value, Some(U256::zero()));
+	assert_eq!(root.input, vec![0xab]);
+	assert_eq!(root.gas, 100_000 - 21_016);
+	assert_eq!(root.gas_used, executor.used_gas() - 21_016);
+	assert_eq!(root.reason, Some(ExitReason::Succeed(ExitSucceed::Stopped)));
+	assert_eq!(root.calls.len(), 2);
+
+	let staticcall = &root.calls[0];
+	assert_eq!((staticcall.kind, staticcall.from, staticcall.to), (CallKind::StaticCall, contract(), callee()));
+	assert_eq!(staticcall.value, None);
+	// 5 opcodes of 3 gas and a word of memory.
+	assert_eq!(staticcall.gas_used, 18);
+	assert_eq!(staticcall.output, H256::from(U256::one()).as_bytes());
+	assert_eq!(staticcall.reason, Some(ExitReason::Succeed(ExitSucceed::Returned)));
+
+	let delegatecall = &root.calls[1];
+	assert_eq!((delegatecall.kind, delegatecall.from, delegatecall.to), (CallKind::DelegateCall, contract(), reverter()));
+	assert_eq!(delegatecall.gas_used, 6);
+	assert_eq!(delegatecall.reason, Some(ExitReason::Revert(ExitRevert::Reverted)));
+	assert!(delegatecall.calls.is_empty());
+}
+
+#[test]
+fn geth_json() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let tracer = CallTracer::new();
+	let mut executor = StackExecutor::new(&backend, 100_000).with_listener(&tracer);
+	let _ = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 100_000);
+
+	let json = tracer.traces()[0].geth_json();
+	assert!(json.starts_with(&format!(
+		"{{\"type\":\"CALL\",\"from\":\"{:?}\",\"to\":\"{:?}\",\"value\":\"0x0\",\"gas\":\"{:#x}\"",
+		caller(), contract(), 100_000 - 21_000,
+	)));
+	assert!(json.contains(&format!(
+		"{{\"type\":\"STATICCALL\",\"from\":\"{:?}\",\"to\":\"{:?}\",\"gas\":",
+		contract(), callee(),
+	)));
+	assert!(json.contains(&format!(
+		"\"gasUsed\":\"0x12\",\"input\":\"0x\",\"output\":\"0x{}01\"}}",
+		"00".repeat(31),
+	)));
+	assert!(json.contains("\"type\":\"DELEGATECALL\""));
+	assert!(json.ends_with("\"gasUsed\":\"0x6\",\"input\":\"0x\",\"error\":\"execution reverted\"}]}"));
+}
+
+#[test]
+fn parity_json() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let tracer = CallTracer::new();
+	let mut executor = StackExecutor::new(&backend, 100_000).with_listener(&tracer);
+	let _ = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 100_000);
+
+	let json = tracer.traces()[0].parity_json();
+	assert!(json.starts_with(&format!(
+		"[{{\"action\":{{\"callType\":\"call\",\"from\":\"{:?}\",\"gas\":\"{:#x}\",\"input\":\"0x\",\"to\":\"{:?}\",\"value\":\"0x0\"}},\"result\":{{\"gasUsed\":",
+		caller(), 100_000 - 21_000, contract(),
+	)));
+	assert!(json.contains("\"subtraces\":2,\"traceAddress\":[],\"type\":\"call\"}"));
+	assert!(json.contains("\"callType\":\"staticcall\""));
+	assert!(json.contains("\"subtraces\":0,\"traceAddress\":[0],\"type\":\"call\"}"));
+	assert!(json.ends_with("\"error\":\"Reverted\",\"subtraces\":0,\"traceAddress\":[1],\"type\":\"call\"}]"));
+}
+
+#[test]
+fn create_trace() {
+	let vicinity = vicinity();
+	let backend = MemoryBackend::new(&vicinity, BTreeMap::new());
+	let tracer = CallTracer::new();
+	let mut executor = StackExecutor::new(&backend, 100_000).with_listener(&tracer);
+	// PUSH1 0 PUSH1 0 RETURN
+	let init_code = vec![0x60, 0x00, 0x60, 0x00, 0xf3];
+	let reason = executor.transact_create(caller(), U256::zero(), init_code.clone(), 100_000);
+	assert!(reason.is_succeed());
+
+	let trace = &tracer.traces()[0];
+	assert_eq!(trace.kind, CallKind::Create);
+	assert_eq!(trace.from, caller());
+	assert_eq!(trace.input, init_code);
+	assert_eq!(trace.gas_used, 6);
+	assert!(trace.geth_json().starts_with("{\"type\":\"CREATE\""));
+	let json = trace.parity_json();
+	assert!(json.contains(&format!("\"result\":{{\"address\":\"{:?}\",\"gasUsed\":\"0x6\"}}", trace.to)));
+	assert!(json.ends_with("\"traceAddress\":[],\"type\":\"create\"}]"));
+}
+
+#[test]
+fn frame_gas() {
+	let mut code = call_with_value(callee(), 1);
+	code.extend(call_with_value(invalid(), 0));
+
+	let vicinity = vicinity();
+	let mut state = BTreeMap::new();
+	state.insert(contract(), MemoryAccount { nonce: U256::one(), balance: U256::one(), code, ..Default::default() });
+	// PUSH1 1 PUSH1 0 MSTORE PUSH1 32 PUSH1 0 RETURN
+	let returns = vec![0x60, 0x01, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3];
+	state.insert(callee(), account(returns));
+	state.insert(invalid(), account(vec![0xfe]));
+	let backend = MemoryBackend::new(&vicinity, state);
+
+	// The gas accounting is part of the exit events, without `FrameGas`.
+	let tracer = CallTracer::new();
+	let mut executor = StackExecutor::new(&backend, 100_000)
+		.with_listener(&tracer)
+		.with_trace_config(TraceConfig::calls());
+	let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 100_000);
+	assert!(reason.is_succeed());
+
+	let root = &tracer.traces()[0];
+	assert_eq!((root.gas, root.stipend), (100_000 - 21_000, 0));
+	assert_eq!(root.gas_used, executor.used_gas() - 21_000);
+	assert_eq!(root.gas_returned, root.gas - root.gas_used);
+
+	let paid = &root.calls[0];
+	assert_eq!((paid.gas, paid.stipend), (1_000 + 2_300, 2_300));
+	assert_eq!(paid.gas_used, 18);
+	assert_eq!(paid.gas_returned, 1_000 + 2_300 - 18);
+
+	let failed = &root.calls[1];
+	assert_eq!((failed.gas, failed.stipend), (1_000, 0));
+	assert_eq!((failed.gas_used, failed.gas_returned), (1_000, 0));
+}
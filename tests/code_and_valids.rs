@@ -0,0 +1,93 @@
+mod common;
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use evm::{CreateScheme, Keccak, H160, H256, U256};
+use evm::backend::{Backend, Basic, MemoryAccount, MemoryBackend};
+use evm::executor::StackExecutor;
+use common::{account, callee, caller, contract, vicinity};
+
+/// Backend serving code and valids in a single query, recording the code
+/// queries made.
+struct RemoteBackend<'vicinity> {
+	inner: MemoryBackend<'vicinity>,
+	queries: RefCell<Vec<(&'static str, H160)>>,
+}
+
+impl Keccak for RemoteBackend<'_> {
+	fn keccak256_h256(&self, data: &[u8]) -> H256 { self.inner.keccak256_h256(data) }
+}
+
+impl Backend for RemoteBackend<'_> {
+	fn gas_price(&self) -> U256 { self.inner.gas_price() }
+	fn origin(&self) -> H160 { self.inner.origin() }
+	fn block_hash(&self, number: U256) -> H256 { self.inner.block_hash(number) }
+	fn block_number(&self) -> U256 { self.inner.block_number() }
+	fn block_coinbase(&self) -> H160 { self.inner.block_coinbase() }
+	fn block_timestamp(&self) -> U256 { self.inner.block_timestamp() }
+	fn block_difficulty(&self) -> U256 { self.inner.block_difficulty() }
+	fn block_gas_limit(&self) -> U256 { self.inner.block_gas_limit() }
+	fn chain_id(&self) -> U256 { self.inner.chain_id() }
+	fn exists(&self, address: H160) -> bool { self.inner.exists(address) }
+	fn basic(&self, address: H160) -> Basic { self.inner.basic(address) }
+	fn code_hash(&self, address: H160) -> H256 { self.inner.code_hash(address) }
+	fn code_size(&self, address: H160) -> usize { self.inner.code_size(address) }
+
+	fn code(&self, address: H160) -> Vec<u8> {
+		self.queries.borrow_mut().push(("code", address));
+		self.inner.code(address)
+	}
+
+	fn valids(&self, address: H160) -> Vec<u8> {
+		self.queries.borrow_mut().push(("valids", address));
+		self.inner.valids(address)
+	}
+
+	fn code_and_valids(&self, address: H160) -> (Vec<u8>, Vec<u8>) {
+		self.queries.borrow_mut().push(("code_and_valids", address));
+		self.inner.code_and_valids(address)
+	}
+
+	fn storage(&self, address: H160, index: U256) -> U256 { self.inner.storage(address, index) }
+	fn create(&self, scheme: &CreateScheme, address: &H160) { self.inner.create(scheme, address) }
+}
+
+/// Calls `callee()`, which jumps over an invalid opcode, so that its valids
+/// are needed.
+fn code() -> Vec<u8> {
+	let mut code = vec![0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x73];
+	code.extend_from_slice(callee().as_bytes());
+	code.extend_from_slice(&[0x5a, 0xf1, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3]);
+	code
+}
+
+/// `PUSH1 4 JUMP INVALID JUMPDEST STOP`.
+const CALLEE_CODE: [u8; 6] = [0x60, 0x04, 0x56, 0xfe, 0x5b, 0x00];
+
+#[test]
+fn single_query_per_frame() {
+	let vicinity = vicinity();
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount::default());
+	state.insert(contract(), account(code()));
+	state.insert(callee(), account(CALLEE_CODE.to_vec()));
+	let backend = RemoteBackend { inner: MemoryBackend::new(&vicinity, state), queries: RefCell::new(Vec::new()) };
+
+	let mut executor = StackExecutor::new(&backend, 1_000_000);
+	let (reason, out) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 1_000_000);
+	assert!(reason.is_succeed());
+	assert_eq!(U256::from_big_endian(&out), U256::one());
+	assert_eq!(*backend.queries.borrow(), vec![("code_and_valids", contract()), ("code_and_valids", callee())]);
+}
+
+#[test]
+fn memory_backend_matches_separate_queries() {
+	let vicinity = vicinity();
+	let mut state = BTreeMap::new();
+	state.insert(callee(), MemoryAccount { code: CALLEE_CODE.to_vec(), ..Default::default() });
+	let backend = MemoryBackend::new(&vicinity, state);
+
+	for address in [callee(), caller()] {
+		assert_eq!(backend.code_and_valids(address), (backend.code(address), backend.valids(address)));
+	}
+}
@@ -0,0 +1,90 @@
+mod common;
+
+use std::collections::BTreeMap;
+use evm::{Keccak, H160, H256, U256};
+use evm::backend::{Apply, MemoryAccount, MemoryBackend, MemoryVicinity};
+use evm::executor::StackExecutor;
+use common::{caller, contract, vicinity};
+
+/// Returns 1.
+fn target() -> H160 {
+	H160::repeat_byte(0x33)
+}
+
+/// Returns `value`.
+fn returning(value: u8) -> Vec<u8> {
+	// PUSH1 value PUSH1 0 MSTORE PUSH1 32 PUSH1 0 RETURN
+	vec![0x60, value, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3]
+}
+
+/// Code of `contract()`, which calls `target`, then returns the first word of
+/// its output, and its code size and hash.
+fn contract_code() -> Vec<u8> {
+	// PUSH1 32 PUSH1 0 PUSH1 0 PUSH1 0 PUSH1 0 PUSH20 target() PUSH2 0xffff CALL POP
+	let mut code = vec![0x60, 0x20, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x73];
+	code.extend_from_slice(target().as_bytes());
+	code.extend_from_slice(&[0x61, 0xff, 0xff, 0xf1, 0x50]);
+	// PUSH20 target() EXTCODESIZE PUSH1 32 MSTORE
+	code.push(0x73);
+	code.extend_from_slice(target().as_bytes());
+	code.extend_from_slice(&[0x3b, 0x60, 0x20, 0x52]);
+	// PUSH20 target() EXTCODEHASH PUSH1 64 MSTORE
+	code.push(0x73);
+	code.extend_from_slice(target().as_bytes());
+	code.extend_from_slice(&[0x3f, 0x60, 0x40, 0x52]);
+	// PUSH1 96 PUSH1 0 RETURN
+	code.extend_from_slice(&[0x60, 0x60, 0x60, 0x00, 0xf3]);
+	code
+}
+
+fn backend(vicinity: &MemoryVicinity, target_code: Option<Vec<u8>>) -> MemoryBackend<'_> {
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount::default());
+	state.insert(contract(), MemoryAccount { code: contract_code(), ..Default::default() });
+	if let Some(code) = target_code {
+		state.insert(target(), MemoryAccount { code, ..Default::default() });
+	}
+	MemoryBackend::new(vicinity, state)
+}
+
+/// Output of `contract()`: the output of `target()`, its code size and hash.
+fn run(executor: &mut StackExecutor<'_, MemoryBackend<'_>>) -> (U256, usize, H256) {
+	let (reason, out) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 1_000_000);
+	assert!(reason.is_succeed());
+	(U256::from_big_endian(&out[..32]), U256::from_big_endian(&out[32..64]).as_usize(), H256::from_slice(&out[64..]))
+}
+
+#[test]
+fn replaces_code_across_transactions() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity, Some(returning(1)));
+	let shim = [returning(2), vec![0x00]].concat();
+	let hash = backend.keccak256_h256(&shim);
+
+	let mut executor = StackExecutor::new(&backend, 1_000_000);
+	assert_eq!(run(&mut executor).0, U256::one());
+
+	let mut executor = StackExecutor::new(&backend, 1_000_000).with_code_override(target(), shim.clone());
+	assert_eq!(executor.code_override(target()), Some(&shim[..]));
+	assert_eq!(run(&mut executor), (U256::from(2), shim.len(), hash));
+
+	// The override is kept for the next transaction, and never committed.
+	executor.reset_for_next_transaction(1_000_000);
+	assert_eq!(run(&mut executor), (U256::from(2), shim.len(), hash));
+	let (applies, _) = executor.deconstruct();
+	assert!(applies.iter().all(|apply| match apply {
+		Apply::Modify { address, code_and_valids, .. } => *address != target() || code_and_valids.is_none(),
+		Apply::Delete { .. } => true,
+	}));
+}
+
+#[test]
+fn missing_account() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity, None);
+	let shim = returning(3);
+	let hash = backend.keccak256_h256(&shim);
+
+	let mut executor = StackExecutor::new(&backend, 1_000_000).with_code_override(target(), shim.clone());
+	assert_eq!(run(&mut executor), (U256::from(3), shim.len(), hash));
+}
@@ -0,0 +1,108 @@
+mod common;
+
+use std::collections::BTreeMap;
+use evm::{CollisionKind, ExitError, ExitReason, Handler, H160, U256};
+use evm::backend::{MemoryAccount, MemoryBackend, MemoryVicinity};
+use evm::executor::{CollisionChecks, CollisionPolicy, StackExecutor};
+use common::{caller, contract, vicinity};
+
+fn used_nonce() -> H160 {
+	H160::repeat_byte(0x33)
+}
+
+fn used_storage() -> H160 {
+	H160::repeat_byte(0x44)
+}
+
+fn system() -> H160 {
+	H160::repeat_byte(0xff)
+}
+
+// PUSH1 0xaa PUSH1 0 MSTORE8 PUSH1 1 PUSH1 0 RETURN
+const INIT_CODE: [u8; 10] = [0x60, 0xaa, 0x60, 0x00, 0x53, 0x60, 0x01, 0x60, 0x00, 0xf3];
+
+fn backend(vicinity: &MemoryVicinity) -> MemoryBackend<'_> {
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount::default());
+	state.insert(system(), MemoryAccount::default());
+	state.insert(contract(), MemoryAccount {
+		code: vec![0x00],
+		..Default::default()
+	});
+	state.insert(used_nonce(), MemoryAccount {
+		nonce: U256::one(),
+		..Default::default()
+	});
+	let mut storage = BTreeMap::new();
+	storage.insert(U256::zero(), U256::one());
+	state.insert(used_storage(), MemoryAccount {
+		storage,
+		..Default::default()
+	});
+	MemoryBackend::new(vicinity, state)
+}
+
+/// Lets the system address deploy over existing accounts.
+struct SystemDeployments;
+
+impl CollisionPolicy for SystemDeployments {
+	fn checks(&self, caller: H160, _address: H160) -> CollisionChecks {
+		if caller == system() {
+			CollisionChecks::NONE
+		} else {
+			CollisionChecks::EIP684
+		}
+	}
+}
+
+#[test]
+fn eip684_checks() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let mut executor = StackExecutor::new(&backend, 1_000_000);
+
+	assert_eq!(executor.create_collision(caller(), contract()), Some(CollisionKind::Code));
+	assert_eq!(executor.create_collision(caller(), used_nonce()), Some(CollisionKind::Nonce));
+	assert_eq!(executor.create_collision(caller(), used_storage()), None);
+
+	let reason = executor.transact_create_fixed(caller(), contract(), U256::zero(), INIT_CODE.to_vec(), 100_000);
+	assert_eq!(reason, ExitReason::Error(ExitError::CreateCollision(CollisionKind::Code)));
+	let reason = executor.transact_create_fixed(caller(), used_nonce(), U256::zero(), INIT_CODE.to_vec(), 100_000);
+	assert_eq!(reason, ExitReason::Error(ExitError::CreateCollision(CollisionKind::Nonce)));
+	let reason = executor.transact_create_fixed(caller(), used_storage(), U256::zero(), INIT_CODE.to_vec(), 100_000);
+	assert!(reason.is_succeed());
+	assert_eq!(executor.code(used_storage()), vec![0xaa]);
+}
+
+#[test]
+fn eip7610_checks() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let mut executor = StackExecutor::new(&backend, 1_000_000).with_collision_policy(&CollisionChecks::EIP7610);
+
+	let reason = executor.transact_create_fixed(caller(), used_storage(), U256::zero(), INIT_CODE.to_vec(), 100_000);
+	assert_eq!(reason, ExitReason::Error(ExitError::CreateCollision(CollisionKind::Storage)));
+	assert_eq!(executor.create_collision(caller(), contract()), Some(CollisionKind::Code));
+
+	// Storage written earlier in the transaction counts as well.
+	let fresh = H160::repeat_byte(0x55);
+	assert_eq!(executor.create_collision(caller(), fresh), None);
+	executor.set_storage(fresh, U256::zero(), U256::one()).unwrap();
+	assert_eq!(executor.create_collision(caller(), fresh), Some(CollisionKind::Storage));
+}
+
+#[test]
+fn relaxed_system_deployments() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let policy = SystemDeployments;
+	let mut executor = StackExecutor::new(&backend, 1_000_000).with_collision_policy(&policy);
+
+	let reason = executor.transact_create_fixed(caller(), contract(), U256::zero(), INIT_CODE.to_vec(), 100_000);
+	assert_eq!(reason, ExitReason::Error(ExitError::CreateCollision(CollisionKind::Code)));
+	assert_eq!(executor.code(contract()), vec![0x00]);
+
+	let reason = executor.transact_create_fixed(system(), contract(), U256::zero(), INIT_CODE.to_vec(), 100_000);
+	assert!(reason.is_succeed());
+	assert_eq!(executor.code(contract()), vec![0xaa]);
+}
@@ -0,0 +1,44 @@
+//! Fixtures shared by the integration tests.
+
+// Each test crate uses a subset of the fixtures.
+#![allow(dead_code)]
+
+use evm::{H160, U256};
+use evm::backend::{MemoryAccount, MemoryVicinity};
+
+/// Block environment at the genesis block, with no gas price and a gas
+/// limit that never binds.
+pub fn vicinity() -> MemoryVicinity {
+	MemoryVicinity {
+		gas_price: U256::zero(),
+		origin: H160::default(),
+		chain_id: U256::one(),
+		block_hashes: Vec::new(),
+		block_number: U256::zero(),
+		block_coinbase: H160::default(),
+		block_timestamp: U256::zero(),
+		block_difficulty: U256::zero(),
+		block_gas_limit: U256::from(u64::MAX),
+	}
+}
+
+pub fn caller() -> H160 {
+	H160::repeat_byte(0x11)
+}
+
+pub fn contract() -> H160 {
+	H160::repeat_byte(0x22)
+}
+
+pub fn callee() -> H160 {
+	H160::repeat_byte(0x33)
+}
+
+/// Deployed contract account running `code`.
+pub fn account(code: Vec<u8>) -> MemoryAccount {
+	MemoryAccount {
+		nonce: U256::one(),
+		code,
+		..Default::default()
+	}
+}
@@ -0,0 +1,61 @@
+mod common;
+
+use std::collections::BTreeMap;
+use evm::{CreateScheme, U256};
+use evm::backend::MemoryBackend;
+use evm::coverage::CoverageCollector;
+use evm::executor::StackExecutor;
+use common::{account, caller, contract, vicinity};
+
+// PUSH1 1 PUSH1 7 JUMPI PUSH1 0 JUMPDEST STOP
+fn code() -> Vec<u8> {
+	vec![0x60, 0x01, 0x60, 0x07, 0x57, 0x60, 0x00, 0x5b, 0x00]
+}
+
+#[test]
+fn deployed_code() {
+	let vicinity = vicinity();
+	let mut state = BTreeMap::new();
+	state.insert(contract(), account(code()));
+	let backend = MemoryBackend::new(&vicinity, state);
+
+	let collector = CoverageCollector::new().with_code(contract(), &code());
+	for _ in 0..2 {
+		let mut executor = StackExecutor::new(&backend, 100_000).with_listener(&collector);
+		let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 100_000);
+		assert!(reason.is_succeed());
+	}
+
+	let coverage = collector.coverage();
+	assert_eq!(coverage.len(), 1);
+	assert_eq!((coverage[0].address, coverage[0].create), (contract(), false));
+	let hits = coverage[0].hits.iter().map(|(position, count)| (*position, *count)).collect::<Vec<_>>();
+	assert_eq!(hits, vec![(0, 2), (2, 2), (4, 2), (5, 0), (7, 2), (8, 2)]);
+	assert_eq!(coverage[0].covered(), 5);
+
+	assert_eq!(collector.json(), format!(
+		"[{{\"address\":\"{:?}\",\"init\":false,\"instructions\":6,\"covered\":5,\"hits\":{{\"0\":2,\"2\":2,\"4\":2,\"5\":0,\"7\":2,\"8\":2}}}}]",
+		contract(),
+	));
+	assert_eq!(collector.lcov(), format!(
+		"SF:{:?}\nDA:1,2\nDA:3,2\nDA:5,2\nDA:6,0\nDA:8,2\nDA:9,2\nLF:6\nLH:5\nend_of_record\n",
+		contract(),
+	));
+}
+
+#[test]
+fn init_code() {
+	let vicinity = vicinity();
+	let backend = MemoryBackend::new(&vicinity, BTreeMap::new());
+	let collector = CoverageCollector::new();
+	let mut executor = StackExecutor::new(&backend, 100_000).with_listener(&collector);
+	let address = executor.create_address(CreateScheme::Legacy { caller: caller() });
+	// PUSH1 0 PUSH1 0 RETURN
+	assert!(executor.transact_create(caller(), U256::zero(), vec![0x60, 0x00, 0x60, 0x00, 0xf3], 100_000).is_succeed());
+
+	let coverage = collector.coverage();
+	assert_eq!(coverage.len(), 1);
+	assert_eq!((coverage[0].address, coverage[0].create), (address, true));
+	assert_eq!(coverage[0].covered(), 3);
+	assert!(collector.lcov().starts_with(&format!("SF:{address:?}:init\n")));
+}
@@ -0,0 +1,124 @@
+mod common;
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use evm::{CreateScheme, Keccak, H160, H256, U256};
+use evm::backend::{Apply, Backend, Basic, MemoryAccount, MemoryBackend, MemoryVicinity};
+use evm::executor::StackExecutor;
+use evm::tracing::{Event, EventListener};
+use common::{account, caller, contract, vicinity};
+
+/// Hashes differently from Keccak-256.
+struct CustomHasher<'vicinity>(MemoryBackend<'vicinity>);
+
+impl Keccak for CustomHasher<'_> {
+	fn keccak256_h256(&self, data: &[u8]) -> H256 {
+		let mut hash = self.0.keccak256_h256(data);
+		hash.0.reverse();
+		hash
+	}
+}
+
+impl Backend for CustomHasher<'_> {
+	fn gas_price(&self) -> U256 { self.0.gas_price() }
+	fn origin(&self) -> H160 { self.0.origin() }
+	fn block_hash(&self, number: U256) -> H256 { self.0.block_hash(number) }
+	fn block_number(&self) -> U256 { self.0.block_number() }
+	fn block_coinbase(&self) -> H160 { self.0.block_coinbase() }
+	fn block_timestamp(&self) -> U256 { self.0.block_timestamp() }
+	fn block_difficulty(&self) -> U256 { self.0.block_difficulty() }
+	fn block_gas_limit(&self) -> U256 { self.0.block_gas_limit() }
+	fn chain_id(&self) -> U256 { self.0.chain_id() }
+	fn exists(&self, address: H160) -> bool { self.0.exists(address) }
+	fn basic(&self, address: H160) -> Basic { self.0.basic(address) }
+	fn code_hash(&self, address: H160) -> H256 { self.0.code_hash(address) }
+	fn code_size(&self, address: H160) -> usize { self.0.code_size(address) }
+	fn code(&self, address: H160) -> Vec<u8> { self.0.code(address) }
+	fn valids(&self, address: H160) -> Vec<u8> { self.0.valids(address) }
+	fn storage(&self, address: H160, index: U256) -> U256 { self.0.storage(address, index) }
+	fn create(&self, scheme: &CreateScheme, address: &H160) { self.0.create(scheme, address) }
+}
+
+/// (address, code hash) of the entered create frames.
+#[derive(Default)]
+struct Recorder(RefCell<Vec<(H160, H256)>>);
+
+impl EventListener for Recorder {
+	fn event(&self, event: Event<'_>) {
+		if let Event::Create { address, code_hash, .. } = event {
+			self.0.borrow_mut().push((address, code_hash));
+		}
+	}
+}
+
+/// Deploys `0x00`.
+const INIT_CODE: [u8; 10] = [0x60, 0x00, 0x60, 0x00, 0x53, 0x60, 0x01, 0x60, 0x00, 0xf3];
+
+/// `CREATE2` of `INIT_CODE` with salt 7, the init code being stored in
+/// memory from the last 10 bytes of a word.
+fn create2_code() -> Vec<u8> {
+	let mut code = vec![0x69];
+	code.extend_from_slice(&INIT_CODE);
+	code.extend_from_slice(&[0x60, 0x00, 0x52, 0x60, 0x07, 0x60, 0x0a, 0x60, 0x16, 0x60, 0x00, 0xf5, 0x00]);
+	code
+}
+
+fn backend(vicinity: &MemoryVicinity) -> CustomHasher<'_> {
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount::default());
+	state.insert(contract(), account(create2_code()));
+	CustomHasher(MemoryBackend::new(vicinity, state))
+}
+
+fn created(applies: Vec<Apply<BTreeMap<U256, U256>>>) -> Vec<H160> {
+	applies.into_iter()
+		.filter_map(|apply| match apply {
+			Apply::Modify { address, code_and_valids: Some((code, _)), .. } if code == [0x00] => Some(address),
+			_ => None,
+		})
+		.collect()
+}
+
+#[test]
+fn transact_create2_uses_backend_hasher() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let code_hash = backend.keccak256_h256(&INIT_CODE);
+	assert_ne!(code_hash, backend.0.keccak256_h256(&INIT_CODE));
+	let salt = H256::repeat_byte(0x07);
+
+	let recorder = Recorder::default();
+	let mut executor = StackExecutor::new(&backend, 1_000_000).with_listener(&recorder);
+	let address = executor.create_address(CreateScheme::Create2 { caller: caller(), code_hash, salt });
+	let reason = executor.transact_create2(caller(), U256::zero(), INIT_CODE.to_vec(), salt, 1_000_000);
+	assert!(reason.is_succeed());
+
+	assert_eq!(*recorder.0.borrow(), vec![(address, code_hash)]);
+	let (applies, _) = executor.deconstruct();
+	assert_eq!(created(applies), vec![address]);
+}
+
+#[test]
+fn create_opcodes_report_code_hash() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let code_hash = backend.keccak256_h256(&INIT_CODE);
+	let mut salt = H256::zero();
+	salt.0[31] = 7;
+
+	let recorder = Recorder::default();
+	let mut executor = StackExecutor::new(&backend, 1_000_000).with_listener(&recorder);
+	let address = executor.create_address(CreateScheme::Create2 { caller: contract(), code_hash, salt });
+	let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 1_000_000);
+	assert!(reason.is_succeed());
+	assert_eq!(*recorder.0.borrow(), vec![(address, code_hash)]);
+	let (applies, _) = executor.deconstruct();
+	assert_eq!(created(applies), vec![address]);
+
+	let recorder = Recorder::default();
+	let mut executor = StackExecutor::new(&backend, 1_000_000).with_listener(&recorder);
+	let address = executor.create_address(CreateScheme::Legacy { caller: caller() });
+	let reason = executor.transact_create(caller(), U256::zero(), INIT_CODE.to_vec(), 1_000_000);
+	assert!(reason.is_succeed());
+	assert_eq!(*recorder.0.borrow(), vec![(address, code_hash)]);
+}
@@ -0,0 +1,69 @@
+mod common;
+
+use std::collections::BTreeMap;
+use evm::{CollisionKind, ExitError, ExitReason, Handler, H160, U256};
+use evm::backend::{MemoryAccount, MemoryBackend, MemoryVicinity};
+use evm::executor::StackExecutor;
+use common::{account, caller, contract, vicinity};
+
+fn funded() -> H160 {
+	H160::repeat_byte(0x33)
+}
+
+fn fixed() -> H160 {
+	H160::repeat_byte(0x44)
+}
+
+/// Deploys `0x00`.
+const INIT_CODE: [u8; 10] = [0x60, 0x00, 0x60, 0x00, 0x53, 0x60, 0x01, 0x60, 0x00, 0xf3];
+
+fn backend(vicinity: &MemoryVicinity) -> MemoryBackend<'_> {
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount {
+		balance: U256::from(10),
+		..Default::default()
+	});
+	state.insert(contract(), account(vec![0x00]));
+	state.insert(funded(), MemoryAccount {
+		balance: U256::from(5),
+		..Default::default()
+	});
+	MemoryBackend::new(vicinity, state)
+}
+
+#[test]
+fn deploys_at_address() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let mut executor = StackExecutor::new(&backend, 1_000_000);
+	let reason = executor.transact_create_fixed(caller(), fixed(), U256::from(3), INIT_CODE.to_vec(), 100_000);
+	assert!(reason.is_succeed());
+	assert_eq!(executor.code(fixed()), vec![0x00]);
+	assert_eq!(executor.balance(fixed()), U256::from(3));
+	assert_eq!(executor.nonce(fixed()), U256::one());
+	assert_eq!(executor.nonce(caller()), U256::one());
+
+	// Accounts holding only a balance are not collisions.
+	let reason = executor.transact_create_fixed(caller(), funded(), U256::zero(), INIT_CODE.to_vec(), 100_000);
+	assert!(reason.is_succeed());
+	assert_eq!(executor.code(funded()), vec![0x00]);
+	assert_eq!(executor.balance(funded()), U256::from(5));
+	assert_eq!(executor.nonce(caller()), U256::from(2));
+}
+
+#[test]
+fn collisions() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let mut executor = StackExecutor::new(&backend, 1_000_000);
+	let reason = executor.transact_create_fixed(caller(), contract(), U256::zero(), INIT_CODE.to_vec(), 100_000);
+	assert_eq!(reason, ExitReason::Error(ExitError::CreateCollision(CollisionKind::Code)));
+	assert_eq!(executor.code(contract()), vec![0x00]);
+	assert_eq!(executor.nonce(caller()), U256::one());
+
+	let reason = executor.transact_create_fixed(caller(), fixed(), U256::zero(), INIT_CODE.to_vec(), 100_000);
+	assert!(reason.is_succeed());
+	let reason = executor.transact_create_fixed(caller(), fixed(), U256::zero(), INIT_CODE.to_vec(), 100_000);
+	assert_eq!(reason, ExitReason::Error(ExitError::CreateCollision(CollisionKind::Code)));
+	assert_eq!(executor.nonce(caller()), U256::from(3));
+}
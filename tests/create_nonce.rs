@@ -0,0 +1,76 @@
+mod common;
+
+use std::collections::BTreeMap;
+use evm::{CollisionKind, ExitError, ExitReason, ExitRevert, ExitSucceed, Handler, H160, U256};
+use evm::backend::{MemoryAccount, MemoryBackend};
+use evm::executor::StackExecutor;
+use common::{caller, vicinity};
+
+fn state() -> BTreeMap<H160, MemoryAccount> {
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount {
+		nonce: U256::one(),
+		balance: U256::from(1_000_000),
+		storage: BTreeMap::new(),
+		code: Vec::new(),
+	});
+	state
+}
+
+fn create_address(backend: &MemoryBackend) -> H160 {
+	let executor = StackExecutor::new(backend, 1_000_000);
+	executor.create_address(evm::CreateScheme::Legacy { caller: caller() })
+}
+
+macro_rules! nonce_test {
+	( $name:ident, $init_code:expr, $gas_limit:expr, $collide:expr, $reason:expr, $created_nonce:expr ) => (
+		#[test]
+		fn $name() {
+			let vicinity = vicinity();
+			let mut backend = MemoryBackend::new(&vicinity, state());
+			let address = create_address(&backend);
+			if $collide {
+				let mut state = state();
+				state.insert(address, MemoryAccount {
+					nonce: U256::one(),
+					..Default::default()
+				});
+				backend = MemoryBackend::new(&vicinity, state);
+			}
+
+			let mut executor = StackExecutor::new(&backend, $gas_limit);
+			let reason = executor.transact_create(caller(), U256::zero(), $init_code, $gas_limit);
+
+			assert_eq!(reason, $reason);
+			assert_eq!(executor.nonce(caller()), U256::from(2));
+			assert_eq!(executor.nonce(address), U256::from($created_nonce));
+		}
+	);
+}
+
+// STOP
+nonce_test!(create_succeed, vec![0x00], 100_000, false,
+	ExitReason::Succeed(ExitSucceed::Stopped), 1);
+// PUSH1 0 PUSH1 0 REVERT
+nonce_test!(create_revert, vec![0x60, 0x00, 0x60, 0x00, 0xfd], 100_000, false,
+	ExitReason::Revert(ExitRevert::Reverted), 0);
+// JUMPDEST PUSH1 0 JUMP
+nonce_test!(create_out_of_gas, vec![0x5b, 0x60, 0x00, 0x56], 100_000, false,
+	ExitReason::Error(ExitError::OutOfGas), 0);
+// STOP, with an account already at the create address
+nonce_test!(create_collision, vec![0x00], 100_000, true,
+	ExitReason::Error(ExitError::CreateCollision(CollisionKind::Nonce)), 1);
+
+#[test]
+fn create_out_of_fund_keeps_nonce() {
+	let vicinity = vicinity();
+	let backend = MemoryBackend::new(&vicinity, state());
+	let address = create_address(&backend);
+
+	let mut executor = StackExecutor::new(&backend, 100_000);
+	let reason = executor.transact_create(caller(), U256::from(2_000_000), vec![0x00], 100_000);
+
+	assert_eq!(reason, ExitReason::Error(ExitError::OutOfFund));
+	assert_eq!(executor.nonce(caller()), U256::one());
+	assert!(!executor.exists(address));
+}
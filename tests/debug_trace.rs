@@ -0,0 +1,64 @@
+#![cfg(feature = "debug-trace")]
+
+mod common;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use evm::{DebugSink, Machine, Valids, WriterSink, U256};
+use evm::backend::{MemoryAccount, MemoryBackend};
+use evm::executor::StackExecutor;
+use common::{account, caller, contract, vicinity};
+
+/// Keeps the mnemonics only.
+#[derive(Default)]
+struct Names(Mutex<Vec<&'static str>>);
+
+impl DebugSink for Names {
+	fn op(&self, name: &'static str, _operands: fmt::Arguments<'_>) {
+		self.0.lock().unwrap().push(name);
+	}
+}
+
+/// `PUSH1 2 PUSH1 3 ADD DUP1 PUSH1 0 MSTORE8 POP PC STOP`.
+const CODE: [u8; 13] = [0x60, 0x02, 0x60, 0x03, 0x01, 0x80, 0x60, 0x00, 0x53, 0x50, 0x58, 0x00, 0x00];
+
+#[test]
+fn machine_output() {
+	let sink = Arc::new(WriterSink::new(Vec::new()));
+	let mut machine = Machine::new(CODE.to_vec(), Arc::new(Valids::compute(&CODE).into()), Vec::new(), 1024, usize::MAX);
+	machine.set_debug_sink(sink.clone());
+	let _ = machine.run(u64::MAX, |_, _, _, _| Ok(()));
+	drop(machine);
+
+	let output = String::from_utf8(Arc::try_unwrap(sink).ok().unwrap().into_inner()).unwrap();
+	assert_eq!(output, "\
+PUSH 1 [@0]: 2
+PUSH 1 [@1]: 3
+ADD 3, 2: 5
+DUP 1 [@2]
+PUSH 1 [@2]: 0
+MSTORE8 0, 5
+POP [@0]: 5
+PC 10
+");
+}
+
+#[test]
+fn executor_reports_every_frame() {
+	let vicinity = vicinity();
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount::default());
+	state.insert(contract(), account(CODE.to_vec()));
+	let backend = MemoryBackend::new(&vicinity, state);
+
+	let names = Arc::new(Names::default());
+	let mut executor = StackExecutor::new(&backend, 100_000).with_debug_sink(names.clone());
+	for _ in 0..2 {
+		let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 100_000);
+		assert!(reason.is_succeed());
+	}
+
+	let once = ["PUSH", "PUSH", "ADD", "DUP", "PUSH", "MSTORE8", "POP", "PC"];
+	assert_eq!(*names.0.lock().unwrap(), [once, once].concat());
+}
@@ -0,0 +1,105 @@
+mod common;
+
+use std::collections::BTreeMap;
+use evm::{Config, Evm, ExitError, ExitReason, Istanbul, Spec, U256};
+use evm::backend::{Backend, MemoryAccount, MemoryBackend, MemoryVicinity};
+use common::{caller, vicinity};
+
+/// Istanbul with EIP-3541.
+#[derive(Clone, Copy, Debug, Default)]
+struct RejectEf;
+
+impl Spec for RejectEf {
+	const CONFIG: Config = Config {
+		reject_ef_code: true,
+		..Config::istanbul()
+	};
+}
+
+/// Istanbul with EIP-3541 and a limit of 4 bytes of code.
+#[derive(Clone, Copy, Debug, Default)]
+struct SmallContracts;
+
+impl Spec for SmallContracts {
+	const CONFIG: Config = Config {
+		reject_ef_code: true,
+		create_contract_limit: Some(4),
+		..Config::istanbul()
+	};
+}
+
+/// Istanbul with a doubled code size limit, as raised by some rollups.
+#[derive(Clone, Copy, Debug, Default)]
+struct LargeContracts;
+
+impl Spec for LargeContracts {
+	const CONFIG: Config = Config {
+		create_contract_limit: Some(2 * 0x6000),
+		..Config::istanbul()
+	};
+}
+
+/// Init code deploying `len` bytes, the first one being `first`.
+fn deploy(first: u8, len: u16) -> Vec<u8> {
+	// PUSH1 first PUSH1 0 MSTORE8 PUSH2 len PUSH1 0 RETURN
+	let [high, low] = len.to_be_bytes();
+	vec![0x60, first, 0x60, 0x00, 0x53, 0x61, high, low, 0x60, 0x00, 0xf3]
+}
+
+fn evm<S: Spec>(vicinity: &MemoryVicinity, spec: S) -> Evm<MemoryBackend<'_>, S> {
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount::default());
+	Evm::new_with_spec(MemoryBackend::new(vicinity, state), spec)
+}
+
+#[test]
+fn ef_prefix() {
+	let vicinity = vicinity();
+
+	let mut evm = evm(&vicinity, Istanbul);
+	let address = evm.create_address(caller());
+	let summary = evm.create(caller(), U256::zero(), deploy(0xef, 1), 100_000);
+	assert!(summary.reason.is_succeed());
+	assert_eq!(evm.backend().code(address), vec![0xef]);
+
+	let mut evm = self::evm(&vicinity, RejectEf);
+	let summary = evm.create(caller(), U256::zero(), deploy(0xef, 1), 100_000);
+	assert_eq!(summary.reason, ExitReason::Error(ExitError::CreateContractStartingWithEF));
+	assert_eq!(summary.used_gas, 100_000);
+	let summary = evm.create(caller(), U256::zero(), deploy(0xee, 1), 100_000);
+	assert!(summary.reason.is_succeed());
+	// Empty code has no prefix.
+	let summary = evm.create(caller(), U256::zero(), deploy(0xef, 0), 100_000);
+	assert!(summary.reason.is_succeed());
+}
+
+#[test]
+fn size_limit() {
+	let vicinity = vicinity();
+
+	let mut evm = evm(&vicinity, SmallContracts);
+	let summary = evm.create(caller(), U256::zero(), deploy(0x00, 4), 100_000);
+	assert!(summary.reason.is_succeed());
+	let summary = evm.create(caller(), U256::zero(), deploy(0x00, 5), 100_000);
+	assert_eq!(summary.reason, ExitReason::Error(ExitError::CreateContractLimit));
+	assert_eq!(summary.used_gas, 100_000);
+	// The size is checked before the prefix.
+	let summary = evm.create(caller(), U256::zero(), deploy(0xef, 5), 100_000);
+	assert_eq!(summary.reason, ExitReason::Error(ExitError::CreateContractLimit));
+}
+
+#[test]
+fn raised_size_limit() {
+	let vicinity = vicinity();
+	let len = 0x6000 + 1;
+
+	let mut evm = evm(&vicinity, Istanbul);
+	let summary = evm.create(caller(), U256::zero(), deploy(0x00, len), 10_000_000);
+	assert_eq!(summary.reason, ExitReason::Error(ExitError::CreateContractLimit));
+
+	let mut evm = self::evm(&vicinity, LargeContracts);
+	let address = evm.create_address(caller());
+	let summary = evm.create(caller(), U256::zero(), deploy(0x00, len), 10_000_000);
+	assert!(summary.reason.is_succeed());
+	assert_eq!(evm.backend().code(address).len(), usize::from(len));
+}
@@ -0,0 +1,70 @@
+mod common;
+
+use std::collections::BTreeMap;
+use evm::{Config, ExitError, ExitReason, Opcode, OpcodeSet, Spec, U256};
+use evm::backend::{MemoryAccount, MemoryBackend};
+use evm::executor::StackExecutor;
+use common::{account, caller, contract, vicinity};
+
+/// Istanbul without `CALLCODE`, `SELFDESTRUCT` and `DIFFICULTY`.
+#[derive(Clone, Copy, Default)]
+struct Restricted;
+
+impl Spec for Restricted {
+	const CONFIG: Config = Config {
+		disabled_opcodes: OpcodeSet::new().with(Opcode::CALLCODE).with(Opcode::SUICIDE).with(Opcode::DIFFICULTY),
+		..Config::istanbul()
+	};
+}
+
+fn run<S: Spec>(code: Vec<u8>, spec: S) -> (ExitReason, u64) {
+	let vicinity = vicinity();
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount::default());
+	state.insert(contract(), account(code));
+	let backend = MemoryBackend::new(&vicinity, state);
+
+	let mut executor = StackExecutor::new_with_spec(&backend, 100_000, |_, _, _| None, spec);
+	let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 100_000);
+	(reason, executor.used_gas())
+}
+
+#[test]
+fn disabled_opcodes_fail() {
+	// `DIFFICULTY`, `POP`.
+	assert!(run(vec![0x44, 0x50], evm::Istanbul).0.is_succeed());
+	assert_eq!(run(vec![0x44, 0x50], Restricted), (ExitReason::Error(ExitError::OpcodeDisabled), 100_000));
+
+	// `SELFDESTRUCT` to the caller.
+	assert!(run(vec![0x33, 0xff], evm::Istanbul).0.is_succeed());
+	assert_eq!(run(vec![0x33, 0xff], Restricted).0, ExitReason::Error(ExitError::OpcodeDisabled));
+
+	// `CALLCODE` to the caller, without value.
+	let callcode = vec![0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x33, 0x5a, 0xf2];
+	assert!(run(callcode.clone(), evm::Istanbul).0.is_succeed());
+	assert_eq!(run(callcode, Restricted).0, ExitReason::Error(ExitError::OpcodeDisabled));
+
+	// Opcodes missing from the hard fork are reported as invalid.
+	assert_eq!(run(vec![0x5f], Restricted).0, ExitReason::Error(ExitError::InvalidOpcode(Opcode(0x5f))));
+}
+
+#[test]
+fn undefined_opcodes_are_invalid() {
+	// `REVERT` before Byzantium.
+	let revert = vec![0x60, 0x00, 0x60, 0x00, 0xfd];
+	assert_eq!(run(revert.clone(), evm::Istanbul).0, ExitReason::Revert(evm::ExitRevert::Reverted));
+	assert_eq!(run(revert, evm::Frontier), (ExitReason::Error(ExitError::InvalidOpcode(Opcode::REVERT)), 100_000));
+
+	// `CHAINID` before Istanbul.
+	assert_eq!(run(vec![0x46], evm::Frontier).0, ExitReason::Error(ExitError::InvalidOpcode(Opcode::CHAINID)));
+}
+
+#[test]
+fn opcode_set() {
+	let set = OpcodeSet::new().with(Opcode::CALLCODE).with(Opcode::STOP).with(Opcode::SUICIDE);
+	assert!(set.contains(Opcode::CALLCODE) && set.contains(Opcode::STOP) && set.contains(Opcode::SUICIDE));
+	assert!(!set.contains(Opcode::CALL));
+	assert!(!set.without(Opcode::CALLCODE).contains(Opcode::CALLCODE));
+	assert!(set.without(Opcode::CALLCODE).without(Opcode::STOP).without(Opcode::SUICIDE).is_empty());
+	assert!(OpcodeSet::default().is_empty());
+}
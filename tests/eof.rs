@@ -0,0 +1,156 @@
+mod common;
+
+use std::collections::BTreeMap;
+use evm::{Config, Eof, EofError, Evm, ExitError, ExitReason, Spec, U256, is_eof, validate_code};
+use evm::backend::{Backend, MemoryAccount, MemoryBackend, MemoryVicinity};
+use common::{account, caller, contract, vicinity};
+
+/// Istanbul with EOF.
+#[derive(Clone, Copy, Debug, Default)]
+struct WithEof;
+
+impl Spec for WithEof {
+	const CONFIG: Config = Config {
+		has_eof: true,
+		..Config::istanbul()
+	};
+}
+
+/// Version 1 container with the given sections, the data section omitted
+/// if empty.
+fn container(code: &[u8], data: &[u8]) -> Vec<u8> {
+	let mut container = vec![0xef, 0x00, 0x01, 0x01];
+	container.extend_from_slice(&(code.len() as u16).to_be_bytes());
+	if !data.is_empty() {
+		container.push(0x02);
+		container.extend_from_slice(&(data.len() as u16).to_be_bytes());
+	}
+	container.push(0x00);
+	container.extend_from_slice(code);
+	container.extend_from_slice(data);
+	container
+}
+
+/// Returns 42 as a word.
+const RETURN_42: [u8; 10] = [0x60, 0x2a, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3];
+
+/// Returns `container(&[0x00], &[])`.
+const DEPLOY_EOF: [u8; 18] = [
+	0x67, 0xef, 0x00, 0x01, 0x01, 0x00, 0x01, 0x00, 0x00,
+	0x60, 0x00, 0x52, 0x60, 0x08, 0x60, 0x18, 0xf3, 0x00,
+];
+
+/// Returns the single byte `0xef`.
+const DEPLOY_EF: [u8; 10] = [0x60, 0xef, 0x60, 0x00, 0x53, 0x60, 0x01, 0x60, 0x00, 0xf3];
+
+fn evm<S: Spec>(vicinity: &MemoryVicinity, spec: S) -> Evm<MemoryBackend<'_>, S> {
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount::default());
+	state.insert(contract(), account(container(&RETURN_42, &[0x01, 0x02])));
+	Evm::new_with_spec(MemoryBackend::new(vicinity, state), spec)
+}
+
+#[test]
+fn parse_container() {
+	let bytes = container(&RETURN_42, &[0x01, 0x02]);
+	assert!(is_eof(&bytes));
+	let eof = Eof::validate(&bytes).unwrap();
+	assert_eq!(eof.version(), 1);
+	assert_eq!(eof.code(), &RETURN_42);
+	assert_eq!(eof.data(), &[0x01, 0x02]);
+
+	let bytes = container(&[0x00], &[]);
+	assert_eq!(Eof::parse(&bytes).unwrap().data(), &[] as &[u8]);
+	assert!(!is_eof(&RETURN_42));
+}
+
+#[test]
+fn invalid_containers() {
+	assert_eq!(Eof::parse(&RETURN_42), Err(EofError::InvalidMagic));
+	assert_eq!(Eof::parse(&[0xef, 0x00]), Err(EofError::IncompleteHeader));
+	assert_eq!(Eof::parse(&[0xef, 0x00, 0x02, 0x01, 0x00, 0x01, 0x00, 0x00]), Err(EofError::UnsupportedVersion(2)));
+	assert_eq!(Eof::parse(&[0xef, 0x00, 0x01, 0x01, 0x00]), Err(EofError::IncompleteHeader));
+	assert_eq!(Eof::parse(&[0xef, 0x00, 0x01, 0x00]), Err(EofError::MissingCodeSection));
+	assert_eq!(Eof::parse(&[0xef, 0x00, 0x01, 0x03, 0x00, 0x01, 0x00, 0x00]), Err(EofError::UnknownSection(3)));
+	assert_eq!(Eof::parse(&[0xef, 0x00, 0x01, 0x01, 0x00, 0x00, 0x00]), Err(EofError::EmptySection(1)));
+	assert_eq!(
+		Eof::parse(&[0xef, 0x00, 0x01, 0x02, 0x00, 0x01, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00]),
+		Err(EofError::InvalidSectionOrder(2)),
+	);
+	assert_eq!(
+		Eof::parse(&[0xef, 0x00, 0x01, 0x01, 0x00, 0x01, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00]),
+		Err(EofError::InvalidSectionOrder(1)),
+	);
+
+	let mut bytes = container(&[0x00], &[]);
+	bytes.push(0x00);
+	assert_eq!(Eof::parse(&bytes), Err(EofError::InvalidContainerSize));
+	bytes.truncate(bytes.len() - 2);
+	assert_eq!(Eof::parse(&bytes), Err(EofError::InvalidContainerSize));
+}
+
+#[test]
+fn code_validation() {
+	assert_eq!(validate_code(&RETURN_42), Ok(()));
+	// INVALID is defined.
+	assert_eq!(validate_code(&[0xfe]), Ok(()));
+	assert_eq!(validate_code(&[0x00, 0x0c]), Err(EofError::UndefinedOpcode { position: 1, opcode: 0x0c }));
+	// Push data is not mistaken for opcodes.
+	assert_eq!(validate_code(&[0x61, 0x0c, 0x0c, 0x00]), Ok(()));
+	assert_eq!(validate_code(&[0x00, 0x61, 0x0c]), Err(EofError::TruncatedPush { position: 1 }));
+	assert_eq!(validate_code(&[0x7f]), Err(EofError::TruncatedPush { position: 0 }));
+
+	let bytes = container(&[0x60], &[]);
+	assert!(Eof::parse(&bytes).is_ok());
+	assert_eq!(Eof::validate(&bytes), Err(EofError::TruncatedPush { position: 0 }));
+}
+
+#[test]
+fn call_runs_code_section() {
+	let vicinity = vicinity();
+	let mut evm = evm(&vicinity, WithEof);
+	let summary = evm.call(caller(), contract(), U256::zero(), Vec::new(), 100_000);
+	assert!(summary.reason.is_succeed());
+	assert_eq!(U256::from_big_endian(&summary.return_value), U256::from(42));
+}
+
+#[test]
+fn create_validates_containers() {
+	let vicinity = vicinity();
+	let mut evm = evm(&vicinity, WithEof);
+
+	let address = evm.create_address(caller());
+	let summary = evm.create(caller(), U256::zero(), container(&DEPLOY_EOF, &[]), 100_000);
+	assert!(summary.reason.is_succeed());
+	assert_eq!(evm.backend().code(address), container(&[0x00], &[]));
+
+	// Invalid init code is rejected before running and consumes all gas.
+	let summary = evm.create(caller(), U256::zero(), container(&[0x0c], &[]), 100_000);
+	assert_eq!(summary.reason, ExitReason::Error(ExitError::InvalidCode));
+	assert_eq!(summary.used_gas, 100_000);
+
+	// EOF init code must deploy a valid container.
+	let summary = evm.create(caller(), U256::zero(), container(&DEPLOY_EF, &[]), 100_000);
+	assert_eq!(summary.reason, ExitReason::Error(ExitError::InvalidCode));
+
+	// Legacy init code must not deploy code starting with 0xEF.
+	let summary = evm.create(caller(), U256::zero(), DEPLOY_EF.to_vec(), 100_000);
+	assert_eq!(summary.reason, ExitReason::Error(ExitError::CreateContractStartingWithEF));
+	let summary = evm.create(caller(), U256::zero(), DEPLOY_EOF.to_vec(), 100_000);
+	assert_eq!(summary.reason, ExitReason::Error(ExitError::CreateContractStartingWithEF));
+}
+
+#[test]
+fn disabled_without_config() {
+	let vicinity = vicinity();
+	let mut evm = evm(&vicinity, evm::Istanbul);
+
+	// The container magic is an undefined opcode.
+	let summary = evm.call(caller(), contract(), U256::zero(), Vec::new(), 100_000);
+	assert!(summary.reason.is_error());
+
+	let address = evm.create_address(caller());
+	let summary = evm.create(caller(), U256::zero(), DEPLOY_EF.to_vec(), 100_000);
+	assert!(summary.reason.is_succeed());
+	assert_eq!(evm.backend().code(address), vec![0xef]);
+}
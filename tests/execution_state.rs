@@ -0,0 +1,203 @@
+mod common;
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use evm::{Capture, Context, ExitReason, ExitSucceed, Runtime, Valids, U256};
+use evm::backend::MemoryBackend;
+use evm::executor::StackExecutor;
+#[cfg(feature = "with-serde")]
+use evm::executor::Pending;
+use common::{account, callee, caller, contract, vicinity};
+
+// PUSH1 1 PUSH1 0 SSTORE
+// PUSH1 0 PUSH1 0 LOG0
+// PUSH1 2 PUSH1 1 SSTORE
+// PUSH1 42 PUSH1 0 MSTORE
+// PUSH1 32 PUSH1 0 RETURN
+fn code() -> Vec<u8> {
+	vec![
+		0x60, 0x01, 0x60, 0x00, 0x55,
+		0x60, 0x00, 0x60, 0x00, 0xa0,
+		0x60, 0x02, 0x60, 0x01, 0x55,
+		0x60, 0x2a, 0x60, 0x00, 0x52,
+		0x60, 0x20, 0x60, 0x00, 0xf3,
+	]
+}
+
+// PUSH1 10, JUMPDEST PUSH1 1 SWAP1 SUB DUP1 PUSH1 2 JUMPI
+// PUSH1 1 PUSH1 0 SSTORE STOP
+fn loop_code() -> Vec<u8> {
+	vec![
+		0x60, 0x0a,
+		0x5b, 0x60, 0x01, 0x90, 0x03, 0x80, 0x60, 0x02, 0x57,
+		0x60, 0x01, 0x60, 0x00, 0x55, 0x00,
+	]
+}
+
+// CALL(GAS, callee, 0, 0, 0, 0, 0), PUSH1 0 SSTORE STOP
+fn calling_code() -> Vec<u8> {
+	let mut code = vec![0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x73];
+	code.extend_from_slice(callee().as_bytes());
+	code.extend_from_slice(&[0x5a, 0xf1, 0x60, 0x00, 0x55, 0x00]);
+	code
+}
+
+fn new_runtime() -> Runtime {
+	runtime_of(code())
+}
+
+fn runtime_of(code: Vec<u8>) -> Runtime {
+	let context = Context { address: contract(), caller: caller(), apparent_value: U256::zero() };
+	Runtime::new(code.clone(), Arc::new(Valids::compute(&code).into()), Vec::new(), context)
+}
+
+fn exit<T>(capture: Capture<ExitReason, T>) -> ExitReason {
+	match capture {
+		Capture::Exit(reason) => reason,
+		Capture::Trap(_) => panic!("unexpected trap"),
+	}
+}
+
+#[test]
+fn resume_in_another_executor() {
+	let vicinity = vicinity();
+	let mut state = BTreeMap::new();
+	state.insert(contract(), account(code()));
+	let backend = MemoryBackend::new(&vicinity, state);
+
+	let mut executor = StackExecutor::new(&backend, 100_000);
+	let mut runtime = new_runtime();
+	assert_eq!(exit(runtime.run(u64::MAX, &mut executor).1), ExitReason::Succeed(ExitSucceed::Returned));
+	let output = runtime.machine().return_value();
+	let used_gas = executor.used_gas();
+	let (applies, logs) = executor.deconstruct();
+
+	let mut executor = StackExecutor::new(&backend, 100_000);
+	let mut runtime = new_runtime();
+	let (steps, capture) = runtime.run(8, &mut executor);
+	assert_eq!(steps, 8);
+	assert_eq!(exit(capture), ExitReason::StepLimitReached);
+	let suspended = executor.execution_state();
+	assert_eq!(executor.deconstruct().1.len(), 1);
+
+	let mut executor = StackExecutor::new(&backend, 100_000).with_execution_state(suspended);
+	assert_eq!(exit(runtime.run(u64::MAX, &mut executor).1), ExitReason::Succeed(ExitSucceed::Returned));
+	assert_eq!(runtime.machine().return_value(), output);
+	assert_eq!(executor.used_gas(), used_gas);
+	let (resumed_applies, resumed_logs) = executor.deconstruct();
+	assert_eq!(format!("{resumed_applies:?}"), format!("{applies:?}"));
+	assert_eq!(resumed_logs, logs);
+}
+
+#[test]
+fn step_limited_slices() {
+	let vicinity = vicinity();
+	let mut state = BTreeMap::new();
+	state.insert(contract(), account(code()));
+	let backend = MemoryBackend::new(&vicinity, state);
+
+	let mut executor = StackExecutor::new(&backend, 100_000);
+	let expected = match executor.execute_with_step_limit(new_runtime(), u64::MAX) {
+		Capture::Exit(exit) => exit,
+		Capture::Trap(_) => panic!("unexpected step limit"),
+	};
+	assert_eq!(expected.0, ExitReason::Succeed(ExitSucceed::Returned));
+	let used_gas = executor.used_gas();
+
+	let mut slices = 1;
+	let mut executor = StackExecutor::new(&backend, 100_000);
+	let mut capture = executor.execute_with_step_limit(new_runtime(), 4);
+	let exit = loop {
+		match capture {
+			Capture::Exit(exit) => break exit,
+			Capture::Trap(pending) => {
+				assert_eq!(pending.steps(), slices * 4);
+				slices += 1;
+				executor = StackExecutor::new(&backend, 100_000);
+				capture = executor.resume(pending, 4);
+			},
+		}
+	};
+	assert!(slices > 1);
+	assert_eq!(exit, expected);
+	assert_eq!(executor.used_gas(), used_gas);
+	assert_eq!(executor.deconstruct().1.len(), 1);
+}
+
+#[test]
+fn step_limit_covers_called_frames() {
+	let vicinity = vicinity();
+	let mut state = BTreeMap::new();
+	state.insert(contract(), account(calling_code()));
+	state.insert(callee(), account(loop_code()));
+	let backend = MemoryBackend::new(&vicinity, state);
+
+	let mut executor = StackExecutor::new(&backend, 100_000);
+	let expected = match executor.execute_with_step_limit(runtime_of(calling_code()), u64::MAX) {
+		Capture::Exit(exit) => exit,
+		Capture::Trap(_) => panic!("unexpected step limit"),
+	};
+	assert_eq!(expected.0, ExitReason::Succeed(ExitSucceed::Stopped));
+	let used_gas = executor.used_gas();
+	let (applies, _) = executor.deconstruct();
+
+	let mut slices = 1;
+	let mut suspended_in_callee = false;
+	let mut executor = StackExecutor::new(&backend, 100_000);
+	let mut capture = executor.execute_with_step_limit(runtime_of(calling_code()), 10);
+	let exit = loop {
+		match capture {
+			Capture::Exit(exit) => break exit,
+			Capture::Trap(pending) => {
+				// Each slice stops after exactly its steps, wherever they ran.
+				assert_eq!(pending.steps(), slices * 10);
+				suspended_in_callee |= pending.depth() == 1;
+				slices += 1;
+				executor = StackExecutor::new(&backend, 100_000);
+				capture = executor.resume(pending, 10);
+			},
+		}
+	};
+	assert!(suspended_in_callee);
+	assert_eq!(exit, expected);
+	assert_eq!(executor.used_gas(), used_gas);
+	let (resumed_applies, _) = executor.deconstruct();
+	assert_eq!(format!("{resumed_applies:?}"), format!("{applies:?}"));
+}
+
+#[cfg(feature = "with-serde")]
+#[test]
+fn resume_after_json_round_trip() {
+	let vicinity = vicinity();
+	let mut state = BTreeMap::new();
+	state.insert(contract(), account(code()));
+	let backend = MemoryBackend::new(&vicinity, state);
+
+	let mut executor = StackExecutor::new(&backend, 100_000);
+	let expected = match executor.execute_with_step_limit(new_runtime(), u64::MAX) {
+		Capture::Exit(exit) => exit,
+		Capture::Trap(_) => panic!("unexpected step limit"),
+	};
+	let used_gas = executor.used_gas();
+	let (applies, logs) = executor.deconstruct();
+
+	let mut executor = StackExecutor::new(&backend, 100_000);
+	let pending = match executor.execute_with_step_limit(new_runtime(), 8) {
+		Capture::Exit(_) => panic!("unexpected exit"),
+		Capture::Trap(pending) => pending,
+	};
+	let json = serde_json::to_string(&pending).unwrap();
+	let pending: Pending = serde_json::from_str(&json).unwrap();
+	assert_eq!(pending.steps(), 8);
+
+	let mut executor = StackExecutor::new(&backend, 100_000);
+	let exit = match executor.resume(pending, u64::MAX) {
+		Capture::Exit(exit) => exit,
+		Capture::Trap(_) => panic!("unexpected step limit"),
+	};
+	assert_eq!(exit, expected);
+	assert_eq!(executor.used_gas(), used_gas);
+	let (resumed_applies, resumed_logs) = executor.deconstruct();
+	assert_eq!(format!("{resumed_applies:?}"), format!("{applies:?}"));
+	assert_eq!(resumed_logs, logs);
+}
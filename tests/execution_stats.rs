@@ -0,0 +1,64 @@
+mod common;
+
+use std::collections::BTreeMap;
+use evm::{H160, U256};
+use evm::backend::{MemoryAccount, MemoryBackend, MemoryVicinity};
+use evm::executor::{ExecutionStats, StackExecutor};
+use common::{caller, contract, vicinity};
+
+/// Stores a word at 0x80, then reverts.
+fn reverting() -> H160 {
+	H160::repeat_byte(0x33)
+}
+
+/// Stops right away.
+fn empty() -> H160 {
+	H160::repeat_byte(0x44)
+}
+
+fn backend(vicinity: &MemoryVicinity) -> MemoryBackend<'_> {
+	// PUSH1 1 PUSH1 0x40 MSTORE
+	let mut code = vec![0x60, 0x01, 0x60, 0x40, 0x52];
+	// PUSH1 0 PUSH1 0 PUSH1 0 PUSH1 0 PUSH1 0 PUSH20 reverting() PUSH2 0xffff CALL POP
+	code.extend_from_slice(&[0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x73]);
+	code.extend_from_slice(reverting().as_bytes());
+	code.extend_from_slice(&[0x61, 0xff, 0xff, 0xf1, 0x50]);
+
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount::default());
+	// `contract()` stores a word at 0x40, then calls `reverting()`.
+	state.insert(contract(), MemoryAccount { code, ..Default::default() });
+	// PUSH1 1 PUSH1 0x80 MSTORE PUSH1 0 PUSH1 0 REVERT
+	state.insert(reverting(), MemoryAccount {
+		code: vec![0x60, 0x01, 0x60, 0x80, 0x52, 0x60, 0x00, 0x60, 0x00, 0xfd],
+		..Default::default()
+	});
+	state.insert(empty(), MemoryAccount { code: vec![0x00], ..Default::default() });
+	MemoryBackend::new(vicinity, state)
+}
+
+#[test]
+fn peaks_across_frames() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let mut executor = StackExecutor::new(&backend, 1_000_000);
+
+	let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 1_000_000);
+	assert!(reason.is_succeed());
+	// The reverted frame still counts.
+	assert_eq!(executor.execution_stats(), ExecutionStats { peak_memory: 0xa0, max_depth: 2 });
+}
+
+#[test]
+fn reset_per_transaction() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let mut executor = StackExecutor::new(&backend, 1_000_000);
+	assert_eq!(executor.execution_stats(), ExecutionStats::default());
+
+	let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 1_000_000);
+	assert!(reason.is_succeed());
+	let (reason, _) = executor.transact_call(caller(), empty(), U256::zero(), Vec::new(), 1_000_000);
+	assert!(reason.is_succeed());
+	assert_eq!(executor.execution_stats(), ExecutionStats { peak_memory: 0, max_depth: 1 });
+}
@@ -0,0 +1,108 @@
+mod common;
+
+use std::collections::BTreeMap;
+use evm::{ExitReason, ExitSucceed, Handler, Limits, U256};
+use evm::backend::{MemoryAccount, MemoryBackend, MemoryVicinity};
+use evm::executor::{BuildError, StackExecutorBuilder, StateOverride};
+use common::{caller, contract, vicinity};
+
+/// Returns the sum of slots 0 and 1.
+const SUM: [u8; 13] = [0x60, 0x01, 0x54, 0x60, 0x00, 0x54, 0x01, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60];
+
+fn sum_code() -> Vec<u8> {
+	let mut code = SUM.to_vec();
+	code.extend_from_slice(&[0x00, 0xf3]);
+	code
+}
+
+/// Stores 1 at slot 0.
+const STORE: [u8; 6] = [0x60, 0x01, 0x60, 0x00, 0x55, 0x00];
+
+fn backend(vicinity: &MemoryVicinity) -> MemoryBackend<'_> {
+	let mut storage = BTreeMap::new();
+	storage.insert(U256::zero(), U256::from(2));
+	storage.insert(U256::one(), U256::from(3));
+
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount::default());
+	state.insert(contract(), MemoryAccount {
+		nonce: U256::one(),
+		code: STORE.to_vec(),
+		storage,
+		..Default::default()
+	});
+	MemoryBackend::new(vicinity, state)
+}
+
+#[test]
+fn rejects_misconfiguration() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+
+	let error = StackExecutorBuilder::new(&backend).build().err();
+	assert_eq!(error, Some(BuildError::MissingGasLimit));
+
+	let error = StackExecutorBuilder::new(&backend).with_gas_limit(100_000).with_depth(1025).build().err();
+	assert_eq!(error, Some(BuildError::DepthTooLarge { depth: 1025, limit: 1024 }));
+
+	let limits = Limits { stack_limit: 0, memory_limit: 1024, return_data_limit: usize::MAX };
+	let error = StackExecutorBuilder::new(&backend).with_gas_limit(100_000).with_limits(limits).build().err();
+	assert_eq!(error, Some(BuildError::InvalidLimits));
+
+	let mut storage_diff = BTreeMap::new();
+	storage_diff.insert(U256::zero(), U256::one());
+	let state_override = StateOverride { storage: Some(BTreeMap::new()), storage_diff, ..StateOverride::default() };
+	let error = StackExecutorBuilder::new(&backend)
+		.with_gas_limit(100_000)
+		.with_state_override(contract(), state_override)
+		.build()
+		.err();
+	assert_eq!(error, Some(BuildError::ConflictingStorage(contract())));
+}
+
+#[test]
+fn state_overrides() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+
+	let mut storage_diff = BTreeMap::new();
+	storage_diff.insert(U256::one(), U256::from(10));
+	let mut executor = StackExecutorBuilder::new(&backend)
+		.with_gas_limit(100_000)
+		.with_state_override(contract(), StateOverride { code: Some(sum_code()), storage_diff, ..StateOverride::default() })
+		.with_state_override(caller(), StateOverride { balance: Some(U256::from(7)), ..StateOverride::default() })
+		.build()
+		.unwrap();
+	assert_eq!(executor.balance(caller()), U256::from(7));
+	let (reason, out) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 100_000);
+	assert_eq!(reason, ExitReason::Succeed(ExitSucceed::Returned));
+	assert_eq!(U256::from_big_endian(&out), U256::from(12));
+
+	let mut storage = BTreeMap::new();
+	storage.insert(U256::one(), U256::from(10));
+	let mut executor = StackExecutorBuilder::new(&backend)
+		.with_gas_limit(100_000)
+		.with_state_override(contract(), StateOverride { code: Some(sum_code()), storage: Some(storage), ..StateOverride::default() })
+		.build()
+		.unwrap();
+	let (_, out) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 100_000);
+	assert_eq!(U256::from_big_endian(&out), U256::from(10));
+
+	// The backend is left untouched.
+	assert_eq!(backend.state()[&contract()].code, STORE.to_vec());
+}
+
+#[test]
+fn static_executor() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+
+	let mut executor = StackExecutorBuilder::new(&backend).with_gas_limit(100_000).build().unwrap();
+	let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 100_000);
+	assert!(reason.is_succeed());
+
+	let mut executor = StackExecutorBuilder::new(&backend).with_gas_limit(100_000).with_static().build().unwrap();
+	let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 100_000);
+	assert!(!reason.is_succeed());
+	assert_eq!(executor.storage(contract(), U256::zero()), U256::from(2));
+}
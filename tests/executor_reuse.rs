@@ -0,0 +1,103 @@
+mod common;
+
+use std::collections::BTreeMap;
+use evm::{ExitReason, ExitSucceed, Handler, H160, H256, U256};
+use evm::backend::{MemoryAccount, MemoryBackend};
+use evm::executor::StackExecutor;
+use common::{account, caller, contract, vicinity};
+
+fn destructible() -> H160 {
+	H160::repeat_byte(0x33)
+}
+
+fn state() -> BTreeMap<H160, MemoryAccount> {
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount { balance: U256::from(1_000_000), ..Default::default() });
+	// PUSH1 0 CALLDATALOAD PUSH1 0 SSTORE
+	state.insert(contract(), account(vec![0x60, 0x00, 0x35, 0x60, 0x00, 0x55]));
+	// CALLER SELFDESTRUCT
+	state.insert(destructible(), MemoryAccount {
+		nonce: U256::one(),
+		balance: U256::from(10),
+		code: vec![0x33, 0xff],
+		..Default::default()
+	});
+	state
+}
+
+fn input(value: u64) -> Vec<u8> {
+	H256::from(U256::from(value)).as_bytes().to_vec()
+}
+
+#[test]
+fn sequential_transactions_match_separate_executors() {
+	let vicinity = vicinity();
+	let backend = MemoryBackend::new(&vicinity, state());
+	let mut committed = MemoryBackend::new(&vicinity, state());
+
+	let mut executor = StackExecutor::new(&backend, 1_000_000);
+	let mut used = Vec::new();
+	for value in [1, 2] {
+		let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), input(value), 100_000);
+		assert_eq!(reason, ExitReason::Succeed(ExitSucceed::Stopped));
+		used.push(executor.used_gas());
+		executor.commit_to(&mut committed);
+		executor.reset_for_next_transaction(1_000_000);
+	}
+	assert_eq!(executor.storage(contract(), U256::zero()), U256::from(2));
+
+	// Replay each transaction with a new executor on the state left by the
+	// previous one.
+	let mut separate = MemoryBackend::new(&vicinity, state());
+	for (value, used) in [1, 2].iter().copied().zip(used) {
+		let snapshot = MemoryBackend::new(&vicinity, separate.clone().into_state());
+		let mut fresh = StackExecutor::new(&snapshot, 1_000_000);
+		let (reason, _) = fresh.transact_call(caller(), contract(), U256::zero(), input(value), 100_000);
+		assert_eq!(reason, ExitReason::Succeed(ExitSucceed::Stopped));
+		assert_eq!(fresh.used_gas(), used);
+		let (values, logs) = fresh.deconstruct();
+		evm::backend::ApplyBackend::apply(&mut separate, values, logs, false);
+	}
+
+	assert_eq!(committed.into_state(), separate.into_state());
+}
+
+#[test]
+fn destroyed_account_is_gone_in_next_transaction() {
+	let vicinity = vicinity();
+	let backend = MemoryBackend::new(&vicinity, state());
+	let mut committed = MemoryBackend::new(&vicinity, state());
+
+	let mut executor = StackExecutor::new(&backend, 1_000_000);
+	let (reason, _) = executor.transact_call(caller(), destructible(), U256::zero(), Vec::new(), 100_000);
+	assert_eq!(reason, ExitReason::Succeed(ExitSucceed::Suicided));
+	executor.reset_for_next_transaction(1_000_000);
+
+	assert!(!executor.exists(destructible()));
+	assert_eq!(executor.balance(destructible()), U256::zero());
+	assert_eq!(executor.balance(caller()), U256::from(1_000_010));
+
+	let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), input(7), 100_000);
+	assert_eq!(reason, ExitReason::Succeed(ExitSucceed::Stopped));
+	executor.commit_to(&mut committed);
+
+	let committed = committed.into_state();
+	assert!(!committed.contains_key(&destructible()));
+	assert_eq!(committed[&caller()].balance, U256::from(1_000_010));
+	assert_eq!(committed[&contract()].storage[&U256::zero()], U256::from(7));
+}
+
+#[test]
+fn reset_clears_transaction_data() {
+	let vicinity = vicinity();
+	let backend = MemoryBackend::new(&vicinity, state());
+
+	let mut executor = StackExecutor::new(&backend, 1_000_000);
+	executor.transact_call(caller(), contract(), U256::zero(), input(1), 100_000);
+	assert!(executor.used_gas() > 0);
+	executor.reset_for_next_transaction(500_000);
+
+	assert_eq!(executor.used_gas(), 0);
+	assert_eq!(executor.gas(), 500_000);
+	assert_eq!(executor.original_storage(contract(), U256::zero()), U256::one());
+}
@@ -0,0 +1,94 @@
+mod common;
+
+use std::collections::BTreeMap;
+use evm::{Config, ExitError, ExitSucceed, Spec, H160, U256};
+use evm::backend::{MemoryAccount, MemoryBackend, MemoryVicinity};
+use evm::executor::{Precompiles, StackExecutor};
+use common::{caller, contract, vicinity};
+
+fn empty() -> H160 {
+	H160::repeat_byte(0x33)
+}
+
+fn funded() -> H160 {
+	H160::repeat_byte(0x44)
+}
+
+fn precompile() -> H160 {
+	H160::from(U256::from(1))
+}
+
+/// Istanbul, with empty accounts of the state considered existing.
+#[derive(Clone, Copy, Default)]
+struct PreEip161;
+
+impl Spec for PreEip161 {
+	const CONFIG: Config = Config { empty_considered_exists: true, ..Config::istanbul() };
+}
+
+/// Istanbul, with precompiles only existing as per their state.
+#[derive(Clone, Copy, Default)]
+struct PrecompilesDoNotExist;
+
+impl Spec for PrecompilesDoNotExist {
+	const CONFIG: Config = Config { precompiles_exist: false, ..Config::istanbul() };
+}
+
+type PrecompileResult = Option<Result<(ExitSucceed, Vec<u8>, u64), ExitError>>;
+
+fn identity(_address: H160, input: &[u8], _target_gas: Option<u64>) -> PrecompileResult {
+	Some(Ok((ExitSucceed::Returned, input.to_vec(), 0)))
+}
+
+fn none(_address: H160, _input: &[u8], _target_gas: Option<u64>) -> PrecompileResult {
+	None
+}
+
+/// `CALL` to `target` with one wei and no input nor output.
+fn call_code(target: H160) -> Vec<u8> {
+	let mut code = vec![0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x01, 0x73];
+	code.extend_from_slice(target.as_bytes());
+	code.extend_from_slice(&[0x61, 0xff, 0xff, 0xf1, 0x00]);
+	code
+}
+
+fn backend(vicinity: &MemoryVicinity, target: H160) -> MemoryBackend<'_> {
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount::default());
+	state.insert(contract(), MemoryAccount {
+		nonce: U256::one(),
+		balance: U256::from(10),
+		code: call_code(target),
+		..Default::default()
+	});
+	state.insert(empty(), MemoryAccount::default());
+	state.insert(funded(), MemoryAccount { balance: U256::one(), ..Default::default() });
+	MemoryBackend::new(vicinity, state)
+}
+
+/// Gas used by the call to `target` on the hard fork `S`.
+fn used_gas<S: Spec>(target: H160, spec: S) -> u64 {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity, target);
+	let precompiles = Precompiles::new().with_precompile(precompile(), identity);
+	let mut executor = StackExecutor::new_with_spec(&backend, 1_000_000, none, spec)
+		.with_precompiles(&precompiles);
+	let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 1_000_000);
+	assert!(reason.is_succeed());
+	executor.used_gas()
+}
+
+#[test]
+fn empty_account() {
+	// After EIP-161, an empty account does not exist, so the value
+	// transfer pays for a new account.
+	assert_eq!(used_gas(empty(), evm::Istanbul) - used_gas(empty(), PreEip161), 25000);
+	assert_eq!(used_gas(H160::repeat_byte(0x55), evm::Istanbul), used_gas(empty(), evm::Istanbul));
+	assert_eq!(used_gas(H160::repeat_byte(0x55), PreEip161) - used_gas(empty(), PreEip161), 25000);
+}
+
+#[test]
+fn precompile_account() {
+	assert_eq!(used_gas(precompile(), evm::Istanbul), used_gas(funded(), evm::Istanbul));
+	assert_eq!(used_gas(precompile(), PrecompilesDoNotExist) - used_gas(precompile(), evm::Istanbul), 25000);
+}
@@ -0,0 +1,67 @@
+use evm::{CollisionKind, ExitError, ExitFatal, ExitReason, ExitRevert, ExitSucceed, Opcode};
+
+fn errors() -> Vec<ExitError> {
+	(0..=u8::MAX).filter_map(ExitError::from_code).collect()
+}
+
+#[test]
+fn error_codes_round_trip() {
+	let errors = errors();
+	assert_eq!(errors.len(), 21);
+	for error in &errors {
+		assert_eq!(ExitError::from_code(error.to_code()), Some(*error));
+	}
+	assert_eq!(ExitError::OutOfGas.to_code(), 9);
+	assert_eq!(ExitError::OpcodeDisabled.to_code(), 16);
+	assert_eq!(ExitError::from_code(17), None);
+	assert_eq!(ExitError::ReturnDataLimit.to_code(), 18);
+	assert_eq!(ExitError::CreateCollision(CollisionKind::Code).to_code(), 6);
+	assert_eq!(ExitError::CreateCollision(CollisionKind::Nonce).to_code(), 19);
+	assert_eq!(ExitError::CreateCollision(CollisionKind::Storage).to_code(), 20);
+	assert_eq!(ExitError::CreateContractStartingWithEF.to_code(), 21);
+	assert_eq!(ExitError::from_code(22), None);
+}
+
+#[test]
+fn reason_codes_round_trip() {
+	let mut reasons = vec![
+		ExitReason::Succeed(ExitSucceed::Stopped),
+		ExitReason::Succeed(ExitSucceed::Returned),
+		ExitReason::Succeed(ExitSucceed::Suicided),
+		ExitReason::Revert(ExitRevert::Reverted),
+		ExitReason::Fatal(ExitFatal::NotSupported),
+		ExitReason::Fatal(ExitFatal::UnhandledInterrupt),
+		ExitReason::StepLimitReached,
+	];
+	for error in errors() {
+		reasons.push(ExitReason::Error(error));
+		reasons.push(ExitReason::Fatal(ExitFatal::CallErrorAsFatal(error)));
+	}
+
+	for opcode in 0..=u8::MAX {
+		let error = ExitError::InvalidOpcode(Opcode(opcode));
+		assert_eq!(error.to_code(), 17);
+		reasons.push(ExitReason::Error(error));
+		reasons.push(ExitReason::Fatal(ExitFatal::CallErrorAsFatal(error)));
+	}
+
+	let decoded: Vec<_> = (0..=u16::MAX).filter_map(ExitReason::from_code).collect();
+	assert_eq!(decoded.len(), reasons.len());
+	for reason in reasons {
+		assert_eq!(ExitReason::from_code(reason.to_code()), Some(reason));
+	}
+}
+
+#[test]
+fn stable_reason_codes() {
+	assert_eq!(ExitReason::Succeed(ExitSucceed::Returned).to_code(), 0x0001);
+	assert_eq!(ExitReason::Revert(ExitRevert::Reverted).to_code(), 0x0100);
+	assert_eq!(ExitReason::Error(ExitError::OutOfGas).to_code(), 0x0209);
+	assert_eq!(ExitReason::Fatal(ExitFatal::CallErrorAsFatal(ExitError::OutOfGas)).to_code(), 0x0389);
+	assert_eq!(ExitReason::StepLimitReached.to_code(), 0x0400);
+	assert_eq!(ExitReason::from_code(0x0003), None);
+	assert_eq!(ExitReason::Error(ExitError::InvalidOpcode(Opcode::REVERT)).to_code(), 0x05fd);
+	assert_eq!(ExitReason::from_code(0x0211), None);
+	assert_eq!(ExitReason::from_code(0x0216), None);
+	assert_eq!(ExitReason::from_code(0x0700), None);
+}
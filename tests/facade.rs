@@ -0,0 +1,108 @@
+mod common;
+
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use evm::{Evm, U256};
+use evm::backend::{Backend, MemoryAccount, MemoryBackend, MemoryVicinity};
+use evm::block::GasLimitError;
+use evm::facade::{Call, TransactionAction};
+use evm::tracing::{Event, EventListener};
+use common::{account, caller, contract};
+
+fn vicinity() -> MemoryVicinity {
+	MemoryVicinity {
+		block_gas_limit: U256::from(100_000),
+		..common::vicinity()
+	}
+}
+
+/// Stores the call value at slot 0.
+const STORE_VALUE: [u8; 5] = [0x34, 0x60, 0x00, 0x55, 0x00];
+
+fn evm(vicinity: &MemoryVicinity) -> Evm<MemoryBackend<'_>> {
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount {
+		balance: U256::from(100),
+		..Default::default()
+	});
+	state.insert(contract(), account(STORE_VALUE.to_vec()));
+	Evm::new(MemoryBackend::new(vicinity, state))
+}
+
+#[derive(Default)]
+struct Calls(Cell<usize>);
+
+impl EventListener for Calls {
+	fn event(&self, event: Event<'_>) {
+		if let Event::Call { .. } = event {
+			self.0.set(self.0.get() + 1);
+		}
+	}
+}
+
+#[test]
+fn call_and_create_commit() {
+	let vicinity = vicinity();
+	let mut evm = evm(&vicinity);
+
+	let summary = evm.call(caller(), contract(), U256::from(7), Vec::new(), 50_000);
+	assert!(summary.reason.is_succeed());
+	assert_eq!(evm.backend().storage(contract(), U256::zero()), U256::from(7));
+	assert_eq!(evm.backend().basic(caller()).nonce, U256::one());
+
+	let address = evm.create_address(caller());
+	let summary = evm.create(caller(), U256::zero(), vec![0x60, 0x00, 0x60, 0x00, 0xf3], 60_000);
+	assert!(summary.reason.is_succeed());
+	assert_eq!(summary.created, vec![address]);
+	assert_eq!(evm.backend().basic(address).nonce, U256::one());
+}
+
+#[test]
+fn trace_call_does_not_commit() {
+	let vicinity = vicinity();
+	let evm = evm(&vicinity);
+	let calls = Calls::default();
+	let summary = evm.trace_call(caller(), contract(), U256::from(7), Vec::new(), 50_000, &calls);
+	assert!(summary.reason.is_succeed());
+	assert_eq!(summary.frame_count, Some(1));
+	assert_eq!(calls.0.get(), 1);
+	assert_eq!(evm.backend().storage(contract(), U256::zero()), U256::zero());
+}
+
+#[test]
+fn execute_block() {
+	let vicinity = vicinity();
+	let mut evm = evm(&vicinity);
+	let call = |value: u64, gas_limit| Call {
+		caller: caller(),
+		action: TransactionAction::Call(contract()),
+		value: U256::from(value),
+		data: Vec::new(),
+		gas_limit,
+	};
+
+	let results = evm.execute_block(vec![call(1, 50_000), call(2, 60_000), call(3, 50_000)]);
+	assert_eq!(results.len(), 3);
+	let used_gas = results[0].as_ref().unwrap().used_gas;
+	assert_eq!(results[1], Err(GasLimitError::BlockGasLimitExceeded {
+		gas_limit: 60_000,
+		gas_used: used_gas,
+		block_gas_limit: 100_000,
+	}));
+	assert!(results[2].as_ref().unwrap().reason.is_succeed());
+	assert_eq!(evm.backend().storage(contract(), U256::zero()), U256::from(3));
+	assert_eq!(evm.backend().basic(caller()).nonce, U256::from(2));
+}
+
+#[test]
+fn snapshot_and_revert() {
+	let vicinity = vicinity();
+	let mut evm = evm(&vicinity);
+	let snapshot = evm.snapshot();
+	let _ = evm.call(caller(), contract(), U256::from(7), Vec::new(), 50_000);
+	assert_eq!(evm.backend().storage(contract(), U256::zero()), U256::from(7));
+
+	evm.revert_to(snapshot);
+	assert_eq!(evm.backend().storage(contract(), U256::zero()), U256::zero());
+	assert_eq!(evm.backend().basic(caller()).balance, U256::from(100));
+}
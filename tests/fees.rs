@@ -0,0 +1,90 @@
+mod common;
+
+use std::collections::BTreeMap;
+use evm::{ExitError, ExitReason, Handler, H160, U256};
+use evm::backend::{MemoryAccount, MemoryBackend, MemoryVicinity};
+use evm::executor::{Fees, StackExecutor};
+use common::{caller, contract};
+
+fn vicinity() -> MemoryVicinity {
+	MemoryVicinity {
+		gas_price: U256::from(10),
+		block_coinbase: coinbase(),
+		..common::vicinity()
+	}
+}
+
+fn coinbase() -> H160 {
+	H160::repeat_byte(0x33)
+}
+
+fn fees() -> Fees {
+	Fees { gas_price: U256::from(10), base_fee: U256::from(4) }
+}
+
+fn backend(vicinity: &MemoryVicinity, balance: u64) -> MemoryBackend<'_> {
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount {
+		balance: U256::from(balance),
+		..Default::default()
+	});
+	state.insert(contract(), MemoryAccount {
+		nonce: U256::one(),
+		// PUSH1 1 PUSH1 0 SSTORE
+		code: vec![0x60, 0x01, 0x60, 0x00, 0x55],
+		..Default::default()
+	});
+	MemoryBackend::new(vicinity, state)
+}
+
+#[test]
+fn call_fees() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity, 1_000_000);
+	let mut executor = StackExecutor::new(&backend, 50_000).with_fees(fees());
+	let (reason, _) = executor.transact_call(caller(), contract(), U256::from(7), Vec::new(), 50_000);
+	assert!(reason.is_succeed());
+
+	let used_gas = executor.used_gas();
+	assert_eq!(used_gas, 21_000 + 3 + 3 + 20_000);
+	assert_eq!(executor.balance(caller()), U256::from(1_000_000 - 7 - used_gas * 10));
+	assert_eq!(executor.balance(contract()), U256::from(7));
+	assert_eq!(executor.balance(coinbase()), U256::from(used_gas * 6));
+}
+
+#[test]
+fn create_fees() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity, 1_000_000);
+	let mut executor = StackExecutor::new(&backend, 60_000).with_fees(Fees { gas_price: U256::from(10), base_fee: U256::zero() });
+	let reason = executor.transact_create(caller(), U256::zero(), Vec::new(), 60_000);
+	assert!(reason.is_succeed());
+
+	let used_gas = executor.used_gas();
+	assert_eq!(used_gas, 53_000);
+	assert_eq!(executor.balance(caller()), U256::from(1_000_000 - used_gas * 10));
+	assert_eq!(executor.balance(coinbase()), U256::from(used_gas * 10));
+}
+
+#[test]
+fn sender_cannot_pay_gas_limit() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity, 499_999);
+	let mut executor = StackExecutor::new(&backend, 50_000).with_fees(fees());
+	let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 50_000);
+	assert_eq!(reason, ExitReason::Error(ExitError::OutOfFund));
+	assert_eq!(executor.balance(caller()), U256::from(499_999));
+	assert_eq!(executor.nonce(caller()), U256::zero());
+	assert_eq!(executor.balance(coinbase()), U256::zero());
+}
+
+#[test]
+fn without_fees() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity, 1_000_000);
+	let mut executor = StackExecutor::new(&backend, 50_000);
+	let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 50_000);
+	assert!(reason.is_succeed());
+	assert_eq!(executor.balance(caller()), U256::from(1_000_000));
+	assert_eq!(executor.balance(coinbase()), U256::zero());
+}
@@ -0,0 +1,83 @@
+mod common;
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use evm::{CONFIG, H160, U256};
+use evm::backend::{MemoryAccount, MemoryBackend};
+use evm::executor::StackExecutor;
+use evm::tracing::{Event, EventListener};
+use common::{callee, caller, contract, vicinity};
+
+/// (gas, l64_reduction, after_gas, target_gas, stipend, gas_limit)
+type FrameGas = (u64, u64, u64, Option<u64>, u64, u64);
+
+#[derive(Default)]
+struct Recorder(RefCell<Vec<FrameGas>>);
+
+impl EventListener for Recorder {
+	fn event(&self, event: Event<'_>) {
+		if let Event::FrameGas { gas, l64_reduction, after_gas, target_gas, stipend, gas_limit } = event {
+			self.0.borrow_mut().push((gas, l64_reduction, after_gas, target_gas, stipend, gas_limit));
+		}
+	}
+}
+
+/// `CALL` to `address` transferring `value`, forwarding `gas`.
+fn call(address: H160, value: u8, gas: u16) -> Vec<u8> {
+	let mut code = vec![0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, value, 0x73];
+	code.extend_from_slice(address.as_bytes());
+	code.push(0x61);
+	code.extend_from_slice(&gas.to_be_bytes());
+	code.extend_from_slice(&[0xf1, 0x00]);
+	code
+}
+
+fn run(code: Vec<u8>, gas_limit: u64) -> Vec<FrameGas> {
+	let vicinity = vicinity();
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount::default());
+	state.insert(contract(), MemoryAccount {
+		nonce: U256::one(),
+		balance: U256::from(10),
+		code,
+		..Default::default()
+	});
+	state.insert(callee(), MemoryAccount::default());
+	let backend = MemoryBackend::new(&vicinity, state);
+
+	let recorder = Recorder::default();
+	let mut executor = StackExecutor::new(&backend, gas_limit).with_listener(&recorder);
+	let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), gas_limit);
+	assert!(reason.is_succeed());
+	recorder.0.into_inner()
+}
+
+#[test]
+fn transaction_frame() {
+	// The intrinsic gas is already charged when the frame is entered.
+	let frames = run(Vec::new(), 60_000);
+	assert_eq!(frames, vec![(39_000, 0, 39_000, Some(60_000), 0, 39_000)]);
+}
+
+#[test]
+fn call_with_stipend() {
+	let frames = run(call(callee(), 1, 5_000), 100_000);
+	assert_eq!(frames.len(), 2);
+	let (gas, l64_reduction, after_gas, target_gas, stipend, gas_limit) = frames[1];
+	assert_eq!(l64_reduction, gas / 64);
+	assert_eq!(after_gas, gas - gas / 64);
+	assert_eq!(target_gas, Some(5_000));
+	assert_eq!(stipend, CONFIG.call_stipend);
+	assert_eq!(gas_limit, 5_000 + CONFIG.call_stipend);
+}
+
+#[test]
+fn call_limited_by_l64() {
+	let frames = run(call(callee(), 0, u16::MAX), 60_000);
+	let (gas, l64_reduction, after_gas, target_gas, stipend, gas_limit) = frames[1];
+	assert!(gas < u64::from(u16::MAX));
+	assert_eq!(after_gas + l64_reduction, gas);
+	assert_eq!(target_gas, Some(u64::from(u16::MAX)));
+	assert_eq!(stipend, 0);
+	assert_eq!(gas_limit, after_gas);
+}
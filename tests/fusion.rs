@@ -0,0 +1,84 @@
+mod common;
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use evm::{Fusion, Machine, Superinstruction, Valids, U256};
+use evm::backend::MemoryBackend;
+use evm::executor::StackExecutor;
+use evm::metrics::MetricsCollector;
+use common::{account, caller, contract, vicinity};
+
+/// Count down from 5, with every kind of superinstruction in the loop.
+const LOOP: [u8; 23] = [
+	0x60, 0x05, // PUSH1 5
+	0x5b, // JUMPDEST
+	0x60, 0x01, 0x60, 0x00, 0x01, // PUSH1 1 PUSH1 0 ADD
+	0x90, 0x03, // SWAP1 SUB
+	0x80, 0x90, 0x50, // DUP1 SWAP1 POP
+	0x80, 0x60, 0x02, 0x57, // DUP1 PUSH1 2 JUMPI
+	0x60, 0x15, 0x56, // PUSH1 21 JUMP
+	0xfe, // INVALID
+	0x5b, 0x00, // JUMPDEST STOP
+];
+
+#[test]
+fn analyze() {
+	let fusion = Fusion::analyze(&LOOP);
+	assert_eq!(fusion.len(), 4);
+	assert_eq!(fusion.get(3), Some(Superinstruction::PushPushAdd {
+		a: U256::one(),
+		b: U256::zero(),
+		push: 2,
+		add: 4,
+	}));
+	assert_eq!(fusion.get(10), Some(Superinstruction::DupSwap { dup: 1, swap: 1 }));
+	assert_eq!(fusion.get(14), Some(Superinstruction::PushJumpi { value: U256::from(2), jumpi: 2 }));
+	assert_eq!(fusion.get(17), Some(Superinstruction::PushJump { value: U256::from(21), jump: 2 }));
+	assert_eq!(fusion.get(0), None);
+
+	// Push data is not mistaken for opcodes, and truncated pushes are not
+	// fused.
+	assert!(Fusion::analyze(&[0x61, 0x80, 0x90, 0x00]).is_empty());
+	assert!(Fusion::analyze(&[0x61, 0x01]).is_empty());
+}
+
+#[test]
+fn machine_step_limit() {
+	for max_steps in 0..40 {
+		let run = |fuse: bool| {
+			let mut machine = Machine::new(LOOP.to_vec(), Arc::new(Valids::compute(&LOOP).into()), Vec::new(), 1024, usize::MAX);
+			if fuse {
+				machine.fuse();
+			}
+			let mut validated = Vec::new();
+			let result = machine.run(max_steps, |opcode, position, stack, _| {
+				validated.push((opcode, position, stack.data().to_vec()));
+				Ok(())
+			});
+			(result, validated, machine.position(), machine.stack().data().to_vec())
+		};
+		assert_eq!(run(true), run(false), "max steps {}", max_steps);
+	}
+}
+
+#[test]
+fn executor_gas_and_metrics() {
+	let vicinity = vicinity();
+	let mut state = BTreeMap::new();
+	state.insert(contract(), account(LOOP.to_vec()));
+	let backend = MemoryBackend::new(&vicinity, state);
+
+	// Every gas limit running out at a different opcode of the loop.
+	for gas_limit in 21_000..21_300 {
+		let run = |fuse: bool| {
+			let collector = MetricsCollector::new();
+			let mut executor = StackExecutor::new(&backend, gas_limit).with_listener(&collector);
+			if fuse {
+				executor = executor.with_fused_dispatch();
+			}
+			let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), gas_limit);
+			(reason, executor.used_gas(), collector.into_report())
+		};
+		assert_eq!(run(true), run(false), "gas limit {}", gas_limit);
+	}
+}
@@ -0,0 +1,107 @@
+mod common;
+
+use std::collections::BTreeMap;
+use evm::{H160, U256};
+use evm::backend::{MemoryAccount, MemoryBackend, MemoryVicinity};
+use evm::executor::StackExecutor;
+use evm::gasometer::{GasBreakdown, GasCategory};
+use common::{callee, caller, contract, vicinity};
+
+/// Clears slot 0, then calls `failing()`.
+fn clearing() -> H160 {
+	H160::repeat_byte(0x23)
+}
+
+/// Fails with `INVALID`.
+fn failing() -> H160 {
+	H160::repeat_byte(0x34)
+}
+
+/// `CALL` to `target` with 0xffff gas, discarding the result.
+fn call(target: H160) -> Vec<u8> {
+	// PUSH1 0 PUSH1 0 PUSH1 0 PUSH1 0 PUSH1 0 PUSH20 target PUSH2 0xffff CALL POP
+	let mut code = vec![0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x73];
+	code.extend_from_slice(target.as_bytes());
+	code.extend_from_slice(&[0x61, 0xff, 0xff, 0xf1, 0x50]);
+	code
+}
+
+fn backend(vicinity: &MemoryVicinity) -> MemoryBackend<'_> {
+	// PUSH1 1 PUSH1 0 SSTORE PUSH1 32 PUSH1 0 MSTORE
+	let mut contract_code = vec![0x60, 0x01, 0x60, 0x00, 0x55, 0x60, 0x20, 0x60, 0x00, 0x52];
+	contract_code.extend(call(callee()));
+	// PUSH1 0 PUSH1 0 SSTORE
+	let mut clearing_code = vec![0x60, 0x00, 0x60, 0x00, 0x55];
+	clearing_code.extend(call(failing()));
+
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount::default());
+	// `contract()` sets slot 0, expands the memory by a word and calls `callee()`.
+	state.insert(contract(), MemoryAccount { code: contract_code, ..Default::default() });
+	state.insert(clearing(), MemoryAccount {
+		code: clearing_code,
+		storage: [(U256::zero(), U256::one())].iter().copied().collect(),
+		..Default::default()
+	});
+	// `callee()` sets slot 0.
+	state.insert(callee(), MemoryAccount { code: vec![0x60, 0x01, 0x60, 0x00, 0x55], ..Default::default() });
+	state.insert(failing(), MemoryAccount { code: vec![0xfe], ..Default::default() });
+	MemoryBackend::new(vicinity, state)
+}
+
+#[test]
+fn categories_across_frames() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let mut executor = StackExecutor::new(&backend, 1_000_000).with_gas_breakdown();
+
+	let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), vec![0x01, 0x00], 1_000_000);
+	assert!(reason.is_succeed());
+
+	let breakdown = executor.gas_breakdown().unwrap();
+	assert_eq!(breakdown, &GasBreakdown {
+		intrinsic: 21_000 + 16 + 4,
+		// Pushes, MSTORE and POP of the contract, pushes of the callee.
+		compute: 2 * 3 + 3 * 3 + 7 * 3 + 2 + 2 * 3,
+		memory: 3,
+		// Both frames set a slot.
+		storage: 2 * 20_000,
+		calls: 700,
+		code_deposit: 0,
+		refund: 0,
+	});
+	assert_eq!(breakdown.get(GasCategory::Storage), 40_000);
+	assert_eq!(breakdown.total(), executor.used_gas());
+}
+
+#[test]
+fn refund_and_failed_frame() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let mut executor = StackExecutor::new(&backend, 1_000_000).with_gas_breakdown();
+
+	let (reason, _) = executor.transact_call(caller(), clearing(), U256::zero(), Vec::new(), 1_000_000);
+	assert!(reason.is_succeed());
+
+	let breakdown = executor.gas_breakdown().unwrap().clone();
+	assert_eq!(breakdown.storage, 5_000);
+	assert_eq!(breakdown.refund, 15_000);
+	// The failed frame used all of its gas, without itemizing any.
+	assert_eq!(breakdown.calls, 700 + 0xffff);
+	assert_eq!(breakdown.total() - executor.refund().effective_refund, executor.used_gas());
+
+	// The breakdown starts over with the next transaction.
+	executor.reset_for_next_transaction(1_000_000);
+	assert_eq!(executor.gas_breakdown(), Some(&GasBreakdown::default()));
+}
+
+#[test]
+fn disabled_by_default() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let mut executor = StackExecutor::new(&backend, 1_000_000);
+
+	let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 1_000_000);
+	assert!(reason.is_succeed());
+	assert_eq!(executor.gas_breakdown(), None);
+}
@@ -0,0 +1,114 @@
+mod common;
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use evm::{Config, Evm, U256};
+use evm::backend::{MemoryAccount, MemoryBackend, MemoryVicinity};
+use evm::executor::{EthereumGasForwarding, FrameKind, GasForwardingPolicy, StackExecutor};
+use evm::tracing::{Event, EventListener};
+use common::{callee, caller, contract, vicinity};
+
+/// Forwards all gas, without stipend.
+struct ForwardAll;
+
+impl GasForwardingPolicy for ForwardAll {
+	fn available_gas(&self, _config: &Config, _kind: FrameKind, gas: u64) -> u64 {
+		gas
+	}
+
+	fn stipend(&self, _config: &Config, _kind: FrameKind, _value: U256) -> u64 {
+		0
+	}
+}
+
+/// (gas, l64_reduction, stipend)
+type FrameGas = (u64, u64, u64);
+
+#[derive(Default)]
+struct Recorder(RefCell<Vec<FrameGas>>);
+
+impl EventListener for Recorder {
+	fn event(&self, event: Event<'_>) {
+		if let Event::FrameGas { gas, l64_reduction, stipend, .. } = event {
+			self.0.borrow_mut().push((gas, l64_reduction, stipend));
+		}
+	}
+}
+
+/// `CALL` to `callee()` transferring 1 wei with all the gas, then `CREATE`
+/// of empty code with all the gas.
+fn code() -> Vec<u8> {
+	let mut code = vec![0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x01, 0x73];
+	code.extend_from_slice(callee().as_bytes());
+	code.extend_from_slice(&[0x5a, 0xf1, 0x50]);
+	code.extend_from_slice(&[0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0xf0, 0x50, 0x00]);
+	code
+}
+
+fn backend(vicinity: &MemoryVicinity) -> MemoryBackend<'_> {
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount::default());
+	state.insert(contract(), MemoryAccount {
+		nonce: U256::one(),
+		balance: U256::from(10),
+		code: code(),
+		..Default::default()
+	});
+	state.insert(callee(), MemoryAccount::default());
+	MemoryBackend::new(vicinity, state)
+}
+
+fn run(policy: Option<&dyn GasForwardingPolicy>) -> Vec<FrameGas> {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let recorder = Recorder::default();
+	let mut executor = StackExecutor::new(&backend, 100_000).with_listener(&recorder);
+	if let Some(policy) = policy {
+		executor = executor.with_gas_forwarding(policy);
+	}
+	let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 100_000);
+	assert!(reason.is_succeed());
+	recorder.0.into_inner()
+}
+
+#[test]
+fn ethereum_rules() {
+	let config = Config::istanbul();
+	let policy = EthereumGasForwarding;
+	assert_eq!(policy.available_gas(&config, FrameKind::Transaction, 6_400), 6_400);
+	assert_eq!(policy.available_gas(&config, FrameKind::Call, 6_400), 6_300);
+	assert_eq!(policy.available_gas(&config, FrameKind::Create, 6_400), 6_300);
+	assert_eq!(policy.available_gas(&Config::frontier(), FrameKind::Call, 6_400), 6_400);
+	assert_eq!(policy.stipend(&config, FrameKind::Call, U256::one()), 2_300);
+	assert_eq!(policy.stipend(&config, FrameKind::Call, U256::zero()), 0);
+	assert_eq!(policy.stipend(&config, FrameKind::Create, U256::one()), 0);
+	assert_eq!(policy.stipend(&config, FrameKind::Transaction, U256::one()), 0);
+
+	let frames = run(None);
+	assert_eq!(frames.len(), 3);
+	assert_eq!(frames[0].1, 0);
+	assert_eq!(frames[1].1, frames[1].0 / 64);
+	assert_eq!(frames[1].2, 2_300);
+	assert_eq!(frames[2].1, frames[2].0 / 64);
+	assert_eq!(frames[2].2, 0);
+	assert_eq!(run(Some(&EthereumGasForwarding)), frames);
+}
+
+#[test]
+fn custom_policy() {
+	let frames = run(Some(&ForwardAll));
+	assert_eq!(frames.len(), 3);
+	assert!(frames.iter().all(|&(_, l64_reduction, stipend)| l64_reduction == 0 && stipend == 0));
+}
+
+#[test]
+fn facade_policy() {
+	let vicinity = vicinity();
+	let mut evm = Evm::new(backend(&vicinity)).with_gas_forwarding(Box::new(ForwardAll));
+	let recorder = Recorder::default();
+	let summary = evm.trace_call(caller(), contract(), U256::zero(), Vec::new(), 100_000, &recorder);
+	assert!(summary.reason.is_succeed());
+	assert!(recorder.0.into_inner().iter().all(|&(_, l64_reduction, _)| l64_reduction == 0));
+	let summary = evm.call(caller(), contract(), U256::zero(), Vec::new(), 100_000);
+	assert!(summary.reason.is_succeed());
+}
@@ -0,0 +1,64 @@
+use std::io::ErrorKind;
+use evm::gas_regression::{Fixture, GasHarness, GasMismatch, GoldenFileError};
+
+fn harness() -> GasHarness {
+	GasHarness::new()
+		// PUSH1 1 PUSH1 2 ADD STOP
+		.with_fixture(Fixture::new("add", vec![0x60, 0x01, 0x60, 0x02, 0x01, 0x00]))
+		// PUSH1 1 PUSH1 0 SSTORE STOP
+		.with_fixture(Fixture::new("sstore", vec![0x60, 0x01, 0x60, 0x00, 0x55, 0x00]))
+		// PUSH1 1 PUSH2 1024 MSTORE STOP
+		.with_fixture(Fixture::new("memory", vec![0x60, 0x01, 0x61, 0x04, 0x00, 0x52, 0x00]))
+		// CALLDATASIZE PUSH1 0 REVERT
+		.with_fixture(Fixture::new("revert", vec![0x36, 0x60, 0x00, 0xfd]).with_input(vec![1, 2, 3]))
+		// JUMPDEST PUSH1 0 JUMP
+		.with_fixture(Fixture::new("out of gas", vec![0x5b, 0x60, 0x00, 0x56]).with_gas_limit(50_000))
+}
+
+#[test]
+fn golden_file() {
+	harness().check_file(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/gas_istanbul.txt")).unwrap();
+}
+
+#[test]
+fn unreadable_golden_file() {
+	// Neither read nor written, whether `UPDATE_GOLDEN` is set or not.
+	let missing = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/missing/gas.txt");
+	match harness().check_file(missing) {
+		Err(GoldenFileError::Io(e)) => assert_eq!(e.kind(), ErrorKind::NotFound),
+		other => panic!("unexpected result {:?}", other),
+	}
+
+	let directory = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden");
+	assert!(matches!(harness().check_file(directory), Err(GoldenFileError::Io(_))));
+}
+
+#[test]
+fn render() {
+	let harness = GasHarness::new().with_fixture(Fixture::new("add", vec![0x60, 0x01, 0x60, 0x02, 0x01, 0x00]));
+	assert_eq!(
+		harness.render(),
+		"fixture add\nreason Succeed(Stopped)\ntotal 21009\n0x00 1 0\n0x01 1 3\n0x60 2 6\n",
+	);
+	assert_eq!(harness.check(&harness.render()), Ok(()));
+}
+
+#[test]
+fn regressions() {
+	let harness = GasHarness::new().with_fixture(Fixture::new("add", vec![0x60, 0x01, 0x60, 0x02, 0x01, 0x00]));
+	let golden = "fixture add\nreason Succeed(Stopped)\ntotal 21010\n0x00 1 0\n0x01 1 4\n0x60 2 6\n\nfixture removed\ntotal 1\n";
+	let regression = harness.check(golden).unwrap_err();
+	assert_eq!(regression.0, vec![
+		GasMismatch {
+			name: "add".into(),
+			expected: Some("fixture add\nreason Succeed(Stopped)\ntotal 21010\n0x00 1 0\n0x01 1 4\n0x60 2 6\n".into()),
+			actual: Some(harness.render()),
+		},
+		GasMismatch {
+			name: "removed".into(),
+			expected: Some("fixture removed\ntotal 1\n".into()),
+			actual: None,
+		},
+	]);
+	assert!(regression.to_string().starts_with("gas usage changed for 2 fixture(s)\n--- add\n"));
+}
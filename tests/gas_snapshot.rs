@@ -0,0 +1,25 @@
+use evm::ExitError;
+use evm::gasometer::Gasometer;
+
+#[test]
+fn revert_to_snapshot() {
+	let mut gasometer = Gasometer::<evm::Istanbul>::new(1000);
+	gasometer.record_cost(100).unwrap();
+	gasometer.record_refund(10).unwrap();
+
+	let snapshot = gasometer.snapshot();
+	gasometer.record_cost(200).unwrap();
+	gasometer.record_refund(20).unwrap();
+	assert_eq!(gasometer.total_used_gas(), 300);
+	gasometer.revert_to(snapshot.clone());
+	assert_eq!(gasometer.total_used_gas(), 100);
+	assert_eq!(gasometer.refunded_gas(), 10);
+
+	// Running out of gas is rolled back as well.
+	assert_eq!(gasometer.record_cost(1000), Err(ExitError::OutOfGas));
+	assert_eq!(gasometer.gas(), 0);
+	gasometer.revert_to(snapshot);
+	assert_eq!(gasometer.gas(), 900);
+	gasometer.record_cost(900).unwrap();
+	assert_eq!(gasometer.gas(), 0);
+}
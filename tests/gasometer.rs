@@ -0,0 +1,144 @@
+use evm::{ExitError, Istanbul, Spec, H256, U256};
+use evm::gasometer::{self, GasCost, Gasometer, MemoryCost, TransactionCost};
+
+/// Remaining, total used and refunded gas.
+fn state(gasometer: &Gasometer<Istanbul>) -> (u64, u64, i64) {
+	(gasometer.gas(), gasometer.total_used_gas(), gasometer.refunded_gas())
+}
+
+#[test]
+fn record_cost() {
+	let mut gasometer = Gasometer::<Istanbul>::new(1000);
+	gasometer.record_cost(400).unwrap();
+	assert_eq!(state(&gasometer), (600, 400, 0));
+
+	assert_eq!(gasometer.record_cost(601), Err(ExitError::OutOfGas));
+	assert_eq!(state(&gasometer), (0, 1000, 0));
+	assert_eq!(gasometer.record_cost(0), Err(ExitError::OutOfGas));
+}
+
+#[test]
+fn record_refund() {
+	let mut gasometer = Gasometer::<Istanbul>::new(1000);
+	gasometer.record_cost(400).unwrap();
+	gasometer.record_refund(300).unwrap();
+	assert_eq!(state(&gasometer), (600, 400, 300));
+	assert_eq!(gasometer.effective_refund(), 400 / Istanbul::CONFIG.max_refund_quotient);
+	assert_eq!(gasometer.used_gas(), 200);
+
+	gasometer.record_refund(-400).unwrap();
+	assert_eq!(gasometer.effective_refund(), 0);
+	assert_eq!(gasometer.used_gas(), 400);
+}
+
+#[test]
+fn record_deposit() {
+	let mut gasometer = Gasometer::<Istanbul>::new(10_000);
+	gasometer.record_deposit(10).unwrap();
+	assert_eq!(state(&gasometer), (8000, 2000, 0));
+	assert_eq!(gasometer.record_deposit(41), Err(ExitError::OutOfGas));
+}
+
+#[test]
+fn record_stipend() {
+	let mut gasometer = Gasometer::<Istanbul>::new(10_000);
+	gasometer.record_cost(5000).unwrap();
+	gasometer.record_stipend(2300).unwrap();
+	assert_eq!(state(&gasometer), (7300, 2700, 0));
+}
+
+#[test]
+fn record_dynamic_cost() {
+	let mut gasometer = Gasometer::<Istanbul>::new(100_000);
+	let memory = MemoryCost { offset: U256::zero(), len: U256::from(64) };
+	gasometer.record_dynamic_cost(GasCost::VeryLow, Some(memory)).unwrap();
+	// 3 for the opcode and 6 for two words of memory.
+	assert_eq!(state(&gasometer), (100_000 - 9, 9, 0));
+
+	// Memory already paid for is not charged again.
+	let memory = MemoryCost { offset: U256::from(32), len: U256::from(32) };
+	gasometer.record_dynamic_cost(GasCost::VeryLow, Some(memory)).unwrap();
+	assert_eq!(state(&gasometer), (100_000 - 12, 12, 0));
+
+	let one = H256::repeat_byte(1);
+	let clear = GasCost::SStore { original: one, current: one, new: H256::zero(), target_is_cold: false };
+	gasometer.record_dynamic_cost(clear, None).unwrap();
+	assert_eq!(state(&gasometer), (
+		100_000 - 12 - Istanbul::CONFIG.gas_sstore_reset,
+		12 + Istanbul::CONFIG.gas_sstore_reset,
+		Istanbul::CONFIG.refund_sstore_clears,
+	));
+
+	let memory = MemoryCost { offset: U256::zero(), len: U256::from(u64::MAX) };
+	assert_eq!(gasometer.record_dynamic_cost(GasCost::VeryLow, Some(memory)), Err(ExitError::OutOfGas));
+	assert_eq!(state(&gasometer), (0, 100_000, 0));
+}
+
+#[test]
+fn record_transaction() {
+	let mut gasometer = Gasometer::<Istanbul>::new(100_000);
+	gasometer.record_transaction(gasometer::call_transaction_cost(&[0, 1])).unwrap();
+	let cost = Istanbul::CONFIG.gas_transaction_call +
+		Istanbul::CONFIG.gas_transaction_zero_data + Istanbul::CONFIG.gas_transaction_non_zero_data;
+	assert_eq!(state(&gasometer), (100_000 - cost, cost, 0));
+
+	let mut gasometer = Gasometer::<Istanbul>::new(20_000);
+	assert_eq!(gasometer.record_transaction(gasometer::call_transaction_cost(&[])), Err(ExitError::OutOfGas));
+	assert_eq!(state(&gasometer), (0, 20_000, 0));
+}
+
+#[test]
+fn memory_words() {
+	let words = |offset: u64, len: u64| MemoryCost { offset: U256::from(offset), len: U256::from(len) }.words();
+	assert_eq!(words(100, 0), Ok(0));
+	assert_eq!(words(0, 1), Ok(1));
+	assert_eq!(words(0, 32), Ok(1));
+	assert_eq!(words(31, 2), Ok(2));
+	let overflow = MemoryCost { offset: U256::MAX, len: U256::one() };
+	assert_eq!(overflow.words(), Err(ExitError::OutOfGas));
+}
+
+#[test]
+fn memory_expansion_cost() {
+	assert_eq!(gasometer::memory_expansion_cost(0, U256::zero(), U256::from(32)), Ok(3));
+	assert_eq!(gasometer::memory_expansion_cost(1, U256::zero(), U256::from(64)), Ok(3));
+	assert_eq!(gasometer::memory_expansion_cost(2, U256::zero(), U256::from(32)), Ok(0));
+	assert_eq!(gasometer::memory_expansion_cost(0, U256::from(7), U256::zero()), Ok(0));
+	// 32 words: 3 per word plus 32 * 32 / 512.
+	assert_eq!(gasometer::memory_expansion_cost(0, U256::zero(), U256::from(1024)), Ok(98));
+	assert_eq!(gasometer::memory_expansion_cost(0, U256::zero(), U256::from(u64::MAX)), Err(ExitError::OutOfGas));
+
+	// Same as charged by the gasometer on top of the opcode cost.
+	let mut gasometer = Gasometer::<Istanbul>::new(100_000);
+	gasometer.record_dynamic_cost(GasCost::Zero, Some(MemoryCost { offset: U256::zero(), len: U256::from(64) })).unwrap();
+	let used = gasometer.total_used_gas();
+	gasometer.record_dynamic_cost(GasCost::Zero, Some(MemoryCost { offset: U256::from(100), len: U256::from(2000) })).unwrap();
+	let expansion = gasometer::memory_expansion_cost(2, U256::from(100), U256::from(2000)).unwrap();
+	assert_eq!(gasometer.total_used_gas() - used, expansion);
+}
+
+#[test]
+fn zero_data_len() {
+	fn zero_data_len(data: &[u8]) -> usize {
+		match gasometer::call_transaction_cost(data) {
+			TransactionCost::Call { zero_data_len, non_zero_data_len } => {
+				assert_eq!(zero_data_len + non_zero_data_len, data.len());
+				zero_data_len
+			},
+			TransactionCost::Create { .. } => unreachable!(),
+		}
+	}
+
+	// Bytes with the high or low bit alone set, next to zero bytes.
+	let mixed = (0..40).map(|i| [0, 0x80, 0x01, 0xff, 0x7f][i * 7 % 5]).collect::<Vec<_>>();
+	for fill in [&[0x80_u8; 40][..], &[0x01; 40], &[0x00; 40], &mixed] {
+		// Unaligned starts, so that every length has every tail.
+		for start in 0..8 {
+			for len in 0..=17 {
+				let data = &fill[start..start + len];
+				let naive = data.iter().filter(|b| **b == 0).count();
+				assert_eq!(zero_data_len(data), naive, "{:?}", data);
+			}
+		}
+	}
+}
@@ -0,0 +1,74 @@
+#![cfg(feature = "genesis")]
+
+mod common;
+
+use std::collections::BTreeMap;
+use evm::{H160, U256};
+use evm::backend::{GenesisError, MemoryAccount, MemoryBackend};
+use common::vicinity;
+
+const GENESIS: &str = r#"{
+	"config": { "chainId": 1 },
+	"difficulty": "0x1",
+	"gasLimit": "0x1c9c380",
+	"alloc": {
+		"1111111111111111111111111111111111111111": { "balance": "1000000000000000000" },
+		"0x2222222222222222222222222222222222222222": {
+			"balance": "0x0",
+			"nonce": "0x1",
+			"code": "0x600160005500",
+			"storage": {
+				"0x00": "0x2a",
+				"0x0000000000000000000000000000000000000000000000000000000000000001": "0x01"
+			}
+		}
+	}
+}"#;
+
+#[test]
+fn load_genesis() {
+	let vicinity = vicinity();
+	let backend = MemoryBackend::from_genesis_json(&vicinity, GENESIS).unwrap();
+
+	let mut storage = BTreeMap::new();
+	storage.insert(U256::zero(), U256::from(42));
+	storage.insert(U256::one(), U256::one());
+	let mut expected = BTreeMap::new();
+	expected.insert(H160::repeat_byte(0x11), MemoryAccount {
+		balance: U256::exp10(18),
+		..Default::default()
+	});
+	expected.insert(H160::repeat_byte(0x22), MemoryAccount {
+		nonce: U256::one(),
+		balance: U256::zero(),
+		storage,
+		code: vec![0x60, 0x01, 0x60, 0x00, 0x55, 0x00],
+	});
+	assert_eq!(backend.state(), &expected);
+}
+
+#[test]
+fn genesis_round_trip() {
+	let vicinity = vicinity();
+	let backend = MemoryBackend::from_genesis_json(&vicinity, GENESIS).unwrap();
+	let json = backend.to_genesis_json();
+	let reloaded = MemoryBackend::from_genesis_json(&vicinity, &json).unwrap();
+	assert_eq!(reloaded.state(), backend.state());
+	assert_eq!(reloaded.to_genesis_json(), json);
+}
+
+#[test]
+fn invalid_genesis() {
+	let vicinity = vicinity();
+	let load = |json: &str| MemoryBackend::from_genesis_json(&vicinity, json).map(|_| ());
+
+	assert!(matches!(load("{"), Err(GenesisError::Json(_))));
+	assert_eq!(
+		load(r#"{ "alloc": { "0x1111": {} } }"#),
+		Err(GenesisError::InvalidValue("0x1111".into())),
+	);
+	assert_eq!(
+		load(r#"{ "alloc": { "0x1111111111111111111111111111111111111111": { "code": "0x6" } } }"#),
+		Err(GenesisError::InvalidValue("0x6".into())),
+	);
+}
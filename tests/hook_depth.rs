@@ -0,0 +1,118 @@
+mod common;
+
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use evm::{CreateScheme, ExitError, ExitReason, ExitSucceed, Keccak, CONFIG, H160, H256, U256};
+use evm::backend::{Backend, Basic, CallInterceptor, CallRequest, CallResponse, MemoryAccount, MemoryBackend, MemoryVicinity};
+use evm::executor::StackExecutor;
+use common::{caller, vicinity};
+
+/// Served by the hook, which calls itself again through a nested executor.
+fn recursive() -> H160 {
+	H160::repeat_byte(0x22)
+}
+
+fn precompile_address() -> H160 {
+	H160::from(U256::from(8))
+}
+
+type PrecompileResult = Option<Result<(ExitSucceed, Vec<u8>, u64), ExitError>>;
+
+fn precompile(address: H160, _input: &[u8], _target_gas: Option<u64>) -> PrecompileResult {
+	if address == precompile_address() {
+		Some(Ok((ExitSucceed::Returned, Vec::new(), 0)))
+	} else {
+		None
+	}
+}
+
+struct HookBackend<'vicinity> {
+	inner: MemoryBackend<'vicinity>,
+	max_depth: Cell<usize>,
+}
+
+impl Keccak for HookBackend<'_> {
+	fn keccak256_h256(&self, data: &[u8]) -> H256 { self.inner.keccak256_h256(data) }
+}
+
+impl Backend for HookBackend<'_> {
+	fn gas_price(&self) -> U256 { self.inner.gas_price() }
+	fn origin(&self) -> H160 { self.inner.origin() }
+	fn block_hash(&self, number: U256) -> H256 { self.inner.block_hash(number) }
+	fn block_number(&self) -> U256 { self.inner.block_number() }
+	fn block_coinbase(&self) -> H160 { self.inner.block_coinbase() }
+	fn block_timestamp(&self) -> U256 { self.inner.block_timestamp() }
+	fn block_difficulty(&self) -> U256 { self.inner.block_difficulty() }
+	fn block_gas_limit(&self) -> U256 { self.inner.block_gas_limit() }
+	fn chain_id(&self) -> U256 { self.inner.chain_id() }
+	fn exists(&self, address: H160) -> bool { self.inner.exists(address) }
+	fn basic(&self, address: H160) -> Basic { self.inner.basic(address) }
+	fn code_hash(&self, address: H160) -> H256 { self.inner.code_hash(address) }
+	fn code_size(&self, address: H160) -> usize { self.inner.code_size(address) }
+	fn code(&self, address: H160) -> Vec<u8> { self.inner.code(address) }
+	fn valids(&self, address: H160) -> Vec<u8> { self.inner.valids(address) }
+	fn storage(&self, address: H160, index: U256) -> U256 { self.inner.storage(address, index) }
+	fn create(&self, scheme: &CreateScheme, address: &H160) { self.inner.create(scheme, address) }
+
+	fn call_interceptor(&self) -> Option<&dyn CallInterceptor> {
+		Some(self)
+	}
+}
+
+impl CallInterceptor for HookBackend<'_> {
+	fn intercept(&self, call: &CallRequest) -> Option<CallResponse> {
+		if call.code_address != recursive() {
+			return None
+		}
+
+		self.max_depth.set(std::cmp::max(self.max_depth.get(), call.depth));
+		let mut executor = StackExecutor::new(self, 1_000_000).with_depth(call.depth);
+		let (reason, output) = executor.transact_call(caller(), recursive(), U256::zero(), call.input.clone(), 1_000_000);
+		// No gas is charged, so that only the call limit bounds the recursion.
+		Some(CallResponse::new(reason, output, 0))
+	}
+}
+
+fn backend(vicinity: &MemoryVicinity) -> HookBackend<'_> {
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount::default());
+	HookBackend {
+		inner: MemoryBackend::new(vicinity, state),
+		max_depth: Cell::new(0),
+	}
+}
+
+#[test]
+fn hook_recursion_terminates() {
+	// Every level runs a nested executor on the native stack.
+	std::thread::Builder::new()
+		.stack_size(512 * 1024 * 1024)
+		.spawn(|| {
+			let vicinity = vicinity();
+			let backend = backend(&vicinity);
+			let mut executor = StackExecutor::new(&backend, 1_000_000);
+			let (reason, _) = executor.transact_call(caller(), recursive(), U256::zero(), Vec::new(), 1_000_000);
+			assert_eq!(reason, ExitReason::Error(ExitError::CallTooDeep));
+			assert_eq!(backend.max_depth.get(), CONFIG.call_stack_limit);
+		})
+		.unwrap()
+		.join()
+		.unwrap();
+}
+
+#[test]
+fn precompile_counts_towards_depth() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+
+	let mut executor = StackExecutor::new_with_precompile(&backend, 1_000_000, precompile)
+		.with_depth(CONFIG.call_stack_limit - 1);
+	let (reason, _) = executor.transact_call(caller(), precompile_address(), U256::zero(), Vec::new(), 1_000_000);
+	assert_eq!(reason, ExitReason::Succeed(ExitSucceed::Returned));
+
+	let mut executor = StackExecutor::new_with_precompile(&backend, 1_000_000, precompile)
+		.with_depth(CONFIG.call_stack_limit);
+	let (reason, _) = executor.transact_call(caller(), precompile_address(), U256::zero(), Vec::new(), 1_000_000);
+	assert_eq!(reason, ExitReason::Error(ExitError::CallTooDeep));
+	assert_eq!(executor.depth(), Some(CONFIG.call_stack_limit));
+}
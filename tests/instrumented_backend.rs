@@ -0,0 +1,84 @@
+mod common;
+
+use std::cell::Cell;
+use std::collections::{BTreeMap, BTreeSet};
+use evm::{Evm, H160, U256};
+use evm::backend::{Backend, InstrumentedBackend, MemoryAccount, MemoryBackend, ReadListener, StateReads};
+use evm::executor::StackExecutor;
+use common::{account, caller, contract, vicinity};
+
+fn queried() -> H160 {
+	H160::repeat_byte(0x33)
+}
+
+fn sized() -> H160 {
+	H160::repeat_byte(0x44)
+}
+
+/// Reads slot 3 twice, the balance of `queried()` and the code size of
+/// `sized()`.
+fn code() -> Vec<u8> {
+	let mut code = vec![0x60, 0x03, 0x54, 0x60, 0x03, 0x54, 0x50, 0x50, 0x73];
+	code.extend_from_slice(queried().as_bytes());
+	code.extend_from_slice(&[0x31, 0x50, 0x73]);
+	code.extend_from_slice(sized().as_bytes());
+	code.extend_from_slice(&[0x3b, 0x50, 0x00]);
+	code
+}
+
+fn state() -> BTreeMap<H160, MemoryAccount> {
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount::default());
+	state.insert(contract(), account(code()));
+	state
+}
+
+#[test]
+fn records_reads() {
+	let vicinity = vicinity();
+	let reads = StateReads::new();
+	let backend = InstrumentedBackend::new(MemoryBackend::new(&vicinity, state()), &reads);
+	let mut executor = StackExecutor::new(&backend, 1_000_000);
+	let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 100_000);
+	assert!(reason.is_succeed());
+
+	assert!(reads.accounts().is_superset(&[caller(), contract(), queried()].iter().copied().collect()));
+	assert!(reads.code().contains(&contract()));
+	assert!(reads.code().contains(&sized()));
+	let mut storage = BTreeMap::new();
+	storage.insert(contract(), BTreeSet::from([U256::from(3)]));
+	assert_eq!(reads.storage(), storage);
+	assert!(reads.reads() > reads.accounts().len() + reads.code().len());
+}
+
+#[test]
+fn records_storage_emptiness_reads() {
+	let vicinity = vicinity();
+	let reads = StateReads::new();
+	let backend = InstrumentedBackend::new(MemoryBackend::new(&vicinity, state()), &reads);
+
+	// As read by the storage collision check of creates.
+	assert!(!backend.has_storage(contract()));
+	assert_eq!(reads.accounts(), BTreeSet::from([contract()]));
+}
+
+/// Counts storage reads only.
+#[derive(Default)]
+struct StorageCounter(Cell<usize>);
+
+impl ReadListener for StorageCounter {
+	fn on_storage_read(&self, _address: H160, _index: U256) {
+		self.0.set(self.0.get() + 1);
+	}
+}
+
+#[test]
+fn custom_listener_through_facade() {
+	let vicinity = vicinity();
+	let backend = InstrumentedBackend::new(MemoryBackend::new(&vicinity, state()), StorageCounter::default());
+	let mut evm = Evm::new(backend);
+	let summary = evm.call(caller(), contract(), U256::zero(), Vec::new(), 100_000);
+	assert!(summary.reason.is_succeed());
+	assert_eq!(evm.backend().listener().0.get(), 2);
+	assert_eq!(evm.backend().inner().state()[&caller()].nonce, U256::one());
+}
@@ -0,0 +1,186 @@
+mod common;
+
+use std::sync::Arc;
+use evm::{
+	Capture, Context, CreateScheme, ExitError, ExitFatal, ExitReason, ExitRevert, ExitSucceed, Handler, Keccak,
+	Machine, Memory, Opcode, Resolve, Runtime, Stack, Transfer, Valids, H160, H256, U256,
+};
+use common::contract;
+
+fn target() -> H160 {
+	H160::repeat_byte(0x33)
+}
+
+/// Handler trapping every call, recording the feedback of the last one.
+#[derive(Default)]
+struct TrapHandler {
+	feedback: Option<u64>,
+}
+
+impl Keccak for TrapHandler {
+	fn keccak256_h256(&self, _data: &[u8]) -> H256 { H256::zero() }
+}
+
+impl Handler for TrapHandler {
+	type CreateInterrupt = ();
+	type CreateFeedback = ();
+	/// Code address of the trapped call.
+	type CallInterrupt = H160;
+	/// Gas left by the call frame.
+	type CallFeedback = u64;
+
+	fn balance(&self, _address: H160) -> U256 { U256::zero() }
+	fn code_size(&self, _address: H160) -> U256 { U256::zero() }
+	fn code_hash(&self, _address: H160) -> H256 { H256::zero() }
+	fn code(&self, _address: H160) -> Vec<u8> { Vec::new() }
+	fn valids(&self, _address: H160) -> Vec<u8> { Vec::new() }
+	fn storage(&self, _address: H160, _index: U256) -> U256 { U256::zero() }
+	fn original_storage(&self, _address: H160, _index: U256) -> U256 { U256::zero() }
+	fn gas_left(&self) -> U256 { U256::zero() }
+	fn gas_price(&self) -> U256 { U256::zero() }
+	fn origin(&self) -> H160 { H160::zero() }
+	fn block_hash(&self, _number: U256) -> H256 { H256::zero() }
+	fn block_number(&self) -> U256 { U256::zero() }
+	fn block_coinbase(&self) -> H160 { H160::zero() }
+	fn block_timestamp(&self) -> U256 { U256::zero() }
+	fn block_difficulty(&self) -> U256 { U256::zero() }
+	fn block_gas_limit(&self) -> U256 { U256::zero() }
+	fn chain_id(&self) -> U256 { U256::zero() }
+	fn exists(&self, _address: H160) -> bool { false }
+	fn deleted(&self, _address: H160) -> bool { false }
+	fn set_storage(&mut self, _address: H160, _index: U256, _value: U256) -> Result<(), ExitError> { Ok(()) }
+	fn log(&mut self, _address: H160, _topics: Vec<H256>, _data: Vec<u8>) -> Result<(), ExitError> { Ok(()) }
+	fn mark_delete(&mut self, _address: H160, _target: H160) -> Result<(), ExitError> { Ok(()) }
+
+	fn create(
+		&mut self,
+		_caller: H160,
+		_scheme: CreateScheme,
+		_value: U256,
+		_init_code: Vec<u8>,
+		_target_gas: Option<u64>,
+	) -> Capture<(ExitReason, Option<H160>, Vec<u8>), ()> {
+		Capture::Trap(())
+	}
+
+	fn call(
+		&mut self,
+		code_address: H160,
+		_transfer: Option<Transfer>,
+		_input: Vec<u8>,
+		_target_gas: Option<u64>,
+		_is_static: bool,
+		_context: Context,
+	) -> Capture<(ExitReason, Vec<u8>), H160> {
+		Capture::Trap(code_address)
+	}
+
+	fn call_feedback(&mut self, gas_left: u64) -> Result<(), ExitError> {
+		self.feedback = Some(gas_left);
+		Ok(())
+	}
+
+	fn pre_validate(
+		&mut self,
+		_context: &Context,
+		_opcode: Opcode,
+		_position: usize,
+		_stack: &Stack,
+		_memory: &Memory,
+	) -> Result<(), ExitError> {
+		Ok(())
+	}
+
+	fn other(&mut self, _opcode: Opcode, _machine: &mut Machine) -> Result<(), ExitError> {
+		Err(ExitError::OutOfGas)
+	}
+}
+
+/// Calls the target with a 32-byte output range at 0, then returns the
+/// output, the success flag and the return data size.
+fn runtime() -> Runtime {
+	let mut code = vec![0x60, 0x20, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x73];
+	code.extend_from_slice(target().as_bytes());
+	code.extend_from_slice(&[
+		0x61, 0xff, 0xff, 0xf1,
+		0x60, 0x20, 0x52,
+		0x3d, 0x60, 0x40, 0x52,
+		0x60, 0x60, 0x60, 0x00, 0xf3,
+	]);
+	let context = Context { address: contract(), caller: H160::zero(), apparent_value: U256::zero() };
+	Runtime::new(code.clone(), Arc::new(Valids::compute(&code).into()), Vec::new(), context)
+}
+
+/// Run until the call is trapped, resolve it, and run to the end.
+fn resolve_call(reason: ExitReason, output: Vec<u8>) -> (TrapHandler, ExitReason, Vec<u8>) {
+	let mut handler = TrapHandler::default();
+	let mut runtime = runtime();
+	match runtime.run(u64::MAX, &mut handler).1 {
+		Capture::Trap(Resolve::Call(address, resolve)) => {
+			assert_eq!(address, target());
+			resolve.resolve(&mut handler, reason, output, 1234).unwrap();
+		},
+		_ => panic!("call not trapped"),
+	}
+	let reason = match runtime.run(u64::MAX, &mut handler).1 {
+		Capture::Exit(reason) => reason,
+		Capture::Trap(_) => panic!("unexpected trap"),
+	};
+	let return_value = runtime.machine().return_value();
+	(handler, reason, return_value)
+}
+
+fn word(n: u64) -> Vec<u8> {
+	H256::from(U256::from(n)).as_bytes().to_vec()
+}
+
+#[test]
+fn resolve_succeeded_call() {
+	let (handler, reason, output) = resolve_call(ExitSucceed::Returned.into(), vec![0xab; 40]);
+	assert_eq!(handler.feedback, Some(1234));
+	assert_eq!(reason, ExitSucceed::Returned.into());
+	assert_eq!(output, [vec![0xab; 32], word(1), word(40)].concat());
+}
+
+#[test]
+fn resolve_reverted_call() {
+	let (_, reason, output) = resolve_call(ExitRevert::Reverted.into(), vec![0xcd; 4]);
+	assert_eq!(reason, ExitSucceed::Returned.into());
+	let mut revert_data = vec![0; 32];
+	revert_data[..4].copy_from_slice(&[0xcd; 4]);
+	assert_eq!(output, [revert_data, word(0), word(4)].concat());
+}
+
+#[test]
+fn resolve_failed_call() {
+	let (_, reason, output) = resolve_call(ExitError::OutOfGas.into(), vec![0xcd; 4]);
+	assert_eq!(reason, ExitSucceed::Returned.into());
+	assert_eq!(output, [word(0), word(0), word(0)].concat());
+}
+
+#[test]
+fn resolve_fatal_call() {
+	let mut handler = TrapHandler::default();
+	let mut runtime = runtime();
+	let fatal: ExitReason = ExitFatal::NotSupported.into();
+	match runtime.run(u64::MAX, &mut handler).1 {
+		Capture::Trap(Resolve::Call(_, resolve)) => {
+			assert_eq!(resolve.resolve(&mut handler, fatal, Vec::new(), 0), Err(fatal));
+		},
+		_ => panic!("call not trapped"),
+	}
+	assert!(matches!(runtime.run(u64::MAX, &mut handler).1, Capture::Exit(reason) if reason == fatal));
+}
+
+#[test]
+fn unresolved_call() {
+	let mut handler = TrapHandler::default();
+	let mut runtime = runtime();
+	assert!(matches!(runtime.run(u64::MAX, &mut handler).1, Capture::Trap(Resolve::Call(..))));
+	let reason = match runtime.run(u64::MAX, &mut handler).1 {
+		Capture::Exit(reason) => reason,
+		Capture::Trap(_) => panic!("unexpected trap"),
+	};
+	assert_eq!(reason, ExitFatal::UnhandledInterrupt.into());
+	assert_eq!(handler.feedback, None);
+}
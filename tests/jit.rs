@@ -0,0 +1,94 @@
+#![cfg(feature = "jit")]
+
+mod common;
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use evm::{CompiledCode, Machine, Valids, U256};
+use evm::backend::{Backend, MemoryBackend};
+use evm::executor::StackExecutor;
+use evm::jit::JitCache;
+use evm::metrics::MetricsCollector;
+use common::{account, caller, contract, vicinity};
+
+/// Count down from 5, storing the counter in memory on every iteration,
+/// and return it. Ends with a truncated push.
+const LOOP: [u8; 26] = [
+	0x60, 0x05, // PUSH1 5
+	0x5b, // JUMPDEST
+	0x60, 0x01, 0x90, 0x03, // PUSH1 1 SWAP1 SUB
+	0x80, 0x60, 0x00, 0x52, // DUP1 PUSH1 0 MSTORE
+	0x80, 0x60, 0x02, 0x57, // DUP1 PUSH1 2 JUMPI
+	0x58, 0x50, // PC POP
+	0x60, 0x20, 0x60, 0x00, 0xf3, // PUSH1 32 PUSH1 0 RETURN
+	0x63, 0x01, 0x02, 0x03, // truncated PUSH4
+];
+
+#[test]
+fn machine_differential() {
+	let compiled = Arc::new(CompiledCode::compile(&LOOP));
+	assert_eq!(compiled.len(), LOOP.len());
+
+	for code in [&LOOP[..], &LOOP[22..]] {
+		for max_steps in 0..40 {
+			let run = |compile: bool| {
+				let mut machine = Machine::new(code.to_vec(), Arc::new(Valids::compute(code).into()), Vec::new(), 1024, usize::MAX);
+				if compile {
+					machine.set_compiled(Arc::new(CompiledCode::compile(code)));
+				}
+				let mut validated = Vec::new();
+				let result = machine.run(max_steps, |opcode, position, stack, _| {
+					validated.push((opcode, position, stack.data().to_vec()));
+					Ok(())
+				});
+				(result, validated, machine.position(), machine.stack().data().to_vec(), machine.return_value())
+			};
+			assert_eq!(run(true), run(false), "max steps {}", max_steps);
+		}
+	}
+}
+
+#[test]
+fn executor_differential() {
+	let vicinity = vicinity();
+	let mut state = BTreeMap::new();
+	state.insert(contract(), account(LOOP.to_vec()));
+	let backend = MemoryBackend::new(&vicinity, state);
+
+	// Every gas limit running out at a different opcode of the loop.
+	for gas_limit in 21_000..21_250 {
+		let run = |jit: Option<JitCache>| {
+			let collector = MetricsCollector::new();
+			let mut executor = StackExecutor::new(&backend, gas_limit).with_listener(&collector);
+			if let Some(jit) = jit {
+				executor = executor.with_jit(jit);
+			}
+			let result = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), gas_limit);
+			(result, executor.used_gas(), collector.into_report())
+		};
+		assert_eq!(run(Some(JitCache::new(1))), run(None), "gas limit {}", gas_limit);
+	}
+}
+
+#[test]
+fn compiled_once_hot() {
+	let vicinity = vicinity();
+	let mut state = BTreeMap::new();
+	state.insert(contract(), account(LOOP.to_vec()));
+	let backend = MemoryBackend::new(&vicinity, state);
+	let hash = backend.code_hash(contract());
+
+	let jit = JitCache::new(3);
+	for count in 1..=3 {
+		assert!(!jit.is_hot(hash));
+		let mut executor = StackExecutor::new(&backend, 100_000).with_jit(jit.clone());
+		let (reason, out) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 100_000);
+		assert!(reason.is_succeed());
+		assert_eq!(out, [0_u8; 32]);
+		if count < 3 {
+			assert_eq!(jit.count(hash), Some(count));
+		}
+	}
+	assert!(jit.is_hot(hash));
+	assert_eq!(jit.count(hash), None);
+}
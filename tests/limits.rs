@@ -0,0 +1,75 @@
+mod common;
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use evm::{Config, Context, ExitError, ExitFatal, ExitReason, Limits, Runtime, Valids, U256};
+use evm::backend::MemoryBackend;
+use evm::executor::StackExecutor;
+use common::{account, caller, contract, vicinity};
+
+fn context() -> Context {
+	Context { address: contract(), caller: caller(), apparent_value: U256::zero() }
+}
+
+fn run(code: Vec<u8>, limits: Option<Limits>) -> ExitReason {
+	let vicinity = vicinity();
+	let mut state = BTreeMap::new();
+	state.insert(contract(), account(code));
+	let backend = MemoryBackend::new(&vicinity, state);
+
+	let mut executor = StackExecutor::new(&backend, 100_000);
+	if let Some(limits) = limits {
+		executor = executor.with_limits(limits);
+	}
+	executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 100_000).0
+}
+
+#[test]
+fn runtime_limits() {
+	let code = vec![0x00];
+	let runtime = Runtime::new(code.clone(), Arc::new(Valids::compute(&code).into()), Vec::new(), context());
+	assert_eq!(runtime.limits(), Limits::from_config(&Config::istanbul()));
+
+	let limits = Limits { stack_limit: 4, memory_limit: 64, return_data_limit: 32 };
+	let runtime = Runtime::new_with_limits(code.clone(), Arc::new(Valids::compute(&code).into()), Vec::new(), context(), limits);
+	assert_eq!(runtime.limits(), limits);
+	assert_eq!(runtime.machine().stack().limit(), 4);
+	assert_eq!(runtime.machine().memory().limit(), 64);
+	assert_eq!(runtime.machine().return_data_limit(), 32);
+}
+
+#[test]
+fn executor_limits() {
+	// PUSH1 1 PUSH1 1 PUSH1 1 STOP
+	let push3 = vec![0x60, 0x01, 0x60, 0x01, 0x60, 0x01, 0x00];
+	assert!(run(push3.clone(), None).is_succeed());
+	let stack = Limits { stack_limit: 2, memory_limit: usize::MAX, return_data_limit: usize::MAX };
+	assert_eq!(run(push3, Some(stack)), ExitReason::Error(ExitError::StackOverflow));
+
+	// PUSH1 1 PUSH1 64 MSTORE STOP
+	let mstore = vec![0x60, 0x01, 0x60, 0x40, 0x52, 0x00];
+	assert!(run(mstore.clone(), None).is_succeed());
+	let memory = Limits { stack_limit: 1024, memory_limit: 64, return_data_limit: usize::MAX };
+	assert_eq!(run(mstore, Some(memory)), ExitReason::Fatal(ExitFatal::NotSupported));
+}
+
+#[test]
+fn return_data_limit() {
+	let limits = Limits { return_data_limit: 32, ..Limits::from_config(&Config::istanbul()) };
+	let vicinity = vicinity();
+	let backend = MemoryBackend::new(&vicinity, BTreeMap::new());
+	assert_eq!(StackExecutor::new(&backend, 0).with_limits(limits).limits(), limits);
+
+	// PUSH1 32 PUSH1 0 RETURN
+	let return32 = vec![0x60, 0x20, 0x60, 0x00, 0xf3];
+	assert!(run(return32, Some(limits)).is_succeed());
+
+	// PUSH1 64 PUSH1 0 RETURN
+	let return64 = vec![0x60, 0x40, 0x60, 0x00, 0xf3];
+	assert!(run(return64.clone(), None).is_succeed());
+	assert_eq!(run(return64, Some(limits)), ExitReason::Error(ExitError::ReturnDataLimit));
+
+	// PUSH1 64 PUSH1 0 REVERT
+	let revert64 = vec![0x60, 0x40, 0x60, 0x00, 0xfd];
+	assert_eq!(run(revert64, Some(limits)), ExitReason::Error(ExitError::ReturnDataLimit));
+}
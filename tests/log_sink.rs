@@ -0,0 +1,133 @@
+mod common;
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use evm::{CreateScheme, ExitReason, ExitRevert, Keccak, H160, H256, U256};
+use evm::backend::{Backend, Basic, Log, LogSink, MemoryAccount, MemoryBackend, MemoryVicinity};
+use evm::executor::StackExecutor;
+use common::{account, callee, caller, contract, vicinity};
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Event {
+	Log(u8, usize),
+	Revert(usize),
+}
+
+/// Backend recording the logs streamed to its sink, identified by their
+/// first data byte.
+struct StreamingBackend<'vicinity> {
+	inner: MemoryBackend<'vicinity>,
+	events: RefCell<Vec<Event>>,
+}
+
+impl Keccak for StreamingBackend<'_> {
+	fn keccak256_h256(&self, data: &[u8]) -> H256 { self.inner.keccak256_h256(data) }
+}
+
+impl Backend for StreamingBackend<'_> {
+	fn gas_price(&self) -> U256 { self.inner.gas_price() }
+	fn origin(&self) -> H160 { self.inner.origin() }
+	fn block_hash(&self, number: U256) -> H256 { self.inner.block_hash(number) }
+	fn block_number(&self) -> U256 { self.inner.block_number() }
+	fn block_coinbase(&self) -> H160 { self.inner.block_coinbase() }
+	fn block_timestamp(&self) -> U256 { self.inner.block_timestamp() }
+	fn block_difficulty(&self) -> U256 { self.inner.block_difficulty() }
+	fn block_gas_limit(&self) -> U256 { self.inner.block_gas_limit() }
+	fn chain_id(&self) -> U256 { self.inner.chain_id() }
+	fn exists(&self, address: H160) -> bool { self.inner.exists(address) }
+	fn basic(&self, address: H160) -> Basic { self.inner.basic(address) }
+	fn code_hash(&self, address: H160) -> H256 { self.inner.code_hash(address) }
+	fn code_size(&self, address: H160) -> usize { self.inner.code_size(address) }
+	fn code(&self, address: H160) -> Vec<u8> { self.inner.code(address) }
+	fn valids(&self, address: H160) -> Vec<u8> { self.inner.valids(address) }
+	fn storage(&self, address: H160, index: U256) -> U256 { self.inner.storage(address, index) }
+	fn create(&self, scheme: &CreateScheme, address: &H160) { self.inner.create(scheme, address) }
+
+	fn log_sink(&self) -> Option<&dyn LogSink> {
+		Some(self)
+	}
+}
+
+impl LogSink for StreamingBackend<'_> {
+	fn log(&self, log: &Log, position: usize) {
+		self.events.borrow_mut().push(Event::Log(log.data[0], position));
+	}
+
+	fn revert(&self, position: usize) {
+		self.events.borrow_mut().push(Event::Revert(position));
+	}
+}
+
+/// `LOG0` of the single byte `id`.
+fn log(id: u8) -> Vec<u8> {
+	// PUSH1 id PUSH1 0 MSTORE8 PUSH1 1 PUSH1 0 LOG0
+	vec![0x60, id, 0x60, 0x00, 0x53, 0x60, 0x01, 0x60, 0x00, 0xa0]
+}
+
+/// Log 2 and revert.
+fn reverting() -> Vec<u8> {
+	let mut code = log(2);
+	code.extend_from_slice(&[0x60, 0x00, 0x60, 0x00, 0xfd]);
+	code
+}
+
+fn backend(vicinity: &MemoryVicinity, callee_code: Vec<u8>) -> StreamingBackend<'_> {
+	// Log 1, call `callee()` and log 3.
+	let mut code = log(1);
+	code.extend_from_slice(&[0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x73]);
+	code.extend_from_slice(callee().as_bytes());
+	code.extend_from_slice(&[0x5a, 0xf1, 0x50]);
+	code.extend_from_slice(&log(3));
+
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount::default());
+	state.insert(contract(), account(code));
+	state.insert(callee(), account(callee_code));
+	StreamingBackend {
+		inner: MemoryBackend::new(vicinity, state),
+		events: RefCell::new(Vec::new()),
+	}
+}
+
+#[test]
+fn reverted_frame_logs_are_retracted() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity, reverting());
+
+	let mut executor = StackExecutor::new(&backend, 1_000_000);
+	let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 1_000_000);
+	assert!(reason.is_succeed());
+	assert_eq!(*backend.events.borrow(), vec![
+		Event::Log(1, 0),
+		Event::Log(2, 1),
+		Event::Revert(1),
+		Event::Log(3, 1),
+	]);
+
+	let (_, logs) = executor.deconstruct();
+	let ids = logs.into_iter().map(|log| log.data[0]).collect::<Vec<_>>();
+	assert_eq!(ids, vec![1, 3]);
+}
+
+#[test]
+fn reverted_transaction_logs_are_retracted() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity, reverting());
+
+	let mut executor = StackExecutor::new(&backend, 1_000_000);
+	let (reason, _) = executor.transact_call(caller(), callee(), U256::zero(), Vec::new(), 1_000_000);
+	assert_eq!(reason, ExitReason::Revert(ExitRevert::Reverted));
+	assert_eq!(*backend.events.borrow(), vec![Event::Log(2, 0), Event::Revert(0)]);
+}
+
+#[test]
+fn frames_without_logs_are_not_retracted() {
+	let vicinity = vicinity();
+	// PUSH1 0 PUSH1 0 REVERT
+	let backend = backend(&vicinity, vec![0x60, 0x00, 0x60, 0x00, 0xfd]);
+
+	let mut executor = StackExecutor::new(&backend, 1_000_000);
+	let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 1_000_000);
+	assert!(reason.is_succeed());
+	assert_eq!(*backend.events.borrow(), vec![Event::Log(1, 0), Event::Log(3, 1)]);
+}
@@ -0,0 +1,90 @@
+use std::sync::Arc;
+use evm::{Capture, ExitError, ExitReason, ExitSucceed, Instruction, Machine, Opcode, Valids, U256};
+
+/// `PUSH1 1 PUSH1 2 ADD POP STOP`.
+const CODE: [u8; 7] = [0x60, 0x01, 0x60, 0x02, 0x01, 0x50, 0x00];
+
+fn machine(code: &[u8]) -> Machine {
+	Machine::new(code.to_vec(), Arc::new(Valids::compute(code).into()), Vec::new(), 1024, usize::MAX)
+}
+
+#[test]
+fn resume_after_step_limit() {
+	let mut machine = machine(&CODE);
+	let (steps, capture) = machine.run(2, |_, _, _, _| Ok(()));
+	assert_eq!(steps, 2);
+	assert_eq!(capture, Capture::Exit(ExitReason::StepLimitReached));
+	assert_eq!(machine.position(), Ok(4));
+
+	let (steps, capture) = machine.run(u64::MAX, |_, _, _, _| Ok(()));
+	assert_eq!(steps, 2);
+	assert_eq!(capture, Capture::Exit(ExitSucceed::Stopped.into()));
+	assert_eq!(machine.position(), Err(ExitSucceed::Stopped.into()));
+	assert!(machine.inspect().is_none());
+
+	// An exited machine stays exited.
+	let (steps, capture) = machine.run(u64::MAX, |_, _, _, _| Ok(()));
+	assert_eq!(steps, 0);
+	assert_eq!(capture, Capture::Exit(ExitSucceed::Stopped.into()));
+	assert_eq!(machine.step(), Err(Capture::Exit(ExitSucceed::Stopped.into())));
+}
+
+#[test]
+fn pre_validate_error_exits_at_opcode() {
+	let mut machine = machine(&CODE);
+	let (steps, capture) = machine.run(u64::MAX, |_, position, _, _| {
+		if position == 4 { Err(ExitError::OutOfGas) } else { Ok(()) }
+	});
+	assert_eq!(steps, 2);
+	assert_eq!(capture, Capture::Exit(ExitError::OutOfGas.into()));
+	assert_eq!(machine.position(), Err(ExitError::OutOfGas.into()));
+}
+
+#[test]
+fn run_matches_step() {
+	let mut stepped = machine(&CODE);
+	let mut positions = Vec::new();
+	while let Ok(position) = stepped.position() {
+		positions.push(position);
+		if stepped.step().is_err() {
+			break
+		}
+	}
+	assert_eq!(positions, vec![0, 2, 4, 5, 6]);
+
+	let mut run = machine(&CODE);
+	let mut validated = Vec::new();
+	let _ = run.run(u64::MAX, |_, position, _, _| {
+		validated.push(position);
+		Ok(())
+	});
+	assert_eq!(validated, positions);
+	assert_eq!(run.position(), stepped.position());
+}
+
+#[test]
+fn inspect_detailed_decodes_pushes() {
+	// PUSH2 0x0102 STOP PUSH3 0xff (truncated)
+	let code = [0x61, 0x01, 0x02, 0x00, 0x62, 0xff];
+	let mut machine = machine(&code);
+
+	let (instruction, stack) = machine.inspect_detailed().unwrap();
+	assert_eq!(instruction, Instruction { opcode: Opcode::PUSH2, immediate: &[0x01, 0x02], next: 3 });
+	assert_eq!(instruction.push_value(), Some(U256::from(0x0102)));
+	assert_eq!(stack.len(), 0);
+
+	machine.step().unwrap();
+	let (instruction, stack) = machine.inspect_detailed().unwrap();
+	assert_eq!(instruction, Instruction { opcode: Opcode::STOP, immediate: &[], next: 4 });
+	assert_eq!(instruction.push_value(), None);
+	assert_eq!(stack.len(), 1);
+
+	let instruction = Instruction::decode(&code, 4).unwrap();
+	assert_eq!(instruction.immediate, [0xff]);
+	assert_eq!(instruction.next, 8);
+	assert_eq!(instruction.push_value(), Some(U256::from(0xff)));
+	assert_eq!(Instruction::decode(&code, 6), None);
+
+	let _ = machine.step();
+	assert!(machine.inspect_detailed().is_none());
+}
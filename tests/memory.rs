@@ -0,0 +1,46 @@
+use evm::{ExitFatal, Memory};
+
+#[test]
+fn len_is_high_water_mark() {
+	let mut memory = Memory::new(usize::MAX);
+	assert!(memory.is_empty());
+
+	memory.set(10, &[1, 2, 3], None).unwrap();
+	assert_eq!(memory.len(), 13);
+	assert_eq!(memory.get(8, 8), vec![0, 0, 1, 2, 3, 0, 0, 0]);
+
+	memory.set(0, &[4], Some(2)).unwrap();
+	assert_eq!(memory.len(), 13);
+	assert_eq!(memory.get(0, 4), vec![4, 0, 0, 0]);
+
+	memory.set(5000, &[5], None).unwrap();
+	assert_eq!(memory.len(), 5001);
+	assert_eq!(memory.get(4999, 3), vec![0, 5, 0]);
+}
+
+#[test]
+fn grow_within_limit() {
+	let mut memory = Memory::new(100);
+	memory.set(60, &[1; 40], None).unwrap();
+	assert_eq!(memory.len(), 100);
+	assert_eq!(memory.set(61, &[1; 40], None), Err(ExitFatal::NotSupported));
+}
+
+#[test]
+fn copy_within() {
+	let mut memory = Memory::new(usize::MAX);
+	memory.set(0, &[1, 2, 3, 4, 5], None).unwrap();
+
+	memory.copy_within(2, 0, 3).unwrap();
+	assert_eq!(memory.get(0, 5), vec![1, 2, 1, 2, 3]);
+
+	memory.copy_within(0, 2, 3).unwrap();
+	assert_eq!(memory.get(0, 5), vec![1, 2, 3, 2, 3]);
+
+	memory.copy_within(40, 3, 4).unwrap();
+	assert_eq!(memory.len(), 44);
+	assert_eq!(memory.get(40, 4), vec![2, 3, 0, 0]);
+
+	memory.copy_within(usize::MAX, 0, 0).unwrap();
+	assert_eq!(memory.copy_within(usize::MAX, 0, 1), Err(ExitFatal::NotSupported));
+}
@@ -0,0 +1,77 @@
+mod common;
+
+use std::collections::BTreeMap;
+use evm::{CollisionKind, ExitError, ExitReason, H160, U256};
+use evm::backend::{MemoryBackend, MemoryVicinity};
+use evm::executor::{NoncePolicy, StackExecutor};
+use common::{account, caller, contract, vicinity};
+
+fn system() -> H160 {
+	H160::repeat_byte(0xff)
+}
+
+/// Calls from `system()` keep its nonce.
+struct SystemCalls;
+
+impl NoncePolicy for SystemCalls {
+	fn increase_on_call(&self, caller: H160) -> bool {
+		caller != system()
+	}
+}
+
+/// Creates never increase the creator nonce.
+struct KeepCreatorNonce;
+
+impl NoncePolicy for KeepCreatorNonce {
+	fn increase_on_create(&self, _caller: H160, _collision: bool) -> bool {
+		false
+	}
+}
+
+fn backend(vicinity: &MemoryVicinity) -> MemoryBackend<'_> {
+	let mut state = BTreeMap::new();
+	state.insert(contract(), account(vec![0x00]));
+	MemoryBackend::new(vicinity, state)
+}
+
+#[test]
+fn zero_nonce_system_calls() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let policy = SystemCalls;
+	let mut executor = StackExecutor::new(&backend, 1_000_000).with_nonce_policy(&policy);
+	for sender in [system(), caller()] {
+		let (reason, _) = executor.transact_call(sender, contract(), U256::zero(), Vec::new(), 100_000);
+		assert!(reason.is_succeed());
+	}
+	assert_eq!(executor.nonce(system()), U256::zero());
+	assert_eq!(executor.nonce(caller()), U256::one());
+}
+
+#[test]
+fn creates_keep_creator_nonce() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let policy = KeepCreatorNonce;
+	let mut executor = StackExecutor::new(&backend, 1_000_000).with_nonce_policy(&policy);
+	let reason = executor.transact_create(caller(), U256::zero(), Vec::new(), 100_000);
+	assert!(reason.is_succeed());
+	assert_eq!(executor.nonce(caller()), U256::zero());
+
+	// The address is derived from the same nonce, and now collides.
+	let reason = executor.transact_create(caller(), U256::zero(), Vec::new(), 100_000);
+	assert_eq!(reason, ExitReason::Error(ExitError::CreateCollision(CollisionKind::Nonce)));
+	assert_eq!(executor.nonce(caller()), U256::zero());
+}
+
+#[test]
+fn default_policy() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let mut executor = StackExecutor::new(&backend, 1_000_000);
+	let (reason, _) = executor.transact_call(system(), contract(), U256::zero(), Vec::new(), 100_000);
+	assert!(reason.is_succeed());
+	let reason = executor.transact_create(system(), U256::zero(), Vec::new(), 100_000);
+	assert!(reason.is_succeed());
+	assert_eq!(executor.nonce(system()), U256::from(2));
+}
@@ -0,0 +1,150 @@
+mod common;
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::io::ErrorKind;
+use evm::{Keccak, Valids, H160, H256, U256};
+use evm::backend::{Backend, MemoryAccount, MemoryBackend, PagedCode, PagedCodeBackend, CODE_PAGE_SIZE};
+use evm::executor::StackExecutor;
+use common::{account, caller, contract, vicinity};
+
+fn copier() -> H160 {
+	H160::repeat_byte(0x33)
+}
+
+/// Returns 32 bytes of the code of `contract()` at `offset`.
+fn extcodecopy(offset: u16) -> Vec<u8> {
+	let mut code = vec![0x60, 0x20, 0x61];
+	code.extend_from_slice(&offset.to_be_bytes());
+	code.extend_from_slice(&[0x60, 0x00, 0x73]);
+	code.extend_from_slice(contract().as_bytes());
+	code.extend_from_slice(&[0x3c, 0x60, 0x20, 0x60, 0x00, 0xf3]);
+	code
+}
+
+/// Returns 42, padded over several pages.
+fn large_code() -> Vec<u8> {
+	let mut code = vec![0x60, 0x2a, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3];
+	let padding = (code.len()..3 * CODE_PAGE_SIZE + 100).map(|i| (i % 251) as u8);
+	code.extend(padding);
+	code
+}
+
+fn write(name: &str, code: &[u8]) -> PathBuf {
+	let path = std::env::temp_dir().join(format!("evm-paged-code-{}-{}", std::process::id(), name));
+	std::fs::write(&path, code).unwrap();
+	path
+}
+
+#[test]
+fn partial_reads_page_in_lazily() {
+	let code = large_code();
+	let path = write("partial", &code);
+	let paged = PagedCode::open(&path, H256::repeat_byte(0xab)).unwrap();
+	assert_eq!(paged.len(), code.len());
+	assert_eq!(paged.resident_pages(), 0);
+
+	let offset = CODE_PAGE_SIZE - 10;
+	assert_eq!(paged.read(offset, 20).unwrap(), &code[offset..offset + 20]);
+	assert_eq!(paged.resident_pages(), 2);
+
+	// Reads past the end are truncated.
+	assert_eq!(paged.read(code.len() - 4, 100).unwrap(), &code[code.len() - 4..]);
+	assert_eq!(paged.read(code.len() + 1, 1).unwrap(), Vec::<u8>::new());
+	assert_eq!(paged.resident_pages(), 3);
+
+	assert_eq!(paged.load().unwrap(), code);
+	assert_eq!(paged.resident_pages(), 3);
+	std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn least_recently_used_pages_are_dropped() {
+	let code = large_code();
+	let path = write("limit", &code);
+	let paged = PagedCode::open(&path, H256::repeat_byte(0xab)).unwrap().with_page_limit(2);
+
+	let page = |index: usize| paged.read(index * CODE_PAGE_SIZE, 1).unwrap();
+	assert_eq!(page(0), &code[..1]);
+	assert_eq!(page(1), &code[CODE_PAGE_SIZE..CODE_PAGE_SIZE + 1]);
+	assert_eq!(page(0), &code[..1]);
+	assert_eq!(page(2), &code[2 * CODE_PAGE_SIZE..2 * CODE_PAGE_SIZE + 1]);
+	assert_eq!(paged.resident_pages(), 2);
+
+	// Page 1 was dropped, page 0 is still served without the file.
+	std::fs::write(&path, []).unwrap();
+	assert_eq!(page(0), &code[..1]);
+	assert_eq!(paged.read(CODE_PAGE_SIZE, 1).unwrap_err().kind(), ErrorKind::UnexpectedEof);
+	std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn unreadable_code_is_empty() {
+	let vicinity = vicinity();
+	let mut backend = PagedCodeBackend::new(MemoryBackend::new(&vicinity, BTreeMap::new()));
+	let code = large_code();
+	let path = write("unreadable", &code);
+	backend.insert(contract(), PagedCode::open(&path, H256::repeat_byte(0xab)).unwrap());
+
+	// Jump destinations are computed once per code hash.
+	assert_eq!(backend.code_and_valids(contract()), (code.clone(), Valids::compute(&code)));
+	std::fs::write(&path, []).unwrap();
+	assert_eq!(backend.valids(contract()), Valids::compute(&code));
+	assert!(backend.take_error().is_none());
+
+	assert_eq!(backend.code(contract()), Vec::<u8>::new());
+	assert_eq!(backend.code_range(contract(), 0, 10), Vec::<u8>::new());
+	assert_eq!(backend.take_error().map(|e| e.kind()), Some(ErrorKind::UnexpectedEof));
+	assert!(backend.take_error().is_none());
+	std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn execute_paged_code() {
+	let vicinity = vicinity();
+	let mut state = BTreeMap::new();
+	state.insert(contract(), MemoryAccount {
+		nonce: U256::one(),
+		..Default::default()
+	});
+	state.insert(copier(), account(extcodecopy(5_000)));
+	let mut backend = PagedCodeBackend::new(MemoryBackend::new(&vicinity, state));
+
+	let code = large_code();
+	let path = write("execute", &code);
+	let hash = backend.keccak256_h256(&code);
+	backend.insert(contract(), PagedCode::open(&path, hash).unwrap());
+
+	assert_eq!(backend.code_size(contract()), code.len());
+	assert_eq!(backend.code_hash(contract()), hash);
+	assert_eq!(backend.paged(contract()).unwrap().resident_pages(), 0);
+
+	let mut executor = StackExecutor::new(&backend, 100_000);
+	let (reason, out) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 100_000);
+	assert!(reason.is_succeed());
+	assert_eq!(U256::from_big_endian(&out), U256::from(42));
+
+	// Copying a window only pages it in.
+	let mut executor = StackExecutor::new(&backend, 100_000);
+	let (reason, out) = executor.transact_call(caller(), copier(), U256::zero(), Vec::new(), 100_000);
+	assert!(reason.is_succeed());
+	assert_eq!(out, &code[5_000..5_032]);
+	assert_eq!(backend.paged(contract()).unwrap().resident_pages(), 1);
+	std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn code_range_past_the_end() {
+	let vicinity = vicinity();
+	let mut state = BTreeMap::new();
+	state.insert(contract(), MemoryAccount {
+		code: vec![1, 2, 3],
+		..Default::default()
+	});
+	let backend = MemoryBackend::new(&vicinity, state);
+	assert_eq!(backend.code_range(contract(), 1, 10), vec![2, 3]);
+	assert_eq!(backend.code_range(contract(), 3, 10), Vec::<u8>::new());
+	assert_eq!(backend.code_range(contract(), 4, 10), Vec::<u8>::new());
+	assert_eq!(backend.code_range(contract(), 1, usize::MAX), Vec::<u8>::new());
+	assert_eq!(backend.code_range(copier(), 0, 1), Vec::<u8>::new());
+}
@@ -0,0 +1,86 @@
+mod common;
+
+use std::collections::BTreeMap;
+use evm::{CreateScheme, ExitFatal, ExitReason, Keccak, H160, H256, U256};
+use evm::backend::{Backend, Basic, CallInterceptor, CallRequest, CallResponse, MemoryAccount, MemoryBackend, MemoryVicinity};
+use evm::executor::StackExecutor;
+use common::{account, caller, contract, vicinity};
+
+/// Served by the hook, which reports a step limit.
+fn hooked() -> H160 {
+	H160::repeat_byte(0x33)
+}
+
+struct HookBackend<'vicinity> {
+	inner: MemoryBackend<'vicinity>,
+}
+
+impl Keccak for HookBackend<'_> {
+	fn keccak256_h256(&self, data: &[u8]) -> H256 { self.inner.keccak256_h256(data) }
+}
+
+impl Backend for HookBackend<'_> {
+	fn gas_price(&self) -> U256 { self.inner.gas_price() }
+	fn origin(&self) -> H160 { self.inner.origin() }
+	fn block_hash(&self, number: U256) -> H256 { self.inner.block_hash(number) }
+	fn block_number(&self) -> U256 { self.inner.block_number() }
+	fn block_coinbase(&self) -> H160 { self.inner.block_coinbase() }
+	fn block_timestamp(&self) -> U256 { self.inner.block_timestamp() }
+	fn block_difficulty(&self) -> U256 { self.inner.block_difficulty() }
+	fn block_gas_limit(&self) -> U256 { self.inner.block_gas_limit() }
+	fn chain_id(&self) -> U256 { self.inner.chain_id() }
+	fn exists(&self, address: H160) -> bool { self.inner.exists(address) }
+	fn basic(&self, address: H160) -> Basic { self.inner.basic(address) }
+	fn code_hash(&self, address: H160) -> H256 { self.inner.code_hash(address) }
+	fn code_size(&self, address: H160) -> usize { self.inner.code_size(address) }
+	fn code(&self, address: H160) -> Vec<u8> { self.inner.code(address) }
+	fn valids(&self, address: H160) -> Vec<u8> { self.inner.valids(address) }
+	fn storage(&self, address: H160, index: U256) -> U256 { self.inner.storage(address, index) }
+	fn create(&self, scheme: &CreateScheme, address: &H160) { self.inner.create(scheme, address) }
+
+	fn call_interceptor(&self) -> Option<&dyn CallInterceptor> {
+		Some(self)
+	}
+}
+
+impl CallInterceptor for HookBackend<'_> {
+	fn intercept(&self, call: &CallRequest) -> Option<CallResponse> {
+		if call.code_address == hooked() {
+			Some(CallResponse::new(ExitReason::StepLimitReached, Vec::new(), 0))
+		} else {
+			None
+		}
+	}
+}
+
+/// `CALL` to the hooked address, then `SSTORE` of 1 at slot 0.
+fn code() -> Vec<u8> {
+	let mut code = vec![0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x73];
+	code.extend_from_slice(hooked().as_bytes());
+	code.extend_from_slice(&[0x5a, 0xf1, 0x50, 0x60, 0x01, 0x60, 0x00, 0x55]);
+	code
+}
+
+fn backend(vicinity: &MemoryVicinity) -> HookBackend<'_> {
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount::default());
+	state.insert(contract(), account(code()));
+	HookBackend { inner: MemoryBackend::new(vicinity, state) }
+}
+
+#[test]
+fn hook_step_limit_is_fatal() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let unhandled = ExitReason::Fatal(ExitFatal::UnhandledInterrupt);
+
+	let mut executor = StackExecutor::new(&backend, 1_000_000);
+	let (reason, _) = executor.transact_call(caller(), hooked(), U256::zero(), Vec::new(), 1_000_000);
+	assert_eq!(reason, unhandled);
+
+	// Reached from a nested call, the transaction stops there.
+	let mut executor = StackExecutor::new(&backend, 1_000_000);
+	let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 1_000_000);
+	assert_eq!(reason, unhandled);
+	assert_eq!(executor.used_gas(), 1_000_000);
+}
@@ -0,0 +1,158 @@
+mod common;
+
+use std::collections::BTreeMap;
+use evm::{Config, ExitError, ExitSucceed, Frontier, H160, U256};
+use evm::backend::{MemoryAccount, MemoryBackend, MemoryVicinity};
+use evm::executor::{Precompiles, StackExecutor};
+use common::{caller, contract, vicinity};
+
+fn address(n: u64) -> H160 {
+	H160::from(U256::from(n))
+}
+
+type PrecompileResult = Option<Result<(ExitSucceed, Vec<u8>, u64), ExitError>>;
+
+/// Returns the low byte of its address.
+fn echo_address(address: H160, _input: &[u8], _target_gas: Option<u64>) -> PrecompileResult {
+	Some(Ok((ExitSucceed::Returned, vec![address[19]], 0)))
+}
+
+/// Returns `0xff`.
+fn exact(_address: H160, _input: &[u8], _target_gas: Option<u64>) -> PrecompileResult {
+	Some(Ok((ExitSucceed::Returned, vec![0xff], 0)))
+}
+
+fn none(_address: H160, _input: &[u8], _target_gas: Option<u64>) -> PrecompileResult {
+	None
+}
+
+/// Active from Istanbul on, as `BLAKE2F`.
+fn istanbul(config: &Config) -> bool {
+	config.has_chain_id
+}
+
+fn registry() -> Precompiles {
+	Precompiles::new()
+		.with_range(address(1)..=address(8), echo_address)
+		.with_precompile(address(4), exact)
+		.with_precompile_if(address(9), istanbul, echo_address)
+}
+
+#[test]
+fn dispatch() {
+	let precompiles = registry();
+	let (istanbul, frontier) = (Config::istanbul(), Config::frontier());
+
+	assert!(precompiles.is_precompile(&istanbul, address(1)));
+	assert!(precompiles.is_precompile(&istanbul, address(8)));
+	assert!(!precompiles.is_precompile(&istanbul, address(0)));
+	assert!(!precompiles.is_precompile(&istanbul, address(10)));
+	assert!(precompiles.is_precompile(&istanbul, address(9)));
+	assert!(!precompiles.is_precompile(&frontier, address(9)));
+
+	let call = |config: &Config, n| precompiles.call(config, address(n), &[], None).map(|ret| ret.unwrap().1);
+	assert_eq!(call(&istanbul, 3), Some(vec![3]));
+	assert_eq!(call(&istanbul, 4), Some(vec![0xff]));
+	assert_eq!(call(&istanbul, 9), Some(vec![9]));
+	assert_eq!(call(&frontier, 9), None);
+
+	// A range registered later does not shadow an exact address.
+	let precompiles = registry().with_range(address(1)..=address(9), echo_address);
+	assert_eq!(precompiles.call(&istanbul, address(4), &[], None).unwrap().unwrap().1, vec![0xff]);
+
+	assert_eq!(registry().addresses(&istanbul), vec![address(4), address(9)]);
+	assert_eq!(registry().addresses(&frontier), vec![address(4)]);
+}
+
+/// `CALL` to `target` with one wei, returning the first word of its output.
+fn call_code(target: H160) -> Vec<u8> {
+	let mut code = vec![0x60, 0x20, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x01, 0x73];
+	code.extend_from_slice(target.as_bytes());
+	code.extend_from_slice(&[0x61, 0xff, 0xff, 0xf1, 0x50, 0x60, 0x20, 0x60, 0x00, 0xf3]);
+	code
+}
+
+fn backend(vicinity: &MemoryVicinity, target: H160) -> MemoryBackend<'_> {
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount::default());
+	state.insert(contract(), MemoryAccount {
+		nonce: U256::one(),
+		balance: U256::from(10),
+		code: call_code(target),
+		..Default::default()
+	});
+	MemoryBackend::new(vicinity, state)
+}
+
+#[test]
+fn executor_calls_registry() {
+	let vicinity = vicinity();
+	let precompiles = registry();
+
+	let backend = backend(&vicinity, address(9));
+	let mut executor = StackExecutor::new(&backend, 1_000_000).with_precompiles(&precompiles);
+	let (reason, output) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 1_000_000);
+	assert!(reason.is_succeed());
+	assert_eq!(output[0], 9);
+
+	// Not active before Istanbul, so the call reaches an empty account.
+	let mut executor = StackExecutor::new_with_spec(&backend, 1_000_000, none, Frontier)
+		.with_precompiles(&precompiles);
+	let (reason, output) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 1_000_000);
+	assert!(reason.is_succeed());
+	assert_eq!(output, vec![0; 32]);
+}
+
+#[test]
+fn precompiles_exist() {
+	let vicinity = vicinity();
+	let precompiles = registry();
+	let backend = backend(&vicinity, address(2));
+
+	let mut executor = StackExecutor::new(&backend, 1_000_000);
+	let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 1_000_000);
+	assert!(reason.is_succeed());
+	let new_account = executor.used_gas();
+
+	let mut executor = StackExecutor::new(&backend, 1_000_000).with_precompiles(&precompiles);
+	let (reason, output) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 1_000_000);
+	assert!(reason.is_succeed());
+	assert_eq!(output[0], 2);
+	assert_eq!(new_account - executor.used_gas(), 25000);
+}
+
+#[test]
+fn reserved_ranges() {
+	let precompiles = Precompiles::new()
+		.with_precompile(address(0x1ff), echo_address)
+		.with_reserved(address(0x100)..=address(0x1ff))
+		.with_reserved_if(address(0x200)..=address(0x2ff), istanbul);
+	let (istanbul, frontier) = (Config::istanbul(), Config::frontier());
+
+	assert!(precompiles.is_reserved(&istanbul, address(0x150)));
+	assert!(!precompiles.is_precompile(&istanbul, address(0x150)));
+	assert!(precompiles.is_reserved(&istanbul, address(0x250)));
+	assert!(!precompiles.is_reserved(&frontier, address(0x250)));
+	assert!(!precompiles.is_reserved(&istanbul, address(0x300)));
+	assert_eq!(precompiles.call(&istanbul, address(0x150), &[], None), None);
+
+	let vicinity = vicinity();
+	let served = backend(&vicinity, address(0x1ff));
+	let mut executor = StackExecutor::new(&served, 1_000_000).with_precompiles(&precompiles);
+	let (reason, output) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 1_000_000);
+	assert!(reason.is_succeed());
+	assert_eq!(output[0], 0xff);
+
+	// A reserved address exists, even without a precompile, so the transfer
+	// is not charged for a new account.
+	let backend = backend(&vicinity, address(0x150));
+	let mut executor = StackExecutor::new(&backend, 1_000_000);
+	let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 1_000_000);
+	assert!(reason.is_succeed());
+	let new_account = executor.used_gas();
+	let mut executor = StackExecutor::new(&backend, 1_000_000).with_precompiles(&precompiles);
+	let (reason, output) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 1_000_000);
+	assert!(reason.is_succeed());
+	assert_eq!(output, vec![0; 32]);
+	assert_eq!(new_account - executor.used_gas(), 25000);
+}
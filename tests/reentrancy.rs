@@ -0,0 +1,123 @@
+mod common;
+
+use std::collections::BTreeMap;
+use evm::{H160, U256};
+use evm::backend::{MemoryAccount, MemoryBackend, MemoryVicinity};
+use evm::executor::StackExecutor;
+use evm::reentrancy::{Finding, ReentrancyDetector};
+use common::{caller, vicinity};
+
+/// Calls the attacker, then writes its storage.
+fn vault() -> H160 {
+	H160::repeat_byte(0x22)
+}
+
+/// Calls the vault back once.
+fn attacker() -> H160 {
+	H160::repeat_byte(0x33)
+}
+
+/// Writes its storage, then calls `noop()`.
+fn safe() -> H160 {
+	H160::repeat_byte(0x44)
+}
+
+fn noop() -> H160 {
+	H160::repeat_byte(0x55)
+}
+
+/// Delegates to `library()`, then writes its storage.
+fn proxy() -> H160 {
+	H160::repeat_byte(0x66)
+}
+
+/// Writes the storage it runs with.
+fn library() -> H160 {
+	H160::repeat_byte(0x77)
+}
+
+/// `opcode` to `target` with no value, input nor output, discarding the
+/// result.
+fn call(opcode: u8, target: H160) -> Vec<u8> {
+	let mut code = if opcode == 0xf1 {
+		vec![0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x73]
+	} else {
+		vec![0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x73]
+	};
+	code.extend_from_slice(target.as_bytes());
+	code.extend_from_slice(&[0x61, 0xff, 0xff, opcode, 0x50]);
+	code
+}
+
+// PUSH1 1 PUSH1 0 SSTORE
+const WRITE: [u8; 5] = [0x60, 0x01, 0x60, 0x00, 0x55];
+
+fn backend(vicinity: &MemoryVicinity) -> MemoryBackend<'_> {
+	let mut vault_code = call(0xf1, attacker());
+	vault_code.extend_from_slice(&WRITE);
+
+	// PUSH1 0 SLOAD PUSH1 end JUMPI, then write and call the vault back.
+	let mut attacker_code = vec![0x60, 0x00, 0x54, 0x60, 0x2f, 0x57];
+	attacker_code.extend_from_slice(&WRITE);
+	attacker_code.extend(call(0xf1, vault()));
+	// JUMPDEST STOP
+	attacker_code.extend_from_slice(&[0x5b, 0x00]);
+	assert_eq!(attacker_code[0x2f], 0x5b);
+
+	let mut safe_code = WRITE.to_vec();
+	safe_code.extend(call(0xf1, noop()));
+
+	let mut proxy_code = call(0xf4, library());
+	proxy_code.extend_from_slice(&WRITE);
+
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount::default());
+	state.insert(vault(), MemoryAccount { code: vault_code, ..Default::default() });
+	state.insert(attacker(), MemoryAccount { code: attacker_code, ..Default::default() });
+	state.insert(safe(), MemoryAccount { code: safe_code, ..Default::default() });
+	state.insert(noop(), MemoryAccount { code: vec![0x00], ..Default::default() });
+	state.insert(proxy(), MemoryAccount { code: proxy_code, ..Default::default() });
+	state.insert(library(), MemoryAccount { code: WRITE.to_vec(), ..Default::default() });
+	MemoryBackend::new(vicinity, state)
+}
+
+fn run(target: H160) -> ReentrancyDetector {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let detector = ReentrancyDetector::new();
+	let mut executor = StackExecutor::new(&backend, 1_000_000).with_listener(&detector);
+	let (reason, _) = executor.transact_call(caller(), target, U256::zero(), Vec::new(), 1_000_000);
+	assert!(reason.is_succeed());
+	detector
+}
+
+#[test]
+fn reentered_vault() {
+	let report = run(vault()).report();
+	assert_eq!(report.findings, vec![
+		Finding::Reentry { address: vault(), caller: attacker(), depth: 3 },
+		Finding::Reentry { address: attacker(), caller: vault(), depth: 4 },
+		Finding::WriteAfterCall { address: vault(), index: U256::zero(), callee: attacker(), depth: 3 },
+		Finding::WriteAfterCall { address: vault(), index: U256::zero(), callee: attacker(), depth: 1 },
+	]);
+	assert_eq!(report.addresses(), [vault(), attacker()].iter().copied().collect());
+
+	let text = report.to_string();
+	assert!(text.starts_with("## Reentrancy report\n"));
+	assert!(text.contains(&format!("- {:?} reentered from {:?} (depth 3)\n", vault(), attacker())));
+	assert!(text.contains(&format!("- {:?} writes slot 0x0 after calling {:?} (depth 1)\n", vault(), attacker())));
+}
+
+#[test]
+fn checks_effects_interactions() {
+	let report = run(safe()).report();
+	assert!(report.is_clean());
+	assert!(report.to_string().ends_with("No finding.\n"));
+}
+
+#[test]
+fn delegated_writes() {
+	// The library runs with the storage of the proxy, which is not another
+	// contract.
+	assert!(run(proxy()).report().is_clean());
+}
@@ -0,0 +1,243 @@
+mod common;
+
+use std::collections::BTreeMap;
+use evm::{Context, CreateScheme, ExitError, ExitReason, ExitSucceed, Handler, H160, U256};
+use evm::backend::{MemoryAccount, MemoryBackend};
+use evm::executor::StackExecutor;
+use common::{account, caller, contract, vicinity};
+
+/// Returns 32 bytes.
+fn returner() -> H160 {
+	H160::repeat_byte(0x33)
+}
+
+/// Reverts with 4 bytes.
+fn reverter() -> H160 {
+	H160::repeat_byte(0x44)
+}
+
+/// Fails with `INVALID`.
+fn failer() -> H160 {
+	H160::repeat_byte(0x55)
+}
+
+/// Records its initial `RETURNDATASIZE` in slot 0, then calls `reverter`.
+fn nested() -> H160 {
+	H160::repeat_byte(0x66)
+}
+
+/// Precompile returning 3 bytes.
+fn precompile_ok() -> H160 {
+	H160::from(U256::from(8))
+}
+
+/// Precompile failing.
+fn precompile_err() -> H160 {
+	H160::from(U256::from(9))
+}
+
+type PrecompileResult = Option<Result<(ExitSucceed, Vec<u8>, u64), ExitError>>;
+
+fn precompile(address: H160, _input: &[u8], _target_gas: Option<u64>) -> PrecompileResult {
+	if address == precompile_ok() {
+		Some(Ok((ExitSucceed::Returned, vec![1, 2, 3], 0)))
+	} else if address == precompile_err() {
+		Some(Err(ExitError::OutOfGas))
+	} else {
+		None
+	}
+}
+
+/// `CALL` to `address` with no input nor output, discarding the result.
+fn call(address: H160) -> Vec<u8> {
+	let mut code = vec![0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x73];
+	code.extend_from_slice(address.as_bytes());
+	code.extend_from_slice(&[0x5a, 0xf1, 0x50]);
+	code
+}
+
+/// `CREATE` with the given init code (at most 32 bytes), discarding the
+/// result.
+fn create(init_code: &[u8]) -> Vec<u8> {
+	let len = init_code.len() as u8;
+	let mut code = vec![0x7f];
+	let mut word = [0_u8; 32];
+	word[32 - init_code.len()..].copy_from_slice(init_code);
+	code.extend_from_slice(&word);
+	code.extend_from_slice(&[0x60, 0x00, 0x52, 0x60, len, 0x60, 32 - len, 0x60, 0x00, 0xf0, 0x50]);
+	code
+}
+
+/// `RETURNDATASIZE` stored in the given slot.
+fn store_size(slot: u8) -> Vec<u8> {
+	vec![0x3d, 0x60, slot, 0x55]
+}
+
+// PUSH1 0x2a PUSH1 0 MSTORE PUSH1 32 PUSH1 0 RETURN
+const RETURN_32: [u8; 10] = [0x60, 0x2a, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3];
+// PUSH1 0x2a PUSH1 0 MSTORE PUSH1 4 PUSH1 28 REVERT
+const REVERT_4: [u8; 10] = [0x60, 0x2a, 0x60, 0x00, 0x52, 0x60, 0x04, 0x60, 0x1c, 0xfd];
+
+fn state(code: Vec<u8>) -> BTreeMap<H160, MemoryAccount> {
+	let mut nested_code = store_size(0);
+	nested_code.extend(call(reverter()));
+
+	let mut state = BTreeMap::new();
+	state.insert(caller(), account(Vec::new()));
+	state.insert(contract(), account(code));
+	state.insert(returner(), account(RETURN_32.to_vec()));
+	state.insert(reverter(), account(REVERT_4.to_vec()));
+	state.insert(failer(), account(vec![0xfe]));
+	state.insert(nested(), account(nested_code));
+	state
+}
+
+fn execute(code: Vec<u8>, check: impl FnOnce(ExitReason, &StackExecutor<MemoryBackend>)) {
+	let vicinity = vicinity();
+	let backend = MemoryBackend::new(&vicinity, state(code));
+	let mut executor = StackExecutor::new_with_precompile(&backend, 1_000_000, precompile);
+	let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 1_000_000);
+	check(reason, &executor);
+}
+
+fn slot(executor: &StackExecutor<MemoryBackend>, address: H160, index: u64) -> U256 {
+	executor.storage(address, U256::from(index))
+}
+
+fn code(parts: &[Vec<u8>]) -> Vec<u8> {
+	parts.concat()
+}
+
+#[test]
+fn empty_at_frame_start() {
+	execute(code(&[store_size(0)]), |reason, executor| {
+		assert_eq!(reason, ExitReason::Succeed(ExitSucceed::Stopped));
+		assert_eq!(slot(executor, contract(), 0), U256::zero());
+	});
+}
+
+#[test]
+fn call_succeed_and_revert() {
+	// RETURNDATACOPY(0, 0, 32) MLOAD(0) stored in slot 1
+	let copy = vec![0x60, 0x20, 0x60, 0x00, 0x60, 0x00, 0x3e, 0x60, 0x00, 0x51, 0x60, 0x01, 0x55];
+	let code = code(&[call(returner()), store_size(0), copy, call(reverter()), store_size(2)]);
+	execute(code, |reason, executor| {
+		assert_eq!(reason, ExitReason::Succeed(ExitSucceed::Stopped));
+		assert_eq!(slot(executor, contract(), 0), U256::from(32));
+		assert_eq!(slot(executor, contract(), 1), U256::from(0x2a));
+		assert_eq!(slot(executor, contract(), 2), U256::from(4));
+	});
+}
+
+#[test]
+fn call_failure_clears() {
+	let code = code(&[call(returner()), call(failer()), store_size(0)]);
+	execute(code, |reason, executor| {
+		assert_eq!(reason, ExitReason::Succeed(ExitSucceed::Stopped));
+		assert_eq!(slot(executor, contract(), 0), U256::zero());
+	});
+}
+
+#[test]
+fn nested_frames_are_isolated() {
+	let code = code(&[call(returner()), call(nested()), store_size(0)]);
+	execute(code, |reason, executor| {
+		assert_eq!(reason, ExitReason::Succeed(ExitSucceed::Stopped));
+		// The callee starts with an empty buffer, regardless of its caller.
+		assert_eq!(slot(executor, nested(), 0), U256::zero());
+		// The callee stopped without output, its own calls are not visible.
+		assert_eq!(slot(executor, contract(), 0), U256::zero());
+	});
+}
+
+#[test]
+fn precompile_calls() {
+	let code = code(&[
+		call(precompile_ok()), store_size(0),
+		call(returner()), call(precompile_err()), store_size(1),
+	]);
+	execute(code, |reason, executor| {
+		assert_eq!(reason, ExitReason::Succeed(ExitSucceed::Stopped));
+		assert_eq!(slot(executor, contract(), 0), U256::from(3));
+		assert_eq!(slot(executor, contract(), 1), U256::zero());
+	});
+}
+
+#[test]
+fn create_revert_sets() {
+	let code = code(&[create(&REVERT_4), store_size(0)]);
+	execute(code, |reason, executor| {
+		assert_eq!(reason, ExitReason::Succeed(ExitSucceed::Stopped));
+		assert_eq!(slot(executor, contract(), 0), U256::from(4));
+	});
+}
+
+#[test]
+fn create_succeed_and_failure_clear() {
+	let code = code(&[
+		call(returner()), create(&[0x00]), store_size(0),
+		call(returner()), create(&[0xfe]), store_size(1),
+	]);
+	execute(code, |reason, executor| {
+		assert_eq!(reason, ExitReason::Succeed(ExitSucceed::Stopped));
+		assert_eq!(slot(executor, contract(), 0), U256::zero());
+		assert_eq!(slot(executor, contract(), 1), U256::zero());
+	});
+}
+
+#[test]
+fn copy_out_of_bounds() {
+	// RETURNDATACOPY(0, 1, 32)
+	let copy = vec![0x60, 0x20, 0x60, 0x01, 0x60, 0x00, 0x3e];
+	let code = code(&[call(returner()), copy]);
+	execute(code, |reason, _| {
+		assert_eq!(reason, ExitReason::Error(ExitError::OutOfOffset));
+	});
+}
+
+#[test]
+fn handler_exposes_last_buffer() {
+	let vicinity = vicinity();
+	let backend = MemoryBackend::new(&vicinity, state(Vec::new()));
+	let mut executor = StackExecutor::new(&backend, 1_000_000);
+
+	executor.transact_call(caller(), returner(), U256::zero(), Vec::new(), 1_000_000);
+	assert_eq!(Handler::return_data(&executor).len(), 32);
+	executor.transact_call(caller(), reverter(), U256::zero(), Vec::new(), 1_000_000);
+	assert_eq!(Handler::return_data(&executor), &[0, 0, 0, 0x2a][..]);
+	executor.transact_call(caller(), failer(), U256::zero(), Vec::new(), 1_000_000);
+	assert!(Handler::return_data(&executor).is_empty());
+
+	Handler::set_return_data(&mut executor, vec![1, 2, 3]);
+	assert_eq!(Handler::return_data(&executor), &[1, 2, 3][..]);
+}
+
+/// Buffer left by a `CALL` from `contract()` to `address`, through any handler.
+fn call_buffer<H: Handler>(handler: &mut H, address: H160) -> Vec<u8> {
+	let context = Context { address, caller: contract(), apparent_value: U256::zero() };
+	let _ = handler.call(address, None, Vec::new(), Some(100_000), false, context);
+	handler.return_data().to_vec()
+}
+
+/// Buffer left by a `CREATE` from `contract()`, through any handler.
+fn create_buffer<H: Handler>(handler: &mut H, init_code: &[u8]) -> Vec<u8> {
+	let scheme = CreateScheme::Legacy { caller: contract() };
+	let _ = handler.create(contract(), scheme, U256::zero(), init_code.to_vec(), Some(100_000));
+	handler.return_data().to_vec()
+}
+
+#[test]
+fn handler_buffer_follows_last_frame() {
+	let vicinity = vicinity();
+	let backend = MemoryBackend::new(&vicinity, state(Vec::new()));
+	let mut executor = StackExecutor::new_with_precompile(&backend, 1_000_000, precompile);
+
+	assert_eq!(call_buffer(&mut executor, returner()).len(), 32);
+	// The revert of the nested frame's own call is not visible to its caller.
+	assert!(call_buffer(&mut executor, nested()).is_empty());
+	assert_eq!(call_buffer(&mut executor, reverter()), vec![0, 0, 0, 0x2a]);
+	assert_eq!(call_buffer(&mut executor, precompile_ok()), vec![1, 2, 3]);
+	assert!(call_buffer(&mut executor, precompile_err()).is_empty());
+	assert_eq!(create_buffer(&mut executor, &REVERT_4), vec![0, 0, 0, 0x2a]);
+	assert!(create_buffer(&mut executor, &[0xfe]).is_empty());
+}
@@ -0,0 +1,83 @@
+mod common;
+
+use std::collections::BTreeMap;
+use evm::{H160, H256, U256};
+use evm::backend::{MemoryAccount, MemoryBackend, MemoryVicinity};
+use evm::executor::StackExecutor;
+use common::{account, caller, contract, vicinity};
+
+fn child() -> H160 {
+	H160::repeat_byte(0x33)
+}
+
+fn reverting() -> H160 {
+	H160::repeat_byte(0x44)
+}
+
+/// `LOG1` without data and with the topic `n`.
+fn log(n: u8) -> Vec<u8> {
+	vec![0x60, n, 0x60, 0x00, 0x60, 0x00, 0xa1]
+}
+
+const REVERT: [u8; 5] = [0x60, 0x00, 0x60, 0x00, 0xfd];
+
+/// Logs 1, calls the child logging 2 and reverting, then logs 3.
+fn parent_code() -> Vec<u8> {
+	let mut code = log(1);
+	code.extend_from_slice(&[0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x73]);
+	code.extend_from_slice(child().as_bytes());
+	code.extend_from_slice(&[0x5a, 0xf1, 0x50]);
+	code.extend(log(3));
+	code
+}
+
+fn backend(vicinity: &MemoryVicinity) -> MemoryBackend<'_> {
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount::default());
+	state.insert(contract(), account(parent_code()));
+	state.insert(child(), account([log(2), REVERT.to_vec()].concat()));
+	state.insert(reverting(), account([log(4), REVERT.to_vec()].concat()));
+	MemoryBackend::new(vicinity, state)
+}
+
+fn topic(n: u8) -> H256 {
+	H256::from(U256::from(n))
+}
+
+#[test]
+fn reverted_logs_are_retained() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let mut executor = StackExecutor::new(&backend, 1_000_000).with_reverted_logs();
+
+	let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 1_000_000);
+	assert!(reason.is_succeed());
+	let (reason, _) = executor.transact_call(caller(), reverting(), U256::zero(), Vec::new(), 1_000_000);
+	assert!(reason.is_revert());
+
+	let traced: Vec<_> = executor.traced_logs().iter()
+		.map(|traced| (traced.log.address, traced.log.topics[0], traced.reverted))
+		.collect();
+	assert_eq!(traced, vec![
+		(contract(), topic(1), false),
+		(child(), topic(2), true),
+		(contract(), topic(3), false),
+		(reverting(), topic(4), true),
+	]);
+
+	// Logs applied to the state are unchanged.
+	let (_, logs) = executor.deconstruct();
+	let topics: Vec<_> = logs.iter().map(|log| log.topics[0]).collect();
+	assert_eq!(topics, vec![topic(1), topic(3)]);
+}
+
+#[test]
+fn reverted_logs_disabled_by_default() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let mut executor = StackExecutor::new(&backend, 1_000_000);
+
+	let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 1_000_000);
+	assert!(reason.is_succeed());
+	assert!(executor.traced_logs().is_empty());
+}
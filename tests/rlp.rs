@@ -0,0 +1,119 @@
+mod common;
+
+use std::collections::BTreeMap;
+use evm::{ExitReason, ExitSucceed, Keccak, H256, U256};
+use evm::backend::{
+	Account, Basic, Log, MemoryBackend, EMPTY_CODE_HASH, EMPTY_STORAGE_ROOT,
+};
+use evm::executor::StackExecutor;
+use evm::receipt::{Bloom, Receipt, ReceiptOutcome};
+use common::{account, caller, contract, vicinity};
+
+fn log() -> Log {
+	Log {
+		address: contract(),
+		topics: vec![H256::repeat_byte(0xaa)],
+		data: vec![0x01, 0x02],
+	}
+}
+
+#[test]
+fn log_encoding() {
+	let mut expected = vec![0xf8, 58, 0x94];
+	expected.extend_from_slice(&[0x22; 20]);
+	expected.extend_from_slice(&[0xe1, 0xa0]);
+	expected.extend_from_slice(&[0xaa; 32]);
+	expected.extend_from_slice(&[0x82, 0x01, 0x02]);
+
+	assert_eq!(rlp::encode(&log()).to_vec(), expected);
+	assert_eq!(rlp::decode::<Log>(&expected).unwrap(), log());
+	assert!(rlp::decode::<Log>(&[0xc0]).is_err());
+}
+
+#[test]
+fn account_encoding() {
+	let vicinity = vicinity();
+	let backend = MemoryBackend::new(&vicinity, BTreeMap::new());
+	assert_eq!(backend.keccak256_h256(&[]), EMPTY_CODE_HASH);
+	assert_eq!(backend.keccak256_h256(&[0x80]), EMPTY_STORAGE_ROOT);
+
+	let basic = Basic { balance: U256::from(1000), nonce: U256::one() };
+	let account = Account::new(&backend, &basic, &[0x00], EMPTY_STORAGE_ROOT);
+	assert_eq!(account.basic(), basic);
+	assert_eq!(account.code_hash, backend.keccak256_h256(&[0x00]));
+	assert!(!account.is_empty());
+	assert!(Account::new(&backend, &Basic::default(), &[], EMPTY_STORAGE_ROOT).is_empty());
+	assert_eq!(Account::default(), Account::new(&backend, &Basic::default(), &[], EMPTY_STORAGE_ROOT));
+
+	let encoded = rlp::encode(&account).to_vec();
+	assert_eq!(&encoded[..5], &[0xf8, 0x46, 0x01, 0x82, 0x03]);
+	assert_eq!(rlp::decode::<Account>(&encoded).unwrap(), account);
+}
+
+#[test]
+fn bloom() {
+	let vicinity = vicinity();
+	let backend = MemoryBackend::new(&vicinity, BTreeMap::new());
+
+	let bloom = Bloom::from_logs(&backend, &[log()]);
+	let bits = bloom.0.iter().map(|byte| byte.count_ones()).sum::<u32>();
+	assert!(bits > 0 && bits <= 6);
+	assert!(bloom.contains_input(&backend, contract().as_bytes()));
+	assert!(bloom.contains_input(&backend, H256::repeat_byte(0xaa).as_bytes()));
+	assert!(!Bloom::default().contains_input(&backend, contract().as_bytes()));
+
+	let mut block = Bloom::default();
+	block.accrue_bloom(&bloom);
+	assert_eq!(block, bloom);
+
+	let encoded = rlp::encode(&bloom).to_vec();
+	assert_eq!(&encoded[..3], &[0xb9, 0x01, 0x00]);
+	assert_eq!(rlp::decode::<Bloom>(&encoded).unwrap(), bloom);
+	assert!(rlp::decode::<Bloom>(&rlp::encode(&vec![0_u8; 255])).is_err());
+}
+
+#[test]
+fn receipt_round_trip() {
+	let vicinity = vicinity();
+	let backend = MemoryBackend::new(&vicinity, BTreeMap::new());
+
+	for outcome in [
+		ReceiptOutcome::Status(true),
+		ReceiptOutcome::Status(false),
+		ReceiptOutcome::StateRoot(H256::repeat_byte(0x33)),
+	] {
+		let receipt = Receipt::new(&backend, outcome, 21_000, vec![log()]);
+		let encoded = rlp::encode(&receipt).to_vec();
+		assert_eq!(rlp::decode::<Receipt>(&encoded).unwrap(), receipt);
+
+		assert_eq!(receipt.encode(0), encoded);
+		assert_eq!(Receipt::decode(&encoded).unwrap(), (0, receipt.clone()));
+
+		let typed = receipt.encode(2);
+		assert_eq!(typed[0], 2);
+		assert_eq!(&typed[1..], &encoded[..]);
+		assert_eq!(Receipt::decode(&typed).unwrap(), (2, receipt));
+	}
+}
+
+#[test]
+fn receipt_of_transaction() {
+	// PUSH1 0xaa PUSH1 0 PUSH1 0 LOG1
+	let mut state = BTreeMap::new();
+	state.insert(contract(), account(vec![0x60, 0xaa, 0x60, 0x00, 0x60, 0x00, 0xa1]));
+	let vicinity = vicinity();
+	let backend = MemoryBackend::new(&vicinity, state);
+
+	let mut executor = StackExecutor::new(&backend, 100_000);
+	let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 100_000);
+	assert_eq!(reason, ExitReason::Succeed(ExitSucceed::Stopped));
+	let used_gas = executor.used_gas();
+	let (_, logs) = executor.deconstruct();
+
+	let receipt = Receipt::new(&backend, ReceiptOutcome::status(&reason), used_gas, logs);
+	assert_eq!(receipt.outcome, ReceiptOutcome::Status(true));
+	assert_eq!(receipt.logs.len(), 1);
+	assert!(receipt.bloom.contains_input(&backend, contract().as_bytes()));
+	assert!(receipt.bloom.contains_input(&backend, H256::from(U256::from(0xaa)).as_bytes()));
+	assert_eq!(rlp::decode::<Receipt>(&rlp::encode(&receipt)).unwrap(), receipt);
+}
@@ -0,0 +1,309 @@
+#![cfg(feature = "with-serde")]
+
+use std::sync::Arc;
+use evm::{Machine, Memory, Stack, Valids, H160, H256, MAX_STACK_LIMIT, SERDE_FORMAT_VERSION, U256};
+use evm::metrics::{ContractMetrics, MetricsReport, OpcodeMetrics};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Encoding in a binary format.
+fn to_binary<T: Serialize>(value: &T) -> Vec<u8> {
+	bincode::serialize(value).unwrap()
+}
+
+fn from_binary<T: DeserializeOwned>(bytes: &[u8]) -> bincode::Result<T> {
+	bincode::deserialize(bytes)
+}
+
+/// Encoding in a human-readable format.
+fn to_json<T: Serialize>(value: &T) -> serde_json::Value {
+	serde_json::to_value(value).unwrap()
+}
+
+fn from_json<T: DeserializeOwned>(value: serde_json::Value) -> serde_json::Result<T> {
+	serde_json::from_value(value)
+}
+
+/// Binary encoding of a byte string, its length then its bytes.
+fn binary_bytes(bytes: &[u8]) -> Vec<u8> {
+	let mut encoded = (bytes.len() as u64).to_le_bytes().to_vec();
+	encoded.extend_from_slice(bytes);
+	encoded
+}
+
+/// Binary encoding of a stack.
+fn binary_stack(version: u8, data: &[u8], limit: u64) -> Vec<u8> {
+	let mut encoded = vec![version];
+	encoded.extend(binary_bytes(data));
+	encoded.extend_from_slice(&limit.to_le_bytes());
+	encoded
+}
+
+fn values() -> Vec<U256> {
+	vec![
+		U256::zero(),
+		U256::one(),
+		U256::from(0x0102_0304_0506_0708_u64),
+		U256::one() << 200,
+		U256::from_dec_str("1234567890123456789012345678901234567890").unwrap(),
+		U256::MAX,
+	]
+}
+
+fn big_endian(value: U256) -> Vec<u8> {
+	let mut bytes = vec![0; 32];
+	value.to_big_endian(&mut bytes);
+	bytes
+}
+
+#[test]
+fn u256_is_big_endian() {
+	let mut expected = vec![0; 32];
+	expected[30] = 0x01;
+	expected[31] = 0x02;
+	assert_eq!(to_binary(&U256::from(0x0102)), binary_bytes(&expected));
+
+	for value in values() {
+		assert_eq!(to_binary(&value), binary_bytes(&big_endian(value)));
+		assert_eq!(to_binary(&value), to_binary(&H256::from(value)));
+		assert_eq!(from_binary::<U256>(&to_binary(&value)).unwrap(), value);
+	}
+}
+
+#[test]
+fn fast_big_endian_matches_uint() {
+	for value in values() {
+		let mut bytes = [0; 32];
+		value.into_big_endian_fast(&mut bytes);
+		assert_eq!(bytes.to_vec(), big_endian(value));
+		assert_eq!(U256::from_big_endian_fast(&bytes), value);
+		assert_eq!(U256::from_big_endian_fast(&bytes[8..]), U256::from_big_endian(&bytes[8..]));
+	}
+	assert_eq!(U256::from_big_endian_fast(&[]), U256::zero());
+}
+
+#[test]
+fn wrong_length_is_rejected() {
+	assert!(from_binary::<U256>(&binary_bytes(&[0; 31])).is_err());
+	assert!(from_binary::<U256>(&binary_bytes(&[0; 33])).is_err());
+	assert!(from_binary::<H256>(&binary_bytes(&[0; 20])).is_err());
+	assert!(from_binary::<H160>(&binary_bytes(&[0; 32])).is_err());
+	assert_eq!(from_binary::<H160>(&binary_bytes(&[0x11; 20])).unwrap(), H160::repeat_byte(0x11));
+}
+
+#[test]
+fn stack_round_trip() {
+	let mut stack = Stack::new(16);
+	for value in values() {
+		stack.push_u256(value).unwrap();
+	}
+
+	let data: Vec<u8> = values().into_iter().flat_map(big_endian).collect();
+	let encoded = to_binary(&stack);
+	assert_eq!(encoded, binary_stack(SERDE_FORMAT_VERSION, &data, 16));
+
+	let decoded = from_binary::<Stack>(&encoded).unwrap();
+	assert_eq!(decoded.data(), stack.data());
+	assert_eq!(decoded.limit(), 16);
+
+	let value = to_json(&stack);
+	assert_eq!(value["version"], SERDE_FORMAT_VERSION);
+	assert_eq!(value["limit"], 16);
+	let decoded = serde_json::from_str::<Stack>(&value.to_string()).unwrap();
+	assert_eq!(decoded.data(), stack.data());
+	assert_eq!(decoded.limit(), 16);
+}
+
+#[test]
+fn stack_rejects_other_versions() {
+	assert!(from_binary::<Stack>(&binary_stack(0, &[0; 32], 16)).is_err());
+	assert!(from_binary::<Stack>(&binary_stack(SERDE_FORMAT_VERSION, &[0; 33], 16)).is_err());
+}
+
+#[test]
+fn stack_rejects_oversized_limits() {
+	let limit = MAX_STACK_LIMIT as u64;
+	assert_eq!(from_binary::<Stack>(&binary_stack(SERDE_FORMAT_VERSION, &[], limit)).unwrap().limit(), MAX_STACK_LIMIT);
+	assert!(from_binary::<Stack>(&binary_stack(SERDE_FORMAT_VERSION, &[], limit + 1)).is_err());
+	assert!(from_binary::<Stack>(&binary_stack(SERDE_FORMAT_VERSION, &[], u64::MAX)).is_err());
+	assert!(from_binary::<Stack>(&binary_stack(SERDE_FORMAT_VERSION, &[0; 64], 1)).is_err());
+}
+
+/// Binary encoding of a memory.
+fn binary_memory(data: &[u8], high_water: u64) -> Vec<u8> {
+	let mut encoded = binary_bytes(data);
+	for value in [high_water, high_water, 1 << 20] {
+		encoded.extend_from_slice(&value.to_le_bytes());
+	}
+	encoded
+}
+
+#[test]
+fn memory_length_within_buffer() {
+	let memory = from_binary::<Memory>(&binary_memory(&[0xff; 64], 40)).unwrap();
+	assert_eq!(memory.data(), &[0xff; 40][..]);
+	assert_eq!(to_binary(&memory), binary_memory(&[0xff; 64], 40));
+
+	assert!(from_binary::<Memory>(&binary_memory(&[0xff; 64], 65)).is_err());
+	assert!(from_binary::<Memory>(&binary_memory(&[], u64::MAX)).is_err());
+}
+
+#[test]
+fn machine_round_trip() {
+	// PUSH1 1 PUSH1 0 MSTORE STOP
+	let code = vec![0x60, 0x01, 0x60, 0x00, 0x52, 0x00];
+	let mut machine = Machine::new(code, Arc::new(Valids::new(vec![1, 0, 1, 0, 1, 1])), vec![0xaa, 0xbb], 1024, 1 << 20);
+	machine.step().unwrap();
+	machine.step().unwrap();
+	machine.memory_mut().set(0, &[0xff; 40], None).unwrap();
+
+	let encoded = to_binary(&machine);
+	assert_eq!(encoded[0], SERDE_FORMAT_VERSION);
+
+	let decoded = from_binary::<Machine>(&encoded).unwrap();
+	assert_eq!(decoded.position(), machine.position());
+	assert_eq!(decoded.stack().data(), machine.stack().data());
+	assert_eq!(decoded.memory().data(), machine.memory().data());
+	assert_eq!(to_binary(&decoded), encoded);
+
+	let mut other = encoded;
+	other[0] = SERDE_FORMAT_VERSION + 1;
+	assert!(from_binary::<Machine>(&other).is_err());
+
+	let json = serde_json::to_string(&machine).unwrap();
+	let decoded = serde_json::from_str::<Machine>(&json).unwrap();
+	assert_eq!(decoded.position(), machine.position());
+	assert_eq!(decoded.stack().data(), machine.stack().data());
+	assert_eq!(decoded.memory().data(), machine.memory().data());
+	assert_eq!(serde_json::to_string(&decoded).unwrap(), json);
+}
+
+#[test]
+fn metrics_report_keys() {
+	let mut report = MetricsReport::default();
+	report.opcodes.insert(0x01, OpcodeMetrics { count: 2, gas: 6 });
+	report.contracts.insert(H160::repeat_byte(0xab), ContractMetrics { steps: 2, gas: 6, calls: 1 });
+	report.steps = 2;
+	report.gas = 6;
+
+	let json = to_json(&report);
+	assert_eq!(json["opcodes"], serde_json::json!({ "0x01": { "count": 2, "gas": 6 } }));
+	assert_eq!(json["contracts"][format!("0x{}", "ab".repeat(20))]["calls"], serde_json::json!(1));
+	assert_eq!(from_json::<MetricsReport>(json.clone()).unwrap(), report);
+	assert_eq!(from_binary::<MetricsReport>(&to_binary(&report)).unwrap(), report);
+
+	let mut invalid = json;
+	invalid["opcodes"] = serde_json::json!({ "0x0102": { "count": 2, "gas": 6 } });
+	assert!(from_json::<MetricsReport>(invalid).is_err());
+}
+
+#[cfg(not(feature = "serde-hex"))]
+#[test]
+fn readable_formats_get_bytes_without_hex() {
+	let value = U256::from(0x0102);
+	assert_eq!(to_json(&value), serde_json::json!(big_endian(value)));
+	assert_eq!(to_json(&H160::repeat_byte(0x11)), serde_json::json!(vec![0x11; 20]));
+
+	for value in values() {
+		assert_eq!(from_json::<U256>(to_json(&value)).unwrap(), value);
+		assert_eq!(from_json::<H256>(to_json(&H256::from(value))).unwrap(), H256::from(value));
+	}
+	assert_eq!(from_json::<H160>(to_json(&H160::repeat_byte(0x11))).unwrap(), H160::repeat_byte(0x11));
+	assert!(from_json::<U256>(serde_json::json!(vec![0; 31])).is_err());
+}
+
+#[cfg(feature = "serde-hex")]
+mod hex {
+	use std::collections::BTreeMap;
+	use evm::backend::{Apply, Basic, Log, MemoryAccount};
+	use evm::{H160, H256, U256};
+	use serde_json::json;
+	use super::{from_binary, from_json, to_binary, to_json};
+
+	#[test]
+	fn u256_is_a_quantity() {
+		assert_eq!(to_json(&U256::zero()), json!("0x0"));
+		assert_eq!(to_json(&U256::from(0x1f)), json!("0x1f"));
+		assert_eq!(to_json(&U256::MAX), json!(format!("0x{}", "f".repeat(64))));
+
+		for value in super::values() {
+			assert_eq!(from_json::<U256>(to_json(&value)).unwrap(), value);
+		}
+		assert_eq!(from_json::<U256>(json!("0x001F")).unwrap(), U256::from(0x1f));
+		assert!(from_json::<U256>(json!("0x")).is_err());
+		assert!(from_json::<U256>(json!("0xzz")).is_err());
+		assert!(from_json::<U256>(json!(format!("0x1{}", "0".repeat(64)))).is_err());
+
+		// Binary formats keep the compact encoding.
+		assert_eq!(to_binary(&U256::from(0x1f)), super::binary_bytes(&super::big_endian(U256::from(0x1f))));
+	}
+
+	#[test]
+	fn hashes_are_hex() {
+		let address = H160::repeat_byte(0xab);
+		assert_eq!(to_json(&address), json!(format!("0x{}", "ab".repeat(20))));
+		assert_eq!(from_json::<H160>(to_json(&address)).unwrap(), address);
+		assert_eq!(from_json::<H256>(json!("CD".repeat(32))).unwrap(), H256::repeat_byte(0xcd));
+		assert!(from_json::<H160>(json!("0xabab")).is_err());
+		assert!(from_json::<H256>(json!("0xgg")).is_err());
+		assert_eq!(to_binary(&address), super::binary_bytes(&[0xab; 20]));
+	}
+
+	#[test]
+	fn log() {
+		let log = Log {
+			address: H160::repeat_byte(0x11),
+			topics: vec![H256::repeat_byte(0x22)],
+			data: vec![0x01, 0x02],
+		};
+
+		let value = to_json(&log);
+		assert_eq!(value["address"], json!(format!("0x{}", "11".repeat(20))));
+		assert_eq!(value["topics"], json!([format!("0x{}", "22".repeat(32))]));
+		assert_eq!(value["data"], json!("0x0102"));
+		assert_eq!(from_json::<Log>(value).unwrap(), log);
+
+		assert_eq!(from_binary::<Log>(&to_binary(&log)).unwrap(), log);
+	}
+
+	#[test]
+	fn memory_account() {
+		let mut storage = BTreeMap::new();
+		storage.insert(U256::one(), U256::from(0xff));
+		let account = MemoryAccount {
+			nonce: U256::one(),
+			balance: U256::from(1000),
+			storage,
+			code: vec![0x60, 0x00],
+		};
+
+		let value = to_json(&account);
+		assert_eq!(value["balance"], json!("0x3e8"));
+		assert_eq!(value["storage"], json!({ "0x1": "0xff" }));
+		assert_eq!(value["code"], json!("0x6000"));
+		assert_eq!(from_json::<MemoryAccount>(value).unwrap(), account);
+		assert_eq!(from_binary::<MemoryAccount>(&to_binary(&account)).unwrap(), account);
+	}
+
+	#[test]
+	fn apply() {
+		let modify = Apply::Modify {
+			address: H160::repeat_byte(0x11),
+			basic: Basic { balance: U256::from(10), nonce: U256::one() },
+			code_and_valids: Some((vec![0x60, 0x00], vec![0x01, 0x00])),
+			storage: vec![(U256::one(), U256::from(2))],
+			reset_storage: true,
+		};
+
+		let value = to_json(&modify);
+		assert_eq!(value["Modify"]["code_and_valids"], json!(["0x6000", "0x0100"]));
+		assert_eq!(value["Modify"]["storage"], json!([["0x1", "0x2"]]));
+
+		for apply in [modify, Apply::Delete { address: H160::repeat_byte(0x22) }] {
+			let decoded = from_json::<Apply<Vec<(U256, U256)>>>(to_json(&apply)).unwrap();
+			assert_eq!(format!("{:?}", decoded), format!("{:?}", apply));
+			let decoded = from_binary::<Apply<Vec<(U256, U256)>>>(&to_binary(&apply)).unwrap();
+			assert_eq!(format!("{:?}", decoded), format!("{:?}", apply));
+		}
+	}
+}
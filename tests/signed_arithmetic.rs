@@ -0,0 +1,198 @@
+use std::sync::Arc;
+use evm::{Capture, ExitReason, ExitSucceed, Machine, Opcode, Sign, Valids, I256, U256};
+
+/// Deterministic xorshift generator, so that failures reproduce.
+struct Rng(u64);
+
+impl Rng {
+	fn next(&mut self) -> u64 {
+		self.0 ^= self.0 << 13;
+		self.0 ^= self.0 >> 7;
+		self.0 ^= self.0 << 17;
+		self.0
+	}
+
+	/// Random word, biased towards edge cases: small values, values close to
+	/// the sign bit, and all ones.
+	fn word(&mut self) -> U256 {
+		let words = [self.next(), self.next(), self.next(), self.next()];
+		match self.next() % 6 {
+			0 => U256::from(words[0] % 300),
+			1 => U256::MAX - U256::from(words[0] % 300),
+			2 => (U256::one() << 255) ^ U256::from(words[0] % 300),
+			3 => U256(words) >> (words[0] % 256) as usize,
+			_ => U256(words),
+		}
+	}
+}
+
+fn bits(value: U256) -> Vec<bool> {
+	(0..256).map(|i| value.bit(i)).collect()
+}
+
+fn from_bits(bits: &[bool]) -> U256 {
+	let mut bytes = [0_u8; 32];
+	for (i, bit) in bits.iter().enumerate() {
+		if *bit {
+			bytes[31 - i / 8] |= 1 << (i % 8);
+		}
+	}
+	U256::from_big_endian(&bytes)
+}
+
+fn shift_amount(shift: U256) -> usize {
+	if shift >= U256::from(256) { 256 } else { shift.as_usize() }
+}
+
+fn reference_shl(shift: U256, value: U256) -> U256 {
+	let (s, v) = (shift_amount(shift), bits(value));
+	from_bits(&(0..256).map(|i| i >= s && v[i - s]).collect::<Vec<_>>())
+}
+
+fn reference_shr(shift: U256, value: U256) -> U256 {
+	let (s, v) = (shift_amount(shift), bits(value));
+	from_bits(&(0..256).map(|i| i + s < 256 && v[i + s]).collect::<Vec<_>>())
+}
+
+fn reference_sar(shift: U256, value: U256) -> U256 {
+	let (s, v) = (shift_amount(shift), bits(value));
+	from_bits(&(0..256).map(|i| if i + s < 256 { v[i + s] } else { v[255] }).collect::<Vec<_>>())
+}
+
+fn reference_signextend(size: U256, value: U256) -> U256 {
+	if size >= U256::from(31) {
+		return value
+	}
+	let (t, v) = (8 * size.as_usize() + 7, bits(value));
+	from_bits(&(0..256).map(|i| if i <= t { v[i] } else { v[t] }).collect::<Vec<_>>())
+}
+
+/// Result of running `opcode` on `a` and `b`, `a` being the top of the stack.
+fn eval(opcode: Opcode, a: U256, b: U256) -> U256 {
+	let mut code = vec![0x7f];
+	code.extend_from_slice(&word_bytes(b));
+	code.push(0x7f);
+	code.extend_from_slice(&word_bytes(a));
+	code.extend_from_slice(&[opcode.as_u8(), 0x00]);
+
+	let mut machine = Machine::new(code.clone(), Arc::new(Valids::compute(&code).into()), Vec::new(), 1024, usize::MAX);
+	let (_, capture) = machine.run(u64::MAX, |_, _, _, _| Ok(()));
+	assert_eq!(capture, Capture::Exit(ExitReason::Succeed(ExitSucceed::Stopped)));
+	machine.stack_mut().peek(0).unwrap()
+}
+
+fn word_bytes(value: U256) -> [u8; 32] {
+	let mut bytes = [0_u8; 32];
+	value.to_big_endian(&mut bytes);
+	bytes
+}
+
+#[test]
+fn shifts_match_reference() {
+	let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+	for _ in 0..400 {
+		let value = rng.word();
+		let shift = if rng.next().is_multiple_of(4) { rng.word() } else { U256::from(rng.next() % 260) };
+		assert_eq!(eval(Opcode::SHL, shift, value), reference_shl(shift, value), "SHL {} {}", shift, value);
+		assert_eq!(eval(Opcode::SHR, shift, value), reference_shr(shift, value), "SHR {} {}", shift, value);
+		assert_eq!(eval(Opcode::SAR, shift, value), reference_sar(shift, value), "SAR {} {}", shift, value);
+	}
+}
+
+#[test]
+fn signextend_matches_reference() {
+	let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+	for size in (0..34).map(U256::from).chain([U256::from(256), U256::MAX]) {
+		for _ in 0..20 {
+			let value = rng.word();
+			assert_eq!(
+				eval(Opcode::SIGNEXTEND, size, value),
+				reference_signextend(size, value),
+				"SIGNEXTEND {} {}", size, value,
+			);
+		}
+	}
+}
+
+/// `I256` of an `i128`.
+fn signed(value: i128) -> I256 {
+	let magnitude = U256::from(value.unsigned_abs());
+	match value {
+		0 => I256::zero(),
+		v if v < 0 => I256(Sign::Minus, magnitude),
+		_ => I256(Sign::Plus, magnitude),
+	}
+}
+
+#[test]
+fn checked_ops_match_i128() {
+	let mut rng = Rng(0xdead_beef_cafe_f00d);
+	let edges = [0, 1, -1, i128::from(i64::MAX), i128::from(i64::MIN)];
+	for round in 0..2000 {
+		let pick = |rng: &mut Rng, n: usize| {
+			if round % 4 == 0 { edges[n % edges.len()] } else { i128::from(rng.next() as i64) }
+		};
+		let (a, b) = (pick(&mut rng, round), pick(&mut rng, round / 5));
+		let (x, y) = (signed(a), signed(b));
+
+		assert_eq!(x.checked_add(y), Some(signed(a + b)));
+		assert_eq!(x.checked_sub(y), Some(signed(a - b)));
+		assert_eq!(x.checked_mul(y), Some(signed(a * b)));
+		assert_eq!(x.checked_neg(), Some(signed(-a)));
+		assert_eq!(x.checked_div(y), a.checked_div(b).map(signed));
+		assert_eq!(x.checked_rem(y), a.checked_rem(b).map(signed));
+		assert_eq!(x < y, a < b);
+		assert_eq!(I256::from(Into::<U256>::into(x)), x);
+
+		if b != 0 {
+			assert_eq!(I256::from(eval(Opcode::SDIV, x.into(), y.into())), signed(a / b));
+			assert_eq!(I256::from(eval(Opcode::SMOD, x.into(), y.into())), signed(a % b));
+		}
+	}
+}
+
+#[test]
+fn checked_ops_overflow() {
+	let (min, max, one, minus_one) = (I256::min_value(), I256::max_value(), signed(1), signed(-1));
+	assert_eq!(Into::<U256>::into(max), U256::MAX >> 1);
+	assert_eq!(Into::<U256>::into(min), U256::one() << 255);
+
+	assert_eq!(max.checked_add(one), None);
+	assert_eq!(min.checked_sub(one), None);
+	assert_eq!(min.checked_add(minus_one), None);
+	assert_eq!(max.checked_sub(minus_one), None);
+	assert_eq!(min.checked_sub(min), Some(I256::zero()));
+	assert_eq!(minus_one.checked_sub(min), Some(max));
+	assert_eq!(max.checked_add(min), Some(minus_one));
+	assert_eq!(min.checked_neg(), None);
+	assert_eq!(max.checked_neg(), min.checked_add(one));
+
+	assert_eq!(min.checked_mul(minus_one), None);
+	assert_eq!(min.checked_mul(one), Some(min));
+	assert_eq!(max.checked_mul(signed(2)), None);
+	let half = I256(Sign::Plus, U256::one() << 128);
+	let quarter = I256(Sign::Plus, U256::one() << 127);
+	assert_eq!(half.checked_mul(quarter), None);
+	assert_eq!(half.checked_mul(quarter.checked_neg().unwrap()), Some(min));
+
+	assert_eq!(min.checked_div(minus_one), None);
+	assert_eq!(min.checked_div(I256::zero()), None);
+	assert_eq!(min.checked_div(one), Some(min));
+	assert_eq!(min.checked_rem(minus_one), Some(I256::zero()));
+	assert_eq!(min.checked_rem(I256::zero()), None);
+}
+
+#[test]
+fn sdiv_and_smod_edges() {
+	let (min, one, minus_one) = (I256::min_value(), signed(1), signed(-1));
+	let sdiv = |a: I256, b: I256| I256::from(eval(Opcode::SDIV, a.into(), b.into()));
+	let smod = |a: I256, b: I256| I256::from(eval(Opcode::SMOD, a.into(), b.into()));
+
+	assert_eq!(sdiv(min, minus_one), min);
+	assert_eq!(sdiv(min, one), min);
+	assert_eq!(sdiv(min, signed(2)), I256(Sign::Minus, U256::one() << 254));
+	assert_eq!(sdiv(min, I256::zero()), I256::zero());
+	assert_eq!(smod(min, minus_one), I256::zero());
+	assert_eq!(smod(signed(-7), I256::zero()), I256::zero());
+	assert_eq!(smod(signed(-7), signed(3)), signed(-1));
+}
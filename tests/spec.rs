@@ -0,0 +1,100 @@
+mod common;
+
+use std::collections::BTreeMap;
+use evm::{Config, ExitError, ExitReason, ExitSucceed, Frontier, Istanbul, Opcode, Spec, H160, U256};
+use evm::backend::MemoryBackend;
+use evm::executor::StackExecutor;
+use evm::gasometer::{self, Gasometer};
+use common::{account, caller, contract, vicinity};
+
+type PrecompileResult = Option<Result<(ExitSucceed, Vec<u8>, u64), ExitError>>;
+
+const fn no_precompile(_address: H160, _input: &[u8], _target_gas: Option<u64>) -> PrecompileResult {
+	None
+}
+
+/// Istanbul with `MCOPY`.
+#[derive(Clone, Copy, Debug, Default)]
+struct WithMcopy;
+
+impl Spec for WithMcopy {
+	const CONFIG: Config = Config {
+		has_mcopy: true,
+		..Config::istanbul()
+	};
+}
+
+fn run<S: Spec>(spec: S, code: Vec<u8>) -> (ExitReason, u64) {
+	let (reason, _, used_gas) = run_with_output(spec, code);
+	(reason, used_gas)
+}
+
+fn run_with_output<S: Spec>(spec: S, code: Vec<u8>) -> (ExitReason, Vec<u8>, u64) {
+	let vicinity = vicinity();
+	let mut state = BTreeMap::new();
+	state.insert(contract(), account(code));
+	let backend = MemoryBackend::new(&vicinity, state);
+
+	let mut executor = StackExecutor::new_with_spec(&backend, 100_000, no_precompile, spec);
+	let (reason, output) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 100_000);
+	(reason, output, executor.used_gas())
+}
+
+#[test]
+fn opcode_availability() {
+	// PUSH1 1 PUSH1 1 SHL
+	let code = vec![0x60, 0x01, 0x60, 0x01, 0x1b];
+	assert!(run(Istanbul, code.clone()).0.is_succeed());
+	assert_eq!(run(Frontier, code).0, ExitReason::Error(ExitError::InvalidOpcode(Opcode::SHL)));
+}
+
+#[test]
+fn gas_schedule() {
+	// PUSH1 0 SLOAD
+	let code = vec![0x60, 0x00, 0x54];
+	let base = 21_000 + 3;
+	assert_eq!(run(Istanbul, code.clone()), (ExitSucceed::Stopped.into(), base + Istanbul::CONFIG.gas_sload));
+	assert_eq!(run(Frontier, code), (ExitSucceed::Stopped.into(), base + Frontier::CONFIG.gas_sload));
+
+	let mut frontier = Gasometer::<Frontier>::new(100_000);
+	let mut istanbul = Gasometer::<Istanbul>::new(100_000);
+	frontier.record_transaction(gasometer::create_transaction_cost(&[])).unwrap();
+	istanbul.record_transaction(gasometer::create_transaction_cost(&[])).unwrap();
+	assert_eq!(frontier.total_used_gas(), Frontier::CONFIG.gas_transaction_create);
+	assert_eq!(istanbul.total_used_gas(), Istanbul::CONFIG.gas_transaction_create);
+}
+
+#[test]
+fn mcopy() {
+	// PUSH1 0x2a PUSH1 0 MSTORE, MCOPY(32, 0, 32), RETURN(32, 32)
+	let code = vec![
+		0x60, 0x2a, 0x60, 0x00, 0x52,
+		0x60, 0x20, 0x60, 0x00, 0x60, 0x20, 0x5e,
+		0x60, 0x20, 0x60, 0x20, 0xf3,
+	];
+	let (reason, output, used_gas) = run_with_output(WithMcopy, code.clone());
+	assert_eq!(reason, ExitSucceed::Returned.into());
+	assert_eq!(U256::from_big_endian(&output), U256::from(0x2a));
+	// Pushes, MSTORE with one word of memory, MCOPY of one word with one
+	// more word of memory.
+	assert_eq!(used_gas, 21_000 + 7 * 3 + 3 + 3 + 6 + 3);
+
+	assert_eq!(run(Istanbul, code).0, ExitReason::Error(ExitError::InvalidOpcode(Opcode::MCOPY)));
+}
+
+#[test]
+fn mcopy_overlapping() {
+	// MSTORE(0, 0x0102..20), MCOPY(1, 0, 31), RETURN(0, 32)
+	let mut code = vec![0x7f];
+	code.extend(1..=32_u8);
+	code.extend_from_slice(&[
+		0x60, 0x00, 0x52,
+		0x60, 0x1f, 0x60, 0x00, 0x60, 0x01, 0x5e,
+		0x60, 0x20, 0x60, 0x00, 0xf3,
+	]);
+	let (reason, output, _) = run_with_output(WithMcopy, code);
+	assert_eq!(reason, ExitSucceed::Returned.into());
+	let mut expected = vec![1];
+	expected.extend(1..=31_u8);
+	assert_eq!(output, expected);
+}
@@ -0,0 +1,145 @@
+use std::rc::Rc;
+use std::sync::Arc;
+use evm::{Capture, ExitError, ExitFatal, ExitSucceed, Machine, Opcode, Stack, Valids, Word, H256, U256};
+
+#[test]
+fn push_pop_within_limit() {
+	let mut stack = Stack::new(2);
+	assert_eq!(stack.pop_u256(), Err(ExitError::StackUnderflow));
+
+	stack.push_u256(U256::from(1)).unwrap();
+	stack.push(H256::repeat_byte(2)).unwrap();
+	assert_eq!(stack.push_u256(U256::from(3)), Err(ExitError::StackOverflow));
+	assert_eq!(stack.len(), 2);
+	assert_eq!(stack.data()[0], U256::from(1));
+
+	assert_eq!(stack.pop(), Ok(H256::repeat_byte(2)));
+	assert_eq!(stack.pop_u256(), Ok(U256::from(1)));
+	assert_eq!(stack.len(), 0);
+}
+
+#[test]
+fn check_height() {
+	let mut stack = Stack::new(3);
+	stack.push_u256(U256::from(1)).unwrap();
+	stack.push_u256(U256::from(2)).unwrap();
+
+	assert_eq!(stack.check(2, 1), Ok(()));
+	assert_eq!(stack.check(0, 1), Ok(()));
+	assert_eq!(stack.check(3, 0), Err(ExitError::StackUnderflow));
+	assert_eq!(stack.check(0, 2), Err(ExitError::StackOverflow));
+	assert_eq!(stack.check(1, 2), Ok(()));
+}
+
+#[test]
+fn dup_swap_peek_set() {
+	let mut stack = Stack::new(4);
+	for value in 1..=3 {
+		stack.push_u256(U256::from(value)).unwrap();
+	}
+
+	stack.dup(2).unwrap();
+	assert_eq!(stack.peek(0), Ok(U256::from(1)));
+	assert_eq!(stack.dup(0), Err(ExitError::StackOverflow));
+
+	stack.swap(3).unwrap();
+	assert_eq!(stack.data(), &[U256::from(1), U256::from(2), U256::from(3), U256::from(1)]);
+
+	stack.set(1, U256::from(9)).unwrap();
+	assert_eq!(stack.peek(1), Ok(U256::from(9)));
+	assert_eq!(stack.peek(4), Err(ExitError::StackUnderflow));
+	assert_eq!(stack.swap(4), Err(ExitError::StackUnderflow));
+}
+
+/// Symbolic word, either concrete, an input variable or an opcode applied
+/// to symbolic words.
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Symbol {
+	Concrete(U256),
+	Input(Rc<str>),
+	Apply(Opcode, Vec<Symbol>),
+}
+
+impl Word for Symbol {
+	fn from_u256(value: U256) -> Self {
+		Self::Concrete(value)
+	}
+
+	fn to_u256(&self) -> Option<U256> {
+		match self {
+			Self::Concrete(value) => Some(*value),
+			Self::Input(_) | Self::Apply(..) => None,
+		}
+	}
+
+	fn apply(opcode: Opcode, args: &[Self]) -> Option<Self> {
+		match args.iter().map(Self::to_u256).collect::<Option<Vec<_>>>() {
+			Some(values) => U256::apply(opcode, &values).map(Self::Concrete),
+			None => Some(Self::Apply(opcode, args.to_vec())),
+		}
+	}
+}
+
+fn symbolic_machine(code: Vec<u8>) -> Machine<Symbol> {
+	let mut machine = Machine::new_with_word(code.clone(), Arc::new(Valids::compute(&code).into()), Vec::new(), 4, 64);
+	machine.stack_mut().push_word(Symbol::Input("x".into())).unwrap();
+	machine
+}
+
+#[test]
+fn custom_words() {
+	let mut stack: Stack<Symbol> = Stack::new(2);
+	stack.push_word(Symbol::Input("x".into())).unwrap();
+	stack.push_word(Symbol::from_u256(U256::from(1))).unwrap();
+	assert_eq!(stack.push_word(Symbol::Input("y".into())), Err(ExitError::StackOverflow));
+
+	stack.swap(1).unwrap();
+	assert_eq!(stack.peek(0).unwrap().to_u256(), None);
+	assert_eq!(stack.data()[0].to_u256(), Some(U256::from(1)));
+
+	assert_eq!(stack.pop_word(), Ok(Symbol::Input("x".into())));
+	assert_eq!(stack.dup(0), Ok(()));
+	assert_eq!(stack.data(), &[Symbol::Concrete(U256::from(1)), Symbol::Concrete(U256::from(1))]);
+}
+
+#[test]
+fn machine_with_custom_words() {
+	let code = vec![0x00];
+	let mut machine: Machine<Symbol> = Machine::new_with_word(code.clone(), Arc::new(Valids::compute(&code).into()), Vec::new(), 2, 64);
+	machine.stack_mut().push_word(Symbol::Input("x".into())).unwrap();
+	machine.stack_mut().push_word(Symbol::from_u256(U256::from(7))).unwrap();
+
+	let value = machine.stack_mut().pop_word().unwrap();
+	machine.memory_mut().store(0, &value).unwrap();
+	assert_eq!(machine.memory().load::<Symbol>(0), Symbol::Concrete(U256::from(7)));
+
+	let input = machine.stack_mut().pop_word().unwrap();
+	assert_eq!(machine.memory_mut().store(32, &input), Err(ExitFatal::NotSupported));
+	assert_eq!(machine.inspect().map(|(_, stack)| stack.len()), Some(0));
+}
+
+#[test]
+fn run_with_custom_words() {
+	// PUSH1 2, PUSH1 3, MUL, ADD, PUSH1 0, MSTORE8
+	let mut machine = symbolic_machine(vec![0x60, 0x02, 0x60, 0x03, 0x02, 0x01, 0x60, 0x00, 0x53]);
+	assert_eq!(machine.step(), Ok(()));
+	assert_eq!(machine.run(3, |_, _, _, _| Ok(())).0, 3);
+	assert_eq!(machine.stack().data(), &[
+		Symbol::Apply(Opcode::ADD, vec![Symbol::Concrete(U256::from(6)), Symbol::Input("x".into())]),
+	]);
+
+	// Stored values need a concrete value.
+	assert_eq!(machine.run(u64::MAX, |_, _, _, _| Ok(())), (1, Capture::Exit(ExitFatal::NotSupported.into())));
+}
+
+#[test]
+fn control_flow_with_custom_words() {
+	// POP, PUSH1 5, JUMP, INVALID, JUMPDEST, SLOAD, STOP
+	let mut machine = symbolic_machine(vec![0x50, 0x60, 0x05, 0x56, 0xfe, 0x5b, 0x54, 0x00]);
+	assert_eq!(machine.run(u64::MAX, |_, _, _, _| Ok(())), (4, Capture::Trap(Opcode::SLOAD)));
+	assert_eq!(machine.run(u64::MAX, |_, _, _, _| Ok(())), (0, Capture::Exit(ExitSucceed::Stopped.into())));
+
+	// Jump destinations need a concrete value.
+	let mut machine = symbolic_machine(vec![0x56]);
+	assert_eq!(machine.step(), Err(Capture::Exit(ExitFatal::NotSupported.into())));
+}
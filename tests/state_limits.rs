@@ -0,0 +1,105 @@
+mod common;
+
+use std::collections::BTreeMap;
+use evm::{ExitError, ExitReason, H160, U256};
+use evm::backend::{MemoryAccount, MemoryBackend, MemoryVicinity};
+use evm::executor::{ExecutorStats, StackExecutor, StateLimits};
+use common::{account, caller, contract, vicinity};
+
+fn logger() -> H160 {
+	H160::repeat_byte(0x33)
+}
+
+fn destructed(n: u8) -> H160 {
+	H160::repeat_byte(0x40 + n)
+}
+
+/// `LOG1` of 32 bytes, 64 bytes counted against the cap.
+const LOG: [u8; 7] = [0x60, 0xaa, 0x60, 0x20, 0x60, 0x00, 0xa1];
+
+/// Logs once, then calls `logger()`.
+fn code() -> Vec<u8> {
+	let mut code = LOG.to_vec();
+	code.extend_from_slice(&[0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x73]);
+	code.extend_from_slice(logger().as_bytes());
+	code.extend_from_slice(&[0x5a, 0xf1, 0x00]);
+	code
+}
+
+fn backend(vicinity: &MemoryVicinity) -> MemoryBackend<'_> {
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount::default());
+	state.insert(contract(), account(code()));
+	state.insert(logger(), account([LOG, LOG, LOG].concat()));
+	for n in 0..2 {
+		state.insert(destructed(n), account(vec![0x33, 0xff]));
+	}
+	MemoryBackend::new(vicinity, state)
+}
+
+#[test]
+fn log_size_cap() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+
+	let limits = StateLimits { max_log_size: Some(192), ..StateLimits::default() };
+	let mut executor = StackExecutor::new(&backend, 1_000_000).with_state_limits(limits);
+	let (reason, _) = executor.transact_call(caller(), logger(), U256::zero(), Vec::new(), 100_000);
+	assert!(reason.is_succeed());
+	assert_eq!((executor.stats().logs, executor.stats().log_size), (3, 192));
+
+	let limits = StateLimits { max_log_size: Some(128), ..StateLimits::default() };
+	let mut executor = StackExecutor::new(&backend, 1_000_000).with_state_limits(limits);
+	let (reason, _) = executor.transact_call(caller(), logger(), U256::zero(), Vec::new(), 100_000);
+	assert_eq!(reason, ExitReason::Error(ExitError::LogLimit));
+	assert_eq!((executor.stats().logs, executor.stats().log_size), (0, 0));
+}
+
+#[test]
+fn failed_frames_release_log_size() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let limits = StateLimits { max_log_size: Some(192), ..StateLimits::default() };
+	let mut executor = StackExecutor::new(&backend, 1_000_000).with_state_limits(limits);
+
+	// The logger fails at its third log, the caller keeps its own.
+	let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 100_000);
+	assert!(reason.is_succeed());
+	assert_eq!((executor.stats().logs, executor.stats().log_size), (1, 64));
+
+	let (reason, _) = executor.transact_call(caller(), logger(), U256::zero(), Vec::new(), 100_000);
+	assert_eq!(reason, ExitReason::Error(ExitError::LogLimit));
+	assert_eq!(executor.stats().log_size, 64);
+}
+
+#[test]
+fn self_destruct_cap() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let limits = StateLimits { max_deleted: Some(1), ..StateLimits::default() };
+	let mut executor = StackExecutor::new(&backend, 1_000_000).with_state_limits(limits);
+
+	let (reason, _) = executor.transact_call(caller(), destructed(0), U256::zero(), Vec::new(), 100_000);
+	assert!(reason.is_succeed());
+	let (reason, _) = executor.transact_call(caller(), destructed(1), U256::zero(), Vec::new(), 100_000);
+	assert_eq!(reason, ExitReason::Error(ExitError::SelfDestructLimit));
+	// Accounts already in the set do not count twice.
+	let (reason, _) = executor.transact_call(caller(), destructed(0), U256::zero(), Vec::new(), 100_000);
+	assert!(reason.is_succeed());
+	assert_eq!(executor.stats().deleted, 1);
+}
+
+#[test]
+fn stats() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let mut executor = StackExecutor::new(&backend, 1_000_000);
+	assert_eq!(executor.stats(), ExecutorStats::default());
+
+	let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 100_000);
+	assert!(reason.is_succeed());
+	let stats = executor.stats();
+	assert_eq!((stats.logs, stats.log_size, stats.deleted), (4, 256, 0));
+	assert!(stats.accounts >= 3);
+	assert!(stats.memory > stats.log_size);
+}
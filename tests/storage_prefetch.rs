@@ -0,0 +1,123 @@
+mod common;
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use evm::{CreateScheme, Handler, Keccak, H160, H256, U256};
+use evm::backend::{Backend, Basic, MemoryAccount, MemoryBackend, MemoryVicinity};
+use evm::executor::StackExecutor;
+use common::{caller, contract, vicinity};
+
+/// Backend recording single and batched storage queries.
+struct BatchBackend<'vicinity> {
+	inner: MemoryBackend<'vicinity>,
+	single: RefCell<Vec<U256>>,
+	batched: RefCell<Vec<Vec<U256>>>,
+}
+
+impl Keccak for BatchBackend<'_> {
+	fn keccak256_h256(&self, data: &[u8]) -> H256 { self.inner.keccak256_h256(data) }
+}
+
+impl Backend for BatchBackend<'_> {
+	fn gas_price(&self) -> U256 { self.inner.gas_price() }
+	fn origin(&self) -> H160 { self.inner.origin() }
+	fn block_hash(&self, number: U256) -> H256 { self.inner.block_hash(number) }
+	fn block_number(&self) -> U256 { self.inner.block_number() }
+	fn block_coinbase(&self) -> H160 { self.inner.block_coinbase() }
+	fn block_timestamp(&self) -> U256 { self.inner.block_timestamp() }
+	fn block_difficulty(&self) -> U256 { self.inner.block_difficulty() }
+	fn block_gas_limit(&self) -> U256 { self.inner.block_gas_limit() }
+	fn chain_id(&self) -> U256 { self.inner.chain_id() }
+	fn exists(&self, address: H160) -> bool { self.inner.exists(address) }
+	fn basic(&self, address: H160) -> Basic { self.inner.basic(address) }
+	fn code_hash(&self, address: H160) -> H256 { self.inner.code_hash(address) }
+	fn code_size(&self, address: H160) -> usize { self.inner.code_size(address) }
+	fn code(&self, address: H160) -> Vec<u8> { self.inner.code(address) }
+	fn valids(&self, address: H160) -> Vec<u8> { self.inner.valids(address) }
+	fn create(&self, scheme: &CreateScheme, address: &H160) { self.inner.create(scheme, address) }
+
+	fn storage(&self, address: H160, index: U256) -> U256 {
+		self.single.borrow_mut().push(index);
+		self.inner.storage(address, index)
+	}
+
+	fn storage_multi(&self, address: H160, indices: &[U256]) -> Vec<U256> {
+		self.batched.borrow_mut().push(indices.to_vec());
+		indices.iter().map(|index| self.inner.storage(address, *index)).collect()
+	}
+}
+
+fn backend(vicinity: &MemoryVicinity) -> BatchBackend<'_> {
+	let mut storage = BTreeMap::new();
+	storage.insert(U256::one(), U256::from(5));
+	storage.insert(U256::from(2), U256::from(7));
+
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount::default());
+	state.insert(contract(), MemoryAccount {
+		nonce: U256::one(),
+		// PUSH1 1 SLOAD PUSH1 2 SLOAD ADD PUSH1 0 MSTORE PUSH1 32 PUSH1 0 RETURN
+		code: vec![0x60, 0x01, 0x54, 0x60, 0x02, 0x54, 0x01, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3],
+		storage,
+		..Default::default()
+	});
+	BatchBackend {
+		inner: MemoryBackend::new(vicinity, state),
+		single: RefCell::new(Vec::new()),
+		batched: RefCell::new(Vec::new()),
+	}
+}
+
+#[test]
+fn access_list_is_prefetched() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let mut executor = StackExecutor::new(&backend, 100_000);
+	executor.prewarm_access_list(&vec![
+		(contract(), vec![H256::from(U256::from(2)), H256::from(U256::one())]),
+	]);
+
+	let (reason, output) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 100_000);
+	assert!(reason.is_succeed());
+	assert_eq!(U256::from_big_endian(&output), U256::from(12));
+	assert_eq!(*backend.batched.borrow(), vec![vec![U256::one(), U256::from(2)]]);
+	assert!(backend.single.borrow().is_empty());
+}
+
+#[test]
+fn unlisted_slots_are_read_one_by_one() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let mut executor = StackExecutor::new(&backend, 100_000);
+	executor.prewarm_storage(contract(), U256::one());
+
+	let (reason, output) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 100_000);
+	assert!(reason.is_succeed());
+	assert_eq!(U256::from_big_endian(&output), U256::from(12));
+	assert_eq!(*backend.batched.borrow(), vec![vec![U256::one()]]);
+	assert_eq!(*backend.single.borrow(), vec![U256::from(2)]);
+}
+
+#[test]
+fn prefetch_skips_known_slots() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let mut executor = StackExecutor::new(&backend, 100_000);
+	executor.set_storage(contract(), U256::one(), U256::from(9)).unwrap();
+
+	executor.prefetch_storage(contract(), &[U256::one(), U256::from(2), U256::from(2)]);
+	executor.prefetch_storage(contract(), &[U256::from(2)]);
+	assert_eq!(*backend.batched.borrow(), vec![vec![U256::from(2)]]);
+	assert_eq!(executor.storage(contract(), U256::one()), U256::from(9));
+	assert_eq!(executor.storage(contract(), U256::from(2)), U256::from(7));
+	// Only the original value of the written slot was read, by `set_storage`.
+	assert_eq!(*backend.single.borrow(), vec![U256::one()]);
+}
+
+#[test]
+fn default_storage_multi() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let values = backend.inner.storage_multi(contract(), &[U256::from(2), U256::from(3), U256::one()]);
+	assert_eq!(values, vec![U256::from(7), U256::zero(), U256::from(5)]);
+}
@@ -0,0 +1,124 @@
+mod common;
+
+use std::collections::BTreeMap;
+use evm::{CreateScheme, ExitReason, ExitRevert, H160, U256};
+use evm::backend::{MemoryAccount, MemoryBackend};
+use evm::executor::StackExecutor;
+use evm::summary::{decode_revert_reason, CallTreeCollector, ExecutionSummary};
+use evm::testing::TestChain;
+use common::{account, caller, vicinity};
+
+fn reverter() -> H160 {
+	H160::repeat_byte(0x22)
+}
+
+/// `PUSH32 word PUSH1 offset MSTORE`, with `prefix` left aligned in the word.
+fn mstore(prefix: &[u8], offset: u8) -> Vec<u8> {
+	let mut code = vec![0x7f];
+	let mut word = [0_u8; 32];
+	word[..prefix.len()].copy_from_slice(prefix);
+	code.extend_from_slice(&word);
+	code.extend_from_slice(&[0x60, offset, 0x52]);
+	code
+}
+
+/// Reverts with `Error("nope")`.
+fn revert_nope() -> Vec<u8> {
+	[
+		mstore(&[0x08, 0xc3, 0x79, 0xa0], 0x00),
+		vec![0x60, 0x20, 0x60, 0x04, 0x52],
+		vec![0x60, 0x04, 0x60, 0x24, 0x52],
+		mstore(b"nope", 0x44),
+		vec![0x60, 0x64, 0x60, 0x00, 0xfd],
+	].concat()
+}
+
+fn state() -> BTreeMap<H160, MemoryAccount> {
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount {
+		balance: U256::from(1_000_000),
+		..Default::default()
+	});
+	state.insert(reverter(), account(revert_nope()));
+	state
+}
+
+#[test]
+fn revert_reason() {
+	let vicinity = vicinity();
+	let backend = MemoryBackend::new(&vicinity, state());
+	let collector = CallTreeCollector::new();
+	let mut executor = StackExecutor::new(&backend, 100_000).with_listener(&collector);
+	let (reason, return_value) = executor.transact_call(caller(), reverter(), U256::zero(), Vec::new(), 100_000);
+	assert_eq!(reason, ExitReason::Revert(ExitRevert::Reverted));
+
+	let summary = ExecutionSummary::new(reason, return_value, 100_000, executor.used_gas(), executor.refund())
+		.with_call_tree(&collector);
+	assert_eq!(summary.revert_reason().as_deref(), Some("nope"));
+	assert_eq!(summary.max_depth, Some(1));
+	assert!(summary.created.is_empty());
+
+	let text = summary.to_string();
+	assert!(text.contains("- Status: Revert(Reverted)"));
+	assert!(text.contains("- Revert reason: \"nope\""));
+	assert!(text.contains("- Call tree: 1 frames, max depth 1"));
+	assert!(text.contains("- Limit: 100000"));
+	assert!(!text.contains("### Created contracts"));
+}
+
+#[test]
+fn panic_reason() {
+	let mut data = vec![0x4e, 0x48, 0x7b, 0x71];
+	data.extend_from_slice(&[0_u8; 31]);
+	data.push(0x11);
+	assert_eq!(decode_revert_reason(&data).as_deref(), Some("Panic(0x11)"));
+	assert_eq!(decode_revert_reason(&data[..20]), None);
+	assert_eq!(decode_revert_reason(&[0x2a; 4]), None);
+}
+
+#[test]
+fn nested_creates() {
+	let vicinity = vicinity();
+	let backend = MemoryBackend::new(&vicinity, state());
+	let collector = CallTreeCollector::new();
+	let mut executor = StackExecutor::new(&backend, 1_000_000).with_listener(&collector);
+	let outer = executor.create_address(CreateScheme::Legacy { caller: caller() });
+	// CREATE with empty init code, twice, the second one in a frame that
+	// reverts.
+	let mut init_code = vec![0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0xf0, 0x50];
+	init_code.extend_from_slice(&[0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0xf0, 0x50, 0x60, 0x00, 0x60, 0x00, 0xfd]);
+	let reason = executor.transact_create(caller(), U256::zero(), init_code.clone(), 1_000_000);
+	assert_eq!(reason, ExitReason::Revert(ExitRevert::Reverted));
+	assert!(collector.created().is_empty());
+	assert_eq!(collector.frame_count(), 3);
+	assert_eq!(collector.max_depth(), 2);
+
+	let collector = CallTreeCollector::new();
+	let mut executor = StackExecutor::new(&backend, 1_000_000).with_listener(&collector);
+	init_code.truncate(8);
+	let reason = executor.transact_create(caller(), U256::zero(), init_code, 1_000_000);
+	assert!(reason.is_succeed());
+	assert_eq!(collector.created().len(), 2);
+	assert_eq!(collector.created()[0], outer);
+	assert_ne!(collector.created()[1], outer);
+}
+
+#[test]
+fn from_receipt() {
+	let mut chain = TestChain::new(vicinity(), state(), U256::from(15));
+	// LOG0, then deploy an empty contract.
+	let init_code = vec![0x60, 0x00, 0x60, 0x00, 0xa0, 0x00];
+	let receipt = chain.transact_create(caller(), U256::zero(), init_code, 100_000).unwrap();
+	let address = receipt.contract_address.unwrap();
+
+	let summary = ExecutionSummary::from(receipt);
+	assert_eq!(summary.created, vec![address]);
+	assert_eq!(summary.gas_limit, 100_000);
+	assert_eq!(summary.logs.len(), 1);
+	assert_eq!(summary.revert_reason(), None);
+
+	let text = summary.to_string();
+	assert!(text.contains("### Created contracts"));
+	assert!(text.contains(&format!("- {:?}: 1", address)));
+	assert!(!text.contains("Call tree"));
+}
@@ -0,0 +1,52 @@
+mod common;
+
+use std::collections::BTreeMap;
+use evm::{ExitReason, ExitSucceed, Handler, H160, U256};
+use evm::backend::{Apply, MemoryBackend};
+use evm::executor::{StackExecutor, SYSTEM_ADDRESS};
+use common::{account, vicinity};
+
+fn beacon_roots() -> H160 {
+	H160::repeat_byte(0x42)
+}
+
+/// Stores the first word of the input at slot `CALLER`.
+const STORE_INPUT: [u8; 6] = [0x60, 0x00, 0x35, 0x33, 0x55, 0x00];
+
+fn slot() -> U256 {
+	U256::from_big_endian(SYSTEM_ADDRESS.as_bytes())
+}
+
+#[test]
+fn system_call_is_free() {
+	let vicinity = vicinity();
+	let mut state = BTreeMap::new();
+	state.insert(beacon_roots(), account(STORE_INPUT.to_vec()));
+	let backend = MemoryBackend::new(&vicinity, state);
+
+	// The executor gas limit does not even cover the intrinsic gas.
+	let mut executor = StackExecutor::new(&backend, 0);
+	let mut root = [0_u8; 32];
+	root[31] = 0xab;
+	let (reason, _) = executor.system_call(beacon_roots(), root.to_vec());
+	assert_eq!(reason, ExitReason::Succeed(ExitSucceed::Stopped));
+	assert_eq!(executor.storage(beacon_roots(), slot()), U256::from(0xab));
+	assert_eq!(executor.used_gas(), 0);
+	assert_eq!(executor.nonce(SYSTEM_ADDRESS), U256::zero());
+
+	let (applies, _) = executor.deconstruct();
+	let addresses: Vec<H160> = applies.iter().map(|apply| match apply {
+		Apply::Modify { address, .. } | Apply::Delete { address } => *address,
+	}).collect();
+	assert_eq!(addresses, vec![beacon_roots()]);
+}
+
+#[test]
+fn system_call_without_code() {
+	let vicinity = vicinity();
+	let backend = MemoryBackend::new(&vicinity, BTreeMap::new());
+	let mut executor = StackExecutor::new(&backend, 0);
+	let (reason, out) = executor.system_call(beacon_roots(), Vec::new());
+	assert!(reason.is_succeed());
+	assert!(out.is_empty());
+}
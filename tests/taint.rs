@@ -0,0 +1,93 @@
+#![cfg(feature = "taint")]
+
+mod common;
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use evm::{Capture, Machine, Opcode, TaintFlow, TaintLog, Valids, U256};
+use evm::backend::{MemoryAccount, MemoryBackend};
+use evm::executor::StackExecutor;
+use common::{callee, caller, contract, vicinity};
+
+#[test]
+fn machine_shadow() {
+	// PUSH1 0 CALLDATALOAD PUSH1 1 ADD PUSH1 0 MSTORE PUSH1 7 PUSH1 0 MLOAD
+	// DUP2 SWAP1 SSTORE
+	let code = vec![
+		0x60, 0x00, 0x35, 0x60, 0x01, 0x01, 0x60, 0x00, 0x52, 0x60, 0x07, 0x60, 0x00, 0x51,
+		0x81, 0x90, 0x55,
+	];
+	let sink = Arc::new(TaintLog::default());
+	let mut machine = Machine::new(code.clone(), Arc::new(Valids::compute(&code).into()), vec![0xff; 32], 1024, usize::MAX);
+	machine.set_taint_sink(sink.clone());
+
+	let (_, capture) = machine.run(u64::MAX, |_, _, _, _| Ok(()));
+	assert_eq!(capture, Capture::Trap(Opcode::SSTORE));
+	let taint = machine.taint().unwrap();
+	// 7 was stored in the slot loaded from memory, the other 7 is left.
+	assert_eq!(taint.stack(), [false]);
+	assert!(taint.is_memory_tainted(0, 32));
+	assert!(taint.is_memory_tainted(31, 1));
+	assert!(!taint.is_memory_tainted(32, 32));
+
+	assert_eq!(sink.flows(), vec![TaintFlow {
+		position: 16,
+		opcode: Opcode::SSTORE,
+		arguments: vec![true, false],
+		input: false,
+	}]);
+}
+
+#[test]
+fn byte_granularity() {
+	// PUSH1 1 PUSH1 0 MSTORE CALLDATASIZE PUSH1 0 MSTORE8 PUSH1 0 MLOAD
+	let code = vec![0x60, 0x01, 0x60, 0x00, 0x52, 0x36, 0x60, 0x00, 0x53, 0x60, 0x00, 0x51];
+	let sink = Arc::new(TaintLog::default());
+	let mut machine = Machine::new(code.clone(), Arc::new(Valids::compute(&code).into()), Vec::new(), 1024, usize::MAX);
+	machine.set_taint_sink(sink);
+
+	let (_, capture) = machine.run(u64::MAX, |_, _, _, _| Ok(()));
+	assert!(matches!(capture, Capture::Exit(reason) if reason.is_succeed()));
+	// The call data length tainted the first byte only.
+	let taint = machine.taint().unwrap();
+	assert_eq!(taint.stack(), [true]);
+	assert!(taint.is_memory_tainted(0, 1));
+	assert!(!taint.is_memory_tainted(1, 31));
+}
+
+#[test]
+fn executor_frames() {
+	// PUSH1 32 PUSH1 0 PUSH1 0 CALLDATACOPY
+	let mut code = vec![0x60, 0x20, 0x60, 0x00, 0x60, 0x00, 0x37];
+	// PUSH1 0 PUSH1 0 PUSH1 32 PUSH1 0 PUSH1 0 PUSH20 callee() PUSH2 0xffff CALL POP
+	code.extend_from_slice(&[0x60, 0x00, 0x60, 0x00, 0x60, 0x20, 0x60, 0x00, 0x60, 0x00, 0x73]);
+	code.extend_from_slice(callee().as_bytes());
+	code.extend_from_slice(&[0x61, 0xff, 0xff, 0xf1, 0x50]);
+	// PUSH1 4 CALLDATALOAD PUSH1 1 SSTORE
+	code.extend_from_slice(&[0x60, 0x04, 0x35, 0x60, 0x01, 0x55]);
+	assert_eq!(code[41], 0xf1);
+
+	let vicinity = vicinity();
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount::default());
+	// `contract()` forwards the first word of its call data to `callee()`, then
+	// stores the word at offset 4 in slot 1.
+	state.insert(contract(), MemoryAccount { code, ..Default::default() });
+	// `callee()` stores the first word of its call data in slot 0:
+	// PUSH1 0 CALLDATALOAD PUSH1 0 SSTORE
+	state.insert(callee(), MemoryAccount { code: vec![0x60, 0x00, 0x35, 0x60, 0x00, 0x55], ..Default::default() });
+	let backend = MemoryBackend::new(&vicinity, state);
+
+	let sink = Arc::new(TaintLog::default());
+	let mut executor = StackExecutor::new(&backend, 1_000_000).with_taint_sink(sink.clone());
+	let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), vec![0x42; 36], 1_000_000);
+	assert!(reason.is_succeed());
+
+	assert_eq!(sink.flows(), vec![
+		TaintFlow { position: 41, opcode: Opcode::CALL, arguments: vec![false; 7], input: true },
+		// The callee tracks its own call data.
+		TaintFlow { position: 5, opcode: Opcode::SSTORE, arguments: vec![false, true], input: false },
+		TaintFlow { position: 48, opcode: Opcode::SSTORE, arguments: vec![false, true], input: false },
+	]);
+	assert!(sink.flows()[2].is_argument_tainted(1));
+}
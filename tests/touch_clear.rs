@@ -0,0 +1,195 @@
+mod common;
+
+use std::collections::BTreeMap;
+use evm::{ExitError, ExitSucceed, Frontier, Istanbul, Spec, H160, U256};
+use evm::backend::{Apply, MemoryAccount, MemoryBackend};
+use evm::executor::StackExecutor;
+use evm::testing::TestChain;
+use common::{account, caller, contract, vicinity};
+
+fn empty() -> H160 {
+	H160::repeat_byte(0x33)
+}
+
+fn sha256() -> H160 {
+	H160::from(U256::from(2))
+}
+
+fn ripemd160() -> H160 {
+	H160::from(U256::from(3))
+}
+
+type PrecompileResult = Option<Result<(ExitSucceed, Vec<u8>, u64), ExitError>>;
+
+/// Both precompiles run out of gas.
+fn precompile(address: H160, _input: &[u8], _target_gas: Option<u64>) -> PrecompileResult {
+	if address == sha256() || address == ripemd160() {
+		Some(Err(ExitError::OutOfGas))
+	} else {
+		None
+	}
+}
+
+fn missing() -> H160 {
+	H160::repeat_byte(0x44)
+}
+
+/// `CALL` to `address` with no value, input nor output, discarding the
+/// result.
+fn call(address: H160) -> Vec<u8> {
+	call_with_value(address, 0)
+}
+
+/// `CALL` to `address` transferring `value`, with no input nor output,
+/// discarding the result.
+fn call_with_value(address: H160, value: u8) -> Vec<u8> {
+	let mut code = vec![0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, value, 0x73];
+	code.extend_from_slice(address.as_bytes());
+	// Forward 10000 gas, as `GAS` would exceed the gas left before EIP-150.
+	code.extend_from_slice(&[0x61, 0x27, 0x10, 0xf1, 0x50]);
+	code
+}
+
+fn deleted(code: Vec<u8>, target: H160) -> Vec<H160> {
+	let vicinity = vicinity();
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount {
+		nonce: U256::one(),
+		..Default::default()
+	});
+	state.insert(contract(), account(code));
+	state.insert(empty(), MemoryAccount::default());
+	state.insert(sha256(), MemoryAccount::default());
+	state.insert(ripemd160(), MemoryAccount::default());
+	let backend = MemoryBackend::new(&vicinity, state);
+
+	let mut executor = StackExecutor::new_with_precompile(&backend, 1_000_000, precompile);
+	let (reason, _) = executor.transact_call(caller(), target, U256::zero(), Vec::new(), 1_000_000);
+	assert!(reason.is_succeed());
+
+	let (applies, _) = executor.deconstruct();
+	applies.into_iter()
+		.filter_map(|apply| match apply {
+			Apply::Delete { address } => Some(address),
+			Apply::Modify { .. } => None,
+		})
+		.collect()
+}
+
+struct Run {
+	used_gas: u64,
+	touched: Vec<H160>,
+	applies: Vec<Apply<BTreeMap<U256, U256>>>,
+}
+
+/// Run `code` from the contract, which holds some balance, under `spec`.
+fn run<S: Spec>(spec: S, code: Vec<u8>) -> Run {
+	let vicinity = vicinity();
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount {
+		nonce: U256::one(),
+		..Default::default()
+	});
+	state.insert(contract(), MemoryAccount {
+		nonce: U256::one(),
+		balance: U256::from(10),
+		code,
+		..Default::default()
+	});
+	state.insert(empty(), MemoryAccount::default());
+	let backend = MemoryBackend::new(&vicinity, state);
+
+	let mut executor = StackExecutor::new_with_spec(&backend, 1_000_000, precompile, spec);
+	let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 1_000_000);
+	assert!(reason.is_succeed(), "{:?}", reason);
+
+	let used_gas = executor.used_gas();
+	let touched = executor.touched().iter().copied().collect();
+	let (applies, _) = executor.deconstruct();
+	Run { used_gas, touched, applies }
+}
+
+/// Balance of `address` if the run modifies it.
+fn modified(run: &Run, address: H160) -> Option<U256> {
+	run.applies.iter().find_map(|apply| match apply {
+		Apply::Modify { address: modified, basic, .. } if *modified == address => Some(basic.balance),
+		_ => None,
+	})
+}
+
+fn is_deleted(run: &Run, address: H160) -> bool {
+	run.applies.iter().any(|apply| matches!(apply, Apply::Delete { address: deleted } if *deleted == address))
+}
+
+#[test]
+fn value_transfer_creates_account() {
+	for run in [run(Frontier, call_with_value(missing(), 1)), run(Istanbul, call_with_value(missing(), 1))] {
+		assert_eq!(modified(&run, missing()), Some(U256::one()));
+		assert!(!is_deleted(&run, missing()));
+		assert!(run.touched.contains(&missing()));
+	}
+
+	// The new account is charged, compared to a transfer to an existing
+	// account.
+	let existing = run(Istanbul, call_with_value(caller(), 1));
+	let created = run(Istanbul, call_with_value(missing(), 1));
+	assert_eq!(created.used_gas - existing.used_gas, 25_000);
+}
+
+#[test]
+fn zero_value_call_to_missing_account() {
+	// Before EIP-161, the call creates an empty account and is charged
+	// for it.
+	let existing = run(Frontier, call(empty()));
+	let created = run(Frontier, call(missing()));
+	assert_eq!(modified(&created, missing()), Some(U256::zero()));
+	assert!(!is_deleted(&created, missing()));
+	assert!(!is_deleted(&created, empty()));
+	assert_eq!(created.used_gas - existing.used_gas, 25_000);
+
+	// Afterwards, the call is not charged and the touched empty account is
+	// deleted at the end of the transaction.
+	let existing = run(Istanbul, call(empty()));
+	let created = run(Istanbul, call(missing()));
+	assert_eq!(created.used_gas, existing.used_gas);
+	assert_eq!(modified(&created, missing()), None);
+	assert!(is_deleted(&created, missing()));
+	assert!(is_deleted(&existing, empty()));
+}
+
+#[test]
+fn touched_empty_account_is_deleted() {
+	assert_eq!(deleted(Vec::new(), empty()), vec![empty()]);
+	assert_eq!(deleted(call(empty()), contract()), vec![empty()]);
+}
+
+#[test]
+fn untouched_empty_account_is_kept() {
+	assert_eq!(deleted(Vec::new(), contract()), Vec::<H160>::new());
+}
+
+#[test]
+fn failed_call_reverts_touch() {
+	assert_eq!(deleted(call(sha256()), contract()), Vec::<H160>::new());
+}
+
+#[test]
+fn failed_call_keeps_ripemd160_touch() {
+	assert_eq!(deleted(call(ripemd160()), contract()), vec![ripemd160()]);
+}
+
+#[test]
+fn test_chain_follows_spec() {
+	fn contains<S: Spec>(spec: S) -> bool {
+		let mut state = BTreeMap::new();
+		state.insert(caller(), MemoryAccount::default());
+		state.insert(empty(), MemoryAccount::default());
+		let mut chain = TestChain::new_with_spec(vicinity(), state, U256::from(15), spec);
+		let receipt = chain.transact_call(caller(), empty(), U256::zero(), Vec::new(), 50_000).unwrap();
+		assert!(receipt.reason.is_succeed());
+		chain.state().contains_key(&empty())
+	}
+
+	assert!(!contains(Istanbul));
+	assert!(contains(Frontier));
+}
@@ -0,0 +1,84 @@
+mod common;
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use evm::U256;
+use evm::backend::{MemoryAccount, MemoryBackend};
+use evm::executor::StackExecutor;
+use evm::tracing::{Event, EventListener, TraceConfig};
+use common::{account, callee, caller, contract, vicinity};
+
+/// Names of the received events, with whether steps carry the stack and the
+/// memory.
+#[derive(Default)]
+struct Recorder(RefCell<Vec<String>>);
+
+impl EventListener for Recorder {
+	fn event(&self, event: Event<'_>) {
+		let name = match event {
+			Event::Call { .. } => String::from("call"),
+			Event::Create { .. } => String::from("create"),
+			Event::FrameGas { .. } => String::from("gas"),
+			Event::Exit { .. } => String::from("exit"),
+			Event::Step { stack, memory, .. } => format!("step {} {}", stack.is_some(), memory.is_some()),
+			Event::SetStorage { index, value, .. } => format!("sstore {} {}", index, value),
+		};
+		self.0.borrow_mut().push(name);
+	}
+}
+
+/// `SSTORE(0, 5)` then `CALL` to `callee()`.
+fn code() -> Vec<u8> {
+	let mut code = vec![0x60, 0x05, 0x60, 0x00, 0x55];
+	code.extend_from_slice(&[0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x73]);
+	code.extend_from_slice(callee().as_bytes());
+	code.extend_from_slice(&[0x61, 0x27, 0x10, 0xf1, 0x00]);
+	code
+}
+
+fn run(config: Option<TraceConfig>) -> Vec<String> {
+	let vicinity = vicinity();
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount::default());
+	state.insert(contract(), account(code()));
+	state.insert(callee(), MemoryAccount {
+		code: vec![0x00],
+		..Default::default()
+	});
+	let backend = MemoryBackend::new(&vicinity, state);
+
+	let recorder = Recorder::default();
+	let mut executor = StackExecutor::new(&backend, 100_000).with_listener(&recorder);
+	if let Some(config) = config {
+		executor = executor.with_trace_config(config);
+	}
+	let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 100_000);
+	assert!(reason.is_succeed());
+	recorder.0.into_inner()
+}
+
+#[test]
+fn all_by_default() {
+	let events = run(None);
+	assert_eq!(events, run(Some(TraceConfig::all())));
+	assert_eq!(TraceConfig::default(), TraceConfig::all());
+	assert_eq!(events.iter().filter(|name| name.as_str() == "step true true").count(), 13);
+	assert!(events.contains(&String::from("sstore 0 5")));
+	assert_eq!(events.iter().filter(|name| name.as_str() == "gas").count(), 2);
+}
+
+#[test]
+fn calls_only() {
+	assert_eq!(run(Some(TraceConfig::calls())), ["call", "call", "exit", "exit"]);
+}
+
+#[test]
+fn steps_without_snapshots() {
+	let config = TraceConfig { memory: false, stack: false, storage: false, gas: false, ..TraceConfig::all() };
+	let events = run(Some(config));
+	assert!(events.iter().all(|name| !name.starts_with("sstore") && name != "gas"));
+	assert_eq!(events.iter().filter(|name| name.as_str() == "step false false").count(), 13);
+
+	let config = TraceConfig { memory: false, ..TraceConfig::all() };
+	assert_eq!(run(Some(config)).iter().filter(|name| name.as_str() == "step true false").count(), 13);
+}
@@ -0,0 +1,72 @@
+mod common;
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use evm::{Opcode, U256};
+use evm::backend::{MemoryAccount, MemoryBackend};
+use evm::executor::StackExecutor;
+use evm::tracing::{Event, EventListener};
+use common::{account, caller, contract, vicinity};
+
+/// Writes `0xaa 0xbb` at memory offset 0, pushes 7, 8 and 9, then stops.
+const CODE: [u8; 17] = [
+	0x60, 0xaa, 0x60, 0x00, 0x53, 0x60, 0xbb, 0x60, 0x01, 0x53,
+	0x60, 0x07, 0x60, 0x08, 0x60, 0x09, 0x00,
+];
+
+/// Parts of the snapshots at the final `STOP`, each copied on its own.
+#[derive(Debug, Default, PartialEq)]
+struct AtStop {
+	stack_len: usize,
+	top: Vec<U256>,
+	peeks: Vec<Option<U256>>,
+	memory_len: usize,
+	written: Option<Vec<u8>>,
+	past_end: Option<Vec<u8>>,
+	padded: Vec<u8>,
+}
+
+#[derive(Default)]
+struct Recorder(RefCell<Option<AtStop>>);
+
+impl EventListener for Recorder {
+	fn event(&self, event: Event<'_>) {
+		if let Event::Step { opcode: Opcode::STOP, stack: Some(stack), memory: Some(memory), .. } = event {
+			*self.0.borrow_mut() = Some(AtStop {
+				stack_len: stack.len(),
+				top: stack.top(2).to_vec(),
+				peeks: vec![stack.peek(0), stack.peek(2), stack.peek(3)],
+				memory_len: memory.len(),
+				written: memory.slice(0, 2).map(<[u8]>::to_vec),
+				past_end: memory.slice(memory.len() - 1, 2).map(<[u8]>::to_vec),
+				padded: memory.read(memory.len(), 3),
+			});
+		}
+	}
+}
+
+#[test]
+fn snapshots_read_on_demand() {
+	let vicinity = vicinity();
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount::default());
+	state.insert(contract(), account(CODE.to_vec()));
+	let backend = MemoryBackend::new(&vicinity, state);
+
+	let recorder = Recorder::default();
+	let mut executor = StackExecutor::new(&backend, 100_000).with_listener(&recorder);
+	let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 100_000);
+	assert!(reason.is_succeed());
+
+	let at_stop = recorder.0.into_inner().unwrap();
+	assert_eq!(at_stop, AtStop {
+		stack_len: 3,
+		top: vec![U256::from(8), U256::from(9)],
+		peeks: vec![Some(U256::from(9)), Some(U256::from(7)), None],
+		memory_len: at_stop.memory_len,
+		written: Some(vec![0xaa, 0xbb]),
+		past_end: None,
+		padded: vec![0x00, 0x00, 0x00],
+	});
+	assert!(at_stop.memory_len >= 2);
+}
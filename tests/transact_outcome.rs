@@ -0,0 +1,73 @@
+mod common;
+
+use std::collections::BTreeMap;
+use evm::{ExitReason, ExitRevert, U256};
+use evm::backend::{MemoryAccount, MemoryBackend, MemoryVicinity};
+use evm::executor::StackExecutor;
+use common::{caller, contract, vicinity};
+
+/// Clears slot 0, emits an empty `LOG0` and returns `0x2a`.
+const CODE: [u8; 20] = [
+	0x60, 0x00, 0x60, 0x00, 0x55, 0x60, 0x00, 0x60, 0x00, 0xa0,
+	0x60, 0x2a, 0x60, 0x00, 0x53, 0x60, 0x01, 0x60, 0x00, 0xf3,
+];
+
+/// Reverts with `0x2a`.
+const REVERTING_INIT: [u8; 10] = [0x60, 0x2a, 0x60, 0x00, 0x53, 0x60, 0x01, 0x60, 0x00, 0xfd];
+
+fn backend(vicinity: &MemoryVicinity) -> MemoryBackend<'_> {
+	let mut storage = BTreeMap::new();
+	storage.insert(U256::zero(), U256::one());
+
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount::default());
+	state.insert(contract(), MemoryAccount {
+		nonce: U256::one(),
+		code: CODE.to_vec(),
+		storage,
+		..Default::default()
+	});
+	MemoryBackend::new(vicinity, state)
+}
+
+#[test]
+fn call_outcome() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let mut executor = StackExecutor::new(&backend, 1_000_000);
+
+	let outcome = executor.transact_call_with_outcome(caller(), contract(), U256::zero(), Vec::new(), 100_000);
+	assert!(outcome.exit.is_succeed());
+	assert_eq!(outcome.output, vec![0x2a]);
+	assert_eq!(outcome.gas_used, executor.used_gas());
+	assert_eq!(outcome.gas_refunded, executor.refund().effective_refund);
+	assert!(outcome.gas_refunded > 0);
+	assert_eq!(outcome.logs.len(), 1);
+	assert_eq!(outcome.logs[0].address, contract());
+
+	// The second transaction only reports its own gas and logs.
+	let (used_gas, refund) = (executor.used_gas(), executor.refund().effective_refund);
+	let outcome = executor.transact_call_with_outcome(caller(), contract(), U256::zero(), Vec::new(), 100_000);
+	assert!(outcome.exit.is_succeed());
+	assert_eq!(outcome.gas_used, executor.used_gas() - used_gas);
+	assert_eq!(outcome.gas_refunded, executor.refund().effective_refund - refund);
+	assert_eq!(outcome.logs.len(), 1);
+}
+
+#[test]
+fn create_outcome() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let mut executor = StackExecutor::new(&backend, 1_000_000);
+
+	let outcome = executor.transact_create_with_outcome(caller(), U256::zero(), REVERTING_INIT.to_vec(), 100_000);
+	assert_eq!(outcome.exit, ExitReason::Revert(ExitRevert::Reverted));
+	assert_eq!(outcome.output, vec![0x2a]);
+	assert_eq!(outcome.gas_used, executor.used_gas());
+	assert!(outcome.logs.is_empty());
+
+	let outcome = executor.transact_create_with_outcome(caller(), U256::zero(), CODE.to_vec(), 100_000);
+	assert!(outcome.exit.is_succeed());
+	assert!(outcome.output.is_empty());
+	assert_eq!(outcome.logs.len(), 1);
+}
@@ -0,0 +1,372 @@
+mod common;
+
+use std::collections::BTreeMap;
+use evm::{Berlin, Config, ExitError, ExitReason, ExitSucceed, Handler, Istanbul, Spec, H160, H256, U256};
+use evm::backend::{MemoryAccount, MemoryBackend, MemoryVicinity};
+use evm::executor::{Fees, StackExecutor, TransactionValidationError};
+use evm::transaction::{
+	Signature, Transaction, TransactionAction, TransactionError, TransactionType, ACCESS_LIST_ADDRESS_GAS,
+	ACCESS_LIST_STORAGE_KEY_GAS,
+};
+use common::{caller, contract};
+
+fn vicinity() -> MemoryVicinity {
+	MemoryVicinity {
+		block_coinbase: coinbase(),
+		..common::vicinity()
+	}
+}
+
+type PrecompileResult = Option<Result<(ExitSucceed, Vec<u8>, u64), ExitError>>;
+
+const fn no_precompile(_address: H160, _input: &[u8], _target_gas: Option<u64>) -> PrecompileResult {
+	None
+}
+
+/// Istanbul with the transactions of EIP-2930 and EIP-1559.
+#[derive(Clone, Copy, Debug, Default)]
+struct TypedTransactions;
+
+impl Spec for TypedTransactions {
+	const CONFIG: Config = Config {
+		has_access_list_transactions: true,
+		has_dynamic_fee_transactions: true,
+		..Config::istanbul()
+	};
+}
+
+fn executor<'backend>(
+	backend: &'backend MemoryBackend<'backend>,
+	gas_limit: u64,
+) -> StackExecutor<'backend, MemoryBackend<'backend>, TypedTransactions> {
+	StackExecutor::new_with_spec(backend, gas_limit, no_precompile, TypedTransactions)
+}
+
+fn coinbase() -> H160 {
+	H160::repeat_byte(0x33)
+}
+
+fn backend(vicinity: &MemoryVicinity) -> MemoryBackend<'_> {
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount {
+		balance: U256::from(10_000_000),
+		..Default::default()
+	});
+	state.insert(contract(), MemoryAccount {
+		nonce: U256::one(),
+		// PUSH1 1 PUSH1 0 SSTORE
+		code: vec![0x60, 0x01, 0x60, 0x00, 0x55],
+		..Default::default()
+	});
+	MemoryBackend::new(vicinity, state)
+}
+
+/// Recovery accepting any signature with a non-zero `r` as signed by
+/// `caller()`.
+fn recover(_: H256, signature: &Signature) -> Option<H160> {
+	if signature.r.is_zero() {
+		None
+	} else {
+		Some(caller())
+	}
+}
+
+fn transaction(transaction_type: TransactionType) -> Transaction {
+	let typed = transaction_type != TransactionType::Legacy;
+	Transaction {
+		transaction_type,
+		chain_id: Some(1),
+		nonce: U256::zero(),
+		gas_price: U256::from(10),
+		max_priority_fee_per_gas: if transaction_type == TransactionType::DynamicFee { Some(U256::from(2)) } else { None },
+		gas_limit: U256::from(100_000),
+		action: TransactionAction::Call(contract()),
+		value: U256::from(7),
+		input: vec![0xab],
+		access_list: if typed { vec![(contract(), vec![H256::zero()])] } else { Vec::new() },
+		signature: Signature { odd_y_parity: true, r: U256::one(), s: U256::from(2) },
+	}
+}
+
+#[test]
+fn envelope_round_trip() {
+	for transaction_type in [TransactionType::Legacy, TransactionType::AccessList, TransactionType::DynamicFee] {
+		let transaction = transaction(transaction_type);
+		let encoded = transaction.encode().unwrap();
+		if transaction_type == TransactionType::Legacy {
+			assert!(encoded[0] >= 0xc0);
+		} else {
+			assert_eq!(encoded[0], transaction_type.as_u8());
+		}
+		assert_eq!(Transaction::decode(&encoded).unwrap(), transaction);
+
+		let create = Transaction { action: TransactionAction::Create, ..transaction };
+		assert_eq!(Transaction::decode(&create.encode().unwrap()).unwrap(), create);
+	}
+
+	let pre_eip155 = Transaction { chain_id: None, ..transaction(TransactionType::Legacy) };
+	assert_eq!(Transaction::decode(&pre_eip155.encode().unwrap()).unwrap(), pre_eip155);
+}
+
+#[test]
+fn envelope_errors() {
+	assert_eq!(Transaction::decode(&[0x03, 0xc0]), Err(TransactionError::UnsupportedType(3)));
+	assert!(matches!(Transaction::decode(&[0x02, 0xc0]), Err(TransactionError::Decode(_))));
+	assert!(matches!(Transaction::decode(&[]), Err(TransactionError::Decode(_))));
+
+	let mut stream = rlp::RlpStream::new_list(9);
+	for _ in 0..6 {
+		stream.append_empty_data();
+	}
+	stream.append(&30_u8);
+	stream.append(&1_u8);
+	stream.append(&1_u8);
+	assert_eq!(Transaction::decode(&stream.out()), Err(TransactionError::InvalidSignature));
+}
+
+#[test]
+fn trailing_bytes_are_rejected() {
+	for transaction_type in [TransactionType::Legacy, TransactionType::AccessList, TransactionType::DynamicFee] {
+		let mut encoded = transaction(transaction_type).encode().unwrap();
+		encoded.push(0x00);
+		assert_eq!(Transaction::decode(&encoded), Err(TransactionError::Decode(rlp::DecoderError::RlpIsTooBig)));
+	}
+}
+
+#[test]
+fn chain_id_overflow() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+
+	// The largest chain ID whose `v` value fits, with either parity.
+	let largest = (u64::MAX - 36) / 2;
+	for odd_y_parity in [false, true] {
+		let signature = Signature { odd_y_parity, ..transaction(TransactionType::Legacy).signature };
+		let transaction = Transaction { chain_id: Some(largest), signature, ..transaction(TransactionType::Legacy) };
+		assert_eq!(Transaction::decode(&transaction.encode().unwrap()).unwrap(), transaction);
+	}
+
+	for chain_id in [largest + 1, u64::MAX / 2, u64::MAX] {
+		let transaction = Transaction { chain_id: Some(chain_id), ..transaction(TransactionType::Legacy) };
+		assert_eq!(transaction.encode(), Err(TransactionError::ChainIdOverflow));
+		assert_eq!(transaction.hash(&backend), Err(TransactionError::ChainIdOverflow));
+	}
+
+	// Typed transactions carry the chain ID as is.
+	let typed = Transaction { chain_id: Some(u64::MAX), ..transaction(TransactionType::AccessList) };
+	assert_eq!(Transaction::decode(&typed.encode().unwrap()).unwrap(), typed);
+}
+
+#[test]
+fn signing_hash() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+
+	let eip155 = transaction(TransactionType::Legacy);
+	let pre_eip155 = Transaction { chain_id: None, ..eip155.clone() };
+	assert_ne!(eip155.signing_hash(&backend), pre_eip155.signing_hash(&backend));
+	assert_ne!(eip155.signing_hash(&backend), eip155.hash(&backend).unwrap());
+
+	let unsigned = Transaction { signature: Signature::default(), ..eip155.clone() };
+	assert_eq!(unsigned.signing_hash(&backend), eip155.signing_hash(&backend));
+
+	let expected = eip155.signing_hash(&backend);
+	let check = |hash: H256, _: &Signature| if hash == expected { Some(caller()) } else { None };
+	assert_eq!(eip155.recover_sender(&backend, &check), Ok(caller()));
+	assert_eq!(pre_eip155.recover_sender(&backend, &check), Err(TransactionError::InvalidSignature));
+}
+
+#[test]
+fn intrinsic_gas() {
+	let transaction = transaction(TransactionType::AccessList);
+	assert_eq!(
+		transaction.intrinsic_gas::<evm::Istanbul>(),
+		21_000 + 16 + ACCESS_LIST_ADDRESS_GAS + ACCESS_LIST_STORAGE_KEY_GAS,
+	);
+
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let mut executor = executor(&backend, 100_000);
+	let short = Transaction { gas_limit: U256::from(22_000), ..transaction };
+	assert_eq!(
+		executor.transact_raw(&short.encode().unwrap(), &recover),
+		Err(TransactionError::Invalid(TransactionValidationError::IntrinsicGasTooLow {
+			intrinsic_gas: 21_016 + ACCESS_LIST_ADDRESS_GAS + ACCESS_LIST_STORAGE_KEY_GAS,
+			gas_limit: 22_000,
+		})),
+	);
+	assert_eq!(executor.used_gas(), 0);
+}
+
+#[test]
+fn rejected_transactions() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let mut executor = executor(&backend, 100_000);
+
+	let other_chain = Transaction { chain_id: Some(5), ..transaction(TransactionType::Legacy) };
+	assert_eq!(
+		executor.transact_raw(&other_chain.encode().unwrap(), &recover),
+		Err(TransactionError::ChainIdMismatch { expected: U256::one(), actual: 5 }),
+	);
+
+	let unsigned = Transaction { signature: Signature::default(), ..transaction(TransactionType::AccessList) };
+	assert_eq!(executor.transact_raw(&unsigned.encode().unwrap(), &recover), Err(TransactionError::InvalidSignature));
+
+	let nonce = Transaction { nonce: U256::one(), ..transaction(TransactionType::AccessList) };
+	assert_eq!(
+		executor.transact_raw(&nonce.encode().unwrap(), &recover),
+		Err(TransactionError::Invalid(TransactionValidationError::InvalidNonce {
+			expected: U256::zero(),
+			actual: U256::one(),
+		})),
+	);
+}
+
+#[test]
+fn typed_transactions_before_activation() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+
+	let access_list = transaction(TransactionType::AccessList).encode().unwrap();
+	let dynamic_fee = transaction(TransactionType::DynamicFee).encode().unwrap();
+
+	let mut istanbul = StackExecutor::new_with_spec(&backend, 100_000, no_precompile, Istanbul);
+	assert_eq!(istanbul.transact_raw(&access_list, &recover), Err(TransactionError::UnsupportedType(1)));
+	assert_eq!(istanbul.transact_raw(&dynamic_fee, &recover), Err(TransactionError::UnsupportedType(2)));
+	assert_eq!(istanbul.used_gas(), 0);
+
+	let mut berlin = StackExecutor::new_with_spec(&backend, 100_000, no_precompile, Berlin);
+	assert_eq!(berlin.transact_raw(&dynamic_fee, &recover), Err(TransactionError::UnsupportedType(2)));
+	assert!(berlin.transact_raw(&access_list, &recover).unwrap().exit.is_succeed());
+}
+
+#[test]
+fn raw_call() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let mut executor = executor(&backend, 100_000);
+
+	let outcome = executor.transact_raw(&transaction(TransactionType::AccessList).encode().unwrap(), &recover).unwrap();
+	assert_eq!(outcome.exit, ExitReason::Succeed(ExitSucceed::Stopped));
+	assert_eq!(
+		outcome.gas_used,
+		21_016 + ACCESS_LIST_ADDRESS_GAS + ACCESS_LIST_STORAGE_KEY_GAS + 3 + 3 + 20_000,
+	);
+	assert_eq!(executor.balance(contract()), U256::from(7));
+	assert_eq!(executor.nonce(caller()), U256::one());
+	assert_eq!(executor.origin(), caller());
+}
+
+#[test]
+fn raw_create_with_fees() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let fees = Fees { gas_price: U256::from(100), base_fee: U256::from(4) };
+	let mut executor = executor(&backend, 100_000).with_fees(fees);
+
+	let create = Transaction {
+		action: TransactionAction::Create,
+		value: U256::zero(),
+		input: Vec::new(),
+		access_list: Vec::new(),
+		..transaction(TransactionType::DynamicFee)
+	};
+	let outcome = executor.transact_raw(&create.encode().unwrap(), &recover).unwrap();
+	assert!(outcome.exit.is_succeed());
+	assert_eq!(outcome.gas_used, 53_000);
+	assert_eq!(executor.gas_price(), U256::from(6));
+	assert_eq!(executor.balance(caller()), U256::from(10_000_000 - 53_000 * 6));
+	assert_eq!(executor.balance(coinbase()), U256::from(53_000 * 2));
+
+	let low_fee = Transaction { nonce: U256::one(), gas_price: U256::from(3), ..create };
+	assert_eq!(
+		executor.transact_raw(&low_fee.encode().unwrap(), &recover),
+		Err(TransactionError::FeeCapTooLow { max_fee_per_gas: U256::from(3), base_fee: U256::from(4) }),
+	);
+}
+
+#[cfg(feature = "secp256k1")]
+mod secp256k1 {
+	use super::*;
+	use evm::transaction::{recover_sender, Secp256k1, SenderRecovery};
+
+	/// Example transaction of EIP-155.
+	fn eip155() -> Transaction {
+		Transaction {
+			transaction_type: TransactionType::Legacy,
+			chain_id: Some(1),
+			nonce: U256::from(9),
+			gas_price: U256::from(20_000_000_000_u64),
+			max_priority_fee_per_gas: None,
+			gas_limit: U256::from(21_000),
+			action: TransactionAction::Call(H160::repeat_byte(0x35)),
+			value: U256::from(1_000_000_000_000_000_000_u64),
+			input: Vec::new(),
+			access_list: Vec::new(),
+			signature: Signature {
+				odd_y_parity: false,
+				r: U256::from_dec_str("18515461264373351373200002665853028612451056578545711640558177340181847433846").unwrap(),
+				s: U256::from_dec_str("46948507304638947509940763649030358759909902576025900602547168820602576006531").unwrap(),
+			},
+		}
+	}
+
+	fn sender() -> H160 {
+		H160::from_slice(&[
+			0x9d, 0x8a, 0x62, 0xf6, 0x56, 0xa8, 0xd1, 0x61, 0x5c, 0x12,
+			0x94, 0xfd, 0x71, 0xe9, 0xcf, 0xb3, 0xe4, 0x85, 0x5a, 0x4f,
+		])
+	}
+
+	#[test]
+	fn recovery() {
+		let vicinity = vicinity();
+		let backend = backend(&vicinity);
+		let transaction = eip155();
+
+		let mut expected = [0; 32];
+		expected.copy_from_slice(&[
+			0xda, 0xf5, 0xa7, 0x79, 0xae, 0x97, 0x2f, 0x97, 0x21, 0x97, 0x30, 0x3d, 0x7b, 0x57, 0x47, 0x46,
+			0xc7, 0xef, 0x83, 0xea, 0xda, 0xc0, 0xf2, 0x79, 0x1a, 0xd2, 0x3d, 0xb9, 0x2e, 0x4c, 0x8e, 0x53,
+		]);
+		assert_eq!(transaction.signing_hash(&backend), H256(expected));
+		assert_eq!(recover_sender(&backend, &transaction), Ok(sender()));
+
+		let wrong_parity = Signature { odd_y_parity: true, ..transaction.signature };
+		assert_ne!(Secp256k1(&backend).recover(H256(expected), &wrong_parity), Some(sender()));
+
+		// The same signature with the high `s` value is rejected (EIP-2).
+		let n = U256::from_str_radix("fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141", 16).unwrap();
+		let high_s = Signature { odd_y_parity: true, s: n - transaction.signature.s, ..transaction.signature };
+		assert_eq!(Secp256k1(&backend).recover(H256(expected), &high_s), None);
+
+		let zero = Signature { r: U256::zero(), ..transaction.signature };
+		assert_eq!(Secp256k1(&backend).recover(H256(expected), &zero), None);
+	}
+
+	#[test]
+	fn signed_transfer() {
+		let vicinity = vicinity();
+		let mut state = BTreeMap::new();
+		state.insert(sender(), MemoryAccount {
+			nonce: U256::from(9),
+			balance: U256::from(2_000_000_000_000_000_000_u64),
+			..Default::default()
+		});
+		let backend = MemoryBackend::new(&vicinity, state);
+		let mut executor = StackExecutor::new(&backend, 21_000);
+
+		let outcome = executor.transact_signed(&eip155().encode().unwrap()).unwrap();
+		assert!(outcome.exit.is_succeed());
+		assert_eq!(outcome.gas_used, 21_000);
+		assert_eq!(executor.balance(H160::repeat_byte(0x35)), U256::from(1_000_000_000_000_000_000_u64));
+		assert_eq!(executor.origin(), sender());
+
+		let forged = Transaction { value: U256::from(2), ..eip155() };
+		assert!(matches!(
+			executor.transact_signed(&forged.encode().unwrap()),
+			Err(TransactionError::Invalid(_) | TransactionError::InvalidSignature),
+		));
+	}
+}
@@ -0,0 +1,74 @@
+mod common;
+
+use std::collections::BTreeMap;
+use evm::{Evm, H160, H256, U256};
+use evm::backend::{MemoryAccount, MemoryBackend, MemoryVicinity};
+use evm::executor::{StackExecutor, TxContext};
+use common::{account, caller, contract};
+
+fn vicinity() -> MemoryVicinity {
+	MemoryVicinity {
+		gas_price: U256::from(5),
+		origin: H160::repeat_byte(0xee),
+		..common::vicinity()
+	}
+}
+
+fn other() -> H160 {
+	H160::repeat_byte(0x33)
+}
+
+/// Returns `ORIGIN` and `GASPRICE`.
+const CODE: [u8; 12] = [0x32, 0x60, 0x00, 0x52, 0x3a, 0x60, 0x20, 0x52, 0x60, 0x40, 0x60, 0x00];
+
+fn state() -> BTreeMap<H160, MemoryAccount> {
+	let mut code = CODE.to_vec();
+	code.push(0xf3);
+
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount::default());
+	state.insert(other(), MemoryAccount::default());
+	state.insert(contract(), account(code));
+	state
+}
+
+fn decode(out: &[u8]) -> (H160, U256) {
+	(H256::from_slice(&out[..32]).into(), U256::from_big_endian(&out[32..]))
+}
+
+#[test]
+fn backend_by_default() {
+	let vicinity = vicinity();
+	let backend = MemoryBackend::new(&vicinity, state());
+	let mut executor = StackExecutor::new(&backend, 100_000);
+	let (reason, out) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 100_000);
+	assert!(reason.is_succeed());
+	assert_eq!(decode(&out), (vicinity.origin, vicinity.gas_price));
+}
+
+#[test]
+fn context_per_transaction() {
+	let vicinity = vicinity();
+	let backend = MemoryBackend::new(&vicinity, state());
+	let context = TxContext { origin: caller(), gas_price: U256::from(7) };
+	let mut executor = StackExecutor::new(&backend, 100_000).with_tx_context(context);
+	let (reason, out) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 100_000);
+	assert!(reason.is_succeed());
+	assert_eq!(decode(&out), (caller(), U256::from(7)));
+
+	executor.set_tx_context(TxContext { origin: other(), gas_price: U256::from(9) });
+	let (reason, out) = executor.transact_call(other(), contract(), U256::zero(), Vec::new(), 100_000);
+	assert!(reason.is_succeed());
+	assert_eq!(decode(&out), (other(), U256::from(9)));
+}
+
+#[test]
+fn facade_uses_caller_as_origin() {
+	let vicinity = vicinity();
+	let mut evm = Evm::new(MemoryBackend::new(&vicinity, state()));
+	for sender in [caller(), other()] {
+		let summary = evm.call(sender, contract(), U256::zero(), Vec::new(), 100_000);
+		assert!(summary.reason.is_succeed());
+		assert_eq!(decode(&summary.return_value), (sender, vicinity.gas_price));
+	}
+}
@@ -0,0 +1,98 @@
+mod common;
+
+use std::collections::BTreeMap;
+use evm::{Config, ExitError, ExitSucceed, Istanbul, Spec, H160, U256};
+use evm::backend::{MemoryAccount, MemoryBackend, MemoryVicinity};
+use evm::executor::{StackExecutor, TransactionValidationError};
+use evm::gasometer::{self, TransactionCost};
+use common::{caller, contract, vicinity};
+
+type PrecompileResult = Option<Result<(ExitSucceed, Vec<u8>, u64), ExitError>>;
+
+const fn no_precompile(_address: H160, _input: &[u8], _target_gas: Option<u64>) -> PrecompileResult {
+	None
+}
+
+/// Istanbul with EIP-3607.
+#[derive(Clone, Copy, Debug, Default)]
+struct RejectSenderWithCode;
+
+impl Spec for RejectSenderWithCode {
+	const CONFIG: Config = Config {
+		reject_sender_with_code: true,
+		..Config::istanbul()
+	};
+}
+
+fn backend(vicinity: &MemoryVicinity) -> MemoryBackend<'_> {
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount {
+		nonce: U256::from(3),
+		balance: U256::from(1_000_000),
+		..Default::default()
+	});
+	state.insert(contract(), MemoryAccount {
+		nonce: U256::one(),
+		balance: U256::from(1_000_000),
+		code: vec![0x00],
+		..Default::default()
+	});
+	MemoryBackend::new(vicinity, state)
+}
+
+fn cost() -> TransactionCost {
+	gasometer::call_transaction_cost(&[0, 1])
+}
+
+#[test]
+fn valid_transaction() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let executor = StackExecutor::new(&backend, 100_000);
+	// 21000 + 4 + 16
+	assert_eq!(gasometer::intrinsic_gas::<Istanbul>(&cost()), 21_020);
+	assert_eq!(executor.validate_transaction(caller(), U256::from(54_100), 21_020, U256::from(45), U256::from(3), &cost()), Ok(()));
+}
+
+#[test]
+fn rejected_transactions() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let executor = StackExecutor::new(&backend, 100_000);
+	let validate = |caller, value: u64, gas_limit, gas_price: u64, nonce: u64| {
+		executor.validate_transaction(caller, U256::from(value), gas_limit, U256::from(gas_price), U256::from(nonce), &cost())
+	};
+
+	assert_eq!(validate(H160::repeat_byte(0x33), 0, 21_020, 0, 0), Err(TransactionValidationError::UnknownSender));
+	assert_eq!(validate(caller(), 0, 21_020, 0, 4), Err(TransactionValidationError::InvalidNonce {
+		expected: U256::from(3),
+		actual: U256::from(4),
+	}));
+	assert_eq!(validate(caller(), 54_101, 21_020, 45, 3), Err(TransactionValidationError::InsufficientBalance {
+		balance: U256::from(1_000_000),
+		cost: Some(U256::from(1_000_001)),
+	}));
+	assert_eq!(
+		executor.validate_transaction(caller(), U256::MAX, 1, U256::one(), U256::from(3), &cost()),
+		Err(TransactionValidationError::InsufficientBalance { balance: U256::from(1_000_000), cost: None }),
+	);
+	assert_eq!(validate(caller(), 0, 21_019, 0, 3), Err(TransactionValidationError::IntrinsicGasTooLow {
+		intrinsic_gas: 21_020,
+		gas_limit: 21_019,
+	}));
+
+	// Sending from a contract is allowed until EIP-3607.
+	assert_eq!(validate(contract(), 0, 21_020, 0, 1), Ok(()));
+}
+
+#[test]
+fn reject_sender_with_code() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let executor = StackExecutor::new_with_spec(&backend, 100_000, no_precompile, RejectSenderWithCode);
+	assert_eq!(
+		executor.validate_transaction(contract(), U256::zero(), 21_020, U256::zero(), U256::one(), &cost()),
+		Err(TransactionValidationError::SenderHasCode),
+	);
+	assert_eq!(executor.validate_transaction(caller(), U256::zero(), 21_020, U256::zero(), U256::from(3), &cost()), Ok(()));
+}
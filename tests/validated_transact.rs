@@ -0,0 +1,139 @@
+mod common;
+
+use std::collections::BTreeMap;
+use evm::{ExitError, ExitReason, ExitRevert, Handler, Opcode, OpcodeSet, H160, U256};
+use evm::backend::{MemoryAccount, MemoryBackend, MemoryVicinity};
+use evm::executor::{PhaseCall, StackExecutor, VALIDATION_BANNED_OPCODES};
+use common::caller;
+
+fn vicinity() -> MemoryVicinity {
+	MemoryVicinity {
+		block_timestamp: U256::from(1234),
+		..common::vicinity()
+	}
+}
+
+/// Account validating the operation, storing 1.
+fn wallet() -> H160 {
+	H160::repeat_byte(0x33)
+}
+
+/// Account validating the operation with the block timestamp.
+fn timed_wallet() -> H160 {
+	H160::repeat_byte(0x34)
+}
+
+/// Account validating the operation with a call reading the block number,
+/// returning whether the call succeeded.
+fn oracle_wallet() -> H160 {
+	H160::repeat_byte(0x35)
+}
+
+fn oracle() -> H160 {
+	H160::repeat_byte(0x36)
+}
+
+/// Account executing the operation, storing the block timestamp.
+fn target() -> H160 {
+	H160::repeat_byte(0x44)
+}
+
+fn reverter() -> H160 {
+	H160::repeat_byte(0x45)
+}
+
+fn backend(vicinity: &MemoryVicinity) -> MemoryBackend<'_> {
+	// PUSH1 0 PUSH1 0 PUSH1 0 PUSH1 0 PUSH1 0 PUSH20 oracle() PUSH2 0xffff CALL
+	// PUSH1 0 MSTORE PUSH1 32 PUSH1 0 RETURN
+	let mut calls_oracle = vec![0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x73];
+	calls_oracle.extend_from_slice(oracle().as_bytes());
+	calls_oracle.extend_from_slice(&[0x61, 0xff, 0xff, 0xf1, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3]);
+
+	let mut state = BTreeMap::new();
+	// Entry point calling both phases.
+	state.insert(caller(), MemoryAccount { nonce: U256::one(), ..Default::default() });
+	// PUSH1 1 PUSH1 0 SSTORE
+	state.insert(wallet(), MemoryAccount { code: vec![0x60, 0x01, 0x60, 0x00, 0x55], ..Default::default() });
+	// TIMESTAMP PUSH1 0 SSTORE
+	let stores_timestamp = vec![0x42, 0x60, 0x00, 0x55];
+	state.insert(timed_wallet(), MemoryAccount { code: stores_timestamp.clone(), ..Default::default() });
+	state.insert(oracle_wallet(), MemoryAccount { code: calls_oracle, ..Default::default() });
+	// NUMBER
+	state.insert(oracle(), MemoryAccount { code: vec![0x43], ..Default::default() });
+	state.insert(target(), MemoryAccount { code: stores_timestamp, ..Default::default() });
+	// PUSH1 0 PUSH1 0 REVERT
+	state.insert(reverter(), MemoryAccount { code: vec![0x60, 0x00, 0x60, 0x00, 0xfd], ..Default::default() });
+	MemoryBackend::new(vicinity, state)
+}
+
+fn phase(address: H160) -> PhaseCall {
+	PhaseCall {
+		address,
+		value: U256::zero(),
+		data: Vec::new(),
+		gas_limit: 100_000,
+	}
+}
+
+#[test]
+fn both_phases_succeed() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let mut executor = StackExecutor::new(&backend, 1_000_000);
+
+	let outcome = executor.transact_validated(caller(), phase(wallet()), phase(target()), VALIDATION_BANNED_OPCODES);
+	assert!(outcome.is_succeed());
+	// The timestamp is only banned from the validation.
+	assert_eq!(executor.storage(wallet(), U256::zero()), U256::one());
+	assert_eq!(executor.storage(target(), U256::zero()), U256::from(1234));
+	assert_eq!(executor.nonce(caller()), U256::one());
+
+	let execution = outcome.execution.unwrap();
+	assert_eq!(outcome.validation.gas_used, 2 * 3 + 20_000);
+	assert_eq!(execution.gas_used, 2 + 3 + 20_000);
+	assert_eq!(executor.used_gas(), outcome.validation.gas_used + execution.gas_used);
+}
+
+#[test]
+fn banned_opcode_fails_validation() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let mut executor = StackExecutor::new(&backend, 1_000_000);
+
+	let outcome = executor.transact_validated(caller(), phase(timed_wallet()), phase(target()), VALIDATION_BANNED_OPCODES);
+	assert_eq!(outcome.validation.exit, ExitReason::Error(ExitError::OpcodeDisabled));
+	assert_eq!(outcome.validation.gas_used, 100_000);
+	assert_eq!(outcome.execution, None);
+	assert!(!outcome.is_succeed());
+	assert_eq!(executor.storage(target(), U256::zero()), U256::zero());
+	assert_eq!(executor.used_gas(), 0);
+
+	// The ban also applies to the frames entered by the validation.
+	let outcome = executor.transact_validated(caller(), phase(oracle_wallet()), phase(target()), VALIDATION_BANNED_OPCODES);
+	assert!(outcome.is_succeed());
+	assert_eq!(U256::from_big_endian(&outcome.validation.output), U256::zero());
+
+	// Custom sets replace the default one.
+	let banned = OpcodeSet::new().with(Opcode::SSTORE);
+	let outcome = executor.transact_validated(caller(), phase(wallet()), phase(target()), banned);
+	assert_eq!(outcome.validation.exit, ExitReason::Error(ExitError::OpcodeDisabled));
+}
+
+#[test]
+fn failed_execution_reverts_validation() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let mut executor = StackExecutor::new(&backend, 1_000_000);
+
+	let outcome = executor.transact_validated(caller(), phase(wallet()), phase(reverter()), VALIDATION_BANNED_OPCODES);
+	assert!(outcome.validation.exit.is_succeed());
+	assert_eq!(outcome.execution.map(|execution| execution.exit), Some(ExitReason::Revert(ExitRevert::Reverted)));
+	assert_eq!(executor.storage(wallet(), U256::zero()), U256::zero());
+	assert_eq!(executor.used_gas(), 0);
+
+	// Validation gas is capped by the phase.
+	let capped = PhaseCall { gas_limit: 10_000, ..phase(wallet()) };
+	let outcome = executor.transact_validated(caller(), capped, phase(target()), VALIDATION_BANNED_OPCODES);
+	assert_eq!(outcome.validation.exit, ExitReason::Error(ExitError::OutOfGas));
+	assert_eq!(outcome.validation.gas_used, 10_000);
+}
@@ -0,0 +1,68 @@
+mod common;
+
+use std::collections::BTreeMap;
+use evm::{ExitReason, ExitSucceed, Handler, H160, U256};
+use evm::backend::{MemoryAccount, MemoryBackend, MemoryVicinity};
+use evm::executor::StackExecutor;
+use common::{caller, contract, vicinity};
+
+/// Returns slot 0, after setting it to 7 if the input is not empty.
+const CODE: [u8; 22] = [
+	0x60, 0x00, 0x54, 0x60, 0x00, 0x52, 0x36, 0x15, 0x60, 0x10, 0x57,
+	0x60, 0x07, 0x60, 0x00, 0x55, 0x5b, 0x60, 0x20, 0x60, 0x00, 0xf3,
+];
+
+fn backend(vicinity: &MemoryVicinity) -> MemoryBackend<'_> {
+	let mut storage = BTreeMap::new();
+	storage.insert(U256::zero(), U256::from(3));
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount::default());
+	state.insert(contract(), MemoryAccount {
+		nonce: U256::one(),
+		code: CODE.to_vec(),
+		storage,
+		..Default::default()
+	});
+	MemoryBackend::new(vicinity, state)
+}
+
+#[test]
+fn reads_without_changes() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let executor = StackExecutor::new(&backend, 0);
+
+	let (reason, out, gas) = executor.view_call(contract(), Vec::new());
+	assert_eq!(reason, ExitReason::Succeed(ExitSucceed::Returned));
+	assert_eq!(U256::from_big_endian(&out), U256::from(3));
+
+	// Writes are rejected.
+	let (reason, _, _) = executor.view_call(contract(), vec![0x01]);
+	assert!(reason.is_error());
+
+	assert_eq!(executor.nonce(H160::default()), U256::zero());
+	assert_eq!(executor.used_gas(), 0);
+	let (applies, logs) = executor.deconstruct();
+	assert!(applies.into_iter().next().is_none());
+	assert!(logs.into_iter().next().is_none());
+
+	// The gas matches the one used by the same call as a transaction.
+	let mut executor = StackExecutor::new(&backend, 100_000);
+	let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 100_000);
+	assert!(reason.is_succeed());
+	assert_eq!(executor.used_gas(), gas);
+}
+
+#[test]
+fn sees_executor_state() {
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let mut executor = StackExecutor::new(&backend, 100_000);
+	let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), vec![0x01], 100_000);
+	assert!(reason.is_succeed());
+
+	let (reason, out, _) = executor.view_call(contract(), Vec::new());
+	assert!(reason.is_succeed());
+	assert_eq!(U256::from_big_endian(&out), U256::from(7));
+	assert_eq!(executor.storage(contract(), U256::zero()), U256::from(7));
+}
@@ -0,0 +1,107 @@
+mod common;
+
+use std::collections::BTreeMap;
+use evm::{H160, H256, U256};
+use evm::backend::{MemoryAccount, MemoryBackend, MemoryVicinity, WitnessBackend};
+use evm::executor::StackExecutor;
+use common::{callee, caller, contract};
+
+fn vicinity() -> MemoryVicinity {
+	MemoryVicinity {
+		block_hashes: vec![H256::repeat_byte(0xaa), H256::repeat_byte(0xbb)],
+		block_number: U256::from(10),
+		..common::vicinity()
+	}
+}
+
+fn other() -> H160 {
+	H160::repeat_byte(0x44)
+}
+
+fn unrelated() -> H160 {
+	H160::repeat_byte(0x55)
+}
+
+/// Returns slot 1, the balance of `other()`, the hash of the previous block
+/// and the code hash of `callee()`, after calling `callee()`.
+fn code() -> Vec<u8> {
+	let mut code = vec![0x60, 0x01, 0x54, 0x60, 0x00, 0x52, 0x73];
+	code.extend_from_slice(other().as_bytes());
+	code.extend_from_slice(&[0x31, 0x60, 0x20, 0x52, 0x60, 0x01, 0x43, 0x03, 0x40, 0x60, 0x40, 0x52, 0x73]);
+	code.extend_from_slice(callee().as_bytes());
+	code.extend_from_slice(&[0x3f, 0x60, 0x60, 0x52]);
+	code.extend_from_slice(&[0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x73]);
+	code.extend_from_slice(callee().as_bytes());
+	code.extend_from_slice(&[0x5a, 0xf1, 0x50, 0x60, 0x80, 0x60, 0x00, 0xf3]);
+	code
+}
+
+/// Increments slot 7.
+const CALLEE_CODE: [u8; 10] = [0x60, 0x07, 0x54, 0x60, 0x01, 0x01, 0x60, 0x07, 0x55, 0x00];
+
+fn storage(index: u64, value: u64) -> BTreeMap<U256, U256> {
+	let mut storage = BTreeMap::new();
+	storage.insert(U256::from(index), U256::from(value));
+	storage
+}
+
+fn state() -> BTreeMap<H160, MemoryAccount> {
+	let mut contract_storage = storage(1, 5);
+	contract_storage.insert(U256::from(2), U256::from(6));
+
+	let mut state = BTreeMap::new();
+	state.insert(caller(), MemoryAccount { balance: U256::from(100), ..Default::default() });
+	state.insert(contract(), MemoryAccount {
+		nonce: U256::one(),
+		code: code(),
+		storage: contract_storage,
+		..Default::default()
+	});
+	state.insert(callee(), MemoryAccount {
+		nonce: U256::one(),
+		code: CALLEE_CODE.to_vec(),
+		storage: storage(7, 41),
+		..Default::default()
+	});
+	state.insert(other(), MemoryAccount { balance: U256::from(3), ..Default::default() });
+	state.insert(unrelated(), MemoryAccount { balance: U256::from(4), code: vec![0x00], ..Default::default() });
+	state
+}
+
+fn run(backend: &MemoryBackend<'_>) -> (Vec<u8>, u64) {
+	let mut executor = StackExecutor::new(backend, 1_000_000);
+	let (reason, out) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 1_000_000);
+	assert!(reason.is_succeed());
+	(out, executor.used_gas())
+}
+
+#[test]
+fn records_accessed_state() {
+	let vicinity = vicinity();
+	let backend = WitnessBackend::new(MemoryBackend::new(&vicinity, state()));
+	let mut executor = StackExecutor::new(&backend, 1_000_000);
+	let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 1_000_000);
+	assert!(reason.is_succeed());
+
+	let witness = backend.witness();
+	assert_eq!(witness.vicinity.block_hashes, vec![H256::repeat_byte(0xaa)]);
+	assert_eq!(witness.vicinity.block_number, vicinity.block_number);
+	assert_eq!(witness.state.keys().copied().collect::<Vec<_>>(), vec![caller(), contract(), callee(), other()]);
+	assert_eq!(witness.state[&contract()].storage, storage(1, 5));
+	assert_eq!(witness.state[&contract()].code, code());
+	assert_eq!(witness.state[&callee()].storage, storage(7, 41));
+	assert_eq!(witness.state[&other()].balance, U256::from(3));
+}
+
+#[test]
+fn replays_from_witness() {
+	let vicinity = vicinity();
+	let backend = WitnessBackend::new(MemoryBackend::new(&vicinity, state()));
+	let mut executor = StackExecutor::new(&backend, 1_000_000);
+	let (_, recorded) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 1_000_000);
+
+	let witness = backend.witness();
+	let (out, used_gas) = run(&witness.backend());
+	assert_eq!(out, recorded);
+	assert_eq!((out, used_gas), run(backend.inner()));
+}